@@ -0,0 +1,469 @@
+//! The command line program, which parses the global options and runs a
+//! command.
+
+use std::cmp;
+use std::env;
+use std::io::{self, IsTerminal};
+use std::panic;
+use std::process;
+
+use docopt::Docopt;
+use serde::Deserialize;
+use serde::de::IntoDeserializer;
+
+use cmd;
+use config;
+use defaults;
+use util;
+use {CliError, CliResult, EXIT_BROKEN_PIPE, EXIT_USAGE};
+
+macro_rules! command_list {
+    () => {
+        "
+    apply       Apply an operation to columns
+    behead      Drop the header row
+    cat         Concatenate by row or column
+    completions Generate shell completion scripts
+    config      Show the defaults of options
+    count       Count records
+    datefmt     Parse and reformat dates
+    dedup       Remove duplicate records
+    diff        Compare two CSV files
+    enum        Add a column with record numbers
+    excel       Convert a spreadsheet sheet to CSV
+    explode     Split multi-valued cells into rows
+    fill        Fill empty fields
+    filter      Select records with an expression
+    fixed       Convert fixed-width text to CSV and back
+    fixlengths  Makes all records have same length
+    flatten     Show one field per line
+    fmt         Format CSV output (change field delimiter)
+    foreach     Run a command for every record
+    frequency   Show frequency tables
+    fromjson    Convert JSON Lines to CSV
+    groupby     Compute aggregates of columns for groups of records
+    hash        Add a column with a digest of each record
+    headers     Show header names
+    help        Show this usage message.
+    implode     Merge rows into multi-valued cells
+    index       Create CSV index for faster access
+    input       Read CSV data with special quoting rules
+    join        Join CSV files
+    json        Convert CSV data to JSON
+    map         Compute new columns from expressions
+    mask        Pseudonymize or redact sensitive columns
+    melt        Reshape wide data into long
+    merge       Merge sorted CSV files
+    parquet     Convert CSV data to Parquet
+    partition   Partition CSV data based on a column value
+    pivot       Reshape long data into wide
+    safenames   Rewrite the header row to safe identifiers
+    sample      Randomly sample CSV data
+    reverse     Reverse rows of CSV data
+    schema      Infer a JSON schema from CSV data
+    search      Search CSV data with regexes
+    select      Select columns from CSV
+    slice       Slice records from CSV
+    sniff       Guess the dialect of CSV data
+    sort        Sort CSV data
+    split       Split CSV data into many files
+    stats       Compute basic statistics
+    table       Align CSV data into columns
+    template    Render records through a text template
+    tosql       Convert CSV data to SQL or load it into SQLite
+    transpose   Transpose rows and columns
+    val         Validate CSV data
+"
+    };
+}
+
+pub static USAGE: &'static str = concat!(
+    "
+Usage:
+    xsv [options] [--http-header <header>]... <command> [<args>...]
+    xsv [options]
+
+Options:
+    --list               List all commands available.
+    -h, --help           Display this message
+    <command> -h         Display the command help message
+    --version            Print version info and exit
+
+Global options (given before the command, e.g., 'xsv --trim all join'):
+    --trim <mode>        Strip leading and trailing whitespace from the
+                         values of all CSV data that is read, after
+                         unquoting them. <mode> is 'all', 'headers' or
+                         'fields'.
+    --comment <char>     Skip the lines of CSV data that start with
+                         <char>, e.g., '#'. The character doesn't start a
+                         comment anywhere else, such as in a quoted field.
+    --flexible <policy>  What to do with records that have a different
+                         number of fields than the first record (the
+                         header): 'error' (the default), 'pad' (add empty
+                         fields to short records, but report long ones as
+                         errors), 'truncate' (cut long records, but report
+                         short ones as errors) or 'skip' (leave out both
+                         kinds, reporting their number on
+                         stderr). Indexes aren't used unless this is
+                         'error'.
+    --http-header <header>  An extra header, like 'Name: value', of the
+                         requests for inputs that are http:// or https://
+                         URLs. This can be given more than once.
+    --download           Download the data of URLs to temporary files
+                         before reading them, which commands that seek
+                         within their input (such as join) require.
+    --progress           Report the progress of reading the input on
+                         stderr, for commands that support it (sort,
+                         stats, split and val, when they don't use an
+                         index). This is also done if XSV_PROGRESS=1 is
+                         set and stderr is a terminal.
+
+Exit status:
+    0  Success.
+    1  Any other error.
+    2  Invalid usage, such as an unknown option or an invalid config file.
+    3  An input or output couldn't be read or written, e.g., a missing
+       file.
+    4  Invalid data, such as CSV data that can't be parsed, or that fails
+       a check like 'xsv val'.
+  141  stdout was closed before all output was written, e.g., by 'head'.
+       Nothing is printed on stderr in this case.
+
+Commands:",
+    command_list!()
+);
+
+#[derive(Deserialize)]
+struct Args {
+    arg_command: Option<Command>,
+    flag_list: bool,
+    flag_trim: Option<config::Trim>,
+    flag_comment: Option<config::Delimiter>,
+    flag_flexible: Option<config::Flexible>,
+    #[cfg(feature = "http")]
+    flag_http_header: Vec<String>,
+    flag_download: bool,
+    flag_progress: bool,
+}
+
+/// Runs xsv with the arguments of the process, and exits.
+pub fn main() {
+    quiet_broken_pipe();
+    let mut argv: Vec<String> = env::args().collect();
+    let end = command_position(&argv);
+    if let Err(err) = defaults::apply(USAGE, &mut argv, 1, end, true) {
+        werr!("{}", err);
+        process::exit(EXIT_USAGE);
+    }
+    let args: Args = Docopt::new(USAGE)
+        .and_then(|d| {
+            d.argv(argv)
+                .options_first(true)
+                .version(Some(util::version()))
+                .deserialize()
+        })
+        .unwrap_or_else(|e| exit_docopt(e));
+    if args.flag_list {
+        wout!(concat!("Installed commands:", command_list!()));
+        return;
+    }
+    config::set_globals(config::Globals {
+        trim: args.flag_trim,
+        comment: args.flag_comment.map(|c| c.as_byte()),
+        flexible: args.flag_flexible,
+        #[cfg(feature = "http")]
+        http_headers: args.flag_http_header,
+        download: args.flag_download,
+        progress: args.flag_progress
+            || (env::var("XSV_PROGRESS").map_or(false, |v| v == "1")
+                && io::stderr().is_terminal()),
+    });
+    match args.arg_command {
+        None => {
+            werr!(concat!(
+                "xsv is a suite of CSV command line utilities.
+
+Please choose one of the following commands:",
+                command_list!()
+            ));
+            process::exit(0);
+        }
+        Some(cmd) => {
+            let result = cmd.run(&command_argv());
+            config::remove_downloads();
+            match result {
+                Ok(()) => process::exit(0),
+                Err(CliError::Flag(err)) => exit_docopt(err),
+                Err(CliError::BrokenPipe) => process::exit(EXIT_BROKEN_PIPE),
+                Err(err) => {
+                    werr!("{}", err);
+                    process::exit(err.exit_code());
+                }
+            }
+        }
+    }
+}
+
+/// Exits with the message of a docopt error. This is the usage message on
+/// stdout for --help and --version, and an error otherwise.
+fn exit_docopt(err: docopt::Error) -> ! {
+    if !err.fatal() {
+        err.exit();
+    }
+    werr!("{}\n", err);
+    process::exit(EXIT_USAGE);
+}
+
+/// Makes a panic because stdout was closed, e.g., by `head`, exit quietly
+/// instead of printing a backtrace. Such panics come from the `println!`s
+/// (and `unwrap`s of writes) of commands.
+fn quiet_broken_pipe() {
+    let default = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        let payload = info.payload();
+        let msg = payload.downcast_ref::<String>().map(|s| &**s)
+            .or_else(|| payload.downcast_ref::<&str>().cloned())
+            .unwrap_or("");
+        if msg.contains("Broken pipe") || msg.contains("BrokenPipe") {
+            config::remove_downloads();
+            process::exit(EXIT_BROKEN_PIPE);
+        }
+        default(info);
+    }));
+}
+
+/// The global options that take a value.
+const GLOBAL_VALUE_OPTIONS: &[&str] = &[
+    "--trim", "--comment", "--flexible", "--http-header",
+];
+
+/// Returns the position of the command in the arguments, after the global
+/// options.
+fn command_position(argv: &[String]) -> usize {
+    let mut pos = 1;
+    while pos < argv.len() && argv[pos].starts_with('-') {
+        // Docopt also accepts unambiguous prefixes of long options.
+        let arg = &argv[pos];
+        if !arg.contains('=')
+            && GLOBAL_VALUE_OPTIONS.iter().any(|o| o.starts_with(&**arg)) {
+            pos += 1;
+        }
+        pos += 1;
+    }
+    cmp::min(pos, argv.len())
+}
+
+/// Returns the arguments of the command. The command sees its name as the
+/// first argument, without the global options before it.
+fn command_argv() -> Vec<String> {
+    let mut argv: Vec<String> = env::args().collect();
+    let start = command_position(&argv);
+    argv.drain(1..start);
+    argv
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Command {
+    Apply,
+    Behead,
+    Cat,
+    Completions,
+    Config,
+    Count,
+    DateFmt,
+    Dedup,
+    Diff,
+    Enum,
+    Excel,
+    Explode,
+    Fill,
+    Filter,
+    Fixed,
+    FixLengths,
+    Flatten,
+    Fmt,
+    ForEach,
+    Frequency,
+    FromJson,
+    GroupBy,
+    Hash,
+    Headers,
+    Help,
+    Implode,
+    Index,
+    Input,
+    Join,
+    Json,
+    Map,
+    Mask,
+    Melt,
+    Merge,
+    Parquet,
+    Partition,
+    Pivot,
+    Reverse,
+    SafeNames,
+    Sample,
+    Schema,
+    Search,
+    Select,
+    Slice,
+    Sniff,
+    Sort,
+    Split,
+    Stats,
+    Table,
+    Template,
+    ToSql,
+    Transpose,
+    Val,
+}
+
+impl Command {
+    fn run(self, argv: &[String]) -> CliResult<()> {
+        let argv: Vec<&str> = argv.iter().map(|s| &**s).collect();
+        let argv = &*argv;
+        if !argv[1].chars().all(char::is_lowercase) {
+            return Err(CliError::Usage(
+                format!(
+                    "xsv expects commands in lowercase. Did you mean '{}'?",
+                    argv[1].to_lowercase()
+                )
+                .to_string(),
+            ));
+        }
+        match self {
+            Command::Apply => cmd::apply::run(argv),
+            Command::Behead => cmd::behead::run(argv),
+            Command::Cat => cmd::cat::run(argv),
+            Command::Completions => cmd::completions::run(argv),
+            Command::Config => cmd::config::run(argv),
+            Command::Count => cmd::count::run(argv),
+            Command::DateFmt => cmd::datefmt::run(argv),
+            Command::Dedup => cmd::dedup::run(argv),
+            Command::Diff => cmd::diff::run(argv),
+            Command::Enum => cmd::enumerate::run(argv),
+            Command::Excel => cmd::excel::run(argv),
+            Command::Explode => cmd::explode::run(argv),
+            Command::Fill => cmd::fill::run(argv),
+            Command::Filter => cmd::filter::run(argv),
+            Command::Fixed => cmd::fixed::run(argv),
+            Command::FixLengths => cmd::fixlengths::run(argv),
+            Command::Flatten => cmd::flatten::run(argv),
+            Command::Fmt => cmd::fmt::run(argv),
+            Command::ForEach => cmd::foreach::run(argv),
+            Command::Frequency => cmd::frequency::run(argv),
+            Command::FromJson => cmd::fromjson::run(argv),
+            Command::GroupBy => cmd::groupby::run(argv),
+            Command::Hash => cmd::hash::run(argv),
+            Command::Headers => cmd::headers::run(argv),
+            Command::Help => {
+                wout!("{}", USAGE);
+                Ok(())
+            }
+            Command::Implode => cmd::implode::run(argv),
+            Command::Index => cmd::index::run(argv),
+            Command::Input => cmd::input::run(argv),
+            Command::Join => cmd::join::run(argv),
+            Command::Json => cmd::json::run(argv),
+            Command::Map => cmd::map::run(argv),
+            Command::Mask => cmd::mask::run(argv),
+            Command::Melt => cmd::melt::run(argv),
+            Command::Merge => cmd::merge::run(argv),
+            Command::Parquet => cmd::parquet::run(argv),
+            Command::Partition => cmd::partition::run(argv),
+            Command::Pivot => cmd::pivot::run(argv),
+            Command::Reverse => cmd::reverse::run(argv),
+            Command::SafeNames => cmd::safenames::run(argv),
+            Command::Sample => cmd::sample::run(argv),
+            Command::Schema => cmd::schema::run(argv),
+            Command::Search => cmd::search::run(argv),
+            Command::Select => cmd::select::run(argv),
+            Command::Slice => cmd::slice::run(argv),
+            Command::Sniff => cmd::sniff::run(argv),
+            Command::Sort => cmd::sort::run(argv),
+            Command::Split => cmd::split::run(argv),
+            Command::Stats => cmd::stats::run(argv),
+            Command::Table => cmd::table::run(argv),
+            Command::Template => cmd::template::run(argv),
+            Command::ToSql => cmd::tosql::run(argv),
+            Command::Transpose => cmd::transpose::run(argv),
+            Command::Val => cmd::validate::run(argv),
+        }
+    }
+    /// The usage message of the command.
+    fn usage(&self) -> &'static str {
+        match *self {
+            Command::Apply => cmd::apply::USAGE,
+            Command::Behead => cmd::behead::USAGE,
+            Command::Cat => cmd::cat::USAGE,
+            Command::Completions => cmd::completions::USAGE,
+            Command::Config => cmd::config::USAGE,
+            Command::Count => cmd::count::USAGE,
+            Command::DateFmt => cmd::datefmt::USAGE,
+            Command::Dedup => cmd::dedup::USAGE,
+            Command::Diff => cmd::diff::USAGE,
+            Command::Enum => cmd::enumerate::USAGE,
+            Command::Excel => cmd::excel::USAGE,
+            Command::Explode => cmd::explode::USAGE,
+            Command::Fill => cmd::fill::USAGE,
+            Command::Filter => cmd::filter::USAGE,
+            Command::Fixed => cmd::fixed::USAGE,
+            Command::FixLengths => cmd::fixlengths::USAGE,
+            Command::Flatten => cmd::flatten::USAGE,
+            Command::Fmt => cmd::fmt::USAGE,
+            Command::ForEach => cmd::foreach::USAGE,
+            Command::Frequency => cmd::frequency::USAGE,
+            Command::FromJson => cmd::fromjson::USAGE,
+            Command::GroupBy => cmd::groupby::USAGE,
+            Command::Hash => cmd::hash::USAGE,
+            Command::Headers => cmd::headers::USAGE,
+            Command::Help => USAGE,
+            Command::Implode => cmd::implode::USAGE,
+            Command::Index => cmd::index::USAGE,
+            Command::Input => cmd::input::USAGE,
+            Command::Join => cmd::join::USAGE,
+            Command::Json => cmd::json::USAGE,
+            Command::Map => cmd::map::USAGE,
+            Command::Mask => cmd::mask::USAGE,
+            Command::Melt => cmd::melt::USAGE,
+            Command::Merge => cmd::merge::USAGE,
+            Command::Parquet => cmd::parquet::USAGE,
+            Command::Partition => cmd::partition::USAGE,
+            Command::Pivot => cmd::pivot::USAGE,
+            Command::Reverse => cmd::reverse::USAGE,
+            Command::SafeNames => cmd::safenames::USAGE,
+            Command::Sample => cmd::sample::USAGE,
+            Command::Schema => cmd::schema::USAGE,
+            Command::Search => cmd::search::USAGE,
+            Command::Select => cmd::select::USAGE,
+            Command::Slice => cmd::slice::USAGE,
+            Command::Sniff => cmd::sniff::USAGE,
+            Command::Sort => cmd::sort::USAGE,
+            Command::Split => cmd::split::USAGE,
+            Command::Stats => cmd::stats::USAGE,
+            Command::Table => cmd::table::USAGE,
+            Command::Template => cmd::template::USAGE,
+            Command::ToSql => cmd::tosql::USAGE,
+            Command::Transpose => cmd::transpose::USAGE,
+            Command::Val => cmd::validate::USAGE,
+        }
+    }
+
+}
+
+/// The names and descriptions of all commands, as listed by `xsv --list`.
+pub fn commands() -> Vec<(&'static str, &'static str)> {
+    command_list!().lines().filter_map(|line| {
+        let line = line.trim();
+        line.find(' ').map(|i| (&line[..i], line[i..].trim()))
+    }).collect()
+}
+
+/// Returns the usage message of the command with the given name.
+pub fn usage(name: &str) -> Option<&'static str> {
+    let de: serde::de::value::StrDeserializer<serde::de::value::Error> =
+        name.into_deserializer();
+    Command::deserialize(de).ok().map(|cmd| cmd.usage())
+}
@@ -0,0 +1,287 @@
+use csv;
+
+use {CliError, CliResult};
+use config::{Config, Delimiter};
+use date::DateTime;
+use select::SelectColumns;
+use util;
+
+pub static USAGE: &'static str = "
+Applies an operation to the values of the selected columns.
+
+The operations are:
+
+    upper                     Convert to upper case.
+    lower                     Convert to lower case.
+    trim                      Remove leading and trailing whitespace.
+    len                       Replace with the number of characters.
+    squeeze                   Replace runs of whitespace with a single space.
+    replace <from> <to>       Replace all occurrences of <from> with <to>.
+    datefmt <in> <out>        Parse a date with the format <in> and write it
+                              with the format <out>. The formats use
+                              strftime-like specifications like %Y-%m-%d.
+    round <digits>            Round a number to <digits> decimal places.
+    abs                       Replace a number with its absolute value.
+
+The arguments of the operation follow its name, e.g.,
+
+    xsv apply -s price replace '$' '' data.csv
+
+By default, the selected columns are changed in place. The results are
+written to new columns at the end of each record instead when --new-column
+or --suffix is given.
+
+Values that aren't valid for an operation (like a non-numeric value for
+'round' or a date that doesn't match the format for 'datefmt') are left
+unchanged, or made empty with --strict. The number of such values is
+reported on stderr. Empty values are always left unchanged.
+
+Usage:
+    xsv apply [options] -s <cols> <operation> [<arg>...]
+    xsv apply --help
+
+apply options:
+    -s, --select <cols>     The columns to apply the operation to. See
+                            'xsv select -h' for the full syntax.
+    --new-column <names>    Write the results to new columns with the given
+                            comma separated names, one for each selected
+                            column.
+    --suffix <suffix>       Write the results to new columns named like the
+                            selected columns followed by <suffix>.
+    --strict                Make values that are invalid for the operation
+                            empty instead of leaving them unchanged.
+
+Common options:
+    -h, --help              Display this message
+    -o, --output <file>     Write output to <file> instead of stdout.
+    -n, --no-headers        When set, the first row will not be interpreted
+                            as headers.
+    -d, --delimiter <arg>   The field delimiter for reading CSV data.
+                            Must be a single character (or tab, pipe,
+                            semicolon, space, \\t or \\xHH). (default: ,)
+";
+
+#[derive(Deserialize)]
+struct Args {
+    arg_operation: String,
+    arg_arg: Vec<String>,
+    flag_select: SelectColumns,
+    flag_new_column: Option<String>,
+    flag_suffix: Option<String>,
+    flag_strict: bool,
+    flag_output: Option<String>,
+    flag_no_headers: bool,
+    flag_delimiter: Option<Delimiter>,
+}
+
+/// Transforms a single value, or returns `None` if the value isn't a valid
+/// input.
+type Transform = Box<dyn Fn(&str) -> Option<String>>;
+
+struct Operation {
+    name: &'static str,
+    /// The number of arguments that the operation takes.
+    arity: usize,
+    /// Builds the transform from the arguments of the operation.
+    build: fn(&[String]) -> Result<Transform, String>,
+}
+
+static OPERATIONS: &'static [Operation] = &[
+    Operation { name: "upper", arity: 0, build: upper },
+    Operation { name: "lower", arity: 0, build: lower },
+    Operation { name: "trim", arity: 0, build: trim },
+    Operation { name: "len", arity: 0, build: len },
+    Operation { name: "squeeze", arity: 0, build: squeeze },
+    Operation { name: "replace", arity: 2, build: replace },
+    Operation { name: "datefmt", arity: 2, build: datefmt },
+    Operation { name: "round", arity: 1, build: round },
+    Operation { name: "abs", arity: 0, build: abs },
+];
+
+fn transform<F>(f: F) -> Result<Transform, String>
+        where F: Fn(&str) -> Option<String> + 'static {
+    Ok(Box::new(f))
+}
+
+fn upper(_: &[String]) -> Result<Transform, String> {
+    transform(|s| Some(s.to_uppercase()))
+}
+
+fn lower(_: &[String]) -> Result<Transform, String> {
+    transform(|s| Some(s.to_lowercase()))
+}
+
+fn trim(_: &[String]) -> Result<Transform, String> {
+    transform(|s| Some(s.trim().to_owned()))
+}
+
+fn len(_: &[String]) -> Result<Transform, String> {
+    transform(|s| Some(s.chars().count().to_string()))
+}
+
+fn squeeze(_: &[String]) -> Result<Transform, String> {
+    transform(|s| {
+        let mut out = String::with_capacity(s.len());
+        let mut space = false;
+        for c in s.chars() {
+            if c.is_whitespace() {
+                if !space {
+                    out.push(' ');
+                }
+                space = true;
+            } else {
+                out.push(c);
+                space = false;
+            }
+        }
+        Some(out)
+    })
+}
+
+fn replace(args: &[String]) -> Result<Transform, String> {
+    let (from, to) = (args[0].clone(), args[1].clone());
+    if from.is_empty() {
+        return Err("The string to replace must not be empty.".to_owned());
+    }
+    transform(move |s| Some(s.replace(&*from, &to)))
+}
+
+fn datefmt(args: &[String]) -> Result<Transform, String> {
+    let (input, output) = (args[0].clone(), args[1].clone());
+    transform(move |s| {
+        DateTime::parse(s.trim(), &input).map(|dt| dt.format(&output))
+    })
+}
+
+fn parse_number(s: &str) -> Option<f64> {
+    s.trim().parse::<f64>().ok().filter(|n| n.is_finite())
+}
+
+fn round(args: &[String]) -> Result<Transform, String> {
+    let digits: usize = match args[0].parse() {
+        Ok(digits) => digits,
+        Err(_) => {
+            return Err(format!(
+                "The number of digits '{}' is not a non-negative integer.",
+                args[0]));
+        }
+    };
+    transform(move |s| parse_number(s).map(|n| format!("{:.*}", digits, n)))
+}
+
+fn abs(_: &[String]) -> Result<Transform, String> {
+    transform(|s| {
+        // Removing the sign keeps the formatting of the number as it is.
+        parse_number(s)?;
+        let s = s.trim();
+        Some(s.trim_start_matches(|c| c == '-' || c == '+').to_owned())
+    })
+}
+
+pub fn run(argv: &[&str]) -> CliResult<()> {
+    let args: Args = util::get_args(USAGE, argv)?;
+    let op = match OPERATIONS.iter().find(|op| op.name == args.arg_operation) {
+        Some(op) => op,
+        None => {
+            let names: Vec<&str> = OPERATIONS.iter().map(|op| op.name)
+                                             .collect();
+            return fail_usage!(format!(
+                "Unknown operation '{}'. The operations are: {}.",
+                args.arg_operation, names.join(", ")));
+        }
+    };
+    // The arguments of the operation are followed by an optional input.
+    if args.arg_arg.len() < op.arity || args.arg_arg.len() > op.arity + 1 {
+        return fail_usage!(format!(
+            "The operation '{}' takes {} argument(s), and may be followed \
+             by an input file.", op.name, op.arity));
+    }
+    let input = args.arg_arg.get(op.arity).cloned();
+    let f = (op.build)(&args.arg_arg[..op.arity]).map_err(CliError::Usage)?;
+    if args.flag_new_column.is_some() && args.flag_suffix.is_some() {
+        return fail_usage!("--new-column and --suffix cannot be used \
+                            together.");
+    }
+
+    let rconfig = Config::new(&input)
+        .delimiter(args.flag_delimiter)
+        .no_headers(args.flag_no_headers)
+        .select(args.flag_select);
+    let mut rdr = rconfig.reader()?;
+    let mut wtr = Config::new(&args.flag_output).writer()?;
+    let mut headers = rdr.byte_headers()?.clone();
+    let sel = rconfig.selection(&headers)?;
+
+    let new_names: Option<Vec<Vec<u8>>> = match (&args.flag_new_column,
+                                                &args.flag_suffix) {
+        (&Some(ref names), _) => {
+            let names: Vec<Vec<u8>> = names.split(',')
+                .map(|n| n.as_bytes().to_vec()).collect();
+            if names.len() != sel.len() {
+                return fail_usage!(format!(
+                    "--new-column has {} name(s), but {} column(s) are \
+                     selected.", names.len(), sel.len()));
+            }
+            Some(names)
+        }
+        (_, &Some(ref suffix)) => {
+            Some(sel.select(&headers).map(|name| {
+                let mut name = name.to_vec();
+                name.extend_from_slice(suffix.as_bytes());
+                name
+            }).collect())
+        }
+        _ => None,
+    };
+    if !rconfig.no_headers {
+        if let Some(ref names) = new_names {
+            for name in names {
+                headers.push_field(name);
+            }
+        }
+        wtr.write_byte_record(&headers)?;
+    }
+
+    let mut failures = 0u64;
+    let mut record = csv::ByteRecord::new();
+    let mut out = csv::ByteRecord::new();
+    let mut results: Vec<Vec<u8>> = vec![];
+    while rdr.read_byte_record(&mut record)? {
+        results.clear();
+        for field in sel.select(&record) {
+            if field.is_empty() {
+                results.push(vec![]);
+                continue;
+            }
+            let value = String::from_utf8_lossy(field);
+            match f(&value) {
+                Some(v) => results.push(v.into_bytes()),
+                None => {
+                    failures += 1;
+                    if args.flag_strict {
+                        results.push(vec![]);
+                    } else {
+                        results.push(field.to_vec());
+                    }
+                }
+            }
+        }
+        out.clear();
+        if new_names.is_some() {
+            out.extend(&record);
+            out.extend(&results);
+        } else {
+            let mut fields: Vec<&[u8]> = record.iter().collect();
+            for (&i, result) in sel.iter().zip(&results) {
+                fields[i] = result;
+            }
+            out.extend(fields);
+        }
+        wtr.write_byte_record(&out)?;
+    }
+    wtr.flush()?;
+    if failures > 0 {
+        werr!("warning: {} value(s) were invalid for '{}'", failures, op.name);
+    }
+    Ok(())
+}
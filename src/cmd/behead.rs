@@ -0,0 +1,51 @@
+use csv;
+
+use CliResult;
+use config::{Config, Delimiter};
+use util;
+
+pub static USAGE: &'static str = "
+Drops the header row from CSV data and writes all other records.
+
+With the --flip flag, this does the opposite and writes only the header row.
+
+Usage:
+    xsv behead [options] [<input>]
+    xsv behead --help
+
+behead options:
+    -f, --flip             Only write the header row.
+
+Common options:
+    -h, --help             Display this message
+    -o, --output <file>    Write output to <file> instead of stdout.
+    -d, --delimiter <arg>  The field delimiter for reading CSV data.
+                           Must be a single character (or tab, pipe,
+                           semicolon, space, \\t or \\xHH). (default: ,)
+";
+
+#[derive(Deserialize)]
+struct Args {
+    arg_input: Option<String>,
+    flag_flip: bool,
+    flag_output: Option<String>,
+    flag_delimiter: Option<Delimiter>,
+}
+
+pub fn run(argv: &[&str]) -> CliResult<()> {
+    let args: Args = util::get_args(USAGE, argv)?;
+    let rconfig = Config::new(&args.arg_input)
+        .delimiter(args.flag_delimiter);
+
+    let mut rdr = rconfig.reader()?;
+    let mut wtr = Config::new(&args.flag_output).writer()?;
+    if args.flag_flip {
+        rconfig.write_headers(&mut rdr, &mut wtr)?;
+        return Ok(wtr.flush()?);
+    }
+    let mut record = csv::ByteRecord::new();
+    while rdr.read_byte_record(&mut record)? {
+        wtr.write_byte_record(&record)?;
+    }
+    Ok(wtr.flush()?)
+}
@@ -4,7 +4,7 @@ use CliResult;
 use config::{Config, Delimiter};
 use util;
 
-static USAGE: &'static str = "
+pub static USAGE: &'static str = "
 Concatenates CSV data by column or by row.
 
 When concatenating by column, the columns will be written in the same order as
@@ -35,7 +35,8 @@ Common options:
                            as column names. Note that this has no effect when
                            concatenating columns.
     -d, --delimiter <arg>  The field delimiter for reading CSV data.
-                           Must be a single character. (default: ,)
+                           Must be a single character (or tab, pipe,
+                           semicolon, space, \\t or \\xHH). (default: ,)
 ";
 
 #[derive(Deserialize)]
@@ -65,7 +66,6 @@ impl Args {
         util::many_configs(&*self.arg_input,
                            self.flag_delimiter,
                            self.flag_no_headers)
-             .map_err(From::from)
     }
 
     fn cat_rows(&self) -> CliResult<()> {
@@ -0,0 +1,242 @@
+use std::io::{self, Write};
+
+use CliResult;
+use cli;
+use util;
+
+pub static USAGE: &'static str = "
+Prints a script that adds tab completion of xsv commands and their options
+to a shell, which is one of 'bash', 'zsh', 'fish' or 'powershell', e.g.,
+
+    xsv completions bash > ~/.local/share/bash-completion/completions/xsv
+    xsv completions zsh > ~/.zfunc/_xsv
+    xsv completions fish > ~/.config/fish/completions/xsv.fish
+    xsv completions powershell >> $PROFILE
+
+The commands and options are taken from the help messages of the commands,
+so the script is always complete for the installed version of xsv. Other
+arguments are completed as file names.
+
+Usage:
+    xsv completions <shell>
+    xsv completions --help
+
+Common options:
+    -h, --help             Display this message
+";
+
+#[derive(Deserialize)]
+struct Args {
+    arg_shell: Shell,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+    PowerShell,
+}
+
+/// An option of a command, as described in its usage message.
+struct Opt {
+    short: Option<char>,
+    long: Option<String>,
+    takes_arg: bool,
+    desc: String,
+}
+
+impl Opt {
+    /// The spellings of the option, like `-o` and `--output`.
+    fn names(&self) -> Vec<String> {
+        let mut names = vec![];
+        if let Some(c) = self.short {
+            names.push(format!("-{}", c));
+        }
+        if let Some(ref long) = self.long {
+            names.push(format!("--{}", long));
+        }
+        names
+    }
+}
+
+/// Returns the options in a usage message. These are the lines that start
+/// with '-' (which docopt doesn't allow for other lines).
+fn options(usage: &str) -> Vec<Opt> {
+    let mut opts = vec![];
+    for line in usage.lines() {
+        let line = line.trim();
+        if !line.starts_with('-') {
+            continue;
+        }
+        let (spec, desc) = match line.find("  ") {
+            Some(i) => (&line[..i], line[i..].trim()),
+            None => (line, ""),
+        };
+        let desc = match desc.find("[default:") {
+            Some(i) => desc[..i].trim(),
+            None => desc,
+        };
+        let mut opt = Opt {
+            short: None,
+            long: None,
+            takes_arg: false,
+            desc: desc.to_owned(),
+        };
+        for word in spec.split(|c| c == ',' || c == ' ') {
+            let (word, arg) = match word.find('=') {
+                Some(i) => (&word[..i], true),
+                None => (word, false),
+            };
+            opt.takes_arg |= arg;
+            if word.starts_with("--") {
+                opt.long = Some(word[2..].to_owned());
+            } else if word.starts_with('-') {
+                opt.short = word[1..].chars().next();
+            } else if word.starts_with('<') {
+                opt.takes_arg = true;
+            }
+        }
+        opts.push(opt);
+    }
+    opts
+}
+
+/// Removes the characters that would need escaping in the scripts from a
+/// description.
+fn clean(desc: &str) -> String {
+    desc.chars().filter(|&c| !"'\"`$\\[]:".contains(c)).collect()
+}
+
+pub fn run(argv: &[&str]) -> CliResult<()> {
+    let args: Args = util::get_args(USAGE, argv)?;
+    let commands: Vec<(&str, &str, Vec<Opt>)> = cli::commands().into_iter()
+        .map(|(name, desc)| {
+            let opts = cli::usage(name).map(options).unwrap_or_else(Vec::new);
+            (name, desc, opts)
+        })
+        .collect();
+    let script = match args.arg_shell {
+        Shell::Bash => bash(&commands),
+        Shell::Zsh => zsh(&commands),
+        Shell::Fish => fish(&commands),
+        Shell::PowerShell => powershell(&commands),
+    };
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    out.write_all(script.as_bytes())?;
+    Ok(out.flush()?)
+}
+
+fn bash(commands: &[(&str, &str, Vec<Opt>)]) -> String {
+    let names: Vec<&str> = commands.iter().map(|c| c.0).collect();
+    let mut s = String::new();
+    s.push_str("_xsv() {\n");
+    s.push_str("    local cur=\"${COMP_WORDS[COMP_CWORD]}\"\n");
+    s.push_str("    if [ \"$COMP_CWORD\" -eq 1 ]; then\n");
+    s.push_str(&format!(
+        "        COMPREPLY=($(compgen -W \"{}\" -- \"$cur\"))\n",
+        names.join(" ")));
+    s.push_str("        return\n    fi\n");
+    s.push_str("    if [[ \"$cur\" != -* ]]; then\n");
+    s.push_str("        COMPREPLY=($(compgen -f -- \"$cur\"))\n");
+    s.push_str("        return\n    fi\n");
+    s.push_str("    local opts\n");
+    s.push_str("    case \"${COMP_WORDS[1]}\" in\n");
+    for &(name, _, ref opts) in commands {
+        let flags: Vec<String> =
+            opts.iter().flat_map(|o| o.names()).collect();
+        s.push_str(&format!("        {}) opts=\"{}\" ;;\n",
+                            name, flags.join(" ")));
+    }
+    s.push_str("    esac\n");
+    s.push_str("    COMPREPLY=($(compgen -W \"$opts\" -- \"$cur\"))\n");
+    s.push_str("}\n");
+    s.push_str("complete -o filenames -F _xsv xsv\n");
+    s
+}
+
+fn zsh(commands: &[(&str, &str, Vec<Opt>)]) -> String {
+    let mut s = String::new();
+    s.push_str("#compdef xsv\n\n_xsv() {\n");
+    s.push_str("    local -a commands\n    commands=(\n");
+    for &(name, desc, _) in commands {
+        s.push_str(&format!("        '{}:{}'\n", name, clean(desc)));
+    }
+    s.push_str("    )\n");
+    s.push_str("    if (( CURRENT == 2 )); then\n");
+    s.push_str("        _describe 'command' commands\n");
+    s.push_str("        return\n    fi\n");
+    s.push_str("    case $words[2] in\n");
+    for &(name, _, ref opts) in commands {
+        s.push_str(&format!("        {})\n            _arguments \\\n", name));
+        for opt in opts {
+            for flag in opt.names() {
+                let arg = if opt.takes_arg { ":value:_files" } else { "" };
+                s.push_str(&format!("                '{}[{}]{}' \\\n",
+                                    flag, clean(&opt.desc), arg));
+            }
+        }
+        s.push_str("                '*:file:_files'\n            ;;\n");
+    }
+    s.push_str("    esac\n}\n\n_xsv \"$@\"\n");
+    s
+}
+
+fn fish(commands: &[(&str, &str, Vec<Opt>)]) -> String {
+    let mut s = String::new();
+    for &(name, desc, _) in commands {
+        s.push_str(&format!(
+            "complete -c xsv -n '__fish_use_subcommand' -f -a {} -d '{}'\n",
+            name, clean(desc)));
+    }
+    for &(name, _, ref opts) in commands {
+        for opt in opts {
+            s.push_str(&format!(
+                "complete -c xsv -n '__fish_seen_subcommand_from {}'", name));
+            if let Some(c) = opt.short {
+                s.push_str(&format!(" -s {}", c));
+            }
+            if let Some(ref long) = opt.long {
+                s.push_str(&format!(" -l {}", long));
+            }
+            if opt.takes_arg {
+                s.push_str(" -r");
+            }
+            s.push_str(&format!(" -d '{}'\n", clean(&opt.desc)));
+        }
+    }
+    s
+}
+
+fn powershell(commands: &[(&str, &str, Vec<Opt>)]) -> String {
+    let mut s = String::new();
+    s.push_str("Register-ArgumentCompleter -Native -CommandName xsv \
+                -ScriptBlock {\n");
+    s.push_str("    param($wordToComplete, $commandAst, $cursorPosition)\n");
+    s.push_str("    $commands = [ordered]@{\n");
+    for &(name, _, ref opts) in commands {
+        let flags: Vec<String> = opts.iter().flat_map(|o| o.names())
+                                     .map(|f| format!("'{}'", f))
+                                     .collect();
+        s.push_str(&format!("        '{}' = @({})\n", name, flags.join(", ")));
+    }
+    s.push_str("    }\n");
+    s.push_str("    $words = @($commandAst.CommandElements | \
+                ForEach-Object { $_.ToString() })\n");
+    s.push_str("    if ($words.Count -lt 2 -or ($words.Count -eq 2 -and \
+                $wordToComplete -ne '')) {\n");
+    s.push_str("        $candidates = $commands.Keys\n");
+    s.push_str("    } elseif ($wordToComplete -like '-*') {\n");
+    s.push_str("        $candidates = $commands[$words[1]]\n");
+    s.push_str("    } else {\n");
+    s.push_str("        return\n");
+    s.push_str("    }\n");
+    s.push_str("    $candidates | Where-Object { $_ -like \"$wordToComplete*\" \
+                } | ForEach-Object {\n");
+    s.push_str("        [System.Management.Automation.CompletionResult]::new(\
+                $_, $_, 'ParameterValue', $_)\n");
+    s.push_str("    }\n}\n");
+    s
+}
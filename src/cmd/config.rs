@@ -0,0 +1,100 @@
+use CliResult;
+use defaults::{self, Source, KEYS};
+use util;
+
+pub static USAGE: &'static str = "
+Shows the defaults of options, which can be set with environment variables
+and a config file.
+
+The config file is $XDG_CONFIG_HOME/xsv/config.toml (or
+~/.config/xsv/config.toml when XDG_CONFIG_HOME isn't set). It has lines
+like these:
+
+    # Read CSV data with semicolons and without a header row.
+    delimiter = \";\"
+    no-headers = true
+
+The environment variable of a setting is its name in uppercase with
+underscores, after XSV_DEFAULT_, like XSV_DEFAULT_DELIMITER=';' or
+XSV_DEFAULT_NO_HEADERS=1. The environment variables win over the config
+file.
+
+A default is used as if its option was given on the command line, for the
+commands that have the option, unless the option is actually given. These
+settings are available:
+
+    delimiter      The --delimiter of commands (also for inputs whose
+                   name ends in .tsv or .tab).
+    quote          The --quote of commands.
+    no-headers     Whether to give --no-headers to commands. Since there
+                   is no option to turn it off, set XSV_DEFAULT_NO_HEADERS=0
+                   to override the config file.
+    out-delimiter  The --out-delimiter of commands (like 'xsv fmt').
+    trim           The global --trim option.
+    comment        The global --comment option.
+    flexible       The global --flexible option.
+    download       Whether to give the global --download option.
+
+Usage:
+    xsv config --show
+    xsv config --path
+    xsv config --help
+
+config options:
+    --show                 Print the settings that are used, in the format
+                           of the config file, with where each comes from.
+    --path                 Print the path of the config file.
+
+Common options:
+    -h, --help             Display this message
+";
+
+#[derive(Deserialize)]
+struct Args {
+    flag_show: bool,
+    flag_path: bool,
+}
+
+pub fn run(argv: &[&str]) -> CliResult<()> {
+    let args: Args = util::get_args(USAGE, argv)?;
+    let path = defaults::config_path();
+    if args.flag_path {
+        match path {
+            None => return fail!("There is no config file, since neither \
+                                  XDG_CONFIG_HOME nor HOME is set."),
+            Some(path) => println!("{}", path.display()),
+        }
+        return Ok(());
+    }
+    if !args.flag_show {
+        return Ok(());
+    }
+    match path {
+        Some(ref path) if path.exists() => {
+            println!("# config file: {}", path.display());
+        }
+        Some(ref path) => {
+            println!("# config file: {} (not found)", path.display());
+        }
+        None => println!("# config file: none"),
+    }
+    let set = defaults::defaults()?;
+    for key in KEYS {
+        match set.iter().find(|d| d.key.name == key.name) {
+            None => println!("# {} is not set ({})", key.name, key.env_var()),
+            Some(d) => {
+                let value = if key.flag {
+                    d.value.clone()
+                } else {
+                    defaults::quote(&d.value)
+                };
+                let source = match d.source {
+                    Source::Env(ref var) => var.clone(),
+                    Source::File => "config file".to_owned(),
+                };
+                println!("{} = {}  # from {}", key.name, value, source);
+            }
+        }
+    }
+    Ok(())
+}
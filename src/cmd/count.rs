@@ -1,42 +1,95 @@
+use std::cmp;
+use std::io::{self, Read, Write};
+
 use csv;
+use memchr::{Memchr, memchr3};
+use tabwriter::TabWriter;
 
 use CliResult;
 use config::{Delimiter, Config};
 use util;
 
-static USAGE: &'static str = "
+pub static USAGE: &'static str = "
 Prints a count of the number of records in the CSV data.
 
 Note that the count will not include the header row (unless --no-headers is
 given).
 
+The input may also be a multi-index created with 'xsv index --multi', in
+which case the records of all of its shards are counted.
+
 Usage:
     xsv count [options] [<input>]
 
+count options:
+    --fast                 Count records by scanning for line terminators
+                           instead of parsing every record. As soon as a
+                           quote or a carriage return is seen, counting
+                           falls back to the CSV parser, so the count is
+                           always the same as without --fast. Note though
+                           that records of unequal length are not reported
+                           as an error in the part of the data that was
+                           scanned.
+    --width                Also report the maximum and average length of a
+                           record and the maximum number of fields seen in
+                           a record. The length of a record is the number
+                           of bytes in its fields plus the delimiters
+                           between them. This always parses every record,
+                           even when an index is present, and permits
+                           records of unequal length.
+    -H, --human-readable   Print counts with thousands separators,
+                           e.g., 12,345,678.
+    --si                   Print counts with SI suffixes, e.g., 12.3M.
+
 Common options:
     -h, --help             Display this message
     -n, --no-headers       When set, the first row will not be included in
                            the count.
     -d, --delimiter <arg>  The field delimiter for reading CSV data.
-                           Must be a single character. (default: ,)
+                           Must be a single character (or tab, pipe,
+                           semicolon, space, \\t or \\xHH). (default: ,)
+    --auto-index           When the index of the CSV data is stale, rebuild
+                           it instead of reporting an error.
 ";
 
 #[derive(Deserialize)]
 struct Args {
     arg_input: Option<String>,
+    flag_fast: bool,
+    flag_width: bool,
+    flag_human_readable: bool,
+    flag_si: bool,
     flag_no_headers: bool,
     flag_delimiter: Option<Delimiter>,
+    flag_auto_index: bool,
 }
 
 pub fn run(argv: &[&str]) -> CliResult<()> {
     let args: Args = util::get_args(USAGE, argv)?;
     let conf = Config::new(&args.arg_input)
         .delimiter(args.flag_delimiter)
-        .no_headers(args.flag_no_headers);
+        .no_headers(args.flag_no_headers)
+        .auto_index(args.flag_auto_index);
 
+    if let Some(midx) = conf.multi_indexed()? {
+        if args.flag_width {
+            return fail_usage!("--width cannot be used with a multi-index.");
+        }
+        return Ok(println!("{}", args.format(midx.count())));
+    }
+    if args.flag_width {
+        if args.flag_fast {
+            return fail_usage!("--fast cannot be used with --width.");
+        }
+        return args.width(conf.flexible(true));
+    }
     let count =
         match conf.indexed()? {
             Some(idx) => idx.count(),
+            None if args.flag_fast => {
+                let count = count_fast(&conf)?;
+                if !conf.no_headers && count > 0 { count - 1 } else { count }
+            }
             None => {
                 let mut rdr = conf.reader()?;
                 let mut count = 0u64;
@@ -47,5 +100,158 @@ pub fn run(argv: &[&str]) -> CliResult<()> {
                 count
             }
         };
-    Ok(println!("{}", count))
+    Ok(println!("{}", args.format(count)))
+}
+
+impl Args {
+    fn width(&self, conf: Config) -> CliResult<()> {
+        let mut rdr = conf.reader()?;
+        let (mut count, mut total_len) = (0u64, 0u64);
+        let (mut max_len, mut max_fields) = (0usize, 0usize);
+        let mut record = csv::ByteRecord::new();
+        while rdr.read_byte_record(&mut record)? {
+            let len = record.as_slice().len() + record.len().saturating_sub(1);
+            count += 1;
+            total_len += len as u64;
+            max_len = cmp::max(max_len, len);
+            max_fields = cmp::max(max_fields, record.len());
+        }
+        let avg_len =
+            if count == 0 { 0.0 } else { total_len as f64 / count as f64 };
+
+        let mut wtr = TabWriter::new(io::stdout());
+        writeln!(&mut wtr, "count\t{}", self.format(count))?;
+        writeln!(&mut wtr, "max_length\t{}", self.format(max_len as u64))?;
+        writeln!(&mut wtr, "avg_length\t{:.2}", avg_len)?;
+        writeln!(&mut wtr, "max_fields\t{}", self.format(max_fields as u64))?;
+        wtr.flush()?;
+        Ok(())
+    }
+
+    fn format(&self, n: u64) -> String {
+        if self.flag_si {
+            si(n)
+        } else if self.flag_human_readable {
+            thousands(n)
+        } else {
+            n.to_string()
+        }
+    }
+}
+
+/// Formats `n` with a comma between every group of three digits.
+fn thousands(n: u64) -> String {
+    let digits = n.to_string();
+    let mut s = String::with_capacity(digits.len() + digits.len() / 3);
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i) % 3 == 0 {
+            s.push(',');
+        }
+        s.push(c);
+    }
+    s
+}
+
+/// Formats `n` with one decimal digit and an SI suffix, e.g., `12.3M`.
+fn si(n: u64) -> String {
+    const SUFFIXES: &'static [&'static str] = &["k", "M", "G", "T", "P", "E"];
+    if n < 1000 {
+        return n.to_string();
+    }
+    let mut value = n as f64;
+    let mut unit = 0;
+    while unit < SUFFIXES.len() {
+        value /= 1000.0;
+        // Bump the unit when rounding would yield something like `1000.0k`.
+        if value < 999.95 || unit == SUFFIXES.len() - 1 {
+            break;
+        }
+        unit += 1;
+    }
+    format!("{:.1}{}", value, SUFFIXES[unit])
+}
+
+/// Counts all records (including the header row) by looking only for line
+/// terminators.
+///
+/// This is only correct as long as the data has no quotes (since a quoted
+/// field may contain a new line), no `\r` bytes (since the CSV parser
+/// also treats those as record terminators) and no comment character (since
+/// the CSV parser skips comment lines). As soon as any of them is found,
+/// the rest of the data is handed to the CSV parser, starting at the
+/// beginning of the line that contains it.
+fn count_fast(conf: &Config) -> CliResult<u64> {
+    let mut rdr = conf.records_io_reader()?;
+    // Lines with comments are skipped by the CSV parser.
+    let comment = conf.get_comment().unwrap_or(b'"');
+    let mut buf = vec![0; 1 << 16];
+    let mut count = 0u64;
+    // The last byte of the previous buffer. The CSV parser skips empty
+    // lines, so a line terminator only ends a record if the byte before it
+    // is not also a line terminator.
+    let mut last: Option<u8> = None;
+    // The part of the current line that was read with previous buffers.
+    let mut partial = vec![];
+    loop {
+        let n = match rdr.read(&mut buf) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(ref err) if err.kind() == io::ErrorKind::Interrupted => {
+                continue;
+            }
+            Err(err) => return fail!(err),
+        };
+        let block = &buf[..n];
+        // Leave the handling of a UTF-8 BOM to the CSV parser.
+        let end = if last.is_none() && block.starts_with(b"\xEF\xBB\xBF") {
+            0
+        } else {
+            memchr3(b'"', b'\r', comment, block).unwrap_or(n)
+        };
+
+        let mut line_start = None;
+        for i in Memchr::new(b'\n', &block[..end]) {
+            let prev = if i == 0 { last } else { Some(block[i - 1]) };
+            if prev.map_or(false, |b| b != b'\n') {
+                count += 1;
+            }
+            line_start = Some(i + 1);
+        }
+        if end < n {
+            let rest = match line_start {
+                Some(s) => block[s..].to_vec(),
+                None => {
+                    partial.extend_from_slice(block);
+                    partial
+                }
+            };
+            let mut rdr = conf.from_reader(io::Cursor::new(rest).chain(rdr));
+            return Ok(count + count_all(&mut rdr)?);
+        }
+        match line_start {
+            Some(s) => {
+                partial.clear();
+                partial.extend_from_slice(&block[s..]);
+            }
+            None => partial.extend_from_slice(block),
+        }
+        last = Some(block[n - 1]);
+    }
+    if last.map_or(false, |b| b != b'\n') {
+        count += 1;
+    }
+    Ok(count)
+}
+
+/// Counts all records, including the header row if there is one.
+fn count_all<R: io::Read>(rdr: &mut csv::Reader<R>) -> CliResult<u64> {
+    let mut count = 0u64;
+    if rdr.has_headers() && !rdr.byte_headers()?.is_empty() {
+        count += 1;
+    }
+    let mut record = csv::ByteRecord::new();
+    while rdr.read_byte_record(&mut record)? {
+        count += 1;
+    }
+    Ok(count)
 }
@@ -0,0 +1,211 @@
+use csv;
+
+use CliResult;
+use config::{Config, Delimiter};
+use date::DateTime;
+use select::SelectColumns;
+use util;
+
+pub static USAGE: &'static str = "
+Parses dates in the selected columns and writes them in another format, e.g.,
+
+    xsv datefmt -s created,updated --output-format '%Y-%m-%d' data.csv
+
+Every value is parsed with the formats given with --input-format, in order,
+then as a Unix timestamp if it is a number of 9 or 10 digits (seconds) or 12
+or 13 digits (milliseconds), and finally with these formats:
+
+    %Y-%m-%dT%H:%M:%S.%f%z    %Y-%m-%dT%H:%M:%S%z    %Y-%m-%dT%H:%M:%S.%f
+    %Y-%m-%dT%H:%M:%S         %Y-%m-%d %H:%M:%S%z    %Y-%m-%d %H:%M:%S
+    %Y-%m-%d                  %m/%d/%y               %m/%d/%Y
+    %Y/%m/%d                  %d %B %Y               %B %d, %Y
+    %d-%b-%Y                  %a, %d %b %Y %H:%M:%S %z
+
+The formats use strftime-like specifications like %Y for the year, %m for the
+month and %d for the day; see the xsv documentation for the full list.
+
+Dates with an offset from UTC (like 2018-01-31T14:05:00+01:00) are written
+in the time of that offset, unless --utc is given, in which case they are
+converted to UTC first. Dates without an offset are written as they are.
+
+Values that can't be parsed are left unchanged, unless --strict-empty is
+given to make them empty or --strict to make them an error. The number of
+values that were parsed and that failed is written to stderr for every
+column.
+
+Usage:
+    xsv datefmt [options] -s <cols> [<input>]
+    xsv datefmt [options] -s <cols> (--input-format <fmt>)... [<input>]
+    xsv datefmt --help
+
+datefmt options:
+    -s, --select <cols>       The columns with dates. See 'xsv select -h'
+                              for the full syntax.
+    -f, --input-format <fmt>  A format to parse the dates with. This can be
+                              given multiple times.
+    --output-format <fmt>     The format to write the dates with.
+                              [default: %Y-%m-%d]
+    --utc                     Convert dates with an offset to UTC.
+    --suffix <suffix>         Write the dates to new columns named like the
+                              selected columns followed by <suffix>.
+    --strict                  Fail on values that can't be parsed.
+    --strict-empty            Make values that can't be parsed empty.
+
+Common options:
+    -h, --help                Display this message
+    -o, --output <file>       Write output to <file> instead of stdout.
+    -n, --no-headers          When set, the first row will not be
+                              interpreted as headers.
+    -d, --delimiter <arg>     The field delimiter for reading CSV data.
+                              Must be a single character (or tab, pipe,
+                              semicolon, space, \\t or \\xHH). (default: ,)
+";
+
+#[derive(Deserialize)]
+struct Args {
+    arg_input: Option<String>,
+    flag_select: SelectColumns,
+    flag_input_format: Vec<String>,
+    flag_output_format: String,
+    flag_utc: bool,
+    flag_suffix: Option<String>,
+    flag_strict: bool,
+    flag_strict_empty: bool,
+    flag_output: Option<String>,
+    flag_no_headers: bool,
+    flag_delimiter: Option<Delimiter>,
+}
+
+/// The formats that are tried after the ones that are given. Formats with
+/// two digit years come before the ones with four digit years that could
+/// match the same values, since %Y also matches two digits.
+static FALLBACK_FORMATS: &'static [&'static str] = &[
+    "%Y-%m-%dT%H:%M:%S.%f%z",
+    "%Y-%m-%dT%H:%M:%S%z",
+    "%Y-%m-%dT%H:%M:%S.%f",
+    "%Y-%m-%dT%H:%M:%S",
+    "%Y-%m-%d %H:%M:%S%z",
+    "%Y-%m-%d %H:%M:%S",
+    "%Y-%m-%d",
+    "%m/%d/%y",
+    "%m/%d/%Y",
+    "%Y/%m/%d",
+    "%d %B %Y",
+    "%B %d, %Y",
+    "%d-%b-%Y",
+    "%a, %d %b %Y %H:%M:%S %z",
+];
+
+fn parse_epoch(s: &str) -> Option<DateTime> {
+    let digits = s.strip_prefix('-').unwrap_or(s);
+    if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let n: i64 = s.parse().ok()?;
+    match digits.len() {
+        9..=10 => Some(DateTime::parse(s, "%s")?),
+        12..=13 => Some(DateTime::from_millis(n)),
+        _ => None,
+    }
+}
+
+fn parse(s: &str, formats: &[String]) -> Option<DateTime> {
+    let s = s.trim();
+    formats.iter().filter_map(|f| DateTime::parse(s, f)).next()
+        .or_else(|| parse_epoch(s))
+        .or_else(|| {
+            FALLBACK_FORMATS.iter().filter_map(|f| DateTime::parse(s, f))
+                            .next()
+        })
+}
+
+pub fn run(argv: &[&str]) -> CliResult<()> {
+    let args: Args = util::get_args(USAGE, argv)?;
+    if args.flag_strict && args.flag_strict_empty {
+        return fail_usage!("--strict and --strict-empty cannot be used \
+                            together.");
+    }
+    let rconfig = Config::new(&args.arg_input)
+        .delimiter(args.flag_delimiter)
+        .no_headers(args.flag_no_headers)
+        .select(args.flag_select);
+    let mut rdr = rconfig.reader()?;
+    let mut wtr = Config::new(&args.flag_output).writer()?;
+    let mut headers = rdr.byte_headers()?.clone();
+    let sel = rconfig.selection(&headers)?;
+    let names: Vec<String> = sel.iter().map(|&i| {
+        if rconfig.no_headers {
+            (i + 1).to_string()
+        } else {
+            String::from_utf8_lossy(&headers[i]).into_owned()
+        }
+    }).collect();
+
+    if !rconfig.no_headers {
+        if let Some(ref suffix) = args.flag_suffix {
+            for name in &names {
+                headers.push_field(format!("{}{}", name, suffix).as_bytes());
+            }
+        }
+        wtr.write_byte_record(&headers)?;
+    }
+
+    let mut parsed = vec![0u64; sel.len()];
+    let mut failed = vec![0u64; sel.len()];
+    let mut record = csv::ByteRecord::new();
+    let mut out = csv::ByteRecord::new();
+    let mut results: Vec<Vec<u8>> = vec![];
+    while rdr.read_byte_record(&mut record)? {
+        results.clear();
+        for (j, field) in sel.select(&record).enumerate() {
+            if field.is_empty() {
+                results.push(vec![]);
+                continue;
+            }
+            let value = String::from_utf8_lossy(field);
+            match parse(&value, &args.flag_input_format) {
+                Some(dt) => {
+                    parsed[j] += 1;
+                    let dt = if args.flag_utc && dt.offset.is_some() {
+                        dt.to_utc()
+                    } else {
+                        dt
+                    };
+                    results.push(dt.format(&args.flag_output_format)
+                                   .into_bytes());
+                }
+                None if args.flag_strict => {
+                    let line = record.position().map_or(0, |p| p.line());
+                    return fail_data!(format!(
+                        "Could not parse the date '{}' in column '{}' on \
+                         line {}.", value, names[j], line));
+                }
+                None => {
+                    failed[j] += 1;
+                    if args.flag_strict_empty {
+                        results.push(vec![]);
+                    } else {
+                        results.push(field.to_vec());
+                    }
+                }
+            }
+        }
+        out.clear();
+        if args.flag_suffix.is_some() {
+            out.extend(&record);
+            out.extend(&results);
+        } else {
+            let mut fields: Vec<&[u8]> = record.iter().collect();
+            for (&i, result) in sel.iter().zip(&results) {
+                fields[i] = result;
+            }
+            out.extend(fields);
+        }
+        wtr.write_byte_record(&out)?;
+    }
+    wtr.flush()?;
+    for (j, name) in names.iter().enumerate() {
+        werr!("{}: {} parsed, {} failed", name, parsed[j], failed[j]);
+    }
+    Ok(())
+}
@@ -0,0 +1,211 @@
+use std::collections::{HashMap, HashSet};
+
+use csv;
+
+use CliResult;
+use config::{Config, Delimiter};
+use select::{SelectColumns, Selection};
+use util;
+
+pub static USAGE: &'static str = "
+Removes duplicate records from CSV data, keeping the first occurrence of each
+record while preserving the order of the records.
+
+Two records are duplicates if they are equal in the key columns, which are
+all columns unless --select is given. Fields are compared exactly (after CSV
+parsing), so differently quoted fields with the same value are equal.
+
+By default, every distinct key is kept in memory. If the CSV data is already
+sorted by the key columns, --sorted makes this command use a constant amount
+of memory instead, by only comparing each record with the one before it.
+
+The number of removed records is written to stderr.
+
+Usage:
+    xsv dedup [options] [<input>]
+    xsv dedup --help
+
+dedup options:
+    -s, --select <arg>     Select the columns that make up the key of a
+                           record. See 'xsv select -h' for the full syntax.
+    --keep <which>         Which record of a set of duplicates to keep:
+                           'first' or 'last'. The kept record stays in its
+                           original position. Keeping the last record of
+                           unsorted data requires reading the data twice,
+                           so the input must be a file.
+                           [default: first]
+    --dupes-output <file>  Write the removed records to <file>.
+    --sorted               The CSV data is sorted by the key columns, so
+                           duplicates are always adjacent. Records that are
+                           equal but not adjacent are all kept.
+
+Common options:
+    -h, --help             Display this message
+    -o, --output <file>    Write output to <file> instead of stdout.
+    -n, --no-headers       When set, the first row will not be interpreted
+                           as headers. (i.e., It is deduplicated along with
+                           the other records.)
+    -d, --delimiter <arg>  The field delimiter for reading CSV data.
+                           Must be a single character (or tab, pipe,
+                           semicolon, space, \\t or \\xHH). (default: ,)
+";
+
+#[derive(Deserialize)]
+struct Args {
+    arg_input: Option<String>,
+    flag_select: SelectColumns,
+    flag_keep: Keep,
+    flag_dupes_output: Option<String>,
+    flag_sorted: bool,
+    flag_output: Option<String>,
+    flag_no_headers: bool,
+    flag_delimiter: Option<Delimiter>,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+enum Keep {
+    First,
+    Last,
+}
+
+type Writer = csv::Writer<Box<::std::io::Write + 'static>>;
+
+pub fn run(argv: &[&str]) -> CliResult<()> {
+    let args: Args = util::get_args(USAGE, argv)?;
+    let rconfig = Config::new(&args.arg_input)
+        .delimiter(args.flag_delimiter)
+        .no_headers(args.flag_no_headers)
+        .select(args.flag_select);
+
+    let mut rdr = rconfig.reader()?;
+    let headers = rdr.byte_headers()?.clone();
+    let sel = rconfig.selection(&headers)?;
+
+    let mut wtr = Config::new(&args.flag_output).writer()?;
+    let mut dupes_wtr = match args.flag_dupes_output {
+        None => None,
+        Some(ref p) => Some(Config::new(&Some(p.clone())).writer()?),
+    };
+    if !rconfig.no_headers {
+        wtr.write_record(&headers)?;
+        if let Some(ref mut w) = dupes_wtr {
+            w.write_record(&headers)?;
+        }
+    }
+
+    let removed = if args.flag_sorted {
+        dedup_sorted(&mut rdr, &sel, args.flag_keep, &mut wtr, &mut dupes_wtr)?
+    } else if args.flag_keep == Keep::Last {
+        if rconfig.is_std() {
+            return fail_usage!("--keep last cannot be used with <stdin> \
+                                unless --sorted is given. Please specify a \
+                                file path.");
+        }
+        let last = last_occurrences(&rconfig, &sel)?;
+        let mut removed = 0u64;
+        let mut record = csv::ByteRecord::new();
+        let mut i = 0u64;
+        while rdr.read_byte_record(&mut record)? {
+            if last[&key(&sel, &record)] == i {
+                wtr.write_byte_record(&record)?;
+            } else {
+                removed += 1;
+                write_dupe(&mut dupes_wtr, &record)?;
+            }
+            i += 1;
+        }
+        removed
+    } else {
+        let mut seen = HashSet::new();
+        let mut removed = 0u64;
+        let mut record = csv::ByteRecord::new();
+        while rdr.read_byte_record(&mut record)? {
+            if seen.insert(key(&sel, &record)) {
+                wtr.write_byte_record(&record)?;
+            } else {
+                removed += 1;
+                write_dupe(&mut dupes_wtr, &record)?;
+            }
+        }
+        removed
+    };
+    wtr.flush()?;
+    if let Some(ref mut w) = dupes_wtr {
+        w.flush()?;
+    }
+    werr!("removed {} duplicate record(s)", removed);
+    Ok(())
+}
+
+fn key(sel: &Selection, record: &csv::ByteRecord) -> Vec<Vec<u8>> {
+    sel.select(record).map(|f| f.to_vec()).collect()
+}
+
+fn write_dupe(
+    dupes_wtr: &mut Option<Writer>,
+    record: &csv::ByteRecord,
+) -> CliResult<()> {
+    if let Some(ref mut w) = *dupes_wtr {
+        w.write_byte_record(record)?;
+    }
+    Ok(())
+}
+
+/// Returns the index of the last record with each key.
+fn last_occurrences(
+    conf: &Config,
+    sel: &Selection,
+) -> CliResult<HashMap<Vec<Vec<u8>>, u64>> {
+    let mut rdr = conf.reader()?;
+    let mut last = HashMap::new();
+    let mut record = csv::ByteRecord::new();
+    let mut i = 0u64;
+    while rdr.read_byte_record(&mut record)? {
+        last.insert(key(sel, &record), i);
+        i += 1;
+    }
+    Ok(last)
+}
+
+/// Removes adjacent duplicates. Returns the number of removed records.
+fn dedup_sorted<R: ::std::io::Read>(
+    rdr: &mut csv::Reader<R>,
+    sel: &Selection,
+    keep: Keep,
+    wtr: &mut Writer,
+    dupes_wtr: &mut Option<Writer>,
+) -> CliResult<u64> {
+    let mut removed = 0u64;
+    // The previous record, which hasn't been written yet with --keep last.
+    let mut prev: Option<csv::ByteRecord> = None;
+    let mut record = csv::ByteRecord::new();
+    while rdr.read_byte_record(&mut record)? {
+        let is_dupe = prev.as_ref().map_or(false, |p| {
+            sel.select(p).eq(sel.select(&record))
+        });
+        match keep {
+            Keep::First if is_dupe => {
+                removed += 1;
+                write_dupe(dupes_wtr, &record)?;
+                continue;
+            }
+            Keep::First => wtr.write_byte_record(&record)?,
+            Keep::Last => {
+                if let Some(ref p) = prev {
+                    if is_dupe {
+                        removed += 1;
+                        write_dupe(dupes_wtr, p)?;
+                    } else {
+                        wtr.write_byte_record(p)?;
+                    }
+                }
+            }
+        }
+        prev = Some(record.clone());
+    }
+    if let (Keep::Last, Some(p)) = (keep, prev) {
+        wtr.write_byte_record(&p)?;
+    }
+    Ok(removed)
+}
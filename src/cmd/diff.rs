@@ -0,0 +1,489 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io::{self, Write};
+
+use csv;
+
+use {CliError, CliResult};
+use config::{Config, Delimiter};
+use json;
+use select::SelectColumns;
+use util;
+
+pub static USAGE: &'static str = "
+Compares two CSV files and reports the records that were added, removed or
+modified between them.
+
+Records are matched by the key columns given with --key. Records whose key
+is only in <right> are added, those whose key is only in <left> are removed
+and those whose key is in both but whose other values differ are modified.
+Without --key, the key is the whole record, so a changed value shows up as
+a removed and an added record.
+
+The output has a 'change' column followed by the columns of the files.
+Added records have the change 'added' and the values from <right>, and
+removed records have the change 'removed' and the values from <left>. A
+modified record is written as two records: one with the change
+'modified-old' and the values from <left>, directly followed by one with
+the change 'modified-new' and the values from <right>. With --json, one
+JSON object is written per change instead, where modified records have the
+key and the old and new values of the changed columns.
+
+By default, <left> or <right> (whichever is smaller) is read into memory.
+With --sorted, both files are assumed to be sorted by the key columns (as
+sorted by 'xsv sort' without options) and are read in a single streaming
+pass instead. It is an error if they aren't sorted.
+
+Both files must have the same columns, though not necessarily in the same
+order. With --ignore-extra-columns, only the columns that are in both files
+are compared.
+
+Usage:
+    xsv diff [options] <left> <right>
+    xsv diff --help
+
+diff options:
+    -s, --key <cols>        The columns that identify a record. See
+                            'xsv select -h' for the full syntax.
+    --sorted                The files are sorted by the key columns.
+    --json                  Write one JSON object per change.
+    --ignore-extra-columns  Only compare the columns in both files.
+
+Common options:
+    -h, --help              Display this message
+    -o, --output <file>     Write output to <file> instead of stdout.
+    -n, --no-headers        When set, the first row will not be interpreted
+                            as headers. Columns are matched by position.
+    -d, --delimiter <arg>   The field delimiter for reading CSV data.
+                            Must be a single character (or tab, pipe,
+                            semicolon, space, \\t or \\xHH). (default: ,)
+";
+
+#[derive(Deserialize)]
+struct Args {
+    arg_left: String,
+    arg_right: String,
+    flag_key: Option<SelectColumns>,
+    flag_sorted: bool,
+    flag_json: bool,
+    flag_ignore_extra_columns: bool,
+    flag_output: Option<String>,
+    flag_no_headers: bool,
+    flag_delimiter: Option<Delimiter>,
+}
+
+/// The values of a record in the columns that are compared.
+type Row = Vec<Vec<u8>>;
+
+enum Change {
+    Added(Row),
+    Removed(Row),
+    Modified(Row, Row),
+}
+
+/// One of the files, with the positions of the compared columns in it.
+struct Side {
+    path: String,
+    rdr: csv::Reader<Box<dyn io::Read + 'static>>,
+    columns: Vec<usize>,
+    record: csv::ByteRecord,
+}
+
+impl Side {
+    fn next(&mut self) -> CliResult<Option<Row>> {
+        if !self.rdr.read_byte_record(&mut self.record)? {
+            return Ok(None);
+        }
+        let record = &self.record;
+        Ok(Some(self.columns.iter()
+                    .map(|&i| record.get(i).unwrap_or(b"").to_vec())
+                    .collect()))
+    }
+}
+
+pub fn run(argv: &[&str]) -> CliResult<()> {
+    let args: Args = util::get_args(USAGE, argv)?;
+    let config = |path: &str| {
+        Config::new(&Some(path.to_owned()))
+            .delimiter(args.flag_delimiter)
+            .no_headers(args.flag_no_headers)
+    };
+    let (lconfig, rconfig) = (config(&args.arg_left), config(&args.arg_right));
+    let mut lrdr = lconfig.reader()?;
+    let mut rrdr = rconfig.reader()?;
+    let lheaders = lrdr.byte_headers()?.clone();
+    let rheaders = rrdr.byte_headers()?.clone();
+
+    let (names, lcols, rcols) = if args.flag_no_headers {
+        if lheaders.len() != rheaders.len() && !args.flag_ignore_extra_columns {
+            return fail_data!(format!(
+                "The files have a different number of columns ({} in {} and \
+                 {} in {}). Use --ignore-extra-columns to compare only the \
+                 columns in both.", lheaders.len(), args.arg_left,
+                rheaders.len(), args.arg_right));
+        }
+        let n = ::std::cmp::min(lheaders.len(), rheaders.len());
+        let names = (1..n + 1).map(|i| format!("field_{}", i).into_bytes())
+                              .collect();
+        (names, (0..n).collect(), (0..n).collect())
+    } else {
+        match_columns(&args, &lheaders, &rheaders)?
+    };
+    let keys: Vec<usize> = match args.flag_key {
+        None => (0..names.len()).collect(),
+        Some(ref sel) => {
+            let mut keys = vec![];
+            for &i in sel.selection(&lheaders, !args.flag_no_headers)?.iter() {
+                match lcols.iter().position(|&c| c == i) {
+                    Some(k) => keys.push(k),
+                    None => {
+                        return fail_data!(format!(
+                            "The key column '{}' isn't in {}.",
+                            String::from_utf8_lossy(&lheaders[i]),
+                            args.arg_right));
+                    }
+                }
+            }
+            keys
+        }
+    };
+
+    let mut out = Output::new(&args, names, keys.clone())?;
+    let left = Side {
+        path: args.arg_left.clone(),
+        rdr: lrdr,
+        columns: lcols,
+        record: csv::ByteRecord::new(),
+    };
+    let right = Side {
+        path: args.arg_right.clone(),
+        rdr: rrdr,
+        columns: rcols,
+        record: csv::ByteRecord::new(),
+    };
+    let whole = args.flag_key.is_none();
+    if args.flag_sorted {
+        diff_sorted(left, right, &keys, whole, &mut out)?;
+    } else {
+        // The size of stdin is unknown, so it is always streamed.
+        let size = |path: &str| {
+            fs::metadata(path).ok().map_or(u64::MAX, |m| m.len())
+        };
+        let swap = size(&args.arg_left) > size(&args.arg_right);
+        diff_hashed(left, right, &keys, whole, swap, &mut out)?;
+    }
+    out.flush()
+}
+
+/// Matches the columns of both files by name, and returns the names of the
+/// compared columns with their positions in each file.
+fn match_columns(
+    args: &Args,
+    lheaders: &csv::ByteRecord,
+    rheaders: &csv::ByteRecord,
+) -> CliResult<(Vec<Vec<u8>>, Vec<usize>, Vec<usize>)> {
+    for headers in &[lheaders, rheaders] {
+        let mut seen = HashSet::new();
+        for name in headers.iter() {
+            if !seen.insert(name) {
+                return fail_data!(format!(
+                    "The column name '{}' is used more than once.",
+                    String::from_utf8_lossy(name)));
+            }
+        }
+    }
+    let (mut names, mut lcols, mut rcols) = (vec![], vec![], vec![]);
+    let mut only_left = vec![];
+    for (i, name) in lheaders.iter().enumerate() {
+        match rheaders.iter().position(|n| n == name) {
+            Some(j) => {
+                names.push(name.to_vec());
+                lcols.push(i);
+                rcols.push(j);
+            }
+            None => only_left.push(String::from_utf8_lossy(name).into_owned()),
+        }
+    }
+    let only_right: Vec<String> = rheaders.iter()
+        .filter(|name| !lheaders.iter().any(|n| n == *name))
+        .map(|name| String::from_utf8_lossy(name).into_owned())
+        .collect();
+    if args.flag_ignore_extra_columns
+            || (only_left.is_empty() && only_right.is_empty()) {
+        return Ok((names, lcols, rcols));
+    }
+    let mut msg = "The files have different columns:".to_owned();
+    if !only_left.is_empty() {
+        msg.push_str(&format!(" {} only in {};",
+                              only_left.join(", "), args.arg_left));
+    }
+    if !only_right.is_empty() {
+        msg.push_str(&format!(" {} only in {};",
+                              only_right.join(", "), args.arg_right));
+    }
+    msg.pop();
+    msg.push_str(". Use --ignore-extra-columns to compare only the columns \
+                  in both files.");
+    fail_data!(msg)
+}
+
+fn key_of(row: &Row, keys: &[usize]) -> Row {
+    keys.iter().map(|&k| row[k].clone()).collect()
+}
+
+fn diff_hashed(
+    left: Side,
+    right: Side,
+    keys: &[usize],
+    whole: bool,
+    swap: bool,
+    out: &mut Output,
+) -> CliResult<()> {
+    // The smaller file is read into memory and the other one is streamed.
+    let (mut hashed, mut streamed) = if swap {
+        (right, left)
+    } else {
+        (left, right)
+    };
+    // The records of the hashed file by key, with their positions. With
+    // whole-record keys, there may be several equal records.
+    let mut map: HashMap<Row, Vec<(usize, Row)>> = HashMap::new();
+    let mut pos = 0;
+    while let Some(row) = hashed.next()? {
+        let entry = map.entry(key_of(&row, keys)).or_insert_with(Vec::new);
+        if !whole && !entry.is_empty() {
+            return Err(CliError::Data(
+                duplicate_key(&hashed.path, &row, keys)));
+        }
+        entry.push((pos, row));
+        pos += 1;
+    }
+
+    let mut matched = HashSet::new();
+    while let Some(row) = streamed.next()? {
+        let key = key_of(&row, keys);
+        let other = match map.get_mut(&key) {
+            Some(rows) if !rows.is_empty() => rows.remove(0).1,
+            _ if !whole && matched.contains(&key) => {
+                return Err(CliError::Data(
+                    duplicate_key(&streamed.path, &row, keys)));
+            }
+            _ => {
+                out.write(if swap {
+                    Change::Removed(row)
+                } else {
+                    Change::Added(row)
+                })?;
+                continue;
+            }
+        };
+        if !whole {
+            matched.insert(key);
+        }
+        let (old, new) = if swap { (row, other) } else { (other, row) };
+        if old != new {
+            out.write(Change::Modified(old, new))?;
+        }
+    }
+
+    let mut rest: Vec<(usize, Row)> =
+        map.into_iter().flat_map(|(_, rows)| rows).collect();
+    rest.sort_by_key(|&(pos, _)| pos);
+    for (_, row) in rest {
+        out.write(if swap { Change::Added(row) } else { Change::Removed(row) })?;
+    }
+    Ok(())
+}
+
+fn diff_sorted(
+    mut left: Side,
+    mut right: Side,
+    keys: &[usize],
+    whole: bool,
+    out: &mut Output,
+) -> CliResult<()> {
+    let mut lprev: Option<Row> = None;
+    let mut rprev: Option<Row> = None;
+    let advance = |side: &mut Side, prev: &mut Option<Row>|
+            -> CliResult<Option<Row>> {
+        let row = match side.next()? {
+            None => return Ok(None),
+            Some(row) => row,
+        };
+        let key = key_of(&row, keys);
+        if let Some(ref prev) = *prev {
+            if key < *prev {
+                return Err(CliError::Data(format!(
+                    "{} is not sorted by the key columns. Sort it with \
+                     'xsv sort' or leave out --sorted.", side.path)));
+            }
+            if key == *prev && !whole {
+                return Err(CliError::Data(
+                    duplicate_key(&side.path, &row, keys)));
+            }
+        }
+        *prev = Some(key);
+        Ok(Some(row))
+    };
+    let mut l = advance(&mut left, &mut lprev)?;
+    let mut r = advance(&mut right, &mut rprev)?;
+    loop {
+        let change = match (l.take(), r.take()) {
+            (None, None) => return Ok(()),
+            (Some(old), None) => {
+                l = advance(&mut left, &mut lprev)?;
+                Change::Removed(old)
+            }
+            (None, Some(new)) => {
+                r = advance(&mut right, &mut rprev)?;
+                Change::Added(new)
+            }
+            (Some(old), Some(new)) => {
+                let (lkey, rkey) = (key_of(&old, keys), key_of(&new, keys));
+                if lkey < rkey {
+                    r = Some(new);
+                    l = advance(&mut left, &mut lprev)?;
+                    Change::Removed(old)
+                } else if lkey > rkey {
+                    l = Some(old);
+                    r = advance(&mut right, &mut rprev)?;
+                    Change::Added(new)
+                } else {
+                    l = advance(&mut left, &mut lprev)?;
+                    r = advance(&mut right, &mut rprev)?;
+                    if old == new {
+                        continue;
+                    }
+                    Change::Modified(old, new)
+                }
+            }
+        };
+        out.write(change)?;
+    }
+}
+
+fn duplicate_key(path: &str, row: &Row, keys: &[usize]) -> String {
+    let key: Vec<String> = key_of(row, keys).iter()
+        .map(|k| String::from_utf8_lossy(k).into_owned())
+        .collect();
+    format!("The key '{}' is used by more than one record in {}.",
+            key.join(","), path)
+}
+
+enum Sink {
+    Csv(csv::Writer<Box<dyn io::Write + 'static>>),
+    Json(io::BufWriter<Box<dyn io::Write + 'static>>),
+}
+
+struct Output {
+    sink: Sink,
+    names: Vec<Vec<u8>>,
+    keys: Vec<usize>,
+}
+
+impl Output {
+    fn new(args: &Args, names: Vec<Vec<u8>>, keys: Vec<usize>)
+            -> CliResult<Output> {
+        let wconfig = Config::new(&args.flag_output);
+        let sink = if args.flag_json {
+            Sink::Json(io::BufWriter::new(wconfig.io_writer()?))
+        } else {
+            let mut wtr = wconfig.writer()?;
+            if !args.flag_no_headers {
+                let mut header = csv::ByteRecord::new();
+                header.push_field(b"change");
+                for name in &names {
+                    header.push_field(name);
+                }
+                wtr.write_byte_record(&header)?;
+            }
+            Sink::Csv(wtr)
+        };
+        Ok(Output { sink: sink, names: names, keys: keys })
+    }
+
+    fn write(&mut self, change: Change) -> CliResult<()> {
+        let wtr = match self.sink {
+            Sink::Csv(ref mut wtr) => {
+                let mut record = csv::ByteRecord::new();
+                match change {
+                    Change::Added(row) => {
+                        record.push_field(b"added");
+                        record.extend(&row);
+                    }
+                    Change::Removed(row) => {
+                        record.push_field(b"removed");
+                        record.extend(&row);
+                    }
+                    Change::Modified(old, new) => {
+                        record.push_field(b"modified-old");
+                        record.extend(&old);
+                        wtr.write_byte_record(&record)?;
+                        record.clear();
+                        record.push_field(b"modified-new");
+                        record.extend(&new);
+                    }
+                }
+                return Ok(wtr.write_byte_record(&record)?);
+            }
+            Sink::Json(ref mut wtr) => wtr,
+        };
+        match change {
+            Change::Added(row) => {
+                write!(wtr, "{{\"change\":\"added\",\"record\":")?;
+                write_object(wtr, &self.names, &row, 0..row.len())?;
+            }
+            Change::Removed(row) => {
+                write!(wtr, "{{\"change\":\"removed\",\"record\":")?;
+                write_object(wtr, &self.names, &row, 0..row.len())?;
+            }
+            Change::Modified(old, new) => {
+                write!(wtr, "{{\"change\":\"modified\",\"key\":")?;
+                write_object(wtr, &self.names, &new, self.keys.iter().cloned())?;
+                write!(wtr, ",\"changes\":{{")?;
+                let changed = (0..new.len()).filter(|&i| old[i] != new[i]);
+                for (n, i) in changed.enumerate() {
+                    if n > 0 {
+                        write!(wtr, ",")?;
+                    }
+                    json::write_str(wtr, &self.names[i])?;
+                    write!(wtr, ":{{\"old\":")?;
+                    json::write_str(wtr, &old[i])?;
+                    write!(wtr, ",\"new\":")?;
+                    json::write_str(wtr, &new[i])?;
+                    write!(wtr, "}}")?;
+                }
+                write!(wtr, "}}")?;
+            }
+        }
+        writeln!(wtr, "}}")?;
+        Ok(())
+    }
+
+    fn flush(&mut self) -> CliResult<()> {
+        match self.sink {
+            Sink::Csv(ref mut wtr) => wtr.flush()?,
+            Sink::Json(ref mut wtr) => wtr.flush()?,
+        }
+        Ok(())
+    }
+}
+
+fn write_object<W, I>(
+    wtr: &mut W,
+    names: &[Vec<u8>],
+    row: &Row,
+    columns: I,
+) -> io::Result<()>
+        where W: Write, I: Iterator<Item=usize> {
+    write!(wtr, "{{")?;
+    for (n, i) in columns.enumerate() {
+        if n > 0 {
+            write!(wtr, ",")?;
+        }
+        json::write_str(wtr, &names[i])?;
+        write!(wtr, ":")?;
+        json::write_str(wtr, &row[i])?;
+    }
+    write!(wtr, "}}")
+}
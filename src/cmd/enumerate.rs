@@ -0,0 +1,131 @@
+use csv;
+use rand::{self, Rng};
+
+use CliResult;
+use config::{Config, Delimiter};
+use util;
+
+pub static USAGE: &'static str = "
+Adds a column with the number of each record to CSV data. The column is
+prepended, or appended with the --append flag, and named 'index' unless
+another name is given with --column-name. It is an error if the CSV data
+already has a column with that name.
+
+Records are numbered from 0 by default. The first number and the step
+between numbers can be changed with --start and --increment.
+
+Instead of numbers, the column can be filled with a random UUID (version 4)
+for every record with --uuid, or with a fixed value with --constant.
+
+Usage:
+    xsv enum [options] [<input>]
+    xsv enum --help
+
+enum options:
+    -a, --append             Append the column instead of prepending it.
+    -c, --column-name <arg>  The name of the column. [default: index]
+    --start <n>              The number of the first record. [default: 0]
+    --increment <k>          The step between the numbers of consecutive
+                             records. [default: 1]
+    --uuid                   Fill the column with random UUIDs.
+    --constant <value>       Fill the column with <value>.
+
+Common options:
+    -h, --help               Display this message
+    -o, --output <file>      Write output to <file> instead of stdout.
+    -n, --no-headers         When set, the first row will not be interpreted
+                             as headers. (i.e., It is numbered like any other
+                             record and no column name is written.)
+    -d, --delimiter <arg>    The field delimiter for reading CSV data.
+                             Must be a single character (or tab, pipe,
+                             semicolon, space, \\t or \\xHH). (default: ,)
+";
+
+#[derive(Deserialize)]
+struct Args {
+    arg_input: Option<String>,
+    flag_append: bool,
+    flag_column_name: String,
+    flag_start: i64,
+    flag_increment: i64,
+    flag_uuid: bool,
+    flag_constant: Option<String>,
+    flag_output: Option<String>,
+    flag_no_headers: bool,
+    flag_delimiter: Option<Delimiter>,
+}
+
+pub fn run(argv: &[&str]) -> CliResult<()> {
+    let args: Args = util::get_args(USAGE, argv)?;
+    if args.flag_uuid && args.flag_constant.is_some() {
+        return fail_usage!("--uuid and --constant cannot be used together.");
+    }
+    let rconfig = Config::new(&args.arg_input)
+        .delimiter(args.flag_delimiter)
+        .no_headers(args.flag_no_headers);
+
+    let mut rdr = rconfig.reader()?;
+    let mut wtr = Config::new(&args.flag_output).writer()?;
+    if !rconfig.no_headers {
+        let headers = rdr.byte_headers()?.clone();
+        let name = args.flag_column_name.as_bytes();
+        if headers.iter().any(|h| h == name) {
+            return fail_data!(format!(
+                "The CSV data already has a column named '{}'. Use \
+                 --column-name to choose another name.",
+                args.flag_column_name));
+        }
+        wtr.write_byte_record(&args.add_field(&headers, name))?;
+    }
+
+    let mut rng = rand::thread_rng();
+    let mut number = args.flag_start;
+    let mut record = csv::ByteRecord::new();
+    while rdr.read_byte_record(&mut record)? {
+        let value = if args.flag_uuid {
+            uuid4(&mut rng).into_bytes()
+        } else if let Some(ref v) = args.flag_constant {
+            v.clone().into_bytes()
+        } else {
+            let v = number.to_string().into_bytes();
+            number = number.wrapping_add(args.flag_increment);
+            v
+        };
+        wtr.write_byte_record(&args.add_field(&record, &value))?;
+    }
+    Ok(wtr.flush()?)
+}
+
+impl Args {
+    fn add_field(
+        &self,
+        record: &csv::ByteRecord,
+        field: &[u8],
+    ) -> csv::ByteRecord {
+        let mut out = csv::ByteRecord::new();
+        if !self.flag_append {
+            out.push_field(field);
+        }
+        out.extend(record);
+        if self.flag_append {
+            out.push_field(field);
+        }
+        out
+    }
+}
+
+/// Returns a random (version 4) UUID in its hyphenated form.
+fn uuid4<R: Rng>(rng: &mut R) -> String {
+    let mut bytes = [0u8; 16];
+    rng.fill(&mut bytes);
+    bytes[6] = (bytes[6] & 0x0f) | 0x40;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+    let mut s = String::with_capacity(36);
+    for (i, b) in bytes.iter().enumerate() {
+        if i == 4 || i == 6 || i == 8 || i == 10 {
+            s.push('-');
+        }
+        s.push_str(&format!("{:02x}", b));
+    }
+    s
+}
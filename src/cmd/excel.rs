@@ -0,0 +1,593 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use CliResult;
+use config::Config;
+use date;
+use util;
+use xml::{self, Event};
+use zip::ZipArchive;
+
+pub static USAGE: &'static str = "
+Converts a sheet of an Excel (.xlsx) or OpenDocument (.ods) spreadsheet to
+CSV. By default, the first sheet is converted.
+
+Numbers are written in plain decimal notation (never in scientific notation)
+and can be rounded with --float-precision. Cells with a date or time format
+are written in ISO 8601 format, e.g., 2024-01-15, 2024-01-15T08:30:00 or
+08:30:00. Booleans are written as 'true' and 'false', and formulas as their
+cached results.
+
+Empty rows and columns at the end of the sheet are dropped. The legacy
+binary Excel format (.xls) is not supported.
+
+Usage:
+    xsv excel [options] <input>
+    xsv excel --help
+
+excel options:
+    -s, --sheet <sheet>      The name or position (starting at 1) of the
+                             sheet to convert.
+    --list-sheets            Print the names of all sheets, one per line,
+                             instead of converting one.
+    --float-precision <n>    Round numbers to at most <n> decimal places.
+
+Common options:
+    -h, --help               Display this message
+    -o, --output <file>      Write output to <file> instead of stdout.
+";
+
+#[derive(Deserialize)]
+struct Args {
+    arg_input: String,
+    flag_sheet: Option<String>,
+    flag_list_sheets: bool,
+    flag_float_precision: Option<usize>,
+    flag_output: Option<String>,
+}
+
+pub fn run(argv: &[&str]) -> CliResult<()> {
+    let args: Args = util::get_args(USAGE, argv)?;
+    let is_xls = Path::new(&args.arg_input)
+        .extension()
+        .map_or(false, |ext| ext.eq_ignore_ascii_case("xls"));
+    let mut zip = match ZipArchive::open(&args.arg_input) {
+        Err(_) if is_xls => {
+            return fail_data!("The legacy binary Excel format (.xls) is not \
+                               supported. Please save the file as .xlsx.");
+        }
+        Err(err) => return fail!(format!("{}: {}", args.arg_input, err)),
+        Ok(zip) => zip,
+    };
+    let book = if zip.contains("xl/workbook.xml") {
+        Workbook::xlsx(&mut zip)?
+    } else if zip.contains("content.xml") {
+        Workbook::ods(&mut zip)?
+    } else {
+        return fail_data!(format!(
+            "{} is neither an .xlsx nor an .ods file.", args.arg_input));
+    };
+
+    let mut wtr = Config::new(&args.flag_output).writer()?;
+    if args.flag_list_sheets {
+        for name in &book.sheets {
+            wtr.write_record(&[name])?;
+        }
+        return Ok(wtr.flush()?);
+    }
+    let index = match args.flag_sheet {
+        None if book.sheets.is_empty() => {
+            return fail_data!("The spreadsheet has no sheets.");
+        }
+        None => 0,
+        Some(ref sheet) => {
+            match book.sheets.iter().position(|s| s == sheet) {
+                Some(i) => i,
+                None => match sheet.parse::<usize>() {
+                    Ok(n) if n >= 1 && n <= book.sheets.len() => n - 1,
+                    _ => return fail_usage!(format!(
+                        "Sheet '{}' not found.", sheet)),
+                },
+            }
+        }
+    };
+    let mut rows = book.read_sheet(&mut zip, index, &args)?;
+
+    for row in &mut rows {
+        while row.last().map_or(false, |c| c.is_empty()) {
+            row.pop();
+        }
+    }
+    while rows.last().map_or(false, |r| r.is_empty()) {
+        rows.pop();
+    }
+    let width = rows.iter().map(|r| r.len()).max().unwrap_or(0);
+    for mut row in rows {
+        row.resize(width, String::new());
+        wtr.write_record(&row)?;
+    }
+    Ok(wtr.flush()?)
+}
+
+enum Format {
+    /// The paths of the sheets in the archive and whether dates are counted
+    /// from 1904 (instead of 1900).
+    Xlsx {
+        paths: Vec<String>,
+        date1904: bool,
+    },
+    Ods,
+}
+
+struct Workbook {
+    sheets: Vec<String>,
+    format: Format,
+}
+
+/// Appends `value` to `row` at column `col`, padding it with empty cells.
+fn set_cell(row: &mut Vec<String>, col: usize, value: String) {
+    if value.is_empty() {
+        return;
+    }
+    if row.len() <= col {
+        row.resize(col + 1, String::new());
+    }
+    row[col] = value;
+}
+
+fn read_string(zip: &mut ZipArchive, name: &str) -> CliResult<String> {
+    match String::from_utf8(zip.read(name)?) {
+        Ok(s) => Ok(s),
+        Err(_) => fail_data!(format!("{} is not valid UTF-8.", name)),
+    }
+}
+
+impl Workbook {
+    fn xlsx(zip: &mut ZipArchive) -> CliResult<Workbook> {
+        let mut rels = HashMap::new();
+        if zip.contains("xl/_rels/workbook.xml.rels") {
+            let s = read_string(zip, "xl/_rels/workbook.xml.rels")?;
+            let mut rdr = xml::Reader::new(&s);
+            while let Some(ev) = rdr.next()? {
+                if let Event::Start { name: "Relationship", ref attrs, .. } = ev {
+                    if let (Some(id), Some(target)) =
+                            (xml::attr(attrs, "Id"), xml::attr(attrs, "Target")) {
+                        let path = if target.starts_with('/') {
+                            target[1..].to_owned()
+                        } else {
+                            format!("xl/{}", target)
+                        };
+                        rels.insert(id.to_owned(), path);
+                    }
+                }
+            }
+        }
+
+        let s = read_string(zip, "xl/workbook.xml")?;
+        let mut rdr = xml::Reader::new(&s);
+        let (mut sheets, mut paths, mut date1904) = (vec![], vec![], false);
+        while let Some(ev) = rdr.next()? {
+            match ev {
+                Event::Start { name: "workbookPr", ref attrs, .. } => {
+                    let v = xml::attr(attrs, "date1904");
+                    date1904 = v == Some("1") || v == Some("true");
+                }
+                Event::Start { name: "sheet", ref attrs, .. } => {
+                    let name = xml::attr(attrs, "name").unwrap_or("");
+                    let path = xml::attr(attrs, "id")
+                        .and_then(|id| rels.get(id))
+                        .cloned()
+                        .unwrap_or_else(|| {
+                            format!("xl/worksheets/sheet{}.xml",
+                                    sheets.len() + 1)
+                        });
+                    sheets.push(name.to_owned());
+                    paths.push(path);
+                }
+                _ => {}
+            }
+        }
+        Ok(Workbook {
+            sheets: sheets,
+            format: Format::Xlsx { paths: paths, date1904: date1904 },
+        })
+    }
+
+    fn ods(zip: &mut ZipArchive) -> CliResult<Workbook> {
+        let s = read_string(zip, "content.xml")?;
+        let mut rdr = xml::Reader::new(&s);
+        let mut sheets = vec![];
+        while let Some(ev) = rdr.next()? {
+            if let Event::Start { name: "table", ref attrs, .. } = ev {
+                sheets.push(xml::attr(attrs, "name").unwrap_or("").to_owned());
+            }
+        }
+        Ok(Workbook { sheets: sheets, format: Format::Ods })
+    }
+
+    fn read_sheet(
+        &self,
+        zip: &mut ZipArchive,
+        index: usize,
+        args: &Args,
+    ) -> CliResult<Vec<Vec<String>>> {
+        match self.format {
+            Format::Xlsx { ref paths, date1904 } => {
+                read_xlsx_sheet(zip, &paths[index], date1904, args)
+            }
+            Format::Ods => read_ods_sheet(zip, index, args),
+        }
+    }
+}
+
+fn read_xlsx_sheet(
+    zip: &mut ZipArchive,
+    path: &str,
+    date1904: bool,
+    args: &Args,
+) -> CliResult<Vec<Vec<String>>> {
+    let mut strings = vec![];
+    if zip.contains("xl/sharedStrings.xml") {
+        let s = read_string(zip, "xl/sharedStrings.xml")?;
+        let mut rdr = xml::Reader::new(&s);
+        let (mut cur, mut in_text, mut in_phonetic) = (String::new(), false, false);
+        while let Some(ev) = rdr.next()? {
+            match ev {
+                Event::Start { name: "t", empty, .. } => in_text = !empty,
+                Event::End("t") => in_text = false,
+                Event::Start { name: "rPh", empty, .. } => in_phonetic = !empty,
+                Event::End("rPh") => in_phonetic = false,
+                Event::Text(ref t) if in_text && !in_phonetic => cur.push_str(t),
+                Event::End("si") => strings.push(::std::mem::replace(
+                    &mut cur, String::new())),
+                _ => {}
+            }
+        }
+    }
+
+    // Whether each cell style has a date or time format.
+    let mut date_styles = vec![];
+    if zip.contains("xl/styles.xml") {
+        let s = read_string(zip, "xl/styles.xml")?;
+        let mut rdr = xml::Reader::new(&s);
+        let mut formats = HashMap::new();
+        let mut in_xfs = false;
+        while let Some(ev) = rdr.next()? {
+            match ev {
+                Event::Start { name: "numFmt", ref attrs, .. } => {
+                    if let (Some(id), Some(code)) = (
+                            xml::attr(attrs, "numFmtId"),
+                            xml::attr(attrs, "formatCode")) {
+                        formats.insert(id.to_owned(), is_date_format(code));
+                    }
+                }
+                Event::Start { name: "cellXfs", empty, .. } => in_xfs = !empty,
+                Event::End("cellXfs") => in_xfs = false,
+                Event::Start { name: "xf", ref attrs, .. } if in_xfs => {
+                    let id = xml::attr(attrs, "numFmtId").unwrap_or("0");
+                    date_styles.push(match formats.get(id) {
+                        Some(&is_date) => is_date,
+                        None => id.parse().map_or(false, is_builtin_date_format),
+                    });
+                }
+                _ => {}
+            }
+        }
+    }
+
+    let s = read_string(zip, path)?;
+    let mut rdr = xml::Reader::new(&s);
+    let mut rows: Vec<Vec<String>> = vec![];
+    let mut row = 0;
+    let mut col = 0;
+    let mut cell_type = String::new();
+    let mut style = 0;
+    let mut value = String::new();
+    let (mut in_value, mut in_phonetic) = (false, false);
+    while let Some(ev) = rdr.next()? {
+        match ev {
+            Event::Start { name: "row", ref attrs, .. } => {
+                row = match xml::attr(attrs, "r").and_then(|r| r.parse::<usize>().ok()) {
+                    Some(r) if r >= 1 => r - 1,
+                    _ => rows.len(),
+                };
+                if rows.len() <= row {
+                    rows.resize(row + 1, vec![]);
+                }
+                col = 0;
+            }
+            Event::Start { name: "c", ref attrs, empty } => {
+                if let Some(c) = xml::attr(attrs, "r").and_then(column_index) {
+                    col = c;
+                }
+                cell_type = xml::attr(attrs, "t").unwrap_or("n").to_owned();
+                style = xml::attr(attrs, "s")
+                    .and_then(|s| s.parse().ok())
+                    .unwrap_or(0);
+                value.clear();
+                if empty {
+                    col += 1;
+                }
+            }
+            Event::Start { name: "v", empty, .. }
+            | Event::Start { name: "t", empty, .. } => in_value = !empty,
+            Event::End("v") | Event::End("t") => in_value = false,
+            Event::Start { name: "rPh", empty, .. } => in_phonetic = !empty,
+            Event::End("rPh") => in_phonetic = false,
+            Event::Text(ref t) if in_value && !in_phonetic => {
+                value.push_str(t);
+            }
+            Event::End("c") => {
+                let v = match &*cell_type {
+                    "s" => {
+                        value.trim().parse::<usize>().ok()
+                            .and_then(|i| strings.get(i))
+                            .cloned()
+                            .unwrap_or_default()
+                    }
+                    "b" => {
+                        if value.trim() == "1" { "true" } else { "false" }
+                            .to_owned()
+                    }
+                    "n" if value.is_empty() => String::new(),
+                    "n" if date_styles.get(style) == Some(&true) => {
+                        match value.trim().parse::<f64>() {
+                            Ok(serial) => serial_to_iso(serial, date1904),
+                            Err(_) => value.clone(),
+                        }
+                    }
+                    "n" => format_number(&value, args.flag_float_precision),
+                    _ => value.clone(),
+                };
+                if rows.len() <= row {
+                    rows.resize(row + 1, vec![]);
+                }
+                set_cell(&mut rows[row], col, v);
+                col += 1;
+            }
+            _ => {}
+        }
+    }
+    Ok(rows)
+}
+
+fn read_ods_sheet(
+    zip: &mut ZipArchive,
+    index: usize,
+    args: &Args,
+) -> CliResult<Vec<Vec<String>>> {
+    let s = read_string(zip, "content.xml")?;
+    let mut rdr = xml::Reader::new(&s);
+    let mut table = 0;
+    let mut in_table = false;
+    let mut rows: Vec<Vec<String>> = vec![];
+    // Empty rows and cells are only added once a non-empty one follows, since
+    // sheets often end with a huge number of repeated empty rows and cells.
+    let mut empty_rows = 0;
+    let mut empty_cells = 0;
+    let mut row: Vec<String> = vec![];
+    let mut row_repeat = 1;
+    let mut cell: Option<(String, usize)> = None;
+    let mut text = String::new();
+    let mut paragraphs = 0;
+    let mut in_annotation = false;
+    while let Some(ev) = rdr.next()? {
+        if !in_table {
+            if let Event::Start { name: "table", empty: false, .. } = ev {
+                in_table = table == index;
+                table += 1;
+            }
+            continue;
+        }
+        match ev {
+            Event::End("table") => break,
+            Event::Start { name: "table-row", ref attrs, empty } => {
+                row.clear();
+                empty_cells = 0;
+                row_repeat = repeat(attrs, "number-rows-repeated");
+                if empty {
+                    empty_rows += row_repeat;
+                }
+            }
+            Event::End("table-row") => {
+                if row.is_empty() {
+                    empty_rows += row_repeat;
+                } else {
+                    for _ in 0..empty_rows {
+                        rows.push(vec![]);
+                    }
+                    empty_rows = 0;
+                    for _ in 0..row_repeat {
+                        rows.push(row.clone());
+                    }
+                }
+            }
+            Event::Start { name, ref attrs, empty }
+                    if name == "table-cell" || name == "covered-table-cell" => {
+                let n = repeat(attrs, "number-columns-repeated");
+                let value = match xml::attr(attrs, "value-type") {
+                    Some("float") | Some("percentage") | Some("currency") => {
+                        xml::attr(attrs, "value").map(|v| {
+                            format_number(v, args.flag_float_precision)
+                        })
+                    }
+                    Some("date") => {
+                        xml::attr(attrs, "date-value").map(|v| v.to_owned())
+                    }
+                    Some("time") => {
+                        xml::attr(attrs, "time-value").map(ods_time)
+                    }
+                    Some("boolean") => {
+                        xml::attr(attrs, "boolean-value").map(|v| v.to_owned())
+                    }
+                    _ => None,
+                };
+                text.clear();
+                paragraphs = 0;
+                cell = Some((value.unwrap_or_default(), n));
+                if empty {
+                    end_ods_cell(&mut row, &mut empty_cells, &mut cell, &text);
+                }
+            }
+            Event::End("table-cell") | Event::End("covered-table-cell") => {
+                end_ods_cell(&mut row, &mut empty_cells, &mut cell, &text);
+            }
+            Event::Start { name: "annotation", empty, .. } => {
+                in_annotation = !empty;
+            }
+            Event::End("annotation") => in_annotation = false,
+            _ if in_annotation => {}
+            Event::Start { name: "p", .. } => {
+                if paragraphs > 0 {
+                    text.push('\n');
+                }
+                paragraphs += 1;
+            }
+            Event::Start { name: "s", ref attrs, .. } => {
+                for _ in 0..repeat(attrs, "c") {
+                    text.push(' ');
+                }
+            }
+            Event::Start { name: "tab", .. } => text.push('\t'),
+            Event::Start { name: "line-break", .. } => text.push('\n'),
+            Event::Text(ref t) if paragraphs > 0 => text.push_str(t),
+            _ => {}
+        }
+    }
+    Ok(rows)
+}
+
+fn end_ods_cell(
+    row: &mut Vec<String>,
+    empty_cells: &mut usize,
+    cell: &mut Option<(String, usize)>,
+    text: &str,
+) {
+    let (value, n) = match cell.take() {
+        None => return,
+        Some((ref v, n)) if v.is_empty() => (text.to_owned(), n),
+        Some(c) => c,
+    };
+    if value.is_empty() {
+        *empty_cells += n;
+        return;
+    }
+    for _ in 0..*empty_cells {
+        row.push(String::new());
+    }
+    *empty_cells = 0;
+    for _ in 0..n {
+        row.push(value.clone());
+    }
+}
+
+fn repeat(attrs: &[(&str, String)], name: &str) -> usize {
+    xml::attr(attrs, name).and_then(|n| n.parse().ok()).unwrap_or(1)
+}
+
+/// Converts a cell reference like "AB12" to the index of its column.
+fn column_index(cell_ref: &str) -> Option<usize> {
+    let mut col = 0;
+    let mut letters = 0;
+    for b in cell_ref.bytes().take_while(|b| b.is_ascii_alphabetic()) {
+        col = col * 26 + (b.to_ascii_uppercase() - b'A') as usize + 1;
+        letters += 1;
+    }
+    if letters == 0 { None } else { Some(col - 1) }
+}
+
+fn format_number(value: &str, precision: Option<usize>) -> String {
+    let n = match value.trim().parse::<f64>() {
+        Err(_) => return value.to_owned(),
+        Ok(n) => n,
+    };
+    match precision {
+        None => format!("{}", n),
+        Some(p) => {
+            let s = format!("{:.*}", p, n);
+            let s = if s.contains('.') {
+                s.trim_end_matches('0').trim_end_matches('.')
+            } else {
+                &*s
+            };
+            if s == "-0" { "0".to_owned() } else { s.to_owned() }
+        }
+    }
+}
+
+/// Returns true if the built-in number format with the given ID is a date
+/// or time format.
+fn is_builtin_date_format(id: u32) -> bool {
+    match id {
+        14..=22 | 27..=36 | 45..=47 | 50..=58 => true,
+        _ => false,
+    }
+}
+
+/// Returns true if a custom number format is a date or time format, i.e.,
+/// contains date or time codes outside of literal text.
+fn is_date_format(code: &str) -> bool {
+    let mut chars = code.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => while chars.next().map_or(false, |c| c != '"') {},
+            '[' => while chars.next().map_or(false, |c| c != ']') {},
+            '\\' | '_' | '*' => { chars.next(); }
+            'd' | 'm' | 'y' | 'h' | 's' | 'D' | 'M' | 'Y' | 'H' | 'S' => {
+                return true;
+            }
+            _ => {}
+        }
+    }
+    false
+}
+
+/// Converts a spreadsheet date (a number of days since the epoch of the
+/// workbook) to ISO 8601.
+fn serial_to_iso(serial: f64, date1904: bool) -> String {
+    let mut days = serial.floor() as i64;
+    let mut secs = ((serial - serial.floor()) * 86400.0).round() as i64;
+    if secs == 86400 {
+        days += 1;
+        secs = 0;
+    }
+    let time = format!("{:02}:{:02}:{:02}",
+                       secs / 3600, secs / 60 % 60, secs % 60);
+    if days == 0 && !date1904 {
+        return time;
+    }
+    // The number of days between the epoch and 1970-01-01. The 1900 date
+    // system treats 1900 as a leap year, so its epoch is effectively
+    // 1899-12-31 before the nonexistent 1900-02-29 and 1899-12-30 after.
+    let epoch = if date1904 {
+        24_107
+    } else if days <= 60 {
+        25_568
+    } else {
+        25_569
+    };
+    let (y, m, d) = date::civil_from_days(days - epoch);
+    let date = format!("{:04}-{:02}-{:02}", y, m, d);
+    if secs == 0 { date } else { format!("{}T{}", date, time) }
+}
+
+/// Converts an ODF duration like PT08H30M00S to a time like 08:30:00.
+fn ods_time(value: &str) -> String {
+    let mut parts = [0f64; 3];
+    let mut num = String::new();
+    for c in value.trim_start_matches("PT").chars() {
+        let i = match c {
+            'H' => 0,
+            'M' => 1,
+            'S' => 2,
+            _ => {
+                num.push(c);
+                continue;
+            }
+        };
+        parts[i] = num.parse().unwrap_or(0.0);
+        num.clear();
+    }
+    let secs = (parts[0] * 3600.0 + parts[1] * 60.0 + parts[2]).round() as i64;
+    format!("{:02}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+}
@@ -0,0 +1,168 @@
+use csv;
+
+use CliResult;
+use config::{Config, Delimiter};
+use select::SelectColumns;
+use util;
+
+pub static USAGE: &'static str = "
+Splits the values of a column on a separator and writes one record for every
+piece, with all other columns copied unchanged.
+
+For example, exploding the 'tags' column of
+
+    id,tags
+    1,red;blue
+
+on ';' results in
+
+    id,tags
+    1,red
+    1,blue
+
+An empty value results in a single record with an empty value (or in no
+record at all with the --drop-empty flag).
+
+The separator is matched literally and may be longer than one character.
+Since the values are split after CSV parsing, pieces that contain the
+delimiter or quotes are written with proper quoting.
+
+Usage:
+    xsv explode [options] <column> <separator> [<input>]
+    xsv explode --help
+
+explode options:
+    --zip                  Explode all selected columns in lockstep: the
+                           i-th piece of every selected column goes into the
+                           i-th output record. Every selected column must
+                           have the same number of pieces. Without --zip,
+                           <column> must select a single column.
+    --drop-empty           Don't write any record for an empty value.
+    -r, --rename <name>    Rename the exploded column. With --zip, give a
+                           comma separated list of names, one for each
+                           selected column.
+
+Common options:
+    -h, --help             Display this message
+    -o, --output <file>    Write output to <file> instead of stdout.
+    -n, --no-headers       When set, the first row will not be interpreted
+                           as headers. (i.e., It is exploded like any other
+                           record.)
+    -d, --delimiter <arg>  The field delimiter for reading CSV data.
+                           Must be a single character (or tab, pipe,
+                           semicolon, space, \\t or \\xHH). (default: ,)
+";
+
+#[derive(Deserialize)]
+struct Args {
+    arg_column: SelectColumns,
+    arg_separator: String,
+    arg_input: Option<String>,
+    flag_zip: bool,
+    flag_drop_empty: bool,
+    flag_rename: Option<String>,
+    flag_output: Option<String>,
+    flag_no_headers: bool,
+    flag_delimiter: Option<Delimiter>,
+}
+
+pub fn run(argv: &[&str]) -> CliResult<()> {
+    let args: Args = util::get_args(USAGE, argv)?;
+    if args.arg_separator.is_empty() {
+        return fail_usage!("The separator must not be empty.");
+    }
+    let rconfig = Config::new(&args.arg_input)
+        .delimiter(args.flag_delimiter)
+        .no_headers(args.flag_no_headers)
+        .select(args.arg_column);
+
+    let mut rdr = rconfig.reader()?;
+    let mut wtr = Config::new(&args.flag_output).writer()?;
+    let mut headers = rdr.byte_headers()?.clone();
+    let sel = rconfig.selection(&headers)?;
+    let columns: Vec<usize> = sel.iter().cloned().collect();
+    if columns.is_empty() {
+        return fail_usage!("No column selected.");
+    }
+    if columns.len() > 1 && !args.flag_zip {
+        return fail_usage!("More than one column selected. Use --zip to \
+                            explode several columns in lockstep.");
+    }
+
+    if let Some(ref names) = args.flag_rename {
+        let names: Vec<&str> = if columns.len() == 1 {
+            vec![&**names]
+        } else {
+            names.split(',').collect()
+        };
+        if names.len() != columns.len() {
+            return fail_usage!(format!(
+                "{} names were given with --rename, but {} columns are \
+                 selected.", names.len(), columns.len()));
+        }
+        let mut renamed = csv::ByteRecord::new();
+        for (i, field) in headers.iter().enumerate() {
+            match columns.iter().position(|&c| c == i) {
+                Some(k) => renamed.push_field(names[k].as_bytes()),
+                None => renamed.push_field(field),
+            }
+        }
+        headers = renamed;
+    }
+    if !rconfig.no_headers {
+        wtr.write_record(&headers)?;
+    }
+
+    let sep = args.arg_separator.as_bytes();
+    let mut record = csv::ByteRecord::new();
+    let mut out = csv::ByteRecord::new();
+    while rdr.read_byte_record(&mut record)? {
+        let pieces: Vec<Vec<&[u8]>> = columns
+            .iter()
+            .map(|&c| split(record.get(c).unwrap_or(b""), sep))
+            .collect();
+        let count = pieces[0].len();
+        if pieces.iter().any(|p| p.len() != count) {
+            let line = record.position().map_or(0, |p| p.line());
+            return fail_data!(format!(
+                "The selected columns on line {} have different numbers \
+                 of pieces.", line));
+        }
+        if count == 0 && !args.flag_drop_empty {
+            wtr.write_byte_record(&record)?;
+        }
+        for i in 0..count {
+            out.clear();
+            for (j, field) in record.iter().enumerate() {
+                match columns.iter().position(|&c| c == j) {
+                    Some(k) => out.push_field(pieces[k][i]),
+                    None => out.push_field(field),
+                }
+            }
+            wtr.write_byte_record(&out)?;
+        }
+    }
+    Ok(wtr.flush()?)
+}
+
+/// Splits `value` on every occurrence of `sep`. An empty value has no
+/// pieces.
+fn split<'a>(value: &'a [u8], sep: &[u8]) -> Vec<&'a [u8]> {
+    if value.is_empty() {
+        return vec![];
+    }
+    let mut pieces = vec![];
+    let mut start = 0;
+    let mut i = 0;
+    while i + sep.len() <= value.len() {
+        if &value[i..i + sep.len()] == sep {
+            pieces.push(&value[start..i]);
+            i += sep.len();
+            start = i;
+        } else {
+            i += 1;
+        }
+    }
+    pieces.push(&value[start..]);
+    pieces
+}
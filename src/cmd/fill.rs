@@ -0,0 +1,170 @@
+use csv;
+
+use CliResult;
+use config::{Config, Delimiter};
+use select::{SelectColumns, Selection};
+use util;
+
+pub static USAGE: &'static str = "
+Fills empty fields in the selected columns. By default, every empty field is
+filled with the last non-empty value above it in the same column.
+
+Empty fields that come before any non-empty value in their column stay empty,
+unless a value is given with --value, in which case they are filled with it.
+If --value is given without --forward or --backward, all empty fields are
+simply filled with that value.
+
+When --group-by is given, values are only carried within runs of adjacent
+records that are equal in the group columns, so a value never leaks from one
+group into the next.
+
+Filling forward or with a constant value uses memory proportional to the
+number of columns. Filling backward requires reading all of the CSV data
+into memory.
+
+Usage:
+    xsv fill [options] [<input>]
+    xsv fill --help
+
+fill options:
+    -s, --select <arg>     Select the columns to fill. See 'xsv select -h'
+                           for the full syntax. By default, all columns are
+                           filled.
+    --forward              Fill empty fields with the last non-empty value
+                           above them. This is the default.
+    --backward             Fill empty fields with the next non-empty value
+                           below them.
+    --value <value>        Fill empty fields with <value>. When filling
+                           forward or backward, only fields that have no
+                           value to take are filled with <value>.
+    -g, --group-by <arg>   Select the columns that identify a group. Values
+                           are never carried from one group into another.
+
+Common options:
+    -h, --help             Display this message
+    -o, --output <file>    Write output to <file> instead of stdout.
+    -n, --no-headers       When set, the first row will not be interpreted
+                           as headers. (i.e., It is filled like any other
+                           record.)
+    -d, --delimiter <arg>  The field delimiter for reading CSV data.
+                           Must be a single character (or tab, pipe,
+                           semicolon, space, \\t or \\xHH). (default: ,)
+";
+
+#[derive(Deserialize)]
+struct Args {
+    arg_input: Option<String>,
+    flag_select: SelectColumns,
+    flag_forward: bool,
+    flag_backward: bool,
+    flag_value: Option<String>,
+    flag_group_by: Option<SelectColumns>,
+    flag_output: Option<String>,
+    flag_no_headers: bool,
+    flag_delimiter: Option<Delimiter>,
+}
+
+pub fn run(argv: &[&str]) -> CliResult<()> {
+    let args: Args = util::get_args(USAGE, argv)?;
+    if args.flag_forward && args.flag_backward {
+        return fail_usage!(
+            "--forward and --backward cannot be used together.");
+    }
+    let rconfig = Config::new(&args.arg_input)
+        .delimiter(args.flag_delimiter)
+        .no_headers(args.flag_no_headers)
+        .select(args.flag_select.clone());
+
+    let mut rdr = rconfig.reader()?;
+    let mut wtr = Config::new(&args.flag_output).writer()?;
+    let headers = rdr.byte_headers()?.clone();
+    let sel = rconfig.selection(&headers)?;
+    let group_sel = match args.flag_group_by {
+        None => None,
+        Some(ref sel) => Some(sel.selection(&headers, !rconfig.no_headers)?),
+    };
+    if !rconfig.no_headers {
+        wtr.write_record(&headers)?;
+    }
+
+    let columns: Vec<usize> = sel.iter().cloned().collect();
+    let mut filler = Filler {
+        last: vec![None; columns.len()],
+        columns: columns,
+        group_sel: group_sel,
+        constant: args.flag_value.as_ref().map(|v| v.as_bytes().to_vec()),
+        carry: args.flag_forward || args.flag_backward
+               || args.flag_value.is_none(),
+        group: None,
+    };
+
+    if args.flag_backward {
+        let mut records = rdr.byte_records().collect::<Result<Vec<_>, _>>()?;
+        for record in records.iter_mut().rev() {
+            *record = filler.fill(record);
+        }
+        for record in &records {
+            wtr.write_byte_record(record)?;
+        }
+    } else {
+        let mut record = csv::ByteRecord::new();
+        while rdr.read_byte_record(&mut record)? {
+            wtr.write_byte_record(&filler.fill(&record))?;
+        }
+    }
+    Ok(wtr.flush()?)
+}
+
+struct Filler {
+    /// The indices of the columns to fill.
+    columns: Vec<usize>,
+    group_sel: Option<Selection>,
+    /// The value for empty fields that have nothing to take.
+    constant: Option<Vec<u8>>,
+    /// Whether non-empty values are carried to the following records.
+    carry: bool,
+    /// The last non-empty value seen in each of the `columns`.
+    last: Vec<Option<Vec<u8>>>,
+    /// The group key of the last record.
+    group: Option<Vec<Vec<u8>>>,
+}
+
+impl Filler {
+    /// Fills the empty fields of `record`, which follows (in the direction of
+    /// filling) the records given to previous calls.
+    fn fill(&mut self, record: &csv::ByteRecord) -> csv::ByteRecord {
+        if let Some(ref sel) = self.group_sel {
+            let key: Vec<Vec<u8>> =
+                sel.select(record).map(|f| f.to_vec()).collect();
+            if self.group.as_ref() != Some(&key) {
+                for last in &mut self.last {
+                    *last = None;
+                }
+                self.group = Some(key);
+            }
+        }
+        let mut out = csv::ByteRecord::new();
+        for (i, field) in record.iter().enumerate() {
+            let k = match self.columns.iter().position(|&c| c == i) {
+                None => {
+                    out.push_field(field);
+                    continue;
+                }
+                Some(k) => k,
+            };
+            if !field.is_empty() {
+                if self.carry {
+                    self.last[k] = Some(field.to_vec());
+                }
+                out.push_field(field);
+            } else if let Some(ref v) = self.last[k] {
+                out.push_field(v);
+            } else if let Some(ref v) = self.constant {
+                out.push_field(v);
+            } else {
+                out.push_field(field);
+            }
+        }
+        out
+    }
+}
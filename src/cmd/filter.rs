@@ -0,0 +1,109 @@
+use csv;
+
+use CliResult;
+use config::{Config, Delimiter};
+use expr::{Expr, Mismatch, Value};
+use util;
+
+pub static USAGE: &'static str = "
+Writes the records for which an expression is true, e.g.,
+
+    xsv filter 'amount > 1000 and status != \"refunded\"' data.csv
+    xsv filter 'country in (\"DE\", \"FR\") and email is not empty' data.csv
+
+Expressions can use:
+
+    amount, `unit price`  The value of a column. Names that aren't
+                          identifiers must be quoted in backticks.
+    1.5, \"text\", true     Number, string and boolean literals.
+    == != < <= > >=       Comparisons.
+    x is empty            Whether x is the empty string. This can also be
+                          written as 'x is not empty'.
+    x in (a, b, ...)      Whether x is equal to one of the values. This can
+                          also be written as 'x not in (a, b, ...)'.
+    and, or, not          Logical operators.
+    ( )                   Grouping.
+
+as well as the arithmetic operators and functions of 'xsv map'.
+
+Comparisons with a number are numeric. When a value that isn't a number is
+compared with a number, the comparison is false, and the record doesn't
+match if the value is needed for arithmetic. With --strict, such a value is
+an error instead. Other comparisons are numeric if both values are numbers,
+and compare strings otherwise.
+
+Usage:
+    xsv filter [options] <expression> [<input>]
+    xsv filter --help
+
+filter options:
+    -v, --invert           Write the records for which the expression is not
+                           true instead.
+    --strict               Fail on values that aren't numbers where numbers
+                           are needed.
+
+Common options:
+    -h, --help             Display this message
+    -o, --output <file>    Write output to <file> instead of stdout.
+    -d, --delimiter <arg>  The field delimiter for reading CSV data.
+                           Must be a single character (or tab, pipe,
+                           semicolon, space, \\t or \\xHH). (default: ,)
+";
+
+#[derive(Deserialize)]
+struct Args {
+    arg_expression: String,
+    arg_input: Option<String>,
+    flag_invert: bool,
+    flag_strict: bool,
+    flag_output: Option<String>,
+    flag_delimiter: Option<Delimiter>,
+}
+
+pub fn run(argv: &[&str]) -> CliResult<()> {
+    let args: Args = util::get_args(USAGE, argv)?;
+    let rconfig = Config::new(&args.arg_input)
+        .delimiter(args.flag_delimiter);
+
+    let mut rdr = rconfig.reader()?;
+    let mut wtr = Config::new(&args.flag_output).writer()?;
+    let headers = rdr.byte_headers()?.clone();
+    let names: Vec<String> = headers.iter()
+        .map(|name| String::from_utf8_lossy(name).into_owned())
+        .collect();
+
+    let src = &args.arg_expression;
+    let resolve = |name: &str| names.iter().position(|n| n == name);
+    let expr = match Expr::parse(src, resolve) {
+        Ok(expr) => expr,
+        Err(err) => return fail_usage!(err.describe(src)),
+    };
+    let expr = expr.mismatch(if args.flag_strict {
+        Mismatch::Error
+    } else {
+        Mismatch::False
+    });
+    wtr.write_byte_record(&headers)?;
+
+    let mut record = csv::ByteRecord::new();
+    let mut row: Vec<Value> = vec![];
+    while rdr.read_byte_record(&mut record)? {
+        row.clear();
+        row.extend(record.iter().map(|field| {
+            Value::String(String::from_utf8_lossy(field).into_owned())
+        }));
+        row.resize(names.len(), Value::String(String::new()));
+        let matched = match expr.eval(&row) {
+            Ok(value) => value.is_truthy(),
+            Err(_) if !args.flag_strict => false,
+            Err(err) => {
+                let line = record.position().map_or(0, |p| p.line());
+                return fail_data!(format!("Error on line {}: {}", line, err));
+            }
+        };
+        if matched != args.flag_invert {
+            wtr.write_byte_record(&record)?;
+        }
+    }
+    Ok(wtr.flush()?)
+}
@@ -0,0 +1,286 @@
+use std::io::{self, BufRead, Write};
+use std::str;
+
+use csv;
+
+use CliResult;
+use config::{Config, Delimiter};
+use util;
+
+pub static USAGE: &'static str = "
+Converts fixed-width text to CSV, or CSV to fixed-width text with --reverse.
+
+The layout of the fields is read from a CSV file with a header row and one
+row for every field, e.g.,
+
+    name,start,width,trim,justify
+    id,1,6,,right
+    name,7,20
+    amount,27,10,no,right
+
+The 'name', 'start' and 'width' columns are required. The start is the
+position of the first character of the field, where the first character of
+a line is at 1. The optional 'trim' column says whether trailing spaces are
+removed from the field ('yes' or 'no', by default 'yes'). The optional
+'justify' column says whether the field is aligned to the 'left' (the
+default) or to the 'right' when writing fixed-width text.
+
+Positions and widths are counted in bytes, unless --chars is given, in which
+case they are counted in characters. Empty lines are skipped. A line that is
+shorter than the layout requires is an error, unless --pad-short is given,
+in which case the missing fields are empty.
+
+With --reverse, the input is CSV with a header row that contains the names
+of the fields in the layout (or, with --no-headers, the fields in the order
+of the layout), and every record is written as a line of fixed-width text.
+Values are padded with spaces, and values that are wider than their field
+are an error.
+
+Usage:
+    xsv fixed [options] --layout <file> [<input>]
+    xsv fixed --help
+
+fixed options:
+    -l, --layout <file>    The CSV file with the layout of the fields.
+    --chars                Count positions and widths in characters instead
+                           of bytes.
+    --pad-short            Allow lines that are shorter than the layout.
+    --reverse              Convert CSV to fixed-width text.
+
+Common options:
+    -h, --help             Display this message
+    -o, --output <file>    Write output to <file> instead of stdout.
+    -n, --no-headers       When set, no header row is written, or when
+                           converting CSV, the CSV data has no header row.
+    -d, --delimiter <arg>  The field delimiter for reading CSV data when
+                           converting CSV. Must be a single character (or
+                           tab, pipe, semicolon, space, \\t or \\xHH).
+                           (default: ,)
+";
+
+#[derive(Deserialize)]
+struct Args {
+    arg_input: Option<String>,
+    flag_layout: String,
+    flag_chars: bool,
+    flag_pad_short: bool,
+    flag_reverse: bool,
+    flag_output: Option<String>,
+    flag_no_headers: bool,
+    flag_delimiter: Option<Delimiter>,
+}
+
+/// A field of the layout. The start is 0-based.
+struct Field {
+    name: String,
+    start: usize,
+    width: usize,
+    trim: bool,
+    right: bool,
+}
+
+fn read_layout(path: &str) -> CliResult<Vec<Field>> {
+    let mut rdr = Config::new(&Some(path.to_owned())).flexible(true)
+                                                     .reader()?;
+    let headers = rdr.headers()?.clone();
+    let column = |name: &str| {
+        headers.iter().position(|h| h.trim().eq_ignore_ascii_case(name))
+    };
+    let (name, start, width) = match (column("name"), column("start"),
+                                      column("width")) {
+        (Some(n), Some(s), Some(w)) => (n, s, w),
+        _ => return fail_usage!(format!(
+            "The layout {} must have the columns 'name', 'start' and \
+             'width'.", path)),
+    };
+    let (trim, justify) = (column("trim"), column("justify"));
+
+    let mut fields = vec![];
+    let mut record = csv::StringRecord::new();
+    while rdr.read_record(&mut record)? {
+        let line = record.position().map_or(0, |p| p.line());
+        let get = |i: Option<usize>| {
+            i.and_then(|i| record.get(i)).unwrap_or("").trim().to_lowercase()
+        };
+        let number = |i: usize, what: &str| -> CliResult<usize> {
+            match get(Some(i)).parse::<usize>() {
+                Ok(n) => Ok(n),
+                Err(_) => fail_usage!(format!(
+                    "Invalid {} '{}' on line {} of the layout.",
+                    what, get(Some(i)), line)),
+            }
+        };
+        let field_start = number(start, "start")?;
+        if field_start == 0 {
+            return fail_usage!(format!(
+                "Invalid start 0 on line {} of the layout. The first \
+                 character of a line is at 1.", line));
+        }
+        let field_trim = match &*get(trim) {
+            "" | "yes" | "y" | "true" | "1" => true,
+            "no" | "n" | "false" | "0" => false,
+            other => return fail_usage!(format!(
+                "Invalid trim '{}' on line {} of the layout. It must be \
+                 'yes' or 'no'.", other, line)),
+        };
+        let field_right = match &*get(justify) {
+            "" | "left" => false,
+            "right" => true,
+            other => return fail_usage!(format!(
+                "Invalid justify '{}' on line {} of the layout. It must be \
+                 'left' or 'right'.", other, line)),
+        };
+        fields.push(Field {
+            name: record.get(name).unwrap_or("").to_owned(),
+            start: field_start - 1,
+            width: number(width, "width")?,
+            trim: field_trim,
+            right: field_right,
+        });
+    }
+    if fields.is_empty() {
+        return fail_usage!(format!("The layout {} has no fields.", path));
+    }
+    Ok(fields)
+}
+
+pub fn run(argv: &[&str]) -> CliResult<()> {
+    let args: Args = util::get_args(USAGE, argv)?;
+    let layout = read_layout(&args.flag_layout)?;
+    if args.flag_reverse {
+        to_fixed(&args, &layout)
+    } else {
+        from_fixed(&args, &layout)
+    }
+}
+
+fn from_fixed(args: &Args, layout: &[Field]) -> CliResult<()> {
+    let rconfig = Config::new(&args.arg_input);
+    let mut rdr = io::BufReader::new(rconfig.io_reader()?);
+    let mut wtr = Config::new(&args.flag_output).writer()?;
+    if !args.flag_no_headers {
+        wtr.write_record(layout.iter().map(|f| &f.name))?;
+    }
+    let required = layout.iter().map(|f| f.start + f.width).max().unwrap_or(0);
+
+    let mut line = vec![];
+    let mut offsets = vec![];
+    let mut line_number = 0u64;
+    let mut record = csv::ByteRecord::new();
+    loop {
+        line.clear();
+        if rdr.read_until(b'\n', &mut line)? == 0 {
+            break;
+        }
+        line_number += 1;
+        while line.last() == Some(&b'\n') || line.last() == Some(&b'\r') {
+            line.pop();
+        }
+        if line.is_empty() {
+            continue;
+        }
+        // With --chars, the offset of every character and of the end.
+        if args.flag_chars {
+            line = String::from_utf8_lossy(&line).into_owned().into_bytes();
+            offsets.clear();
+            offsets.extend(str::from_utf8(&line).unwrap().char_indices()
+                                                .map(|(i, _)| i));
+            offsets.push(line.len());
+        }
+        let len = if args.flag_chars { offsets.len() - 1 } else { line.len() };
+        let offset = |i: usize| if args.flag_chars { offsets[i] } else { i };
+        if len < required && !args.flag_pad_short {
+            return fail_data!(format!(
+                "Line {} is {} {} long, but the layout requires {}.",
+                line_number, len,
+                if args.flag_chars { "characters" } else { "bytes" },
+                required));
+        }
+        record.clear();
+        for field in layout {
+            let start = offset(field.start.min(len));
+            let end = offset((field.start + field.width).min(len));
+            let mut value = &line[start..end];
+            if field.trim {
+                while value.last() == Some(&b' ') {
+                    value = &value[..value.len() - 1];
+                }
+            }
+            record.push_field(value);
+        }
+        wtr.write_byte_record(&record)?;
+    }
+    Ok(wtr.flush()?)
+}
+
+fn to_fixed(args: &Args, layout: &[Field]) -> CliResult<()> {
+    let rconfig = Config::new(&args.arg_input)
+        .delimiter(args.flag_delimiter)
+        .no_headers(args.flag_no_headers);
+    let mut rdr = rconfig.reader()?;
+    let headers = rdr.byte_headers()?.clone();
+    let columns: Vec<usize> = if rconfig.no_headers {
+        (0..layout.len()).collect()
+    } else {
+        let mut columns = vec![];
+        for field in layout {
+            match headers.iter().position(|h| h == field.name.as_bytes()) {
+                Some(i) => columns.push(i),
+                None => return fail_usage!(format!(
+                    "The field '{}' of the layout is not a column of the \
+                     CSV data.", field.name)),
+            }
+        }
+        columns
+    };
+    let mut order: Vec<usize> = (0..layout.len()).collect();
+    order.sort_by_key(|&i| layout[i].start);
+    for w in order.windows(2) {
+        let (a, b) = (&layout[w[0]], &layout[w[1]]);
+        if a.start + a.width > b.start {
+            return fail_usage!(format!(
+                "The fields '{}' and '{}' of the layout overlap.",
+                a.name, b.name));
+        }
+    }
+
+    let mut wtr = io::BufWriter::new(
+        Config::new(&args.flag_output).io_writer()?);
+    let mut record = csv::ByteRecord::new();
+    let mut line = vec![];
+    while rdr.read_byte_record(&mut record)? {
+        line.clear();
+        // The number of bytes or characters written to the line so far.
+        let mut pos = 0;
+        for &i in &order {
+            let field = &layout[i];
+            let value = record.get(columns[i]).unwrap_or(b"");
+            let width = if args.flag_chars {
+                String::from_utf8_lossy(value).chars().count()
+            } else {
+                value.len()
+            };
+            if width > field.width {
+                let line_number = record.position().map_or(0, |p| p.line());
+                return fail_data!(format!(
+                    "The value '{}' of the field '{}' on line {} is wider \
+                     than the width {} of the field.",
+                    String::from_utf8_lossy(value), field.name, line_number,
+                    field.width));
+            }
+            let padding = field.width - width;
+            line.resize(line.len() + (field.start - pos), b' ');
+            if field.right {
+                line.resize(line.len() + padding, b' ');
+                line.extend_from_slice(value);
+            } else {
+                line.extend_from_slice(value);
+                line.resize(line.len() + padding, b' ');
+            }
+            pos = field.start + field.width;
+        }
+        line.push(b'\n');
+        wtr.write_all(&line)?;
+    }
+    Ok(wtr.flush()?)
+}
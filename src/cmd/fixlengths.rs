@@ -1,12 +1,14 @@
 use std::cmp;
+use std::collections::HashMap;
 
 use csv;
+use serde::de::{Deserialize, Deserializer, Error};
 
 use CliResult;
 use config::{Config, Delimiter};
 use util;
 
-static USAGE: &'static str = "
+pub static USAGE: &'static str = "
 Transforms CSV data so that all records have the same length. The length is
 the length of the longest record in the data (not counting trailing empty fields,
 but at least 1). Records with smaller lengths are padded with empty fields.
@@ -18,6 +20,20 @@ given must be a file and not stdin.
 Alternatively, if --length is set, then all records are forced to that length.
 This requires a single pass and can be done with stdin.
 
+The target length can also be chosen with --length-from:
+
+    header  The length of the first record. This requires a single pass
+            and can be done with stdin.
+    max     The length of the longest record, as described above. (This is
+            the default.)
+    mode    The most common record length. Like 'max', this requires two
+            complete scans of the CSV data, and it additionally uses memory
+            proportional to the number of distinct record lengths.
+    <n>     Exactly <n>, which is the same as --length <n>.
+
+When --length-from is used, the number of records that were padded and
+truncated is written to stderr.
+
 Usage:
     xsv fixlengths [options] [<input>]
 
@@ -25,71 +41,188 @@ fixlengths options:
     -l, --length <arg>     Forcefully set the length of each record. If a
                            record is not the size given, then it is truncated
                            or expanded as appropriate.
+    --length-from <arg>    Choose the target length from 'header', 'max',
+                           'mode' or an explicit number. See above.
+    --fill <value>         The value to use for the fields added when a
+                           record is padded. [default: ]
+    --pad-only             Only pad records that are too short. Records that
+                           are too long are written unchanged.
+    --truncate-only        Only truncate records that are too long. Records
+                           that are too short are written unchanged.
+    --strict               When used with --pad-only or --truncate-only,
+                           report an error instead of writing a record that
+                           needs the other kind of fix unchanged.
+    --report               Don't write any CSV data. Instead, write a CSV
+                           report with one row per record that doesn't have
+                           the target length, containing its line number,
+                           its length and the target length.
 
 Common options:
     -h, --help             Display this message
     -o, --output <file>    Write output to <file> instead of stdout.
     -d, --delimiter <arg>  The field delimiter for reading CSV data.
-                           Must be a single character. (default: ,)
+                           Must be a single character (or tab, pipe,
+                           semicolon, space, \\t or \\xHH). (default: ,)
 ";
 
 #[derive(Deserialize)]
 struct Args {
     arg_input: Option<String>,
     flag_length: Option<usize>,
+    flag_length_from: Option<LengthFrom>,
+    flag_fill: String,
+    flag_pad_only: bool,
+    flag_truncate_only: bool,
+    flag_strict: bool,
+    flag_report: bool,
     flag_output: Option<String>,
     flag_delimiter: Option<Delimiter>,
 }
 
 pub fn run(argv: &[&str]) -> CliResult<()> {
     let args: Args = util::get_args(USAGE, argv)?;
+    if args.flag_pad_only && args.flag_truncate_only {
+        return fail_usage!("--pad-only and --truncate-only cannot be used at \
+                            the same time.");
+    }
     let config = Config::new(&args.arg_input)
         .delimiter(args.flag_delimiter)
         .no_headers(true)
         .flexible(true);
-    let length = match args.flag_length {
-        Some(length) => {
-            if length == 0 {
-                return fail!("Length must be greater than 0.");
-            }
-            length
+    let length_from = match (args.flag_length, args.flag_length_from) {
+        (Some(_), Some(_)) => {
+            return fail_usage!("--length and --length-from cannot be used at \
+                                the same time.");
+        }
+        (Some(n), None) => LengthFrom::Exact(n),
+        (None, Some(from)) => from,
+        (None, None) => LengthFrom::Max,
+    };
+    // When the length is taken from the header, it isn't known until the
+    // first record is read.
+    let mut length = match length_from {
+        LengthFrom::Exact(0) => {
+            return fail_usage!("Length must be greater than 0.");
         }
-        None => {
+        LengthFrom::Exact(n) => Some(n),
+        LengthFrom::Header => None,
+        LengthFrom::Max | LengthFrom::Mode => {
             if config.is_std() {
-                return fail!("<stdin> cannot be used in this command. \
-                              Please specify a file path.");
-            }
-            let mut maxlen = 0usize;
-            let mut rdr = config.reader()?;
-            let mut record = csv::ByteRecord::new();
-            while rdr.read_byte_record(&mut record)? {
-                let mut index = 0;
-                let mut nonempty_count = 0;
-                for field in &record {
-                    index += 1;
-                    if index == 1 || !field.is_empty() {
-                        nonempty_count = index;
-                    }
-                }
-                maxlen = cmp::max(maxlen, nonempty_count);
+                return fail_usage!("<stdin> cannot be used in this command. \
+                                    Please specify a file path.");
             }
-            maxlen
+            Some(scan_length(&config, length_from)?)
         }
     };
 
     let mut rdr = config.reader()?;
-    let mut wtr = Config::new(&args.flag_output).writer()?;
+    let mut wtr = Config::new(&args.flag_output)
+        .flexible(args.flag_pad_only || args.flag_truncate_only)
+        .writer()?;
+    if args.flag_report {
+        wtr.write_record(&["line", "length", "target"])?;
+    }
+    let (mut padded, mut truncated) = (0u64, 0u64);
     for r in rdr.byte_records() {
         let mut r = r?;
-        if length >= r.len() {
+        let length = *length.get_or_insert(r.len());
+        if r.len() == length {
+            if !args.flag_report {
+                wtr.write_byte_record(&r)?;
+            }
+            continue;
+        }
+        let line = r.position().map_or(0, |p| p.line());
+        if args.flag_report {
+            wtr.write_record(&[
+                line.to_string(), r.len().to_string(), length.to_string(),
+            ])?;
+            continue;
+        }
+        let fix = if r.len() < length {
+            !args.flag_truncate_only
+        } else {
+            !args.flag_pad_only
+        };
+        if !fix {
+            if args.flag_strict {
+                return fail_data!(format!(
+                    "The record on line {} has length {} but the target \
+                     length is {}.", line, r.len(), length));
+            }
+        } else if r.len() < length {
             for _ in r.len()..length {
-                r.push_field(b"");
+                r.push_field(args.flag_fill.as_bytes());
             }
+            padded += 1;
         } else {
             r.truncate(length);
+            truncated += 1;
         }
         wtr.write_byte_record(&r)?;
     }
     wtr.flush()?;
+    if args.flag_length_from.is_some() && !args.flag_report {
+        werr!("padded {} record(s), truncated {} record(s)",
+              padded, truncated);
+    }
     Ok(())
 }
+
+/// Determines the target length with a complete scan of the CSV data.
+fn scan_length(config: &Config, from: LengthFrom) -> CliResult<usize> {
+    let mut maxlen = 0usize;
+    let mut counts: HashMap<usize, u64> = HashMap::new();
+    let mut rdr = config.reader()?;
+    let mut record = csv::ByteRecord::new();
+    while rdr.read_byte_record(&mut record)? {
+        if from == LengthFrom::Mode {
+            *counts.entry(record.len()).or_insert(0) += 1;
+            continue;
+        }
+        let mut index = 0;
+        let mut nonempty_count = 0;
+        for field in &record {
+            index += 1;
+            if index == 1 || !field.is_empty() {
+                nonempty_count = index;
+            }
+        }
+        maxlen = cmp::max(maxlen, nonempty_count);
+    }
+    if from == LengthFrom::Mode {
+        // Ties are broken in favor of the shorter length.
+        return Ok(counts
+            .into_iter()
+            .max_by_key(|&(len, count)| (count, cmp::Reverse(len)))
+            .map_or(0, |(len, _)| len));
+    }
+    Ok(maxlen)
+}
+
+/// Where the target length of every record comes from.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum LengthFrom {
+    Header,
+    Max,
+    Mode,
+    Exact(usize),
+}
+
+impl<'de> Deserialize<'de> for LengthFrom {
+    fn deserialize<D: Deserializer<'de>>(
+        d: D,
+    ) -> Result<LengthFrom, D::Error> {
+        let raw = String::deserialize(d)?;
+        match &*raw {
+            "header" => Ok(LengthFrom::Header),
+            "max" => Ok(LengthFrom::Max),
+            "mode" => Ok(LengthFrom::Mode),
+            s => s.parse().map(LengthFrom::Exact).map_err(|_| {
+                D::Error::custom(format!(
+                    "Unknown --length-from value '{}'. Expected 'header', \
+                     'max', 'mode' or a number.", s))
+            }),
+        }
+    }
+}
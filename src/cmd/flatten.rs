@@ -1,13 +1,15 @@
 use std::borrow::Cow;
 use std::io::{self, Write};
 
+use csv;
 use tabwriter::TabWriter;
 
 use CliResult;
 use config::{Config, Delimiter};
+use json;
 use util;
 
-static USAGE: &'static str = "
+pub static USAGE: &'static str = "
 Prints flattened records such that fields are labeled separated by a new line.
 This mode is particularly useful for viewing one record at a time. Each
 record is separated by a special '#' character (on a line by itself), which
@@ -16,6 +18,10 @@ can be changed with the --separator flag.
 There is also a condensed view (-c or --condense) that will shorten the
 contents of each field to provide a summary view.
 
+With --json or --jsonl, each record is instead written as a JSON object that
+maps the header names to the values of the record (which are always strings).
+These values are also shortened by --condense.
+
 Usage:
     xsv flatten [options] [<input>]
 
@@ -28,14 +34,19 @@ flatten options:
                            When non-empty, a new line is automatically
                            appended to the separator.
                            [default: #]
+    --json                 Write the records as a JSON array of objects.
+    --jsonl                Write the records as JSON Lines, i.e., one JSON
+                           object per line.
 
 Common options:
     -h, --help             Display this message
     -n, --no-headers       When set, the first row will not be interpreted
                            as headers. When set, the name of each field
-                           will be its index.
+                           will be its index. (With --json or --jsonl, the
+                           keys are field_1, field_2, etc.)
     -d, --delimiter <arg>  The field delimiter for reading CSV data.
-                           Must be a single character. (default: ,)
+                           Must be a single character (or tab, pipe,
+                           semicolon, space, \\t or \\xHH). (default: ,)
 ";
 
 #[derive(Deserialize)]
@@ -43,6 +54,8 @@ struct Args {
     arg_input: Option<String>,
     flag_condense: Option<usize>,
     flag_separator: String,
+    flag_json: bool,
+    flag_jsonl: bool,
     flag_no_headers: bool,
     flag_delimiter: Option<Delimiter>,
 }
@@ -55,6 +68,21 @@ pub fn run(argv: &[&str]) -> CliResult<()> {
     let mut rdr = rconfig.reader()?;
     let headers = rdr.byte_headers()?.clone();
 
+    if args.flag_json || args.flag_jsonl {
+        if args.flag_json && args.flag_jsonl {
+            return fail_usage!("--json and --jsonl cannot be used at the same \
+                                time.");
+        }
+        let keys: Vec<Vec<u8>> = headers.iter().enumerate().map(|(i, h)| {
+            if rconfig.no_headers {
+                format!("field_{}", i + 1).into_bytes()
+            } else {
+                h.to_vec()
+            }
+        }).collect();
+        return args.write_json(rdr, &keys);
+    }
+
     let mut wtr = TabWriter::new(io::stdout());
     let mut first = true;
     for r in rdr.byte_records() {
@@ -78,3 +106,43 @@ pub fn run(argv: &[&str]) -> CliResult<()> {
     wtr.flush()?;
     Ok(())
 }
+
+impl Args {
+    fn write_json<R: io::Read>(
+        &self,
+        mut rdr: csv::Reader<R>,
+        keys: &[Vec<u8>],
+    ) -> CliResult<()> {
+        let mut wtr = io::BufWriter::new(io::stdout());
+        let mut count = 0u64;
+        if self.flag_json {
+            write!(&mut wtr, "[")?;
+        }
+        for r in rdr.byte_records() {
+            let r = r?;
+            if self.flag_json {
+                write!(&mut wtr, "{}\n  ", if count == 0 { "" } else { "," })?;
+            }
+            count += 1;
+            write!(&mut wtr, "{{")?;
+            for (i, (key, field)) in keys.iter().zip(&r).enumerate() {
+                if i > 0 {
+                    write!(&mut wtr, ", ")?;
+                }
+                json::write_str(&mut wtr, key)?;
+                write!(&mut wtr, ": ")?;
+                json::write_str(&mut wtr, &*util::condense(
+                    Cow::Borrowed(&*field), self.flag_condense))?;
+            }
+            write!(&mut wtr, "}}")?;
+            if self.flag_jsonl {
+                writeln!(&mut wtr)?;
+            }
+        }
+        if self.flag_json {
+            writeln!(&mut wtr, "{}]", if count == 0 { "" } else { "\n" })?;
+        }
+        wtr.flush()?;
+        Ok(())
+    }
+}
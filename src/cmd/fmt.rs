@@ -1,10 +1,15 @@
+use std::borrow::Cow;
+use std::mem;
+use std::str;
+
 use csv;
+use serde::de::{Deserialize, Deserializer, Error};
 
 use CliResult;
 use config::{Config, Delimiter};
 use util;
 
-static USAGE: &'static str = "
+pub static USAGE: &'static str = "
 Formats CSV data with a custom delimiter or CRLF line endings.
 
 Generally, all commands in xsv output CSV data in a default format, which is
@@ -13,24 +18,55 @@ pipe multiple xsv commands together. However, you may want the final result to
 have a specific delimiter or record separator, and this is where 'xsv fmt' is
 useful.
 
+The --trim and --squeeze flags also clean up whitespace in fields. When either
+is given, the number of fields that were modified is written to stderr.
+
 Usage:
     xsv fmt [options] [<input>]
 
 fmt options:
-    -t, --out-delimiter <arg>  The field delimiter for writing CSV data.
+    -t, --out-delimiter <arg>  The field delimiter for writing CSV data,
+                               which is given like --delimiter.
                                [default: ,]
     --crlf                     Use '\\r\\n' line endings in the output.
+                               This is the same as '--terminator crlf'.
+    --terminator <arg>         The record terminator for writing CSV data.
+                               This is either 'crlf' or a single character,
+                               which is written after the header and every
+                               record instead of '\\n'. It takes precedence
+                               over the record separator set by --ascii.
     --ascii                    Use ASCII field and record separators.
     --quote <arg>              The quote character to use. [default: \"]
-    --quote-always             Put quotes around every value.
+    --quote-always             Put quotes around every value. This is the
+                               same as '--quote-style always'.
+    --quote-style <arg>        The quoting style to use for the output:
+                               'minimal' only quotes values when necessary,
+                               'non-numeric' quotes every value that isn't
+                               a number, 'always' quotes every value and
+                               'never' never quotes values. With 'never',
+                               values containing the delimiter or a record
+                               terminator are an error unless --escape is
+                               given, in which case those characters (and
+                               the escape character itself) are preceded
+                               by the escape character.
+                               [default: minimal]
     --escape <arg>             The escape character to use. When not specified,
                                quotes are escaped by doubling them.
+    --trim                     Strip leading and trailing whitespace from
+                               every field, including the header row.
+                               This happens after a field is parsed, so
+                               whitespace inside quotes is stripped too.
+    --squeeze                  Collapse every run of whitespace inside a
+                               field to a single space.
+    --no-trim-headers          Don't apply --trim or --squeeze to the first
+                               row.
 
 Common options:
     -h, --help             Display this message
     -o, --output <file>    Write output to <file> instead of stdout.
     -d, --delimiter <arg>  The field delimiter for reading CSV data.
-                           Must be a single character. (default: ,)
+                           Must be a single character (or tab, pipe,
+                           semicolon, space, \\t or \\xHH). (default: ,)
 ";
 
 #[derive(Deserialize)]
@@ -38,16 +74,25 @@ struct Args {
     arg_input: Option<String>,
     flag_out_delimiter: Option<Delimiter>,
     flag_crlf: bool,
+    flag_terminator: Option<Terminator>,
     flag_ascii: bool,
     flag_output: Option<String>,
     flag_delimiter: Option<Delimiter>,
     flag_quote: Delimiter,
     flag_quote_always: bool,
+    flag_quote_style: QuoteStyle,
     flag_escape: Option<Delimiter>,
+    flag_trim: bool,
+    flag_squeeze: bool,
+    flag_no_trim_headers: bool,
 }
 
 pub fn run(argv: &[&str]) -> CliResult<()> {
     let args: Args = util::get_args(USAGE, argv)?;
+    if args.flag_crlf && args.flag_terminator.is_some() {
+        return fail_usage!("--crlf and --terminator cannot be used at the \
+                            same time.");
+    }
 
     let rconfig = Config::new(&args.arg_input)
         .delimiter(args.flag_delimiter)
@@ -61,21 +106,195 @@ pub fn run(argv: &[&str]) -> CliResult<()> {
             .delimiter(Some(Delimiter(b'\x1f')))
             .terminator(csv::Terminator::Any(b'\x1e'));
     }
-    if args.flag_quote_always {
-        wconfig = wconfig.quote_style(csv::QuoteStyle::Always);
+    if let Some(term) = args.flag_terminator {
+        wconfig = wconfig.terminator(term.0);
     }
+    let quote_style =
+        if args.flag_quote_always {
+            QuoteStyle::Always
+        } else {
+            args.flag_quote_style
+        };
+    wconfig = wconfig.quote_style(quote_style.csv());
     if let Some(escape) = args.flag_escape {
         wconfig = wconfig.escape(Some(escape.as_byte())).double_quote(false);
     }
     wconfig = wconfig.quote(args.flag_quote.as_byte());
 
+    // Since nothing is quoted with '--quote-style never', the characters
+    // that delimit fields and records need to be escaped (or refused)
+    // explicitly.
+    let mut special = vec![if args.flag_ascii {
+        b'\x1f'
+    } else {
+        args.flag_out_delimiter.map_or(b',', |d| d.as_byte())
+    }];
+    match args.flag_terminator {
+        Some(Terminator(csv::Terminator::Any(b))) => special.push(b),
+        None if args.flag_ascii => special.push(b'\x1e'),
+        _ => special.extend_from_slice(b"\r\n"),
+    }
+    let escape = args.flag_escape.map(|e| e.as_byte());
+    if let Some(escape) = escape {
+        special.push(escape);
+    }
 
     let mut rdr = rconfig.reader()?;
     let mut wtr = wconfig.writer()?;
     let mut r = csv::ByteRecord::new();
+    let mut cleaned = csv::ByteRecord::new();
+    let mut escaped = csv::ByteRecord::new();
+    let mut modified = 0u64;
+    let mut is_header = true;
     while rdr.read_byte_record(&mut r)? {
-        wtr.write_byte_record(&r)?;
+        let skip = is_header && args.flag_no_trim_headers;
+        is_header = false;
+        if (args.flag_trim || args.flag_squeeze) && !skip {
+            cleaned.clear();
+            for field in r.iter() {
+                let field2 = normalize(
+                    field, args.flag_trim, args.flag_squeeze);
+                if &*field2 != field {
+                    modified += 1;
+                }
+                cleaned.push_field(&field2);
+            }
+            cleaned.set_position(r.position().cloned());
+            mem::swap(&mut r, &mut cleaned);
+        }
+        if quote_style != QuoteStyle::Never {
+            wtr.write_byte_record(&r)?;
+            continue;
+        }
+
+        escaped.clear();
+        for field in r.iter() {
+            if !field.iter().any(|b| special.contains(b)) {
+                escaped.push_field(field);
+                continue;
+            }
+            let escape = match escape {
+                Some(escape) => escape,
+                None => {
+                    let line = r.position().map_or(0, |p| p.line());
+                    return fail_data!(format!(
+                        "The record on line {} has a field containing the \
+                         delimiter or a record terminator, which can't be \
+                         written with '--quote-style never' unless \
+                         --escape is given.", line));
+                }
+            };
+            let mut buf = Vec::with_capacity(field.len() + 1);
+            for &b in field {
+                if special.contains(&b) {
+                    buf.push(escape);
+                }
+                buf.push(b);
+            }
+            escaped.push_field(&buf);
+        }
+        wtr.write_byte_record(&escaped)?;
     }
     wtr.flush()?;
+    if args.flag_trim || args.flag_squeeze {
+        werr!("modified {} cell(s)", modified);
+    }
     Ok(())
 }
+
+/// Strips leading and trailing whitespace from `field` if `trim` is set and
+/// collapses every run of whitespace inside it to a single space if
+/// `squeeze` is set.
+///
+/// Fields that are valid UTF-8 use the Unicode definition of whitespace.
+/// Others only consider ASCII whitespace.
+pub fn normalize(field: &[u8], trim: bool, squeeze: bool) -> Cow<'_, [u8]> {
+    match str::from_utf8(field) {
+        Ok(s) => {
+            let s = if trim { s.trim() } else { s };
+            if !squeeze {
+                return Cow::Borrowed(s.as_bytes());
+            }
+            let mut out = String::with_capacity(s.len());
+            let mut in_space = false;
+            for c in s.chars() {
+                if c.is_whitespace() {
+                    if !in_space {
+                        out.push(' ');
+                    }
+                    in_space = true;
+                } else {
+                    out.push(c);
+                    in_space = false;
+                }
+            }
+            Cow::Owned(out.into_bytes())
+        }
+        Err(_) => {
+            let is_space = |b: &u8| b.is_ascii_whitespace();
+            let mut s = field;
+            if trim {
+                let start = s.iter().position(|b| !is_space(b))
+                             .unwrap_or(s.len());
+                let end = s.iter().rposition(|b| !is_space(b))
+                           .map_or(start, |i| i + 1);
+                s = &s[start..end];
+            }
+            if !squeeze {
+                return Cow::Borrowed(s);
+            }
+            let mut out = Vec::with_capacity(s.len());
+            let mut in_space = false;
+            for &b in s {
+                if is_space(&b) {
+                    if !in_space {
+                        out.push(b' ');
+                    }
+                    in_space = true;
+                } else {
+                    out.push(b);
+                    in_space = false;
+                }
+            }
+            Cow::Owned(out)
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum QuoteStyle {
+    Minimal,
+    NonNumeric,
+    Always,
+    Never,
+}
+
+impl QuoteStyle {
+    pub fn csv(self) -> csv::QuoteStyle {
+        match self {
+            QuoteStyle::Minimal => csv::QuoteStyle::Necessary,
+            QuoteStyle::NonNumeric => csv::QuoteStyle::NonNumeric,
+            QuoteStyle::Always => csv::QuoteStyle::Always,
+            QuoteStyle::Never => csv::QuoteStyle::Never,
+        }
+    }
+}
+
+/// The record terminator given with --terminator.
+#[derive(Clone, Copy, Debug)]
+struct Terminator(csv::Terminator);
+
+impl<'de> Deserialize<'de> for Terminator {
+    fn deserialize<D: Deserializer<'de>>(
+        d: D,
+    ) -> Result<Terminator, D::Error> {
+        let raw = String::deserialize(d)?;
+        if raw.eq_ignore_ascii_case("crlf") {
+            return Ok(Terminator(csv::Terminator::CRLF));
+        }
+        Delimiter::parse(&raw)
+            .map(|d| Terminator(csv::Terminator::Any(d.as_byte())))
+            .map_err(D::Error::custom)
+    }
+}
@@ -0,0 +1,389 @@
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+use std::process::{Command, ExitStatus, Stdio};
+
+use channel;
+use csv;
+use threadpool::ThreadPool;
+
+use {CliError, CliResult};
+use cmd::template::{Template, shell_quote};
+use config::{Config, Delimiter};
+use util;
+
+pub static USAGE: &'static str = "
+Runs a command for every record, e.g.,
+
+    xsv foreach 'curl -s -o {id}.json https://example.com/items/{id}' data.csv
+
+Placeholders of the form '{column}' are replaced by the value of the column
+in the record, and can use the same filters as in 'xsv template'. To write
+literal braces, double them, as in '{{' and '}}'.
+
+By default, the command is run by the shell. Every value is quoted for the
+shell before it is substituted, so values with spaces, quotes or other
+special characters are passed as they are. Placeholders must therefore not
+be quoted in the command, and a command with a placeholder inside single or
+double quotes is an error. With --no-shell, the command is split into words
+at whitespace, the placeholders are substituted into the words, and the
+program is run with the words as its arguments, without a shell. On
+Windows, values can't be quoted reliably for cmd.exe, so commands are always
+run as with --no-shell.
+
+The output of the commands is passed through as it is written, so the
+output of concurrent commands may be interleaved. With --unify, the output
+of every command is captured instead, and written as a new column of the
+records, which are written as CSV in their original order.
+
+Commands that fail are reported on stderr. By default, all records are
+still processed, and it is an error at the end if any command failed. No
+more commands are started after a failure if --halt-on-error is given.
+
+Usage:
+    xsv foreach [options] <command> [<input>]
+    xsv foreach --help
+
+foreach options:
+    -j, --jobs <n>           The number of commands to run concurrently.
+                             [default: 1]
+    --no-shell               Run the program directly instead of with the
+                             shell. This is always the case on Windows.
+    --dry-run                Write the commands instead of running them.
+    --halt-on-error          Stop when a command fails.
+    --unify                  Capture the output of the commands as a new
+                             column.
+    --new-column <name>      The name of the column with --unify.
+                             [default: output]
+
+Common options:
+    -h, --help               Display this message
+    -o, --output <file>      Write output to <file> instead of stdout.
+    -n, --no-headers         When set, the first row will not be
+                             interpreted as headers. Placeholders then
+                             give columns by position, as in '{1}'.
+    -d, --delimiter <arg>    The field delimiter for reading CSV data.
+                             Must be a single character (or tab, pipe,
+                             semicolon, space, \\t or \\xHH). (default: ,)
+";
+
+#[derive(Deserialize)]
+struct Args {
+    arg_command: String,
+    arg_input: Option<String>,
+    flag_jobs: usize,
+    flag_no_shell: bool,
+    flag_dry_run: bool,
+    flag_halt_on_error: bool,
+    flag_unify: bool,
+    flag_new_column: String,
+    flag_output: Option<String>,
+    flag_no_headers: bool,
+    flag_delimiter: Option<Delimiter>,
+}
+
+/// A command that is ready to run.
+enum Job {
+    Shell(String),
+    Program(Vec<String>),
+}
+
+impl Job {
+    fn command(&self) -> Command {
+        match *self {
+            Job::Shell(ref line) => {
+                let mut cmd = Command::new("sh");
+                cmd.arg("-c").arg(line);
+                cmd
+            }
+            Job::Program(ref words) => {
+                let mut cmd = Command::new(&words[0]);
+                cmd.args(&words[1..]);
+                cmd
+            }
+        }
+    }
+
+    fn run(&self, capture: bool) -> io::Result<(ExitStatus, Vec<u8>)> {
+        let mut cmd = self.command();
+        cmd.stdin(Stdio::null());
+        if capture {
+            let output = cmd.stderr(Stdio::inherit()).output()?;
+            Ok((output.status, output.stdout))
+        } else {
+            Ok((cmd.status()?, vec![]))
+        }
+    }
+
+    /// The command as it would be typed into a shell.
+    fn display(&self) -> String {
+        match *self {
+            Job::Shell(ref line) => line.clone(),
+            Job::Program(ref words) => {
+                let words: Vec<String> = words.iter().map(|w| {
+                    let plain = !w.is_empty() && w.chars().all(|c| {
+                        c.is_alphanumeric() || "-_./=:,@%+".contains(c)
+                    });
+                    if plain { w.clone() } else { shell_quote(w) }
+                }).collect();
+                words.join(" ")
+            }
+        }
+    }
+}
+
+/// A command that has finished, along with the record it was run for.
+struct Done {
+    seq: u64,
+    record: csv::ByteRecord,
+    display: String,
+    result: io::Result<(ExitStatus, Vec<u8>)>,
+}
+
+/// Returns the first placeholder of a shell command that is inside single
+/// or double quotes, if there is one.
+///
+/// The value that replaces it would already be quoted, so the quotes
+/// around it would end that quoting instead, and the shell would interpret
+/// the value.
+fn quoted_placeholder(command: &str) -> Option<String> {
+    let mut quote = None;
+    let mut chars = command.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (_, '{') if chars.peek() == Some(&'{') => {
+                chars.next();
+            }
+            (None, '{') => {
+                // Skip the placeholder.
+                for c in chars.by_ref() {
+                    if c == '}' {
+                        break;
+                    }
+                }
+            }
+            (Some(_), '{') => {
+                let mut placeholder = String::from("{");
+                for c in chars.by_ref() {
+                    placeholder.push(c);
+                    if c == '}' {
+                        break;
+                    }
+                }
+                return Some(placeholder);
+            }
+            (None, '\\') | (Some('"'), '\\') => {
+                chars.next();
+            }
+            (None, '\'') | (None, '"') => quote = Some(c),
+            (Some(q), c) if c == q => quote = None,
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Splits a command into words at whitespace outside of placeholders.
+fn split_words(command: &str) -> Vec<String> {
+    let mut words = vec![];
+    let mut word = String::new();
+    let mut in_placeholder = false;
+    let mut chars = command.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if !in_placeholder && chars.peek() == Some(&'{') => {
+                chars.next();
+                word.push_str("{{");
+            }
+            '{' => {
+                in_placeholder = true;
+                word.push(c);
+            }
+            '}' => {
+                in_placeholder = false;
+                word.push(c);
+            }
+            c if c.is_whitespace() && !in_placeholder => {
+                if !word.is_empty() {
+                    words.push(word.clone());
+                    word.clear();
+                }
+            }
+            c => word.push(c),
+        }
+    }
+    if !word.is_empty() {
+        words.push(word);
+    }
+    words
+}
+
+struct Runner {
+    jobs: usize,
+    halt_on_error: bool,
+    in_flight: usize,
+    failures: u64,
+    halted: Option<String>,
+    /// The next record to write with --unify, and the finished records
+    /// that come after it.
+    next: u64,
+    pending: BTreeMap<u64, (csv::ByteRecord, Vec<u8>)>,
+    wtr: Option<csv::Writer<Box<dyn io::Write>>>,
+}
+
+impl Runner {
+    fn finish(&mut self, done: Done) -> CliResult<()> {
+        self.in_flight -= 1;
+        let line = done.record.position().map_or(0, |p| p.line());
+        let output = match done.result {
+            Ok((status, output)) => {
+                if !status.success() {
+                    self.fail(format!(
+                        "the command for line {} failed with {}: {}",
+                        line, status, done.display));
+                }
+                output
+            }
+            Err(err) => {
+                self.fail(format!(
+                    "the command for line {} could not be run ({}): {}",
+                    line, err, done.display));
+                vec![]
+            }
+        };
+        let wtr = match self.wtr {
+            Some(ref mut wtr) => wtr,
+            None => return Ok(()),
+        };
+        self.pending.insert(done.seq, (done.record, output));
+        while let Some((mut record, output)) = self.pending.remove(&self.next) {
+            let mut end = output.len();
+            while end > 0 && (output[end - 1] == b'\n'
+                              || output[end - 1] == b'\r') {
+                end -= 1;
+            }
+            record.push_field(&output[..end]);
+            wtr.write_byte_record(&record)?;
+            self.next += 1;
+        }
+        Ok(())
+    }
+
+    fn fail(&mut self, msg: String) {
+        werr!("{}", msg);
+        self.failures += 1;
+        if self.halt_on_error && self.halted.is_none() {
+            self.halted = Some(msg);
+        }
+    }
+}
+
+pub fn run(argv: &[&str]) -> CliResult<()> {
+    let args: Args = util::get_args(USAGE, argv)?;
+    let rconfig = Config::new(&args.arg_input)
+        .delimiter(args.flag_delimiter)
+        .no_headers(args.flag_no_headers);
+    let mut rdr = rconfig.reader()?;
+    let headers = rdr.byte_headers()?.clone();
+
+    let no_shell = args.flag_no_shell || cfg!(windows);
+    let templates: Vec<Template> = if no_shell {
+        let words = split_words(&args.arg_command);
+        if words.is_empty() {
+            return fail_usage!("The command is empty.");
+        }
+        words.iter().map(|w| Template::parse(w, &headers, rconfig.no_headers))
+             .collect::<Result<_, _>>()
+             .map_err(CliError::Usage)?
+    } else {
+        if let Some(p) = quoted_placeholder(&args.arg_command) {
+            return fail_usage!(format!(
+                "The placeholder '{}' is inside quotes in the command. Values \
+                 are quoted when they are substituted, so placeholders must \
+                 not be quoted.", p));
+        }
+        vec![Template::parse(&args.arg_command, &headers, rconfig.no_headers)
+             .map_err(CliError::Usage)?]
+    };
+
+    let mut record = csv::ByteRecord::new();
+    let mut rendered = String::new();
+    let mut render = |record: &csv::ByteRecord| -> CliResult<Job> {
+        if !no_shell {
+            templates[0].render(record, &mut rendered,
+                                |v| Ok(shell_quote(&v)))?;
+            return Ok(Job::Shell(rendered.clone()));
+        }
+        let mut words = vec![];
+        for t in &templates {
+            t.render(record, &mut rendered, Ok)?;
+            words.push(rendered.clone());
+        }
+        Ok(Job::Program(words))
+    };
+
+    if args.flag_dry_run {
+        let mut out = io::BufWriter::new(
+            Config::new(&args.flag_output).io_writer()?);
+        while rdr.read_byte_record(&mut record)? {
+            writeln!(out, "{}", render(&record)?.display())?;
+        }
+        return Ok(out.flush()?);
+    }
+
+    let mut runner = Runner {
+        jobs: if args.flag_jobs == 0 { 1 } else { args.flag_jobs },
+        halt_on_error: args.flag_halt_on_error,
+        in_flight: 0,
+        failures: 0,
+        halted: None,
+        next: 0,
+        pending: BTreeMap::new(),
+        wtr: None,
+    };
+    if args.flag_unify {
+        let mut wtr = Config::new(&args.flag_output).writer()?;
+        if !rconfig.no_headers {
+            let mut headers = headers.clone();
+            headers.push_field(args.flag_new_column.as_bytes());
+            wtr.write_byte_record(&headers)?;
+        }
+        runner.wtr = Some(wtr);
+    }
+
+    let pool = ThreadPool::new(runner.jobs);
+    let (send, recv) = channel::unbounded();
+    let mut seq = 0;
+    while runner.halted.is_none() && rdr.read_byte_record(&mut record)? {
+        let job = render(&record)?;
+        let (send, record, capture) =
+            (send.clone(), record.clone(), args.flag_unify);
+        pool.execute(move || {
+            let result = job.run(capture);
+            send.send(Done {
+                seq: seq,
+                record: record,
+                display: job.display(),
+                result: result,
+            });
+        });
+        seq += 1;
+        runner.in_flight += 1;
+        while runner.in_flight >= runner.jobs {
+            runner.finish(recv.recv().unwrap())?;
+        }
+    }
+    while runner.in_flight > 0 {
+        runner.finish(recv.recv().unwrap())?;
+    }
+    if let Some(ref mut wtr) = runner.wtr {
+        wtr.flush()?;
+    }
+    if let Some(msg) = runner.halted {
+        return fail!(format!("Stopped because {}", msg));
+    }
+    if runner.failures > 0 {
+        return fail!(format!("{} of {} commands failed.",
+                             runner.failures, seq));
+    }
+    Ok(())
+}
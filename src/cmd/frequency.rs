@@ -1,18 +1,19 @@
-use std::fs;
 use std::io;
+use std::str;
 
 use channel;
 use csv;
-use stats::{Frequencies, merge_all};
+use stats::merge_all;
 use threadpool::ThreadPool;
 
 use CliResult;
-use config::{Config, Delimiter};
-use index::Indexed;
+use config::{Config, DataFile, Delimiter};
+use index::{IndexFile, Indexed};
+use intern::{Counts, Interner};
 use select::{SelectColumns, Selection};
 use util;
 
-static USAGE: &'static str = "
+pub static USAGE: &'static str = "
 Compute a frequency table on CSV data.
 
 The frequency table is formatted as CSV data:
@@ -57,7 +58,10 @@ Common options:
                            column will be 1-based indices instead of header
                            names.
     -d, --delimiter <arg>  The field delimiter for reading CSV data.
-                           Must be a single character. (default: ,)
+                           Must be a single character (or tab, pipe,
+                           semicolon, space, \\t or \\xHH). (default: ,)
+    --auto-index           When the index of the CSV data is stale, rebuild
+                           it instead of reporting an error.
 ";
 
 #[derive(Clone, Deserialize)]
@@ -71,6 +75,7 @@ struct Args {
     flag_output: Option<String>,
     flag_no_headers: bool,
     flag_delimiter: Option<Delimiter>,
+    flag_auto_index: bool,
 }
 
 pub fn run(argv: &[&str]) -> CliResult<()> {
@@ -92,27 +97,27 @@ pub fn run(argv: &[&str]) -> CliResult<()> {
         }
         for (value, count) in args.counts(&ftab).into_iter() {
             let count = count.to_string();
-            let row = vec![&*header, &*value, count.as_bytes()];
+            let row = vec![&*header, value, count.as_bytes()];
             wtr.write_record(row)?;
         }
     }
     Ok(())
 }
 
-type ByteString = Vec<u8>;
 type Headers = csv::ByteRecord;
-type FTable = Frequencies<Vec<u8>>;
-type FTables = Vec<Frequencies<Vec<u8>>>;
+type FTable = Counts;
+type FTables = Vec<Counts>;
 
 impl Args {
     fn rconfig(&self) -> Config {
         Config::new(&self.arg_input)
             .delimiter(self.flag_delimiter)
             .no_headers(self.flag_no_headers)
+            .auto_index(self.flag_auto_index)
             .select(self.flag_select.clone())
     }
 
-    fn counts(&self, ftab: &FTable) -> Vec<(ByteString, u64)> {
+    fn counts<'a>(&self, ftab: &'a FTable) -> Vec<(&'a [u8], u64)> {
         let mut counts = if self.flag_asc {
             ftab.least_frequent()
         } else {
@@ -122,10 +127,10 @@ impl Args {
             counts = counts.into_iter().take(self.flag_limit).collect();
         }
         counts.into_iter().map(|(bs, c)| {
-            if b"" == &**bs {
-                (b"(NULL)"[..].to_vec(), c)
+            if bs.is_empty() {
+                (&b"(NULL)"[..], c)
             } else {
-                (bs.clone(), c)
+                (bs, c)
             }
         }).collect()
     }
@@ -136,7 +141,7 @@ impl Args {
         Ok((headers, self.ftables(&sel, rdr.byte_records())?))
     }
 
-    fn parallel_ftables(&self, idx: &mut Indexed<fs::File, fs::File>)
+    fn parallel_ftables(&self, idx: &mut Indexed<DataFile, IndexFile>)
                        -> CliResult<(Headers, FTables)> {
         let mut rdr = self.rconfig().reader()?;
         let (headers, sel) = self.sel_headers(&mut rdr)?;
@@ -165,20 +170,18 @@ impl Args {
 
     fn ftables<I>(&self, sel: &Selection, it: I) -> CliResult<FTables>
             where I: Iterator<Item=csv::Result<csv::ByteRecord>> {
-        let null = &b""[..].to_vec();
         let nsel = sel.normal();
+        // The interner is shared by the columns, so each distinct value is
+        // copied once, when it is first seen.
+        let mut interner = Interner::new();
         let mut tabs: Vec<_> =
-            (0..nsel.len()).map(|_| Frequencies::new()).collect();
+            (0..nsel.len()).map(|_| Counts::new()).collect();
         for row in it {
             let row = row?;
             for (i, field) in nsel.select(row.into_iter()).enumerate() {
-                let field = trim(field.to_vec());
-                if !field.is_empty() {
-                    tabs[i].add(field);
-                } else {
-                    if !self.flag_no_nulls {
-                        tabs[i].add(null.clone());
-                    }
+                let field = trim(field);
+                if !field.is_empty() || !self.flag_no_nulls {
+                    tabs[i].add(&mut interner, field);
                 }
             }
         }
@@ -197,9 +200,9 @@ impl Args {
     }
 }
 
-fn trim(bs: ByteString) -> ByteString {
-    match String::from_utf8(bs) {
-        Ok(s) => s.trim().as_bytes().to_vec(),
-        Err(bs) => bs.into_bytes(),
+fn trim(bs: &[u8]) -> &[u8] {
+    match str::from_utf8(bs) {
+        Ok(s) => s.trim().as_bytes(),
+        Err(_) => bs,
     }
 }
@@ -0,0 +1,234 @@
+use std::collections::{HashMap, HashSet};
+use std::io;
+
+use csv;
+
+use CliResult;
+use config::Config;
+use json::{Parser, Value};
+use util;
+
+pub static USAGE: &'static str = "
+Converts JSON Lines (one JSON object per line) to CSV. With the --array flag,
+the input is a single JSON array of objects instead.
+
+Nested objects are flattened into columns with dotted names, e.g.,
+{\"address\": {\"city\": \"Paris\"}} results in a column 'address.city'. Arrays
+are written as JSON in a single field by default. With --explode-arrays,
+every element gets its own column, e.g., 'tags.0', 'tags.1', and so on.
+Empty objects (and, with --explode-arrays, empty arrays) have no columns.
+Strings are written without quotes, null as an empty field and all other
+values as JSON.
+
+The header row consists of all keys in the order in which they first appear.
+Since they are only known after reading all of the input, a file is read
+twice, while input from stdin is kept in memory. To stream instead, give the
+columns with --columns. Records that don't have one of these columns get an
+empty field, and keys that aren't one of them are dropped. The number of
+dropped values is written to stderr.
+
+Usage:
+    xsv fromjson [options] [<input>]
+    xsv fromjson --help
+
+fromjson options:
+    --array                The input is a JSON array of objects.
+    --explode-arrays       Write every element of an array in its own column.
+    --columns <list>       A comma separated list of the columns to write.
+
+Common options:
+    -h, --help             Display this message
+    -o, --output <file>    Write output to <file> instead of stdout.
+";
+
+#[derive(Deserialize)]
+struct Args {
+    arg_input: Option<String>,
+    flag_array: bool,
+    flag_explode_arrays: bool,
+    flag_columns: Option<String>,
+    flag_output: Option<String>,
+}
+
+type Row = Vec<(String, Vec<u8>)>;
+
+pub fn run(argv: &[&str]) -> CliResult<()> {
+    let args: Args = util::get_args(USAGE, argv)?;
+    let conf = Config::new(&args.arg_input);
+    let mut wtr = Config::new(&args.flag_output).writer()?;
+
+    if let Some(ref list) = args.flag_columns {
+        let columns: Vec<String> =
+            list.split(',').map(|s| s.to_owned()).collect();
+        let pos = positions(&columns);
+        wtr.write_record(&columns)?;
+        let mut dropped = 0u64;
+        let mut dropped_keys = HashSet::new();
+        args.for_each_row(&conf, |row| {
+            for &(ref key, _) in &row {
+                if !pos.contains_key(key) {
+                    dropped += 1;
+                    dropped_keys.insert(key.clone());
+                }
+            }
+            write_row(&mut wtr, &pos, row)
+        })?;
+        wtr.flush()?;
+        if dropped > 0 {
+            werr!("warning: dropped {} value(s) of {} key(s) not in \
+                   --columns", dropped, dropped_keys.len());
+        }
+        return Ok(());
+    }
+
+    let mut columns: Vec<String> = vec![];
+    let mut pos = HashMap::new();
+    let mut add_columns = |row: &Row| {
+        for &(ref key, _) in row {
+            if !pos.contains_key(key) {
+                pos.insert(key.clone(), columns.len());
+                columns.push(key.clone());
+            }
+        }
+    };
+    if conf.is_std() {
+        let mut rows = vec![];
+        args.for_each_row(&conf, |row| {
+            add_columns(&row);
+            rows.push(row);
+            Ok(())
+        })?;
+        let pos = positions(&columns);
+        write_header(&mut wtr, &columns)?;
+        for row in rows {
+            write_row(&mut wtr, &pos, row)?;
+        }
+    } else {
+        args.for_each_row(&conf, |row| {
+            add_columns(&row);
+            Ok(())
+        })?;
+        let pos = positions(&columns);
+        write_header(&mut wtr, &columns)?;
+        args.for_each_row(&conf, |row| write_row(&mut wtr, &pos, row))?;
+    }
+    Ok(wtr.flush()?)
+}
+
+fn positions(columns: &[String]) -> HashMap<String, usize> {
+    columns.iter().enumerate().map(|(i, c)| (c.clone(), i)).collect()
+}
+
+/// Writes the header row, unless there are no columns at all (because the
+/// input has no non-empty objects).
+fn write_header<W: io::Write>(
+    wtr: &mut csv::Writer<W>,
+    columns: &[String],
+) -> CliResult<()> {
+    if !columns.is_empty() {
+        wtr.write_record(columns)?;
+    }
+    Ok(())
+}
+
+fn write_row<W: io::Write>(
+    wtr: &mut csv::Writer<W>,
+    pos: &HashMap<String, usize>,
+    row: Row,
+) -> CliResult<()> {
+    if pos.is_empty() {
+        return Ok(());
+    }
+    let mut fields = vec![vec![]; pos.len()];
+    for (key, value) in row {
+        if let Some(&i) = pos.get(&key) {
+            fields[i] = value;
+        }
+    }
+    wtr.write_record(&fields)?;
+    Ok(())
+}
+
+impl Args {
+    /// Calls `f` with the flattened keys and values of every object in the
+    /// input.
+    fn for_each_row<F>(&self, conf: &Config, mut f: F) -> CliResult<()>
+            where F: FnMut(Row) -> CliResult<()> {
+        let mut parser = Parser::new(io::BufReader::new(conf.io_reader()?));
+        if self.flag_array && !parser.eat(b'[')? {
+            return Err(parser.error("expected a JSON array").into());
+        }
+        let mut first = true;
+        loop {
+            let value = if self.flag_array {
+                if parser.eat(b']')? {
+                    break;
+                }
+                if !first && !parser.eat(b',')? {
+                    return Err(parser.error("expected ',' or ']'").into());
+                }
+                match parser.next_value()? {
+                    None => {
+                        return Err(parser.error("unexpected end of input")
+                                         .into());
+                    }
+                    Some(value) => value,
+                }
+            } else {
+                match parser.next_value()? {
+                    None => break,
+                    Some(value) => value,
+                }
+            };
+            first = false;
+            match value {
+                Value::Object(_) => {}
+                Value::Array(_) if !self.flag_array => {
+                    return Err(parser.error(
+                        "expected an object (use --array to read a JSON \
+                         array)").into());
+                }
+                _ => return Err(parser.error("expected an object").into()),
+            }
+            let mut row = vec![];
+            self.flatten("", &value, &mut row);
+            f(row)?;
+        }
+        if self.flag_array && parser.peek()?.is_some() {
+            return Err(parser.error("unexpected data after the array")
+                             .into());
+        }
+        Ok(())
+    }
+
+    fn flatten(&self, prefix: &str, value: &Value, row: &mut Row) {
+        let key = |k: &str| {
+            if prefix.is_empty() {
+                k.to_owned()
+            } else {
+                format!("{}.{}", prefix, k)
+            }
+        };
+        match *value {
+            Value::Object(ref members) => {
+                for &(ref k, ref v) in members {
+                    self.flatten(&key(k), v, row);
+                }
+            }
+            Value::Array(ref vals) if self.flag_explode_arrays => {
+                for (i, v) in vals.iter().enumerate() {
+                    self.flatten(&key(&i.to_string()), v, row);
+                }
+            }
+            Value::Null => row.push((prefix.to_owned(), vec![])),
+            Value::String(ref s) => {
+                row.push((prefix.to_owned(), s.clone().into_bytes()));
+            }
+            ref v => {
+                let mut buf = vec![];
+                v.write(&mut buf).unwrap();
+                row.push((prefix.to_owned(), buf));
+            }
+        }
+    }
+}
@@ -0,0 +1,318 @@
+use std::collections::HashMap;
+use std::str;
+
+use csv;
+
+use {CliError, CliResult};
+use config::{Config, Delimiter};
+use select::SelectColumns;
+use util;
+
+pub static USAGE: &'static str = "
+Groups records by the values of the key columns and computes aggregates of
+other columns for each group, e.g.,
+
+    xsv groupby region --agg 'sum(amount),mean(latency),max(ts),count()'
+
+The output has the key columns, followed by one column per aggregate named
+like 'amount_sum'. Groups are written in sorted order of their keys.
+
+The aggregates are:
+
+    count()      The number of records.
+    count(col)   The number of non-empty values.
+    sum(col)     The sum of the numbers.
+    mean(col)    The arithmetic mean of the numbers.
+    min(col)     The smallest value, compared as numbers if all values are
+                 numbers and as strings otherwise.
+    max(col)     The largest value, like min.
+    first(col)   The first non-empty value.
+    last(col)    The last non-empty value.
+
+Empty values are ignored by all aggregates. Values that aren't numbers are
+skipped by sum and mean. With --report-skipped, the number of skipped values
+of each aggregate is written to stderr.
+
+The data is read in a single pass, and memory is proportional to the number
+of groups.
+
+Usage:
+    xsv groupby [options] <keys> --agg <aggregates> [<input>]
+    xsv groupby --help
+
+groupby options:
+    -a, --agg <aggregates>  A comma separated list of aggregates.
+    --report-skipped        Report the number of values that were skipped
+                            because they aren't numbers.
+
+Common options:
+    -h, --help              Display this message
+    -o, --output <file>     Write output to <file> instead of stdout.
+    -n, --no-headers        When set, the first row will not be interpreted
+                            as headers. Columns are then given by position.
+    -d, --delimiter <arg>   The field delimiter for reading CSV data.
+                            Must be a single character (or tab, pipe,
+                            semicolon, space, \\t or \\xHH). (default: ,)
+";
+
+#[derive(Deserialize)]
+struct Args {
+    arg_keys: SelectColumns,
+    arg_input: Option<String>,
+    flag_agg: String,
+    flag_report_skipped: bool,
+    flag_output: Option<String>,
+    flag_no_headers: bool,
+    flag_delimiter: Option<Delimiter>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Func {
+    Count,
+    Sum,
+    Mean,
+    Min,
+    Max,
+    First,
+    Last,
+}
+
+impl Func {
+    fn from_name(name: &str) -> Option<Func> {
+        Some(match name {
+            "count" => Func::Count,
+            "sum" => Func::Sum,
+            "mean" => Func::Mean,
+            "min" => Func::Min,
+            "max" => Func::Max,
+            "first" => Func::First,
+            "last" => Func::Last,
+            _ => return None,
+        })
+    }
+}
+
+struct Aggregate {
+    func: Func,
+    /// The column that is aggregated, which is `None` for `count()`.
+    column: Option<usize>,
+    name: Vec<u8>,
+}
+
+/// The state of an aggregate for one group.
+#[derive(Clone, Default)]
+struct Acc {
+    count: u64,
+    sum: f64,
+    /// The number of values that were used for sum or mean.
+    numbers: u64,
+    skipped: u64,
+    min: Option<f64>,
+    max: Option<f64>,
+    /// Whether all values are numbers, so min and max are numeric.
+    all_numbers: bool,
+    min_str: Option<Vec<u8>>,
+    max_str: Option<Vec<u8>>,
+    first: Option<Vec<u8>>,
+    last: Option<Vec<u8>>,
+}
+
+impl Acc {
+    fn new() -> Acc {
+        Acc { all_numbers: true, ..Acc::default() }
+    }
+
+    fn add(&mut self, func: Func, value: &[u8]) {
+        self.count += 1;
+        let number = str::from_utf8(value).ok()
+            .and_then(|s| s.trim().parse::<f64>().ok())
+            .filter(|n| n.is_finite());
+        match func {
+            Func::Count => {}
+            Func::Sum | Func::Mean => match number {
+                Some(n) => {
+                    self.sum += n;
+                    self.numbers += 1;
+                }
+                None => self.skipped += 1,
+            },
+            Func::Min | Func::Max => {
+                match number {
+                    Some(n) => {
+                        self.min = Some(self.min.map_or(n, |m| m.min(n)));
+                        self.max = Some(self.max.map_or(n, |m| m.max(n)));
+                    }
+                    None => self.all_numbers = false,
+                }
+                if self.min_str.as_ref().map_or(true, |m| value < &**m) {
+                    self.min_str = Some(value.to_vec());
+                }
+                if self.max_str.as_ref().map_or(true, |m| value > &**m) {
+                    self.max_str = Some(value.to_vec());
+                }
+            }
+            Func::First => {
+                if self.first.is_none() {
+                    self.first = Some(value.to_vec());
+                }
+            }
+            Func::Last => self.last = Some(value.to_vec()),
+        }
+    }
+
+    fn result(&self, func: Func) -> Vec<u8> {
+        let s = match func {
+            Func::Count => self.count.to_string(),
+            Func::Sum if self.numbers > 0 => self.sum.to_string(),
+            Func::Mean if self.numbers > 0 => {
+                (self.sum / self.numbers as f64).to_string()
+            }
+            Func::Min if self.all_numbers => {
+                self.min.map(|n| n.to_string()).unwrap_or_default()
+            }
+            Func::Max if self.all_numbers => {
+                self.max.map(|n| n.to_string()).unwrap_or_default()
+            }
+            Func::Min => return self.min_str.clone().unwrap_or_default(),
+            Func::Max => return self.max_str.clone().unwrap_or_default(),
+            Func::First => return self.first.clone().unwrap_or_default(),
+            Func::Last => return self.last.clone().unwrap_or_default(),
+            Func::Sum | Func::Mean => String::new(),
+        };
+        s.into_bytes()
+    }
+}
+
+fn parse_aggregates(
+    spec: &str,
+    headers: &csv::ByteRecord,
+    use_names: bool,
+) -> CliResult<Vec<Aggregate>> {
+    let mut aggs = vec![];
+    let mut rest = spec.trim();
+    while !rest.is_empty() {
+        let (open, close) = match (rest.find('('), rest.find(')')) {
+            (Some(open), Some(close)) if open < close => (open, close),
+            _ => {
+                return fail_usage!(format!(
+                    "Could not parse the aggregate '{}'. Aggregates look \
+                     like 'sum(amount)'.", rest));
+            }
+        };
+        let name = rest[..open].trim();
+        let func = match Func::from_name(name) {
+            Some(func) => func,
+            None => return fail_usage!(format!(
+                "Unknown aggregate '{}'.", name)),
+        };
+        let arg = rest[open + 1..close].trim();
+        let column = if arg.is_empty() {
+            if func != Func::Count {
+                return fail_usage!(format!(
+                    "The aggregate {}() needs a column.", name));
+            }
+            None
+        } else {
+            let sel = SelectColumns::parse(arg)
+                .map_err(CliError::Usage)?
+                .selection(headers, use_names)?;
+            if sel.len() != 1 {
+                return fail_usage!(format!(
+                    "The aggregate {}({}) must select exactly one column.",
+                    name, arg));
+            }
+            Some(sel[0])
+        };
+        let agg_name = match column {
+            None => name.as_bytes().to_vec(),
+            Some(i) => {
+                let mut n = if use_names {
+                    headers[i].to_vec()
+                } else {
+                    (i + 1).to_string().into_bytes()
+                };
+                n.push(b'_');
+                n.extend_from_slice(name.as_bytes());
+                n
+            }
+        };
+        aggs.push(Aggregate { func: func, column: column, name: agg_name });
+        rest = rest[close + 1..].trim_start();
+        if rest.starts_with(',') {
+            rest = rest[1..].trim_start();
+        } else if !rest.is_empty() {
+            return fail_usage!(format!(
+                "Expected a comma between aggregates, found '{}'.", rest));
+        }
+    }
+    if aggs.is_empty() {
+        return fail_usage!("No aggregates were given with --agg.");
+    }
+    Ok(aggs)
+}
+
+pub fn run(argv: &[&str]) -> CliResult<()> {
+    let args: Args = util::get_args(USAGE, argv)?;
+    let rconfig = Config::new(&args.arg_input)
+        .delimiter(args.flag_delimiter)
+        .no_headers(args.flag_no_headers)
+        .select(args.arg_keys);
+
+    let mut rdr = rconfig.reader()?;
+    let headers = rdr.byte_headers()?.clone();
+    let sel = rconfig.selection(&headers)?;
+    let aggs = parse_aggregates(&args.flag_agg, &headers,
+                                !rconfig.no_headers)?;
+
+    let mut groups: HashMap<Vec<Vec<u8>>, Vec<Acc>> = HashMap::new();
+    let mut record = csv::ByteRecord::new();
+    while rdr.read_byte_record(&mut record)? {
+        let key: Vec<Vec<u8>> = sel.select(&record).map(|f| f.to_vec())
+                                   .collect();
+        let accs = groups.entry(key)
+                         .or_insert_with(|| vec![Acc::new(); aggs.len()]);
+        for (agg, acc) in aggs.iter().zip(accs.iter_mut()) {
+            match agg.column {
+                None => acc.count += 1,
+                Some(i) => {
+                    let value = record.get(i).unwrap_or(b"");
+                    if !value.is_empty() {
+                        acc.add(agg.func, value);
+                    }
+                }
+            }
+        }
+    }
+
+    let mut wtr = Config::new(&args.flag_output).writer()?;
+    let mut out = csv::ByteRecord::new();
+    if !rconfig.no_headers {
+        out.extend(sel.select(&headers));
+        for agg in &aggs {
+            out.push_field(&agg.name);
+        }
+        wtr.write_byte_record(&out)?;
+    }
+    let mut groups: Vec<_> = groups.into_iter().collect();
+    groups.sort_by(|a, b| a.0.cmp(&b.0));
+    let mut skipped = vec![0u64; aggs.len()];
+    for (key, accs) in groups {
+        out.clear();
+        out.extend(&key);
+        for (i, (agg, acc)) in aggs.iter().zip(&accs).enumerate() {
+            out.push_field(&acc.result(agg.func));
+            skipped[i] += acc.skipped;
+        }
+        wtr.write_byte_record(&out)?;
+    }
+    wtr.flush()?;
+    if args.flag_report_skipped {
+        for (agg, n) in aggs.iter().zip(&skipped) {
+            if agg.func == Func::Sum || agg.func == Func::Mean {
+                werr!("{}: {} value(s) skipped",
+                      String::from_utf8_lossy(&agg.name), n);
+            }
+        }
+    }
+    Ok(())
+}
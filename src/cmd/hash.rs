@@ -0,0 +1,133 @@
+use std::collections::HashSet;
+
+use csv;
+
+use CliResult;
+use config::{Config, Delimiter};
+use digest;
+use select::SelectColumns;
+use util;
+
+pub static USAGE: &'static str = "
+Appends a column with a digest of the selected fields of every record, e.g.,
+to detect changed records or to make keys for records without one.
+
+The digest is computed over the values of the fields after CSV parsing, so
+the same data hashes the same regardless of quoting or the delimiter. Each
+field is written as its length, a colon, its value and a comma before it is
+hashed, so that records like 'a,bc' and 'ab,c' have different digests. The
+digest is written in lowercase hexadecimal.
+
+The algorithm is one of 'xxh3' (the 64 bit XXH3, which is fast but not
+cryptographic), 'md5', 'sha1' or 'sha256'.
+
+With --uniq, records whose digest was already seen are removed instead, so
+only the first record with each digest is written, as with 'xsv dedup'. The
+number of removed records is written to stderr.
+
+Usage:
+    xsv hash [options] [<input>]
+    xsv hash --help
+
+hash options:
+    -s, --select <arg>     The fields to hash. All fields are hashed unless
+                           this is given. See 'xsv select -h' for the full
+                           syntax.
+    --algorithm <name>     The digest algorithm. [default: xxh3]
+    --new-column <name>    The name of the new column. [default: hash]
+    --uniq                 Remove records whose digest was already seen.
+
+Common options:
+    -h, --help             Display this message
+    -o, --output <file>    Write output to <file> instead of stdout.
+    -n, --no-headers       When set, the first row will not be interpreted
+                           as headers.
+    -d, --delimiter <arg>  The field delimiter for reading CSV data.
+                           Must be a single character (or tab, pipe,
+                           semicolon, space, \\t or \\xHH). (default: ,)
+";
+
+#[derive(Deserialize)]
+struct Args {
+    arg_input: Option<String>,
+    flag_select: SelectColumns,
+    flag_algorithm: Algorithm,
+    flag_new_column: String,
+    flag_uniq: bool,
+    flag_output: Option<String>,
+    flag_no_headers: bool,
+    flag_delimiter: Option<Delimiter>,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+enum Algorithm {
+    Xxh3,
+    Md5,
+    Sha1,
+    Sha256,
+}
+
+impl Algorithm {
+    fn digest(self, data: &[u8]) -> Vec<u8> {
+        match self {
+            Algorithm::Xxh3 => digest::xxh3_64(data).to_be_bytes().to_vec(),
+            Algorithm::Md5 => digest::md5(data),
+            Algorithm::Sha1 => digest::sha1(data),
+            Algorithm::Sha256 => digest::sha256(data),
+        }
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+pub fn run(argv: &[&str]) -> CliResult<()> {
+    let args: Args = util::get_args(USAGE, argv)?;
+    let rconfig = Config::new(&args.arg_input)
+        .delimiter(args.flag_delimiter)
+        .no_headers(args.flag_no_headers)
+        .select(args.flag_select);
+
+    let mut rdr = rconfig.reader()?;
+    let mut wtr = Config::new(&args.flag_output).writer()?;
+    let mut headers = rdr.byte_headers()?.clone();
+    let sel = rconfig.selection(&headers)?;
+    if !rconfig.no_headers {
+        if !args.flag_uniq {
+            headers.push_field(args.flag_new_column.as_bytes());
+        }
+        wtr.write_byte_record(&headers)?;
+    }
+
+    let mut seen = HashSet::new();
+    let mut removed = 0u64;
+    let mut record = csv::ByteRecord::new();
+    let mut data = vec![];
+    while rdr.read_byte_record(&mut record)? {
+        data.clear();
+        for field in sel.select(&record) {
+            data.extend_from_slice(field.len().to_string().as_bytes());
+            data.push(b':');
+            data.extend_from_slice(field);
+            data.push(b',');
+        }
+        let digest = args.flag_algorithm.digest(&data);
+        if args.flag_uniq {
+            if seen.insert(digest) {
+                wtr.write_byte_record(&record)?;
+            } else {
+                removed += 1;
+            }
+        } else {
+            record.push_field(hex(&digest).as_bytes());
+            wtr.write_byte_record(&record)?;
+        }
+    }
+    wtr.flush()?;
+    if args.flag_uniq {
+        werr!("removed {} duplicate record(s)", removed);
+    }
+    Ok(())
+}
@@ -1,12 +1,14 @@
-use std::io;
+use std::io::{self, Write};
 
+use csv;
 use tabwriter::TabWriter;
 
-use CliResult;
-use config::Delimiter;
+use {CliError, CliResult};
+use config::{Config, Delimiter};
+use json;
 use util;
 
-static USAGE: &'static str = "
+pub static USAGE: &'static str = "
 Prints the fields of the first row in the CSV data.
 
 These names can be used in commands like 'select' to refer to columns in the
@@ -24,11 +26,33 @@ headers options:
                            input is given.
     --intersect            Shows the intersection of all headers in all of
                            the inputs given.
+    --check                Instead of listing the header names, report
+                           problems with them: duplicate names (with their
+                           positions), empty names, names with leading or
+                           trailing whitespace and names containing the
+                           delimiter. If any problem is found, this command
+                           exits with a non-zero status.
+    --diff                 Compare the headers of two or more inputs. This
+                           prints a table showing which inputs contain
+                           each column, followed by notes on names that
+                           differ only by case or whitespace and on common
+                           columns that appear in a different order. If the
+                           headers are not identical, this command exits
+                           with a non-zero status.
+    --ignore-case          When used with --diff, compare header names case
+                           insensitively.
+    --json                 Print the headers as a JSON array of objects
+                           like {\"index\": 1, \"name\": \"id\"}. The index is
+                           omitted with --just-names. When more than one
+                           input is given (and --intersect isn't set), this
+                           prints an object mapping each input to its
+                           array of headers.
 
 Common options:
     -h, --help             Display this message
     -d, --delimiter <arg>  The field delimiter for reading CSV data.
-                           Must be a single character. (default: ,)
+                           Must be a single character (or tab, pipe,
+                           semicolon, space, \\t or \\xHH). (default: ,)
 ";
 
 #[derive(Deserialize)]
@@ -36,6 +60,10 @@ struct Args {
     arg_input: Vec<String>,
     flag_just_names: bool,
     flag_intersect: bool,
+    flag_check: bool,
+    flag_diff: bool,
+    flag_ignore_case: bool,
+    flag_json: bool,
     flag_delimiter: Option<Delimiter>,
 }
 
@@ -44,6 +72,21 @@ pub fn run(argv: &[&str]) -> CliResult<()> {
     let configs = util::many_configs(
         &*args.arg_input, args.flag_delimiter, true)?;
 
+    if args.flag_check {
+        return check(configs);
+    }
+    if args.flag_diff {
+        return diff(configs, args.flag_ignore_case);
+    }
+    if args.flag_json && !args.flag_intersect && configs.len() > 1 {
+        let mut all = vec![];
+        for conf in configs.into_iter() {
+            let mut rdr = conf.reader()?;
+            let headers = rdr.byte_headers()?.iter().map(|h| h.to_vec());
+            all.push((conf.display_path(), headers.collect()));
+        }
+        return args.write_json_map(all);
+    }
     let num_inputs = configs.len();
     let mut headers: Vec<Vec<u8>> = vec![];
     for conf in configs.into_iter() {
@@ -57,6 +100,12 @@ pub fn run(argv: &[&str]) -> CliResult<()> {
         }
     }
 
+    if args.flag_json {
+        let mut wtr = io::stdout();
+        args.write_json_array(&mut wtr, &headers, "")?;
+        writeln!(&mut wtr)?;
+        return Ok(wtr.flush()?);
+    }
     let mut wtr: Box<io::Write> =
         if args.flag_just_names {
             Box::new(io::stdout())
@@ -73,3 +122,189 @@ pub fn run(argv: &[&str]) -> CliResult<()> {
     wtr.flush()?;
     Ok(())
 }
+
+impl Args {
+    fn write_json_map(&self, all: Vec<(String, Vec<Vec<u8>>)>) -> CliResult<()> {
+        let mut wtr = io::stdout();
+        writeln!(&mut wtr, "{{")?;
+        for (i, (path, headers)) in all.into_iter().enumerate() {
+            if i > 0 {
+                writeln!(&mut wtr, ",")?;
+            }
+            write!(&mut wtr, "  ")?;
+            json::write_str(&mut wtr, path.as_bytes())?;
+            write!(&mut wtr, ": ")?;
+            self.write_json_array(&mut wtr, &headers, "  ")?;
+        }
+        writeln!(&mut wtr, "\n}}")?;
+        Ok(wtr.flush()?)
+    }
+
+    fn write_json_array<W: io::Write>(
+        &self,
+        wtr: &mut W,
+        headers: &[Vec<u8>],
+        indent: &str,
+    ) -> io::Result<()> {
+        if headers.is_empty() {
+            return write!(wtr, "[]");
+        }
+        write!(wtr, "[")?;
+        for (i, header) in headers.iter().enumerate() {
+            if i > 0 {
+                write!(wtr, ",")?;
+            }
+            write!(wtr, "\n{}  {{", indent)?;
+            if !self.flag_just_names {
+                write!(wtr, "\"index\": {}, ", i + 1)?;
+            }
+            write!(wtr, "\"name\": ")?;
+            json::write_str(wtr, header)?;
+            write!(wtr, "}}")?;
+        }
+        write!(wtr, "\n{}]", indent)
+    }
+}
+
+fn check(configs: Vec<Config>) -> CliResult<()> {
+    let num_inputs = configs.len();
+    let mut problems = vec![];
+    for conf in configs.into_iter() {
+        let mut rdr = conf.reader()?;
+        let headers = rdr.byte_headers()?.clone();
+        let prefix = if num_inputs == 1 {
+            "".to_owned()
+        } else {
+            format!("{}: ", conf.display_path())
+        };
+        for (name, positions) in duplicates(&headers) {
+            let positions: Vec<String> =
+                positions.iter().map(|i| (i + 1).to_string()).collect();
+            problems.push(format!(
+                "{}{}: duplicate header name '{}'",
+                prefix, positions.join(","), String::from_utf8_lossy(name)));
+        }
+        for (i, name) in headers.iter().enumerate() {
+            let lossy = String::from_utf8_lossy(name);
+            if name.is_empty() {
+                problems.push(format!("{}{}: empty header name", prefix, i+1));
+                continue;
+            }
+            if lossy.trim() != lossy {
+                problems.push(format!(
+                    "{}{}: header name '{}' has leading or trailing \
+                     whitespace", prefix, i+1, lossy));
+            }
+            if name.contains(&conf.get_delimiter()) {
+                problems.push(format!(
+                    "{}{}: header name '{}' contains the delimiter",
+                    prefix, i+1, lossy));
+            }
+        }
+    }
+    for problem in &problems {
+        wout!("{}", problem);
+    }
+    if problems.is_empty() {
+        Ok(())
+    } else {
+        Err(CliError::Data(format!(
+            "Found {} problem(s) with the header names.", problems.len())))
+    }
+}
+
+/// Returns every header name that appears more than once, along with all of
+/// its (0-based) positions, in order of first appearance.
+fn duplicates(headers: &csv::ByteRecord) -> Vec<(&[u8], Vec<usize>)> {
+    let mut names: Vec<(&[u8], Vec<usize>)> = vec![];
+    for (i, name) in headers.iter().enumerate() {
+        match names.iter_mut().find(|&&mut (n, _)| n == name) {
+            Some(&mut (_, ref mut positions)) => positions.push(i),
+            None => names.push((name, vec![i])),
+        }
+    }
+    names.into_iter().filter(|&(_, ref positions)| positions.len() > 1).collect()
+}
+
+fn diff(configs: Vec<Config>, ignore_case: bool) -> CliResult<()> {
+    if configs.len() < 2 {
+        return Err(CliError::Usage(
+            "--diff requires at least two inputs.".to_owned()));
+    }
+    let key = |name: &[u8]| -> String {
+        let name = String::from_utf8_lossy(name).into_owned();
+        if ignore_case { name.to_lowercase() } else { name }
+    };
+
+    let mut paths = vec![];
+    // The header keys of each input, in order.
+    let mut all_keys: Vec<Vec<String>> = vec![];
+    // Every distinct key along with the name it was first seen as, in
+    // order of first appearance.
+    let mut columns: Vec<(String, String)> = vec![];
+    for conf in configs.into_iter() {
+        let mut rdr = conf.reader()?;
+        let mut keys = vec![];
+        for name in rdr.byte_headers()?.iter() {
+            let k = key(name);
+            if !columns.iter().any(|&(ref c, _)| *c == k) {
+                let name = String::from_utf8_lossy(name).into_owned();
+                columns.push((k.clone(), name));
+            }
+            keys.push(k);
+        }
+        paths.push(conf.display_path());
+        all_keys.push(keys);
+    }
+
+    let mut identical = true;
+    let mut wtr = TabWriter::new(io::stdout());
+    write!(&mut wtr, "name")?;
+    for path in &paths {
+        write!(&mut wtr, "\t{}", path)?;
+    }
+    writeln!(&mut wtr)?;
+    for &(ref k, ref name) in &columns {
+        write!(&mut wtr, "{}", name)?;
+        for keys in &all_keys {
+            if keys.contains(k) {
+                write!(&mut wtr, "\t\u{2713}")?;
+            } else {
+                identical = false;
+                write!(&mut wtr, "\t\u{2013}")?;
+            }
+        }
+        writeln!(&mut wtr)?;
+    }
+    wtr.flush()?;
+
+    let normalize = |name: &str| name.trim().to_lowercase();
+    for (i, &(ref k1, ref name1)) in columns.iter().enumerate() {
+        for &(ref k2, ref name2) in &columns[i+1..] {
+            if k1 != k2 && normalize(name1) == normalize(name2) {
+                wout!("'{}' and '{}' differ only by case or whitespace",
+                      name1, name2);
+            }
+        }
+    }
+
+    let common = |keys: &Vec<String>| -> Vec<String> {
+        keys.iter()
+            .filter(|k| all_keys.iter().all(|keys| keys.contains(k)))
+            .cloned()
+            .collect()
+    };
+    let expected_order = common(&all_keys[0]);
+    for (path, keys) in paths.iter().zip(&all_keys).skip(1) {
+        if common(keys) != expected_order {
+            identical = false;
+            wout!("common columns in {} are not in the same order as in {}",
+                  path, paths[0]);
+        }
+    }
+    if identical {
+        Ok(())
+    } else {
+        fail_data!("The headers are not identical.")
+    }
+}
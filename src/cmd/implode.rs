@@ -0,0 +1,177 @@
+use std::collections::HashMap;
+
+use csv;
+
+use CliResult;
+use config::{Config, Delimiter};
+use select::SelectColumns;
+use util;
+
+pub static USAGE: &'static str = "
+Merges records that only differ in one column into a single record, in which
+the values of that column are joined with a separator. This is the inverse
+of 'xsv explode'.
+
+For example, imploding the 'tags' column of
+
+    id,tags
+    1,red
+    1,blue
+
+with ';' results in
+
+    id,tags
+    1,red;blue
+
+Records are merged if they are equal in all other columns, or in the
+columns selected with the --select flag. In the latter case, the other
+columns of a merged record are taken from the first record of its group.
+
+By default, records don't have to be adjacent to be merged and every group
+is kept in memory until all of the CSV data has been read. The merged
+records are written in the order in which their groups first appear. If the
+CSV data is sorted or grouped by the key columns, the --sorted flag makes
+this command only merge adjacent records and use memory proportional to the
+largest group instead.
+
+Usage:
+    xsv implode [options] <column> <separator> [<input>]
+    xsv implode --help
+
+implode options:
+    -s, --select <arg>     Select the key columns that merged records must
+                           have in common. See 'xsv select -h' for the full
+                           syntax. By default, these are all columns but
+                           <column>.
+    --sorted               Only merge adjacent records.
+    -u, --unique           Only keep the first of several equal values within
+                           a merged record.
+
+Common options:
+    -h, --help             Display this message
+    -o, --output <file>    Write output to <file> instead of stdout.
+    -n, --no-headers       When set, the first row will not be interpreted
+                           as headers. (i.e., It is merged like any other
+                           record.)
+    -d, --delimiter <arg>  The field delimiter for reading CSV data.
+                           Must be a single character (or tab, pipe,
+                           semicolon, space, \\t or \\xHH). (default: ,)
+";
+
+#[derive(Deserialize)]
+struct Args {
+    arg_column: SelectColumns,
+    arg_separator: String,
+    arg_input: Option<String>,
+    flag_select: Option<SelectColumns>,
+    flag_sorted: bool,
+    flag_unique: bool,
+    flag_output: Option<String>,
+    flag_no_headers: bool,
+    flag_delimiter: Option<Delimiter>,
+}
+
+/// The first record of a group along with the values of the imploded column
+/// of all records in the group.
+struct Group {
+    first: csv::ByteRecord,
+    values: Vec<Vec<u8>>,
+}
+
+pub fn run(argv: &[&str]) -> CliResult<()> {
+    let args: Args = util::get_args(USAGE, argv)?;
+    let rconfig = Config::new(&args.arg_input)
+        .delimiter(args.flag_delimiter)
+        .no_headers(args.flag_no_headers);
+
+    let mut rdr = rconfig.reader()?;
+    let mut wtr = Config::new(&args.flag_output).writer()?;
+    let headers = rdr.byte_headers()?.clone();
+    let column = {
+        let sel = args.arg_column.selection(&headers, !rconfig.no_headers)?;
+        if sel.len() != 1 {
+            return fail_usage!("<column> must select exactly one column.");
+        }
+        sel[0]
+    };
+    let key_sel = match args.flag_select {
+        Some(ref sel) => Some(sel.selection(&headers, !rconfig.no_headers)?),
+        None => None,
+    };
+    let key = |record: &csv::ByteRecord| -> Vec<Vec<u8>> {
+        match key_sel {
+            Some(ref sel) => sel.select(record).map(|f| f.to_vec()).collect(),
+            None => {
+                record.iter()
+                    .enumerate()
+                    .filter(|&(i, _)| i != column)
+                    .map(|(_, f)| f.to_vec())
+                    .collect()
+            }
+        }
+    };
+    if !rconfig.no_headers {
+        wtr.write_record(&headers)?;
+    }
+
+    let mut record = csv::ByteRecord::new();
+    if args.flag_sorted {
+        let mut current: Option<(Vec<Vec<u8>>, Group)> = None;
+        while rdr.read_byte_record(&mut record)? {
+            let k = key(&record);
+            let value = record.get(column).unwrap_or(b"").to_vec();
+            if let Some((ref ck, ref mut group)) = current {
+                if *ck == k {
+                    args.add(group, value);
+                    continue;
+                }
+                args.write_group(&mut wtr, column, group)?;
+            }
+            let group = Group { first: record.clone(), values: vec![value] };
+            current = Some((k, group));
+        }
+        if let Some((_, ref group)) = current {
+            args.write_group(&mut wtr, column, group)?;
+        }
+    } else {
+        let mut groups: Vec<Group> = vec![];
+        let mut index: HashMap<Vec<Vec<u8>>, usize> = HashMap::new();
+        while rdr.read_byte_record(&mut record)? {
+            let value = record.get(column).unwrap_or(b"").to_vec();
+            let k = key(&record);
+            if let Some(&i) = index.get(&k) {
+                args.add(&mut groups[i], value);
+                continue;
+            }
+            index.insert(k, groups.len());
+            groups.push(Group { first: record.clone(), values: vec![value] });
+        }
+        for group in &groups {
+            args.write_group(&mut wtr, column, group)?;
+        }
+    }
+    Ok(wtr.flush()?)
+}
+
+impl Args {
+    fn add(&self, group: &mut Group, value: Vec<u8>) {
+        if !self.flag_unique || !group.values.contains(&value) {
+            group.values.push(value);
+        }
+    }
+
+    fn write_group<W: ::std::io::Write>(
+        &self,
+        wtr: &mut csv::Writer<W>,
+        column: usize,
+        group: &Group,
+    ) -> CliResult<()> {
+        let joined = group.values.join(self.arg_separator.as_bytes());
+        let mut out = csv::ByteRecord::new();
+        for (i, field) in group.first.iter().enumerate() {
+            out.push_field(if i == column { &joined } else { field });
+        }
+        wtr.write_byte_record(&out)?;
+        Ok(())
+    }
+}
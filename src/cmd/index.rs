@@ -1,22 +1,47 @@
 use std::fs;
-use std::io;
 use std::path::{Path, PathBuf};
 
-use csv_index::RandomAccessSimple;
-
 use CliResult;
-use config::{Config, Delimiter};
+use config::{self, Config, Delimiter, Flexible};
+use index::{self, IndexFile, MultiIndexed};
 use util;
 
-static USAGE: &'static str = "
+pub static USAGE: &'static str = "
 Creates an index of the given CSV data, which can make other operations like
 slicing, splitting and gathering statistics much faster.
 
 Note that this does not accept CSV data on stdin. You must give a file
 path. The index is created at 'path/to/input.csv.idx'. The index will be
-automatically used by commands that can benefit from it. If the original CSV
-data changes after the index is made, commands that try to use it will result
-in an error (you have to regenerate the index before it can be used again).
+automatically used by commands that can benefit from it.
+
+The index records the size and modification time of the CSV data. If the
+original CSV data changes after the index is made, commands that try to use it
+will result in an error (you have to regenerate the index before it can be used
+again), unless they are given --auto-index, in which case the index is rebuilt.
+Indexes created by older versions of xsv don't have this information, so they
+are only checked against the modification time of the index file itself.
+
+The records of CSV data with comment lines (which are skipped when the
+global --comment option is given, as in 'xsv --comment # index data.csv')
+are only at the same positions when the same comment character is used, so
+the index also records it. An index is stale when it is used with a
+different comment character, or with none. Indexes are not used when records
+with the wrong number of fields are fixed with the global --flexible option.
+
+Gzip compressed CSV data (a file name ending in '.gz') can be indexed too. In
+that case, the index also stores checkpoints of the decompressor, so that
+reading a record in the middle of the data only has to decompress from the
+closest checkpoint before it instead of from the start of the file. Each
+checkpoint takes up about 32KB in the index.
+
+With --multi, <input> is a pattern like 'events-*.csv' (where only the file
+name may contain the wildcards '*' and '?') and a single multi-index of all
+matching files (shards) is written to the file given with --output. Passing
+the multi-index to 'xsv count', 'xsv slice' or 'xsv sample' in place of CSV
+data makes them treat the shards as one CSV file, in which the shards are
+concatenated in the order of their names. Unless --no-headers is given, all
+shards must have the same header. If a shard is modified, removed or added
+after the multi-index is created, using it results in an error.
 
 Usage:
     xsv index [options] <input>
@@ -27,32 +52,105 @@ index options:
                            Generally, this is not currently useful because
                            the only way to use an index is if it is specially
                            named <input>.idx.
+    --fields               Also store the offsets of the fields of every
+                           record. This makes the index larger, but lets
+                           'xsv select' read the selected fields without
+                           parsing the others, which is faster for very
+                           wide CSV data. Records containing quotes are
+                           still parsed.
+    --checkpoint-span <n>  For gzip compressed data, the number of bytes of
+                           decompressed data between checkpoints. Smaller
+                           values make seeking faster but the index larger.
+                           [default: 1048576]
+    --check                Don't create an index. Instead, report whether
+                           the existing index is up to date. If it is
+                           missing or stale, this exits with a non-zero
+                           status.
+    --multi                Create a multi-index of all files matching the
+                           pattern <input>. This requires --output.
+    -n, --no-headers       When used with --multi, the first row of each
+                           shard is not treated as a header.
 
 Common options:
     -h, --help             Display this message
     -d, --delimiter <arg>  The field delimiter for reading CSV data.
-                           Must be a single character. (default: ,)
+                           Must be a single character (or tab, pipe,
+                           semicolon, space, \\t or \\xHH). (default: ,)
 ";
 
 #[derive(Deserialize)]
 struct Args {
     arg_input: String,
     flag_output: Option<String>,
+    flag_fields: bool,
+    flag_checkpoint_span: u64,
+    flag_check: bool,
+    flag_multi: bool,
+    flag_no_headers: bool,
     flag_delimiter: Option<Delimiter>,
 }
 
 pub fn run(argv: &[&str]) -> CliResult<()> {
     let args: Args = util::get_args(USAGE, argv)?;
+    if Config::new(&None).get_flexible() != Flexible::Error {
+        return fail_usage!("An index can't be created with --flexible, since \
+                            it must have the records of the CSV data as it \
+                            is.");
+    }
+    if config::is_url(&args.arg_input) {
+        return fail_usage!("CSV data at a URL can't be indexed. Please \
+                            download it first.");
+    }
+    if args.flag_multi {
+        return args.multi();
+    }
 
     let pidx = match args.flag_output {
         None => util::idx_path(&Path::new(&args.arg_input)),
         Some(p) => PathBuf::from(&p),
     };
 
-    let rconfig = Config::new(&Some(args.arg_input))
+    let rconfig = Config::new(&Some(args.arg_input.clone()))
                          .delimiter(args.flag_delimiter);
-    let mut rdr = rconfig.reader_file()?;
-    let mut wtr = io::BufWriter::new(fs::File::create(&pidx)?);
-    RandomAccessSimple::create(&mut rdr, &mut wtr)?;
-    Ok(())
+    if args.flag_check {
+        return check(&rconfig, &args.arg_input, &pidx);
+    }
+    if args.flag_checkpoint_span == 0 {
+        return fail_usage!("--checkpoint-span must be greater than 0.");
+    }
+    index::create(
+        &rconfig, &pidx, args.flag_fields, args.flag_checkpoint_span)
+}
+
+impl Args {
+    fn multi(&self) -> CliResult<()> {
+        let pidx = match self.flag_output {
+            None => return fail_usage!("--multi requires --output."),
+            Some(ref p) => PathBuf::from(p),
+        };
+        if self.flag_check {
+            let conf = Config::new(&self.flag_output)
+                .delimiter(self.flag_delimiter)
+                .no_headers(self.flag_no_headers);
+            MultiIndexed::open(&conf, &pidx)?;
+            return Ok(println!("{}: up to date", pidx.display()));
+        }
+        let conf = Config::new(&Some(self.arg_input.clone()))
+            .delimiter(self.flag_delimiter)
+            .no_headers(self.flag_no_headers);
+        index::create_multi(&conf, &self.arg_input, &pidx)
+    }
+}
+
+fn check(conf: &Config, input: &str, pidx: &Path) -> CliResult<()> {
+    let idx = match IndexFile::open(pidx) {
+        Ok(idx) => idx,
+        Err(_) => return fail_data!(format!("{}: missing", pidx.display())),
+    };
+    match idx.staleness(&fs::metadata(input)?, conf.get_comment())? {
+        None => Ok(println!("{}: up to date", pidx.display())),
+        Some(reason) => {
+            fail_data!(format!("{}: stale ({})", pidx.display(), reason))
+        }
+    }
 }
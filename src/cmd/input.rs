@@ -1,10 +1,15 @@
+use std::io::{self, BufRead, Read};
+use std::str;
+
 use csv;
+use regex::bytes::Regex;
 
 use CliResult;
+use cmd::fmt::{QuoteStyle, normalize};
 use config::{Config, Delimiter};
 use util;
 
-static USAGE: &'static str = "
+pub static USAGE: &'static str = "
 Read CSV data with special quoting rules.
 
 Generally, all xsv commands support basic options like specifying the delimiter
@@ -12,6 +17,14 @@ used in CSV data. This does not cover all possible types of CSV data. For
 example, some CSV files don't use '\"' for quotes or use different escaping
 styles.
 
+This command reads CSV data as leniently as possible and writes it as
+standard CSV data, so that the other commands can read it. Records may have
+different numbers of fields. The options below clean up other problems:
+lines before the data can be skipped, escapes like '\\\"' can be read,
+whitespace can be trimmed and text in other encodings can be converted to
+UTF-8. With --report, the number of changes of each kind is written to
+stderr.
+
 Usage:
     xsv input [options] [<input>]
 
@@ -20,12 +33,27 @@ input options:
     --escape <arg>         The escape character to use. When not specified,
                            quotes are escaped by doubling them.
     --no-quoting           Disable quoting completely.
+    --skip-lines <n>       Skip the first <n> lines of the input.
+                           [default: 0]
+    --skip-lines-matching <regex>
+                           After the lines skipped by --skip-lines, skip
+                           the lines at the start of the input that match
+                           <regex>, e.g., '^#' for comments.
+    --trim                 Strip leading and trailing whitespace from every
+                           field.
+    --encoding <enc>       Convert the input from this encoding to UTF-8.
+                           This is either 'latin1' or 'utf8', which
+                           replaces bytes that aren't valid UTF-8.
+    --quote-style <arg>    The quoting style to use for the output, as in
+                           'xsv fmt'. [default: minimal]
+    --report               Write a summary of the changes to stderr.
 
 Common options:
     -h, --help             Display this message
     -o, --output <file>    Write output to <file> instead of stdout.
     -d, --delimiter <arg>  The field delimiter for reading CSV data.
-                           Must be a single character. (default: ,)
+                           Must be a single character (or tab, pipe,
+                           semicolon, space, \\t or \\xHH). (default: ,)
 ";
 
 #[derive(Deserialize)]
@@ -36,6 +64,19 @@ struct Args {
     flag_quote: Delimiter,
     flag_escape: Option<Delimiter>,
     flag_no_quoting: bool,
+    flag_skip_lines: u64,
+    flag_skip_lines_matching: Option<String>,
+    flag_trim: bool,
+    flag_encoding: Option<Encoding>,
+    flag_quote_style: QuoteStyle,
+    flag_report: bool,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+enum Encoding {
+    Latin1,
+    Utf8,
 }
 
 pub fn run(argv: &[&str]) -> CliResult<()> {
@@ -43,8 +84,11 @@ pub fn run(argv: &[&str]) -> CliResult<()> {
     let mut rconfig = Config::new(&args.arg_input)
         .delimiter(args.flag_delimiter)
         .no_headers(true)
+        .flexible(true)
         .quote(args.flag_quote.as_byte());
-    let wconfig = Config::new(&args.flag_output);
+    let wconfig = Config::new(&args.flag_output)
+        .flexible(true)
+        .quote_style(args.flag_quote_style.csv());
 
     if let Some(escape) = args.flag_escape {
         rconfig = rconfig.escape(Some(escape.as_byte())).double_quote(false);
@@ -52,13 +96,89 @@ pub fn run(argv: &[&str]) -> CliResult<()> {
     if args.flag_no_quoting {
         rconfig = rconfig.quoting(false);
     }
+    let pattern = match args.flag_skip_lines_matching {
+        None => None,
+        Some(ref re) => Some(Regex::new(re)?),
+    };
 
-    let mut rdr = rconfig.reader()?;
+    // The skipped lines are read before the CSV parser sees the data. The
+    // first line that isn't skipped is put back in front of the rest.
+    let mut src = io::BufReader::new(rconfig.io_reader()?);
+    let mut skipped_lines = 0;
+    let mut line = vec![];
+    for _ in 0..args.flag_skip_lines {
+        line.clear();
+        if src.read_until(b'\n', &mut line)? == 0 {
+            break;
+        }
+        skipped_lines += 1;
+    }
+    line.clear();
+    if let Some(ref re) = pattern {
+        loop {
+            line.clear();
+            if src.read_until(b'\n', &mut line)? == 0 {
+                break;
+            }
+            let mut end = line.len();
+            while end > 0
+                  && (line[end - 1] == b'\n' || line[end - 1] == b'\r') {
+                end -= 1;
+            }
+            if !re.is_match(&line[..end]) {
+                break;
+            }
+            skipped_lines += 1;
+        }
+    }
+
+    let mut rdr = rconfig.from_reader(io::Cursor::new(line).chain(src));
     let mut wtr = wconfig.writer()?;
     let mut row = csv::ByteRecord::new();
+    let mut out = csv::ByteRecord::new();
+    let (mut trimmed, mut transcoded, mut irregular) = (0u64, 0u64, 0u64);
+    let mut first_len = None;
     while rdr.read_byte_record(&mut row)? {
-        wtr.write_record(&row)?;
+        if *first_len.get_or_insert(row.len()) != row.len() {
+            irregular += 1;
+        }
+        out.clear();
+        for field in &row {
+            let mut field = match args.flag_encoding {
+                Some(Encoding::Latin1) if !field.is_ascii() => {
+                    transcoded += 1;
+                    field.iter().map(|&b| b as char).collect::<String>()
+                         .into_bytes()
+                }
+                Some(Encoding::Utf8) if str::from_utf8(field).is_err() => {
+                    transcoded += 1;
+                    String::from_utf8_lossy(field).into_owned().into_bytes()
+                }
+                _ => field.to_vec(),
+            };
+            if args.flag_trim {
+                let t = normalize(&field, true, false).into_owned();
+                if t.len() != field.len() {
+                    trimmed += 1;
+                    field = t;
+                }
+            }
+            out.push_field(&field);
+        }
+        wtr.write_byte_record(&out)?;
     }
     wtr.flush()?;
+
+    if args.flag_report {
+        werr!("skipped {} line(s)", skipped_lines);
+        if args.flag_trim {
+            werr!("trimmed {} field(s)", trimmed);
+        }
+        if args.flag_encoding.is_some() {
+            werr!("converted {} field(s) to UTF-8", transcoded);
+        }
+        werr!("found {} record(s) with a different number of fields than \
+               the first", irregular);
+    }
     Ok(())
 }
@@ -1,6 +1,5 @@
 use std::collections::hash_map::{HashMap, Entry};
 use std::fmt;
-use std::fs;
 use std::io;
 use std::iter::repeat;
 use std::str;
@@ -9,12 +8,12 @@ use byteorder::{WriteBytesExt, BigEndian};
 use csv;
 
 use CliResult;
-use config::{Config, Delimiter};
+use config::{Config, DataFile, Delimiter};
 use index::Indexed;
 use select::{SelectColumns, Selection};
 use util;
 
-static USAGE: &'static str = "
+pub static USAGE: &'static str = "
 Joins two sets of CSV data on the specified columns.
 
 The default join operation is an 'inner' join. This corresponds to the
@@ -68,7 +67,8 @@ Common options:
                            as headers. (i.e., They are not searched, analyzed,
                            sliced, etc.)
     -d, --delimiter <arg>  The field delimiter for reading CSV data.
-                           Must be a single character. (default: ,)
+                           Must be a single character (or tab, pipe,
+                           semicolon, space, \\t or \\xHH). (default: ,)
 ";
 
 type ByteString = Vec<u8>;
@@ -119,7 +119,7 @@ pub fn run(argv: &[&str]) -> CliResult<()> {
             state.write_headers()?;
             state.inner_join()
         }
-        _ => fail!("Please pick exactly one join operation.")
+        _ => fail_usage!("Please pick exactly one join operation.")
     }
 }
 
@@ -278,7 +278,7 @@ impl<R: io::Read + io::Seek, W: io::Write> IoState<R, W> {
 
 impl Args {
     fn new_io_state(&self)
-        -> CliResult<IoState<fs::File, Box<io::Write+'static>>> {
+        -> CliResult<IoState<DataFile, Box<io::Write+'static>>> {
         let rconf1 = Config::new(&Some(self.arg_input1.clone()))
             .delimiter(self.flag_delimiter)
             .no_headers(self.flag_no_headers)
@@ -314,7 +314,7 @@ impl Args {
         let select1 = rconf1.selection(&*headers1)?;
         let select2 = rconf2.selection(&*headers2)?;
         if select1.len() != select2.len() {
-            return fail!(format!(
+            return fail_usage!(format!(
                 "Column selections must have the same number of columns, \
                  but found column selections with {} and {} columns.",
                 select1.len(), select2.len()));
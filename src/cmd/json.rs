@@ -0,0 +1,259 @@
+use std::io::{self, Write};
+use std::str;
+
+use csv;
+
+use CliResult;
+use config::{Config, Delimiter};
+use json;
+use select::SelectColumns;
+use util;
+
+pub static USAGE: &'static str = "
+Converts CSV data to JSON. By default, the output is a JSON array with one
+object per record. With the --jsonl flag, the output is JSON Lines instead,
+i.e., one object per line.
+
+The keys of each object are the header names, or field_1, field_2, ... when
+the CSV data has no header row. All values are strings, unless the
+flag --infer-types is given. Then numbers in JSON syntax (like 12, -3.5 or
+1e6) become numbers, 'true' and 'false' (in any case) become booleans, and
+empty fields and 'null' (in any case) become null. Note that fields like 007
+are not numbers in JSON syntax and stay strings.
+
+With --nest, header names are split on a separator and the pieces become
+nested objects, e.g., with '.' the headers 'address.city' and
+'address.zip' result in {\"address\": {\"city\": ..., \"zip\": ...}}.
+
+Bytes that aren't valid UTF-8 are replaced with the Unicode replacement
+character, and a warning with the number of affected fields is written to
+stderr.
+
+Usage:
+    xsv json [options] [<input>]
+    xsv json --help
+
+json options:
+    --jsonl                Write one JSON object per line.
+    --infer-types          Convert numbers, booleans and nulls to native JSON
+                           values instead of writing all values as strings.
+    -s, --select <arg>     Select the columns to write. See 'xsv select -h'
+                           for the full syntax.
+    --nest <sep>           Split header names on <sep> and write nested
+                           objects.
+
+Common options:
+    -h, --help             Display this message
+    -o, --output <file>    Write output to <file> instead of stdout.
+    -n, --no-headers       When set, the first row will not be interpreted
+                           as headers. (i.e., It is converted like any other
+                           record.)
+    -d, --delimiter <arg>  The field delimiter for reading CSV data.
+                           Must be a single character (or tab, pipe,
+                           semicolon, space, \\t or \\xHH). (default: ,)
+";
+
+#[derive(Deserialize)]
+struct Args {
+    arg_input: Option<String>,
+    flag_jsonl: bool,
+    flag_infer_types: bool,
+    flag_select: SelectColumns,
+    flag_nest: Option<String>,
+    flag_output: Option<String>,
+    flag_no_headers: bool,
+    flag_delimiter: Option<Delimiter>,
+}
+
+/// The structure of the objects that are written.
+enum Node {
+    /// A value taken from the field at the given position of a selection.
+    Field(usize),
+    /// A nested object.
+    Object(Vec<(Vec<u8>, Node)>),
+}
+
+pub fn run(argv: &[&str]) -> CliResult<()> {
+    let args: Args = util::get_args(USAGE, argv)?;
+    if args.flag_nest.as_ref().map_or(false, |s| s.is_empty()) {
+        return fail_usage!("The --nest separator must not be empty.");
+    }
+    let rconfig = Config::new(&args.arg_input)
+        .delimiter(args.flag_delimiter)
+        .no_headers(args.flag_no_headers)
+        .select(args.flag_select.clone());
+
+    let mut rdr = rconfig.reader()?;
+    let headers = rdr.byte_headers()?.clone();
+    let sel = rconfig.selection(&headers)?;
+    let keys: Vec<Vec<u8>> = sel.iter().map(|&i| {
+        if rconfig.no_headers {
+            format!("field_{}", i + 1).into_bytes()
+        } else {
+            headers[i].to_vec()
+        }
+    }).collect();
+    let root = match args.flag_nest {
+        None => Node::Object(keys.into_iter()
+                                 .enumerate()
+                                 .map(|(i, k)| (k, Node::Field(i)))
+                                 .collect()),
+        Some(ref sep) => nest(&keys, sep.as_bytes())?,
+    };
+
+    let mut wtr = io::BufWriter::new(Config::new(&args.flag_output)
+                                         .io_writer()?);
+    let mut count = 0u64;
+    let mut invalid = 0u64;
+    if !args.flag_jsonl {
+        write!(&mut wtr, "[")?;
+    }
+    let mut record = csv::ByteRecord::new();
+    while rdr.read_byte_record(&mut record)? {
+        if !args.flag_jsonl {
+            write!(&mut wtr, "{}\n  ", if count == 0 { "" } else { "," })?;
+        }
+        count += 1;
+        let fields: Vec<&[u8]> = sel.select(&record).collect();
+        invalid += fields.iter()
+                         .filter(|f| str::from_utf8(f).is_err())
+                         .count() as u64;
+        args.write_node(&mut wtr, &root, &fields)?;
+        if args.flag_jsonl {
+            writeln!(&mut wtr)?;
+        }
+    }
+    if !args.flag_jsonl {
+        writeln!(&mut wtr, "{}]", if count == 0 { "" } else { "\n" })?;
+    }
+    wtr.flush()?;
+    if invalid > 0 {
+        werr!("warning: replaced invalid UTF-8 in {} field(s)", invalid);
+    }
+    Ok(())
+}
+
+/// Builds nested objects from keys that are split on `sep`.
+fn nest(keys: &[Vec<u8>], sep: &[u8]) -> CliResult<Node> {
+    let mut root = Node::Object(vec![]);
+    for (i, key) in keys.iter().enumerate() {
+        let path = split(key, sep);
+        let mut node = &mut root;
+        for (depth, piece) in path.iter().enumerate() {
+            let last = depth + 1 == path.len();
+            let children = match *node {
+                Node::Object(ref mut children) => children,
+                Node::Field(_) => {
+                    return fail_data!(format!(
+                        "Cannot nest '{}' inside the value of '{}'.",
+                        String::from_utf8_lossy(key),
+                        String::from_utf8_lossy(&path[..depth].join(sep))));
+                }
+            };
+            let pos = match children.iter().position(|c| c.0 == *piece) {
+                Some(pos) if last => {
+                    return match children[pos].1 {
+                        Node::Field(_) => fail_data!(format!(
+                            "The key '{}' is used more than once.",
+                            String::from_utf8_lossy(key))),
+                        // An earlier key is nested inside this one.
+                        ref nested => {
+                            let inner = &keys[first_field(nested)];
+                            fail_data!(format!(
+                                "Cannot nest '{}' inside the value of '{}'.",
+                                String::from_utf8_lossy(inner),
+                                String::from_utf8_lossy(key)))
+                        }
+                    };
+                }
+                Some(pos) => pos,
+                None => {
+                    let child = if last {
+                        Node::Field(i)
+                    } else {
+                        Node::Object(vec![])
+                    };
+                    children.push((piece.to_vec(), child));
+                    children.len() - 1
+                }
+            };
+            node = &mut children[pos].1;
+        }
+    }
+    Ok(root)
+}
+
+/// Returns the index of the first field in `node`.
+fn first_field(node: &Node) -> usize {
+    match *node {
+        Node::Field(i) => i,
+        // Objects are only created for keys that have a field below them.
+        Node::Object(ref children) => first_field(&children[0].1),
+    }
+}
+
+fn split<'a>(s: &'a [u8], sep: &[u8]) -> Vec<&'a [u8]> {
+    let mut pieces = vec![];
+    let mut start = 0;
+    let mut i = 0;
+    while i + sep.len() <= s.len() {
+        if &s[i..i + sep.len()] == sep {
+            pieces.push(&s[start..i]);
+            i += sep.len();
+            start = i;
+        } else {
+            i += 1;
+        }
+    }
+    pieces.push(&s[start..]);
+    pieces
+}
+
+impl Args {
+    fn write_node<W: io::Write>(
+        &self,
+        wtr: &mut W,
+        node: &Node,
+        fields: &[&[u8]],
+    ) -> CliResult<()> {
+        match *node {
+            Node::Field(i) => self.write_value(wtr, fields.get(i)
+                                                          .map_or(b"", |f| f)),
+            Node::Object(ref children) => {
+                write!(wtr, "{{")?;
+                for (i, &(ref key, ref child)) in children.iter().enumerate() {
+                    if i > 0 {
+                        write!(wtr, ", ")?;
+                    }
+                    json::write_str(wtr, key)?;
+                    write!(wtr, ": ")?;
+                    self.write_node(wtr, child, fields)?;
+                }
+                write!(wtr, "}}")?;
+                Ok(())
+            }
+        }
+    }
+
+    fn write_value<W: io::Write>(
+        &self,
+        wtr: &mut W,
+        field: &[u8],
+    ) -> CliResult<()> {
+        if self.flag_infer_types {
+            let lower = field.to_ascii_lowercase();
+            if json::is_number(field) {
+                wtr.write_all(field)?;
+                return Ok(());
+            } else if lower == b"true" || lower == b"false" {
+                wtr.write_all(&lower)?;
+                return Ok(());
+            } else if field.is_empty() || lower == b"null" {
+                wtr.write_all(b"null")?;
+                return Ok(());
+            }
+        }
+        json::write_str(wtr, field)?;
+        Ok(())
+    }
+}
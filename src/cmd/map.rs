@@ -0,0 +1,166 @@
+use csv;
+
+use CliResult;
+use config::{Config, Delimiter};
+use expr::{Expr, Value};
+use util;
+
+pub static USAGE: &'static str = "
+Computes new columns from expressions that are evaluated for every record.
+
+Each expression has the form 'name = expression', e.g.,
+
+    xsv map 'total = price * qty' data.csv
+    xsv map 'full_name = concat(first, \" \", last)' data.csv
+
+The result of each expression is written to a new column at the end of the
+record, or replaces the column if there already is one with the same name.
+To compute several columns, give each expression with --expr; they are
+evaluated in order, so later expressions can use the results of earlier
+ones.
+
+With --filter, the expressions have no 'name =' part. Instead of adding
+columns, only the records for which every expression is true are written.
+The values false, 0 and the empty string are false, all others are true.
+
+Expressions can use:
+
+    price, `unit price`   The value of a column. Names that aren't
+                          identifiers must be quoted in backticks.
+    1.5, \"text\", true     Number, string and boolean literals.
+    + - * / %             Arithmetic on numbers.
+    == != < <= > >=       Comparisons, which are numeric if both sides are
+                          numbers and compare strings otherwise.
+    x is empty            Whether x is the empty string, or with
+                          'x is not empty', whether it isn't.
+    x in (a, b, ...)      Whether x is equal to one of the values, or with
+                          'x not in (a, b, ...)', whether it isn't.
+    and, or, not          Logical operators.
+    ( )                   Grouping.
+
+and the functions:
+
+    concat(a, b, ...)     The values joined together as a string.
+    substr(s, i[, n])     The characters of s from position i (starting
+                          at 0), at most n of them.
+    len(s)                The number of characters in s.
+    upper(s), lower(s)    s in upper or lower case.
+    trim(s)               s without leading and trailing whitespace.
+    if(cond, a, b)        a if cond is true and b otherwise.
+    round(x[, digits])    x rounded to a number of decimal places.
+    abs(x)                The absolute value of x.
+    min(x, ...), max(x, ...)
+                          The smallest or largest of the numbers.
+
+Values of columns are strings, which are converted to numbers where
+needed. It is an error if a value isn't a number where one is needed.
+
+Usage:
+    xsv map [options] (--expr <expression>)... [<input>]
+    xsv map [options] <expression> [<input>]
+    xsv map --help
+
+map options:
+    -e, --expr <expression>  An expression to evaluate. This can be given
+                             multiple times.
+    --filter                 Only write the records for which the
+                             expressions are true.
+
+Common options:
+    -h, --help               Display this message
+    -o, --output <file>      Write output to <file> instead of stdout.
+    -d, --delimiter <arg>    The field delimiter for reading CSV data.
+                             Must be a single character (or tab, pipe,
+                             semicolon, space, \\t or \\xHH). (default: ,)
+";
+
+#[derive(Deserialize)]
+struct Args {
+    arg_expression: Option<String>,
+    arg_input: Option<String>,
+    flag_expr: Vec<String>,
+    flag_filter: bool,
+    flag_output: Option<String>,
+    flag_delimiter: Option<Delimiter>,
+}
+
+/// An expression along with its source and the position of its result in
+/// the output record (which is `None` for filters).
+struct Compiled {
+    src: String,
+    expr: Expr,
+    target: Option<usize>,
+}
+
+pub fn run(argv: &[&str]) -> CliResult<()> {
+    let args: Args = util::get_args(USAGE, argv)?;
+    let rconfig = Config::new(&args.arg_input)
+        .delimiter(args.flag_delimiter);
+
+    let mut rdr = rconfig.reader()?;
+    let mut wtr = Config::new(&args.flag_output).writer()?;
+    let mut names: Vec<String> = rdr.byte_headers()?.iter()
+        .map(|name| String::from_utf8_lossy(name).into_owned())
+        .collect();
+
+    let sources = args.arg_expression.iter().chain(&args.flag_expr);
+    let mut exprs = vec![];
+    for src in sources {
+        let compiled = {
+            let resolve = |name: &str| names.iter().position(|n| n == name);
+            if args.flag_filter {
+                Expr::parse(src, resolve).map(|expr| (None, expr))
+            } else {
+                Expr::parse_assignment(src, resolve)
+                    .map(|(name, expr)| (Some(name), expr))
+            }
+        };
+        let (name, expr) = match compiled {
+            Ok(compiled) => compiled,
+            Err(err) => return fail_usage!(err.describe(src)),
+        };
+        let target = name.map(|name| {
+            match names.iter().position(|n| *n == name) {
+                Some(i) => i,
+                None => {
+                    names.push(name);
+                    names.len() - 1
+                }
+            }
+        });
+        exprs.push(Compiled { src: src.clone(), expr: expr, target: target });
+    }
+    wtr.write_record(&names)?;
+
+    let mut record = csv::ByteRecord::new();
+    let mut row: Vec<Value> = vec![];
+    'records: while rdr.read_byte_record(&mut record)? {
+        row.clear();
+        row.extend(record.iter().map(|field| {
+            Value::String(String::from_utf8_lossy(field).into_owned())
+        }));
+        row.resize(names.len(), Value::String(String::new()));
+        for e in &exprs {
+            let value = match e.expr.eval(&row) {
+                Ok(value) => value,
+                Err(err) => {
+                    let line = record.position().map_or(0, |p| p.line());
+                    return fail_data!(format!(
+                        "Error on line {} in expression '{}': {}",
+                        line, e.src, err));
+                }
+            };
+            match e.target {
+                Some(i) => row[i] = value,
+                None if !value.is_truthy() => continue 'records,
+                None => {}
+            }
+        }
+        if args.flag_filter {
+            wtr.write_byte_record(&record)?;
+        } else {
+            wtr.write_record(row.iter().map(|v| v.to_string()))?;
+        }
+    }
+    Ok(wtr.flush()?)
+}
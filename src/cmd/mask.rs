@@ -0,0 +1,286 @@
+use std::fs;
+
+use csv;
+
+use {CliError, CliResult};
+use config::{Config, Delimiter};
+use digest;
+use select::SelectColumns;
+use util;
+
+pub static USAGE: &'static str = "
+Masks the values of sensitive columns, e.g., to share a sample of the data
+without personal information.
+
+The selected columns are masked with the strategy given with --strategy.
+Different columns can be masked with different strategies with --rule, which
+has the form 'column:strategy' or 'column:strategy:argument' and can be given
+multiple times. The column is everything before the first ':', and can be
+any selection as in 'xsv select'. Rules take precedence over --select, and
+later rules over earlier ones. For example,
+
+    xsv mask --key-file key.txt -r email:hash -r card:partial:0,4 data.csv
+
+The strategies are:
+
+    hash     Replace the value by its HMAC-SHA256 with the key given with the
+             options --key or --key-file, so that equal values are replaced
+             by equal tokens and joins still work. The argument is the
+             encoding of the token, 'hex' or 'base64'.
+    redact   Replace the value by a fixed text, which is the argument.
+    partial  Keep the first and the last characters of the value and replace
+             the others by the mask character. The argument is the number
+             of characters to keep at the start and at the end, as in '0,4'.
+             Values that aren't longer than that are masked completely.
+    null     Make the value empty.
+
+Strategies without an argument use the defaults given by the options below.
+Empty values are left empty. The header row is not changed.
+
+Usage:
+    xsv mask [options] [<input>]
+    xsv mask [options] (--rule <rule>)... [<input>]
+    xsv mask --help
+
+mask options:
+    -s, --select <cols>      The columns to mask with --strategy. See
+                             'xsv select -h' for the full syntax.
+    --strategy <name>        The strategy for the selected columns.
+                             [default: hash]
+    -r, --rule <rule>        A strategy for some columns. This can be given
+                             multiple times.
+    --key <key>              The key for the hash strategy.
+    --key-file <file>        Read the key for the hash strategy from <file>.
+                             A trailing newline is ignored.
+    --hash-encoding <enc>    The encoding of hashed values, 'hex' or
+                             'base64'. [default: hex]
+    --redact-with <text>     The text of redacted values.
+                             [default: REDACTED]
+    --partial <first,last>   The number of characters that the partial
+                             strategy keeps. [default: 0,4]
+    --mask-char <char>       The character that the partial strategy
+                             replaces characters with. [default: *]
+
+Common options:
+    -h, --help               Display this message
+    -o, --output <file>      Write output to <file> instead of stdout.
+    -n, --no-headers         When set, the first row will not be
+                             interpreted as headers.
+    -d, --delimiter <arg>    The field delimiter for reading CSV data.
+                             Must be a single character (or tab, pipe,
+                             semicolon, space, \\t or \\xHH). (default: ,)
+";
+
+#[derive(Deserialize)]
+struct Args {
+    arg_input: Option<String>,
+    flag_select: Option<SelectColumns>,
+    flag_strategy: String,
+    flag_rule: Vec<String>,
+    flag_key: Option<String>,
+    flag_key_file: Option<String>,
+    flag_hash_encoding: String,
+    flag_redact_with: String,
+    flag_partial: String,
+    flag_mask_char: char,
+    flag_output: Option<String>,
+    flag_no_headers: bool,
+    flag_delimiter: Option<Delimiter>,
+}
+
+#[derive(Clone, Debug)]
+enum Strategy {
+    Hash { base64: bool },
+    Redact(String),
+    Partial(usize, usize),
+    Null,
+}
+
+impl Args {
+    fn strategy(&self, name: &str, arg: Option<&str>)
+               -> Result<Strategy, String> {
+        Ok(match name {
+            "hash" => {
+                match arg.unwrap_or(&self.flag_hash_encoding) {
+                    "hex" => Strategy::Hash { base64: false },
+                    "base64" => Strategy::Hash { base64: true },
+                    enc => return Err(format!(
+                        "Unknown hash encoding '{}'. The encoding must be \
+                         'hex' or 'base64'.", enc)),
+                }
+            }
+            "redact" => {
+                Strategy::Redact(arg.unwrap_or(&self.flag_redact_with)
+                                    .to_owned())
+            }
+            "partial" => {
+                let arg = arg.unwrap_or(&self.flag_partial);
+                let counts: Vec<&str> = arg.split(',').collect();
+                match (counts.len(), counts[0].trim().parse(),
+                       counts.last().unwrap().trim().parse()) {
+                    (2, Ok(first), Ok(last)) => Strategy::Partial(first, last),
+                    _ => return Err(format!(
+                        "Invalid argument '{}' for the partial strategy. \
+                         It must have the form 'first,last'.", arg)),
+                }
+            }
+            "null" => {
+                if arg.is_some() {
+                    return Err("The null strategy has no argument."
+                               .to_owned());
+                }
+                Strategy::Null
+            }
+            name => return Err(format!(
+                "Unknown strategy '{}'. The strategy must be one of 'hash', \
+                 'redact', 'partial' or 'null'.", name)),
+        })
+    }
+
+    fn key(&self) -> CliResult<Vec<u8>> {
+        match (&self.flag_key, &self.flag_key_file) {
+            (&Some(_), &Some(_)) => {
+                fail_usage!("--key and --key-file cannot be used together.")
+            }
+            (&Some(ref key), &None) => Ok(key.clone().into_bytes()),
+            (&None, &Some(ref path)) => {
+                let mut key = fs::read(path)?;
+                if key.ends_with(b"\n") {
+                    key.pop();
+                    if key.ends_with(b"\r") {
+                        key.pop();
+                    }
+                }
+                Ok(key)
+            }
+            (&None, &None) => {
+                fail_usage!("The hash strategy requires a key. Please give it \
+                             with --key or --key-file.")
+            }
+        }
+    }
+}
+
+fn base64(bytes: &[u8]) -> String {
+    const CHARS: &'static [u8] =
+        b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::with_capacity((bytes.len() + 2) / 3 * 4);
+    for chunk in bytes.chunks(3) {
+        let b = [chunk[0], *chunk.get(1).unwrap_or(&0),
+                 *chunk.get(2).unwrap_or(&0)];
+        let n = u32::from(b[0]) << 16 | u32::from(b[1]) << 8 | u32::from(b[2]);
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(CHARS[(n >> (18 - 6 * i) & 0x3F) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+fn mask(
+    strategy: &Strategy,
+    key: &[u8],
+    mask_char: char,
+    value: &[u8],
+) -> Vec<u8> {
+    if value.is_empty() {
+        return vec![];
+    }
+    match *strategy {
+        Strategy::Hash { base64: b64 } => {
+            let mac = digest::hmac_sha256(key, value);
+            if b64 {
+                base64(&mac).into_bytes()
+            } else {
+                mac.iter().map(|b| format!("{:02x}", b)).collect::<String>()
+                   .into_bytes()
+            }
+        }
+        Strategy::Redact(ref text) => text.clone().into_bytes(),
+        Strategy::Partial(first, last) => {
+            let value = String::from_utf8_lossy(value);
+            let chars: Vec<char> = value.chars().collect();
+            let n = chars.len();
+            let mut masked = String::with_capacity(value.len());
+            for (i, &c) in chars.iter().enumerate() {
+                if n > first + last && (i < first || i >= n - last) {
+                    masked.push(c);
+                } else {
+                    masked.push(mask_char);
+                }
+            }
+            masked.into_bytes()
+        }
+        Strategy::Null => vec![],
+    }
+}
+
+pub fn run(argv: &[&str]) -> CliResult<()> {
+    let args: Args = util::get_args(USAGE, argv)?;
+    if args.flag_select.is_none() && args.flag_rule.is_empty() {
+        return fail_usage!("Please select the columns to mask with --select \
+                            or --rule.");
+    }
+    let rconfig = Config::new(&args.arg_input)
+        .delimiter(args.flag_delimiter)
+        .no_headers(args.flag_no_headers);
+
+    let mut rdr = rconfig.reader()?;
+    let mut wtr = Config::new(&args.flag_output).writer()?;
+    let headers = rdr.byte_headers()?.clone();
+
+    let mut strategies: Vec<Option<Strategy>> = vec![None; headers.len()];
+    if let Some(ref sel) = args.flag_select {
+        let strategy = args.strategy(&args.flag_strategy, None)
+            .map_err(CliError::Usage)?;
+        for i in sel.selection(&headers, !rconfig.no_headers)?.iter() {
+            strategies[*i] = Some(strategy.clone());
+        }
+    }
+    for rule in &args.flag_rule {
+        let mut parts = rule.splitn(3, ':');
+        let (col, name, arg) = match (parts.next(), parts.next()) {
+            (Some(col), Some(name)) => (col, name, parts.next()),
+            _ => return fail_usage!(format!(
+                "Invalid rule '{}'. A rule must have the form \
+                 'column:strategy' or 'column:strategy:argument'.", rule)),
+        };
+        let strategy = args.strategy(name, arg).map_err(CliError::Usage)?;
+        let sel = SelectColumns::parse(col).map_err(CliError::Usage)?
+            .selection(&headers, !rconfig.no_headers)?;
+        for i in sel.iter() {
+            strategies[*i] = Some(strategy.clone());
+        }
+    }
+    let key = if strategies.iter().any(|s| match *s {
+        Some(Strategy::Hash { .. }) => true,
+        _ => false,
+    }) {
+        args.key()?
+    } else {
+        vec![]
+    };
+
+    if !rconfig.no_headers {
+        wtr.write_byte_record(&headers)?;
+    }
+    let mut record = csv::ByteRecord::new();
+    let mut out = csv::ByteRecord::new();
+    while rdr.read_byte_record(&mut record)? {
+        out.clear();
+        for (i, field) in record.iter().enumerate() {
+            match strategies.get(i) {
+                Some(&Some(ref strategy)) => {
+                    out.push_field(&mask(strategy, &key, args.flag_mask_char,
+                                         field));
+                }
+                _ => out.push_field(field),
+            }
+        }
+        wtr.write_byte_record(&out)?;
+    }
+    Ok(wtr.flush()?)
+}
@@ -0,0 +1,110 @@
+use csv;
+
+use CliResult;
+use config::{Config, Delimiter};
+use select::SelectColumns;
+use util;
+
+pub static USAGE: &'static str = "
+Reshapes CSV data from wide to long format, which is the inverse of
+'xsv pivot'. For example, melting
+
+    id,jan,feb
+    a,1,2
+    b,3,4
+
+with '--id id' results in
+
+    id,variable,value
+    a,jan,1
+    a,feb,2
+    b,jan,3
+    b,feb,4
+
+Every input record is turned into one output record for each of the value
+columns, which are all columns that aren't id columns unless they are
+chosen with --value-columns. Each output record has the id columns,
+followed by the name of the value column and its value in that record.
+
+Both --id and --value-columns take a selection as described in
+'xsv select -h', so ranges and negation can be used.
+
+Usage:
+    xsv melt [options] [<input>]
+    xsv melt --help
+
+melt options:
+    -i, --id <cols>             The columns that are repeated in every output
+                                record.
+    -v, --value-columns <cols>  The columns to turn into records. By default,
+                                all columns that aren't id columns.
+    --variable-name <name>      The name of the output column holding the
+                                names of the value columns.
+                                [default: variable]
+    --value-name <name>         The name of the output column holding the
+                                values. [default: value]
+    --drop-empty                Don't write records with an empty value.
+
+Common options:
+    -h, --help                  Display this message
+    -o, --output <file>         Write output to <file> instead of stdout.
+    -d, --delimiter <arg>       The field delimiter for reading CSV data.
+                                Must be a single character (or tab, pipe,
+                                semicolon, space, \\t or \\xHH). (default: ,)
+";
+
+#[derive(Deserialize)]
+struct Args {
+    arg_input: Option<String>,
+    flag_id: Option<SelectColumns>,
+    flag_value_columns: Option<SelectColumns>,
+    flag_variable_name: String,
+    flag_value_name: String,
+    flag_drop_empty: bool,
+    flag_output: Option<String>,
+    flag_delimiter: Option<Delimiter>,
+}
+
+pub fn run(argv: &[&str]) -> CliResult<()> {
+    let args: Args = util::get_args(USAGE, argv)?;
+    let rconfig = Config::new(&args.arg_input)
+        .delimiter(args.flag_delimiter);
+
+    let mut rdr = rconfig.reader()?;
+    let mut wtr = Config::new(&args.flag_output).writer()?;
+    let headers = rdr.byte_headers()?.clone();
+    let ids: Vec<usize> = match args.flag_id {
+        None => vec![],
+        Some(ref sel) => sel.selection(&headers, true)?.to_vec(),
+    };
+    let values: Vec<usize> = match args.flag_value_columns {
+        None => (0..headers.len()).filter(|i| !ids.contains(i)).collect(),
+        Some(ref sel) => sel.selection(&headers, true)?.to_vec(),
+    };
+
+    let mut out = csv::ByteRecord::new();
+    for &i in &ids {
+        out.push_field(&headers[i]);
+    }
+    out.push_field(args.flag_variable_name.as_bytes());
+    out.push_field(args.flag_value_name.as_bytes());
+    wtr.write_byte_record(&out)?;
+
+    let mut record = csv::ByteRecord::new();
+    while rdr.read_byte_record(&mut record)? {
+        for &v in &values {
+            let value = &record[v];
+            if args.flag_drop_empty && value.is_empty() {
+                continue;
+            }
+            out.clear();
+            for &i in &ids {
+                out.push_field(&record[i]);
+            }
+            out.push_field(&headers[v]);
+            out.push_field(value);
+            wtr.write_byte_record(&out)?;
+        }
+    }
+    Ok(wtr.flush()?)
+}
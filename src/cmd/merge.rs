@@ -0,0 +1,177 @@
+use std::cmp;
+use std::collections::BinaryHeap;
+
+use csv;
+
+use CliResult;
+use cmd::sort::{iter_cmp, iter_cmp_num};
+use config::{Config, Delimiter};
+use select::SelectColumns;
+use util;
+
+pub static USAGE: &'static str = "
+Merges CSV files that are each sorted into a single sorted file.
+
+The files must be sorted as by 'xsv sort' with the same --select, --numeric
+and --reverse options. This is checked while merging, and it is an error if
+a record of any file is out of order. Records that compare equal are
+written in the order of the files given.
+
+Only one record of each file is kept in memory at a time. All files must
+have the same header row.
+
+Usage:
+    xsv merge [options] <input>...
+    xsv merge --help
+
+merge options:
+    -s, --select <arg>     Select the columns the files are sorted by.
+                           See 'xsv select --help' for the format details.
+    -N, --numeric          Compare according to string numerical value
+    -R, --reverse          Reverse order
+
+Common options:
+    -h, --help             Display this message
+    -o, --output <file>    Write output to <file> instead of stdout.
+    -n, --no-headers       When set, the first row will not be interpreted
+                           as headers. Namely, it will be merged with the
+                           rest of the rows.
+    -d, --delimiter <arg>  The field delimiter for reading CSV data.
+                           Must be a single character (or tab, pipe,
+                           semicolon, space, \\t or \\xHH). (default: ,)
+";
+
+#[derive(Deserialize)]
+struct Args {
+    arg_input: Vec<String>,
+    flag_select: SelectColumns,
+    flag_numeric: bool,
+    flag_reverse: bool,
+    flag_output: Option<String>,
+    flag_no_headers: bool,
+    flag_delimiter: Option<Delimiter>,
+}
+
+#[derive(Clone, Copy)]
+struct Order {
+    numeric: bool,
+    reverse: bool,
+}
+
+impl Order {
+    fn cmp(self, a: &[Vec<u8>], b: &[Vec<u8>]) -> cmp::Ordering {
+        let (a, b) = if self.reverse { (b, a) } else { (a, b) };
+        let (a, b) = (a.iter().map(|f| &**f), b.iter().map(|f| &**f));
+        if self.numeric { iter_cmp_num(a, b) } else { iter_cmp(a, b) }
+    }
+}
+
+/// The next record of one of the files.
+struct Head {
+    key: Vec<Vec<u8>>,
+    record: csv::ByteRecord,
+    source: usize,
+    order: Order,
+}
+
+impl PartialEq for Head {
+    fn eq(&self, other: &Head) -> bool {
+        self.cmp(other) == cmp::Ordering::Equal
+    }
+}
+
+impl Eq for Head {}
+
+impl PartialOrd for Head {
+    fn partial_cmp(&self, other: &Head) -> Option<cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Head {
+    /// `BinaryHeap` is a max-heap, so the record that comes first is the
+    /// greatest.
+    fn cmp(&self, other: &Head) -> cmp::Ordering {
+        self.order.cmp(&other.key, &self.key)
+            .then_with(|| other.source.cmp(&self.source))
+    }
+}
+
+pub fn run(argv: &[&str]) -> CliResult<()> {
+    let args: Args = util::get_args(USAGE, argv)?;
+    let order = Order {
+        numeric: args.flag_numeric,
+        reverse: args.flag_reverse,
+    };
+    let configs: Vec<Config> = args.arg_input.iter().map(|path| {
+        Config::new(&Some(path.clone()))
+            .delimiter(args.flag_delimiter)
+            .no_headers(args.flag_no_headers)
+            .select(args.flag_select.clone())
+    }).collect();
+
+    let mut rdrs = vec![];
+    let mut headers: Option<csv::ByteRecord> = None;
+    for config in &configs {
+        let mut rdr = config.reader()?;
+        let h = rdr.byte_headers()?.clone();
+        match headers {
+            Some(ref first) if !config.no_headers && *first != h => {
+                return fail_data!(format!(
+                    "The header row of {} differs from the one of {}.",
+                    config.display_path(), configs[0].display_path()));
+            }
+            Some(_) => {}
+            None => headers = Some(h),
+        }
+        rdrs.push(rdr);
+    }
+    let headers = match headers {
+        Some(headers) => headers,
+        None => return fail_usage!("No input files were given."),
+    };
+    let sel = configs[0].selection(&headers)?;
+
+    let mut wtr = Config::new(&args.flag_output).writer()?;
+    if !args.flag_no_headers {
+        wtr.write_byte_record(&headers)?;
+    }
+
+    let mut heap = BinaryHeap::with_capacity(rdrs.len());
+    let mut read = |source: usize, prev: Option<&[Vec<u8>]>|
+            -> CliResult<Option<Head>> {
+        let mut record = csv::ByteRecord::new();
+        if !rdrs[source].read_byte_record(&mut record)? {
+            return Ok(None);
+        }
+        let key: Vec<Vec<u8>> = sel.select(&record).map(|f| f.to_vec())
+                                   .collect();
+        if let Some(prev) = prev {
+            if order.cmp(prev, &key) == cmp::Ordering::Greater {
+                let line = record.position().map_or(0, |p| p.line());
+                return fail_data!(format!(
+                    "The record on line {} of {} is out of order. The files \
+                     must be sorted with the same options as given to \
+                     'xsv merge'.", line, configs[source].display_path()));
+            }
+        }
+        Ok(Some(Head {
+            key: key,
+            record: record,
+            source: source,
+            order: order,
+        }))
+    };
+    for source in 0..configs.len() {
+        if let Some(head) = read(source, None)? {
+            heap.push(head);
+        }
+    }
+    while let Some(head) = heap.pop() {
+        wtr.write_byte_record(&head.record)?;
+        if let Some(next) = read(head.source, Some(&head.key))? {
+            heap.push(next);
+        }
+    }
+    Ok(wtr.flush()?)
+}
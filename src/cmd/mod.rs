@@ -1,21 +1,52 @@
+pub mod apply;
+pub mod behead;
 pub mod cat;
+pub mod completions;
+pub mod config;
 pub mod count;
+pub mod datefmt;
+pub mod dedup;
+pub mod diff;
+pub mod enumerate;
+pub mod excel;
+pub mod explode;
+pub mod fill;
+pub mod filter;
+pub mod fixed;
 pub mod fixlengths;
 pub mod flatten;
 pub mod fmt;
+pub mod foreach;
 pub mod frequency;
+pub mod fromjson;
+pub mod groupby;
+pub mod hash;
 pub mod headers;
+pub mod implode;
 pub mod index;
 pub mod input;
 pub mod join;
+pub mod json;
+pub mod map;
+pub mod mask;
+pub mod melt;
+pub mod merge;
+pub mod parquet;
 pub mod partition;
+pub mod pivot;
 pub mod reverse;
+pub mod safenames;
 pub mod sample;
+pub mod schema;
 pub mod search;
 pub mod select;
 pub mod slice;
+pub mod sniff;
 pub mod sort;
 pub mod split;
 pub mod stats;
 pub mod table;
+pub mod template;
+pub mod tosql;
+pub mod transpose;
 pub mod validate;
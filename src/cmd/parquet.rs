@@ -0,0 +1,277 @@
+use std::collections::HashMap;
+use std::io;
+use std::str;
+
+use csv;
+
+use CliResult;
+use config::{Config, Delimiter};
+use json;
+use parquet::{ColumnData, Compression, Type, Values, Writer};
+use util;
+
+pub static USAGE: &'static str = "
+Converts CSV data to a Parquet file.
+
+The type of each column is inferred from the first --sample-rows records:
+int64 if all non-null values are integers, double if they are all numbers,
+boolean if they are all 'true' or 'false' (in any case) and string
+otherwise. Note that integers with leading zeros (like 007) are strings.
+With --all-text, all columns are strings. To pin the types of some columns,
+give a CSV file with the columns 'column' and 'type' with --schema, e.g.,
+
+    column,type
+    zip,string
+    price,double
+
+All columns are optional (nullable). Empty fields are null, as are fields
+that are equal to one of the values given with --null-values. It is an
+error if a value after the sample doesn't have the type of its column.
+
+The records are written in row groups of --row-group-size records, so only
+one row group is kept in memory at a time.
+
+Usage:
+    xsv parquet [options] [<input>]
+    xsv parquet --help
+
+parquet options:
+    --sample-rows <n>      The number of records to infer column types from.
+                           [default: 1000]
+    --all-text             Don't infer column types.
+    --schema <file>        A CSV file with the types of some columns.
+    --compression <codec>  The compression codec: 'snappy', 'zstd' or
+                           'none'. [default: snappy]
+    --row-group-size <n>   The number of records in a row group.
+                           [default: 100000]
+    --null-values <list>   A comma separated list of values that are null,
+                           in addition to empty fields.
+
+Common options:
+    -h, --help             Display this message
+    -o, --output <file>    Write output to <file> instead of stdout.
+    -n, --no-headers       When set, the first row will not be interpreted
+                           as headers. The columns are named field_1,
+                           field_2, and so on.
+    -d, --delimiter <arg>  The field delimiter for reading CSV data.
+                           Must be a single character (or tab, pipe,
+                           semicolon, space, \\t or \\xHH). (default: ,)
+";
+
+#[derive(Deserialize)]
+struct Args {
+    arg_input: Option<String>,
+    flag_sample_rows: usize,
+    flag_all_text: bool,
+    flag_schema: Option<String>,
+    flag_compression: String,
+    flag_row_group_size: usize,
+    flag_null_values: Option<String>,
+    flag_output: Option<String>,
+    flag_no_headers: bool,
+    flag_delimiter: Option<Delimiter>,
+}
+
+fn parse_type(name: &str) -> Option<Type> {
+    match &*name.to_lowercase() {
+        "boolean" => Some(Type::Boolean),
+        "int64" => Some(Type::Int64),
+        "double" => Some(Type::Double),
+        "string" => Some(Type::String),
+        _ => None,
+    }
+}
+
+fn type_name(ty: Type) -> &'static str {
+    match ty {
+        Type::Boolean => "boolean",
+        Type::Int64 => "int64",
+        Type::Double => "double",
+        Type::String => "string",
+    }
+}
+
+fn parse_bool(field: &[u8]) -> Option<bool> {
+    if field.eq_ignore_ascii_case(b"true") {
+        Some(true)
+    } else if field.eq_ignore_ascii_case(b"false") {
+        Some(false)
+    } else {
+        None
+    }
+}
+
+/// Returns the most specific type that both `ty` and `field` fit, where
+/// `None` means that no value has been seen yet.
+fn merge(ty: Option<Type>, field: &[u8]) -> Option<Type> {
+    let fits = |ty| match ty {
+        Type::Boolean => parse_bool(field).is_some(),
+        Type::Int64 => util::is_integer(field),
+        Type::Double => json::is_number(field),
+        Type::String => true,
+    };
+    match ty {
+        Some(ty) if fits(ty) => Some(ty),
+        Some(Type::Int64) if fits(Type::Double) => Some(Type::Double),
+        Some(_) => Some(Type::String),
+        None => {
+            [Type::Boolean, Type::Int64, Type::Double, Type::String]
+                .iter().cloned().find(|&ty| fits(ty))
+        }
+    }
+}
+
+pub fn run(argv: &[&str]) -> CliResult<()> {
+    let args: Args = util::get_args(USAGE, argv)?;
+    let compression = match &*args.flag_compression {
+        "snappy" => Compression::Snappy,
+        "none" => Compression::Uncompressed,
+        "zstd" => Compression::Zstd,
+        c => return fail_usage!(format!("Unknown compression codec '{}'.", c)),
+    };
+    if args.flag_row_group_size == 0 {
+        return fail_usage!("--row-group-size must be greater than 0.");
+    }
+    let nulls: Vec<Vec<u8>> = match args.flag_null_values {
+        None => vec![],
+        Some(ref list) => list.split(',').map(|s| s.as_bytes().to_vec())
+                              .collect(),
+    };
+    let is_null = |field: &[u8]| {
+        field.is_empty() || nulls.iter().any(|n| &**n == field)
+    };
+
+    let rconfig = Config::new(&args.arg_input)
+        .delimiter(args.flag_delimiter)
+        .no_headers(args.flag_no_headers);
+    let mut rdr = rconfig.reader()?;
+    let headers = rdr.byte_headers()?.clone();
+    let names: Vec<String> = (0..headers.len()).map(|i| {
+        if rconfig.no_headers {
+            format!("field_{}", i + 1)
+        } else {
+            String::from_utf8_lossy(&headers[i]).into_owned()
+        }
+    }).collect();
+    let pinned = match args.flag_schema {
+        None => HashMap::new(),
+        Some(ref path) => read_schema(path, &names)?,
+    };
+
+    let mut sample = vec![];
+    let mut record = csv::ByteRecord::new();
+    let mut inferred: Vec<Option<Type>> = vec![None; names.len()];
+    while sample.len() < args.flag_sample_rows
+            && rdr.read_byte_record(&mut record)? {
+        for (ty, field) in inferred.iter_mut().zip(&record) {
+            if !is_null(field) {
+                *ty = merge(*ty, field);
+            }
+        }
+        sample.push(record.clone());
+    }
+    let types: Vec<Type> = names.iter().zip(&inferred).map(|(name, ty)| {
+        match pinned.get(name) {
+            Some(&ty) => ty,
+            None if args.flag_all_text => Type::String,
+            None => ty.unwrap_or(Type::String),
+        }
+    }).collect();
+
+    let columns = names.iter().cloned().zip(types.iter().cloned()).collect();
+    let mut wtr = Writer::new(
+        io::BufWriter::new(Config::new(&args.flag_output).io_writer()?),
+        columns,
+        compression,
+        format!("xsv version {}", util::version()),
+    )?;
+    let mut data: Vec<ColumnData> =
+        types.iter().map(|&ty| ColumnData::new(ty)).collect();
+    let mut rows = 0;
+    let mut add = |wtr: &mut Writer<_>, record: &csv::ByteRecord|
+            -> CliResult<()> {
+        for (i, col) in data.iter_mut().enumerate() {
+            let field = record.get(i).unwrap_or(b"");
+            if is_null(field) {
+                col.defined.push(false);
+                continue;
+            }
+            col.defined.push(true);
+            let ok = match col.values {
+                Values::Boolean(ref mut v) => {
+                    parse_bool(field).map(|b| v.push(b)).is_some()
+                }
+                Values::Int64(ref mut v) => {
+                    str::from_utf8(field).ok()
+                        .and_then(|s| s.parse().ok())
+                        .map(|n| v.push(n))
+                        .is_some()
+                }
+                Values::Double(ref mut v) => {
+                    str::from_utf8(field).ok()
+                        .and_then(|s| s.parse().ok())
+                        .map(|n| v.push(n))
+                        .is_some()
+                }
+                Values::String(ref mut v) => {
+                    v.push(String::from_utf8_lossy(field).into_owned()
+                                                        .into_bytes());
+                    true
+                }
+            };
+            if !ok {
+                let line = record.position().map_or(0, |p| p.line());
+                return fail_data!(format!(
+                    "The value '{}' of column '{}' on line {} is not of \
+                     type {}. Use --schema or a larger --sample-rows to \
+                     choose another type.",
+                    String::from_utf8_lossy(field), names[i], line,
+                    type_name(types[i])));
+            }
+        }
+        rows += 1;
+        if rows == args.flag_row_group_size {
+            wtr.write_row_group(&data)?;
+            for col in &mut data {
+                col.clear();
+            }
+            rows = 0;
+        }
+        Ok(())
+    };
+    for record in &sample {
+        add(&mut wtr, record)?;
+    }
+    while rdr.read_byte_record(&mut record)? {
+        add(&mut wtr, &record)?;
+    }
+    if rows > 0 {
+        wtr.write_row_group(&data)?;
+    }
+    wtr.finish()?;
+    Ok(())
+}
+
+/// Reads the types of the columns given in a schema file.
+fn read_schema(path: &str, names: &[String]) -> CliResult<HashMap<String, Type>> {
+    let mut rdr = Config::new(&Some(path.to_owned())).reader()?;
+    let mut types = HashMap::new();
+    for row in rdr.records() {
+        let row = row?;
+        let (column, ty) = (row.get(0).unwrap_or(""), row.get(1).unwrap_or(""));
+        let ty = match parse_type(ty) {
+            None => {
+                return fail_usage!(format!(
+                    "Unknown type '{}' in {}. The types are boolean, int64, \
+                     double and string.", ty, path));
+            }
+            Some(ty) => ty,
+        };
+        if !names.iter().any(|n| n == column) {
+            return fail_usage!(format!(
+                "The column '{}' in {} doesn't exist.", column, path));
+        }
+        types.insert(column.to_owned(), ty);
+    }
+    Ok(types)
+}
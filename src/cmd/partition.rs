@@ -12,7 +12,7 @@ use config::{Config, Delimiter};
 use select::SelectColumns;
 use util::{self, FilenameTemplate};
 
-static USAGE: &'static str = "
+pub static USAGE: &'static str = "
 Partitions the given CSV data into chunks based on the value of a column
 
 The files are written to the output directory with filenames based on the
@@ -39,7 +39,8 @@ Common options:
                            as column names. Otherwise, the first row will
                            appear in all chunks as the header row.
     -d, --delimiter <arg>  The field delimiter for reading CSV data.
-                           Must be a single character. (default: ,)
+                           Must be a single character (or tab, pipe,
+                           semicolon, space, \\t or \\xHH). (default: ,)
 ";
 
 #[derive(Clone, Deserialize)]
@@ -84,7 +85,7 @@ impl Args {
         if select_cols.len() == 1 {
             Ok(select_cols[0])
         } else {
-            fail!("can only partition on one column")
+            fail_usage!("can only partition on one column")
         }
     }
 
@@ -0,0 +1,227 @@
+use std::collections::HashMap;
+use std::str;
+
+use csv;
+
+use CliResult;
+use config::{Config, Delimiter};
+use select::SelectColumns;
+use util;
+
+pub static USAGE: &'static str = "
+Reshapes CSV data from long to wide format. For example, pivoting
+
+    date,metric,value
+    mon,temp,20
+    mon,rain,5
+    tue,temp,22
+
+with '--index date --columns metric --values value' results in
+
+    date,rain,temp
+    mon,5,20
+    tue,,22
+
+The output has one record for every distinct value of the index columns (in
+the order in which they first appear) and one column for every distinct
+value of the --columns column (in sorted order, or in the order in which
+they first appear with --no-sort). Each cell holds the value of the values
+column for its index and column, or is empty if there is none.
+
+If there is more than one value for the same index and column, they are
+combined as chosen with --agg: 'first' or 'last' keeps one of them, 'sum'
+adds them up, 'count' writes their number, and 'error' (the default) stops
+with an error. With 'count', every cell holds the number of values.
+
+All output records are kept in memory. Since pivoting on a column with many
+distinct values (like a timestamp) is usually a mistake, a warning is
+written to stderr when there are more than --max-columns output columns.
+
+Usage:
+    xsv pivot [options] --index <cols> --columns <col> --values <col> [<input>]
+    xsv pivot --help
+
+pivot options:
+    -i, --index <cols>     The columns that identify an output record. See
+                           'xsv select -h' for the full syntax.
+    -c, --columns <col>    The column whose values become the output columns.
+    -v, --values <col>     The column whose values fill the output cells.
+    --agg <func>           How to combine several values for the same cell:
+                           'first', 'last', 'sum', 'count' or 'error'.
+                           [default: error]
+    --no-sort              Order the output columns by first appearance
+                           instead of sorting them.
+    --max-columns <n>      Warn if there are more than <n> output columns.
+                           [default: 1000]
+
+Common options:
+    -h, --help             Display this message
+    -o, --output <file>    Write output to <file> instead of stdout.
+    -d, --delimiter <arg>  The field delimiter for reading CSV data.
+                           Must be a single character (or tab, pipe,
+                           semicolon, space, \\t or \\xHH). (default: ,)
+";
+
+#[derive(Deserialize)]
+struct Args {
+    arg_input: Option<String>,
+    flag_index: SelectColumns,
+    flag_columns: SelectColumns,
+    flag_values: SelectColumns,
+    flag_agg: Agg,
+    flag_no_sort: bool,
+    flag_max_columns: usize,
+    flag_output: Option<String>,
+    flag_delimiter: Option<Delimiter>,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+enum Agg {
+    First,
+    Last,
+    Sum,
+    Count,
+    Error,
+}
+
+enum Cell {
+    Value(Vec<u8>),
+    Sum(f64),
+    Count(u64),
+}
+
+impl Cell {
+    fn to_bytes(&self) -> Vec<u8> {
+        match *self {
+            Cell::Value(ref v) => v.clone(),
+            Cell::Sum(n) => n.to_string().into_bytes(),
+            Cell::Count(n) => n.to_string().into_bytes(),
+        }
+    }
+}
+
+/// The index key of an output record along with its cells, by column.
+type Row = (Vec<Vec<u8>>, HashMap<usize, Cell>);
+
+pub fn run(argv: &[&str]) -> CliResult<()> {
+    let args: Args = util::get_args(USAGE, argv)?;
+    let rconfig = Config::new(&args.arg_input)
+        .delimiter(args.flag_delimiter);
+
+    let mut rdr = rconfig.reader()?;
+    let headers = rdr.byte_headers()?.clone();
+    let index_sel = args.flag_index.selection(&headers, true)?;
+    let single = |sel: &SelectColumns, flag: &str| -> CliResult<usize> {
+        let sel = sel.selection(&headers, true)?;
+        if sel.len() != 1 {
+            return fail_usage!(format!(
+                "{} must select exactly one column.", flag));
+        }
+        Ok(sel[0])
+    };
+    let column = single(&args.flag_columns, "--columns")?;
+    let value = single(&args.flag_values, "--values")?;
+
+    let mut columns: Vec<Vec<u8>> = vec![];
+    let mut column_pos: HashMap<Vec<u8>, usize> = HashMap::new();
+    let mut rows: Vec<Row> = vec![];
+    let mut row_pos: HashMap<Vec<Vec<u8>>, usize> = HashMap::new();
+    let mut warned = false;
+    let mut record = csv::ByteRecord::new();
+    while rdr.read_byte_record(&mut record)? {
+        let key: Vec<Vec<u8>> =
+            index_sel.select(&record).map(|f| f.to_vec()).collect();
+        let col = record.get(column).unwrap_or(b"");
+        let val = record.get(value).unwrap_or(b"");
+
+        let c = match column_pos.get(col) {
+            Some(&c) => c,
+            None => {
+                column_pos.insert(col.to_vec(), columns.len());
+                columns.push(col.to_vec());
+                if columns.len() > args.flag_max_columns && !warned {
+                    werr!("warning: more than {} distinct values in the \
+                           --columns column", args.flag_max_columns);
+                    warned = true;
+                }
+                columns.len() - 1
+            }
+        };
+        let r = match row_pos.get(&key) {
+            Some(&r) => r,
+            None => {
+                row_pos.insert(key.clone(), rows.len());
+                rows.push((key, HashMap::new()));
+                rows.len() - 1
+            }
+        };
+        let line = record.position().map_or(0, |p| p.line());
+        let cells = &mut rows[r].1;
+        if let Some(cell) = cells.get_mut(&c) {
+            match (args.flag_agg, cell) {
+                (Agg::First, _) => {}
+                (Agg::Last, cell) => *cell = Cell::Value(val.to_vec()),
+                (Agg::Sum, &mut Cell::Sum(ref mut n)) => {
+                    *n += parse_number(val, line)?;
+                }
+                (Agg::Count, &mut Cell::Count(ref mut n)) => *n += 1,
+                _ => {
+                    return fail_data!(format!(
+                        "Line {} has the same index and column as an \
+                         earlier record. Use --agg to combine their \
+                         values.", line));
+                }
+            }
+            continue;
+        }
+        let cell = match args.flag_agg {
+            Agg::Sum => Cell::Sum(parse_number(val, line)?),
+            Agg::Count => Cell::Count(1),
+            _ => Cell::Value(val.to_vec()),
+        };
+        cells.insert(c, cell);
+    }
+
+    // The order in which the columns are written.
+    let mut order: Vec<usize> = (0..columns.len()).collect();
+    if !args.flag_no_sort {
+        order.sort_by(|&a, &b| columns[a].cmp(&columns[b]));
+    }
+    let mut wtr = Config::new(&args.flag_output).writer()?;
+    let mut out = csv::ByteRecord::new();
+    for field in index_sel.select(&headers) {
+        out.push_field(field);
+    }
+    for &c in &order {
+        out.push_field(&columns[c]);
+    }
+    wtr.write_byte_record(&out)?;
+    for &(ref key, ref cells) in &rows {
+        out.clear();
+        for field in key {
+            out.push_field(field);
+        }
+        for c in &order {
+            match cells.get(c) {
+                Some(cell) => out.push_field(&cell.to_bytes()),
+                None => out.push_field(b""),
+            }
+        }
+        wtr.write_byte_record(&out)?;
+    }
+    Ok(wtr.flush()?)
+}
+
+/// Parses a value to sum up. Empty values count as zero.
+fn parse_number(val: &[u8], line: u64) -> CliResult<f64> {
+    if val.is_empty() {
+        return Ok(0.0);
+    }
+    match str::from_utf8(val).ok().and_then(|s| s.trim().parse().ok()) {
+        Some(n) => Ok(n),
+        None => fail_data!(format!(
+            "Cannot sum the value '{}' on line {}, which is not a number.",
+            String::from_utf8_lossy(val), line)),
+    }
+}
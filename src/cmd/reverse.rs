@@ -1,52 +1,144 @@
+use csv;
+
 use CliResult;
-use config::{Config, Delimiter};
+use config::{Config, DataFile, Delimiter};
+use index::{IndexFile, Indexed, MultiIndexed};
 use util;
 
-static USAGE: &'static str = "
+pub static USAGE: &'static str = "
 Reverses rows of CSV data.
 
-Useful for cases when there is no column that can be used for sorting in reverse order,
-or when keys are not unique and order of rows with the same key needs to be preserved.
+Useful for cases when there is no column that can be used for sorting in
+reverse order, or when keys are not unique and order of rows with the same
+key needs to be preserved.
 
-Note that this requires reading all of the CSV data into memory.
+If the CSV data has an index (created with 'xsv index'), the records are
+read from the last to the first, so that only one record is kept in memory
+at a time. Otherwise, this requires reading all of the CSV data into
+memory. With --memcheck, the command fails instead of reading more data into
+memory than is available (where the operating system reports it).
 
 Usage:
     xsv reverse [options] [<input>]
 
+reverse options:
+    --memcheck             When there is no index, fail if the CSV data
+                           doesn't fit into the available memory.
+
 Common options:
     -h, --help             Display this message
     -o, --output <file>    Write output to <file> instead of stdout.
     -n, --no-headers       When set, the first row will not be interpreted
-                           as headers. Namely, it will be reversed with the rest
-                           of the rows. Otherwise, the first row will always
-                           appear as the header row in the output.
+                           as headers. Namely, it will be reversed with the
+                           rest of the rows. Otherwise, the first row will
+                           always appear as the header row in the output.
     -d, --delimiter <arg>  The field delimiter for reading CSV data.
-                           Must be a single character. (default: ,)
+                           Must be a single character (or tab, pipe,
+                           semicolon, space, \\t or \\xHH). (default: ,)
+    --auto-index           When the index of the CSV data is stale, rebuild
+                           it instead of reporting an error.
 ";
 
 #[derive(Deserialize)]
 struct Args {
     arg_input: Option<String>,
+    flag_memcheck: bool,
     flag_output: Option<String>,
     flag_no_headers: bool,
     flag_delimiter: Option<Delimiter>,
+    flag_auto_index: bool,
 }
 
 pub fn run(argv: &[&str]) -> CliResult<()> {
     let args: Args = util::get_args(USAGE, argv)?;
-    let rconfig = Config::new(&args.arg_input)
-        .delimiter(args.flag_delimiter)
-        .no_headers(args.flag_no_headers);
+    if let Some(midx) = args.rconfig().multi_indexed()? {
+        return args.with_multi_index(midx);
+    }
+    match args.rconfig().indexed()? {
+        None => args.no_index(),
+        Some(idxed) => args.with_index(idxed),
+    }
+}
+
+impl Args {
+    fn no_index(&self) -> CliResult<()> {
+        let rconfig = self.rconfig();
+        let mut rdr = rconfig.reader()?;
+        let limit = if self.flag_memcheck {
+            util::available_memory()
+        } else {
+            None
+        };
+
+        let mut all = vec![];
+        let mut size = 0u64;
+        let mut record = csv::ByteRecord::new();
+        while rdr.read_byte_record(&mut record)? {
+            size += record.as_slice().len() as u64
+                    + 8 * record.len() as u64;
+            if let Some(limit) = limit {
+                if size > limit {
+                    return fail!(format!(
+                        "The CSV data doesn't fit into the available memory \
+                         ({} bytes). Please create an index with 'xsv index' \
+                         to reverse it without reading it into memory.",
+                        limit));
+                }
+            }
+            all.push(record.clone());
+        }
+        all.reverse();
 
-    let mut rdr = rconfig.reader()?;
+        let mut wtr = self.wconfig().writer()?;
+        rconfig.write_headers(&mut rdr, &mut wtr)?;
+        for r in all.into_iter() {
+            wtr.write_byte_record(&r)?;
+        }
+        Ok(wtr.flush()?)
+    }
+
+    fn with_index(
+        &self,
+        mut idx: Indexed<DataFile, IndexFile>,
+    ) -> CliResult<()> {
+        let mut wtr = self.wconfig().writer()?;
+        self.rconfig().write_headers(&mut *idx, &mut wtr)?;
+
+        let mut record = csv::ByteRecord::new();
+        for i in (0..idx.count()).rev() {
+            idx.seek(i)?;
+            if !idx.read_byte_record(&mut record)? {
+                break;
+            }
+            wtr.write_byte_record(&record)?;
+        }
+        Ok(wtr.flush()?)
+    }
 
-    let mut all = rdr.byte_records().collect::<Result<Vec<_>, _>>()?;
-    all.reverse();
+    fn with_multi_index(&self, mut midx: MultiIndexed) -> CliResult<()> {
+        let mut wtr = self.wconfig().writer()?;
+        if !self.flag_no_headers && !midx.byte_headers().is_empty() {
+            wtr.write_record(midx.byte_headers())?;
+        }
+        let mut record = csv::ByteRecord::new();
+        for i in (0..midx.count()).rev() {
+            midx.seek(i)?;
+            if !midx.read_byte_record(&mut record)? {
+                break;
+            }
+            wtr.write_byte_record(&record)?;
+        }
+        Ok(wtr.flush()?)
+    }
+
+    fn rconfig(&self) -> Config {
+        Config::new(&self.arg_input)
+            .delimiter(self.flag_delimiter)
+            .no_headers(self.flag_no_headers)
+            .auto_index(self.flag_auto_index)
+    }
 
-    let mut wtr = Config::new(&args.flag_output).writer()?;
-    rconfig.write_headers(&mut rdr, &mut wtr)?;
-    for r in all.into_iter() {
-        wtr.write_byte_record(&r)?;
+    fn wconfig(&self) -> Config {
+        Config::new(&self.flag_output)
     }
-    Ok(wtr.flush()?)
 }
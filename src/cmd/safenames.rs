@@ -0,0 +1,159 @@
+use std::collections::HashSet;
+
+use csv;
+
+use CliResult;
+use config::{Config, Delimiter};
+use util;
+
+pub static USAGE: &'static str = "
+Rewrites the header row so that every column name is a safe identifier, e.g.,
+for loading the data into a database. The records are passed through as
+they are.
+
+The names are made lowercase, and every run of characters that aren't
+letters or digits is replaced by a single underscore, with underscores at
+the start and the end removed. Names that start with a digit are prefixed
+(with '_' by default), and empty names become 'column'. If the same name
+occurs more than once, a number is appended to the later ones, as in
+'notes', 'notes_2'.
+
+With --ascii, accented letters are replaced by ASCII letters (e.g., 'Región'
+becomes 'region') and other letters that aren't ASCII are treated like any
+other character that isn't allowed.
+
+Usage:
+    xsv safenames [options] [<input>]
+    xsv safenames --help
+
+safenames options:
+    --ascii                  Only use ASCII letters in the names.
+    --prefix <prefix>        The prefix of names that would start with a
+                             digit. [default: _]
+    --report                 Write the old and the new names as CSV instead
+                             of the data.
+    --mapping-output <file>  Also write the old and the new names as CSV
+                             to <file>.
+
+Common options:
+    -h, --help               Display this message
+    -o, --output <file>      Write output to <file> instead of stdout.
+    -n, --no-headers         Not supported, since there are no names
+                             to rewrite.
+    -d, --delimiter <arg>    The field delimiter for reading CSV data.
+                             Must be a single character (or tab, pipe,
+                             semicolon, space, \\t or \\xHH). (default: ,)
+";
+
+#[derive(Deserialize)]
+struct Args {
+    arg_input: Option<String>,
+    flag_ascii: bool,
+    flag_prefix: String,
+    flag_report: bool,
+    flag_mapping_output: Option<String>,
+    flag_output: Option<String>,
+    flag_no_headers: bool,
+    flag_delimiter: Option<Delimiter>,
+}
+
+/// Returns the ASCII letters for an accented letter.
+fn transliterate(c: char) -> Option<&'static str> {
+    Some(match c {
+        'à' | 'á' | 'â' | 'ã' | 'ä' | 'å' | 'ā' | 'ă' | 'ą' => "a",
+        'æ' => "ae",
+        'ç' | 'ć' | 'č' => "c",
+        'ď' | 'đ' | 'ð' => "d",
+        'è' | 'é' | 'ê' | 'ë' | 'ē' | 'ė' | 'ę' | 'ě' => "e",
+        'ğ' => "g",
+        'ì' | 'í' | 'î' | 'ï' | 'ī' | 'į' | 'ı' => "i",
+        'ł' | 'ľ' | 'ĺ' => "l",
+        'ñ' | 'ń' | 'ň' => "n",
+        'ò' | 'ó' | 'ô' | 'õ' | 'ö' | 'ø' | 'ō' | 'ő' => "o",
+        'œ' => "oe",
+        'ř' | 'ŕ' => "r",
+        'ś' | 'š' | 'ş' => "s",
+        'ß' => "ss",
+        'ť' | 'ţ' => "t",
+        'þ' => "th",
+        'ù' | 'ú' | 'û' | 'ü' | 'ū' | 'ů' | 'ű' | 'ų' => "u",
+        'ý' | 'ÿ' => "y",
+        'ź' | 'ż' | 'ž' => "z",
+        _ => return None,
+    })
+}
+
+fn safe_name(name: &str, ascii: bool, prefix: &str) -> String {
+    let mut safe = String::with_capacity(name.len());
+    let mut underscore = false;
+    for c in name.chars().flat_map(char::to_lowercase) {
+        let letters = if ascii { transliterate(c) } else { None };
+        if c.is_ascii_alphanumeric() || (!ascii && c.is_alphanumeric()) {
+            safe.push(c);
+            underscore = false;
+        } else if let Some(letters) = letters {
+            safe.push_str(letters);
+            underscore = false;
+        } else if !underscore {
+            safe.push('_');
+            underscore = true;
+        }
+    }
+    let safe = safe.trim_matches('_');
+    if safe.is_empty() {
+        "column".to_owned()
+    } else if safe.starts_with(|c: char| c.is_numeric()) {
+        format!("{}{}", prefix, safe)
+    } else {
+        safe.to_owned()
+    }
+}
+
+pub fn run(argv: &[&str]) -> CliResult<()> {
+    let args: Args = util::get_args(USAGE, argv)?;
+    if args.flag_no_headers {
+        return fail_usage!("safenames rewrites the header row, so it cannot \
+                            be used with --no-headers.");
+    }
+    let rconfig = Config::new(&args.arg_input)
+        .delimiter(args.flag_delimiter);
+    let mut rdr = rconfig.reader()?;
+    let headers = rdr.byte_headers()?.clone();
+
+    let mut used = HashSet::new();
+    let mut safe = vec![];
+    for name in &headers {
+        let base = safe_name(&String::from_utf8_lossy(name), args.flag_ascii,
+                             &args.flag_prefix);
+        let mut name = base.clone();
+        let mut n = 1;
+        while used.contains(&name) {
+            n += 1;
+            name = format!("{}_{}", base, n);
+        }
+        used.insert(name.clone());
+        safe.push(name);
+    }
+
+    let write_mapping = |wtr: &mut csv::Writer<_>| -> CliResult<()> {
+        wtr.write_record(&["old", "new"])?;
+        for (old, new) in headers.iter().zip(&safe) {
+            wtr.write_record(&[old, new.as_bytes()])?;
+        }
+        Ok(wtr.flush()?)
+    };
+    if let Some(ref path) = args.flag_mapping_output {
+        write_mapping(&mut Config::new(&Some(path.clone())).writer()?)?;
+    }
+    let mut wtr = Config::new(&args.flag_output).writer()?;
+    if args.flag_report {
+        return write_mapping(&mut wtr);
+    }
+
+    wtr.write_record(&safe)?;
+    let mut record = csv::ByteRecord::new();
+    while rdr.read_byte_record(&mut record)? {
+        wtr.write_byte_record(&record)?;
+    }
+    Ok(wtr.flush()?)
+}
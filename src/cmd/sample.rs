@@ -5,12 +5,12 @@ use csv;
 use rand::{self, Rng, SeedableRng};
 use rand::rngs::StdRng;
 
-use CliResult;
+use {CliError, CliResult};
 use config::{Config, Delimiter};
-use index::Indexed;
+use index::{Indexed, MultiIndexed};
 use util;
 
-static USAGE: &'static str = "
+pub static USAGE: &'static str = "
 Randomly samples CSV data uniformly using memory proportional to the size of
 the sample.
 
@@ -25,6 +25,9 @@ once, which is necessary to provide a uniform random sample. If you wish to
 limit the number of records visited, use the 'xsv slice' command to pipe into
 'xsv sample'.
 
+The input may also be a multi-index created with 'xsv index --multi', in
+which case the sample is taken from the records of all of its shards.
+
 Usage:
     xsv sample [options] <sample-size> [<input>]
     xsv sample --help
@@ -40,7 +43,10 @@ Common options:
                            first row is the header row and will always appear
                            in the output.)
     -d, --delimiter <arg>  The field delimiter for reading CSV data.
-                           Must be a single character. (default: ,)
+                           Must be a single character (or tab, pipe,
+                           semicolon, space, \\t or \\xHH). (default: ,)
+    --auto-index           When the index of the CSV data is stale, rebuild
+                           it instead of reporting an error.
 ";
 
 #[derive(Deserialize)]
@@ -50,6 +56,7 @@ struct Args {
     flag_output: Option<String>,
     flag_no_headers: bool,
     flag_delimiter: Option<Delimiter>,
+    flag_auto_index: bool,
     flag_seed: Option<usize>,
 }
 
@@ -57,10 +64,20 @@ pub fn run(argv: &[&str]) -> CliResult<()> {
     let args: Args = util::get_args(USAGE, argv)?;
     let rconfig = Config::new(&args.arg_input)
         .delimiter(args.flag_delimiter)
-        .no_headers(args.flag_no_headers);
+        .no_headers(args.flag_no_headers)
+        .auto_index(args.flag_auto_index);
     let sample_size = args.arg_sample_size;
 
     let mut wtr = Config::new(&args.flag_output).writer()?;
+    if let Some(midx) = rconfig.multi_indexed()? {
+        if !rconfig.no_headers && !midx.byte_headers().is_empty() {
+            wtr.write_record(midx.byte_headers())?;
+        }
+        for row in sample_multi(midx, sample_size, args.flag_seed)? {
+            wtr.write_byte_record(&row)?;
+        }
+        return Ok(wtr.flush()?);
+    }
     let sampled = match rconfig.indexed()? {
         Some(mut idx) => {
             if do_random_access(sample_size, idx.count()) {
@@ -69,13 +86,13 @@ pub fn run(argv: &[&str]) -> CliResult<()> {
             } else {
                 let mut rdr = rconfig.reader()?;
                 rconfig.write_headers(&mut rdr, &mut wtr)?;
-                sample_reservoir(&mut rdr, sample_size, args.flag_seed)?
+                sample_reservoir(rdr.byte_records(), sample_size, args.flag_seed)?
             }
         }
         _ => {
             let mut rdr = rconfig.reader()?;
             rconfig.write_headers(&mut rdr, &mut wtr)?;
-            sample_reservoir(&mut rdr, sample_size, args.flag_seed)?
+            sample_reservoir(rdr.byte_records(), sample_size, args.flag_seed)?
         }
     };
     for row in sampled.into_iter() {
@@ -102,15 +119,52 @@ where R: io::Read + io::Seek, I: io::Read + io::Seek
     Ok(sampled)
 }
 
-fn sample_reservoir<R: io::Read>(
-    rdr: &mut csv::Reader<R>,
+/// Samples from all shards of a multi-index, with random access if the
+/// sample is small enough.
+fn sample_multi(
+    mut midx: MultiIndexed,
     sample_size: u64,
-    seed: Option<usize>
+    seed: Option<usize>,
 ) -> CliResult<Vec<csv::ByteRecord>> {
+    let total = midx.count();
+    let mut record = csv::ByteRecord::new();
+    if do_random_access(sample_size, total) {
+        let mut all_indices = (0..total).collect::<Vec<_>>();
+        let mut rng = ::rand::thread_rng();
+        rng.shuffle(&mut *all_indices);
+
+        let mut sampled = Vec::with_capacity(sample_size as usize);
+        for i in all_indices.into_iter().take(sample_size as usize) {
+            midx.seek(i)?;
+            midx.read_byte_record(&mut record)?;
+            sampled.push(record.clone());
+        }
+        return Ok(sampled);
+    }
+    if total > 0 {
+        midx.seek(0)?;
+    }
+    let records = ::std::iter::from_fn(|| {
+        match midx.read_byte_record(&mut record) {
+            Ok(true) => Some(Ok(record.clone())),
+            Ok(false) => None,
+            Err(err) => Some(Err(err)),
+        }
+    });
+    sample_reservoir(records, sample_size, seed)
+}
+
+fn sample_reservoir<I, E>(
+    records: I,
+    sample_size: u64,
+    seed: Option<usize>
+) -> CliResult<Vec<csv::ByteRecord>>
+where I: Iterator<Item=Result<csv::ByteRecord, E>>, CliError: From<E>
+{
     // The following algorithm has been adapted from:
     // https://en.wikipedia.org/wiki/Reservoir_sampling
     let mut reservoir = Vec::with_capacity(sample_size as usize);
-    let mut records = rdr.byte_records().enumerate();
+    let mut records = records.enumerate();
     for (_, row) in records.by_ref().take(reservoir.capacity()) {
         reservoir.push(row?);
     }
@@ -0,0 +1,463 @@
+use std::collections::BTreeSet;
+use std::io::{self, Write};
+
+use csv;
+
+use CliResult;
+use config::{Config, Delimiter};
+use date::DateTime;
+use json::{self, Value};
+use util;
+
+pub static USAGE: &'static str = "
+Infers a schema from CSV data and writes it as a JSON Schema document.
+
+For every column, the schema has its type, whether it has empty values
+(which makes it nullable), the smallest and largest value of numbers and
+the shortest and longest length of strings. The types are:
+
+    integer    Whole numbers, like 42 or -7.
+    number     Any numbers, like 1.5 or 2e10.
+    boolean    true or false, in any case.
+    date       Dates like 2018-01-31.
+    date-time  Dates with times like 2018-01-31T14:05:00.
+    string     Anything else.
+
+If a column has values of different types, its type is widened to one that
+allows all of them, which is number for integers and numbers and string
+otherwise. With --strict-types, this is an error instead.
+
+The schema can be used to check other CSV data with 'xsv val --schema', so
+that a schema can be inferred from a file known to be good and be used to
+validate files later on.
+
+Usage:
+    xsv schema [options] [<input>]
+    xsv schema --help
+
+schema options:
+    --sample-rows <n>      Only look at the first <n> records.
+    --enum-threshold <n>   List the allowed values of columns which have
+                           fewer than <n> distinct values. [default: 0]
+    --strict-types         Fail if a column has values of different types.
+
+Common options:
+    -h, --help             Display this message
+    -o, --output <file>    Write output to <file> instead of stdout.
+    -d, --delimiter <arg>  The field delimiter for reading CSV data.
+                           Must be a single character (or tab, pipe,
+                           semicolon, space, \\t or \\xHH). (default: ,)
+";
+
+#[derive(Deserialize)]
+struct Args {
+    arg_input: Option<String>,
+    flag_sample_rows: Option<u64>,
+    flag_enum_threshold: usize,
+    flag_strict_types: bool,
+    flag_output: Option<String>,
+    flag_delimiter: Option<Delimiter>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Type {
+    Integer,
+    Number,
+    Boolean,
+    Date,
+    DateTime,
+    String,
+}
+
+impl Type {
+    /// Returns the most specific type of `s`.
+    fn of(s: &str) -> Type {
+        if s.parse::<i64>().is_ok() {
+            Type::Integer
+        } else if s.parse::<f64>().map(|n| n.is_finite()).unwrap_or(false) {
+            Type::Number
+        } else if s.eq_ignore_ascii_case("true")
+                  || s.eq_ignore_ascii_case("false") {
+            Type::Boolean
+        } else if DateTime::parse(s, "%F").is_some() {
+            Type::Date
+        } else if DateTime::parse(s, "%FT%T").is_some()
+                  || DateTime::parse(s, "%F %T").is_some() {
+            Type::DateTime
+        } else {
+            Type::String
+        }
+    }
+
+    /// Returns whether a value of type `t` is allowed in a column of this
+    /// type.
+    fn allows(self, t: Type) -> bool {
+        self == t || self == Type::String
+            || (self == Type::Number && t == Type::Integer)
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Type::Integer => "integer",
+            Type::Number => "number",
+            Type::Boolean => "boolean",
+            Type::Date => "date",
+            Type::DateTime => "date-time",
+            Type::String => "string",
+        }
+    }
+
+    /// The JSON Schema type, along with the format for dates.
+    fn json(self) -> (&'static str, Option<&'static str>) {
+        match self {
+            Type::Date => ("string", Some("date")),
+            Type::DateTime => ("string", Some("date-time")),
+            t => (t.name(), None),
+        }
+    }
+
+    fn from_json(t: &str, format: Option<&str>) -> Option<Type> {
+        Some(match (t, format) {
+            ("integer", _) => Type::Integer,
+            ("number", _) => Type::Number,
+            ("boolean", _) => Type::Boolean,
+            ("string", Some("date")) => Type::Date,
+            ("string", Some("date-time")) => Type::DateTime,
+            ("string", _) => Type::String,
+            _ => return None,
+        })
+    }
+}
+
+/// The schema of a column.
+#[derive(Clone, Debug)]
+pub struct Column {
+    pub name: String,
+    pub kind: Type,
+    pub nullable: bool,
+    pub required: bool,
+    pub minimum: Option<f64>,
+    pub maximum: Option<f64>,
+    pub min_length: Option<usize>,
+    pub max_length: Option<usize>,
+    pub values: Option<Vec<String>>,
+}
+
+impl Column {
+    /// Checks that `value` is allowed in this column, or describes why it
+    /// isn't.
+    pub fn check(&self, value: &[u8]) -> Result<(), String> {
+        let value = String::from_utf8_lossy(value);
+        if value.is_empty() {
+            return if self.nullable {
+                Ok(())
+            } else {
+                Err("the value is empty but the column isn't nullable"
+                    .to_owned())
+            };
+        }
+        if !self.kind.allows(Type::of(&value)) {
+            return Err(format!("the value is not of type {}",
+                               self.kind.name()));
+        }
+        if let Ok(n) = value.parse::<f64>() {
+            if self.minimum.map_or(false, |min| n < min) {
+                return Err(format!("the value is less than the minimum {}",
+                                   self.minimum.unwrap()));
+            }
+            if self.maximum.map_or(false, |max| n > max) {
+                return Err(format!("the value is greater than the maximum {}",
+                                   self.maximum.unwrap()));
+            }
+        }
+        let len = value.chars().count();
+        if self.min_length.map_or(false, |min| len < min) {
+            return Err(format!("the value is shorter than {} characters",
+                               self.min_length.unwrap()));
+        }
+        if self.max_length.map_or(false, |max| len > max) {
+            return Err(format!("the value is longer than {} characters",
+                               self.max_length.unwrap()));
+        }
+        if let Some(ref values) = self.values {
+            if !values.iter().any(|v| *v == value) {
+                return Err("the value is not one of the allowed values"
+                           .to_owned());
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A schema of CSV data, which is read from and written as a JSON Schema
+/// document that describes every record as an object.
+#[derive(Clone, Debug)]
+pub struct Schema {
+    pub columns: Vec<Column>,
+    /// Whether columns that aren't in the schema are allowed.
+    pub additional: bool,
+}
+
+impl Schema {
+    pub fn to_json(&self) -> Value {
+        let number = |n: f64| Value::Number(n.to_string());
+        let mut properties = vec![];
+        for c in &self.columns {
+            let mut p = vec![];
+            let (t, format) = c.kind.json();
+            let t = Value::String(t.to_owned());
+            p.push(("type".to_owned(), if c.nullable {
+                Value::Array(vec![t, Value::String("null".to_owned())])
+            } else {
+                t
+            }));
+            if let Some(format) = format {
+                p.push(("format".to_owned(), Value::String(format.to_owned())));
+            }
+            if let Some(n) = c.minimum {
+                p.push(("minimum".to_owned(), number(n)));
+            }
+            if let Some(n) = c.maximum {
+                p.push(("maximum".to_owned(), number(n)));
+            }
+            if let Some(n) = c.min_length {
+                p.push(("minLength".to_owned(), number(n as f64)));
+            }
+            if let Some(n) = c.max_length {
+                p.push(("maxLength".to_owned(), number(n as f64)));
+            }
+            if let Some(ref values) = c.values {
+                let numeric = c.kind == Type::Integer || c.kind == Type::Number;
+                p.push(("enum".to_owned(), Value::Array(values.iter().map(|v| {
+                    if numeric && json::is_number(v.as_bytes()) {
+                        Value::Number(v.clone())
+                    } else {
+                        Value::String(v.clone())
+                    }
+                }).collect())));
+            }
+            properties.push((c.name.clone(), Value::Object(p)));
+        }
+        let required = self.columns.iter().filter(|c| c.required)
+            .map(|c| Value::String(c.name.clone())).collect();
+        Value::Object(vec![
+            ("$schema".to_owned(), Value::String(
+                "https://json-schema.org/draft/2020-12/schema".to_owned())),
+            ("type".to_owned(), Value::String("object".to_owned())),
+            ("properties".to_owned(), Value::Object(properties)),
+            ("required".to_owned(), Value::Array(required)),
+            ("additionalProperties".to_owned(), Value::Bool(self.additional)),
+        ])
+    }
+
+    /// Reads a schema from a JSON Schema document. Keywords that aren't
+    /// used by `xsv schema` are ignored.
+    pub fn from_json(doc: &Value) -> Result<Schema, String> {
+        let properties = match doc.get("properties") {
+            Some(&Value::Object(ref properties)) => properties,
+            _ => return Err("the schema has no 'properties' object".to_owned()),
+        };
+        let required: Vec<&str> = match doc.get("required") {
+            Some(&Value::Array(ref names)) => names.iter().filter_map(|n| {
+                match *n {
+                    Value::String(ref n) => Some(&**n),
+                    _ => None,
+                }
+            }).collect(),
+            _ => vec![],
+        };
+        let mut columns = vec![];
+        for &(ref name, ref p) in properties {
+            let err = |msg: &str| format!("column '{}': {}", name, msg);
+            let (t, nullable) = match p.get("type") {
+                Some(&Value::String(ref t)) => (&**t, false),
+                Some(&Value::Array(ref ts)) => {
+                    let mut t = None;
+                    let mut nullable = false;
+                    for v in ts {
+                        match *v {
+                            Value::String(ref s) if s == "null" => {
+                                nullable = true
+                            }
+                            Value::String(ref s) if t.is_none() => t = Some(&**s),
+                            _ => return Err(err("unsupported 'type'")),
+                        }
+                    }
+                    (t.unwrap_or("string"), nullable)
+                }
+                None => ("string", false),
+                Some(_) => return Err(err("unsupported 'type'")),
+            };
+            let format = match p.get("format") {
+                Some(&Value::String(ref f)) => Some(&**f),
+                _ => None,
+            };
+            let kind = match Type::from_json(t, format) {
+                Some(kind) => kind,
+                None => return Err(err(&format!("unsupported type '{}'", t))),
+            };
+            let number = |key: &str| -> Result<Option<f64>, String> {
+                match p.get(key) {
+                    None => Ok(None),
+                    Some(&Value::Number(ref n)) => Ok(n.parse().ok()),
+                    Some(_) => Err(err(&format!("'{}' must be a number", key))),
+                }
+            };
+            let values = match p.get("enum") {
+                None => None,
+                Some(&Value::Array(ref vals)) => Some(vals.iter().map(|v| {
+                    match *v {
+                        Value::String(ref s) | Value::Number(ref s) => s.clone(),
+                        Value::Bool(b) => b.to_string(),
+                        _ => String::new(),
+                    }
+                }).collect()),
+                Some(_) => return Err(err("'enum' must be an array")),
+            };
+            columns.push(Column {
+                name: name.clone(),
+                kind: kind,
+                nullable: nullable,
+                required: required.contains(&&**name),
+                minimum: number("minimum")?,
+                maximum: number("maximum")?,
+                min_length: number("minLength")?.map(|n| n as usize),
+                max_length: number("maxLength")?.map(|n| n as usize),
+                values: values,
+            });
+        }
+        let additional = match doc.get("additionalProperties") {
+            Some(&Value::Bool(false)) => false,
+            _ => true,
+        };
+        Ok(Schema { columns: columns, additional: additional })
+    }
+}
+
+/// The statistics of a column that are collected to infer its schema.
+struct Infer {
+    /// The types that were seen, with the first value and line of each.
+    types: Vec<(Type, String, u64)>,
+    nullable: bool,
+    minimum: Option<f64>,
+    maximum: Option<f64>,
+    min_length: Option<usize>,
+    max_length: Option<usize>,
+    /// The distinct values, until there are too many of them.
+    values: Option<BTreeSet<String>>,
+}
+
+impl Infer {
+    fn new(enum_threshold: usize) -> Infer {
+        Infer {
+            types: vec![],
+            nullable: false,
+            minimum: None,
+            maximum: None,
+            min_length: None,
+            max_length: None,
+            values: if enum_threshold > 0 { Some(BTreeSet::new()) } else { None },
+        }
+    }
+
+    fn add(&mut self, value: &[u8], line: u64, enum_threshold: usize) {
+        let value = String::from_utf8_lossy(value);
+        if value.is_empty() {
+            self.nullable = true;
+            return;
+        }
+        let t = Type::of(&value);
+        if !self.types.iter().any(|s| s.0 == t) {
+            self.types.push((t, value.clone().into_owned(), line));
+        }
+        if t == Type::Integer || t == Type::Number {
+            let n: f64 = value.parse().unwrap();
+            self.minimum = Some(self.minimum.map_or(n, |m| m.min(n)));
+            self.maximum = Some(self.maximum.map_or(n, |m| m.max(n)));
+        }
+        let len = value.chars().count();
+        self.min_length = Some(self.min_length.map_or(len, |m| m.min(len)));
+        self.max_length = Some(self.max_length.map_or(len, |m| m.max(len)));
+        if let Some(mut values) = self.values.take() {
+            values.insert(value.into_owned());
+            if values.len() < enum_threshold {
+                self.values = Some(values);
+            }
+        }
+    }
+
+    fn finish(self, name: &str, strict: bool) -> Result<Column, String> {
+        let mut types: Vec<Type> = self.types.iter().map(|t| t.0).collect();
+        types.sort();
+        let kind = match &*types {
+            [] => Type::String,
+            [t] => *t,
+            [Type::Integer, Type::Number] => Type::Number,
+            _ if strict => {
+                let (a, b) = (&self.types[0], &self.types[1]);
+                return Err(format!(
+                    "The column '{}' has values of different types, e.g., \
+                     the {} '{}' on line {} and the {} '{}' on line {}.",
+                    name, a.0.name(), a.1, a.2, b.0.name(), b.1, b.2));
+            }
+            _ => Type::String,
+        };
+        let numeric = kind == Type::Integer || kind == Type::Number;
+        Ok(Column {
+            name: name.to_owned(),
+            kind: kind,
+            nullable: self.nullable,
+            required: true,
+            minimum: if numeric { self.minimum } else { None },
+            maximum: if numeric { self.maximum } else { None },
+            min_length: if kind == Type::String { self.min_length } else { None },
+            max_length: if kind == Type::String { self.max_length } else { None },
+            values: match kind {
+                Type::Boolean => None,
+                _ => self.values.map(|v| v.into_iter().collect()),
+            },
+        })
+    }
+}
+
+pub fn run(argv: &[&str]) -> CliResult<()> {
+    let args: Args = util::get_args(USAGE, argv)?;
+    let rconfig = Config::new(&args.arg_input)
+        .delimiter(args.flag_delimiter);
+
+    let mut rdr = rconfig.reader()?;
+    let headers = rdr.byte_headers()?.clone();
+    let mut names: Vec<String> = vec![];
+    for name in &headers {
+        let name = String::from_utf8_lossy(name).into_owned();
+        if names.contains(&name) {
+            return fail_data!(format!(
+                "The column name '{}' is used more than once.", name));
+        }
+        names.push(name);
+    }
+
+    let threshold = args.flag_enum_threshold;
+    let mut columns: Vec<Infer> =
+        names.iter().map(|_| Infer::new(threshold)).collect();
+    let mut record = csv::ByteRecord::new();
+    let mut count = 0;
+    while args.flag_sample_rows.map_or(true, |n| count < n)
+          && rdr.read_byte_record(&mut record)? {
+        count += 1;
+        let line = record.position().map_or(0, |p| p.line());
+        for (column, value) in columns.iter_mut().zip(&record) {
+            column.add(value, line, threshold);
+        }
+    }
+
+    let mut schema = Schema { columns: vec![], additional: false };
+    for (name, column) in names.iter().zip(columns) {
+        schema.columns.push(column.finish(name, args.flag_strict_types)?);
+    }
+    let mut out = io::BufWriter::new(
+        Config::new(&args.flag_output).io_writer()?);
+    schema.to_json().write_pretty(&mut out, 0)?;
+    writeln!(out)?;
+    Ok(out.flush()?)
+}
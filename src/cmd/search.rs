@@ -6,7 +6,7 @@ use config::{Config, Delimiter};
 use select::SelectColumns;
 use util;
 
-static USAGE: &'static str = "
+pub static USAGE: &'static str = "
 Filters CSV data by whether the given regex matches a row.
 
 The regex is applied to each field in each row, and if any field matches,
@@ -32,7 +32,8 @@ Common options:
                            as headers. (i.e., They are not searched, analyzed,
                            sliced, etc.)
     -d, --delimiter <arg>  The field delimiter for reading CSV data.
-                           Must be a single character. (default: ,)
+                           Must be a single character (or tab, pipe,
+                           semicolon, space, \\t or \\xHH). (default: ,)
 ";
 
 #[derive(Deserialize)]
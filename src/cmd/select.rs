@@ -1,11 +1,14 @@
+use std::io;
+
 use csv;
 
 use CliResult;
 use config::{Config, Delimiter};
+use index::{self, FieldRecords};
 use select::SelectColumns;
 use util;
 
-static USAGE: &'static str = "
+pub static USAGE: &'static str = "
 Select columns from CSV data efficiently.
 
 This command lets you manipulate the columns in CSV data. You can re-order
@@ -33,6 +36,9 @@ more indexing). Finally, column ranges can be specified.
   Quote column names that conflict with selector syntax:
   $ xsv select '\"Date - Opening\",\"Date - Actual Closing\"'
 
+If the CSV data has an index created with 'xsv index --fields', then the
+selected fields are read without parsing the others.
+
 Usage:
     xsv select [options] [--] <selection> [<input>]
     xsv select --help
@@ -44,7 +50,8 @@ Common options:
                            as headers. (i.e., They are not searched, analyzed,
                            sliced, etc.)
     -d, --delimiter <arg>  The field delimiter for reading CSV data.
-                           Must be a single character. (default: ,)
+                           Must be a single character (or tab, pipe,
+                           semicolon, space, \\t or \\xHH). (default: ,)
 ";
 
 #[derive(Deserialize)]
@@ -56,6 +63,33 @@ struct Args {
     flag_delimiter: Option<Delimiter>,
 }
 
+/// The options of `xsv select`.
+#[derive(Clone, Debug, Default)]
+pub struct SelectOptions {
+    /// The columns to write, in their order.
+    pub select: SelectColumns,
+    /// Whether the first row is data, rather than the names of the columns.
+    pub no_headers: bool,
+    /// The field delimiter of the input, which is a comma if this is
+    /// `None`. The output is always delimited by commas.
+    pub delimiter: Option<Delimiter>,
+}
+
+/// Writes the selected columns of the CSV data in `rdr` to `wtr`, as CSV
+/// data.
+pub fn select<R: io::Read, W: io::Write>(
+    opts: &SelectOptions,
+    rdr: R,
+    wtr: W,
+) -> CliResult<()> {
+    let rconfig = Config::new(&None)
+        .delimiter(opts.delimiter)
+        .no_headers(opts.no_headers)
+        .select(opts.select.clone());
+    let rdr = rconfig.from_reader(rdr);
+    select_records(&rconfig, rdr, Config::new(&None).from_writer(wtr))
+}
+
 pub fn run(argv: &[&str]) -> CliResult<()> {
     let args: Args = util::get_args(USAGE, argv)?;
 
@@ -64,9 +98,18 @@ pub fn run(argv: &[&str]) -> CliResult<()> {
         .no_headers(args.flag_no_headers)
         .select(args.arg_selection);
 
-    let mut rdr = rconfig.reader()?;
-    let mut wtr = Config::new(&args.flag_output).writer()?;
+    let wtr = Config::new(&args.flag_output).writer()?;
+    if let Some(recs) = rconfig.field_records()? {
+        return select_fields(&rconfig, recs, wtr);
+    }
+    select_records(&rconfig, rconfig.reader()?, wtr)
+}
 
+fn select_records<R: io::Read, W: io::Write>(
+    rconfig: &Config,
+    mut rdr: csv::Reader<R>,
+    mut wtr: csv::Writer<W>,
+) -> CliResult<()> {
     let headers = rdr.byte_headers()?.clone();
     let sel = rconfig.selection(&headers)?;
 
@@ -80,3 +123,52 @@ pub fn run(argv: &[&str]) -> CliResult<()> {
     wtr.flush()?;
     Ok(())
 }
+
+/// Selects columns using the field offsets stored in an index.
+fn select_fields<W: io::Write>(
+    rconfig: &Config,
+    mut recs: FieldRecords,
+    mut wtr: csv::Writer<W>,
+) -> CliResult<()> {
+    let (mut raw, mut bounds) = (vec![], vec![]);
+    let mut record = csv::ByteRecord::new();
+    let mut first = true;
+    let mut sel = None;
+    while let Some(has_bounds) = recs.read(&mut raw, &mut bounds)? {
+        if first || !has_bounds {
+            if has_bounds {
+                record.clear();
+                for w in bounds.windows(2) {
+                    record.push_field(&raw[w[0] as usize..w[1] as usize - 1]);
+                }
+            } else {
+                index::parse_raw(rconfig, &raw, &mut record)?;
+            }
+        }
+        if first {
+            first = false;
+            sel = Some((rconfig.selection(&record)?, record.len()));
+            if !rconfig.no_headers {
+                let sel = &sel.as_ref().unwrap().0;
+                wtr.write_record(sel.iter().map(|&i| &record[i]))?;
+                continue;
+            }
+        }
+        let (ref sel, len) = *sel.as_ref().unwrap();
+        let found = if has_bounds { bounds.len() - 1 } else { record.len() };
+        if found != len {
+            return fail_data!(format!(
+                "found record with {} fields, but the previous record \
+                 has {} fields", found, len));
+        }
+        if has_bounds {
+            wtr.write_record(sel.iter().map(|&i| {
+                &raw[bounds[i] as usize..bounds[i + 1] as usize - 1]
+            }))?;
+        } else {
+            wtr.write_record(sel.iter().map(|&i| &record[i]))?;
+        }
+    }
+    wtr.flush()?;
+    Ok(())
+}
@@ -1,12 +1,11 @@
-use std::fs;
-
+use csv;
 
 use CliResult;
-use config::{Config, Delimiter};
-use index::Indexed;
+use config::{Config, DataFile, Delimiter};
+use index::{IndexFile, Indexed, MultiIndexed};
 use util;
 
-static USAGE: &'static str = "
+pub static USAGE: &'static str = "
 Returns the rows in the range specified (starting at 0, half-open interval).
 The range does not include headers.
 
@@ -21,6 +20,9 @@ first. Namely, a slice on an index requires parsing just the rows that are
 sliced. Without an index, all rows up to the first row in the slice must be
 parsed.
 
+The input may also be a multi-index created with 'xsv index --multi', in
+which case the shards are sliced as if they were one CSV file.
+
 Usage:
     xsv slice [options] [<input>]
 
@@ -38,7 +40,10 @@ Common options:
                            as headers. Otherwise, the first row will always
                            appear in the output as the header row.
     -d, --delimiter <arg>  The field delimiter for reading CSV data.
-                           Must be a single character. (default: ,)
+                           Must be a single character (or tab, pipe,
+                           semicolon, space, \\t or \\xHH). (default: ,)
+    --auto-index           When the index of the CSV data is stale, rebuild
+                           it instead of reporting an error.
 ";
 
 #[derive(Deserialize)]
@@ -51,10 +56,14 @@ struct Args {
     flag_output: Option<String>,
     flag_no_headers: bool,
     flag_delimiter: Option<Delimiter>,
+    flag_auto_index: bool,
 }
 
 pub fn run(argv: &[&str]) -> CliResult<()> {
     let args: Args = util::get_args(USAGE, argv)?;
+    if let Some(midx) = args.rconfig().multi_indexed()? {
+        return args.with_multi_index(midx);
+    }
     match args.rconfig().indexed()? {
         None => args.no_index(),
         Some(idxed) => args.with_index(idxed),
@@ -76,7 +85,7 @@ impl Args {
 
     fn with_index(
         &self,
-        mut idx: Indexed<fs::File, fs::File>,
+        mut idx: Indexed<DataFile, IndexFile>,
     ) -> CliResult<()> {
         let mut wtr = self.wconfig().writer()?;
         self.rconfig().write_headers(&mut *idx, &mut wtr)?;
@@ -93,7 +102,27 @@ impl Args {
         Ok(())
     }
 
-    fn range(&self) -> Result<(usize, usize), String> {
+    fn with_multi_index(&self, mut midx: MultiIndexed) -> CliResult<()> {
+        let mut wtr = self.wconfig().writer()?;
+        if !self.flag_no_headers && !midx.byte_headers().is_empty() {
+            wtr.write_record(midx.byte_headers())?;
+        }
+        let (start, end) = self.range()?;
+        if end - start == 0 || start as u64 >= midx.count() {
+            return Ok(wtr.flush()?);
+        }
+        midx.seek(start as u64)?;
+        let mut record = csv::ByteRecord::new();
+        for _ in start..end {
+            if !midx.read_byte_record(&mut record)? {
+                break;
+            }
+            wtr.write_byte_record(&record)?;
+        }
+        Ok(wtr.flush()?)
+    }
+
+    fn range(&self) -> CliResult<(usize, usize)> {
         util::range(
             self.flag_start, self.flag_end, self.flag_len, self.flag_index)
     }
@@ -102,6 +131,7 @@ impl Args {
         Config::new(&self.arg_input)
             .delimiter(self.flag_delimiter)
             .no_headers(self.flag_no_headers)
+            .auto_index(self.flag_auto_index)
     }
 
     fn wconfig(&self) -> Config {
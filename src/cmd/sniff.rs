@@ -0,0 +1,308 @@
+use std::io::{self, Read, Write};
+use std::str;
+
+use csv;
+
+use CliResult;
+use config::Config;
+use json;
+use util;
+
+pub static USAGE: &'static str = "
+Guesses the dialect of CSV data from a sample at its start: the delimiter,
+the quote character, whether the first row is a header row, the number of
+columns, the line terminator and the encoding.
+
+The delimiter is chosen among ',', tab, ';' and '|' by how consistently
+it splits the sample into records with the same number of fields. The
+header row is detected by comparing the first row with the rest: a first
+row with text in columns that otherwise hold numbers (or values of the same
+length) is likely a header row. Guesses with little evidence are reported
+as having low confidence.
+
+By default, the results are written in a human-readable form. With --json,
+they are written as a JSON object instead. With --shell, the options that
+tell other xsv commands about the dialect (like \"-d semicolon\" or \"-n\")
+are written as words that need no quoting, so that they can be used like
+this:
+
+    xsv stats $(xsv sniff --shell data.csv) data.csv
+
+Usage:
+    xsv sniff [options] [<input>]
+    xsv sniff --help
+
+sniff options:
+    --sample-size <kb>     The size of the sample in kilobytes.
+                           [default: 64]
+    --json                 Write the results as JSON.
+    --shell                Write the results as xsv command-line options.
+
+Common options:
+    -h, --help             Display this message
+";
+
+#[derive(Deserialize)]
+struct Args {
+    arg_input: Option<String>,
+    flag_sample_size: usize,
+    flag_json: bool,
+    flag_shell: bool,
+}
+
+const DELIMITERS: &'static [u8] = b",\t;|";
+
+/// The number of records of the sample used to detect the header row.
+const HEADER_SAMPLE: usize = 100;
+
+#[derive(Debug)]
+struct Dialect {
+    delimiter: u8,
+    delimiter_sure: bool,
+    quote: u8,
+    /// Whether the sample has quoted fields at all.
+    quoted: bool,
+    has_header: bool,
+    header_sure: bool,
+    columns: usize,
+    records: usize,
+    terminator: &'static str,
+    encoding: &'static str,
+    bom: bool,
+}
+
+pub fn run(argv: &[&str]) -> CliResult<()> {
+    let args: Args = util::get_args(USAGE, argv)?;
+    if args.flag_json && args.flag_shell {
+        return fail_usage!("--json and --shell cannot be used together.");
+    }
+    let rdr = Config::new(&args.arg_input).io_reader()?;
+    let mut sample = vec![];
+    let limit = args.flag_sample_size.saturating_mul(1024) as u64;
+    rdr.take(limit).read_to_end(&mut sample)?;
+    // Only whole lines are looked at, unless the input is shorter than the
+    // sample size.
+    if sample.len() as u64 == limit {
+        if let Some(end) = sample.iter().rposition(|&b| b == b'\n') {
+            sample.truncate(end + 1);
+        }
+    }
+    let dialect = sniff(&sample);
+
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    if args.flag_shell {
+        // The words are used without quotes, as in `$(xsv sniff --shell)`,
+        // so delimiters that are special to the shell are given by name.
+        let delimiter = match dialect.delimiter {
+            b'\t' => "tab".to_owned(),
+            b';' => "semicolon".to_owned(),
+            b'|' => "pipe".to_owned(),
+            d => (d as char).to_string(),
+        };
+        write!(out, "-d {}", delimiter)?;
+        if !dialect.has_header {
+            write!(out, " -n")?;
+        }
+        writeln!(out)?;
+    } else if args.flag_json {
+        write_json(&mut out, &dialect)?;
+    } else {
+        let sure = |sure: bool| if sure { "" } else { " (low confidence)" };
+        let delimiter = match dialect.delimiter {
+            b'\t' => "tab".to_owned(),
+            d => format!("'{}'", d as char),
+        };
+        writeln!(out, "Delimiter:        {}{}",
+                 delimiter, sure(dialect.delimiter_sure))?;
+        writeln!(out, "Quote:            '{}'{}", dialect.quote as char,
+                 if dialect.quoted { "" } else { " (no quoted fields)" })?;
+        writeln!(out, "Header row:       {}{}",
+                 if dialect.has_header { "yes" } else { "no" },
+                 sure(dialect.header_sure))?;
+        writeln!(out, "Columns:          {}", dialect.columns)?;
+        writeln!(out, "Line terminator:  {}", dialect.terminator)?;
+        writeln!(out, "Encoding:         {}{}", dialect.encoding,
+                 if dialect.bom { " (with BOM)" } else { "" })?;
+        writeln!(out, "Records sampled:  {}", dialect.records)?;
+    }
+    Ok(out.flush()?)
+}
+
+fn write_json<W: Write>(out: &mut W, d: &Dialect) -> io::Result<()> {
+    let confidence = |sure: bool| if sure { "\"high\"" } else { "\"low\"" };
+    write!(out, "{{\"delimiter\":")?;
+    json::write_str(out, &[d.delimiter])?;
+    write!(out, ",\"delimiter_confidence\":{}",
+           confidence(d.delimiter_sure))?;
+    write!(out, ",\"quote\":")?;
+    json::write_str(out, &[d.quote])?;
+    write!(out, ",\"has_header\":{}", d.has_header)?;
+    write!(out, ",\"header_confidence\":{}", confidence(d.header_sure))?;
+    write!(out, ",\"columns\":{}", d.columns)?;
+    write!(out, ",\"line_terminator\":\"{}\"", d.terminator)?;
+    write!(out, ",\"encoding\":\"{}\"", d.encoding)?;
+    write!(out, ",\"bom\":{}", d.bom)?;
+    writeln!(out, ",\"records_sampled\":{}}}", d.records)
+}
+
+fn sniff(sample: &[u8]) -> Dialect {
+    let (encoding, bom_len) = if sample.starts_with(b"\xEF\xBB\xBF") {
+        ("UTF-8", 3)
+    } else if sample.starts_with(b"\xFF\xFE") {
+        ("UTF-16LE", 2)
+    } else if sample.starts_with(b"\xFE\xFF") {
+        ("UTF-16BE", 2)
+    } else if sample.is_ascii() {
+        ("ASCII", 0)
+    } else if is_utf8(sample) {
+        ("UTF-8", 0)
+    } else {
+        ("unknown (not UTF-8)", 0)
+    };
+    let data = &sample[bom_len..];
+
+    let crlf = data.windows(2).filter(|w| w == b"\r\n").count();
+    let lf = data.iter().filter(|&&b| b == b'\n').count() - crlf;
+    let cr = data.iter().filter(|&&b| b == b'\r').count() - crlf;
+    let terminator = if crlf == 0 && lf == 0 && cr == 0 {
+        "none"
+    } else if crlf >= lf && crlf >= cr {
+        "CRLF"
+    } else if lf >= cr {
+        "LF"
+    } else {
+        "CR"
+    };
+
+    // The best delimiter by the share of records that have the most common
+    // number of fields, and then by that number of fields.
+    let mut best: Option<(f64, usize, u8, Vec<csv::ByteRecord>)> = None;
+    for &delimiter in DELIMITERS {
+        let records = parse(data, delimiter);
+        let (consistency, columns) = consistency(&records);
+        if columns < 2 {
+            continue;
+        }
+        let better = match best {
+            None => true,
+            Some((c, n, _, _)) => {
+                consistency > c || (consistency == c && columns > n)
+            }
+        };
+        if better {
+            best = Some((consistency, columns, delimiter, records));
+        }
+    }
+    let (delimiter, delimiter_sure, columns, records) = match best {
+        Some((consistency, columns, delimiter, records)) => {
+            // One record (or a rough split) isn't much evidence.
+            let sure = consistency >= 0.9 && records.len() > 1;
+            (delimiter, sure, columns, records)
+        }
+        None => {
+            let records = parse(data, b',');
+            let columns = consistency(&records).1;
+            (b',', false, columns, records)
+        }
+    };
+
+    let (quote, quoted) = detect_quote(data, delimiter);
+    let (has_header, header_sure) = detect_header(&records);
+    Dialect {
+        delimiter: delimiter,
+        delimiter_sure: delimiter_sure,
+        quote: quote,
+        quoted: quoted,
+        has_header: has_header,
+        header_sure: header_sure,
+        columns: columns,
+        records: records.len(),
+        terminator: terminator,
+        encoding: encoding,
+        bom: bom_len > 0,
+    }
+}
+
+fn is_utf8(sample: &[u8]) -> bool {
+    match str::from_utf8(sample) {
+        Ok(_) => true,
+        // The sample may end in the middle of a character.
+        Err(err) => err.error_len().is_none(),
+    }
+}
+
+fn parse(data: &[u8], delimiter: u8) -> Vec<csv::ByteRecord> {
+    let rdr = csv::ReaderBuilder::new()
+        .delimiter(delimiter)
+        .has_headers(false)
+        .flexible(true)
+        .from_reader(data);
+    // A parse error ends the sample, like the end of the data.
+    rdr.into_byte_records().map_while(Result::ok).collect()
+}
+
+/// Returns the most common number of fields in the records, along with the
+/// share of the records which have it.
+fn consistency(records: &[csv::ByteRecord]) -> (f64, usize) {
+    let mut counts: Vec<(usize, usize)> = vec![];
+    for r in records {
+        match counts.iter_mut().find(|c| c.0 == r.len()) {
+            Some(c) => c.1 += 1,
+            None => counts.push((r.len(), 1)),
+        }
+    }
+    match counts.iter().max_by_key(|c| (c.1, c.0)) {
+        None => (0.0, 0),
+        Some(&(len, n)) => (n as f64 / records.len() as f64, len),
+    }
+}
+
+/// Returns the quote character that most often starts or ends fields, and
+/// whether there are any quoted fields.
+fn detect_quote(data: &[u8], delimiter: u8) -> (u8, bool) {
+    let boundary = |b: u8| b == delimiter || b == b'\n' || b == b'\r';
+    let count = |quote: u8| {
+        (0..data.len()).filter(|&i| {
+            data[i] == quote
+                && (i == 0 || boundary(data[i - 1])
+                    || i + 1 == data.len() || boundary(data[i + 1]))
+        }).count()
+    };
+    let (double, single) = (count(b'"'), count(b'\''));
+    if single > double {
+        (b'\'', true)
+    } else {
+        (b'"', double > 0)
+    }
+}
+
+/// Guesses whether the first record is a header row, with votes from each
+/// column like Python's csv.Sniffer, and whether the guess is confident.
+fn detect_header(records: &[csv::ByteRecord]) -> (bool, bool) {
+    if records.len() < 2 {
+        return (true, false);
+    }
+    let header = &records[0];
+    let rest = &records[1..::std::cmp::min(records.len(), HEADER_SAMPLE + 1)];
+    let mut votes = 0i32;
+    for (i, name) in header.iter().enumerate() {
+        let values: Vec<&[u8]> = rest.iter().filter_map(|r| r.get(i))
+                                     .filter(|v| !v.is_empty()).collect();
+        if values.is_empty() {
+            continue;
+        }
+        if values.iter().all(|v| is_number(v)) {
+            votes += if is_number(name) { -1 } else { 1 };
+        } else if values.iter().all(|v| v.len() == values[0].len()) {
+            votes += if name.len() == values[0].len() { -1 } else { 1 };
+        }
+    }
+    // Without evidence either way, a header row is the more common case.
+    (votes >= 0, votes != 0)
+}
+
+fn is_number(s: &[u8]) -> bool {
+    str::from_utf8(s).ok().and_then(|s| s.trim().parse::<f64>().ok())
+                     .is_some()
+}
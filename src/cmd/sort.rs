@@ -8,7 +8,7 @@ use std::str::from_utf8;
 
 use self::Number::{Float, Int};
 
-static USAGE: &'static str = "
+pub static USAGE: &'static str = "
 Sorts CSV data lexicographically.
 
 Note that this requires reading all of the CSV data into memory.
@@ -30,7 +30,8 @@ Common options:
                            of the rows. Otherwise, the first row will always
                            appear as the header row in the output.
     -d, --delimiter <arg>  The field delimiter for reading CSV data.
-                           Must be a single character. (default: ,)
+                           Must be a single character (or tab, pipe,
+                           semicolon, space, \\t or \\xHH). (default: ,)
 ";
 
 #[derive(Deserialize)]
@@ -58,7 +59,10 @@ pub fn run(argv: &[&str]) -> CliResult<()> {
     let headers = rdr.byte_headers()?.clone();
     let sel = rconfig.selection(&headers)?;
 
-    let mut all = rdr.byte_records().collect::<Result<Vec<_>, _>>()?;
+    let mut progress = util::progress(&rconfig);
+    let mut all = progress.records(rdr.byte_records())
+                          .collect::<Result<Vec<_>, _>>()?;
+    progress.finish();
     match (numeric, reverse) {
         (false, false) =>
             all.sort_by(|r1, r2| {
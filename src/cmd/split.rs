@@ -7,11 +7,11 @@ use csv;
 use threadpool::ThreadPool;
 
 use CliResult;
-use config::{Config, Delimiter};
-use index::Indexed;
+use config::{Config, DataFile, Delimiter};
+use index::{IndexFile, Indexed};
 use util::{self, FilenameTemplate};
 
-static USAGE: &'static str = "
+pub static USAGE: &'static str = "
 Splits the given CSV data into chunks.
 
 The files are written to the directory given with the name '{start}.csv',
@@ -43,7 +43,10 @@ Common options:
                            as column names. Otherwise, the first row will
                            appear in all chunks as the header row.
     -d, --delimiter <arg>  The field delimiter for reading CSV data.
-                           Must be a single character. (default: ,)
+                           Must be a single character (or tab, pipe,
+                           semicolon, space, \\t or \\xHH). (default: ,)
+    --auto-index           When the index of the CSV data is stale, rebuild
+                           it instead of reporting an error.
 ";
 
 #[derive(Clone, Deserialize)]
@@ -55,12 +58,13 @@ struct Args {
     flag_filename: FilenameTemplate,
     flag_no_headers: bool,
     flag_delimiter: Option<Delimiter>,
+    flag_auto_index: bool,
 }
 
 pub fn run(argv: &[&str]) -> CliResult<()> {
     let args: Args = util::get_args(USAGE, argv)?;
     if args.flag_size == 0 {
-        return fail!("--size must be greater than 0.");
+        return fail_usage!("--size must be greater than 0.");
     }
     fs::create_dir_all(&args.arg_outdir)?;
 
@@ -79,7 +83,9 @@ impl Args {
         let mut wtr = self.new_writer(&headers, 0)?;
         let mut i = 0;
         let mut row = csv::ByteRecord::new();
+        let mut progress = util::progress(&rconfig);
         while rdr.read_byte_record(&mut row)? {
+            progress.record(&row);
             if i > 0 && i % self.flag_size == 0 {
                 wtr.flush()?;
                 wtr = self.new_writer(&headers, i)?;
@@ -87,13 +93,14 @@ impl Args {
             wtr.write_byte_record(&row)?;
             i += 1;
         }
+        progress.finish();
         wtr.flush()?;
         Ok(())
     }
 
     fn parallel_split(
         &self,
-        idx: Indexed<fs::File, fs::File>,
+        idx: Indexed<DataFile, IndexFile>,
     ) -> CliResult<()> {
         let nchunks = util::num_of_chunks(
             idx.count() as usize, self.flag_size);
@@ -143,6 +150,7 @@ impl Args {
         Config::new(&self.arg_input)
             .delimiter(self.flag_delimiter)
             .no_headers(self.flag_no_headers)
+            .auto_index(self.flag_auto_index)
     }
 
     fn njobs(&self) -> usize {
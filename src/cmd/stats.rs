@@ -1,7 +1,6 @@
 use std::borrow::ToOwned;
 use std::default::Default;
 use std::fmt;
-use std::fs;
 use std::io;
 use std::iter::{repeat, FromIterator};
 use std::str::{self, FromStr};
@@ -11,15 +10,16 @@ use csv;
 use stats::{merge_all, Commute, MinMax, OnlineStats, Unsorted};
 use threadpool::ThreadPool;
 
-use config::{Config, Delimiter};
-use index::Indexed;
+use config::{Config, DataFile, Delimiter};
+use index::{IndexFile, Indexed};
+use intern::{Counts, Interner};
 use select::{SelectColumns, Selection};
 use util;
 use CliResult;
 
 use self::FieldType::{TFloat, TInteger, TNull, TUnicode, TUnknown};
 
-static USAGE: &'static str = "
+pub static USAGE: &'static str = "
 Computes basic statistics on CSV data.
 
 Basic statistics includes mean, median, mode, standard deviation, sum, max and
@@ -42,9 +42,11 @@ stats options:
                            into 'xsv stats' will disable the use of indexing.
     --everything           Show all statistics available.
     --mode                 Show the mode.
-                           This requires storing all CSV data in memory.
+                           This requires memory proportional to the
+                           number of distinct values.
     --cardinality          Show the cardinality.
-                           This requires storing all CSV data in memory.
+                           This requires memory proportional to the
+                           number of distinct values.
     --median               Show the median.
                            This requires storing all CSV data in memory.
     --nulls                Include NULLs in the population size for computing
@@ -64,7 +66,10 @@ Common options:
                            as column names. i.e., They will be included
                            in statistics.
     -d, --delimiter <arg>  The field delimiter for reading CSV data.
-                           Must be a single character. (default: ,)
+                           Must be a single character (or tab, pipe,
+                           semicolon, space, \\t or \\xHH). (default: ,)
+    --auto-index           When the index of the CSV data is stale, rebuild
+                           it instead of reporting an error.
 ";
 
 #[derive(Clone, Deserialize)]
@@ -80,6 +85,147 @@ struct Args {
     flag_output: Option<String>,
     flag_no_headers: bool,
     flag_delimiter: Option<Delimiter>,
+    flag_auto_index: bool,
+}
+
+/// The options of `xsv stats`, which select the statistics that are
+/// computed by `compute`.
+#[derive(Clone, Debug, Default)]
+pub struct StatsOptions {
+    /// The columns to compute statistics for.
+    pub select: SelectColumns,
+    /// Whether the first row is data, rather than the names of the columns.
+    pub no_headers: bool,
+    /// The field delimiter, which is a comma if this is `None`.
+    pub delimiter: Option<Delimiter>,
+    /// Compute the mode, which requires storing the distinct values.
+    pub mode: bool,
+    /// Compute the cardinality, which requires storing the distinct values.
+    pub cardinality: bool,
+    /// Compute the median, which requires storing all values in memory.
+    pub median: bool,
+    /// Include NULLs in the population size for computing the mean and
+    /// standard deviation.
+    pub nulls: bool,
+}
+
+impl StatsOptions {
+    fn which(&self) -> WhichStats {
+        WhichStats {
+            include_nulls: self.nulls,
+            sum: true,
+            range: true,
+            dist: true,
+            cardinality: self.cardinality,
+            median: self.median,
+            mode: self.mode,
+        }
+    }
+}
+
+/// The statistics of a column, as in a row of the output of `xsv stats`.
+///
+/// The values are formatted as they are there. A value is `None` if it
+/// doesn't apply to the type of the column (like the mean of text), if
+/// there were no values, or if it wasn't asked for in the `StatsOptions`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ColumnStats {
+    /// The name of the column, or its index (from 0) with `no_headers`.
+    pub field: String,
+    /// The type of the values: `Integer`, `Float`, `Unicode` or `NULL` (if
+    /// all values are empty).
+    pub field_type: String,
+    pub sum: Option<String>,
+    pub min: Option<String>,
+    pub max: Option<String>,
+    pub min_length: Option<usize>,
+    pub max_length: Option<usize>,
+    pub mean: Option<f64>,
+    pub stddev: Option<f64>,
+    pub median: Option<f64>,
+    /// The most common value, or `N/A` if there is none (as when the most
+    /// common values are tied).
+    pub mode: Option<String>,
+    pub cardinality: Option<usize>,
+}
+
+impl ColumnStats {
+    /// Returns the record of the statistics in the output of `xsv stats`,
+    /// without the field name.
+    fn to_record(&self, which: &WhichStats) -> csv::StringRecord {
+        fn show<T: ToString>(v: &Option<T>) -> String {
+            v.as_ref().map_or(String::new(), |v| v.to_string())
+        }
+        let mut pieces = vec![
+            self.field_type.clone(),
+            show(&self.sum),
+            show(&self.min),
+            show(&self.max),
+            show(&self.min_length),
+            show(&self.max_length),
+            show(&self.mean),
+            show(&self.stddev),
+        ];
+        if which.median {
+            pieces.push(show(&self.median));
+        }
+        if which.mode {
+            pieces.push(show(&self.mode));
+        }
+        if which.cardinality {
+            pieces.push(show(&self.cardinality));
+        }
+        csv::StringRecord::from(pieces)
+    }
+}
+
+/// Computes the statistics of the selected columns of the CSV data in
+/// `rdr`, which is read to the end.
+pub fn compute<R: io::Read>(
+    opts: &StatsOptions,
+    rdr: R,
+) -> CliResult<Vec<ColumnStats>> {
+    let rconfig = Config::new(&None)
+        .delimiter(opts.delimiter)
+        .no_headers(opts.no_headers)
+        .select(opts.select.clone());
+    let mut rdr = rconfig.from_reader(rdr);
+    let headers = rdr.byte_headers()?.clone();
+    let sel = rconfig.selection(&headers)?;
+    let mut stats = compute_stats(&opts.which(), &sel, rdr.byte_records())?;
+    Ok(stats.iter_mut().zip(sel.select(&headers)).enumerate()
+        .map(|(i, (stat, name))| {
+            let mut column = stat.to_column();
+            column.field = if opts.no_headers {
+                i.to_string()
+            } else {
+                String::from_utf8_lossy(name).into_owned()
+            };
+            column
+        })
+        .collect())
+}
+
+fn compute_stats<I>(
+    which: &WhichStats,
+    sel: &Selection,
+    it: I,
+) -> CliResult<Vec<Stats>>
+where
+    I: Iterator<Item = csv::Result<csv::ByteRecord>>,
+{
+    let mut stats: Vec<Stats> =
+        repeat(Stats::new(which.clone())).take(sel.len()).collect();
+    // The values that are counted for the mode and cardinality are shared
+    // by the columns.
+    let mut interner = Interner::new();
+    for row in it {
+        let row = row?;
+        for (i, field) in sel.select(&row).enumerate() {
+            stats[i].add(&mut interner, field);
+        }
+    }
+    Ok(stats)
 }
 
 pub fn run(argv: &[&str]) -> CliResult<()> {
@@ -115,15 +261,19 @@ pub fn run(argv: &[&str]) -> CliResult<()> {
 
 impl Args {
     fn sequential_stats(&self) -> CliResult<(csv::ByteRecord, Vec<Stats>)> {
-        let mut rdr = self.rconfig().reader()?;
+        let rconfig = self.rconfig();
+        let mut rdr = rconfig.reader()?;
         let (headers, sel) = self.sel_headers(&mut rdr)?;
-        let stats = self.compute(&sel, rdr.byte_records())?;
+        let mut progress = util::progress(&rconfig);
+        let stats =
+            self.compute(&sel, progress.records(rdr.byte_records()))?;
+        progress.finish();
         Ok((headers, stats))
     }
 
     fn parallel_stats(
         &self,
-        idx: Indexed<fs::File, fs::File>,
+        idx: Indexed<DataFile, IndexFile>,
     ) -> CliResult<(csv::ByteRecord, Vec<Stats>)> {
         // N.B. This method doesn't handle the case when the number of records
         // is zero correctly. So we use `sequential_stats` instead.
@@ -160,7 +310,8 @@ impl Args {
             let (send, recv) = channel::bounded(0);
             results.push(recv);
             pool.execute(move || {
-                send.send(stat.to_record());
+                let which = stat.which.clone();
+                send.send(stat.to_column().to_record(&which));
             });
         }
         for (i, recv) in results.into_iter().enumerate() {
@@ -173,14 +324,7 @@ impl Args {
     where
         I: Iterator<Item = csv::Result<csv::ByteRecord>>,
     {
-        let mut stats = self.new_stats(sel.len());
-        for row in it {
-            let row = row?;
-            for (i, field) in sel.select(&row).enumerate() {
-                stats[i].add(field);
-            }
-        }
-        Ok(stats)
+        compute_stats(&self.options().which(), sel, it)
     }
 
     fn sel_headers<R: io::Read>(
@@ -196,6 +340,7 @@ impl Args {
         Config::new(&self.arg_input)
             .delimiter(self.flag_delimiter)
             .no_headers(self.flag_no_headers)
+            .auto_index(self.flag_auto_index)
             .select(self.flag_select.clone())
     }
 
@@ -207,18 +352,16 @@ impl Args {
         }
     }
 
-    fn new_stats(&self, record_len: usize) -> Vec<Stats> {
-        repeat(Stats::new(WhichStats {
-            include_nulls: self.flag_nulls,
-            sum: true,
-            range: true,
-            dist: true,
+    fn options(&self) -> StatsOptions {
+        StatsOptions {
+            select: self.flag_select.clone(),
+            no_headers: self.flag_no_headers,
+            delimiter: self.flag_delimiter,
+            mode: self.flag_mode || self.flag_everything,
             cardinality: self.flag_cardinality || self.flag_everything,
             median: self.flag_median || self.flag_everything,
-            mode: self.flag_mode || self.flag_everything,
-        }))
-        .take(record_len)
-        .collect()
+            nulls: self.flag_nulls,
+        }
     }
 
     fn stat_headers(&self) -> csv::StringRecord {
@@ -270,7 +413,7 @@ struct Stats {
     sum: Option<TypedSum>,
     minmax: Option<TypedMinMax>,
     online: Option<OnlineStats>,
-    mode: Option<Unsorted<Vec<u8>>>,
+    mode: Option<Counts>,
     median: Option<Unsorted<f64>>,
     which: WhichStats,
 }
@@ -305,14 +448,14 @@ impl Stats {
         }
     }
 
-    fn add(&mut self, sample: &[u8]) {
+    fn add(&mut self, interner: &mut Interner, sample: &[u8]) {
         let sample_type = FieldType::from_sample(sample);
         self.typ.merge(sample_type);
 
         let t = self.typ;
         self.sum.as_mut().map(|v| v.add(t, sample));
         self.minmax.as_mut().map(|v| v.add(t, sample));
-        self.mode.as_mut().map(|v| v.add(sample.to_vec()));
+        self.mode.as_mut().map(|v| v.add(interner, sample));
         match self.typ {
             TUnknown => {}
             TNull => {
@@ -343,87 +486,35 @@ impl Stats {
         }
     }
 
-    fn to_record(&mut self) -> csv::StringRecord {
+    fn to_column(&mut self) -> ColumnStats {
         let typ = self.typ;
-        let mut pieces = vec![];
-        let empty = || "".to_owned();
-
-        pieces.push(self.typ.to_string());
-        match self.sum.as_ref().and_then(|sum| sum.show(typ)) {
-            Some(sum) => {
-                pieces.push(sum);
-            }
-            None => {
-                pieces.push(empty());
-            }
-        }
-        match self.minmax.as_ref().and_then(|mm| mm.show(typ)) {
-            Some(mm) => {
-                pieces.push(mm.0);
-                pieces.push(mm.1);
-            }
-            None => {
-                pieces.push(empty());
-                pieces.push(empty());
-            }
-        }
-        match self.minmax.as_ref().and_then(|mm| mm.len_range()) {
-            Some(mm) => {
-                pieces.push(mm.0);
-                pieces.push(mm.1);
-            }
-            None => {
-                pieces.push(empty());
-                pieces.push(empty());
-            }
-        }
-
-        if !self.typ.is_number() {
-            pieces.push(empty());
-            pieces.push(empty());
-        } else {
-            match self.online {
-                Some(ref v) => {
-                    pieces.push(v.mean().to_string());
-                    pieces.push(v.stddev().to_string());
-                }
-                None => {
-                    pieces.push(empty());
-                    pieces.push(empty());
-                }
-            }
-        }
-        match self.median.as_mut().and_then(|v| v.median()) {
-            None => {
-                if self.which.median {
-                    pieces.push(empty());
-                }
+        let minmax = self.minmax.as_ref().and_then(|mm| mm.show(typ));
+        let lengths = self.minmax.as_ref().and_then(|mm| mm.len_range());
+        let online = if typ.is_number() { self.online.as_ref() } else { None };
+        let lossy = |s: &[u8]| String::from_utf8_lossy(s).into_owned();
+        let (mut mode, mut cardinality) = (None, None);
+        if let Some(ref v) = self.mode {
+            if self.which.mode {
+                mode = Some(v.mode().map_or("N/A".to_owned(), lossy));
             }
-            Some(v) => {
-                pieces.push(v.to_string());
+            if self.which.cardinality {
+                cardinality = Some(v.cardinality());
             }
         }
-        match self.mode.as_mut() {
-            None => {
-                if self.which.mode {
-                    pieces.push(empty());
-                }
-                if self.which.cardinality {
-                    pieces.push(empty());
-                }
-            }
-            Some(ref mut v) => {
-                if self.which.mode {
-                    let lossy =
-                        |s: Vec<u8>| -> String { String::from_utf8_lossy(&*s).into_owned() };
-                    pieces.push(v.mode().map_or("N/A".to_owned(), lossy));
-                }
-                if self.which.cardinality {
-                    pieces.push(v.cardinality().to_string());
-                }
-            }
+        ColumnStats {
+            field: String::new(),
+            field_type: typ.to_string(),
+            sum: self.sum.as_ref().and_then(|sum| sum.show(typ)),
+            min: minmax.as_ref().map(|mm| mm.0.clone()),
+            max: minmax.map(|mm| mm.1),
+            min_length: lengths.map(|lens| lens.0),
+            max_length: lengths.map(|lens| lens.1),
+            mean: online.map(|v| v.mean()),
+            stddev: online.map(|v| v.stddev()),
+            median: self.median.as_mut().and_then(|v| v.median()),
+            mode: mode,
+            cardinality: cardinality,
         }
-        csv::StringRecord::from(pieces)
     }
 }
 
@@ -610,9 +701,9 @@ impl TypedMinMax {
         }
     }
 
-    fn len_range(&self) -> Option<(String, String)> {
+    fn len_range(&self) -> Option<(usize, usize)> {
         match (self.str_len.min(), self.str_len.max()) {
-            (Some(min), Some(max)) => Some((min.to_string(), max.to_string())),
+            (Some(&min), Some(&max)) => Some((min, max)),
             _ => None,
         }
     }
@@ -1,20 +1,30 @@
 use std::borrow::Cow;
+use std::cmp;
+use std::io::{self, Write};
+use std::iter;
+use std::str;
 
 use csv;
-use tabwriter::TabWriter;
+use serde::de::{Deserialize, Deserializer, Error};
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
 
 use CliResult;
 use config::{Config, Delimiter};
+use select::SelectColumns;
 use util;
 
-static USAGE: &'static str = "
+pub static USAGE: &'static str = "
 Outputs CSV data as a table with columns in alignment.
 
-This will not work well if the CSV data contains large fields.
+This will not work well if the CSV data contains large fields, unless the
+width of each column is limited with --max-width.
 
-Note that formatting a table requires buffering all CSV data into memory.
-Therefore, you should use the 'sample' or 'slice' command to trim down large
-CSV data before formatting it with this command.
+By default, the width of each column is computed from the first 1000 records
+(see --sample), after which the remaining records are streamed. Values in
+later records that are wider than their column are truncated to fit and end
+with '…' (or wrapped, with --wrap), except in Markdown tables, where they are
+written whole. This keeps memory use proportional to the sample size. Use --exact to compute the widths from all records instead, which
+requires buffering all CSV data into memory.
 
 Usage:
     xsv table [options] [<input>]
@@ -28,12 +38,48 @@ table options:
                            specified. If the field is UTF-8 encoded, then
                            <arg> refers to the number of code points.
                            Otherwise, it refers to the number of bytes.
+    -m, --max-width <arg>  The maximum width of each column. Longer values
+                           are truncated to fit and end with '…'. Widths
+                           are measured in display columns, so wide
+                           characters (e.g., CJK) count as two.
+    --wrap                 When used with --max-width, wrap longer values
+                           across multiple lines instead of truncating
+                           them. Lines are broken at spaces when possible.
+    --align <arg>          Align the values in every column to the 'left',
+                           'right' or 'center'. With 'auto', columns where
+                           nearly all values are numbers are right aligned
+                           and all others are left aligned. Alignments
+                           can also be given per column as a comma
+                           separated list of <selection>:<alignment>
+                           pairs, e.g., 'price:right,name:center'. A bare
+                           alignment in the list applies to all other
+                           columns, e.g., 'auto,id:left'. Values are left
+                           aligned by default.
+    --sample <n>           The number of records to compute the column
+                           widths (and the alignments for --align auto)
+                           from. [default: 1000]
+    --exact                Compute the column widths from all records.
+                           This buffers all CSV data into memory.
+    --max-rows <n>         Stop after writing <n> records (not counting
+                           the header row).
+    --format <arg>         The output format: 'text' or 'markdown'. The
+                           latter writes a GitHub flavored Markdown table,
+                           where pipes and backslashes in values are
+                           escaped and line breaks become '<br>'.
+                           [default: text]
 
 Common options:
     -h, --help             Display this message
     -o, --output <file>    Write output to <file> instead of stdout.
+    -n, --no-headers       When set, the first row is not treated as a
+                           header. It is then included when detecting
+                           numeric columns and column names can't be used
+                           with --align. Since Markdown tables need a
+                           header, one with the names col1, col2, etc. is
+                           added when writing Markdown.
     -d, --delimiter <arg>  The field delimiter for reading CSV data.
-                           Must be a single character. (default: ,)
+                           Must be a single character (or tab, pipe,
+                           semicolon, space, \\t or \\xHH). (default: ,)
 ";
 
 #[derive(Deserialize)]
@@ -44,28 +90,412 @@ struct Args {
     flag_output: Option<String>,
     flag_delimiter: Option<Delimiter>,
     flag_condense: Option<usize>,
+    flag_max_width: Option<usize>,
+    flag_wrap: bool,
+    flag_align: Option<AlignSpec>,
+    flag_sample: usize,
+    flag_exact: bool,
+    flag_max_rows: Option<u64>,
+    flag_format: Format,
+    flag_no_headers: bool,
 }
 
 pub fn run(argv: &[&str]) -> CliResult<()> {
     let args: Args = util::get_args(USAGE, argv)?;
+    if args.flag_max_width == Some(0) {
+        return fail_usage!("--max-width must be greater than 0.");
+    }
+    if args.flag_wrap && args.flag_max_width.is_none() {
+        return fail_usage!("--wrap requires --max-width.");
+    }
+    if args.flag_wrap && args.flag_format == Format::Markdown {
+        return fail_usage!("--wrap cannot be used with '--format markdown'.");
+    }
     let rconfig = Config::new(&args.arg_input)
         .delimiter(args.flag_delimiter)
         .no_headers(true);
-    let wconfig = Config::new(&args.flag_output)
-        .delimiter(Some(Delimiter(b'\t')));
-
-    let tw = TabWriter::new(wconfig.io_writer()?)
-        .minwidth(args.flag_width)
-        .padding(args.flag_pad);
-    let mut wtr = wconfig.from_writer(tw);
+    let mut wtr = Config::new(&args.flag_output).io_writer()?;
     let mut rdr = rconfig.reader()?;
 
+    let mut rows = vec![];
+    let mut headers = csv::ByteRecord::new();
+    // For each column, the number of non-empty values and the number of
+    // those that are numbers.
+    let mut numeric: Vec<(u64, u64)> = vec![];
     let mut record = csv::ByteRecord::new();
-    while rdr.read_byte_record(&mut record)? {
-        wtr.write_record(record.iter().map(|f| {
-            util::condense(Cow::Borrowed(f), args.flag_condense)
-        }))?;
+    let done = |count: u64| args.flag_max_rows.map_or(false, |n| count >= n);
+    // The number of records read, not counting the header row.
+    let mut count = 0u64;
+    while !done(count)
+        && (args.flag_exact || count < args.flag_sample as u64)
+        && rdr.read_byte_record(&mut record)?
+    {
+        if rows.is_empty() && !args.flag_no_headers {
+            headers = record.clone();
+        } else {
+            count_numeric(&mut numeric, &record);
+            count += 1;
+        }
+        rows.push(record.iter().map(|f| args.cell(f)).collect::<Vec<_>>());
+    }
+    let len = rows.iter().map(|r| r.len()).max().unwrap_or(0);
+    let aligns = match args.flag_align {
+        None => vec![Align::Left; len],
+        Some(ref spec) => {
+            spec.resolve(&headers, !args.flag_no_headers, &numeric, len)?
+        }
+    };
+    if args.flag_format == Format::Markdown && args.flag_no_headers {
+        // Markdown tables always have a header row.
+        rows.insert(0, (1..len+1).map(|i| format!("col{}", i)).collect());
+    }
+    let widths = args.widths(&rows);
+    for (i, row) in rows.iter().enumerate() {
+        args.write_row(&mut wtr, row, &widths, &aligns)?;
+        if i == 0 && args.flag_format == Format::Markdown {
+            args.write_separator(&mut wtr, &widths, &aligns)?;
+        }
+    }
+    drop(rows);
+    let mut row = vec![];
+    while !done(count) && rdr.read_byte_record(&mut record)? {
+        count += 1;
+        row.clear();
+        row.extend(record.iter().map(|f| args.cell(f)));
+        args.write_row(&mut wtr, &row, &widths, &aligns)?;
     }
     wtr.flush()?;
     Ok(())
 }
+
+impl Args {
+    /// Converts a field to the text shown in its cell.
+    ///
+    /// In text tables, fields containing tabs, quotes or line terminators
+    /// are quoted like they would be in tab delimited CSV data. In Markdown
+    /// tables, pipes and backslashes are escaped and line terminators are
+    /// replaced with `<br>`.
+    fn cell(&self, field: &[u8]) -> String {
+        let field = util::condense(Cow::Borrowed(field), self.flag_condense);
+        let mut s = String::from_utf8_lossy(&field).into_owned();
+        match self.flag_format {
+            Format::Text => {
+                let special = |c| {
+                    c == '\t' || c == '"' || c == '\r' || c == '\n'
+                };
+                if s.contains(special) {
+                    s = format!("\"{}\"", s.replace('"', "\"\""));
+                }
+            }
+            Format::Markdown => {
+                s = s.replace('\\', "\\\\")
+                     .replace('|', "\\|")
+                     .replace("\r\n", "<br>")
+                     .replace('\n', "<br>")
+                     .replace('\r', "<br>");
+            }
+        }
+        match self.flag_max_width {
+            Some(n) if !self.flag_wrap => truncate(&s, n),
+            _ => s,
+        }
+    }
+
+    /// Computes the width of every column.
+    fn widths(&self, rows: &[Vec<String>]) -> Vec<usize> {
+        // The separator row of a Markdown table needs at least three dashes.
+        let min_width = match self.flag_format {
+            Format::Text => self.flag_width,
+            Format::Markdown => cmp::max(self.flag_width, 3),
+        };
+        let mut widths = vec![];
+        for row in rows {
+            for (i, cell) in row.iter().enumerate() {
+                if i >= widths.len() {
+                    widths.push(min_width);
+                }
+                let mut width = cell.width();
+                if let Some(n) = self.flag_max_width {
+                    width = width.min(n);
+                }
+                widths[i] = widths[i].max(width);
+            }
+        }
+        widths
+    }
+
+    /// Writes one row of the table. With --wrap, this may take up multiple
+    /// lines.
+    fn write_row<W: io::Write>(
+        &self,
+        wtr: &mut W,
+        row: &[String],
+        widths: &[usize],
+        aligns: &[Align],
+    ) -> io::Result<()> {
+        let markdown = self.flag_format == Format::Markdown;
+        // Values in records after the sample may be wider than their
+        // column. The cells of a Markdown table don't have to line up, so
+        // such values are written whole there.
+        let lines: Vec<Vec<String>> = row.iter().zip(widths).map(|(cell, &w)| {
+            if self.flag_wrap {
+                wrap(cell, w)
+            } else if markdown {
+                vec![cell.clone()]
+            } else {
+                vec![truncate(cell, w)]
+            }
+        }).collect();
+        let height = lines.iter().map(|l| l.len()).max().unwrap_or(1);
+        let empty = String::new();
+        for k in 0..height {
+            let mut line = String::new();
+            if markdown {
+                line.push_str("| ");
+            }
+            for (i, cell_lines) in lines.iter().enumerate() {
+                let text = cell_lines.get(k).unwrap_or(&empty);
+                let last = i + 1 == lines.len();
+                // The last cell of a text table isn't padded on the right.
+                let fill = markdown || !last;
+                line.push_str(&aligns[i].pad(text, widths[i], fill));
+                if last {
+                    continue;
+                }
+                if markdown {
+                    line.push_str(" | ");
+                } else {
+                    line.extend(iter::repeat(' ').take(self.flag_pad));
+                }
+            }
+            if markdown {
+                line.push_str(" |");
+            }
+            // Don't leave trailing whitespace on continuation lines.
+            let line = if k == 0 { &*line } else { line.trim_end() };
+            wtr.write_all(line.as_bytes())?;
+            wtr.write_all(b"\n")?;
+        }
+        Ok(())
+    }
+
+    /// Writes the row separating the header from the rest of a Markdown
+    /// table.
+    fn write_separator<W: io::Write>(
+        &self,
+        wtr: &mut W,
+        widths: &[usize],
+        aligns: &[Align],
+    ) -> io::Result<()> {
+        let cells: Vec<String> = widths.iter().zip(aligns).map(|(&w, a)| {
+            let (left, right) = match (self.flag_align.is_some(), *a) {
+                (false, _) => ("-", "-"),
+                (true, Align::Right) => ("-", ":"),
+                (true, Align::Center) => (":", ":"),
+                (true, _) => (":", "-"),
+            };
+            let dashes: String = iter::repeat('-').take(w - 2).collect();
+            format!("{}{}{}", left, dashes, right)
+        }).collect();
+        writeln!(wtr, "| {} |", cells.join(" | "))
+    }
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+enum Format {
+    Text,
+    Markdown,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Align {
+    Left,
+    Right,
+    Center,
+    Auto,
+}
+
+impl Align {
+    fn parse(s: &str) -> Option<Align> {
+        match s {
+            "left" => Some(Align::Left),
+            "right" => Some(Align::Right),
+            "center" => Some(Align::Center),
+            "auto" => Some(Align::Auto),
+            _ => None,
+        }
+    }
+}
+
+impl Align {
+    /// Pads `text` with spaces to `width` display columns. If `fill` is
+    /// false, no spaces are added after `text`.
+    fn pad(&self, text: &str, width: usize, fill: bool) -> String {
+        let extra = width.saturating_sub(text.width());
+        let (before, after) = match *self {
+            Align::Left | Align::Auto => (0, extra),
+            Align::Right => (extra, 0),
+            Align::Center => (extra / 2, extra - extra / 2),
+        };
+        let mut s = String::with_capacity(text.len() + extra);
+        s.extend(iter::repeat(' ').take(before));
+        s.push_str(text);
+        if fill {
+            s.extend(iter::repeat(' ').take(after));
+        }
+        s
+    }
+}
+
+/// The alignments given with --align.
+struct AlignSpec {
+    default: Align,
+    columns: Vec<(SelectColumns, Align)>,
+}
+
+impl AlignSpec {
+    /// Determines the alignment of each of the first `len` columns.
+    ///
+    /// `numeric` holds the number of non-empty values and the number of
+    /// numeric values of each column, which is used for 'auto'.
+    fn resolve(
+        &self,
+        headers: &csv::ByteRecord,
+        use_names: bool,
+        numeric: &[(u64, u64)],
+        len: usize,
+    ) -> CliResult<Vec<Align>> {
+        let mut aligns = vec![self.default; len];
+        for &(ref sel, align) in &self.columns {
+            for &i in &*sel.selection(headers, use_names)? {
+                if i < len {
+                    aligns[i] = align;
+                }
+            }
+        }
+        for (i, align) in aligns.iter_mut().enumerate() {
+            if *align != Align::Auto {
+                continue;
+            }
+            let (nonempty, numbers) =
+                numeric.get(i).cloned().unwrap_or((0, 0));
+            // Require the overwhelming majority of values to be numbers, so
+            // that the odd "N/A" doesn't matter.
+            *align = if nonempty > 0 && numbers * 10 >= nonempty * 9 {
+                Align::Right
+            } else {
+                Align::Left
+            };
+        }
+        Ok(aligns)
+    }
+}
+
+impl<'de> Deserialize<'de> for AlignSpec {
+    fn deserialize<D: Deserializer<'de>>(
+        d: D,
+    ) -> Result<AlignSpec, D::Error> {
+        let raw = String::deserialize(d)?;
+        let unknown = |s: &str| {
+            D::Error::custom(format!(
+                "Unknown alignment '{}'. Expected 'left', 'right', \
+                 'center' or 'auto'.", s))
+        };
+        let mut spec = AlignSpec { default: Align::Left, columns: vec![] };
+        for item in raw.split(',') {
+            match item.rfind(':') {
+                None => {
+                    spec.default = Align::parse(item)
+                        .ok_or_else(|| unknown(item))?;
+                }
+                Some(i) => {
+                    let align = Align::parse(&item[i+1..])
+                        .ok_or_else(|| unknown(&item[i+1..]))?;
+                    let sel = SelectColumns::parse(&item[..i])
+                        .map_err(D::Error::custom)?;
+                    spec.columns.push((sel, align));
+                }
+            }
+        }
+        Ok(spec)
+    }
+}
+
+/// Updates the number of non-empty and numeric values of each column with
+/// the fields in `record`.
+fn count_numeric(counts: &mut Vec<(u64, u64)>, record: &csv::ByteRecord) {
+    for (i, field) in record.iter().enumerate() {
+        if i >= counts.len() {
+            counts.push((0, 0));
+        }
+        let field = match str::from_utf8(field) {
+            Ok(field) => field.trim(),
+            Err(_) => {
+                counts[i].0 += 1;
+                continue;
+            }
+        };
+        if field.is_empty() {
+            continue;
+        }
+        counts[i].0 += 1;
+        if field.parse::<f64>().is_ok() {
+            counts[i].1 += 1;
+        }
+    }
+}
+
+/// Truncates `s` to at most `n` display columns, ending it with an ellipsis
+/// if anything was removed.
+fn truncate(s: &str, n: usize) -> String {
+    if s.width() <= n {
+        return s.to_owned();
+    }
+    let mut out = String::new();
+    let mut width = 0;
+    for c in s.chars() {
+        let w = c.width().unwrap_or(0);
+        if width + w > n.saturating_sub(1) {
+            break;
+        }
+        out.push(c);
+        width += w;
+    }
+    out.push('…');
+    out
+}
+
+/// Wraps `s` into lines of at most `n` display columns, breaking at spaces
+/// when possible. A single character wider than `n` gets a line of its own.
+fn wrap(s: &str, n: usize) -> Vec<String> {
+    let mut lines = vec![];
+    let mut line = String::new();
+    let mut width = 0;
+    for word in s.split(' ') {
+        let word_width = word.width();
+        if width > 0 && width + 1 + word_width <= n {
+            line.push(' ');
+            line.push_str(word);
+            width += 1 + word_width;
+            continue;
+        }
+        if width > 0 {
+            lines.push(line);
+            line = String::new();
+            width = 0;
+        }
+        for c in word.chars() {
+            let w = c.width().unwrap_or(0);
+            if width > 0 && width + w > n {
+                lines.push(line);
+                line = String::new();
+                width = 0;
+            }
+            line.push(c);
+            width += w;
+        }
+    }
+    lines.push(line);
+    lines
+}
@@ -0,0 +1,340 @@
+use std::collections::HashSet;
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+
+use csv;
+
+use {CliError, CliResult};
+use config::{Config, Delimiter};
+use json;
+use util;
+
+pub static USAGE: &'static str = "
+Renders every record through a text template, e.g.,
+
+    xsv template --template 'INSERT INTO t VALUES ({id}, {name|escape_sql});'
+
+Placeholders of the form '{column}' are replaced by the value of the column
+in the record. It is an error if a placeholder names a column that doesn't
+exist. To write literal braces, double them, as in '{{' and '}}'.
+
+Values can be passed through filters, as in '{name|trim|upper}':
+
+    upper, lower   The value in upper or lower case.
+    trim           The value without leading and trailing whitespace.
+    escape_sql     The value as a quoted SQL string literal.
+    escape_html    The value with the HTML special characters escaped.
+    escape_shell   The value quoted for a POSIX shell.
+    json           The value as a JSON string.
+
+The rendered blocks are written one after another to stdout, each followed
+by a newline unless the template already ends with one. With --output-dir,
+every block is written to its own file instead, named by the --filename
+template, which can use placeholders in the same way. Values used in file
+names must not contain path separators.
+
+Usage:
+    xsv template [options] --template <tpl> [<input>]
+    xsv template [options] --template-file <file> [<input>]
+    xsv template --help
+
+template options:
+    -t, --template <tpl>        The template to render.
+    --template-file <file>      Read the template from <file>.
+    --output-dir <dir>          Write every block to a file in <dir>.
+    --filename <tpl>            The template of the names of the files in
+                                the output directory, e.g., '{id}.txt'.
+
+Common options:
+    -h, --help                  Display this message
+    -o, --output <file>         Write output to <file> instead of stdout.
+    -n, --no-headers            When set, the first row will not be
+                                interpreted as headers. Placeholders then
+                                give columns by position, as in '{1}'.
+    -d, --delimiter <arg>       The field delimiter for reading CSV data.
+                                Must be a single character (or tab, pipe,
+                                semicolon, space, \\t or \\xHH). (default: ,)
+";
+
+#[derive(Deserialize)]
+struct Args {
+    arg_input: Option<String>,
+    flag_template: Option<String>,
+    flag_template_file: Option<String>,
+    flag_output_dir: Option<String>,
+    flag_filename: Option<String>,
+    flag_output: Option<String>,
+    flag_no_headers: bool,
+    flag_delimiter: Option<Delimiter>,
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Filter {
+    Upper,
+    Lower,
+    Trim,
+    EscapeSql,
+    EscapeHtml,
+    EscapeShell,
+    Json,
+}
+
+impl Filter {
+    fn from_name(name: &str) -> Option<Filter> {
+        Some(match name {
+            "upper" => Filter::Upper,
+            "lower" => Filter::Lower,
+            "trim" => Filter::Trim,
+            "escape_sql" => Filter::EscapeSql,
+            "escape_html" => Filter::EscapeHtml,
+            "escape_shell" => Filter::EscapeShell,
+            "json" => Filter::Json,
+            _ => return None,
+        })
+    }
+
+    fn apply(self, s: String) -> String {
+        match self {
+            Filter::Upper => s.to_uppercase(),
+            Filter::Lower => s.to_lowercase(),
+            Filter::Trim => s.trim().to_owned(),
+            Filter::EscapeSql => {
+                format!("'{}'", s.replace('\0', "").replace('\'', "''"))
+            }
+            Filter::EscapeHtml => {
+                s.replace('&', "&amp;").replace('<', "&lt;")
+                 .replace('>', "&gt;").replace('"', "&quot;")
+                 .replace('\'', "&#39;")
+            }
+            Filter::EscapeShell => shell_quote(&s),
+            Filter::Json => {
+                let mut buf = vec![];
+                json::write_str(&mut buf, s.as_bytes()).unwrap();
+                String::from_utf8(buf).unwrap()
+            }
+        }
+    }
+}
+
+/// Quotes `s` so that a POSIX shell reads it as a single word.
+pub fn shell_quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', "'\\''"))
+}
+
+enum Part {
+    Text(String),
+    Field { column: usize, filters: Vec<Filter> },
+}
+
+/// A parsed template, with the placeholders resolved to column indices.
+pub struct Template {
+    parts: Vec<Part>,
+}
+
+impl Template {
+    pub fn parse(
+        src: &str,
+        headers: &csv::ByteRecord,
+        no_headers: bool,
+    ) -> Result<Template, String> {
+        let mut parts = vec![];
+        let mut text = String::new();
+        let mut chars = src.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    text.push('{');
+                }
+                '}' if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    text.push('}');
+                }
+                '}' => {
+                    return Err(format!(
+                        "Unmatched '}}' in template '{}'. Use '}}}}' for a \
+                         literal brace.", src));
+                }
+                '{' => {
+                    let mut placeholder = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(c) => placeholder.push(c),
+                            None => {
+                                return Err(format!(
+                                    "Unclosed placeholder in template '{}'. \
+                                     Use '{{{{' for a literal brace.", src));
+                            }
+                        }
+                    }
+                    if !text.is_empty() {
+                        parts.push(Part::Text(text.clone()));
+                        text.clear();
+                    }
+                    parts.push(parse_placeholder(&placeholder, headers,
+                                                 no_headers)?);
+                }
+                c => text.push(c),
+            }
+        }
+        if !text.is_empty() {
+            parts.push(Part::Text(text));
+        }
+        Ok(Template { parts: parts })
+    }
+
+    /// Renders the template for `record` into `out`. Every substituted
+    /// value is passed through `hook`, which can change or reject it.
+    pub fn render<F>(
+        &self,
+        record: &csv::ByteRecord,
+        out: &mut String,
+        mut hook: F,
+    ) -> Result<(), String>
+        where F: FnMut(String) -> Result<String, String>
+    {
+        out.clear();
+        for part in &self.parts {
+            match *part {
+                Part::Text(ref text) => out.push_str(text),
+                Part::Field { column, ref filters } => {
+                    let field = record.get(column).unwrap_or(b"");
+                    let mut value = String::from_utf8_lossy(field).into_owned();
+                    for &filter in filters {
+                        value = filter.apply(value);
+                    }
+                    out.push_str(&hook(value)?);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn ends_with_newline(&self) -> bool {
+        match self.parts.last() {
+            Some(&Part::Text(ref text)) => text.ends_with('\n'),
+            _ => false,
+        }
+    }
+}
+
+fn parse_placeholder(
+    placeholder: &str,
+    headers: &csv::ByteRecord,
+    no_headers: bool,
+) -> Result<Part, String> {
+    let mut pieces = placeholder.split('|');
+    let name = pieces.next().unwrap_or("").trim();
+    if name.is_empty() {
+        return Err(format!("The placeholder '{{{}}}' has no column name.",
+                           placeholder));
+    }
+    let column = if no_headers {
+        name.parse::<usize>().ok()
+            .and_then(|i| i.checked_sub(1))
+            .filter(|&i| i < headers.len())
+    } else {
+        headers.iter().position(|h| h == name.as_bytes())
+    };
+    let column = match column {
+        Some(column) => column,
+        None => {
+            return Err(format!(
+                "The placeholder '{{{}}}' names the column '{}', which \
+                 doesn't exist.", placeholder, name));
+        }
+    };
+    let mut filters = vec![];
+    for filter in pieces {
+        let filter = filter.trim();
+        match Filter::from_name(filter) {
+            Some(f) => filters.push(f),
+            None => {
+                return Err(format!(
+                    "Unknown filter '{}' in placeholder '{{{}}}'.",
+                    filter, placeholder));
+            }
+        }
+    }
+    Ok(Part::Field { column: column, filters: filters })
+}
+
+pub fn run(argv: &[&str]) -> CliResult<()> {
+    let args: Args = util::get_args(USAGE, argv)?;
+    let src = match (args.flag_template, args.flag_template_file) {
+        (Some(src), _) => src,
+        (None, Some(path)) => fs::read_to_string(&path).map_err(|err| {
+            format!("Could not read the template file {}: {}", path, err)
+        })?,
+        (None, None) => unreachable!(),
+    };
+    if args.flag_output_dir.is_some() != args.flag_filename.is_some() {
+        return fail_usage!("--output-dir and --filename must be used \
+                            together.");
+    }
+    if args.flag_output_dir.is_some() && args.flag_output.is_some() {
+        return fail_usage!("--output and --output-dir cannot be used \
+                            together.");
+    }
+
+    let rconfig = Config::new(&args.arg_input)
+        .delimiter(args.flag_delimiter)
+        .no_headers(args.flag_no_headers);
+    let mut rdr = rconfig.reader()?;
+    let headers = rdr.byte_headers()?.clone();
+    let template = Template::parse(&src, &headers, rconfig.no_headers)
+        .map_err(CliError::Usage)?;
+    let filename = match args.flag_filename {
+        Some(ref f) => Some(Template::parse(f, &headers, rconfig.no_headers)
+            .map_err(CliError::Usage)?),
+        None => None,
+    };
+
+    let mut out = io::BufWriter::new(
+        Config::new(&args.flag_output).io_writer()?);
+    if let Some(ref dir) = args.flag_output_dir {
+        fs::create_dir_all(dir)?;
+    }
+    let newline = !template.ends_with_newline();
+
+    let mut record = csv::ByteRecord::new();
+    let mut rendered = String::new();
+    let mut name = String::new();
+    let mut written = HashSet::new();
+    while rdr.read_byte_record(&mut record)? {
+        let line = record.position().map_or(0, |p| p.line());
+        template.render(&record, &mut rendered, Ok)?;
+        if newline {
+            rendered.push('\n');
+        }
+        let (dir, filename) = match (&args.flag_output_dir, &filename) {
+            (&Some(ref dir), &Some(ref filename)) => (dir, filename),
+            _ => {
+                out.write_all(rendered.as_bytes())?;
+                continue;
+            }
+        };
+        filename.render(&record, &mut name, |value| {
+            if value.contains('/') || value.contains('\\') || value == ".." {
+                Err(format!(
+                    "The value '{}' on line {} can't be used in a file \
+                     name.", value, line))
+            } else {
+                Ok(value)
+            }
+        }).map_err(CliError::Data)?;
+        if name.is_empty() {
+            return fail_data!(format!(
+                "The file name for line {} is empty.", line));
+        }
+        if !written.insert(name.clone()) {
+            return fail_data!(format!(
+                "The file name '{}' for line {} was already used by an \
+                 earlier record.", name, line));
+        }
+        fs::write(Path::new(dir).join(&name), &rendered)?;
+    }
+    Ok(out.flush()?)
+}
@@ -0,0 +1,322 @@
+use std::collections::HashSet;
+use std::io;
+use std::path::Path;
+#[cfg(feature = "sqlite")]
+use std::process;
+use std::str;
+
+use csv;
+
+use CliResult;
+use config::{Config, Delimiter};
+use json;
+use util;
+
+pub static USAGE: &'static str = "
+Converts CSV data to SQL statements that create a table and insert all
+records into it, or loads the CSV data into an SQLite database directly.
+
+By default (or with --dump), the SQL statements are written to stdout: a
+CREATE TABLE statement followed by INSERT statements that each insert as
+many records as given by --batch-size, all inside a single transaction.
+
+With --db, the same statements are executed on an SQLite database file
+(which is created if it doesn't exist), by running the sqlite3 command-line
+shell, which must be installed and on the PATH. Builds of xsv without the
+'sqlite' feature don't support --db.
+
+The type of each column is inferred from the first --sample-size records:
+INTEGER if all non-empty values are integers, REAL if they are all numbers
+and TEXT otherwise. Note that integers with leading zeros (like 007) are
+TEXT. Empty values in INTEGER and REAL columns become NULL. With --all-text,
+all columns are TEXT. Values that aren't valid UTF-8 or that contain NUL
+bytes can't be SQL strings, so they are inserted as BLOBs.
+
+Usage:
+    xsv tosql [options] [<input>]
+    xsv tosql --help
+
+tosql options:
+    --dump                 Write SQL statements to stdout. This is the
+                           default.
+    --db <file>            Load the CSV data into the SQLite database <file>.
+    -t, --table <name>     The name of the table. By default, this is the
+                           file name of <input> without its extension, or
+                           'data' when reading from stdin.
+    --if-exists <action>   What to do if the table already exists: 'fail',
+                           'replace' it or 'append' to it. [default: fail]
+    --all-text             Don't infer column types.
+    --sample-size <n>      The number of records to infer column types
+                           from. [default: 1000]
+    --batch-size <n>       The number of records inserted by a single
+                           INSERT statement. [default: 500]
+
+Common options:
+    -h, --help             Display this message
+    -o, --output <file>    Write output to <file> instead of stdout.
+    -n, --no-headers       When set, the first row will not be interpreted
+                           as headers. The columns are named field_1,
+                           field_2, and so on.
+    -d, --delimiter <arg>  The field delimiter for reading CSV data.
+                           Must be a single character (or tab, pipe,
+                           semicolon, space, \\t or \\xHH). (default: ,)
+";
+
+#[derive(Deserialize)]
+struct Args {
+    arg_input: Option<String>,
+    flag_dump: bool,
+    flag_db: Option<String>,
+    flag_table: Option<String>,
+    flag_if_exists: IfExists,
+    flag_all_text: bool,
+    flag_sample_size: usize,
+    flag_batch_size: usize,
+    flag_output: Option<String>,
+    flag_no_headers: bool,
+    flag_delimiter: Option<Delimiter>,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+enum IfExists {
+    Fail,
+    Replace,
+    Append,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Type {
+    Integer,
+    Real,
+    Text,
+}
+
+impl Type {
+    fn name(self) -> &'static str {
+        match self {
+            Type::Integer => "INTEGER",
+            Type::Real => "REAL",
+            Type::Text => "TEXT",
+        }
+    }
+
+    /// Returns the most specific type that both `self` and `field` fit.
+    fn merge(self, field: &[u8]) -> Type {
+        if field.is_empty() || self == Type::Text {
+            self
+        } else if self == Type::Integer && util::is_integer(field) {
+            Type::Integer
+        } else if json::is_number(field) {
+            Type::Real
+        } else {
+            Type::Text
+        }
+    }
+}
+
+pub fn run(argv: &[&str]) -> CliResult<()> {
+    let args: Args = util::get_args(USAGE, argv)?;
+    if args.flag_dump && args.flag_db.is_some() {
+        return fail_usage!("--dump and --db cannot be used together.");
+    }
+    if args.flag_batch_size == 0 {
+        return fail_usage!("--batch-size must be greater than 0.");
+    }
+    let rconfig = Config::new(&args.arg_input)
+        .delimiter(args.flag_delimiter)
+        .no_headers(args.flag_no_headers);
+
+    match args.flag_db {
+        None => {
+            let wtr = io::BufWriter::new(
+                Config::new(&args.flag_output).io_writer()?);
+            args.write_sql(&rconfig, wtr)
+        }
+        Some(ref db) => load_db(&args, &rconfig, db),
+    }
+}
+
+/// Executes the SQL statements with the sqlite3 shell.
+#[cfg(feature = "sqlite")]
+fn load_db(args: &Args, rconfig: &Config, db: &str) -> CliResult<()> {
+    let mut child = match process::Command::new("sqlite3")
+        .arg("-bail")
+        .arg(db)
+        .stdin(process::Stdio::piped())
+        .spawn()
+    {
+        Err(ref err) if err.kind() == io::ErrorKind::NotFound => {
+            return fail!("--db requires the sqlite3 command-line shell, \
+                          which was not found on the PATH. Install it, or \
+                          use --dump instead.");
+        }
+        Err(err) => return fail!(format!("Could not run sqlite3: {}", err)),
+        Ok(child) => child,
+    };
+    let result = {
+        let stdin = child.stdin.take().unwrap();
+        args.write_sql(rconfig, io::BufWriter::new(stdin))
+    };
+    let status = child.wait()?;
+    if !status.success() {
+        // sqlite3 reports the reason itself. This also covers errors from
+        // writing the statements, which are caused by sqlite3 exiting early.
+        return fail!(format!("Loading the CSV data into {} failed.", db));
+    }
+    result
+}
+
+#[cfg(not(feature = "sqlite"))]
+fn load_db(_: &Args, _: &Config, _: &str) -> CliResult<()> {
+    fail_usage!("This build of xsv can't load data into SQLite databases. Use \
+                 --dump instead.")
+}
+
+impl Args {
+    fn table_name(&self) -> String {
+        if let Some(ref table) = self.flag_table {
+            return table.clone();
+        }
+        self.arg_input
+            .as_ref()
+            .and_then(|p| Path::new(p).file_stem())
+            .map(|s| s.to_string_lossy().into_owned())
+            .filter(|s| s != "-")
+            .unwrap_or_else(|| "data".to_owned())
+    }
+
+    fn write_sql<W: io::Write>(
+        &self,
+        rconfig: &Config,
+        mut wtr: W,
+    ) -> CliResult<()> {
+        let mut rdr = rconfig.reader()?;
+        let headers = rdr.byte_headers()?.clone();
+        let names: Vec<String> = (0..headers.len()).map(|i| {
+            if rconfig.no_headers {
+                format!("field_{}", i + 1)
+            } else {
+                String::from_utf8_lossy(&headers[i]).into_owned()
+            }
+        }).collect();
+        let mut seen = HashSet::new();
+        for name in &names {
+            if !seen.insert(name.to_lowercase()) {
+                return fail_data!(format!(
+                    "The column name '{}' is used more than once.", name));
+            }
+        }
+
+        let mut sample = vec![];
+        let mut record = csv::ByteRecord::new();
+        let mut types = vec![Type::Integer; names.len()];
+        // Whether each column has a non-empty value in the sample.
+        let mut has_values = vec![false; names.len()];
+        while sample.len() < self.flag_sample_size
+                && rdr.read_byte_record(&mut record)? {
+            for (i, field) in record.iter().enumerate().take(types.len()) {
+                types[i] = types[i].merge(field);
+                has_values[i] |= !field.is_empty();
+            }
+            sample.push(record.clone());
+        }
+        for (t, &has_values) in types.iter_mut().zip(&has_values) {
+            if self.flag_all_text || !has_values {
+                *t = Type::Text;
+            }
+        }
+
+        let table = quote_ident(&self.table_name());
+        writeln!(wtr, "BEGIN;")?;
+        let create = match self.flag_if_exists {
+            IfExists::Fail => "CREATE TABLE",
+            IfExists::Replace => {
+                writeln!(wtr, "DROP TABLE IF EXISTS {};", table)?;
+                "CREATE TABLE"
+            }
+            IfExists::Append => "CREATE TABLE IF NOT EXISTS",
+        };
+        writeln!(wtr, "{} {} (", create, table)?;
+        for (i, (name, t)) in names.iter().zip(&types).enumerate() {
+            let sep = if i + 1 < names.len() { "," } else { "" };
+            writeln!(wtr, "  {} {}{}", quote_ident(name), t.name(), sep)?;
+        }
+        writeln!(wtr, ");")?;
+
+        let columns: Vec<String> = names.iter().map(|n| quote_ident(n))
+                                        .collect();
+        let insert = format!("INSERT INTO {} ({}) VALUES",
+                             table, columns.join(", "));
+        let mut batch = 0;
+        let mut write_record = |wtr: &mut W, record: &csv::ByteRecord| {
+            if batch == 0 {
+                write!(wtr, "{}\n  (", insert)?;
+            } else {
+                write!(wtr, ",\n  (")?;
+            }
+            for (i, t) in types.iter().enumerate() {
+                if i > 0 {
+                    write!(wtr, ", ")?;
+                }
+                write_value(wtr, *t, record.get(i).unwrap_or(b""))?;
+            }
+            write!(wtr, ")")?;
+            batch += 1;
+            if batch == self.flag_batch_size {
+                writeln!(wtr, ";")?;
+                batch = 0;
+            }
+            Ok::<(), io::Error>(())
+        };
+        for record in &sample {
+            write_record(&mut wtr, record)?;
+        }
+        while rdr.read_byte_record(&mut record)? {
+            write_record(&mut wtr, &record)?;
+        }
+        if batch > 0 {
+            writeln!(wtr, ";")?;
+        }
+        writeln!(wtr, "COMMIT;")?;
+        wtr.flush()?;
+        Ok(())
+    }
+}
+
+fn quote_ident(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+fn write_value<W: io::Write>(
+    wtr: &mut W,
+    t: Type,
+    field: &[u8],
+) -> io::Result<()> {
+    let is_literal = match t {
+        _ if field.is_empty() => {
+            if t == Type::Text {
+                return wtr.write_all(b"''");
+            }
+            return wtr.write_all(b"NULL");
+        }
+        Type::Integer => util::is_integer(field),
+        Type::Real => json::is_number(field),
+        Type::Text => false,
+    };
+    if is_literal {
+        return wtr.write_all(field);
+    }
+    match str::from_utf8(field) {
+        Ok(s) if !s.contains('\0') => {
+            write!(wtr, "'{}'", s.replace('\'', "''"))
+        }
+        _ => {
+            wtr.write_all(b"X'")?;
+            for b in field {
+                write!(wtr, "{:02X}", b)?;
+            }
+            wtr.write_all(b"'")
+        }
+    }
+}
@@ -0,0 +1,100 @@
+use std::cmp;
+
+use csv;
+
+use CliResult;
+use config::{Config, Delimiter};
+use util;
+
+pub static USAGE: &'static str = "
+Transposes CSV data: row i, column j of the output is row j, column i of the
+input. Rows that are shorter than the longest row are padded with empty
+fields first.
+
+The header row takes part in the transposition like any other row, so the
+header names become the first column of the output and the first column of
+the input becomes the header row of the output. Since the first row is treated
+the same way whether or not it is a header, there is no --no-headers flag.
+
+Note that this requires reading all of the CSV data into memory. Alternatively,
+the --multipass flag reads the CSV data once for every column and only keeps
+one output row in memory at a time, which is slower but works for data that
+doesn't fit into memory.
+
+Usage:
+    xsv transpose [options] [<input>]
+    xsv transpose --help
+
+transpose options:
+    -m, --multipass        Read the CSV data once per column instead of
+                           reading all of it into memory. The input must be
+                           a file.
+
+Common options:
+    -h, --help             Display this message
+    -o, --output <file>    Write output to <file> instead of stdout.
+    -d, --delimiter <arg>  The field delimiter for reading CSV data.
+                           Must be a single character (or tab, pipe,
+                           semicolon, space, \\t or \\xHH). (default: ,)
+";
+
+#[derive(Deserialize)]
+struct Args {
+    arg_input: Option<String>,
+    flag_multipass: bool,
+    flag_output: Option<String>,
+    flag_delimiter: Option<Delimiter>,
+}
+
+pub fn run(argv: &[&str]) -> CliResult<()> {
+    let args: Args = util::get_args(USAGE, argv)?;
+    let rconfig = Config::new(&args.arg_input)
+        .delimiter(args.flag_delimiter)
+        .no_headers(true)
+        .flexible(true);
+    let mut wtr = Config::new(&args.flag_output).writer()?;
+
+    if args.flag_multipass {
+        if rconfig.is_std() {
+            return fail_usage!("<stdin> cannot be used with --multipass. \
+                                Please specify a file path.");
+        }
+        return transpose_multipass(&rconfig, &mut wtr);
+    }
+    let rows = rconfig.reader()?
+        .byte_records()
+        .collect::<Result<Vec<_>, _>>()?;
+    let width = rows.iter().map(|r| r.len()).max().unwrap_or(0);
+    let mut record = csv::ByteRecord::new();
+    for i in 0..width {
+        record.clear();
+        for row in &rows {
+            record.push_field(row.get(i).unwrap_or(b""));
+        }
+        wtr.write_byte_record(&record)?;
+    }
+    Ok(wtr.flush()?)
+}
+
+fn transpose_multipass<W: ::std::io::Write>(
+    rconfig: &Config,
+    wtr: &mut csv::Writer<W>,
+) -> CliResult<()> {
+    let mut width = 0;
+    let mut row = csv::ByteRecord::new();
+    let mut rdr = rconfig.reader()?;
+    while rdr.read_byte_record(&mut row)? {
+        width = cmp::max(width, row.len());
+    }
+
+    let mut record = csv::ByteRecord::new();
+    for i in 0..width {
+        record.clear();
+        let mut rdr = rconfig.reader()?;
+        while rdr.read_byte_record(&mut row)? {
+            record.push_field(row.get(i).unwrap_or(b""));
+        }
+        wtr.write_byte_record(&record)?;
+    }
+    Ok(wtr.flush()?)
+}
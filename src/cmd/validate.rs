@@ -1,29 +1,35 @@
 use std::fs::File;
-use std::io::prelude::*;
-use std::io::BufReader;
+use std::io::{self, BufRead, BufReader, Write};
 use std::path::Path;
 
-use crate::config::Delimiter;
+use crate::cmd::schema::Schema;
+use crate::config::{Config, Delimiter};
+use crate::json;
 use crate::util;
-use crate::CliResult;
+use crate::{CliError, CliResult};
 
-static USAGE: &'static str = "
+pub static USAGE: &'static str = "
 Validate a CSV file for common errors.
 
 Errors are reported in the format <line no> <expected delimiters> <actual delimiters> <data>
 
+With --schema, the values are instead checked against a schema as written by
+'xsv schema', and errors are reported in the format <line no> <column> <value> <error>
+
 Usage:
     xsv val [options] [<input>]
 
 input options:
     --quote <arg>          The quote character to use. [default: \"]
     --no-quoting           Disable quoting completely.
+    --schema <file>        Check the values against the JSON schema in <file>.
 
 Common options:
     -h, --help             Display this message
     -o, --output <file>    Write output to <file> instead of stdout.
     -d, --delimiter <arg>  The field delimiter for reading CSV data.
-                           Must be a single character. (default: ,)
+                           Must be a single character (or tab, pipe,
+                           semicolon, space, \\t or \\xHH). (default: ,)
 ";
 
 #[derive(Deserialize)]
@@ -33,21 +39,83 @@ struct Args {
     flag_delimiter: Option<Delimiter>,
     flag_quote: Option<Delimiter>,
     flag_no_quoting: bool,
+    flag_schema: Option<String>,
+}
+
+/// The options of `xsv val` (without --schema), which checks that every
+/// line has as many delimiters as the first one.
+#[derive(Clone, Debug)]
+pub struct ValidateOptions {
+    /// The field delimiter.
+    pub delimiter: Delimiter,
+    /// The quote character, within which delimiters aren't counted, or
+    /// `None` to count all delimiters.
+    pub quote: Option<Delimiter>,
+}
+
+impl Default for ValidateOptions {
+    fn default() -> ValidateOptions {
+        ValidateOptions {
+            delimiter: Delimiter(b','),
+            quote: Some(Delimiter(b'"')),
+        }
+    }
+}
+
+/// A line with a different number of delimiters than the first line.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LineError {
+    /// The number of the line, where the line after the first one is 1.
+    pub line: usize,
+    /// The number of delimiters on the first line.
+    pub expected: usize,
+    /// The number of delimiters on this line.
+    pub actual: usize,
+    /// The line, without its line terminator.
+    pub data: String,
+}
+
+/// The result of `validate`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ValidationReport {
+    pub errors: Vec<LineError>,
+}
+
+impl ValidationReport {
+    /// Returns true if all lines have the same number of delimiters.
+    pub fn is_valid(&self) -> bool {
+        self.errors.is_empty()
+    }
+}
+
+/// Checks that every line of the data in `rdr` has as many delimiters as
+/// the first one, as 'xsv val' does.
+pub fn validate<R: io::Read>(
+    opts: &ValidateOptions,
+    rdr: R,
+) -> io::Result<ValidationReport> {
+    let mut reader = BufReader::new(rdr);
+    let delim = opts.delimiter.as_byte();
+    let errs = match opts.quote {
+        Some(qual) => validate_quoted(&mut reader, delim, qual.as_byte())?,
+        None => validate_unquoted(&mut reader, delim)?,
+    };
+    Ok(ValidationReport { errors: errs })
 }
 
 pub fn run(argv: &[&str]) -> CliResult<()> {
-    let args: Args = util::get_args(USAGE, argv).unwrap();
+    let args: Args = util::get_args(USAGE, argv)?;
 
     let delim_arg = if let Some(delim) = args.flag_delimiter {
-        delim.as_byte()
+        delim
     } else {
-        b','
+        Delimiter(b',')
     };
 
     let qual_char = if let Some(qual) = args.flag_quote {
-        qual.as_byte()
+        qual
     } else {
-        b'"'
+        Delimiter(b'"')
     };
 
     let qual = if args.flag_no_quoting {
@@ -56,7 +124,12 @@ pub fn run(argv: &[&str]) -> CliResult<()> {
         Some(qual_char)
     };
 
-    let res = validate_file(delim_arg, qual, !args.flag_no_quoting, &args.arg_input);
+    if let Some(ref schema) = args.flag_schema {
+        return validate_schema(&args, schema);
+    }
+
+    let opts = ValidateOptions { delimiter: delim_arg, quote: qual };
+    let res = validate_file(&opts, &args.arg_input);
 
     match res {
         Ok(_) => {
@@ -75,15 +148,13 @@ pub fn run(argv: &[&str]) -> CliResult<()> {
                 println!("Line_Number,Expected_Delimiters,Actual_Delimiters,Data");
                 e.into_iter().for_each(|s| println!("{}", s));
             }
-            Err("File is invalid".into())
+            Err(CliError::Data("File is invalid".to_owned()))
         }
     }
 }
 
 fn validate_file(
-    delim: u8,
-    qual: Option<u8>,
-    is_quoted: bool,
+    opts: &ValidateOptions,
     file_path: &str,
 ) -> Result<(), Vec<String>> {
     let filepath = validate_path(file_path);
@@ -95,13 +166,14 @@ fn validate_file(
     let file: File = File::open::<&Path>(filepath.unwrap())
         .map_err(|e| Vec::from([format!("Error opening file: {}", e)]))?;
 
-    let mut reader = BufReader::new(file);
-
-    if is_quoted {
-        validate_quoted(&mut reader, delim, qual.unwrap())
-    } else {
-        validate_unquoted(&mut reader, delim)
+    let report = validate(opts, file)
+        .map_err(|e| vec![format!("Error reading line: {}", e)])?;
+    if report.is_valid() {
+        return Ok(());
     }
+    Err(report.errors.iter()
+        .map(|e| fmt_error(e.line, e.expected, e.actual, &e.data))
+        .collect())
 }
 
 fn validate_path(path: &str) -> Result<&Path, String> {
@@ -116,7 +188,11 @@ fn validate_path(path: &str) -> Result<&Path, String> {
     }
 }
 
-fn validate_quoted(reader: &mut BufReader<File>, delim: u8, qual: u8) -> Result<(), Vec<String>> {
+fn validate_quoted<R: BufRead>(
+    reader: &mut R,
+    delim: u8,
+    qual: u8,
+) -> io::Result<Vec<LineError>> {
     let mut qual_flag: bool = false;
     let mut delim_count: usize = 0;
 
@@ -124,7 +200,7 @@ fn validate_quoted(reader: &mut BufReader<File>, delim: u8, qual: u8) -> Result<
     //set expected delims
 
     let mut line = String::new();
-    reader.read_line(&mut line).expect("Error reading line");
+    reader.read_line(&mut line)?;
     let iter = line.bytes();
 
     for ch in iter {
@@ -140,7 +216,7 @@ fn validate_quoted(reader: &mut BufReader<File>, delim: u8, qual: u8) -> Result<
     qual_flag = false;
 
     for (i, line_result) in reader.lines().enumerate() {
-        let line = line_result.expect("Error reading line");
+        let line = line_result?;
 
         for ch in line.bytes() {
             match ch {
@@ -156,20 +232,24 @@ fn validate_quoted(reader: &mut BufReader<File>, delim: u8, qual: u8) -> Result<
             }
         }
         if delim_count != expected_delims {
-            errs.push(fmt_error(i + 1, expected_delims, delim_count, &line));
+            errs.push(LineError {
+                line: i + 1,
+                expected: expected_delims,
+                actual: delim_count,
+                data: line,
+            });
         }
         delim_count = 0;
         qual_flag = false;
     }
 
-    if errs.is_empty() {
-        Ok(())
-    } else {
-        Err(errs)
-    }
+    Ok(errs)
 }
 
-fn validate_unquoted(reader: &mut BufReader<File>, delim: u8) -> Result<(), Vec<String>> {
+fn validate_unquoted<R: BufRead>(
+    reader: &mut R,
+    delim: u8,
+) -> io::Result<Vec<LineError>> {
     let mut delim_count: usize = 0;
 
     let mut errs = Vec::new();
@@ -177,9 +257,7 @@ fn validate_unquoted(reader: &mut BufReader<File>, delim: u8) -> Result<(), Vec<
     //set expected delims
 
     let mut line = String::new();
-    reader
-        .read_line(&mut line)
-        .expect("Error reading line from file");
+    reader.read_line(&mut line)?;
     let iter = line.bytes();
 
     for ch in iter {
@@ -192,7 +270,7 @@ fn validate_unquoted(reader: &mut BufReader<File>, delim: u8) -> Result<(), Vec<
     delim_count = 0;
 
     for (i, line_result) in reader.lines().enumerate() {
-        let line = line_result.expect("Error reading line");
+        let line = line_result?;
 
         for ch in line.bytes() {
             match ch {
@@ -204,18 +282,100 @@ fn validate_unquoted(reader: &mut BufReader<File>, delim: u8) -> Result<(), Vec<
             }
         }
         if delim_count != expected_delims {
-            errs.push(fmt_error(i + 1, expected_delims, delim_count, &line));
+            errs.push(LineError {
+                line: i + 1,
+                expected: expected_delims,
+                actual: delim_count,
+                data: line,
+            });
         }
         delim_count = 0;
     }
 
-    if errs.is_empty() {
-        Ok(())
-    } else {
-        Err(errs)
-    }
+    Ok(errs)
 }
 
 fn fmt_error(line_no: usize, expected: usize, actual: usize, data: &str) -> String {
     format!("{},{},{},\"{}\"", line_no, expected, actual, data)
 }
+
+fn validate_schema(args: &Args, schema_path: &str) -> CliResult<()> {
+    let file = File::open(schema_path)
+        .map_err(|e| io::Error::new(e.kind(), format!(
+            "Error opening schema {}: {}", schema_path, e)))?;
+    let doc = match json::Parser::new(BufReader::new(file)).next_value()? {
+        Some(doc) => doc,
+        None => return fail_usage!(format!(
+            "The schema {} is empty.", schema_path)),
+    };
+    let schema = Schema::from_json(&doc)
+        .map_err(|e| CliError::Usage(
+            format!("Invalid schema {}: {}", schema_path, e)))?;
+
+    let mut rconfig = Config::new(&Some(args.arg_input.clone()))
+        .delimiter(args.flag_delimiter)
+        .quoting(!args.flag_no_quoting);
+    if let Some(quote) = args.flag_quote {
+        rconfig = rconfig.quote(quote.as_byte());
+    }
+    let mut rdr = rconfig.reader()?;
+    let headers = rdr.byte_headers()?.clone();
+
+    let mut errs: Vec<[String; 4]> = Vec::new();
+    let mut columns = Vec::new();
+    for column in &schema.columns {
+        match headers.iter().position(|h| h == column.name.as_bytes()) {
+            Some(i) => columns.push((i, column)),
+            None if column.required => errs.push([
+                "1".to_owned(),
+                column.name.clone(),
+                String::new(),
+                "the column is missing".to_owned(),
+            ]),
+            None => {}
+        }
+    }
+    if !schema.additional {
+        for name in headers.iter() {
+            if !schema.columns.iter().any(|c| c.name.as_bytes() == name) {
+                errs.push([
+                    "1".to_owned(),
+                    String::from_utf8_lossy(name).into_owned(),
+                    String::new(),
+                    "the column is not in the schema".to_owned(),
+                ]);
+            }
+        }
+    }
+
+    let mut record = csv::ByteRecord::new();
+    let mut progress = util::progress(&rconfig);
+    while rdr.read_byte_record(&mut record)? {
+        progress.record(&record);
+        let line = record.position().map_or(0, |p| p.line());
+        for &(i, column) in &columns {
+            let value = record.get(i).unwrap_or(b"");
+            if let Err(e) = column.check(value) {
+                errs.push([
+                    line.to_string(),
+                    column.name.clone(),
+                    String::from_utf8_lossy(value).into_owned(),
+                    e,
+                ]);
+            }
+        }
+    }
+    progress.finish();
+
+    if errs.is_empty() {
+        println!("File is valid");
+        return Ok(());
+    }
+    let mut wtr = Config::new(&args.flag_output).writer()?;
+    wtr.write_record(&["Line_Number", "Column", "Value", "Error"])?;
+    for e in &errs {
+        wtr.write_record(e)?;
+    }
+    wtr.flush()?;
+    Err(CliError::Data("File is invalid".to_owned()))
+}
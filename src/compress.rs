@@ -0,0 +1,256 @@
+//! The parts that the gzip and zstd compressors share: finding repeated
+//! data (LZ77) and building Huffman codes.
+
+use std::cmp::{self, Reverse};
+use std::collections::BinaryHeap;
+
+use byteorder::{ByteOrder, LittleEndian};
+
+/// The shortest repetition that is worth a match.
+const MIN_MATCH: usize = 4;
+
+const HASH_BITS: u32 = 16;
+
+/// A run of literal bytes followed by a copy of `len` bytes from `dist`
+/// bytes back. The last sequence of a block may have no copy.
+#[derive(Clone, Copy, Debug)]
+pub struct Sequence {
+    pub literals: usize,
+    pub len: usize,
+    pub dist: usize,
+}
+
+/// Finds repetitions within a window of data with hash chains.
+///
+/// Positions are counted from the start of the stream, so the data that is
+/// passed in can be a sliding window of it.
+pub struct Matcher {
+    window: usize,
+    max_len: usize,
+    /// The number of earlier positions that are tried for each match.
+    chain: usize,
+    /// Whether to prefer a longer match at the next position.
+    lazy: bool,
+    /// The last position (plus one) with each hash.
+    head: Vec<u64>,
+    /// The previous position (plus one) with the same hash as a position.
+    prev: Vec<u64>,
+    /// The position of the next byte to add to the hash chains.
+    next: u64,
+}
+
+fn hash(data: &[u8]) -> usize {
+    let v = LittleEndian::read_u32(data).wrapping_mul(0x9E37_79B1);
+    (v >> (32 - HASH_BITS)) as usize
+}
+
+impl Matcher {
+    /// Creates a matcher for matches of up to `max_len` bytes and up to
+    /// `window` bytes back, which must be a power of two.
+    pub fn new(
+        window: usize,
+        max_len: usize,
+        chain: usize,
+        lazy: bool,
+    ) -> Matcher {
+        Matcher {
+            window: window,
+            max_len: max_len,
+            chain: chain,
+            lazy: lazy,
+            head: vec![0; 1 << HASH_BITS],
+            prev: vec![0; window],
+            next: 0,
+        }
+    }
+
+    /// Adds the positions before `upto` to the hash chains.
+    fn insert(&mut self, data: &[u8], base: u64, upto: u64) {
+        while self.next < upto {
+            let i = (self.next - base) as usize;
+            if i + MIN_MATCH > data.len() {
+                break;
+            }
+            let h = hash(&data[i..]);
+            self.prev[self.next as usize & (self.window - 1)] = self.head[h];
+            self.head[h] = self.next + 1;
+            self.next += 1;
+        }
+    }
+
+    /// Returns the length and distance of the longest match at `data[i..]`,
+    /// or a length of 0 if there is none.
+    fn longest(&self, data: &[u8], base: u64, i: usize) -> (usize, usize) {
+        let limit = cmp::min(self.max_len, data.len() - i);
+        if limit < MIN_MATCH {
+            return (0, 0);
+        }
+        let pos = base + i as u64;
+        let mut best = (0, 0);
+        let mut cand = self.head[hash(&data[i..])];
+        for _ in 0..self.chain {
+            if cand == 0 || cand - 1 < base {
+                break;
+            }
+            let c = cand - 1;
+            let dist = (pos - c) as usize;
+            if dist > self.window {
+                break;
+            }
+            let j = (c - base) as usize;
+            if data[j + best.0] == data[i + best.0] {
+                let len = data[j..j + limit].iter()
+                    .zip(&data[i..i + limit])
+                    .take_while(|&(a, b)| a == b)
+                    .count();
+                if len > best.0 {
+                    best = (len, dist);
+                    if len == limit {
+                        break;
+                    }
+                }
+            }
+            let next = self.prev[c as usize & (self.window - 1)];
+            if next >= cand {
+                break;
+            }
+            cand = next;
+        }
+        if best.0 < MIN_MATCH { (0, 0) } else { best }
+    }
+
+    /// Splits `data[start..]` into sequences. `data` starts at the stream
+    /// position `base`, and `data[..start]` is data that was passed before.
+    pub fn parse(
+        &mut self,
+        data: &[u8],
+        base: u64,
+        start: usize,
+        out: &mut Vec<Sequence>,
+    ) {
+        if self.next < base {
+            self.next = base;
+        }
+        let mut i = start;
+        let mut literals = start;
+        while i < data.len() {
+            self.insert(data, base, base + i as u64);
+            let (mut len, mut dist) = self.longest(data, base, i);
+            if len == 0 {
+                i += 1;
+                continue;
+            }
+            if self.lazy {
+                self.insert(data, base, base + i as u64 + 1);
+                let (len2, dist2) = self.longest(data, base, i + 1);
+                if len2 > len {
+                    i += 1;
+                    len = len2;
+                    dist = dist2;
+                }
+            }
+            out.push(Sequence {
+                literals: i - literals,
+                len: len,
+                dist: dist,
+            });
+            i += len;
+            literals = i;
+        }
+        if literals < data.len() {
+            out.push(Sequence {
+                literals: data.len() - literals,
+                len: 0,
+                dist: 0,
+            });
+        }
+        self.insert(data, base, base + data.len() as u64);
+    }
+}
+
+/// Returns the lengths of the Huffman codes for symbols with the given
+/// frequencies, where no code is longer than `limit` bits. Symbols that
+/// don't occur get no code. A single symbol gets a code of length 1.
+pub fn code_lengths(freqs: &[u32], limit: u32) -> Vec<u8> {
+    let mut freqs: Vec<u64> = freqs.iter().map(|&f| f as u64).collect();
+    loop {
+        let lens = huffman_lengths(&freqs);
+        if lens.iter().all(|&len| len as u32 <= limit) {
+            return lens;
+        }
+        // Flatten the distribution until the longest code fits.
+        for f in freqs.iter_mut().filter(|f| **f > 0) {
+            *f = (*f + 1) / 2;
+        }
+    }
+}
+
+fn huffman_lengths(freqs: &[u64]) -> Vec<u8> {
+    let mut lens = vec![0; freqs.len()];
+    let used: Vec<usize> =
+        (0..freqs.len()).filter(|&s| freqs[s] > 0).collect();
+    if used.len() == 1 {
+        lens[used[0]] = 1;
+    }
+    if used.len() <= 1 {
+        return lens;
+    }
+    // The nodes of the tree are the symbols, followed by the inner nodes.
+    let mut parent = vec![0; 2 * used.len() - 1];
+    let mut heap = BinaryHeap::new();
+    for (node, &s) in used.iter().enumerate() {
+        heap.push(Reverse((freqs[s], node)));
+    }
+    let mut next = used.len();
+    while heap.len() > 1 {
+        let Reverse((f1, a)) = heap.pop().unwrap();
+        let Reverse((f2, b)) = heap.pop().unwrap();
+        parent[a] = next;
+        parent[b] = next;
+        heap.push(Reverse((f1 + f2, next)));
+        next += 1;
+    }
+    let root = next - 1;
+    let mut depth = vec![0u8; parent.len()];
+    for node in (0..root).rev() {
+        depth[node] = depth[parent[node]] + 1;
+    }
+    for (node, &s) in used.iter().enumerate() {
+        lens[s] = depth[node];
+    }
+    lens
+}
+
+/// Writes bits to a buffer, least significant bit first.
+pub struct BitWriter {
+    pub out: Vec<u8>,
+    bits: u64,
+    nbits: u32,
+}
+
+impl BitWriter {
+    pub fn new() -> BitWriter {
+        BitWriter { out: vec![], bits: 0, nbits: 0 }
+    }
+
+    /// Writes the low `n` bits of `value`, where `n` is at most 32.
+    #[inline]
+    pub fn put(&mut self, value: u64, n: u32) {
+        self.bits |= (value & ((1 << n) - 1)) << self.nbits;
+        self.nbits += n;
+        while self.nbits >= 8 {
+            self.out.push(self.bits as u8);
+            self.bits >>= 8;
+            self.nbits -= 8;
+        }
+    }
+
+    /// Pads the bits written so far with zeros to a whole byte.
+    pub fn align(&mut self) {
+        if self.nbits > 0 {
+            self.out.push(self.bits as u8);
+            self.bits = 0;
+            self.nbits = 0;
+        }
+    }
+}
@@ -1,28 +1,37 @@
 #[allow(deprecated, unused_imports)]
 use std::ascii::AsciiExt;
 use std::borrow::ToOwned;
+use std::cmp;
 use std::env;
 use std::fs;
-use std::io::{self, Read};
+use std::io::{self, Read, Seek, SeekFrom};
 use std::ops::Deref;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::{Mutex, OnceLock};
+use std::sync::atomic::{AtomicUsize, Ordering};
 
 use csv;
-use index::Indexed;
+use gzip::{self, GzDecoder, GzEncoder, GzFile};
+#[cfg(feature = "http")]
+use http::{self, HttpReader};
+use index::{self, FieldRecords, IndexFile, Indexed, MultiIndexed};
 use serde::de::{Deserialize, Deserializer, Error};
 
 use select::{SelectColumns, Selection};
 use util;
+#[cfg(feature = "zstd")]
+use zstd::{ZstdDecoder, ZstdEncoder};
 use CliResult;
 
-#[derive(Clone, Copy, Debug)]
-pub struct Delimiter(pub u8);
-
 /// Delimiter represents values that can be passed from the command line that
 /// can be used as a field delimiter in CSV data.
 ///
 /// Its purpose is to ensure that the Unicode character given decodes to a
 /// valid ASCII character as required by the CSV parser.
+#[derive(Clone, Copy, Debug)]
+pub struct Delimiter(pub u8);
+
 impl Delimiter {
     pub fn as_byte(self) -> u8 {
         self.0
@@ -32,34 +41,146 @@ impl Delimiter {
 impl<'de> Deserialize<'de> for Delimiter {
     fn deserialize<D: Deserializer<'de>>(d: D) -> Result<Delimiter, D::Error> {
         let c = String::deserialize(d)?;
-        match &*c {
-            r"\t" => Ok(Delimiter(b'\t')),
-            s => {
-                if s.len() != 1 {
-                    let msg = format!(
-                        "Could not convert '{}' to a single \
-                                       ASCII character.",
-                        s
-                    );
-                    return Err(D::Error::custom(msg));
-                }
-                let c = s.chars().next().unwrap();
-                if c.is_ascii() {
-                    Ok(Delimiter(c as u8))
-                } else {
-                    let msg = format!(
-                        "Could not convert '{}' \
-                                       to ASCII delimiter.",
-                        c
-                    );
-                    Err(D::Error::custom(msg))
-                }
-            }
+        Delimiter::parse(&c).map_err(D::Error::custom)
+    }
+}
+
+impl Delimiter {
+    /// Parses a delimiter as given on the command line.
+    ///
+    /// Besides a single character, this accepts the escapes `\t` and `\xHH`
+    /// (for an ASCII character in hexadecimal, e.g., `\x1f`) and the names
+    /// `tab`, `pipe`, `semicolon` and `space`.
+    pub fn parse(s: &str) -> Result<Delimiter, String> {
+        match &*s.to_ascii_lowercase() {
+            r"\t" | "tab" => return Ok(Delimiter(b'\t')),
+            "pipe" => return Ok(Delimiter(b'|')),
+            "semicolon" => return Ok(Delimiter(b';')),
+            "space" => return Ok(Delimiter(b' ')),
+            _ => {}
+        }
+        let hex = s.len() == 4 && s.starts_with(r"\x");
+        if hex && s[2..].chars().all(|c| c.is_ascii_hexdigit()) {
+            return match u8::from_str_radix(&s[2..], 16) {
+                Ok(b) if b.is_ascii() => Ok(Delimiter(b)),
+                _ => Err(format!(
+                    "Could not convert '{}' to an ASCII character. \
+                     Hexadecimal escapes must be between \\x00 and \\x7f.",
+                    s)),
+            };
+        }
+        if s.len() != 1 {
+            let msg = format!(
+                "Could not convert '{}' to a single \
+                               ASCII character.",
+                s
+            );
+            return Err(msg);
+        }
+        let c = s.chars().next().unwrap();
+        if c.is_ascii() {
+            Ok(Delimiter(c as u8))
+        } else {
+            let msg = format!(
+                "Could not convert '{}' \
+                               to ASCII delimiter.",
+                c
+            );
+            Err(msg)
+        }
+    }
+}
+
+/// Which values are stripped of leading and trailing whitespace when CSV
+/// data is read, as given with `xsv --trim <mode>`.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Trim {
+    All,
+    Headers,
+    Fields,
+}
+
+impl Trim {
+    fn to_csv(self) -> csv::Trim {
+        match self {
+            Trim::All => csv::Trim::All,
+            Trim::Headers => csv::Trim::Headers,
+            Trim::Fields => csv::Trim::Fields,
         }
     }
 }
 
-#[derive(Debug)]
+/// What happens to records with a different number of fields than the
+/// first record, as given with `xsv --flexible <policy>`.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Flexible {
+    /// Report an error, which is the default.
+    Error,
+    /// Add empty fields to records that are too short. Records that are too
+    /// long are still an error, so that no data is lost.
+    Pad,
+    /// Remove the extra fields of records that are too long. Records that
+    /// are too short are still an error.
+    Truncate,
+    /// Leave out all records with a different number of fields.
+    Skip,
+}
+
+/// The options that are given before the command, e.g., `xsv --trim all
+/// join ...`. They apply to every `Config` of the command.
+#[derive(Clone, Debug, Default)]
+pub struct Globals {
+    pub trim: Option<Trim>,
+    pub comment: Option<u8>,
+    pub flexible: Option<Flexible>,
+    /// The extra headers of HTTP requests, like `Name: value`.
+    #[cfg(feature = "http")]
+    pub http_headers: Vec<String>,
+    /// Whether URLs are downloaded to a temporary file before reading.
+    pub download: bool,
+    /// Whether commands report their progress on stderr.
+    pub progress: bool,
+}
+
+static GLOBALS: OnceLock<Globals> = OnceLock::new();
+
+/// Sets the global options. This is done once, before the command runs.
+pub fn set_globals(globals: Globals) {
+    let _ = GLOBALS.set(globals);
+}
+
+fn globals() -> &'static Globals {
+    GLOBALS.get_or_init(Globals::default)
+}
+
+/// Returns true if commands should report their progress.
+pub fn show_progress() -> bool {
+    globals().progress
+}
+
+/// Returns true if `s` is an `http://` or `https://` URL.
+pub fn is_url(s: &str) -> bool {
+    let s = s.to_ascii_lowercase();
+    s.starts_with("http://") || s.starts_with("https://")
+}
+
+/// Returns the part of an input that its file name extensions are taken
+/// from, which is all of it unless it's a URL with a query or fragment.
+fn name_part(s: &str) -> &str {
+    if is_url(s) {
+        s.split(|c| c == '?' || c == '#').next().unwrap()
+    } else {
+        s
+    }
+}
+
+/// How to read or write CSV data: the path (or stdin and stdout), the
+/// delimiter, quoting, whether there is a header row, and so on. It is made
+/// with `Config::new` and its builder methods, and then opens CSV readers
+/// and writers with `reader` and `writer`.
+#[derive(Clone, Debug)]
 pub struct Config {
     path: Option<PathBuf>, // None implies <stdin>
     idx_path: Option<PathBuf>,
@@ -73,6 +194,10 @@ pub struct Config {
     double_quote: bool,
     escape: Option<u8>,
     quoting: bool,
+    auto_index: bool,
+    trim: Option<Trim>,
+    comment: Option<u8>,
+    ragged: Flexible,
 }
 
 impl Config {
@@ -82,7 +207,15 @@ impl Config {
             Some(ref s) if s.deref() == "-" => (None, b','),
             Some(ref s) => {
                 let path = PathBuf::from(s);
-                let delim = if path.extension().map_or(false, |v| v == "tsv" || v == "tab") {
+                let name = PathBuf::from(name_part(s));
+                // The delimiter of 'data.tsv.gz' is also a tab.
+                let inner = if gzip::is_gzip_path(&name)
+                               || is_zstd_path(&name) {
+                    name.with_extension("")
+                } else {
+                    name
+                };
+                let delim = if inner.extension().map_or(false, |v| v == "tsv" || v == "tab") {
                     b'\t'
                 } else {
                     b','
@@ -103,6 +236,10 @@ impl Config {
             double_quote: true,
             escape: None,
             quoting: true,
+            auto_index: false,
+            trim: globals().trim,
+            comment: globals().comment,
+            ragged: globals().flexible.unwrap_or(Flexible::Error),
         }
     }
 
@@ -121,6 +258,12 @@ impl Config {
         self
     }
 
+    /// When set, a stale index is rebuilt instead of reported as an error.
+    pub fn auto_index(mut self, yes: bool) -> Config {
+        self.auto_index = yes;
+        self
+    }
+
     pub fn flexible(mut self, yes: bool) -> Config {
         self.flexible = yes;
         self
@@ -170,15 +313,71 @@ impl Config {
         self
     }
 
+    /// Returns a copy of this configuration for reading the file at `path`.
+    pub fn with_path(&self, path: PathBuf) -> Config {
+        let mut conf = self.clone();
+        conf.path = Some(path);
+        conf.idx_path = None;
+        conf
+    }
+
     pub fn is_std(&self) -> bool {
         self.path.is_none()
     }
 
-    pub fn selection(&self, first_record: &csv::ByteRecord) -> Result<Selection, String> {
+    pub fn get_delimiter(&self) -> u8 {
+        self.delimiter
+    }
+
+    pub fn get_quote(&self) -> u8 {
+        self.quote
+    }
+
+    /// Returns what happens to records with the wrong number of fields.
+    pub fn get_flexible(&self) -> Flexible {
+        self.ragged
+    }
+
+    /// Returns the byte that starts the comment lines which are skipped.
+    pub fn get_comment(&self) -> Option<u8> {
+        self.comment
+    }
+
+    /// Returns the size of the CSV data if it's read from a file as it is,
+    /// so that the size is known and the positions of records are offsets
+    /// in the file.
+    pub fn input_size(&self) -> Option<u64> {
+        match self.path {
+            Some(ref p) if self.url().is_none()
+                           && !gzip::is_gzip_path(p)
+                           && !is_zstd_path(p) => {
+                fs::metadata(p).ok().filter(|md| md.is_file())
+                                    .map(|md| md.len())
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns the input if it's a URL.
+    fn url(&self) -> Option<&str> {
+        self.path.as_ref().and_then(|p| p.to_str()).filter(|s| is_url(s))
+    }
+
+    /// Returns the path of the input for use in messages.
+    pub fn display_path(&self) -> String {
+        match self.path {
+            None => "<stdin>".to_owned(),
+            Some(ref p) => p.display().to_string(),
+        }
+    }
+
+    pub fn selection(
+        &self,
+        first_record: &csv::ByteRecord,
+    ) -> CliResult<Selection> {
         match self.select_columns {
-            None => Err("Config has no 'SelectColums'. Did you call \
-                         Config::select?"
-                .to_owned()),
+            None => fail!("Config has no 'SelectColums'. Did you call \
+                           Config::select?"),
             Some(ref sel) => sel.selection(first_record, !self.no_headers),
         }
     }
@@ -202,69 +401,164 @@ impl Config {
     }
 
     pub fn reader(&self) -> io::Result<csv::Reader<Box<io::Read + 'static>>> {
-        Ok(self.from_reader(self.io_reader()?))
+        Ok(self.from_reader(self.records_io_reader()?))
+    }
+
+    /// Returns a seekable reader of the input file.
+    ///
+    /// Unless records with the wrong number of fields are errors, they are
+    /// fixed while the input is copied to a temporary file, which is read
+    /// instead.
+    pub fn reader_file(&self) -> io::Result<csv::Reader<DataFile>> {
+        if self.ragged == Flexible::Error {
+            return self.data_file(vec![]).map(|f| self.from_reader(f));
+        }
+        let mut tmp = temp_file()?;
+        io::copy(&mut self.records_io_reader()?, &mut tmp)?;
+        tmp.seek(SeekFrom::Start(0))?;
+        Ok(self.from_reader(DataFile::Plain(tmp)))
     }
 
-    pub fn reader_file(&self) -> io::Result<csv::Reader<fs::File>> {
+    /// Opens the input file, which is decompressed if it's gzip compressed.
+    /// Seeking within compressed data starts at the closest of the given
+    /// checkpoints. Zstd compressed data can't be seeked, so it can't be
+    /// opened here.
+    fn data_file(
+        &self,
+        checkpoints: Vec<gzip::Checkpoint>,
+    ) -> io::Result<DataFile> {
+        if let Some(url) = self.url() {
+            if !globals().download {
+                return Err(io::Error::new(io::ErrorKind::Other, format!(
+                    "Cannot seek in CSV data read from a URL ({}). Please \
+                     download it first, e.g., with the global --download \
+                     option.", url)));
+            }
+            return self.with_path(downloaded(url)?).data_file(checkpoints);
+        }
         match self.path {
             None => Err(io::Error::new(
                 io::ErrorKind::Other,
                 "Cannot use <stdin> here",
             )),
-            Some(ref p) => fs::File::open(p).map(|f| self.from_reader(f)),
+            Some(ref p) if gzip::is_gzip_path(p) => {
+                Ok(DataFile::Gzip(GzFile::new(fs::File::open(p)?, checkpoints)?))
+            }
+            Some(ref p) if is_zstd_path(p) => Err(io::Error::new(
+                io::ErrorKind::Other,
+                format!("Cannot use an index with zstd compressed data ({}). \
+                         Please decompress it or compress it with gzip \
+                         instead.", p.display()),
+            )),
+            Some(ref p) => fs::File::open(p).map(DataFile::Plain),
         }
     }
 
-    pub fn index_files(&self) -> io::Result<Option<(csv::Reader<fs::File>, fs::File)>> {
-        let (csv_file, idx_file) = match (&self.path, &self.idx_path) {
-            (&None, &None) => return Ok(None),
-            (&None, &Some(_)) => {
-                return Err(io::Error::new(
-                    io::ErrorKind::Other,
-                    "Cannot use <stdin> with indexes",
-                    // Some(format!("index file: {}", p.display()))
-                ))
+    pub fn index_files(
+        &self,
+    ) -> CliResult<Option<(csv::Reader<DataFile>, IndexFile)>> {
+        // The records of an index are those of the file as it is.
+        if self.ragged != Flexible::Error || self.url().is_some() {
+            return Ok(None);
+        }
+        let (csv_file, idx_file, idx_path) =
+            match (&self.path, &self.idx_path) {
+                (&None, &None) => return Ok(None),
+                (&None, &Some(_)) => {
+                    return fail!(io::Error::new(
+                        io::ErrorKind::Other,
+                        "Cannot use <stdin> with indexes",
+                        // Some(format!("index file: {}", p.display()))
+                    ))
+                }
+                (&Some(ref p), &None) => {
+                    // We generally don't want to report an error here, since
+                    // we're passively trying to find an index.
+                    let ip = util::idx_path(p);
+                    let idx_file = match IndexFile::open(&ip) {
+                        // TODO: Maybe we should report an error if the file
+                        // exists but is not readable.
+                        Err(_) => return Ok(None),
+                        Ok(f) => f,
+                    };
+                    (fs::File::open(p)?, idx_file, ip)
+                }
+                (&Some(ref p), &Some(ref ip)) => {
+                    (fs::File::open(p)?, IndexFile::open(ip)?, ip.clone())
+                }
+            };
+        // If the CSV data changed after the index was created, then either
+        // rebuild the index or demand that the user regenerates it.
+        let md = csv_file.metadata()?;
+        let mut idx_file = match idx_file.staleness(&md, self.comment)? {
+            None => idx_file,
+            Some(_) if self.auto_index => {
+                index::create(
+                    self, &idx_path, idx_file.has_fields(), index::DEFAULT_SPAN)?;
+                IndexFile::open(&idx_path)?
             }
-            (&Some(ref p), &None) => {
-                // We generally don't want to report an error here, since we're
-                // passively trying to find an index.
-                let idx_file = match fs::File::open(&util::idx_path(p)) {
-                    // TODO: Maybe we should report an error if the file exists
-                    // but is not readable.
-                    Err(_) => return Ok(None),
-                    Ok(f) => f,
-                };
-                (fs::File::open(p)?, idx_file)
+            Some(reason) => {
+                return fail!(format!(
+                    "The index {} is stale: {}. Please re-run 'xsv index' \
+                     (or use --auto-index).", idx_path.display(), reason));
             }
-            (&Some(ref p), &Some(ref ip)) => (fs::File::open(p)?, fs::File::open(ip)?),
         };
-        // If the CSV data was last modified after the index file was last
-        // modified, then return an error and demand the user regenerate the
-        // index.
-        let data_modified = util::last_modified(&csv_file.metadata()?);
-        let idx_modified = util::last_modified(&idx_file.metadata()?);
-        if data_modified > idx_modified {
-            return Err(io::Error::new(
-                io::ErrorKind::Other,
-                "The CSV file was modified after the index file. \
-                 Please re-create the index.",
-            ));
-        }
-        let csv_rdr = self.from_reader(csv_file);
-        Ok(Some((csv_rdr, idx_file)))
+        let csv_file = self.data_file(idx_file.checkpoints()?)?;
+        Ok(Some((self.from_reader(csv_file), idx_file)))
     }
 
-    pub fn indexed(&self) -> CliResult<Option<Indexed<fs::File, fs::File>>> {
+    pub fn indexed(
+        &self,
+    ) -> CliResult<Option<Indexed<DataFile, IndexFile>>> {
         match self.index_files()? {
             None => Ok(None),
             Some((r, i)) => Ok(Some(Indexed::open(r, i)?)),
         }
     }
 
+    /// Returns the shards of a multi-index if the input is a multi-index
+    /// created with `xsv index --multi`.
+    pub fn multi_indexed(&self) -> CliResult<Option<MultiIndexed>> {
+        match self.path {
+            Some(ref p) if index::is_multi(p) => {
+                if self.ragged != Flexible::Error {
+                    return fail_usage!("--flexible cannot be used with a \
+                                        multi-index.");
+                }
+                Ok(Some(MultiIndexed::open(self, p)?))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// Returns a reader of the raw records and their field offsets if the
+    /// CSV data has an up to date index created with `xsv index --fields`.
+    pub fn field_records(&self) -> CliResult<Option<FieldRecords>> {
+        // The raw records aren't trimmed.
+        if self.trim.is_some() {
+            return Ok(None);
+        }
+        match self.index_files()? {
+            None => Ok(None),
+            Some((r, i)) => Ok(i.field_records(r.into_inner())?),
+        }
+    }
+
+    /// Returns a reader of the input, which is decompressed if its file
+    /// name ends in `.gz` or `.zst`. Compressed data on <stdin> is detected
+    /// by its first bytes.
     pub fn io_reader(&self) -> io::Result<Box<io::Read + 'static>> {
+        if let Some(url) = self.url() {
+            if globals().download {
+                return sniff_compression(fs::File::open(downloaded(url)?)?);
+            }
+            return sniff_compression(http_reader(url)?);
+        }
         Ok(match self.path {
-            None => Box::new(io::stdin()),
+            None => sniff_compression(io::stdin())?,
             Some(ref p) => match fs::File::open(p) {
+                Ok(x) if gzip::is_gzip_path(p) => Box::new(GzDecoder::new(x)),
+                Ok(x) if is_zstd_path(p) => zstd_decoder(x)?,
                 Ok(x) => Box::new(x),
                 Err(err) => {
                     let msg = format!("failed to open {}: {}", p.display(), err);
@@ -274,6 +568,16 @@ impl Config {
         })
     }
 
+    /// Returns a reader of the CSV data of the input, in which records with
+    /// the wrong number of fields are fixed according to `--flexible`.
+    pub fn records_io_reader(&self) -> io::Result<Box<io::Read + 'static>> {
+        let rdr = self.io_reader()?;
+        Ok(match self.ragged {
+            Flexible::Error => rdr,
+            ragged => Box::new(FixLengths::new(self, rdr, ragged)),
+        })
+    }
+
     pub fn from_reader<R: Read>(&self, rdr: R) -> csv::Reader<R> {
         csv::ReaderBuilder::new()
             .flexible(self.flexible)
@@ -282,12 +586,25 @@ impl Config {
             .quote(self.quote)
             .quoting(self.quoting)
             .escape(self.escape)
+            .trim(self.trim.map_or(csv::Trim::None, Trim::to_csv))
+            .comment(self.comment)
             .from_reader(rdr)
     }
 
+    /// Returns a writer of the output, which is compressed if its file name
+    /// ends in `.gz` or `.zst`. The compressed data is complete whenever the
+    /// writer was flushed.
     pub fn io_writer(&self) -> io::Result<Box<io::Write + 'static>> {
         Ok(match self.path {
             None => Box::new(io::stdout()),
+            Some(ref p) if gzip::is_gzip_path(p) => {
+                let level = compress_level(0, 9, 6)?;
+                Box::new(GzEncoder::new(fs::File::create(p)?, level))
+            }
+            Some(ref p) if is_zstd_path(p) => {
+                let level = compress_level(1, 19, 3)?;
+                zstd_encoder(fs::File::create(p)?, level)?
+            }
             Some(ref p) => Box::new(fs::File::create(p)?),
         })
     }
@@ -301,7 +618,308 @@ impl Config {
             .quote_style(self.quote_style)
             .double_quote(self.double_quote)
             .escape(self.escape.unwrap_or(b'\\'))
+            .comment(self.comment)
             .buffer_capacity(32 * (1 << 10))
             .from_writer(wtr)
     }
 }
+
+/// The first bytes of gzip and zstd compressed data.
+const GZIP_MAGIC: [u8; 2] = [0x1F, 0x8B];
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xB5, 0x2F, 0xFD];
+
+/// Returns true if `path` looks like it names zstd compressed data.
+fn is_zstd_path(path: &Path) -> bool {
+    path.extension().map_or(false, |ext| ext == "zst")
+}
+
+#[cfg(feature = "zstd")]
+fn zstd_decoder<R: Read + 'static>(rdr: R) -> io::Result<Box<dyn Read>> {
+    Ok(Box::new(ZstdDecoder::new(rdr)))
+}
+
+#[cfg(not(feature = "zstd"))]
+fn zstd_decoder<R: Read + 'static>(_: R) -> io::Result<Box<dyn Read>> {
+    Err(io::Error::new(
+        io::ErrorKind::Other,
+        "This build of xsv can't read zstd compressed data.",
+    ))
+}
+
+#[cfg(feature = "zstd")]
+fn zstd_encoder<W: io::Write + 'static>(
+    wtr: W,
+    level: u32,
+) -> io::Result<Box<dyn io::Write>> {
+    Ok(Box::new(ZstdEncoder::new(wtr, level)))
+}
+
+#[cfg(not(feature = "zstd"))]
+fn zstd_encoder<W: io::Write + 'static>(
+    _: W,
+    _: u32,
+) -> io::Result<Box<dyn io::Write>> {
+    Err(io::Error::new(
+        io::ErrorKind::Other,
+        "This build of xsv can't write zstd compressed data.",
+    ))
+}
+
+/// Returns the compression level set with `XSV_COMPRESS_LEVEL`, which must
+/// be between `min` and `max`, or `default`.
+fn compress_level(min: u32, max: u32, default: u32) -> io::Result<u32> {
+    let level = match env::var("XSV_COMPRESS_LEVEL") {
+        Err(_) => return Ok(default),
+        Ok(level) => level,
+    };
+    match level.trim().parse() {
+        Ok(n) if n >= min && n <= max => Ok(n),
+        _ => Err(io::Error::new(
+            io::ErrorKind::Other,
+            format!("Invalid XSV_COMPRESS_LEVEL '{}' (must be between {} \
+                     and {}).", level, min, max),
+        )),
+    }
+}
+
+/// Returns a reader of `rdr` that decompresses it if its first bytes are
+/// those of gzip or zstd compressed data.
+fn sniff_compression<R: Read + 'static>(
+    mut rdr: R,
+) -> io::Result<Box<dyn Read>> {
+    let mut magic = [0; 4];
+    let mut n = 0;
+    while n < magic.len() {
+        match rdr.read(&mut magic[n..]) {
+            Ok(0) => break,
+            Ok(k) => n += k,
+            Err(ref err) if err.kind() == io::ErrorKind::Interrupted => {}
+            Err(err) => return Err(err),
+        }
+    }
+    let magic = &magic[..n];
+    let rdr = io::Cursor::new(magic.to_vec()).chain(rdr);
+    Ok(if magic.starts_with(&GZIP_MAGIC) {
+        Box::new(GzDecoder::new(rdr))
+    } else if magic == ZSTD_MAGIC {
+        zstd_decoder(rdr)?
+    } else {
+        Box::new(rdr)
+    })
+}
+
+/// Reads CSV data and writes it out again with the records that have a
+/// different number of fields than the first one padded, truncated or left
+/// out.
+struct FixLengths<R> {
+    rdr: csv::Reader<R>,
+    wtr: csv::WriterBuilder,
+    ragged: Flexible,
+    /// The number of fields of the first record.
+    len: Option<usize>,
+    skipped: u64,
+    record: csv::ByteRecord,
+    /// The CSV data of the records that were fixed, and how much of it was
+    /// already read.
+    buf: Vec<u8>,
+    pos: usize,
+    done: bool,
+}
+
+impl<R: Read> FixLengths<R> {
+    fn new(conf: &Config, rdr: R, ragged: Flexible) -> FixLengths<R> {
+        let mut wtr = csv::WriterBuilder::new();
+        wtr.delimiter(conf.delimiter)
+            .terminator(csv::Terminator::Any(b'\n'))
+            .quote(conf.quote)
+            .quote_style(if conf.quoting {
+                csv::QuoteStyle::Necessary
+            } else {
+                csv::QuoteStyle::Never
+            })
+            .comment(conf.comment);
+        FixLengths {
+            rdr: csv::ReaderBuilder::new()
+                .flexible(true)
+                .delimiter(conf.delimiter)
+                .has_headers(false)
+                .quote(conf.quote)
+                .quoting(conf.quoting)
+                .escape(conf.escape)
+                .comment(conf.comment)
+                .from_reader(rdr),
+            wtr: wtr,
+            ragged: ragged,
+            len: None,
+            skipped: 0,
+            record: csv::ByteRecord::new(),
+            buf: vec![],
+            pos: 0,
+            done: false,
+        }
+    }
+
+    /// Fixes the next records, until about 64KB of CSV data is written.
+    fn fill(&mut self) -> io::Result<()> {
+        let mut wtr = self.wtr.from_writer(vec![]);
+        let mut size = 0;
+        while size < 1 << 16 {
+            if !self.rdr.read_byte_record(&mut self.record)? {
+                self.done = true;
+                if self.skipped > 0 {
+                    werr!("skipped {} record(s) with the wrong number of \
+                           fields", self.skipped);
+                }
+                break;
+            }
+            let len = *self.len.get_or_insert(self.record.len());
+            let n = self.record.len();
+            if n < len && self.ragged == Flexible::Pad {
+                for _ in n..len {
+                    self.record.push_field(b"");
+                }
+            } else if n > len && self.ragged == Flexible::Truncate {
+                self.record.truncate(len);
+            } else if n != len && self.ragged == Flexible::Skip {
+                self.skipped += 1;
+                continue;
+            } else if n != len {
+                let pos = self.record.position().expect("position");
+                let msg = format!(
+                    "CSV error: record {} (line: {}, byte: {}): found record \
+                     with {} fields, but the first record (the header) has \
+                     {} fields",
+                    pos.record(), pos.line(), pos.byte(), n, len);
+                return Err(io::Error::new(io::ErrorKind::InvalidData, msg));
+            }
+            wtr.write_byte_record(&self.record)?;
+            size += self.record.as_slice().len() + self.record.len();
+        }
+        self.buf = wtr.into_inner().map_err(|err| err.into_error())?;
+        self.pos = 0;
+        Ok(())
+    }
+}
+
+impl<R: Read> Read for FixLengths<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.pos == self.buf.len() {
+            if self.done {
+                return Ok(0);
+            }
+            self.fill()?;
+        }
+        let n = cmp::min(buf.len(), self.buf.len() - self.pos);
+        buf[..n].copy_from_slice(&self.buf[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+/// Returns a new path for a temporary file whose name ends in `name`.
+fn temp_path(name: &str) -> PathBuf {
+    static NEXT: AtomicUsize = AtomicUsize::new(0);
+    env::temp_dir().join(format!(
+        "xsv-{}-{}-{}",
+        process::id(), NEXT.fetch_add(1, Ordering::SeqCst), name))
+}
+
+/// Creates a temporary file, which is removed as soon as it's closed (on
+/// platforms that permit removing open files).
+fn temp_file() -> io::Result<fs::File> {
+    let path = temp_path("data.csv");
+    let file = fs::OpenOptions::new()
+        .read(true).write(true).create_new(true)
+        .open(&path)?;
+    let _ = fs::remove_file(&path);
+    Ok(file)
+}
+
+/// The URLs that were downloaded with `--download`, and the temporary files
+/// they were downloaded to.
+static DOWNLOADS: Mutex<Vec<(String, PathBuf)>> = Mutex::new(Vec::new());
+
+/// Returns the temporary file that `url` is downloaded to, downloading it
+/// unless it was already.
+fn downloaded(url: &str) -> io::Result<PathBuf> {
+    let mut downloads = DOWNLOADS.lock().unwrap();
+    if let Some(&(_, ref path)) = downloads.iter().find(|d| d.0 == url) {
+        return Ok(path.clone());
+    }
+    // The file name keeps the extensions, like '.gz', of the URL.
+    let name = Path::new(name_part(url)).file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or("download")
+        .to_owned();
+    let path = temp_path(&name);
+    if let Err(err) = http_download(url, &path) {
+        let _ = fs::remove_file(&path);
+        return Err(err);
+    }
+    downloads.push((url.to_owned(), path.clone()));
+    Ok(path)
+}
+
+/// Removes the temporary files that URLs were downloaded to.
+pub fn remove_downloads() {
+    for (_, path) in DOWNLOADS.lock().unwrap().drain(..) {
+        let _ = fs::remove_file(path);
+    }
+}
+
+#[cfg(feature = "http")]
+fn http_reader(url: &str) -> io::Result<Box<dyn Read>> {
+    Ok(Box::new(HttpReader::open(url, &globals().http_headers)?))
+}
+
+#[cfg(not(feature = "http"))]
+fn http_reader(_: &str) -> io::Result<Box<dyn Read>> {
+    Err(io::Error::new(
+        io::ErrorKind::Other,
+        "This build of xsv can't read from URLs.",
+    ))
+}
+
+#[cfg(feature = "http")]
+fn http_download(url: &str, path: &Path) -> io::Result<()> {
+    http::download(url, &globals().http_headers, path)
+}
+
+#[cfg(not(feature = "http"))]
+fn http_download(_: &str, _: &Path) -> io::Result<()> {
+    http_reader("").map(|_| ())
+}
+
+/// An input file, which is transparently decompressed if it's gzip
+/// compressed.
+pub enum DataFile {
+    Plain(fs::File),
+    Gzip(GzFile),
+}
+
+impl DataFile {
+    pub fn metadata(&self) -> io::Result<fs::Metadata> {
+        match *self {
+            DataFile::Plain(ref f) => f.metadata(),
+            DataFile::Gzip(ref f) => f.get_ref().metadata(),
+        }
+    }
+}
+
+impl io::Read for DataFile {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match *self {
+            DataFile::Plain(ref mut f) => f.read(buf),
+            DataFile::Gzip(ref mut f) => f.read(buf),
+        }
+    }
+}
+
+impl io::Seek for DataFile {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        match *self {
+            DataFile::Plain(ref mut f) => f.seek(pos),
+            DataFile::Gzip(ref mut f) => f.seek(pos),
+        }
+    }
+}
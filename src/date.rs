@@ -0,0 +1,373 @@
+//! Parsing and formatting of dates with strftime-like formats.
+//!
+//! The supported conversion specifications are:
+//!
+//! * `%Y` the year, `%y` the year without the century (69-99 are 1969-1999)
+//! * `%m` the month, `%b` or `%h` its abbreviated name, `%B` its full name
+//! * `%d` the day of the month, `%e` the same padded with a space
+//! * `%j` the day of the year
+//! * `%a` the abbreviated name of the weekday, `%A` its full name
+//! * `%H` the hour, `%I` the hour on a 12-hour clock, `%p` AM or PM
+//! * `%M` the minute, `%S` the second
+//! * `%s` the number of seconds since 1970-01-01 00:00:00 UTC
+//! * `%z` the offset from UTC like `+0100`, which can also be written as
+//!   `+01:00`, `+01` or `Z` when parsing
+//! * `%f` a fraction of a second, which is ignored and can only be parsed
+//! * `%F` is `%Y-%m-%d`, `%T` is `%H:%M:%S` and `%%` is a literal `%`
+//!
+//! When parsing, names are matched case insensitively, weekday names are
+//! ignored and whitespace in the format matches any amount of whitespace.
+//! Dates without an offset are taken to be in UTC.
+
+const MONTHS: [&'static str; 12] = [
+    "January", "February", "March", "April", "May", "June", "July",
+    "August", "September", "October", "November", "December",
+];
+
+const WEEKDAYS: [&'static str; 7] = [
+    "Sunday", "Monday", "Tuesday", "Wednesday", "Thursday", "Friday",
+    "Saturday",
+];
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct DateTime {
+    pub year: i64,
+    pub month: u32,
+    pub day: u32,
+    pub hour: u32,
+    pub minute: u32,
+    pub second: u32,
+    /// The offset from UTC in seconds, if it is known.
+    pub offset: Option<i32>,
+}
+
+/// Converts a number of days since 1970-01-01 to a (year, month, day).
+pub fn civil_from_days(z: i64) -> (i64, u32, u32) {
+    let z = z + 719_468;
+    let era = if z >= 0 { z } else { z - 146_096 } / 146_097;
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let d = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let m = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let y = yoe + era * 400 + if m <= 2 { 1 } else { 0 };
+    (y, m, d)
+}
+
+/// Converts a (year, month, day) to a number of days since 1970-01-01.
+pub fn days_from_civil(y: i64, m: u32, d: u32) -> i64 {
+    let y = if m <= 2 { y - 1 } else { y };
+    let era = if y >= 0 { y } else { y - 399 } / 400;
+    let yoe = y - era * 400;
+    let mp = if m > 2 { m - 3 } else { m + 9 } as i64;
+    let doy = (153 * mp + 2) / 5 + d as i64 - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+fn is_leap_year(y: i64) -> bool {
+    y % 4 == 0 && (y % 100 != 0 || y % 400 == 0)
+}
+
+fn days_in_month(y: i64, m: u32) -> u32 {
+    match m {
+        2 if is_leap_year(y) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+impl DateTime {
+    fn from_timestamp(secs: i64) -> DateTime {
+        let days = secs.div_euclid(86_400);
+        let rem = secs.rem_euclid(86_400) as u32;
+        let (year, month, day) = civil_from_days(days);
+        DateTime {
+            year: year,
+            month: month,
+            day: day,
+            hour: rem / 3600,
+            minute: rem / 60 % 60,
+            second: rem % 60,
+            offset: None,
+        }
+    }
+
+    /// Converts a date with an offset to UTC.
+    pub fn to_utc(&self) -> DateTime {
+        let utc = DateTime::from_timestamp(self.timestamp());
+        DateTime { offset: Some(0), ..utc }
+    }
+
+    /// Converts a number of milliseconds since 1970-01-01 00:00:00 UTC to a
+    /// date.
+    pub fn from_millis(millis: i64) -> DateTime {
+        DateTime::from_timestamp(millis.div_euclid(1000))
+    }
+
+    /// The number of seconds since 1970-01-01 00:00:00 UTC.
+    pub fn timestamp(&self) -> i64 {
+        days_from_civil(self.year, self.month, self.day) * 86_400
+            + (self.hour * 3600 + self.minute * 60 + self.second) as i64
+            - self.offset.unwrap_or(0) as i64
+    }
+
+    /// The day of the week, where 0 is Sunday.
+    pub fn weekday(&self) -> u32 {
+        (days_from_civil(self.year, self.month, self.day) + 4)
+            .rem_euclid(7) as u32
+    }
+
+    /// The day of the year, starting at 1.
+    pub fn ordinal(&self) -> u32 {
+        (days_from_civil(self.year, self.month, self.day)
+            - days_from_civil(self.year, 1, 1)) as u32 + 1
+    }
+
+    /// Parses `s` according to `fmt`, which must match all of `s`. Fields
+    /// that aren't in the format default to the start of their range.
+    pub fn parse(s: &str, fmt: &str) -> Option<DateTime> {
+        let mut p = Parser {
+            s: s,
+            dt: DateTime { year: 1970, month: 1, day: 1, ..DateTime::default() },
+            hour12: None,
+            pm: None,
+            ordinal: None,
+        };
+        p.parse(fmt)?;
+        if !p.s.is_empty() {
+            return None;
+        }
+        p.finish()
+    }
+
+    /// Formats the date according to `fmt`. Unknown conversions are
+    /// written as is.
+    pub fn format(&self, fmt: &str) -> String {
+        let mut out = String::new();
+        let mut chars = fmt.chars();
+        while let Some(c) = chars.next() {
+            if c != '%' {
+                out.push(c);
+                continue;
+            }
+            let month = MONTHS[self.month as usize - 1];
+            let weekday = WEEKDAYS[self.weekday() as usize];
+            let hour12 = if self.hour % 12 == 0 { 12 } else { self.hour % 12 };
+            let s = match chars.next() {
+                Some('Y') => format!("{:04}", self.year),
+                Some('y') => format!("{:02}", self.year.rem_euclid(100)),
+                Some('m') => format!("{:02}", self.month),
+                Some('b') | Some('h') => month[..3].to_owned(),
+                Some('B') => month.to_owned(),
+                Some('d') => format!("{:02}", self.day),
+                Some('e') => format!("{:2}", self.day),
+                Some('j') => format!("{:03}", self.ordinal()),
+                Some('a') => weekday[..3].to_owned(),
+                Some('A') => weekday.to_owned(),
+                Some('H') => format!("{:02}", self.hour),
+                Some('I') => format!("{:02}", hour12),
+                Some('p') => if self.hour < 12 { "AM" } else { "PM" }.to_owned(),
+                Some('M') => format!("{:02}", self.minute),
+                Some('S') => format!("{:02}", self.second),
+                Some('s') => self.timestamp().to_string(),
+                Some('z') => {
+                    let offset = self.offset.unwrap_or(0);
+                    let sign = if offset < 0 { '-' } else { '+' };
+                    let minutes = offset.abs() / 60;
+                    format!("{}{:02}{:02}", sign, minutes / 60, minutes % 60)
+                }
+                Some('F') => self.format("%Y-%m-%d"),
+                Some('T') => self.format("%H:%M:%S"),
+                Some('%') => "%".to_owned(),
+                Some(c) => format!("%{}", c),
+                None => "%".to_owned(),
+            };
+            out.push_str(&s);
+        }
+        out
+    }
+}
+
+struct Parser<'a> {
+    s: &'a str,
+    dt: DateTime,
+    hour12: Option<u32>,
+    pm: Option<bool>,
+    ordinal: Option<u32>,
+}
+
+impl<'a> Parser<'a> {
+    fn parse(&mut self, fmt: &str) -> Option<()> {
+        let mut chars = fmt.chars();
+        while let Some(c) = chars.next() {
+            if c.is_whitespace() {
+                self.s = self.s.trim_start();
+                continue;
+            }
+            if c != '%' {
+                self.s = strip_prefix(self.s, c)?;
+                continue;
+            }
+            match chars.next()? {
+                'Y' => {
+                    let neg = self.s.starts_with('-');
+                    if neg {
+                        self.s = &self.s[1..];
+                    }
+                    let y = self.number(4)? as i64;
+                    self.dt.year = if neg { -y } else { y };
+                }
+                'y' => {
+                    let y = self.number(2)? as i64;
+                    self.dt.year = if y < 69 { 2000 + y } else { 1900 + y };
+                }
+                'm' => self.dt.month = self.number(2)?,
+                'b' | 'h' | 'B' => self.dt.month = self.name(&MONTHS)? + 1,
+                'e' => {
+                    self.s = self.s.trim_start();
+                    self.dt.day = self.number(2)?;
+                }
+                'd' => self.dt.day = self.number(2)?,
+                'j' => self.ordinal = Some(self.number(3)?),
+                'a' | 'A' => {
+                    self.name(&WEEKDAYS)?;
+                }
+                'H' => self.dt.hour = self.number(2)?,
+                'I' => self.hour12 = Some(self.number(2)?),
+                'p' => {
+                    let upper = self.s.get(..2)?.to_uppercase();
+                    self.pm = match &*upper {
+                        "AM" => Some(false),
+                        "PM" => Some(true),
+                        _ => return None,
+                    };
+                    self.s = &self.s[2..];
+                }
+                'M' => self.dt.minute = self.number(2)?,
+                'S' => self.dt.second = self.number(2)?,
+                's' => {
+                    let end = self.s.char_indices()
+                        .find(|&(i, c)| !(c.is_ascii_digit()
+                                          || (i == 0 && c == '-')))
+                        .map_or(self.s.len(), |(i, _)| i);
+                    let secs = self.s[..end].parse().ok()?;
+                    self.s = &self.s[end..];
+                    self.dt = DateTime::from_timestamp(secs);
+                }
+                'z' => self.dt.offset = Some(self.offset()?),
+                'f' => {
+                    let len = self.s.bytes()
+                                    .take_while(|b| b.is_ascii_digit()).count();
+                    if len == 0 {
+                        return None;
+                    }
+                    self.s = &self.s[len..];
+                }
+                'F' => self.parse("%Y-%m-%d")?,
+                'T' => self.parse("%H:%M:%S")?,
+                '%' => self.s = strip_prefix(self.s, '%')?,
+                _ => return None,
+            }
+        }
+        Some(())
+    }
+
+    /// Parses a number of at most `max` digits.
+    fn number(&mut self, max: usize) -> Option<u32> {
+        let len = self.s.bytes().take(max)
+                        .take_while(|b| b.is_ascii_digit()).count();
+        if len == 0 {
+            return None;
+        }
+        let n = self.s[..len].parse().ok()?;
+        self.s = &self.s[len..];
+        Some(n)
+    }
+
+    /// Parses an offset from UTC and returns it in seconds.
+    fn offset(&mut self) -> Option<i32> {
+        if let Some(rest) = strip_prefix(self.s, 'Z')
+                            .or_else(|| strip_prefix(self.s, 'z')) {
+            self.s = rest;
+            return Some(0);
+        }
+        let neg = match self.s.chars().next()? {
+            '+' => false,
+            '-' => true,
+            _ => return None,
+        };
+        self.s = &self.s[1..];
+        if self.s.bytes().take_while(|b| b.is_ascii_digit()).count() < 2 {
+            return None;
+        }
+        let hours = self.number(2)?;
+        if let Some(rest) = strip_prefix(self.s, ':') {
+            self.s = rest;
+        }
+        let minutes = if self.s.starts_with(|c: char| c.is_ascii_digit()) {
+            self.number(2)?
+        } else {
+            0
+        };
+        if hours > 23 || minutes > 59 {
+            return None;
+        }
+        let offset = (hours * 3600 + minutes * 60) as i32;
+        Some(if neg { -offset } else { offset })
+    }
+
+    /// Parses a full or abbreviated name and returns its index in `names`.
+    fn name(&mut self, names: &[&str]) -> Option<u32> {
+        let lower = self.s.to_lowercase();
+        for (i, name) in names.iter().enumerate() {
+            let name = name.to_lowercase();
+            for prefix in &[&name[..], &name[..3]] {
+                if lower.starts_with(prefix) && self.s.is_char_boundary(prefix.len()) {
+                    self.s = &self.s[prefix.len()..];
+                    return Some(i as u32);
+                }
+            }
+        }
+        None
+    }
+
+    fn finish(self) -> Option<DateTime> {
+        let mut dt = self.dt;
+        if let Some(h) = self.hour12 {
+            if h < 1 || h > 12 {
+                return None;
+            }
+            dt.hour = h % 12;
+        }
+        if let Some(pm) = self.pm {
+            if dt.hour > 12 || (dt.hour == 0 && self.hour12.is_none()) {
+                return None;
+            }
+            dt.hour = dt.hour % 12 + if pm { 12 } else { 0 };
+        }
+        if let Some(n) = self.ordinal {
+            let len = if is_leap_year(dt.year) { 366 } else { 365 };
+            if n < 1 || n > len {
+                return None;
+            }
+            let days = days_from_civil(dt.year, 1, 1) + n as i64 - 1;
+            let (_, m, d) = civil_from_days(days);
+            dt.month = m;
+            dt.day = d;
+        }
+        if dt.month < 1 || dt.month > 12
+            || dt.day < 1 || dt.day > days_in_month(dt.year, dt.month)
+            || dt.hour > 23 || dt.minute > 59 || dt.second > 60 {
+            return None;
+        }
+        Some(dt)
+    }
+}
+
+fn strip_prefix(s: &str, c: char) -> Option<&str> {
+    if s.starts_with(c) { Some(&s[c.len_utf8()..]) } else { None }
+}
+
@@ -0,0 +1,328 @@
+//! Defaults for command line options, from `XSV_DEFAULT_*` environment
+//! variables and the config file `$XDG_CONFIG_HOME/xsv/config.toml`.
+//!
+//! A default is given to a command as if its option had been given on the
+//! command line, so it only applies to commands that have the option, and
+//! an option that is actually given always wins. Environment variables win
+//! over the config file.
+
+use std::env;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+/// An option whose default can be set.
+pub struct Key {
+    /// The name in the config file, which is also the long option.
+    pub name: &'static str,
+    /// Whether the option is a flag, which has no value.
+    pub flag: bool,
+    /// Whether the option is a global option, given before the command.
+    pub global: bool,
+}
+
+impl Key {
+    /// The environment variable with the default, like
+    /// `XSV_DEFAULT_NO_HEADERS` for `no-headers`.
+    pub fn env_var(&self) -> String {
+        format!("XSV_DEFAULT_{}",
+                self.name.to_ascii_uppercase().replace('-', "_"))
+    }
+
+    fn option(&self) -> String {
+        format!("--{}", self.name)
+    }
+}
+
+pub static KEYS: &[Key] = &[
+    Key { name: "delimiter", flag: false, global: false },
+    Key { name: "quote", flag: false, global: false },
+    Key { name: "no-headers", flag: true, global: false },
+    Key { name: "out-delimiter", flag: false, global: false },
+    Key { name: "trim", flag: false, global: true },
+    Key { name: "comment", flag: false, global: true },
+    Key { name: "flexible", flag: false, global: true },
+    Key { name: "download", flag: true, global: true },
+];
+
+/// Where a default comes from.
+pub enum Source {
+    Env(String),
+    File,
+}
+
+/// The default of an option. The value of a flag is `true` or `false`.
+pub struct Setting {
+    pub key: &'static Key,
+    pub value: String,
+    pub source: Source,
+}
+
+impl Setting {
+    fn is_on(&self) -> bool {
+        !self.key.flag || self.value == "true"
+    }
+}
+
+static DEFAULTS: OnceLock<Result<Vec<Setting>, String>> = OnceLock::new();
+
+/// Returns the defaults that are set, reading them on the first call.
+pub fn defaults() -> Result<&'static [Setting], String> {
+    match *DEFAULTS.get_or_init(load) {
+        Ok(ref defaults) => Ok(defaults),
+        Err(ref err) => Err(err.clone()),
+    }
+}
+
+/// The config file, which doesn't have to exist.
+pub fn config_path() -> Option<PathBuf> {
+    let dir = match env::var_os("XDG_CONFIG_HOME") {
+        Some(ref dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => match env::var_os("HOME") {
+            Some(ref home) if !home.is_empty() => {
+                PathBuf::from(home).join(".config")
+            }
+            _ => return None,
+        },
+    };
+    Some(dir.join("xsv").join("config.toml"))
+}
+
+fn load() -> Result<Vec<Setting>, String> {
+    let mut defaults = vec![];
+    if let Some(path) = config_path() {
+        match fs::read_to_string(&path) {
+            Ok(data) => {
+                for (key, value) in parse(&data).map_err(|(line, err)| {
+                    format!("{}:{}: {}", path.display(), line, err)
+                })? {
+                    defaults.push(Setting {
+                        key: key,
+                        value: value,
+                        source: Source::File,
+                    });
+                }
+            }
+            Err(ref err) if err.kind() == io::ErrorKind::NotFound => {}
+            Err(err) => {
+                return Err(format!("{}: {}", path.display(), err));
+            }
+        }
+    }
+    for key in KEYS {
+        let var = key.env_var();
+        let value = match env::var(&var) {
+            Ok(ref value) if value.is_empty() => continue,
+            Ok(value) => value,
+            Err(env::VarError::NotPresent) => continue,
+            Err(env::VarError::NotUnicode(_)) => {
+                return Err(format!("{} is not valid UTF-8.", var));
+            }
+        };
+        let value = if key.flag {
+            match &*value.to_ascii_lowercase() {
+                "1" | "true" | "yes" => "true".to_owned(),
+                "0" | "false" | "no" => "false".to_owned(),
+                _ => return Err(format!(
+                    "{} must be 1 or 0 (or true or false), not '{}'.",
+                    var, value)),
+            }
+        } else {
+            value
+        };
+        defaults.retain(|d: &Setting| d.key.name != key.name);
+        defaults.push(Setting {
+            key: key,
+            value: value,
+            source: Source::Env(var),
+        });
+    }
+    Ok(defaults)
+}
+
+/// Parses the `key = value` lines of a config file. Values are TOML strings
+/// or booleans, and the other parts of TOML aren't supported. Errors come
+/// with their line number.
+fn parse(data: &str) -> Result<Vec<(&'static Key, String)>, (usize, String)> {
+    let mut settings: Vec<(&'static Key, String)> = vec![];
+    for (i, line) in data.lines().enumerate() {
+        let lineno = i + 1;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if line.starts_with('[') {
+            return Err((lineno, "tables are not supported.".to_owned()));
+        }
+        let eq = match line.find('=') {
+            Some(eq) => eq,
+            None => {
+                return Err((lineno, "expected 'name = value'.".to_owned()));
+            }
+        };
+        let name = line[..eq].trim();
+        let key = match KEYS.iter().find(|k| k.name == name) {
+            Some(key) => key,
+            None => {
+                return Err((lineno, format!("unknown setting '{}'.", name)));
+            }
+        };
+        let (value, is_string) =
+            parse_value(line[eq + 1..].trim()).map_err(|e| (lineno, e))?;
+        if key.flag == is_string {
+            let want = if key.flag { "true or false" } else { "a string" };
+            return Err((lineno, format!("'{}' must be {}.", name, want)));
+        }
+        settings.retain(|&(k, _)| k.name != key.name);
+        settings.push((key, value));
+    }
+    Ok(settings)
+}
+
+/// Parses a value, returning it and whether it is a string (rather than a
+/// boolean).
+fn parse_value(s: &str) -> Result<(String, bool), String> {
+    let mut chars = s.chars();
+    let (value, rest) = match chars.next() {
+        Some('"') => {
+            let mut value = String::new();
+            loop {
+                match chars.next() {
+                    None => return Err("unterminated string.".to_owned()),
+                    Some('"') => break,
+                    Some('\\') => value.push(match chars.next() {
+                        Some('"') => '"',
+                        Some('\\') => '\\',
+                        Some('t') => '\t',
+                        Some('n') => '\n',
+                        Some('r') => '\r',
+                        Some(c) => {
+                            return Err(format!("unknown escape '\\{}'.", c));
+                        }
+                        None => {
+                            return Err("unterminated string.".to_owned());
+                        }
+                    }),
+                    Some(c) => value.push(c),
+                }
+            }
+            (value, chars.as_str())
+        }
+        Some('\'') => {
+            let rest = chars.as_str();
+            match rest.find('\'') {
+                None => return Err("unterminated string.".to_owned()),
+                Some(end) => (rest[..end].to_owned(), &rest[end + 1..]),
+            }
+        }
+        _ => {
+            let word = s.split('#').next().unwrap_or("").trim();
+            return match word {
+                "true" | "false" => Ok((word.to_owned(), false)),
+                _ => Err(format!(
+                    "invalid value '{}'. Strings must be quoted.", s)),
+            };
+        }
+    };
+    let rest = rest.trim();
+    if !rest.is_empty() && !rest.starts_with('#') {
+        return Err(format!("unexpected '{}' after the value.", rest));
+    }
+    Ok((value, true))
+}
+
+/// Adds the defaults for the options of `usage` to the command line `argv`,
+/// unless the options are given in `argv[start..end]`. They are added at
+/// `start`. `global` selects the global options or those of commands.
+pub fn apply(
+    usage: &str,
+    argv: &mut Vec<String>,
+    start: usize,
+    end: usize,
+    global: bool,
+) -> Result<(), String> {
+    let mut extra = vec![];
+    for d in defaults()? {
+        if d.key.global != global || !d.is_on() {
+            continue;
+        }
+        let long = d.key.option();
+        match usage_option(usage, &long) {
+            Some((short, takes_value)) if takes_value != d.key.flag => {
+                if !is_given(&argv[start..end], &long, short) {
+                    extra.push(long);
+                    if !d.key.flag {
+                        extra.push(d.value.clone());
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    let tail = argv.split_off(start);
+    argv.extend(extra);
+    argv.extend(tail);
+    Ok(())
+}
+
+/// Finds the option `long` in the options of a usage message, returning
+/// its short form and whether it takes a value.
+fn usage_option(usage: &str, long: &str) -> Option<(Option<char>, bool)> {
+    for line in usage.lines() {
+        let line = line.trim_start();
+        if !line.starts_with('-') {
+            continue;
+        }
+        let spec = line.split("  ").next().unwrap_or("");
+        let words: Vec<&str> = spec
+            .split(|c| c == ',' || c == ' ' || c == '=')
+            .filter(|w| !w.is_empty())
+            .collect();
+        if !words.iter().any(|&w| w == long) {
+            continue;
+        }
+        let short = words.iter()
+            .find(|w| w.len() == 2 && w.starts_with('-') && **w != "--")
+            .and_then(|w| w.chars().nth(1));
+        let takes_value = words.iter().any(|w| w.starts_with('<'));
+        return Some((short, takes_value));
+    }
+    None
+}
+
+/// Whether the option is in the arguments. Like docopt, this accepts
+/// prefixes of long options and short options that are grouped, like `-nd`.
+fn is_given(args: &[String], long: &str, short: Option<char>) -> bool {
+    args.iter().take_while(|arg| *arg != "--").any(|arg| {
+        if arg.starts_with("--") {
+            let name = arg.split('=').next().unwrap_or("");
+            name.len() > 2 && long.starts_with(name)
+        } else if arg.starts_with('-') {
+            short.map_or(false, |short| {
+                arg[1..].chars()
+                    .take_while(|c| c.is_ascii_alphabetic())
+                    .any(|c| c == short)
+            })
+        } else {
+            false
+        }
+    })
+}
+
+/// Writes a value as a TOML string.
+pub fn quote(value: &str) -> String {
+    let mut s = String::from("\"");
+    for c in value.chars() {
+        match c {
+            '"' => s.push_str("\\\""),
+            '\\' => s.push_str("\\\\"),
+            '\t' => s.push_str("\\t"),
+            '\n' => s.push_str("\\n"),
+            '\r' => s.push_str("\\r"),
+            c => s.push(c),
+        }
+    }
+    s.push('"');
+    s
+}
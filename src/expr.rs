@@ -0,0 +1,764 @@
+//! A small expression language for computing values from CSV records.
+//!
+//! Expressions are made of column names (identifiers, or any text quoted in
+//! backticks), number and string literals, `true` and `false`, the
+//! arithmetic operators `+ - * / %`, the comparison operators
+//! `== != < <= > >=`, the tests `x is empty`, `x is not empty`,
+//! `x in (a, b, c)` and `x not in (a, b, c)`, the logical operators `and`,
+//! `or` and `not`, and function calls like `concat(first, " ", last)`.
+//!
+//! There are three types of values: numbers, strings and booleans. The
+//! values of columns are strings, which are converted to numbers by the
+//! arithmetic operators. Comparisons are numeric if both operands are
+//! numbers (or strings that can be parsed as numbers) and lexicographic
+//! otherwise. How a number is compared with a string that isn't a number
+//! depends on the `Mismatch` of the expression.
+
+use std::cmp::Ordering;
+use std::fmt;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    Bool(bool),
+    Number(f64),
+    String(String),
+}
+
+impl Value {
+    /// Returns false for `false`, zero and the empty string, and true for
+    /// all other values.
+    pub fn is_truthy(&self) -> bool {
+        match *self {
+            Value::Bool(b) => b,
+            Value::Number(n) => n != 0.0,
+            Value::String(ref s) => !s.is_empty(),
+        }
+    }
+
+    fn as_number(&self) -> Option<f64> {
+        match *self {
+            Value::Bool(_) => None,
+            Value::Number(n) => Some(n),
+            Value::String(ref s) => {
+                s.trim().parse::<f64>().ok().filter(|n| n.is_finite())
+            }
+        }
+    }
+
+    fn to_number(&self) -> Result<f64, String> {
+        self.as_number().ok_or_else(|| match *self {
+            Value::String(ref s) => format!("'{}' is not a number", s),
+            _ => format!("{} is not a number", self),
+        })
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Value::Bool(b) => write!(f, "{}", b),
+            Value::Number(n) => write!(f, "{}", n),
+            Value::String(ref s) => write!(f, "{}", s),
+        }
+    }
+}
+
+/// How a comparison of a number with a string that can't be parsed as a
+/// number is evaluated.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Mismatch {
+    /// The values are compared as strings.
+    Compare,
+    /// The comparison is false.
+    False,
+    /// The comparison is an error.
+    Error,
+}
+
+/// An error in the syntax of an expression, at a byte offset in it.
+#[derive(Debug)]
+pub struct SyntaxError {
+    pub pos: usize,
+    pub msg: String,
+}
+
+impl SyntaxError {
+    /// Describes the error, pointing at its position in the expression.
+    pub fn describe(&self, src: &str) -> String {
+        let col = src[..self.pos].chars().count();
+        format!("syntax error in expression:\n    {}\n    {}^ {}",
+                src, " ".repeat(col), self.msg)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Number(f64),
+    Str(String),
+    Ident(String),
+    /// An identifier in backticks, which is never a keyword or function.
+    Quoted(String),
+    LParen,
+    RParen,
+    Comma,
+    Assign,
+    Op(BinOp),
+    Not,
+    True,
+    False,
+    Eof,
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Token::Number(n) => write!(f, "{}", n),
+            Token::Str(ref s) => write!(f, "\"{}\"", s),
+            Token::Ident(ref s) => write!(f, "{}", s),
+            Token::Quoted(ref s) => write!(f, "`{}`", s),
+            Token::LParen => write!(f, "("),
+            Token::RParen => write!(f, ")"),
+            Token::Comma => write!(f, ","),
+            Token::Assign => write!(f, "="),
+            Token::Op(op) => write!(f, "{}", op.symbol()),
+            Token::Not => write!(f, "not"),
+            Token::True => write!(f, "true"),
+            Token::False => write!(f, "false"),
+            Token::Eof => write!(f, "end of expression"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum BinOp {
+    Or,
+    And,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+}
+
+impl BinOp {
+    fn symbol(self) -> &'static str {
+        match self {
+            BinOp::Or => "or",
+            BinOp::And => "and",
+            BinOp::Eq => "==",
+            BinOp::Ne => "!=",
+            BinOp::Lt => "<",
+            BinOp::Le => "<=",
+            BinOp::Gt => ">",
+            BinOp::Ge => ">=",
+            BinOp::Add => "+",
+            BinOp::Sub => "-",
+            BinOp::Mul => "*",
+            BinOp::Div => "/",
+            BinOp::Rem => "%",
+        }
+    }
+
+    /// The binding power of the operator, where higher binds tighter.
+    fn precedence(self) -> u8 {
+        match self {
+            BinOp::Or => 1,
+            BinOp::And => 2,
+            BinOp::Eq | BinOp::Ne | BinOp::Lt | BinOp::Le
+            | BinOp::Gt | BinOp::Ge => 4,
+            BinOp::Add | BinOp::Sub => 5,
+            BinOp::Mul | BinOp::Div | BinOp::Rem => 6,
+        }
+    }
+
+    fn is_comparison(self) -> bool {
+        self.precedence() == 4
+    }
+}
+
+/// The precedence of `not`, which binds looser than comparisons.
+const NOT_PRECEDENCE: u8 = 3;
+
+/// The operators and punctuation, with longer ones first.
+static SYMBOLS: &'static [(&'static str, Token)] = &[
+    ("==", Token::Op(BinOp::Eq)),
+    ("!=", Token::Op(BinOp::Ne)),
+    ("<=", Token::Op(BinOp::Le)),
+    (">=", Token::Op(BinOp::Ge)),
+    ("&&", Token::Op(BinOp::And)),
+    ("||", Token::Op(BinOp::Or)),
+    ("(", Token::LParen),
+    (")", Token::RParen),
+    (",", Token::Comma),
+    ("=", Token::Assign),
+    ("<", Token::Op(BinOp::Lt)),
+    (">", Token::Op(BinOp::Gt)),
+    ("!", Token::Not),
+    ("+", Token::Op(BinOp::Add)),
+    ("-", Token::Op(BinOp::Sub)),
+    ("*", Token::Op(BinOp::Mul)),
+    ("/", Token::Op(BinOp::Div)),
+    ("%", Token::Op(BinOp::Rem)),
+];
+
+fn tokenize(src: &str) -> Result<Vec<(usize, Token)>, SyntaxError> {
+    let err = |pos, msg: &str| SyntaxError { pos: pos, msg: msg.to_owned() };
+    let bytes = src.as_bytes();
+    let mut tokens = vec![];
+    let mut i = 0;
+    while i < bytes.len() {
+        let start = i;
+        let c = bytes[i];
+        if c.is_ascii_whitespace() {
+            i += 1;
+        } else if c == b'"' || c == b'\'' {
+            let mut s = String::new();
+            let mut chars = src[i + 1..].char_indices();
+            let end = loop {
+                match chars.next() {
+                    None => return Err(err(start, "unterminated string")),
+                    Some((j, ch)) if ch as u32 == c as u32 => break i + 2 + j,
+                    Some((_, '\\')) => match chars.next() {
+                        Some((_, 'n')) => s.push('\n'),
+                        Some((_, 't')) => s.push('\t'),
+                        Some((_, ch)) => s.push(ch),
+                        None => return Err(err(start, "unterminated string")),
+                    },
+                    Some((_, ch)) => s.push(ch),
+                }
+            };
+            tokens.push((start, Token::Str(s)));
+            i = end;
+        } else if c == b'`' {
+            let end = match src[i + 1..].find('`') {
+                None => return Err(err(start, "unterminated column name")),
+                Some(end) => i + 1 + end,
+            };
+            tokens.push((start, Token::Quoted(src[i + 1..end].to_owned())));
+            i = end + 1;
+        } else if c.is_ascii_digit() || c == b'.' {
+            while i < bytes.len()
+                    && (bytes[i].is_ascii_digit() || bytes[i] == b'.') {
+                i += 1;
+            }
+            if i < bytes.len() && (bytes[i] == b'e' || bytes[i] == b'E') {
+                let mut j = i + 1;
+                if j < bytes.len() && (bytes[j] == b'+' || bytes[j] == b'-') {
+                    j += 1;
+                }
+                if j < bytes.len() && bytes[j].is_ascii_digit() {
+                    i = j;
+                    while i < bytes.len() && bytes[i].is_ascii_digit() {
+                        i += 1;
+                    }
+                }
+            }
+            match src[start..i].parse() {
+                Ok(n) => tokens.push((start, Token::Number(n))),
+                Err(_) => return Err(err(start, "invalid number")),
+            }
+        } else if c == b'_' || c.is_ascii_alphabetic() {
+            while i < bytes.len()
+                    && (bytes[i] == b'_' || bytes[i].is_ascii_alphanumeric()) {
+                i += 1;
+            }
+            let word = &src[start..i];
+            let tok = match &*word.to_lowercase() {
+                "and" => Token::Op(BinOp::And),
+                "or" => Token::Op(BinOp::Or),
+                "not" => Token::Not,
+                "true" => Token::True,
+                "false" => Token::False,
+                _ => Token::Ident(word.to_owned()),
+            };
+            tokens.push((start, tok));
+        } else {
+            match SYMBOLS.iter().find(|s| src[i..].starts_with(s.0)) {
+                Some(&(sym, ref tok)) => {
+                    tokens.push((start, tok.clone()));
+                    i += sym.len();
+                }
+                None => return Err(err(start, "unexpected character")),
+            }
+        }
+    }
+    tokens.push((src.len(), Token::Eof));
+    Ok(tokens)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Func {
+    Abs,
+    Concat,
+    If,
+    Len,
+    Lower,
+    Max,
+    Min,
+    Round,
+    Substr,
+    Trim,
+    Upper,
+}
+
+/// The functions with their names and the minimum and maximum number of
+/// arguments they take.
+static FUNCTIONS: &'static [(&'static str, Func, usize, usize)] = &[
+    ("abs", Func::Abs, 1, 1),
+    ("concat", Func::Concat, 1, ::std::usize::MAX),
+    ("if", Func::If, 3, 3),
+    ("len", Func::Len, 1, 1),
+    ("lower", Func::Lower, 1, 1),
+    ("max", Func::Max, 1, ::std::usize::MAX),
+    ("min", Func::Min, 1, ::std::usize::MAX),
+    ("round", Func::Round, 1, 2),
+    ("substr", Func::Substr, 2, 3),
+    ("trim", Func::Trim, 1, 1),
+    ("upper", Func::Upper, 1, 1),
+];
+
+#[derive(Clone, Debug)]
+enum Node {
+    Literal(Value),
+    Column(usize),
+    Neg(Box<Node>),
+    Not(Box<Node>),
+    Binary(BinOp, Box<Node>, Box<Node>),
+    /// `x is empty`, or `x is not empty` if the flag is set.
+    IsEmpty(Box<Node>, bool),
+    /// `x in (...)`, or `x not in (...)` if the flag is set.
+    In(Box<Node>, Vec<Node>, bool),
+    Call(Func, Vec<Node>),
+}
+
+/// A parsed expression, whose column references have been resolved to
+/// positions in a row of values.
+#[derive(Clone, Debug)]
+pub struct Expr {
+    root: Node,
+    mismatch: Mismatch,
+}
+
+impl Expr {
+    /// Parses an expression. `resolve` returns the position of a column in
+    /// the rows that the expression is evaluated on.
+    pub fn parse<F>(src: &str, resolve: F) -> Result<Expr, SyntaxError>
+            where F: FnMut(&str) -> Option<usize> {
+        let mut p = Parser::new(src, resolve)?;
+        let root = p.expr(0)?;
+        p.expect_eof()?;
+        Ok(Expr { root: root, mismatch: Mismatch::Compare })
+    }
+
+    /// Parses an assignment of the form `name = expression` and returns the
+    /// name with the expression.
+    pub fn parse_assignment<F>(
+        src: &str,
+        resolve: F,
+    ) -> Result<(String, Expr), SyntaxError>
+            where F: FnMut(&str) -> Option<usize> {
+        let mut p = Parser::new(src, resolve)?;
+        let name = match p.next() {
+            (_, Token::Ident(name)) | (_, Token::Quoted(name)) => name,
+            (pos, tok) => return Err(p.unexpected(pos, &tok, "a column name")),
+        };
+        match p.next() {
+            (_, Token::Assign) => {}
+            (pos, tok) => return Err(p.unexpected(pos, &tok, "'='")),
+        }
+        let root = p.expr(0)?;
+        p.expect_eof()?;
+        Ok((name, Expr { root: root, mismatch: Mismatch::Compare }))
+    }
+
+    /// Sets how numbers are compared with strings that aren't numbers. By
+    /// default, they are compared as strings.
+    pub fn mismatch(mut self, mismatch: Mismatch) -> Expr {
+        self.mismatch = mismatch;
+        self
+    }
+
+    /// Evaluates the expression on a row of values.
+    pub fn eval(&self, row: &[Value]) -> Result<Value, String> {
+        eval(&self.root, row, self.mismatch)
+    }
+}
+
+struct Parser<F> {
+    tokens: Vec<(usize, Token)>,
+    i: usize,
+    resolve: F,
+}
+
+impl<F: FnMut(&str) -> Option<usize>> Parser<F> {
+    fn new(src: &str, resolve: F) -> Result<Parser<F>, SyntaxError> {
+        Ok(Parser { tokens: tokenize(src)?, i: 0, resolve: resolve })
+    }
+
+    fn peek(&self) -> &Token {
+        &self.tokens[self.i].1
+    }
+
+    fn next(&mut self) -> (usize, Token) {
+        let tok = self.tokens[self.i].clone();
+        if self.i + 1 < self.tokens.len() {
+            self.i += 1;
+        }
+        tok
+    }
+
+    fn unexpected(&self, pos: usize, tok: &Token, expected: &str) -> SyntaxError {
+        SyntaxError {
+            pos: pos,
+            msg: format!("expected {}, found {}", expected, tok),
+        }
+    }
+
+    fn expect_eof(&mut self) -> Result<(), SyntaxError> {
+        match self.next() {
+            (_, Token::Eof) => Ok(()),
+            (pos, tok) => Err(self.unexpected(pos, &tok, "an operator")),
+        }
+    }
+
+    /// Parses an expression whose operators bind tighter than `min`.
+    fn expr(&mut self, min: u8) -> Result<Node, SyntaxError> {
+        let mut lhs = self.unary()?;
+        loop {
+            if min < BinOp::Eq.precedence() {
+                if let Some(node) = self.postfix(&lhs)? {
+                    lhs = node;
+                    continue;
+                }
+            }
+            let op = match *self.peek() {
+                Token::Op(op) if op.precedence() > min => op,
+                _ => return Ok(lhs),
+            };
+            self.next();
+            let rhs = self.expr(op.precedence())?;
+            if op.is_comparison() {
+                if let Token::Op(next) = *self.peek() {
+                    if next.is_comparison() {
+                        let (pos, tok) = self.next();
+                        return Err(SyntaxError {
+                            pos: pos,
+                            msg: format!("comparisons can't be chained, \
+                                          found {}", tok),
+                        });
+                    }
+                }
+            }
+            lhs = Node::Binary(op, Box::new(lhs), Box::new(rhs));
+        }
+    }
+
+    /// The keyword at the current token, if it is an identifier.
+    fn keyword(&self, offset: usize) -> Option<String> {
+        match self.tokens.get(self.i + offset) {
+            Some(&(_, Token::Ident(ref word))) => Some(word.to_lowercase()),
+            _ => None,
+        }
+    }
+
+    /// Parses `is [not] empty` or `[not] in (...)` after an operand, if
+    /// they follow. The words are only keywords here, so that they can
+    /// still be column names.
+    fn postfix(&mut self, lhs: &Node) -> Result<Option<Node>, SyntaxError> {
+        let operand = Box::new(lhs.clone());
+        if self.keyword(0).as_ref().map(|s| &**s) == Some("is") {
+            self.next();
+            let negated = *self.peek() == Token::Not;
+            if negated {
+                self.next();
+            }
+            match self.keyword(0) {
+                Some(ref word) if word == "empty" => {
+                    self.next();
+                    return Ok(Some(Node::IsEmpty(operand, negated)));
+                }
+                _ => {
+                    let (pos, tok) = self.next();
+                    return Err(self.unexpected(pos, &tok, "'empty'"));
+                }
+            }
+        }
+        let negated = match (self.peek(), self.keyword(1)) {
+            (&Token::Not, Some(ref word)) if word == "in" => true,
+            _ => false,
+        };
+        if !negated && self.keyword(0).as_ref().map(|s| &**s) != Some("in") {
+            return Ok(None);
+        }
+        self.next();
+        if negated {
+            self.next();
+        }
+        match self.next() {
+            (_, Token::LParen) => {}
+            (pos, tok) => return Err(self.unexpected(pos, &tok, "'('")),
+        }
+        let mut items = vec![];
+        loop {
+            items.push(self.expr(0)?);
+            match self.next() {
+                (_, Token::Comma) => {}
+                (_, Token::RParen) => break,
+                (pos, tok) => {
+                    return Err(self.unexpected(pos, &tok, "',' or ')'"));
+                }
+            }
+        }
+        Ok(Some(Node::In(operand, items, negated)))
+    }
+
+    fn unary(&mut self) -> Result<Node, SyntaxError> {
+        match *self.peek() {
+            Token::Not => {
+                self.next();
+                let operand = self.expr(NOT_PRECEDENCE - 1)?;
+                Ok(Node::Not(Box::new(operand)))
+            }
+            Token::Op(BinOp::Sub) => {
+                self.next();
+                let operand = self.unary()?;
+                Ok(Node::Neg(Box::new(operand)))
+            }
+            _ => self.primary(),
+        }
+    }
+
+    fn primary(&mut self) -> Result<Node, SyntaxError> {
+        let (pos, tok) = self.next();
+        match tok {
+            Token::Number(n) => Ok(Node::Literal(Value::Number(n))),
+            Token::Str(s) => Ok(Node::Literal(Value::String(s))),
+            Token::True => Ok(Node::Literal(Value::Bool(true))),
+            Token::False => Ok(Node::Literal(Value::Bool(false))),
+            Token::LParen => {
+                let node = self.expr(0)?;
+                match self.next() {
+                    (_, Token::RParen) => Ok(node),
+                    (pos, tok) => Err(self.unexpected(pos, &tok, "')'")),
+                }
+            }
+            Token::Ident(ref name) if *self.peek() == Token::LParen => {
+                self.call(pos, name)
+            }
+            Token::Ident(name) | Token::Quoted(name) => {
+                match (self.resolve)(&name) {
+                    Some(i) => Ok(Node::Column(i)),
+                    None => Err(SyntaxError {
+                        pos: pos,
+                        msg: format!("unknown column '{}'", name),
+                    }),
+                }
+            }
+            tok => Err(self.unexpected(pos, &tok, "a value")),
+        }
+    }
+
+    fn call(&mut self, pos: usize, name: &str) -> Result<Node, SyntaxError> {
+        let lower = name.to_lowercase();
+        let &(_, func, min, max) = match FUNCTIONS.iter()
+                                                  .find(|f| f.0 == lower) {
+            Some(f) => f,
+            None => {
+                return Err(SyntaxError {
+                    pos: pos,
+                    msg: format!("unknown function '{}'", name),
+                });
+            }
+        };
+        self.next();
+        let mut args = vec![];
+        if *self.peek() == Token::RParen {
+            self.next();
+        } else {
+            loop {
+                args.push(self.expr(0)?);
+                match self.next() {
+                    (_, Token::Comma) => {}
+                    (_, Token::RParen) => break,
+                    (pos, tok) => {
+                        return Err(self.unexpected(pos, &tok, "',' or ')'"));
+                    }
+                }
+            }
+        }
+        if args.len() < min || args.len() > max {
+            let expected = if min == max {
+                format!("{}", min)
+            } else if max == ::std::usize::MAX {
+                format!("at least {}", min)
+            } else {
+                format!("{} to {}", min, max)
+            };
+            return Err(SyntaxError {
+                pos: pos,
+                msg: format!("{} takes {} argument(s), but got {}",
+                             lower, expected, args.len()),
+            });
+        }
+        Ok(Node::Call(func, args))
+    }
+}
+
+/// Compares two values. Returns `None` if the comparison is false because
+/// a number is compared with a string that isn't a number.
+fn compare(
+    a: &Value,
+    b: &Value,
+    mismatch: Mismatch,
+) -> Result<Option<Ordering>, String> {
+    if let (&Value::Bool(x), &Value::Bool(y)) = (a, b) {
+        return Ok(Some(x.cmp(&y)));
+    }
+    match (a.as_number(), b.as_number()) {
+        (Some(x), Some(y)) => {
+            return Ok(Some(x.partial_cmp(&y).unwrap_or(Ordering::Equal)));
+        }
+        (Some(_), None) | (None, Some(_)) if mismatch != Mismatch::Compare => {
+            let typed = match (a, b) {
+                (&Value::Number(_), &Value::String(ref s))
+                | (&Value::String(ref s), &Value::Number(_)) => Some(s),
+                _ => None,
+            };
+            if let Some(s) = typed {
+                if mismatch == Mismatch::False {
+                    return Ok(None);
+                }
+                return Err(format!("'{}' is not a number", s));
+            }
+        }
+        _ => {}
+    }
+    Ok(Some(a.to_string().cmp(&b.to_string())))
+}
+
+fn eval(node: &Node, row: &[Value], mismatch: Mismatch)
+       -> Result<Value, String> {
+    let eval = |node: &Node| eval(node, row, mismatch);
+    Ok(match *node {
+        Node::Literal(ref v) => v.clone(),
+        Node::Column(i) => row[i].clone(),
+        Node::Neg(ref e) => Value::Number(-eval(e)?.to_number()?),
+        Node::Not(ref e) => Value::Bool(!eval(e)?.is_truthy()),
+        Node::Binary(BinOp::And, ref a, ref b) => {
+            Value::Bool(eval(a)?.is_truthy() && eval(b)?.is_truthy())
+        }
+        Node::Binary(BinOp::Or, ref a, ref b) => {
+            Value::Bool(eval(a)?.is_truthy() || eval(b)?.is_truthy())
+        }
+        Node::IsEmpty(ref e, negated) => {
+            Value::Bool(eval(e)?.to_string().is_empty() != negated)
+        }
+        Node::In(ref e, ref items, negated) => {
+            let value = eval(e)?;
+            let mut found = false;
+            for item in items {
+                match compare(&value, &eval(item)?, mismatch)? {
+                    None => return Ok(Value::Bool(false)),
+                    Some(Ordering::Equal) => found = true,
+                    Some(_) => {}
+                }
+            }
+            Value::Bool(found != negated)
+        }
+        Node::Binary(op, ref a, ref b) => {
+            let (a, b) = (eval(a)?, eval(b)?);
+            if op.is_comparison() {
+                let ord = match compare(&a, &b, mismatch)? {
+                    None => return Ok(Value::Bool(false)),
+                    Some(ord) => ord,
+                };
+                return Ok(Value::Bool(match op {
+                    BinOp::Eq => ord == Ordering::Equal,
+                    BinOp::Ne => ord != Ordering::Equal,
+                    BinOp::Lt => ord == Ordering::Less,
+                    BinOp::Le => ord != Ordering::Greater,
+                    BinOp::Gt => ord == Ordering::Greater,
+                    _ => ord != Ordering::Less,
+                }));
+            }
+            let (x, y) = (a.to_number()?, b.to_number()?);
+            Value::Number(match op {
+                BinOp::Add => x + y,
+                BinOp::Sub => x - y,
+                BinOp::Mul => x * y,
+                _ if y == 0.0 => return Err("division by zero".to_owned()),
+                BinOp::Div => x / y,
+                _ => x % y,
+            })
+        }
+        Node::Call(Func::If, ref args) => {
+            if eval(&args[0])?.is_truthy() {
+                eval(&args[1])?
+            } else {
+                eval(&args[2])?
+            }
+        }
+        Node::Call(func, ref args) => {
+            let mut vals = Vec::with_capacity(args.len());
+            for arg in args {
+                vals.push(eval(arg)?);
+            }
+            call(func, &vals)?
+        }
+    })
+}
+
+fn call(func: Func, args: &[Value]) -> Result<Value, String> {
+    let string = |i: usize| args[i].to_string();
+    Ok(match func {
+        Func::Abs => Value::Number(args[0].to_number()?.abs()),
+        Func::Concat => {
+            Value::String(args.iter().map(|v| v.to_string()).collect())
+        }
+        Func::Len => Value::Number(string(0).chars().count() as f64),
+        Func::Lower => Value::String(string(0).to_lowercase()),
+        Func::Upper => Value::String(string(0).to_uppercase()),
+        Func::Trim => Value::String(string(0).trim().to_owned()),
+        Func::Max | Func::Min => {
+            let mut best = args[0].to_number()?;
+            for arg in &args[1..] {
+                let n = arg.to_number()?;
+                if (func == Func::Max) == (n > best) {
+                    best = n;
+                }
+            }
+            Value::Number(best)
+        }
+        Func::Round => {
+            let digits = match args.get(1) {
+                None => 0.0,
+                Some(d) => d.to_number()?.trunc().clamp(-308.0, 308.0),
+            };
+            let x = args[0].to_number()?;
+            let scale = 10f64.powf(digits);
+            // With many digits, x has no more digits to round.
+            if (x * scale).is_infinite() {
+                Value::Number(x)
+            } else {
+                Value::Number((x * scale).round() / scale)
+            }
+        }
+        Func::Substr => {
+            let s = string(0);
+            let start = args[1].to_number()?.max(0.0) as usize;
+            let chars = s.chars().skip(start);
+            Value::String(match args.get(2) {
+                None => chars.collect(),
+                Some(len) => chars.take(len.to_number()?.max(0.0) as usize)
+                                  .collect(),
+            })
+        }
+        Func::If => unreachable!(),
+    })
+}
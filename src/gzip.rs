@@ -0,0 +1,1072 @@
+//! Decoding and encoding of gzip compressed data.
+//!
+//! This is a plain implementation of DEFLATE (RFC 1951) inside the gzip file
+//! format (RFC 1952). Besides decoding a stream from start to end, it can
+//! record checkpoints at the boundaries of DEFLATE blocks and later resume
+//! decoding from one of them (like zlib's `zran` example). This is what
+//! makes it possible to index gzip compressed CSV data.
+//!
+//! Raw DEFLATE data without the gzip framing (as stored in zip archives) can
+//! be decoded, too.
+
+use std::cmp;
+use std::fs;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+
+use compress::{self, BitWriter, Matcher, Sequence};
+
+/// The size of the DEFLATE window, i.e., the maximum distance that a match
+/// may refer back to.
+const WINDOW_SIZE: usize = 1 << 15;
+const WINDOW_MASK: usize = WINDOW_SIZE - 1;
+
+/// The number of bits that Huffman codes are decoded with a single table
+/// lookup for. Longer codes are decoded bit by bit.
+const FAST_BITS: u32 = 10;
+
+const LENGTH_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59,
+    67, 83, 99, 115, 131, 163, 195, 227, 258,
+];
+const LENGTH_EXTRA: [u8; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4,
+    5, 5, 5, 5, 0,
+];
+const DIST_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385,
+    513, 769, 1025, 1537, 2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+const DIST_EXTRA: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10,
+    11, 11, 12, 12, 13, 13,
+];
+/// The order in which the code lengths of the code length alphabet are
+/// stored in a dynamic block header.
+const CLEN_ORDER: [usize; 19] = [
+    16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15,
+];
+
+const CRC_TABLE: [u32; 256] = crc_table();
+
+const fn crc_table() -> [u32; 256] {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut c = i as u32;
+        let mut k = 0;
+        while k < 8 {
+            c = if c & 1 == 1 { 0xEDB8_8320 ^ (c >> 1) } else { c >> 1 };
+            k += 1;
+        }
+        table[i] = c;
+        i += 1;
+    }
+    table
+}
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData, format!("invalid gzip data: {}", msg))
+}
+
+/// Returns true if `path` looks like it names gzip compressed data.
+pub fn is_gzip_path(path: &::std::path::Path) -> bool {
+    path.extension().map_or(false, |ext| ext == "gz")
+}
+
+/// A point in a gzip stream at the start of a DEFLATE block, from which
+/// decoding can resume.
+#[derive(Clone, Debug)]
+pub struct Checkpoint {
+    /// The offset in the decompressed data.
+    pub out: u64,
+    /// The offset in the compressed data, in bits.
+    pub in_bits: u64,
+    /// The (up to 32KB of) decompressed data preceding `out`.
+    pub window: Vec<u8>,
+}
+
+/// Reads bits from a byte stream, least significant bit first.
+struct BitReader<R> {
+    inner: R,
+    buf: Box<[u8]>,
+    pos: usize,
+    len: usize,
+    bits: u64,
+    nbits: u32,
+    /// The number of bytes taken from `inner`, including those that were
+    /// read before this reader was created.
+    consumed: u64,
+}
+
+impl<R: Read> BitReader<R> {
+    fn new(inner: R, offset: u64) -> BitReader<R> {
+        BitReader {
+            inner: inner,
+            buf: vec![0; 1 << 16].into_boxed_slice(),
+            pos: 0,
+            len: 0,
+            bits: 0,
+            nbits: 0,
+            consumed: offset,
+        }
+    }
+
+    /// Fills the bit buffer with as many whole bytes as fit, unless the end
+    /// of the input is reached.
+    fn refill(&mut self) -> io::Result<()> {
+        while self.nbits <= 56 {
+            if self.pos == self.len {
+                self.len = loop {
+                    match self.inner.read(&mut self.buf) {
+                        Ok(n) => break n,
+                        Err(ref err)
+                            if err.kind() == io::ErrorKind::Interrupted => {}
+                        Err(err) => return Err(err),
+                    }
+                };
+                self.pos = 0;
+                if self.len == 0 {
+                    return Ok(());
+                }
+            }
+            self.bits |= (self.buf[self.pos] as u64) << self.nbits;
+            self.pos += 1;
+            self.consumed += 1;
+            self.nbits += 8;
+        }
+        Ok(())
+    }
+
+    /// Returns the next `n` bits without consuming them. Past the end of
+    /// the input, the missing bits are zero.
+    fn peek(&mut self, n: u32) -> io::Result<u32> {
+        if self.nbits < n {
+            self.refill()?;
+        }
+        Ok((self.bits & ((1u64 << n) - 1)) as u32)
+    }
+
+    fn consume(&mut self, n: u32) -> io::Result<()> {
+        if n > self.nbits {
+            return Err(invalid("unexpected end of data"));
+        }
+        self.bits >>= n;
+        self.nbits -= n;
+        Ok(())
+    }
+
+    fn bits(&mut self, n: u32) -> io::Result<u32> {
+        let v = self.peek(n)?;
+        self.consume(n)?;
+        Ok(v)
+    }
+
+    fn byte(&mut self) -> io::Result<u8> {
+        self.bits(8).map(|b| b as u8)
+    }
+
+    fn u16_le(&mut self) -> io::Result<u16> {
+        self.bits(16).map(|b| b as u16)
+    }
+
+    fn u32_le(&mut self) -> io::Result<u32> {
+        let lo = self.bits(16)?;
+        let hi = self.bits(16)?;
+        Ok(lo | (hi << 16))
+    }
+
+    /// Discards the bits up to the next byte boundary.
+    fn align(&mut self) {
+        let n = self.nbits % 8;
+        self.bits >>= n;
+        self.nbits -= n;
+    }
+
+    /// Returns the position of the next bit in the input.
+    fn bit_pos(&self) -> u64 {
+        self.consumed * 8 - self.nbits as u64
+    }
+
+    fn at_eof(&mut self) -> io::Result<bool> {
+        if self.nbits == 0 {
+            self.refill()?;
+        }
+        Ok(self.nbits == 0)
+    }
+}
+
+/// A canonical Huffman code.
+struct Huffman {
+    /// The number of codes of each length.
+    counts: [u16; 16],
+    /// The symbols ordered by their codes.
+    symbols: Vec<u16>,
+    /// A table indexed by the next `FAST_BITS` bits of the input. Each entry
+    /// is the symbol shifted left by 4 bits plus the length of its code, or
+    /// zero if the code is longer.
+    fast: Vec<u16>,
+}
+
+impl Huffman {
+    fn new(lengths: &[u8]) -> io::Result<Huffman> {
+        let mut counts = [0u16; 16];
+        for &len in lengths {
+            counts[len as usize] += 1;
+        }
+        counts[0] = 0;
+        let mut left = 1i32;
+        for &count in &counts[1..] {
+            left = (left << 1) - count as i32;
+            if left < 0 {
+                return Err(invalid("over-subscribed Huffman code"));
+            }
+        }
+        let mut offsets = [0u16; 16];
+        for len in 1..15 {
+            offsets[len + 1] = offsets[len] + counts[len];
+        }
+        let mut symbols = vec![0; lengths.len()];
+        for (sym, &len) in lengths.iter().enumerate() {
+            if len != 0 {
+                symbols[offsets[len as usize] as usize] = sym as u16;
+                offsets[len as usize] += 1;
+            }
+        }
+
+        let mut fast = vec![0u16; 1 << FAST_BITS];
+        let (mut code, mut index) = (0u32, 0usize);
+        for len in 1..(FAST_BITS + 1) {
+            for _ in 0..counts[len as usize] {
+                let entry = (symbols[index] << 4) | len as u16;
+                let mut i = reverse_bits(code, len) as usize;
+                while i < fast.len() {
+                    fast[i] = entry;
+                    i += 1 << len;
+                }
+                code += 1;
+                index += 1;
+            }
+            code <<= 1;
+        }
+        Ok(Huffman { counts: counts, symbols: symbols, fast: fast })
+    }
+
+    fn decode<R: Read>(&self, br: &mut BitReader<R>) -> io::Result<u16> {
+        let entry = self.fast[br.peek(FAST_BITS)? as usize];
+        if entry != 0 {
+            br.consume((entry & 15) as u32)?;
+            return Ok(entry >> 4);
+        }
+        let (mut code, mut first, mut index) = (0i32, 0i32, 0i32);
+        for len in 1..16 {
+            code |= br.bits(1)? as i32;
+            let count = self.counts[len] as i32;
+            if code - count < first {
+                return Ok(self.symbols[(index + code - first) as usize]);
+            }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+        Err(invalid("bad Huffman code"))
+    }
+}
+
+fn reverse_bits(code: u32, len: u32) -> u32 {
+    code.reverse_bits() >> (32 - len)
+}
+
+fn fixed_codes() -> io::Result<(Huffman, Huffman)> {
+    let mut lengths = [0u8; 288];
+    for (sym, len) in lengths.iter_mut().enumerate() {
+        *len = match sym {
+            0..=143 => 8,
+            144..=255 => 9,
+            256..=279 => 7,
+            _ => 8,
+        };
+    }
+    Ok((Huffman::new(&lengths)?, Huffman::new(&[5; 30])?))
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum State {
+    /// At the start of a gzip member.
+    Header,
+    /// At the start of a DEFLATE block.
+    Block,
+    /// Inside a stored block with this many bytes left.
+    Stored(usize),
+    /// Inside a block compressed with Huffman codes.
+    Codes,
+    /// At the end of a gzip member.
+    Trailer,
+    Done,
+}
+
+/// A reader of the decompressed data in a gzip stream.
+///
+/// Streams made of several gzip members (e.g., created by concatenating gzip
+/// files) are decompressed as one.
+pub struct GzDecoder<R> {
+    br: BitReader<R>,
+    state: State,
+    /// Whether the current block is the last one of its member.
+    last: bool,
+    /// Whether the data is raw DEFLATE data without gzip framing.
+    raw: bool,
+    lit: Huffman,
+    dist: Huffman,
+    window: Box<[u8]>,
+    /// The number of bytes decompressed so far.
+    out: u64,
+    /// The length and distance of a match that is being copied.
+    copy_len: usize,
+    copy_dist: usize,
+    /// The CRC-32 and size of the decompressed data of the current member,
+    /// which are only checked if decoding started at the member's beginning.
+    crc: u32,
+    member_len: u32,
+    verify: bool,
+    /// The distance between recorded checkpoints, if they're recorded.
+    span: Option<u64>,
+    next_checkpoint: u64,
+    checkpoints: Vec<Checkpoint>,
+}
+
+impl<R: Read> GzDecoder<R> {
+    pub fn new(rdr: R) -> GzDecoder<R> {
+        GzDecoder::with_bits(BitReader::new(rdr, 0))
+    }
+
+    fn with_bits(br: BitReader<R>) -> GzDecoder<R> {
+        GzDecoder {
+            br: br,
+            state: State::Header,
+            last: false,
+            raw: false,
+            lit: Huffman { counts: [0; 16], symbols: vec![], fast: vec![] },
+            dist: Huffman { counts: [0; 16], symbols: vec![], fast: vec![] },
+            window: vec![0; WINDOW_SIZE].into_boxed_slice(),
+            out: 0,
+            copy_len: 0,
+            copy_dist: 0,
+            crc: !0,
+            member_len: 0,
+            verify: true,
+            span: None,
+            next_checkpoint: 0,
+            checkpoints: vec![],
+        }
+    }
+
+    /// Returns a reader of raw DEFLATE data, which has no gzip header and
+    /// trailer and ends with its last block.
+    pub fn raw(rdr: R) -> GzDecoder<R> {
+        let mut dec = GzDecoder::with_bits(BitReader::new(rdr, 0));
+        dec.state = State::Block;
+        dec.raw = true;
+        dec
+    }
+
+    /// Records a checkpoint at the first block boundary after every `span`
+    /// bytes of decompressed data.
+    pub fn record_checkpoints(&mut self, span: u64) {
+        self.span = Some(span);
+        self.next_checkpoint = self.out + span;
+    }
+
+    /// Returns the checkpoints recorded so far.
+    pub fn take_checkpoints(&mut self) -> Vec<Checkpoint> {
+        ::std::mem::replace(&mut self.checkpoints, vec![])
+    }
+
+    /// Returns the number of bytes decompressed so far.
+    pub fn position(&self) -> u64 {
+        self.out
+    }
+
+    #[inline]
+    fn emit(&mut self, b: u8) {
+        self.window[self.out as usize & WINDOW_MASK] = b;
+        self.out += 1;
+        self.crc = CRC_TABLE[((self.crc ^ b as u32) & 0xFF) as usize]
+                   ^ (self.crc >> 8);
+        self.member_len = self.member_len.wrapping_add(1);
+    }
+
+    /// Reads the header of a gzip member. Returns false if there is no
+    /// further member.
+    fn read_header(&mut self) -> io::Result<bool> {
+        let first = self.checkpoints.is_empty() && self.out == 0;
+        if self.br.at_eof()? {
+            return Ok(false);
+        }
+        if self.br.peek(16)? != 0x8B1F {
+            if first {
+                return Err(invalid("not in gzip format"));
+            }
+            // Like gzip, ignore trailing garbage after the last member.
+            return Ok(false);
+        }
+        self.br.consume(16)?;
+        if self.br.byte()? != 8 {
+            return Err(invalid("unknown compression method"));
+        }
+        let flags = self.br.byte()?;
+        // The modification time, extra flags and operating system.
+        for _ in 0..6 {
+            self.br.byte()?;
+        }
+        if flags & 4 != 0 {
+            let len = self.br.u16_le()?;
+            for _ in 0..len {
+                self.br.byte()?;
+            }
+        }
+        for &flag in &[8, 16] {
+            if flags & flag != 0 {
+                while self.br.byte()? != 0 {}
+            }
+        }
+        if flags & 2 != 0 {
+            self.br.u16_le()?;
+        }
+        self.crc = !0;
+        self.member_len = 0;
+        self.last = false;
+        Ok(true)
+    }
+
+    fn read_trailer(&mut self) -> io::Result<()> {
+        self.br.align();
+        let crc = self.br.u32_le()?;
+        let len = self.br.u32_le()?;
+        if self.verify && (crc != !self.crc || len != self.member_len) {
+            return Err(invalid("checksum mismatch"));
+        }
+        self.verify = true;
+        Ok(())
+    }
+
+    fn read_block_header(&mut self) -> io::Result<()> {
+        if let Some(span) = self.span {
+            if self.out >= self.next_checkpoint {
+                self.checkpoint();
+                self.next_checkpoint = self.out + span;
+            }
+        }
+        self.last = self.br.bits(1)? == 1;
+        match self.br.bits(2)? {
+            0 => {
+                self.br.align();
+                let len = self.br.u16_le()?;
+                let nlen = self.br.u16_le()?;
+                if len != !nlen {
+                    return Err(invalid("bad stored block length"));
+                }
+                self.state = State::Stored(len as usize);
+            }
+            1 => {
+                let (lit, dist) = fixed_codes()?;
+                self.lit = lit;
+                self.dist = dist;
+                self.state = State::Codes;
+            }
+            2 => {
+                self.read_dynamic_codes()?;
+                self.state = State::Codes;
+            }
+            _ => return Err(invalid("bad block type")),
+        }
+        Ok(())
+    }
+
+    fn read_dynamic_codes(&mut self) -> io::Result<()> {
+        let nlit = self.br.bits(5)? as usize + 257;
+        let ndist = self.br.bits(5)? as usize + 1;
+        let nclen = self.br.bits(4)? as usize + 4;
+        let mut clens = [0u8; 19];
+        for &i in &CLEN_ORDER[..nclen] {
+            clens[i] = self.br.bits(3)? as u8;
+        }
+        let clen = Huffman::new(&clens)?;
+        let mut lengths = vec![0u8; nlit + ndist];
+        let mut i = 0;
+        while i < lengths.len() {
+            let sym = clen.decode(&mut self.br)?;
+            let (value, repeat) = match sym {
+                0..=15 => (sym as u8, 1),
+                16 => {
+                    if i == 0 {
+                        return Err(invalid("repeat with no first length"));
+                    }
+                    (lengths[i - 1], 3 + self.br.bits(2)? as usize)
+                }
+                17 => (0, 3 + self.br.bits(3)? as usize),
+                _ => (0, 11 + self.br.bits(7)? as usize),
+            };
+            if i + repeat > lengths.len() {
+                return Err(invalid("too many code lengths"));
+            }
+            for len in &mut lengths[i..i + repeat] {
+                *len = value;
+            }
+            i += repeat;
+        }
+        if lengths[256] == 0 {
+            return Err(invalid("missing end of block code"));
+        }
+        self.lit = Huffman::new(&lengths[..nlit])?;
+        self.dist = Huffman::new(&lengths[nlit..])?;
+        Ok(())
+    }
+
+    fn checkpoint(&mut self) {
+        let len = cmp::min(self.out, WINDOW_SIZE as u64) as usize;
+        let start = self.out as usize - len;
+        let window = (start..start + len)
+            .map(|i| self.window[i & WINDOW_MASK])
+            .collect();
+        self.checkpoints.push(Checkpoint {
+            out: self.out,
+            in_bits: self.br.bit_pos(),
+            window: window,
+        });
+    }
+}
+
+impl<R: Read + Seek> GzDecoder<R> {
+    /// Resumes decoding at `checkpoint`, which must have been recorded for
+    /// the same data.
+    pub fn resume(
+        mut rdr: R,
+        checkpoint: &Checkpoint,
+    ) -> io::Result<GzDecoder<R>> {
+        let byte = checkpoint.in_bits / 8;
+        rdr.seek(SeekFrom::Start(byte))?;
+        let mut dec = GzDecoder::with_bits(BitReader::new(rdr, byte));
+        dec.br.bits((checkpoint.in_bits % 8) as u32)?;
+        let start = checkpoint.out - checkpoint.window.len() as u64;
+        for (i, &b) in checkpoint.window.iter().enumerate() {
+            dec.window[(start as usize + i) & WINDOW_MASK] = b;
+        }
+        dec.out = checkpoint.out;
+        dec.state = State::Block;
+        dec.verify = false;
+        Ok(dec)
+    }
+}
+
+impl<R: Read> Read for GzDecoder<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let mut n = 0;
+        while n < buf.len() {
+            if self.copy_len > 0 {
+                let k = cmp::min(self.copy_len, buf.len() - n);
+                for _ in 0..k {
+                    let from = (self.out as usize).wrapping_sub(self.copy_dist);
+                    let b = self.window[from & WINDOW_MASK];
+                    self.emit(b);
+                    buf[n] = b;
+                    n += 1;
+                }
+                self.copy_len -= k;
+                continue;
+            }
+            match self.state {
+                State::Done => break,
+                State::Header => {
+                    self.state = if self.read_header()? {
+                        State::Block
+                    } else {
+                        State::Done
+                    };
+                }
+                State::Block if self.last && self.raw => {
+                    self.state = State::Done;
+                }
+                State::Block if self.last => self.state = State::Trailer,
+                State::Block => self.read_block_header()?,
+                State::Stored(0) => self.state = State::Block,
+                State::Stored(left) => {
+                    let b = self.br.byte()?;
+                    self.emit(b);
+                    buf[n] = b;
+                    n += 1;
+                    self.state = State::Stored(left - 1);
+                }
+                State::Codes => {
+                    let sym = self.lit.decode(&mut self.br)? as usize;
+                    if sym < 256 {
+                        self.emit(sym as u8);
+                        buf[n] = sym as u8;
+                        n += 1;
+                        continue;
+                    } else if sym == 256 {
+                        self.state = State::Block;
+                        continue;
+                    } else if sym > 285 {
+                        return Err(invalid("bad length symbol"));
+                    }
+                    let i = sym - 257;
+                    let len = LENGTH_BASE[i] as usize
+                        + self.br.bits(LENGTH_EXTRA[i] as u32)? as usize;
+                    let d = self.dist.decode(&mut self.br)? as usize;
+                    if d >= 30 {
+                        return Err(invalid("bad distance symbol"));
+                    }
+                    let dist = DIST_BASE[d] as usize
+                        + self.br.bits(DIST_EXTRA[d] as u32)? as usize;
+                    if dist as u64 > cmp::min(self.out, WINDOW_SIZE as u64) {
+                        return Err(invalid("distance too far back"));
+                    }
+                    self.copy_len = len;
+                    self.copy_dist = dist;
+                }
+                State::Trailer => {
+                    self.read_trailer()?;
+                    self.state = State::Header;
+                }
+            }
+        }
+        Ok(n)
+    }
+}
+
+/// A gzip compressed file that can be read and seeked as if it were
+/// decompressed.
+///
+/// Seeking backwards or far ahead resumes decoding at the closest preceding
+/// checkpoint (or at the start of the file, if there is none) and then
+/// decodes up to the requested position.
+pub struct GzFile {
+    file: fs::File,
+    checkpoints: Vec<Checkpoint>,
+    dec: GzDecoder<fs::File>,
+}
+
+impl GzFile {
+    /// Opens a gzip compressed file. The checkpoints, if any, must be
+    /// ordered by their decompressed offsets.
+    pub fn new(
+        file: fs::File,
+        checkpoints: Vec<Checkpoint>,
+    ) -> io::Result<GzFile> {
+        let dec = GzDecoder::new(file.try_clone()?);
+        Ok(GzFile { file: file, checkpoints: checkpoints, dec: dec })
+    }
+
+    pub fn get_ref(&self) -> &fs::File {
+        &self.file
+    }
+
+    pub fn record_checkpoints(&mut self, span: u64) {
+        self.dec.record_checkpoints(span);
+    }
+
+    pub fn take_checkpoints(&mut self) -> Vec<Checkpoint> {
+        self.dec.take_checkpoints()
+    }
+}
+
+impl Read for GzFile {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.dec.read(buf)
+    }
+}
+
+impl Seek for GzFile {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let target = match pos {
+            SeekFrom::Start(n) => n,
+            SeekFrom::Current(n) => (self.dec.position() as i64 + n) as u64,
+            SeekFrom::End(_) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::Other,
+                    "cannot seek relative to the end of gzip data"));
+            }
+        };
+        let current = self.dec.position();
+        let checkpoint =
+            self.checkpoints.iter().rev().find(|cp| cp.out <= target);
+        let resume_at = checkpoint.map_or(0, |cp| cp.out);
+        if target < current || resume_at > current {
+            let mut file = self.file.try_clone()?;
+            self.dec = match checkpoint {
+                Some(cp) => GzDecoder::resume(file, cp)?,
+                None => {
+                    file.seek(SeekFrom::Start(0))?;
+                    GzDecoder::new(file)
+                }
+            };
+        }
+        let skip = target - self.dec.position();
+        io::copy(&mut (&mut self.dec).take(skip), &mut io::sink())?;
+        Ok(self.dec.position())
+    }
+}
+
+/// The amount of data that is compressed as one DEFLATE block.
+const BLOCK_INPUT: usize = 1 << 16;
+
+/// The largest match of DEFLATE.
+const MAX_MATCH: usize = 258;
+
+fn crc32(mut crc: u32, data: &[u8]) -> u32 {
+    for &b in data {
+        crc = CRC_TABLE[((crc ^ b as u32) & 0xFF) as usize] ^ (crc >> 8);
+    }
+    crc
+}
+
+/// Returns the index of the largest of `bases` that is at most `n`.
+fn code_for(bases: &[u16], n: usize) -> usize {
+    bases.iter().rposition(|&base| base as usize <= n).unwrap()
+}
+
+/// Returns the codes of the canonical Huffman code with the given lengths,
+/// with their bits reversed for writing them least significant bit first.
+fn canonical_codes(lens: &[u8]) -> Vec<u16> {
+    let mut count = [0u16; 16];
+    for &len in lens {
+        count[len as usize] += 1;
+    }
+    count[0] = 0;
+    let mut next = [0u16; 16];
+    let mut code = 0u16;
+    for len in 1..16 {
+        code = (code + count[len - 1]) << 1;
+        next[len] = code;
+    }
+    lens.iter().map(|&len| {
+        if len == 0 {
+            return 0;
+        }
+        let code = next[len as usize];
+        next[len as usize] += 1;
+        code.reverse_bits() >> (16 - len)
+    }).collect()
+}
+
+/// The fixed Huffman code lengths of the literal/length and distance
+/// alphabets.
+fn fixed_lengths() -> (Vec<u8>, Vec<u8>) {
+    let mut lit = vec![8; 288];
+    for len in &mut lit[144..256] {
+        *len = 9;
+    }
+    for len in &mut lit[256..280] {
+        *len = 7;
+    }
+    (lit, vec![5; 30])
+}
+
+/// Run-length encodes the code lengths of a dynamic block header with the
+/// symbols of the code length alphabet. Returns the symbols with the values
+/// of their extra bits.
+fn encode_lengths(lens: &[u8]) -> Vec<(u8, u8)> {
+    let mut out = vec![];
+    let mut i = 0;
+    while i < lens.len() {
+        let len = lens[i];
+        let run = lens[i..].iter().take_while(|&&l| l == len).count();
+        if len == 0 && run >= 11 {
+            let n = cmp::min(run, 138);
+            out.push((18, (n - 11) as u8));
+            i += n;
+        } else if len == 0 && run >= 3 {
+            out.push((17, (run - 3) as u8));
+            i += run;
+        } else if len != 0 && run >= 4 {
+            out.push((len, 0));
+            let n = cmp::min(run - 1, 6);
+            out.push((16, (n - 3) as u8));
+            i += 1 + n;
+        } else {
+            out.push((len, 0));
+            i += 1;
+        }
+    }
+    out
+}
+
+/// A writer that compresses the data written to it in the gzip format.
+///
+/// Flushing ends the current gzip member, so that everything written so far
+/// is complete gzip data. Data written after that starts a new member, which
+/// decoders read as a continuation of the same stream. Dropping the writer
+/// flushes it, too (but ignores errors).
+pub struct GzEncoder<W: Write> {
+    wtr: W,
+    level: u32,
+    matcher: Matcher,
+    /// The last `WINDOW_SIZE` bytes of compressed data, which matches may
+    /// refer to, followed by the data that wasn't compressed yet.
+    data: Vec<u8>,
+    /// The start of the data that wasn't compressed yet.
+    pending: usize,
+    /// The position of `data[0]` in the current member.
+    base: u64,
+    bw: BitWriter,
+    seqs: Vec<Sequence>,
+    in_member: bool,
+    /// Whether a complete member was written.
+    finished: bool,
+    crc: u32,
+    member_len: u32,
+}
+
+impl<W: Write> GzEncoder<W> {
+    /// Creates an encoder with a compression level from 0 (no compression)
+    /// to 9 (best compression).
+    pub fn new(wtr: W, level: u32) -> GzEncoder<W> {
+        GzEncoder {
+            wtr: wtr,
+            level: level,
+            matcher: GzEncoder::<W>::matcher(level),
+            data: vec![],
+            pending: 0,
+            base: 0,
+            bw: BitWriter::new(),
+            seqs: vec![],
+            in_member: false,
+            finished: false,
+            crc: !0,
+            member_len: 0,
+        }
+    }
+
+    fn matcher(level: u32) -> Matcher {
+        let chain = [1, 4, 8, 16, 32, 64, 128, 256, 1024, 4096];
+        Matcher::new(WINDOW_SIZE, MAX_MATCH,
+                     chain[cmp::min(level, 9) as usize], level >= 4)
+    }
+
+    fn start_member(&mut self) {
+        if !self.in_member {
+            // No file name and modification time, and an unknown system.
+            self.bw.out.extend_from_slice(
+                &[0x1F, 0x8B, 8, 0, 0, 0, 0, 0, 0, 255]);
+            self.in_member = true;
+            self.crc = !0;
+            self.member_len = 0;
+        }
+    }
+
+    /// Compresses the pending data up to `end` as one block.
+    fn compress_block(&mut self, end: usize, last: bool) -> io::Result<()> {
+        self.seqs.clear();
+        if self.level > 0 {
+            self.matcher.parse(
+                &self.data[..end], self.base, self.pending, &mut self.seqs);
+        } else if end > self.pending {
+            self.seqs.push(Sequence {
+                literals: end - self.pending,
+                len: 0,
+                dist: 0,
+            });
+        }
+
+        let mut lit_freqs = [0u32; 286];
+        let mut dist_freqs = [0u32; 30];
+        let mut pos = self.pending;
+        for seq in &self.seqs {
+            for &b in &self.data[pos..pos + seq.literals] {
+                lit_freqs[b as usize] += 1;
+            }
+            pos += seq.literals;
+            if seq.len > 0 {
+                lit_freqs[257 + code_for(&LENGTH_BASE, seq.len)] += 1;
+                dist_freqs[code_for(&DIST_BASE, seq.dist)] += 1;
+                pos += seq.len;
+            }
+        }
+        lit_freqs[256] = 1;
+
+        let lit_lens = compress::code_lengths(&lit_freqs, 15);
+        let mut dist_lens = compress::code_lengths(&dist_freqs, 15);
+        if dist_lens.iter().all(|&len| len == 0) {
+            dist_lens[0] = 1;
+        }
+        let nlit = cmp::max(257, lit_lens.iter().rposition(|&l| l > 0)
+                                         .unwrap() + 1);
+        let ndist = dist_lens.iter().rposition(|&l| l > 0).unwrap() + 1;
+        let mut all_lens = lit_lens[..nlit].to_vec();
+        all_lens.extend_from_slice(&dist_lens[..ndist]);
+        let clens = encode_lengths(&all_lens);
+        let mut clen_freqs = [0u32; 19];
+        for &(sym, _) in &clens {
+            clen_freqs[sym as usize] += 1;
+        }
+        let clen_lens = compress::code_lengths(&clen_freqs, 7);
+        let nclen = cmp::max(4, CLEN_ORDER.iter()
+                                 .rposition(|&s| clen_lens[s] > 0)
+                                 .unwrap() + 1);
+
+        let (fixed_lit, fixed_dist) = fixed_lengths();
+        let data_bits = |lit: &[u8], dist: &[u8]| -> u64 {
+            let mut bits = 0;
+            for (s, &f) in lit_freqs.iter().enumerate() {
+                bits += f as u64 * lit[s] as u64;
+                if s > 256 {
+                    bits += f as u64 * LENGTH_EXTRA[s - 257] as u64;
+                }
+            }
+            for (s, &f) in dist_freqs.iter().enumerate() {
+                bits += f as u64 * (dist[s] as u64 + DIST_EXTRA[s] as u64);
+            }
+            bits
+        };
+        let mut dynamic_bits = 17 + 3 * nclen as u64
+                               + data_bits(&lit_lens, &dist_lens);
+        for &(sym, _) in &clens {
+            dynamic_bits += clen_lens[sym as usize] as u64
+                + [2, 3, 7].get((sym as usize).wrapping_sub(16))
+                           .map_or(0, |&n| n);
+        }
+        let fixed_bits = 3 + data_bits(&fixed_lit, &fixed_dist);
+        let len = (end - self.pending) as u64;
+        let stored_bits = 8 * len + 40 * cmp::max(1, (len + 65534) / 65535);
+
+        if self.level == 0
+            || (stored_bits < dynamic_bits && stored_bits < fixed_bits) {
+            self.write_stored(end, last);
+        } else if fixed_bits <= dynamic_bits {
+            self.bw.put(last as u64, 1);
+            self.bw.put(1, 2);
+            self.write_data(&fixed_lit, &fixed_dist);
+        } else {
+            self.bw.put(last as u64, 1);
+            self.bw.put(2, 2);
+            self.bw.put((nlit - 257) as u64, 5);
+            self.bw.put((ndist - 1) as u64, 5);
+            self.bw.put((nclen - 4) as u64, 4);
+            for &s in &CLEN_ORDER[..nclen] {
+                self.bw.put(clen_lens[s] as u64, 3);
+            }
+            let codes = canonical_codes(&clen_lens);
+            for &(sym, extra) in &clens {
+                let sym = sym as usize;
+                self.bw.put(codes[sym] as u64, clen_lens[sym] as u32);
+                match sym {
+                    16 => self.bw.put(extra as u64, 2),
+                    17 => self.bw.put(extra as u64, 3),
+                    18 => self.bw.put(extra as u64, 7),
+                    _ => {}
+                }
+            }
+            self.write_data(&lit_lens, &dist_lens);
+        }
+
+        self.pending = end;
+        // Keep as much data as later matches may refer to.
+        if self.pending > 2 * WINDOW_SIZE {
+            let drop = self.pending - WINDOW_SIZE;
+            self.data.drain(..drop);
+            self.base += drop as u64;
+            self.pending -= drop;
+        }
+        self.wtr.write_all(&self.bw.out)?;
+        self.bw.out.clear();
+        Ok(())
+    }
+
+    fn write_stored(&mut self, end: usize, last: bool) {
+        let mut chunks: Vec<&[u8]> =
+            self.data[self.pending..end].chunks(65535).collect();
+        if chunks.is_empty() {
+            chunks.push(&[]);
+        }
+        let n = chunks.len();
+        for (i, chunk) in chunks.into_iter().enumerate() {
+            self.bw.put((last && i + 1 == n) as u64, 1);
+            self.bw.put(0, 2);
+            self.bw.align();
+            let len = chunk.len() as u16;
+            self.bw.out.extend_from_slice(&len.to_le_bytes());
+            self.bw.out.extend_from_slice(&(!len).to_le_bytes());
+            self.bw.out.extend_from_slice(chunk);
+        }
+    }
+
+    /// Writes the sequences of the current block and its end with the
+    /// given codes.
+    fn write_data(&mut self, lit_lens: &[u8], dist_lens: &[u8]) {
+        let lit_codes = canonical_codes(lit_lens);
+        let dist_codes = canonical_codes(dist_lens);
+        let mut pos = self.pending;
+        for seq in &self.seqs {
+            for &b in &self.data[pos..pos + seq.literals] {
+                let b = b as usize;
+                self.bw.put(lit_codes[b] as u64, lit_lens[b] as u32);
+            }
+            pos += seq.literals;
+            if seq.len > 0 {
+                let i = code_for(&LENGTH_BASE, seq.len);
+                self.bw.put(lit_codes[257 + i] as u64,
+                            lit_lens[257 + i] as u32);
+                self.bw.put((seq.len - LENGTH_BASE[i] as usize) as u64,
+                            LENGTH_EXTRA[i] as u32);
+                let d = code_for(&DIST_BASE, seq.dist);
+                self.bw.put(dist_codes[d] as u64, dist_lens[d] as u32);
+                self.bw.put((seq.dist - DIST_BASE[d] as usize) as u64,
+                            DIST_EXTRA[d] as u32);
+                pos += seq.len;
+            }
+        }
+        self.bw.put(lit_codes[256] as u64, lit_lens[256] as u32);
+    }
+
+    /// Compresses the pending data and writes the end of the member.
+    fn finish_member(&mut self) -> io::Result<()> {
+        self.start_member();
+        let end = self.data.len();
+        self.compress_block(end, true)?;
+        self.bw.align();
+        let (crc, len) = (!self.crc, self.member_len);
+        self.bw.out.extend_from_slice(&crc.to_le_bytes());
+        self.bw.out.extend_from_slice(&len.to_le_bytes());
+        self.wtr.write_all(&self.bw.out)?;
+        self.bw.out.clear();
+        self.in_member = false;
+        self.finished = true;
+        self.data.clear();
+        self.pending = 0;
+        self.base = 0;
+        self.matcher = GzEncoder::<W>::matcher(self.level);
+        Ok(())
+    }
+}
+
+impl<W: Write> Write for GzEncoder<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.start_member();
+        self.crc = crc32(self.crc, buf);
+        self.member_len = self.member_len.wrapping_add(buf.len() as u32);
+        self.data.extend_from_slice(buf);
+        while self.data.len() - self.pending >= BLOCK_INPUT {
+            let end = self.pending + BLOCK_INPUT;
+            self.compress_block(end, false)?;
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if self.in_member {
+            self.finish_member()?;
+        }
+        self.wtr.flush()
+    }
+}
+
+impl<W: Write> Drop for GzEncoder<W> {
+    fn drop(&mut self) {
+        // Even without any data, the output should be valid gzip data.
+        if self.in_member || !self.finished {
+            let _ = self.finish_member();
+            let _ = self.wtr.flush();
+        }
+    }
+}
@@ -0,0 +1,129 @@
+//! Reading CSV data from HTTP and HTTPS URLs.
+//!
+//! None of xsv's dependencies is an HTTP client, so the transfer is done by
+//! the `curl` program, and the response body is read from its output.
+
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::Path;
+use std::process::{Child, ChildStdout, Command, Stdio};
+
+/// How long to wait for a connection, in seconds.
+const CONNECT_TIMEOUT: u32 = 30;
+/// A transfer fails when it receives less than a byte per second for this
+/// many seconds.
+const STALL_TIMEOUT: u32 = 120;
+
+/// The response body of a request, which fails with an error at its end
+/// if the response status was not a success or the transfer was cut off.
+pub struct HttpReader {
+    url: String,
+    child: Child,
+    body: ChildStdout,
+    done: bool,
+}
+
+impl HttpReader {
+    /// Requests `url` with the extra request `headers`, which are given
+    /// like `Name: value`.
+    ///
+    /// The headers are written to the standard input of curl rather than
+    /// given as its arguments, since they often hold credentials, and the
+    /// arguments of a process can be read by any user of the system.
+    pub fn open(url: &str, headers: &[String]) -> io::Result<HttpReader> {
+        if let Some(h) = headers.iter().find(|h| h.contains(['\r', '\n'])) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("HTTP header {:?} contains a line break", h),
+            ));
+        }
+        let mut cmd = Command::new("curl");
+        cmd.args(["--silent", "--show-error", "--fail", "--location"])
+            .arg("--connect-timeout").arg(CONNECT_TIMEOUT.to_string())
+            .arg("--speed-limit").arg("1")
+            .arg("--speed-time").arg(STALL_TIMEOUT.to_string());
+        if headers.is_empty() {
+            cmd.stdin(Stdio::null());
+        } else {
+            // `@-` reads one header from each line of standard input.
+            cmd.arg("--header").arg("@-").stdin(Stdio::piped());
+        }
+        cmd.arg(url)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+        let mut child = cmd.spawn().map_err(|err| io::Error::new(
+            io::ErrorKind::Other,
+            format!("Reading from URLs requires the curl program, which \
+                     could not be run: {}", err),
+        ))?;
+        if let Some(mut stdin) = child.stdin.take() {
+            let mut lines = String::new();
+            for header in headers {
+                lines.push_str(header);
+                lines.push('\n');
+            }
+            // curl reads all of the headers before it makes the request,
+            // and the pipe is closed when `stdin` is dropped.
+            if let Err(err) = stdin.write_all(lines.as_bytes()) {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(err);
+            }
+        }
+        let body = child.stdout.take().unwrap();
+        Ok(HttpReader {
+            url: url.to_owned(),
+            child: child,
+            body: body,
+            done: false,
+        })
+    }
+
+    /// Waits for the transfer to end and reports why it failed, if it did.
+    fn finish(&mut self) -> io::Result<()> {
+        self.done = true;
+        let mut msg = String::new();
+        if let Some(ref mut stderr) = self.child.stderr {
+            let _ = stderr.read_to_string(&mut msg);
+        }
+        let status = self.child.wait()?;
+        if status.success() {
+            return Ok(());
+        }
+        let msg = msg.trim().trim_start_matches("curl: ").to_owned();
+        Err(io::Error::new(io::ErrorKind::Other, format!(
+            "failed to read {}: {}", self.url,
+            if msg.is_empty() { status.to_string() } else { msg })))
+    }
+}
+
+impl Read for HttpReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.done {
+            return Ok(0);
+        }
+        let n = self.body.read(buf)?;
+        if n == 0 && !buf.is_empty() {
+            self.finish()?;
+        }
+        Ok(n)
+    }
+}
+
+impl Drop for HttpReader {
+    fn drop(&mut self) {
+        // The command may not read the whole response.
+        if !self.done {
+            let _ = self.child.kill();
+            let _ = self.child.wait();
+        }
+    }
+}
+
+/// Downloads `url` to the file at `path`.
+pub fn download(url: &str, headers: &[String], path: &Path) -> io::Result<()> {
+    let mut rdr = HttpReader::open(url, headers)?;
+    let mut file = fs::File::create(path)?;
+    io::copy(&mut rdr, &mut file)?;
+    Ok(())
+}
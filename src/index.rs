@@ -1,10 +1,42 @@
-use std::io;
+use std::fs;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::mem;
 use std::ops;
+use std::path::{Path, PathBuf};
 
+use byteorder::{BigEndian, ByteOrder, ReadBytesExt, WriteBytesExt};
 use csv;
 use csv_index::RandomAccessSimple;
+use filetime::FileTime;
 
 use CliResult;
+use config::{Config, DataFile};
+use gzip::Checkpoint;
+use util;
+
+/// The bytes every index file starts with, followed by the version of the
+/// format as a big endian `u16`.
+///
+/// Index files written by older versions of xsv have no header at all. They
+/// consist only of the record offsets written by `RandomAccessSimple`.
+///
+/// The header also has the metadata of the CSV file, the lengths of the
+/// record offsets, of the field offsets (which are only written with
+/// --fields) and of the decompressor checkpoints of gzip compressed CSV
+/// data, and the comment character the index was created with (as a byte
+/// that is 1 if there is one, followed by the character). These sections
+/// follow the header in that order.
+const MAGIC: &'static [u8] = b"XSVIDX";
+const VERSION: u16 = 1;
+const HEADER_LEN: u64 = 6 + 2 + 8 + 8 + 4 + 8 + 8 + 8 + 2;
+/// The position of the lengths of the sections in the header.
+const LENGTHS_POS: u64 = 6 + 2 + 8 + 8 + 4;
+/// The default distance, in bytes of decompressed data, between the
+/// checkpoints stored for gzip compressed CSV data.
+pub const DEFAULT_SPAN: u64 = 1 << 20;
+/// The number of fields written for records whose field offsets aren't
+/// stored, because they contain quotes or start with a byte order mark.
+const NO_FIELDS: u32 = ::std::u32::MAX;
 
 /// Indexed composes a CSV reader with a simple random access index.
 pub struct Indexed<R, I> {
@@ -59,3 +91,817 @@ impl<R: io::Read + io::Seek, I: io::Read + io::Seek> Indexed<R, I> {
         Ok(())
     }
 }
+
+/// Metadata about the CSV data an index was created from, which is used to
+/// detect stale indexes.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Source {
+    size: u64,
+    mtime_secs: u64,
+    mtime_nanos: u32,
+}
+
+impl Source {
+    fn from_metadata(md: &fs::Metadata) -> Source {
+        let mtime = FileTime::from_last_modification_time(md);
+        Source {
+            size: md.len(),
+            mtime_secs: mtime.seconds_relative_to_1970(),
+            mtime_nanos: mtime.nanoseconds(),
+        }
+    }
+
+    /// Describes how `current` differs from this metadata of the file
+    /// called `what`, if it does.
+    fn difference(&self, current: &Source, what: &str) -> Option<String> {
+        if self.size != current.size {
+            Some(format!("{} was {} bytes when it was indexed and is now {} \
+                          bytes", what, self.size, current.size))
+        } else if (self.mtime_secs, self.mtime_nanos)
+            != (current.mtime_secs, current.mtime_nanos)
+        {
+            Some(format!("{} was modified after it was indexed", what))
+        } else {
+            None
+        }
+    }
+
+    fn read<R: io::Read>(rdr: &mut R) -> io::Result<Source> {
+        Ok(Source {
+            size: rdr.read_u64::<BigEndian>()?,
+            mtime_secs: rdr.read_u64::<BigEndian>()?,
+            mtime_nanos: rdr.read_u32::<BigEndian>()?,
+        })
+    }
+
+    fn write<W: io::Write>(&self, wtr: &mut W) -> io::Result<()> {
+        wtr.write_u64::<BigEndian>(self.size)?;
+        wtr.write_u64::<BigEndian>(self.mtime_secs)?;
+        wtr.write_u32::<BigEndian>(self.mtime_nanos)
+    }
+}
+
+/// Describes how the comment character an index was created with differs
+/// from the `current` one, if it does.
+fn comment_difference(
+    indexed: Option<u8>,
+    current: Option<u8>,
+) -> Option<String> {
+    if indexed == current {
+        return None;
+    }
+    Some(match indexed {
+        None => "it was created without --comment".to_owned(),
+        Some(c) => format!("it was created with --comment '{}'", c as char),
+    })
+}
+
+/// An index file opened for reading.
+///
+/// This reads and seeks within the record offsets only, so that it can be
+/// given to `RandomAccessSimple` regardless of what surrounds them.
+#[derive(Debug)]
+pub struct IndexFile {
+    file: fs::File,
+    start: u64,
+    /// The end of the record offsets, if they aren't at the end of the file.
+    end: Option<u64>,
+    /// The position and length of the field offsets, if there are any.
+    fields: Option<(u64, u64)>,
+    /// The position and length of the checkpoints, if there are any.
+    checkpoints: Option<(u64, u64)>,
+    /// None for index files in the old format, which have no metadata.
+    source: Option<Source>,
+    /// The character that starts the comment lines which were skipped.
+    comment: Option<u8>,
+}
+
+impl IndexFile {
+    pub fn open(path: &Path) -> io::Result<IndexFile> {
+        let mut file = fs::File::open(path)?;
+        let file_len = file.metadata()?.len();
+        let mut magic = [0; 6];
+        let is_new = file_len >= HEADER_LEN
+            && file.read_exact(&mut magic).is_ok()
+            && &magic[..] == MAGIC;
+        if !is_new {
+            return Ok(IndexFile {
+                file: file, start: 0, end: None,
+                fields: None, checkpoints: None, source: None,
+                comment: None,
+            });
+        }
+        let version = file.read_u16::<BigEndian>()?;
+        if version != VERSION {
+            return Err(io::Error::new(io::ErrorKind::Other, format!(
+                "The index file {} has unsupported format version {}. \
+                 Please re-create the index.", path.display(), version)));
+        }
+        let source = Source::read(&mut file)?;
+        let end = HEADER_LEN + file.read_u64::<BigEndian>()?;
+        let fields_len = file.read_u64::<BigEndian>()?;
+        let checkpoints_len = file.read_u64::<BigEndian>()?;
+        let has_comment = file.read_u8()? == 1;
+        let comment = file.read_u8()?;
+        Ok(IndexFile {
+            file: file,
+            start: HEADER_LEN,
+            end: Some(end),
+            fields: if fields_len > 0 { Some((end, fields_len)) } else { None },
+            checkpoints: if checkpoints_len > 0 {
+                Some((end + fields_len, checkpoints_len))
+            } else {
+                None
+            },
+            source: Some(source),
+            comment: if has_comment { Some(comment) } else { None },
+        })
+    }
+
+    /// Opens the record offsets between `start` and `end` in `file`.
+    fn section(file: fs::File, start: u64, end: u64) -> IndexFile {
+        IndexFile {
+            file: file, start: start, end: Some(end),
+            fields: None, checkpoints: None, source: None,
+            comment: None,
+        }
+    }
+
+    pub fn has_fields(&self) -> bool {
+        self.fields.is_some()
+    }
+
+    /// Reads the decompressor checkpoints stored for gzip compressed CSV
+    /// data.
+    pub fn checkpoints(&mut self) -> io::Result<Vec<Checkpoint>> {
+        let start = match self.checkpoints {
+            None => return Ok(vec![]),
+            Some((start, _)) => start,
+        };
+        self.file.seek(SeekFrom::Start(start))?;
+        let mut rdr = io::BufReader::new(&self.file);
+        let count = rdr.read_u64::<BigEndian>()?;
+        let mut checkpoints = Vec::with_capacity(count as usize);
+        for _ in 0..count {
+            let out = rdr.read_u64::<BigEndian>()?;
+            let in_bits = rdr.read_u64::<BigEndian>()?;
+            let mut window = vec![0; rdr.read_u32::<BigEndian>()? as usize];
+            rdr.read_exact(&mut window)?;
+            checkpoints.push(Checkpoint {
+                out: out, in_bits: in_bits, window: window,
+            });
+        }
+        Ok(checkpoints)
+    }
+
+    /// Returns a reader of the raw bytes and field offsets of every record
+    /// in `csv_file`, if this index has field offsets.
+    pub fn field_records(
+        mut self,
+        csv_file: DataFile,
+    ) -> io::Result<Option<FieldRecords>> {
+        let fields_start = match self.fields {
+            None => return Ok(None),
+            Some((start, _)) => start,
+        };
+        self.seek(SeekFrom::End(-8))?;
+        let count = self.read_u64::<BigEndian>()?;
+        self.file.seek(SeekFrom::Start(fields_start))?;
+        Ok(Some(FieldRecords {
+            data: io::BufReader::new(csv_file),
+            pos: 0,
+            idx: io::BufReader::new(self.file),
+            remaining: count,
+            buf: vec![],
+        }))
+    }
+
+    /// Returns the reason why this index doesn't match the CSV data in
+    /// the file with metadata `csv_md` anymore, if it doesn't. The records
+    /// of an index also only match when they're read with the same comment
+    /// character.
+    pub fn staleness(
+        &self,
+        csv_md: &fs::Metadata,
+        comment: Option<u8>,
+    ) -> io::Result<Option<String>> {
+        if let Some(reason) = comment_difference(self.comment, comment) {
+            return Ok(Some(reason));
+        }
+        let current = Source::from_metadata(csv_md);
+        let source = match self.source {
+            Some(source) => source,
+            None => {
+                // Old index files can only be compared by modification time.
+                let data_modified = util::last_modified(csv_md);
+                let idx_modified = util::last_modified(&self.file.metadata()?);
+                return Ok(if data_modified > idx_modified {
+                    Some("the CSV file was modified after the index file"
+                         .to_owned())
+                } else {
+                    None
+                });
+            }
+        };
+        Ok(source.difference(&current, "the CSV file"))
+    }
+}
+
+impl io::Read for IndexFile {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.file.read(buf)
+    }
+}
+
+impl io::Seek for IndexFile {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let pos = match (pos, self.end) {
+            (SeekFrom::Start(n), _) => SeekFrom::Start(self.start + n),
+            (SeekFrom::End(n), Some(end)) => {
+                SeekFrom::Start((end as i64 + n) as u64)
+            }
+            (pos, _) => pos,
+        };
+        let n = self.file.seek(pos)?;
+        Ok(n.saturating_sub(self.start))
+    }
+}
+
+/// Reads the raw bytes of every record in the CSV data along with the
+/// offsets of its fields, as stored by `xsv index --fields`.
+///
+/// This allows reading a few fields of a wide record without parsing all of
+/// them.
+pub struct FieldRecords {
+    data: io::BufReader<DataFile>,
+    pos: u64,
+    idx: io::BufReader<fs::File>,
+    remaining: u64,
+    buf: Vec<u8>,
+}
+
+impl FieldRecords {
+    /// Reads the next record into `raw`.
+    ///
+    /// If its field offsets are stored, they are written to `bounds` such
+    /// that field `i` is `raw[bounds[i]..bounds[i+1] - 1]`, and this returns
+    /// `Some(true)`. Otherwise, this returns `Some(false)` and the record
+    /// must be parsed. At the end of the data, this returns `None`.
+    pub fn read(
+        &mut self,
+        raw: &mut Vec<u8>,
+        bounds: &mut Vec<u32>,
+    ) -> io::Result<Option<bool>> {
+        if self.remaining == 0 {
+            return Ok(None);
+        }
+        self.remaining -= 1;
+        let start = self.idx.read_u64::<BigEndian>()?;
+        let len = self.idx.read_u64::<BigEndian>()?;
+        let n = self.idx.read_u32::<BigEndian>()?;
+        if start < self.pos {
+            return Err(io::Error::new(
+                io::ErrorKind::Other, "corrupt field offsets in index"));
+        }
+        io::copy(&mut (&mut self.data).take(start - self.pos),
+                 &mut io::sink())?;
+        raw.clear();
+        (&mut self.data).take(len).read_to_end(raw)?;
+        self.pos = start + raw.len() as u64;
+        bounds.clear();
+        if n == NO_FIELDS {
+            return Ok(Some(false));
+        }
+        self.buf.resize((n as usize + 1) * 4, 0);
+        self.idx.read_exact(&mut self.buf)?;
+        bounds.resize(n as usize + 1, 0);
+        BigEndian::read_u32_into(&self.buf, bounds);
+        Ok(Some(true))
+    }
+}
+
+/// Parses the raw bytes of a single record read by `FieldRecords`.
+pub fn parse_raw(
+    conf: &Config,
+    raw: &[u8],
+    record: &mut csv::ByteRecord,
+) -> CliResult<()> {
+    let mut rdr = conf.from_reader(raw);
+    if rdr.has_headers() {
+        *record = rdr.byte_headers()?.clone();
+    } else {
+        rdr.read_byte_record(record)?;
+    }
+    Ok(())
+}
+
+/// Computes the field offsets of a record from its raw bytes and its parsed
+/// fields, if the record is simple enough.
+fn field_bounds(
+    conf: &Config,
+    raw: &[u8],
+    record: &csv::ByteRecord,
+) -> Option<Vec<u32>> {
+    if raw.starts_with(b"\xEF\xBB\xBF")
+        || raw.contains(&conf.get_quote())
+        || raw.len() > NO_FIELDS as usize
+    {
+        return None;
+    }
+    let mut bounds = Vec::with_capacity(record.len() + 1);
+    let mut pos = 0;
+    bounds.push(0);
+    for field in record {
+        pos += field.len() + 1;
+        bounds.push(pos as u32);
+        // Every field but the last must be followed by a delimiter and the
+        // last one by a record terminator or the end of the data.
+        match raw.get(pos - 1) {
+            None if bounds.len() == record.len() + 1 => {}
+            Some(&b) if bounds.len() == record.len() + 1 => {
+                if b != b'\n' && b != b'\r' {
+                    return None;
+                }
+            }
+            Some(&b) if b == conf.get_delimiter() => {}
+            _ => return None,
+        }
+    }
+    Some(bounds)
+}
+
+fn write_field_entry<W: io::Write>(
+    wtr: &mut W,
+    conf: &Config,
+    start: u64,
+    raw: &[u8],
+    record: &csv::ByteRecord,
+) -> io::Result<()> {
+    wtr.write_u64::<BigEndian>(start)?;
+    wtr.write_u64::<BigEndian>(raw.len() as u64)?;
+    match field_bounds(conf, raw, record) {
+        None => wtr.write_u32::<BigEndian>(NO_FIELDS),
+        Some(bounds) => {
+            wtr.write_u32::<BigEndian>(record.len() as u32)?;
+            for b in bounds {
+                wtr.write_u32::<BigEndian>(b)?;
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Writes the field offsets of every record in the CSV data given by
+/// `conf`.
+fn write_fields<W: io::Write>(conf: &Config, wtr: &mut W) -> CliResult<()> {
+    let mut rdr = conf.reader_file()?;
+    let mut data = io::BufReader::new(conf.io_reader()?);
+    // Since the raw bytes of a record extend to the start of the next one,
+    // each entry is written after reading the following record.
+    let mut prev = csv::ByteRecord::new();
+    let mut prev_start = None;
+    let mut pos = 0;
+    let mut raw = vec![];
+    let mut record = csv::ByteRecord::new();
+    if rdr.has_headers() {
+        let header = rdr.byte_headers()?;
+        if !header.is_empty() {
+            prev = header.clone();
+            prev_start = header.position().map(|p| p.byte());
+        }
+    }
+    loop {
+        let more = rdr.read_byte_record(&mut record)?;
+        let start = record.position().map_or(0, |p| p.byte());
+        if let Some(prev_start) = prev_start {
+            io::copy(&mut (&mut data).take(prev_start - pos),
+                     &mut io::sink())?;
+            raw.clear();
+            if more {
+                (&mut data).take(start - prev_start).read_to_end(&mut raw)?;
+            } else {
+                data.read_to_end(&mut raw)?;
+            }
+            pos = prev_start + raw.len() as u64;
+            write_field_entry(wtr, conf, prev_start, &raw, &prev)?;
+        }
+        if !more {
+            return Ok(());
+        }
+        mem::swap(&mut prev, &mut record);
+        prev_start = Some(start);
+    }
+}
+
+fn write_checkpoints<W: io::Write>(
+    wtr: &mut W,
+    checkpoints: &[Checkpoint],
+) -> io::Result<()> {
+    wtr.write_u64::<BigEndian>(checkpoints.len() as u64)?;
+    for cp in checkpoints {
+        wtr.write_u64::<BigEndian>(cp.out)?;
+        wtr.write_u64::<BigEndian>(cp.in_bits)?;
+        wtr.write_u32::<BigEndian>(cp.window.len() as u32)?;
+        wtr.write_all(&cp.window)?;
+    }
+    Ok(())
+}
+
+/// Creates an index of the CSV file given by `conf` at `idx_path`.
+///
+/// If `fields` is set, the index also stores the field offsets of every
+/// record. If the CSV file is gzip compressed, the index also stores a
+/// checkpoint of the decompressor about every `span` bytes of decompressed
+/// data.
+pub fn create(
+    conf: &Config,
+    idx_path: &Path,
+    fields: bool,
+    span: u64,
+) -> CliResult<()> {
+    let mut rdr = conf.reader_file()?;
+    if let DataFile::Gzip(ref mut gz) = *rdr.get_mut() {
+        gz.record_checkpoints(span);
+    }
+    let source = Source::from_metadata(&rdr.get_ref().metadata()?);
+    let mut wtr = io::BufWriter::new(fs::File::create(idx_path)?);
+    wtr.write_all(MAGIC)?;
+    wtr.write_u16::<BigEndian>(VERSION)?;
+    source.write(&mut wtr)?;
+    // The lengths of the sections aren't known until they're written.
+    wtr.write_all(&[0; 24])?;
+    match conf.get_comment() {
+        None => wtr.write_all(&[0, 0])?,
+        Some(c) => wtr.write_all(&[1, c])?,
+    }
+    RandomAccessSimple::create(&mut rdr, &mut wtr)?;
+    let records_end = wtr.seek(SeekFrom::Current(0))?;
+    if fields {
+        write_fields(conf, &mut wtr)?;
+    }
+    let fields_end = wtr.seek(SeekFrom::Current(0))?;
+    if let DataFile::Gzip(ref mut gz) = *rdr.get_mut() {
+        write_checkpoints(&mut wtr, &gz.take_checkpoints())?;
+    }
+    let checkpoints_end = wtr.seek(SeekFrom::Current(0))?;
+    wtr.seek(SeekFrom::Start(LENGTHS_POS))?;
+    wtr.write_u64::<BigEndian>(records_end - HEADER_LEN)?;
+    wtr.write_u64::<BigEndian>(fields_end - records_end)?;
+    wtr.write_u64::<BigEndian>(checkpoints_end - fields_end)?;
+    wtr.flush()?;
+    Ok(())
+}
+
+/// The bytes every multi-index file starts with, followed by the version of
+/// the format as a big endian `u16` and the position of the shard table as a
+/// big endian `u64`. Then come the record offsets of every shard, as written
+/// by `RandomAccessSimple`, and the shard table.
+///
+/// The shard table records whether the shards have headers, the pattern the
+/// shards were found with and the path, metadata and position of the record
+/// offsets of every shard. The comment character follows whether the shards
+/// have headers, like in the header of an index.
+const MULTI_MAGIC: &'static [u8] = b"XSVMIDX";
+const MULTI_VERSION: u16 = 1;
+
+/// Returns true if the file at `path` is a multi-index.
+pub fn is_multi(path: &Path) -> bool {
+    let mut magic = [0; 7];
+    fs::File::open(path)
+        .and_then(|mut f| f.read_exact(&mut magic))
+        .is_ok()
+        && &magic[..] == MULTI_MAGIC
+}
+
+/// One of the files covered by a multi-index.
+#[derive(Debug)]
+struct Shard {
+    path: PathBuf,
+    source: Source,
+    start: u64,
+    end: u64,
+    /// The number of records, including the header.
+    len: u64,
+}
+
+impl Shard {
+    fn count(&self, headers: bool) -> u64 {
+        if headers && self.len > 0 { self.len - 1 } else { self.len }
+    }
+}
+
+fn write_string<W: io::Write>(wtr: &mut W, s: &str) -> io::Result<()> {
+    wtr.write_u32::<BigEndian>(s.len() as u32)?;
+    wtr.write_all(s.as_bytes())
+}
+
+fn read_string<R: io::Read>(rdr: &mut R) -> io::Result<String> {
+    let mut buf = vec![0; rdr.read_u32::<BigEndian>()? as usize];
+    rdr.read_exact(&mut buf)?;
+    String::from_utf8(buf).map_err(|_| {
+        io::Error::new(io::ErrorKind::InvalidData, "invalid multi-index")
+    })
+}
+
+/// Makes a shard pattern absolute, so that a multi-index can be used from
+/// any directory.
+fn absolute_pattern(pattern: &str) -> CliResult<PathBuf> {
+    let pattern = Path::new(pattern);
+    let dir = match pattern.parent() {
+        Some(dir) if dir != Path::new("") => dir,
+        _ => Path::new("."),
+    };
+    match pattern.file_name() {
+        None => fail!(format!("Invalid shard pattern '{}'.",
+                              pattern.display())),
+        Some(name) => Ok(fs::canonicalize(dir)?.join(name)),
+    }
+}
+
+/// Returns the shards matching `pattern`, leaving out the multi-index itself.
+fn find_shards(pattern: &Path, idx_path: &Path) -> CliResult<Vec<PathBuf>> {
+    let idx_path = fs::canonicalize(idx_path).ok();
+    Ok(util::glob(pattern)?
+        .into_iter()
+        .filter(|p| idx_path.as_ref().map_or(true, |ip| p != ip))
+        .collect())
+}
+
+/// Creates a multi-index at `idx_path` of all files matching `pattern`,
+/// which are read with `conf`.
+///
+/// Unless `conf` has no headers, all files must have the same header.
+pub fn create_multi(
+    conf: &Config,
+    pattern: &str,
+    idx_path: &Path,
+) -> CliResult<()> {
+    let pattern = absolute_pattern(pattern)?;
+    let pattern_str = match pattern.to_str() {
+        Some(s) => s.to_owned(),
+        None => return fail!("Shard patterns must be valid UTF-8."),
+    };
+    let paths = find_shards(&pattern, idx_path)?;
+    if paths.is_empty() {
+        return fail!(format!("No files match '{}'.", pattern.display()));
+    }
+    if !conf.no_headers {
+        check_shard_headers(conf, &paths)?;
+    }
+    let mut wtr = io::BufWriter::new(fs::File::create(idx_path)?);
+    wtr.write_all(MULTI_MAGIC)?;
+    wtr.write_u16::<BigEndian>(MULTI_VERSION)?;
+    // The position of the shard table isn't known until the record offsets
+    // are written.
+    wtr.write_u64::<BigEndian>(0)?;
+
+    let mut shards = vec![];
+    for path in paths {
+        let mut rdr = conf.with_path(path.clone()).reader_file()?;
+        let source = Source::from_metadata(&rdr.get_ref().metadata()?);
+        let start = wtr.seek(SeekFrom::Current(0))?;
+        RandomAccessSimple::create(&mut rdr, &mut wtr)?;
+        let end = wtr.seek(SeekFrom::Current(0))?;
+        shards.push(Shard {
+            path: path, source: source, start: start, end: end,
+            len: (end - start) / 8 - 1,
+        });
+    }
+
+    let table_pos = wtr.seek(SeekFrom::Current(0))?;
+    wtr.write_u8(if conf.no_headers { 0 } else { 1 })?;
+    match conf.get_comment() {
+        None => wtr.write_all(&[0, 0])?,
+        Some(c) => wtr.write_all(&[1, c])?,
+    }
+    write_string(&mut wtr, &pattern_str)?;
+    wtr.write_u64::<BigEndian>(shards.len() as u64)?;
+    for shard in &shards {
+        match shard.path.to_str() {
+            Some(p) => write_string(&mut wtr, p)?,
+            None => return fail!("Shard paths must be valid UTF-8."),
+        }
+        shard.source.write(&mut wtr)?;
+        wtr.write_u64::<BigEndian>(shard.start)?;
+        wtr.write_u64::<BigEndian>(shard.end)?;
+        wtr.write_u64::<BigEndian>(shard.len)?;
+    }
+    wtr.seek(SeekFrom::Start((MULTI_MAGIC.len() + 2) as u64))?;
+    wtr.write_u64::<BigEndian>(table_pos)?;
+    wtr.flush()?;
+    Ok(())
+}
+
+/// Checks that all shards have the same header, ignoring empty shards.
+fn check_shard_headers(conf: &Config, paths: &[PathBuf]) -> CliResult<()> {
+    let mut first: Option<(&PathBuf, csv::ByteRecord)> = None;
+    for path in paths {
+        let mut rdr = conf.with_path(path.clone()).reader_file()?;
+        let header = rdr.byte_headers()?.clone();
+        if header.is_empty() {
+            continue;
+        }
+        match first {
+            None => first = Some((path, header)),
+            Some((first_path, ref first_header)) => {
+                if header != *first_header {
+                    return fail!(format!(
+                        "The header of {} is different from the header \
+                         of {}.", path.display(), first_path.display()));
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// A set of CSV files (shards) that are read as if they were concatenated,
+/// using a multi-index created by `xsv index --multi`.
+///
+/// The header of the first non-empty shard is used as the header of all of
+/// them.
+pub struct MultiIndexed {
+    file: fs::File,
+    conf: Config,
+    headers: csv::ByteRecord,
+    shards: Vec<Shard>,
+    /// The shard that is currently being read.
+    current: Option<(usize, Indexed<DataFile, IndexFile>)>,
+}
+
+impl MultiIndexed {
+    /// Opens the multi-index at `path`. The shards are read with `conf`.
+    ///
+    /// This fails if any shard was modified, removed or added since the
+    /// multi-index was created.
+    pub fn open(conf: &Config, path: &Path) -> CliResult<MultiIndexed> {
+        let mut file = fs::File::open(path)?;
+        file.seek(SeekFrom::Start(MULTI_MAGIC.len() as u64))?;
+        let version = file.read_u16::<BigEndian>()?;
+        if version != MULTI_VERSION {
+            return fail!(format!(
+                "The multi-index {} has unsupported format version {}. \
+                 Please re-create it.", path.display(), version));
+        }
+        let table_pos = file.read_u64::<BigEndian>()?;
+        file.seek(SeekFrom::Start(table_pos))?;
+        let mut rdr = io::BufReader::new(&file);
+        let has_headers = rdr.read_u8()? == 1;
+        let has_comment = rdr.read_u8()? == 1;
+        let c = rdr.read_u8()?;
+        let comment = if has_comment { Some(c) } else { None };
+        let pattern = PathBuf::from(read_string(&mut rdr)?);
+        let mut shards = vec![];
+        for _ in 0..rdr.read_u64::<BigEndian>()? {
+            shards.push(Shard {
+                path: PathBuf::from(read_string(&mut rdr)?),
+                source: Source::read(&mut rdr)?,
+                start: rdr.read_u64::<BigEndian>()?,
+                end: rdr.read_u64::<BigEndian>()?,
+                len: rdr.read_u64::<BigEndian>()?,
+            });
+        }
+        drop(rdr);
+
+        if has_headers == conf.no_headers {
+            return fail!(format!(
+                "The multi-index {} was created {} --no-headers, so it must \
+                 also be used {} it.", path.display(),
+                if has_headers { "without" } else { "with" },
+                if has_headers { "without" } else { "with" }));
+        }
+        let stale = |reason: String| -> CliResult<MultiIndexed> {
+            fail!(format!(
+                "The multi-index {} is stale: {}. Please re-run \
+                 'xsv index --multi'.", path.display(), reason))
+        };
+        let comment = comment_difference(comment, conf.get_comment());
+        if let Some(reason) = comment {
+            return stale(reason);
+        }
+        for shard in &shards {
+            let md = match fs::metadata(&shard.path) {
+                Ok(md) => md,
+                Err(_) => {
+                    return stale(format!(
+                        "the shard {} is missing", shard.path.display()));
+                }
+            };
+            let what = format!("the shard {}", shard.path.display());
+            let current = Source::from_metadata(&md);
+            if let Some(reason) = shard.source.difference(&current, &what) {
+                return stale(reason);
+            }
+        }
+        for p in find_shards(&pattern, path)? {
+            if !shards.iter().any(|s| s.path == p) {
+                return stale(format!(
+                    "the shard {} was added after it was indexed",
+                    p.display()));
+            }
+        }
+
+        let mut midx = MultiIndexed {
+            file: file,
+            conf: conf.clone(),
+            headers: csv::ByteRecord::new(),
+            shards: shards,
+            current: None,
+        };
+        let first = midx.shards.iter().position(|s| s.len > 0);
+        if let (true, Some(s)) = (has_headers, first) {
+            midx.open_shard(s)?;
+            if let Some((_, ref mut idx)) = midx.current {
+                midx.headers = idx.byte_headers()?.clone();
+            }
+        }
+        Ok(midx)
+    }
+
+    /// Returns the header of the first non-empty shard (which is empty if
+    /// the shards have no headers).
+    pub fn byte_headers(&self) -> &csv::ByteRecord {
+        &self.headers
+    }
+
+    /// Returns the number of records (not including headers) in all shards.
+    pub fn count(&self) -> u64 {
+        let headers = !self.conf.no_headers;
+        self.shards.iter().map(|s| s.count(headers)).sum()
+    }
+
+    /// Seeks to the starting position of record `i` among all shards.
+    pub fn seek(&mut self, mut i: u64) -> CliResult<()> {
+        if i >= self.count() {
+            let msg = format!(
+                "invalid record index {} (there are {} records)",
+                i, self.count());
+            return fail!(io::Error::new(io::ErrorKind::Other, msg));
+        }
+        let headers = !self.conf.no_headers;
+        for s in 0..self.shards.len() {
+            let n = self.shards[s].count(headers);
+            if i < n {
+                self.open_shard(s)?;
+                if let Some((_, ref mut idx)) = self.current {
+                    idx.seek(i)?;
+                }
+                return Ok(());
+            }
+            i -= n;
+        }
+        unreachable!()
+    }
+
+    /// Reads the next record, continuing with the next shard at the end of
+    /// the current one.
+    pub fn read_byte_record(
+        &mut self,
+        record: &mut csv::ByteRecord,
+    ) -> CliResult<bool> {
+        loop {
+            let s = match self.current {
+                None => return Ok(false),
+                Some((s, ref mut idx)) => {
+                    if idx.read_byte_record(record)? {
+                        return Ok(true);
+                    }
+                    s
+                }
+            };
+            let headers = !self.conf.no_headers;
+            let next = (s + 1..self.shards.len())
+                .find(|&t| self.shards[t].count(headers) > 0);
+            match next {
+                None => {
+                    self.current = None;
+                    return Ok(false);
+                }
+                Some(t) => {
+                    self.open_shard(t)?;
+                    if let Some((_, ref mut idx)) = self.current {
+                        idx.seek(0)?;
+                    }
+                }
+            }
+        }
+    }
+
+    fn open_shard(&mut self, s: usize) -> CliResult<()> {
+        if self.current.as_ref().map_or(false, |&(cur, _)| cur == s) {
+            return Ok(());
+        }
+        let shard = &self.shards[s];
+        let rdr = self.conf.with_path(shard.path.clone()).reader_file()?;
+        let section = IndexFile::section(
+            self.file.try_clone()?, shard.start, shard.end);
+        let mut idx = Indexed::open(rdr, section)?;
+        if idx.has_headers() {
+            // The header must be read before seeking, since the CSV reader
+            // would otherwise take the first record read as the header.
+            idx.byte_headers()?;
+        }
+        self.current = Some((s, idx));
+        Ok(())
+    }
+}
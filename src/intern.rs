@@ -0,0 +1,113 @@
+//! Counting of byte strings with interned keys, for commands like
+//! `frequency` and `stats` that count the values of columns with few
+//! distinct values in many records.
+//!
+//! A value is looked up by its borrowed bytes, so it is only copied the
+//! first time it is seen. The copy is kept in an `Interner` that can be
+//! shared by the counts of several columns, so a value that occurs in more
+//! than one column is stored once.
+
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+use stats::Commute;
+
+/// A set of byte strings, each of which is stored once and shared.
+#[derive(Clone, Default)]
+pub struct Interner {
+    values: HashSet<Arc<[u8]>>,
+}
+
+impl Interner {
+    pub fn new() -> Interner {
+        Default::default()
+    }
+
+    /// Returns the shared copy of `value`, copying it on the first call.
+    pub fn intern(&mut self, value: &[u8]) -> Arc<[u8]> {
+        if let Some(v) = self.values.get(value) {
+            return v.clone();
+        }
+        let v: Arc<[u8]> = Arc::from(value);
+        self.values.insert(v.clone());
+        v
+    }
+}
+
+/// The number of times each value occurs, like `stats::Frequencies`.
+#[derive(Clone, Default)]
+pub struct Counts {
+    counts: HashMap<Arc<[u8]>, u64>,
+}
+
+impl Counts {
+    pub fn new() -> Counts {
+        Default::default()
+    }
+
+    /// Counts `value`, which is only interned when it is new to these
+    /// counts.
+    pub fn add(&mut self, interner: &mut Interner, value: &[u8]) {
+        if let Some(count) = self.counts.get_mut(value) {
+            *count += 1;
+            return;
+        }
+        self.counts.insert(interner.intern(value), 1);
+    }
+
+    /// Returns the number of distinct values.
+    pub fn cardinality(&self) -> usize {
+        self.counts.len()
+    }
+
+    /// Returns the values and their counts in descending order of count.
+    pub fn most_frequent(&self) -> Vec<(&[u8], u64)> {
+        let mut counts = self.to_vec();
+        counts.sort_by_key(|&(_, c)| Reverse(c));
+        counts
+    }
+
+    /// Returns the values and their counts in ascending order of count.
+    pub fn least_frequent(&self) -> Vec<(&[u8], u64)> {
+        let mut counts = self.to_vec();
+        counts.sort_by_key(|&(_, c)| c);
+        counts
+    }
+
+    /// Returns the mode, which is the same as that of `stats::Unsorted`
+    /// for all of the values.
+    ///
+    /// That visits the values in sorted order. A value becomes the mode
+    /// when it occurs more often than the mode so far, while a value that
+    /// occurs as often leaves no mode, as if no value had been seen yet.
+    /// So a value that occurs once is never the mode.
+    pub fn mode(&self) -> Option<&[u8]> {
+        let mut counts = self.to_vec();
+        counts.sort_by_key(|&(v, _)| v);
+        let (mut mode, mut mode_count) = (None, 1);
+        for (value, count) in counts {
+            if count == mode_count {
+                mode = None;
+                mode_count = 1;
+            } else if count > mode_count {
+                mode = Some(value);
+                mode_count = count;
+            }
+        }
+        mode
+    }
+
+    fn to_vec(&self) -> Vec<(&[u8], u64)> {
+        self.counts.iter().map(|(v, &c)| (&**v, c)).collect()
+    }
+}
+
+impl Commute for Counts {
+    /// Adds the counts of `other`. Its values are moved rather than copied.
+    fn merge(&mut self, other: Counts) {
+        for (value, count) in other.counts {
+            *self.counts.entry(value).or_insert(0) += count;
+        }
+    }
+}
@@ -0,0 +1,376 @@
+use std::io::{self, BufRead};
+
+/// Writes `s` as a JSON string, including the surrounding quotes.
+///
+/// Bytes that aren't valid UTF-8 are replaced with the Unicode replacement
+/// character.
+pub fn write_str<W: io::Write>(wtr: &mut W, s: &[u8]) -> io::Result<()> {
+    let s = String::from_utf8_lossy(s);
+    wtr.write_all(b"\"")?;
+    let mut start = 0;
+    for (i, c) in s.char_indices() {
+        let escape = match c {
+            '"' => "\\\"".to_owned(),
+            '\\' => "\\\\".to_owned(),
+            '\n' => "\\n".to_owned(),
+            '\r' => "\\r".to_owned(),
+            '\t' => "\\t".to_owned(),
+            c if (c as u32) < 0x20 => format!("\\u{:04x}", c as u32),
+            _ => continue,
+        };
+        wtr.write_all(s[start..i].as_bytes())?;
+        wtr.write_all(escape.as_bytes())?;
+        start = i + c.len_utf8();
+    }
+    wtr.write_all(s[start..].as_bytes())?;
+    wtr.write_all(b"\"")
+}
+
+/// Returns true if `s` is a number in JSON syntax, e.g., `-1`, `0.5` or
+/// `2e10`, but not `01`, `.5` or `+1`.
+pub fn is_number(s: &[u8]) -> bool {
+    fn digits(s: &[u8], i: &mut usize) -> bool {
+        let start = *i;
+        while *i < s.len() && s[*i].is_ascii_digit() {
+            *i += 1;
+        }
+        *i > start
+    }
+
+    let mut i = 0;
+    if s.first() == Some(&b'-') {
+        i += 1;
+    }
+    if s.get(i) == Some(&b'0') {
+        i += 1;
+    } else if !digits(s, &mut i) {
+        return false;
+    }
+    if s.get(i) == Some(&b'.') {
+        i += 1;
+        if !digits(s, &mut i) {
+            return false;
+        }
+    }
+    if s.get(i) == Some(&b'e') || s.get(i) == Some(&b'E') {
+        i += 1;
+        if s.get(i) == Some(&b'+') || s.get(i) == Some(&b'-') {
+            i += 1;
+        }
+        if !digits(s, &mut i) {
+            return false;
+        }
+    }
+    i == s.len()
+}
+
+/// A parsed JSON value. Numbers are kept in their original textual form and
+/// the keys of objects are kept in their original order.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    Number(String),
+    String(String),
+    Array(Vec<Value>),
+    Object(Vec<(String, Value)>),
+}
+
+impl Value {
+    /// Writes this value as compact JSON.
+    pub fn write<W: io::Write>(&self, wtr: &mut W) -> io::Result<()> {
+        match *self {
+            Value::Null => wtr.write_all(b"null"),
+            Value::Bool(b) => write!(wtr, "{}", b),
+            Value::Number(ref n) => wtr.write_all(n.as_bytes()),
+            Value::String(ref s) => write_str(wtr, s.as_bytes()),
+            Value::Array(ref vals) => {
+                wtr.write_all(b"[")?;
+                for (i, v) in vals.iter().enumerate() {
+                    if i > 0 {
+                        wtr.write_all(b",")?;
+                    }
+                    v.write(wtr)?;
+                }
+                wtr.write_all(b"]")
+            }
+            Value::Object(ref members) => {
+                wtr.write_all(b"{")?;
+                for (i, &(ref k, ref v)) in members.iter().enumerate() {
+                    if i > 0 {
+                        wtr.write_all(b",")?;
+                    }
+                    write_str(wtr, k.as_bytes())?;
+                    wtr.write_all(b":")?;
+                    v.write(wtr)?;
+                }
+                wtr.write_all(b"}")
+            }
+        }
+    }
+
+    /// Writes this value as JSON with arrays and objects indented by two
+    /// spaces per level, starting at `level`.
+    pub fn write_pretty<W: io::Write>(
+        &self,
+        wtr: &mut W,
+        level: usize,
+    ) -> io::Result<()> {
+        let indent = |wtr: &mut W, level: usize| {
+            write!(wtr, "\n{:1$}", "", level * 2)
+        };
+        match *self {
+            Value::Array(ref vals) if !vals.is_empty() => {
+                wtr.write_all(b"[")?;
+                for (i, v) in vals.iter().enumerate() {
+                    if i > 0 {
+                        wtr.write_all(b",")?;
+                    }
+                    indent(wtr, level + 1)?;
+                    v.write_pretty(wtr, level + 1)?;
+                }
+                indent(wtr, level)?;
+                wtr.write_all(b"]")
+            }
+            Value::Object(ref members) if !members.is_empty() => {
+                wtr.write_all(b"{")?;
+                for (i, &(ref k, ref v)) in members.iter().enumerate() {
+                    if i > 0 {
+                        wtr.write_all(b",")?;
+                    }
+                    indent(wtr, level + 1)?;
+                    write_str(wtr, k.as_bytes())?;
+                    wtr.write_all(b": ")?;
+                    v.write_pretty(wtr, level + 1)?;
+                }
+                indent(wtr, level)?;
+                wtr.write_all(b"}")
+            }
+            _ => self.write(wtr),
+        }
+    }
+
+    /// Returns the value of the member `key` if this is an object.
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        match *self {
+            Value::Object(ref members) => {
+                members.iter().find(|m| m.0 == key).map(|m| &m.1)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// A parser for a stream of JSON values.
+///
+/// Errors in the JSON syntax are reported as `io::Error`s of the kind
+/// `InvalidData`, with the line on which they occurred.
+pub struct Parser<R> {
+    rdr: R,
+    line: u64,
+}
+
+impl<R: BufRead> Parser<R> {
+    pub fn new(rdr: R) -> Parser<R> {
+        Parser { rdr: rdr, line: 1 }
+    }
+
+    /// Skips whitespace and returns the next byte without consuming it, or
+    /// `None` at the end of the input.
+    pub fn peek(&mut self) -> io::Result<Option<u8>> {
+        loop {
+            let (b, ws) = match self.rdr.fill_buf()?.first() {
+                None => return Ok(None),
+                Some(&b) => (b, b == b' ' || b == b'\t'
+                                || b == b'\n' || b == b'\r'),
+            };
+            if !ws {
+                return Ok(Some(b));
+            }
+            self.bump(b);
+        }
+    }
+
+    /// Consumes the byte `b` that was returned by `peek`.
+    pub fn bump(&mut self, b: u8) {
+        if b == b'\n' {
+            self.line += 1;
+        }
+        self.rdr.consume(1);
+    }
+
+    /// Consumes the next non-whitespace byte if it is `b`.
+    pub fn eat(&mut self, b: u8) -> io::Result<bool> {
+        if self.peek()? == Some(b) {
+            self.bump(b);
+            Ok(true)
+        } else {
+            Ok(false)
+        }
+    }
+
+    /// Returns an error at the current line.
+    pub fn error(&self, msg: &str) -> io::Error {
+        io::Error::new(io::ErrorKind::InvalidData,
+                       format!("invalid JSON on line {}: {}", self.line, msg))
+    }
+
+    /// Parses the next value, or returns `None` at the end of the input.
+    pub fn next_value(&mut self) -> io::Result<Option<Value>> {
+        if self.peek()?.is_none() {
+            return Ok(None);
+        }
+        self.value().map(Some)
+    }
+
+    fn value(&mut self) -> io::Result<Value> {
+        let b = match self.peek()? {
+            None => return Err(self.error("unexpected end of input")),
+            Some(b) => b,
+        };
+        match b {
+            b'{' => {
+                self.bump(b);
+                let mut members = vec![];
+                if self.eat(b'}')? {
+                    return Ok(Value::Object(members));
+                }
+                loop {
+                    if self.peek()? != Some(b'"') {
+                        return Err(self.error("expected a string key"));
+                    }
+                    let key = self.string()?;
+                    if !self.eat(b':')? {
+                        return Err(self.error("expected ':'"));
+                    }
+                    members.push((key, self.value()?));
+                    if self.eat(b'}')? {
+                        return Ok(Value::Object(members));
+                    }
+                    if !self.eat(b',')? {
+                        return Err(self.error("expected ',' or '}'"));
+                    }
+                }
+            }
+            b'[' => {
+                self.bump(b);
+                let mut vals = vec![];
+                if self.eat(b']')? {
+                    return Ok(Value::Array(vals));
+                }
+                loop {
+                    vals.push(self.value()?);
+                    if self.eat(b']')? {
+                        return Ok(Value::Array(vals));
+                    }
+                    if !self.eat(b',')? {
+                        return Err(self.error("expected ',' or ']'"));
+                    }
+                }
+            }
+            b'"' => self.string().map(Value::String),
+            _ => {
+                let word = self.word()?;
+                match &*word {
+                    b"null" => Ok(Value::Null),
+                    b"true" => Ok(Value::Bool(true)),
+                    b"false" => Ok(Value::Bool(false)),
+                    n if is_number(n) => {
+                        Ok(Value::Number(String::from_utf8(word).unwrap()))
+                    }
+                    _ => Err(self.error(&format!(
+                        "unexpected '{}'", String::from_utf8_lossy(&word)))),
+                }
+            }
+        }
+    }
+
+    /// Reads a literal or a number.
+    fn word(&mut self) -> io::Result<Vec<u8>> {
+        let mut word = vec![];
+        loop {
+            let b = match self.rdr.fill_buf()?.first() {
+                Some(&b) if b.is_ascii_alphanumeric()
+                            || b == b'-' || b == b'+' || b == b'.' => b,
+                _ => break,
+            };
+            word.push(b);
+            self.bump(b);
+        }
+        if word.is_empty() {
+            return Err(self.error("expected a value"));
+        }
+        Ok(word)
+    }
+
+    fn next_byte(&mut self) -> io::Result<u8> {
+        let b = match self.rdr.fill_buf()?.first() {
+            None => return Err(self.error("unexpected end of input")),
+            Some(&b) => b,
+        };
+        self.bump(b);
+        Ok(b)
+    }
+
+    fn string(&mut self) -> io::Result<String> {
+        self.next_byte()?; // the opening quote
+        let mut buf = vec![];
+        loop {
+            match self.next_byte()? {
+                b'"' => break,
+                b'\\' => {
+                    let c = match self.next_byte()? {
+                        b'"' => '"',
+                        b'\\' => '\\',
+                        b'/' => '/',
+                        b'b' => '\u{8}',
+                        b'f' => '\u{c}',
+                        b'n' => '\n',
+                        b'r' => '\r',
+                        b't' => '\t',
+                        b'u' => self.unicode_escape()?,
+                        _ => return Err(self.error("invalid escape")),
+                    };
+                    let mut utf8 = [0; 4];
+                    buf.extend_from_slice(c.encode_utf8(&mut utf8).as_bytes());
+                }
+                b => buf.push(b),
+            }
+        }
+        Ok(match String::from_utf8(buf) {
+            Ok(s) => s,
+            Err(err) => String::from_utf8_lossy(err.as_bytes()).into_owned(),
+        })
+    }
+
+    /// Reads the digits of a \u escape (and of a second one if the first is
+    /// a high surrogate).
+    fn unicode_escape(&mut self) -> io::Result<char> {
+        let hi = self.hex4()?;
+        let code = if hi >= 0xd800 && hi < 0xdc00 {
+            if self.next_byte()? != b'\\' || self.next_byte()? != b'u' {
+                return Err(self.error("unpaired surrogate"));
+            }
+            let lo = self.hex4()?;
+            if lo < 0xdc00 || lo >= 0xe000 {
+                return Err(self.error("unpaired surrogate"));
+            }
+            0x10000 + ((hi - 0xd800) << 10) + (lo - 0xdc00)
+        } else {
+            hi
+        };
+        ::std::char::from_u32(code).ok_or_else(|| self.error("invalid escape"))
+    }
+
+    fn hex4(&mut self) -> io::Result<u32> {
+        let mut n = 0;
+        for _ in 0..4 {
+            let d = (self.next_byte()? as char).to_digit(16);
+            match d {
+                None => return Err(self.error("invalid escape")),
+                Some(d) => n = n * 16 + d,
+            }
+        }
+        Ok(n)
+    }
+}
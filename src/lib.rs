@@ -0,0 +1,236 @@
+//! xsv is a command line program for indexing, slicing, analyzing,
+//! splitting and joining CSV files. This library has the parts of some of
+//! its commands that can be used in-process, without running the program:
+//! they take typed options and any `Read` (and `Write`), and return
+//! structured results instead of printing them.
+//!
+//! ```
+//! use xsv::commands::{stats, validate};
+//!
+//! let data = "name,age\nalice,30\nbob,40\n";
+//!
+//! let columns = stats::compute(&stats::StatsOptions::default(),
+//!                              data.as_bytes()).unwrap();
+//! assert_eq!(columns[1].field, "age");
+//! assert_eq!(columns[1].field_type, "Integer");
+//! assert_eq!(columns[1].mean, Some(35.0));
+//!
+//! let report = validate::validate(&validate::ValidateOptions::default(),
+//!                                 data.as_bytes()).unwrap();
+//! assert!(report.is_valid());
+//! ```
+//!
+//! The columns of a command are given with [`SelectColumns`], in the syntax
+//! of `xsv select`:
+//!
+//! ```
+//! use xsv::SelectColumns;
+//! use xsv::commands::select;
+//!
+//! let opts = select::SelectOptions {
+//!     select: SelectColumns::parse("age,name").unwrap(),
+//!     ..Default::default()
+//! };
+//! let mut out = vec![];
+//! select::select(&opts, "name,age\nalice,30\n".as_bytes(), &mut out)
+//!     .unwrap();
+//! assert_eq!(out, b"age,name\n30,alice\n");
+//! ```
+
+extern crate byteorder;
+extern crate crossbeam_channel as channel;
+extern crate csv;
+extern crate csv_index;
+extern crate docopt;
+extern crate filetime;
+extern crate memchr;
+extern crate num_cpus;
+extern crate rand;
+extern crate regex;
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+extern crate stats;
+extern crate tabwriter;
+extern crate threadpool;
+extern crate unicode_width;
+
+use std::fmt;
+use std::io;
+
+pub use config::{Config, Delimiter};
+pub use select::SelectColumns;
+
+macro_rules! wout {
+    ($($arg:tt)*) => ({
+        use std::io::Write;
+        (writeln!(&mut ::std::io::stdout(), $($arg)*)).unwrap();
+    });
+}
+
+macro_rules! werr {
+    ($($arg:tt)*) => ({
+        use std::io::Write;
+        (writeln!(&mut ::std::io::stderr(), $($arg)*)).unwrap();
+    });
+}
+
+macro_rules! fail {
+    ($e:expr) => {
+        Err(::std::convert::From::from($e))
+    };
+}
+
+/// Fails with `CliError::Usage`, for invalid options or arguments that a
+/// command finds itself, like options that can't be used together.
+macro_rules! fail_usage {
+    ($e:expr) => {
+        Err(::CliError::Usage(::std::convert::From::from($e)))
+    };
+}
+
+/// Fails with `CliError::Data`, for input data that can't be handled, like
+/// a value of the wrong type.
+macro_rules! fail_data {
+    ($e:expr) => {
+        Err(::CliError::Data(::std::convert::From::from($e)))
+    };
+}
+
+#[doc(hidden)]
+pub mod cli;
+mod cmd;
+mod compress;
+mod config;
+mod date;
+mod defaults;
+mod digest;
+mod expr;
+mod gzip;
+#[cfg(feature = "http")]
+mod http;
+mod index;
+mod intern;
+mod json;
+mod parquet;
+mod select;
+mod snappy;
+mod util;
+mod xml;
+mod zip;
+#[cfg(feature = "zstd")]
+mod zstd;
+
+/// The commands that can be used in-process. Each also has the `run`
+/// function with which the command line program runs it.
+pub mod commands {
+    pub use cmd::select;
+    pub use cmd::stats;
+    pub use cmd::validate;
+}
+
+pub type CliResult<T> = Result<T, CliError>;
+
+/// The exit status for invalid usage.
+const EXIT_USAGE: i32 = 2;
+/// The exit status when reading or writing fails.
+const EXIT_IO: i32 = 3;
+/// The exit status for invalid data.
+const EXIT_DATA: i32 = 4;
+/// The exit status when stdout is closed early, as for a process that is
+/// killed by SIGPIPE.
+const EXIT_BROKEN_PIPE: i32 = 141;
+
+#[derive(Debug)]
+pub enum CliError {
+    /// Invalid command line arguments, or --help and --version.
+    Flag(docopt::Error),
+    /// Invalid usage that is found by a command.
+    Usage(String),
+    /// CSV data that can't be parsed. The message has the number of the
+    /// record and its position, where they are known.
+    Csv(csv::Error),
+    Io(io::Error),
+    /// Data that is invalid, other than CSV syntax.
+    Data(String),
+    /// stdout was closed before all output was written.
+    BrokenPipe,
+    Other(String),
+}
+
+impl CliError {
+    /// The exit status of xsv for the error.
+    pub fn exit_code(&self) -> i32 {
+        match *self {
+            CliError::Flag(ref e) if !e.fatal() => 0,
+            CliError::Flag(_) | CliError::Usage(_) => EXIT_USAGE,
+            CliError::Csv(_) | CliError::Data(_) => EXIT_DATA,
+            // Like invalid gzip data.
+            CliError::Io(ref e) if e.kind() == io::ErrorKind::InvalidData => {
+                EXIT_DATA
+            }
+            CliError::Io(_) => EXIT_IO,
+            CliError::BrokenPipe => EXIT_BROKEN_PIPE,
+            CliError::Other(_) => 1,
+        }
+    }
+}
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            CliError::Flag(ref e) => e.fmt(f),
+            CliError::Usage(ref s) => f.write_str(&**s),
+            CliError::Csv(ref e) => e.fmt(f),
+            CliError::Io(ref e) => e.fmt(f),
+            CliError::Data(ref s) => f.write_str(&**s),
+            CliError::BrokenPipe => f.write_str("Broken pipe"),
+            CliError::Other(ref s) => f.write_str(&**s),
+        }
+    }
+}
+
+impl From<docopt::Error> for CliError {
+    fn from(err: docopt::Error) -> CliError {
+        CliError::Flag(err)
+    }
+}
+
+impl From<csv::Error> for CliError {
+    fn from(err: csv::Error) -> CliError {
+        if !err.is_io_error() {
+            return CliError::Csv(err);
+        }
+        match err.into_kind() {
+            csv::ErrorKind::Io(v) => From::from(v),
+            _ => unreachable!(),
+        }
+    }
+}
+
+impl From<io::Error> for CliError {
+    fn from(err: io::Error) -> CliError {
+        if err.kind() == io::ErrorKind::BrokenPipe {
+            return CliError::BrokenPipe;
+        }
+        CliError::Io(err)
+    }
+}
+
+impl From<String> for CliError {
+    fn from(err: String) -> CliError {
+        CliError::Other(err)
+    }
+}
+
+impl<'a> From<&'a str> for CliError {
+    fn from(err: &'a str) -> CliError {
+        CliError::Other(err.to_owned())
+    }
+}
+
+impl From<regex::Error> for CliError {
+    fn from(err: regex::Error) -> CliError {
+        CliError::Other(format!("{:?}", err))
+    }
+}
@@ -0,0 +1,425 @@
+//! Writing of Parquet files.
+//!
+//! This supports flat schemas of optional columns with a few physical types,
+//! which are written with PLAIN encoding (and RLE encoded definition levels)
+//! in a single version 1 data page per column chunk. The file metadata is
+//! encoded with the Thrift compact protocol.
+
+use std::io::{self, Write};
+
+use snappy;
+#[cfg(feature = "zstd")]
+use zstd::ZstdEncoder;
+
+const MAGIC: &'static [u8] = b"PAR1";
+
+// Thrift compact protocol types.
+const T_I32: u8 = 5;
+const T_I64: u8 = 6;
+const T_BINARY: u8 = 8;
+const T_LIST: u8 = 9;
+const T_STRUCT: u8 = 12;
+
+// Parquet enum values.
+const ENCODING_PLAIN: i32 = 0;
+const ENCODING_RLE: i32 = 3;
+const PAGE_DATA: i32 = 0;
+const REPETITION_OPTIONAL: i32 = 1;
+const CONVERTED_UTF8: i32 = 0;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Type {
+    Boolean,
+    Int64,
+    Double,
+    /// A UTF-8 encoded string, stored as a byte array.
+    String,
+}
+
+impl Type {
+    fn physical(self) -> i32 {
+        match self {
+            Type::Boolean => 0,
+            Type::Int64 => 2,
+            Type::Double => 5,
+            Type::String => 6,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Compression {
+    Uncompressed,
+    Snappy,
+    Zstd,
+}
+
+impl Compression {
+    fn codec(self) -> i32 {
+        match self {
+            Compression::Uncompressed => 0,
+            Compression::Snappy => 1,
+            Compression::Zstd => 6,
+        }
+    }
+}
+
+/// Compresses a page as one zstd frame.
+#[cfg(feature = "zstd")]
+fn zstd_compress(data: &[u8]) -> io::Result<Vec<u8>> {
+    let mut out = vec![];
+    {
+        let mut enc = ZstdEncoder::new(&mut out, 3);
+        enc.write_all(data)?;
+        enc.flush()?;
+    }
+    Ok(out)
+}
+
+#[cfg(not(feature = "zstd"))]
+fn zstd_compress(_: &[u8]) -> io::Result<Vec<u8>> {
+    Err(io::Error::new(
+        io::ErrorKind::Other,
+        "This build of xsv can't write zstd compressed data.",
+    ))
+}
+
+/// The values of one column in a row group.
+pub enum Values {
+    Boolean(Vec<bool>),
+    Int64(Vec<i64>),
+    Double(Vec<f64>),
+    String(Vec<Vec<u8>>),
+}
+
+/// The data of one column in a row group: whether each row has a value
+/// (i.e., isn't null) and the values of the rows that have one.
+pub struct ColumnData {
+    pub defined: Vec<bool>,
+    pub values: Values,
+}
+
+impl ColumnData {
+    pub fn new(ty: Type) -> ColumnData {
+        ColumnData {
+            defined: vec![],
+            values: match ty {
+                Type::Boolean => Values::Boolean(vec![]),
+                Type::Int64 => Values::Int64(vec![]),
+                Type::Double => Values::Double(vec![]),
+                Type::String => Values::String(vec![]),
+            },
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.defined.clear();
+        match self.values {
+            Values::Boolean(ref mut v) => v.clear(),
+            Values::Int64(ref mut v) => v.clear(),
+            Values::Double(ref mut v) => v.clear(),
+            Values::String(ref mut v) => v.clear(),
+        }
+    }
+
+    /// Returns the PLAIN encoding of the values.
+    fn encode_values(&self, out: &mut Vec<u8>) {
+        match self.values {
+            Values::Boolean(ref v) => {
+                for chunk in v.chunks(8) {
+                    let mut byte = 0u8;
+                    for (i, &b) in chunk.iter().enumerate() {
+                        byte |= (b as u8) << i;
+                    }
+                    out.push(byte);
+                }
+            }
+            Values::Int64(ref v) => {
+                for n in v {
+                    out.extend_from_slice(&n.to_le_bytes());
+                }
+            }
+            Values::Double(ref v) => {
+                for n in v {
+                    out.extend_from_slice(&n.to_bits().to_le_bytes());
+                }
+            }
+            Values::String(ref v) => {
+                for s in v {
+                    out.extend_from_slice(&(s.len() as u32).to_le_bytes());
+                    out.extend_from_slice(s);
+                }
+            }
+        }
+    }
+}
+
+/// Encodes definition levels (with a maximum level of 1) with the
+/// RLE/bit-packing hybrid encoding, using only RLE runs.
+fn encode_levels(defined: &[bool], out: &mut Vec<u8>) {
+    let mut i = 0;
+    while i < defined.len() {
+        let run = defined[i..].iter().take_while(|&&d| d == defined[i]).count();
+        write_uvarint(out, (run as u64) << 1);
+        out.push(defined[i] as u8);
+        i += run;
+    }
+}
+
+fn write_uvarint(out: &mut Vec<u8>, mut n: u64) {
+    while n >= 0x80 {
+        out.push((n as u8) | 0x80);
+        n >>= 7;
+    }
+    out.push(n as u8);
+}
+
+/// An encoder for the Thrift compact protocol.
+struct Thrift {
+    buf: Vec<u8>,
+    /// The ID of the last field written in each of the enclosing structs.
+    last: Vec<i16>,
+}
+
+impl Thrift {
+    fn new() -> Thrift {
+        Thrift { buf: vec![], last: vec![0] }
+    }
+
+    fn varint(&mut self, n: i64) {
+        write_uvarint(&mut self.buf, ((n << 1) ^ (n >> 63)) as u64);
+    }
+
+    fn field(&mut self, id: i16, ty: u8) {
+        let last = self.last.last_mut().unwrap();
+        let delta = id - *last;
+        *last = id;
+        if delta > 0 && delta <= 15 {
+            self.buf.push((delta as u8) << 4 | ty);
+        } else {
+            self.buf.push(ty);
+            self.varint(id as i64);
+        }
+    }
+
+    fn i32(&mut self, id: i16, n: i32) {
+        self.field(id, T_I32);
+        self.varint(n as i64);
+    }
+
+    fn i64(&mut self, id: i16, n: i64) {
+        self.field(id, T_I64);
+        self.varint(n);
+    }
+
+    fn binary(&mut self, id: i16, b: &[u8]) {
+        self.field(id, T_BINARY);
+        self.binary_value(b);
+    }
+
+    fn binary_value(&mut self, b: &[u8]) {
+        write_uvarint(&mut self.buf, b.len() as u64);
+        self.buf.extend_from_slice(b);
+    }
+
+    fn list(&mut self, id: i16, elem_ty: u8, len: usize) {
+        self.field(id, T_LIST);
+        if len < 15 {
+            self.buf.push((len as u8) << 4 | elem_ty);
+        } else {
+            self.buf.push(0xF0 | elem_ty);
+            write_uvarint(&mut self.buf, len as u64);
+        }
+    }
+
+    /// Starts a struct that is an element of a list.
+    fn begin(&mut self) {
+        self.last.push(0);
+    }
+
+    /// Starts a struct that is the value of a field.
+    fn begin_field(&mut self, id: i16) {
+        self.field(id, T_STRUCT);
+        self.begin();
+    }
+
+    fn end(&mut self) {
+        self.buf.push(0);
+        self.last.pop();
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        self.end();
+        self.buf
+    }
+}
+
+struct ChunkMeta {
+    offset: u64,
+    num_values: i64,
+    uncompressed_len: i64,
+    compressed_len: i64,
+}
+
+struct RowGroupMeta {
+    num_rows: i64,
+    chunks: Vec<ChunkMeta>,
+}
+
+pub struct Writer<W: Write> {
+    wtr: W,
+    pos: u64,
+    columns: Vec<(String, Type)>,
+    compression: Compression,
+    created_by: String,
+    row_groups: Vec<RowGroupMeta>,
+}
+
+impl<W: Write> Writer<W> {
+    pub fn new(
+        mut wtr: W,
+        columns: Vec<(String, Type)>,
+        compression: Compression,
+        created_by: String,
+    ) -> io::Result<Writer<W>> {
+        wtr.write_all(MAGIC)?;
+        Ok(Writer {
+            wtr: wtr,
+            pos: MAGIC.len() as u64,
+            columns: columns,
+            compression: compression,
+            created_by: created_by,
+            row_groups: vec![],
+        })
+    }
+
+    /// Writes a row group with the data of every column.
+    pub fn write_row_group(&mut self, data: &[ColumnData]) -> io::Result<()> {
+        assert_eq!(data.len(), self.columns.len());
+        let num_rows = data.first().map_or(0, |d| d.defined.len());
+        let mut chunks = vec![];
+        for col in data {
+            let mut levels = vec![];
+            encode_levels(&col.defined, &mut levels);
+            let mut page = Vec::with_capacity(levels.len() + 4);
+            page.extend_from_slice(&(levels.len() as u32).to_le_bytes());
+            page.extend_from_slice(&levels);
+            col.encode_values(&mut page);
+            let body = match self.compression {
+                Compression::Uncompressed => page.clone(),
+                Compression::Snappy => snappy::compress(&page),
+                Compression::Zstd => zstd_compress(&page)?,
+            };
+
+            let mut header = Thrift::new();
+            header.i32(1, PAGE_DATA);
+            header.i32(2, page.len() as i32);
+            header.i32(3, body.len() as i32);
+            header.begin_field(5);
+            header.i32(1, col.defined.len() as i32);
+            header.i32(2, ENCODING_PLAIN);
+            header.i32(3, ENCODING_RLE);
+            header.i32(4, ENCODING_RLE);
+            header.end();
+            let header = header.finish();
+
+            chunks.push(ChunkMeta {
+                offset: self.pos,
+                num_values: col.defined.len() as i64,
+                uncompressed_len: (header.len() + page.len()) as i64,
+                compressed_len: (header.len() + body.len()) as i64,
+            });
+            self.wtr.write_all(&header)?;
+            self.wtr.write_all(&body)?;
+            self.pos += (header.len() + body.len()) as u64;
+        }
+        self.row_groups.push(RowGroupMeta {
+            num_rows: num_rows as i64,
+            chunks: chunks,
+        });
+        Ok(())
+    }
+
+    /// Writes the file metadata and returns the underlying writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        let mut meta = Thrift::new();
+        meta.i32(1, 1);
+        meta.list(2, T_STRUCT, self.columns.len() + 1);
+        meta.begin();
+        meta.binary(4, b"schema");
+        meta.i32(5, self.columns.len() as i32);
+        meta.end();
+        for &(ref name, ty) in &self.columns {
+            meta.begin();
+            meta.i32(1, ty.physical());
+            meta.i32(3, REPETITION_OPTIONAL);
+            meta.binary(4, name.as_bytes());
+            if ty == Type::String {
+                meta.i32(6, CONVERTED_UTF8);
+                // The STRING logical type, which is an empty struct.
+                meta.begin_field(10);
+                meta.begin_field(1);
+                meta.end();
+                meta.end();
+            }
+            meta.end();
+        }
+        let num_rows: i64 = self.row_groups.iter().map(|g| g.num_rows).sum();
+        meta.i64(3, num_rows);
+        meta.list(4, T_STRUCT, self.row_groups.len());
+        for group in &self.row_groups {
+            meta.begin();
+            meta.list(1, T_STRUCT, group.chunks.len());
+            for (chunk, &(ref name, ty)) in group.chunks.iter()
+                                                 .zip(&self.columns) {
+                meta.begin();
+                meta.i64(2, chunk.offset as i64);
+                meta.begin_field(3);
+                meta.i32(1, ty.physical());
+                meta.list(2, T_I32, 2);
+                meta.varint(ENCODING_PLAIN as i64);
+                meta.varint(ENCODING_RLE as i64);
+                meta.list(3, T_BINARY, 1);
+                meta.binary_value(name.as_bytes());
+                meta.i32(4, self.compression.codec());
+                meta.i64(5, chunk.num_values);
+                meta.i64(6, chunk.uncompressed_len);
+                meta.i64(7, chunk.compressed_len);
+                meta.i64(9, chunk.offset as i64);
+                meta.end();
+                meta.end();
+            }
+            let total: i64 = group.chunks.iter()
+                                  .map(|c| c.uncompressed_len).sum();
+            meta.i64(2, total);
+            meta.i64(3, group.num_rows);
+            meta.end();
+        }
+        meta.binary(6, self.created_by.as_bytes());
+        let meta = meta.finish();
+        self.wtr.write_all(&meta)?;
+        self.wtr.write_all(&(meta.len() as u32).to_le_bytes())?;
+        self.wtr.write_all(MAGIC)?;
+        self.wtr.flush()?;
+        Ok(self.wtr)
+    }
+}
+
+#[cfg(all(test, feature = "zstd"))]
+mod tests {
+    use std::io::Read;
+
+    use zstd::ZstdDecoder;
+
+    use super::zstd_compress;
+
+    #[test]
+    fn zstd_pages_round_trip() {
+        let page: Vec<u8> = (0..5000u32).map(|i| (i % 7 * i % 251) as u8)
+                                        .collect();
+        let frame = zstd_compress(&page).unwrap();
+        let mut got = vec![];
+        ZstdDecoder::new(&frame[..]).read_to_end(&mut got).unwrap();
+        assert_eq!(got, page);
+    }
+}
@@ -9,14 +9,21 @@ use std::str::FromStr;
 use csv;
 use serde::de::{Deserializer, Deserialize, Error};
 
-#[derive(Clone)]
+use {CliError, CliResult};
+
+/// A selection of columns, in the syntax of `xsv select`, like `1,3-5`,
+/// `name`, `name[1]` (the second column called `name`) or `!1` (all but the
+/// first column). Names are resolved to columns with the header row of the
+/// CSV data. The default selects all columns.
+#[derive(Clone, Default)]
 pub struct SelectColumns {
     selectors: Vec<Selector>,
     invert: bool,
 }
 
 impl SelectColumns {
-    fn parse(mut s: &str) -> Result<SelectColumns, String> {
+    /// Parses a selection. An empty selection selects all columns.
+    pub fn parse(mut s: &str) -> Result<SelectColumns, String> {
         let invert =
             if !s.is_empty() && s.as_bytes()[0] == b'!' {
                 s = &s[1..];
@@ -30,11 +37,13 @@ impl SelectColumns {
         })
     }
 
+    /// Resolves the selection with the header row (or first record)
+    /// `first_record`. A selector that matches no column is a usage error.
     pub fn selection(
         &self,
         first_record: &csv::ByteRecord,
         use_names: bool,
-    ) -> Result<Selection, String> {
+    ) -> CliResult<Selection> {
         if self.selectors.is_empty() {
             return Ok(Selection(if self.invert {
                 // Inverting everything means we get nothing.
@@ -47,7 +56,7 @@ impl SelectColumns {
         let mut map = vec![];
         for sel in &self.selectors {
             let idxs = sel.indices(first_record, use_names);
-            map.extend(idxs?.into_iter());
+            map.extend(idxs.map_err(CliError::Usage)?.into_iter());
         }
         if self.invert {
             let set: HashSet<_> = map.into_iter().collect();
@@ -0,0 +1,200 @@
+//! Compression in the (raw, unframed) Snappy format.
+//!
+//! This is a simple greedy compressor: it finds matches of at least four
+//! bytes with a hash table of recent positions, like the reference
+//! implementation, but without its block splitting and skipping heuristics.
+
+const MIN_MATCH: usize = 4;
+const MAX_OFFSET: usize = 0xFFFF;
+const HASH_BITS: u32 = 14;
+
+fn hash(bytes: &[u8]) -> usize {
+    let v = u32::from(bytes[0])
+        | u32::from(bytes[1]) << 8
+        | u32::from(bytes[2]) << 16
+        | u32::from(bytes[3]) << 24;
+    (v.wrapping_mul(0x1E35_A7BD) >> (32 - HASH_BITS)) as usize
+}
+
+fn write_varint(out: &mut Vec<u8>, mut n: u64) {
+    while n >= 0x80 {
+        out.push((n as u8) | 0x80);
+        n >>= 7;
+    }
+    out.push(n as u8);
+}
+
+fn write_literal(out: &mut Vec<u8>, lit: &[u8]) {
+    let mut rest = lit;
+    while !rest.is_empty() {
+        // Splitting literals into pieces of at most 64KB keeps their lengths
+        // within two bytes.
+        let len = ::std::cmp::min(rest.len(), 1 << 16);
+        let n = len - 1;
+        if n < 60 {
+            out.push((n as u8) << 2);
+        } else if n < 1 << 8 {
+            out.push(60 << 2);
+            out.push(n as u8);
+        } else {
+            out.push(61 << 2);
+            out.push(n as u8);
+            out.push((n >> 8) as u8);
+        }
+        out.extend_from_slice(&rest[..len]);
+        rest = &rest[len..];
+    }
+}
+
+fn write_copy(out: &mut Vec<u8>, offset: usize, mut len: usize) {
+    while len > 0 {
+        // A single copy is at most 64 bytes long.
+        let n = ::std::cmp::min(len, 64);
+        if n >= 4 && n < 12 && offset < 2048 {
+            out.push(((offset >> 8) << 5) as u8 | ((n - 4) << 2) as u8 | 1);
+            out.push(offset as u8);
+        } else {
+            out.push(((n - 1) << 2) as u8 | 2);
+            out.push(offset as u8);
+            out.push((offset >> 8) as u8);
+        }
+        len -= n;
+    }
+}
+
+/// Compresses `data`.
+pub fn compress(data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(data.len() / 2 + 16);
+    write_varint(&mut out, data.len() as u64);
+    if data.len() < MIN_MATCH {
+        write_literal(&mut out, data);
+        return out;
+    }
+    // Positions are stored plus one, so that zero means "none".
+    let mut table = vec![0usize; 1 << HASH_BITS];
+    let mut lit_start = 0;
+    let mut i = 0;
+    while i + MIN_MATCH <= data.len() {
+        let h = hash(&data[i..]);
+        let candidate = table[h];
+        table[h] = i + 1;
+        if candidate > 0 {
+            let c = candidate - 1;
+            if i - c <= MAX_OFFSET && data[c..c + MIN_MATCH] == data[i..i + MIN_MATCH] {
+                let mut len = MIN_MATCH;
+                while i + len < data.len() && data[c + len] == data[i + len] {
+                    len += 1;
+                }
+                write_literal(&mut out, &data[lit_start..i]);
+                write_copy(&mut out, i - c, len);
+                i += len;
+                lit_start = i;
+                continue;
+            }
+        }
+        i += 1;
+    }
+    write_literal(&mut out, &data[lit_start..]);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::compress;
+
+    /// Decompresses raw Snappy data, to check that `compress` round-trips.
+    fn decompress(data: &[u8]) -> Vec<u8> {
+        let (mut len, mut shift, mut pos) = (0usize, 0, 0);
+        loop {
+            let b = data[pos];
+            pos += 1;
+            len |= ((b & 0x7F) as usize) << shift;
+            if b < 0x80 {
+                break;
+            }
+            shift += 7;
+        }
+        let mut out = Vec::with_capacity(len);
+        while pos < data.len() {
+            let tag = data[pos];
+            pos += 1;
+            let (offset, n) = match tag & 3 {
+                0 => {
+                    let mut n = (tag >> 2) as usize;
+                    if n >= 60 {
+                        let k = n - 59;
+                        n = (0..k).map(|i| (data[pos + i] as usize) << (8 * i))
+                                  .sum();
+                        pos += k;
+                    }
+                    out.extend_from_slice(&data[pos..pos + n + 1]);
+                    pos += n + 1;
+                    continue;
+                }
+                1 => {
+                    let offset = (tag as usize >> 5) << 8 | data[pos] as usize;
+                    pos += 1;
+                    (offset, ((tag >> 2) & 7) as usize + 4)
+                }
+                2 => {
+                    let offset = data[pos] as usize
+                                 | (data[pos + 1] as usize) << 8;
+                    pos += 2;
+                    (offset, (tag >> 2) as usize + 1)
+                }
+                _ => panic!("compress doesn't write 4 byte offsets"),
+            };
+            assert!(offset > 0 && offset <= out.len());
+            for _ in 0..n {
+                let b = out[out.len() - offset];
+                out.push(b);
+            }
+        }
+        assert_eq!(out.len(), len);
+        out
+    }
+
+    #[test]
+    fn known_answers() {
+        assert_eq!(compress(b""), b"\x00");
+        assert_eq!(compress(b"abc"), b"\x03\x08abc");
+        // A literal, then a copy of 8 bytes at offset 4 with a 1 byte offset.
+        assert_eq!(compress(b"abcdabcdabcd"), b"\x0c\x0cabcd\x11\x04");
+        // 100 bytes: a literal of one byte and copies of 64 and 35 bytes
+        // with 2 byte offsets.
+        assert_eq!(compress(&[b'x'; 100]),
+                   b"\x64\x00x\xfe\x01\x00\x8a\x01\x00");
+        // A literal of 60 bytes or more has its length in an extra byte.
+        let lit: Vec<u8> = (0..70u8).collect();
+        let mut want = vec![70, 60 << 2, 69];
+        want.extend_from_slice(&lit);
+        assert_eq!(compress(&lit), want);
+    }
+
+    #[test]
+    fn round_trip() {
+        // Pseudorandom bytes with repeated pieces, whose offsets go beyond
+        // what fits in a 1 byte offset and the 64KB window.
+        let mut data = vec![];
+        let mut x = 0x1234_5678u32;
+        while data.len() < 300_000 {
+            x ^= x << 13;
+            x ^= x >> 17;
+            x ^= x << 5;
+            if x % 3 == 0 && data.len() > 70_000 {
+                let start = data.len() - (x as usize % 70_000) - 1;
+                let len = x as usize % 200;
+                for i in 0..len {
+                    let b = data[start + i];
+                    data.push(b);
+                }
+            } else {
+                data.push((x >> 24) as u8 % 16);
+            }
+        }
+        for len in &[0, 1, 3, 4, 5, 11, 12, 64, 65, 1000, data.len()] {
+            let d = &data[..*len];
+            assert_eq!(decompress(&compress(d)), d);
+        }
+    }
+}
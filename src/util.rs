@@ -1,6 +1,6 @@
 use std::borrow::Cow;
 use std::fs;
-use std::io;
+use std::io::{self, IsTerminal};
 use std::path::{Path, PathBuf};
 use std::str;
 use std::thread;
@@ -12,7 +12,8 @@ use num_cpus;
 use serde::de::{Deserializer, Deserialize, DeserializeOwned, Error};
 
 use CliResult;
-use config::{Config, Delimiter};
+use config::{self, Config, Delimiter};
+use defaults;
 
 pub fn num_cpus() -> usize {
     num_cpus::get()
@@ -31,17 +32,24 @@ pub fn version() -> String {
     }
 }
 
+/// Parses the arguments of a command. The defaults from the environment and
+/// the config file are added for the options that aren't given.
 pub fn get_args<T>(usage: &str, argv: &[&str]) -> CliResult<T>
         where T: DeserializeOwned {
+    let mut argv: Vec<String> = argv.iter().map(|&x| x.to_owned()).collect();
+    if argv.len() >= 2 {
+        let end = argv.len();
+        defaults::apply(usage, &mut argv, 2, end, false)?;
+    }
     Docopt::new(usage)
-           .and_then(|d| d.argv(argv.iter().map(|&x| x))
+           .and_then(|d| d.argv(argv.iter().map(|x| &**x))
                           .version(Some(version()))
                           .deserialize())
            .map_err(From::from)
 }
 
 pub fn many_configs(inps: &[String], delim: Option<Delimiter>,
-                    no_headers: bool) -> Result<Vec<Config>, String> {
+                    no_headers: bool) -> CliResult<Vec<Config>> {
     let mut inps = inps.to_vec();
     if inps.is_empty() {
         inps.push("-".to_owned()); // stdin
@@ -55,10 +63,10 @@ pub fn many_configs(inps: &[String], delim: Option<Delimiter>,
     Ok(confs)
 }
 
-pub fn errif_greater_one_stdin(inps: &[Config]) -> Result<(), String> {
+pub fn errif_greater_one_stdin(inps: &[Config]) -> CliResult<()> {
     let nstd = inps.iter().filter(|inp| inp.is_std()).count();
     if nstd > 1 {
-        return Err("At most one <stdin> input is allowed.".to_owned());
+        return fail_usage!("At most one <stdin> input is allowed.");
     }
     Ok(())
 }
@@ -87,6 +95,17 @@ pub fn last_modified(md: &fs::Metadata) -> u64 {
     FileTime::from_last_modification_time(md).seconds_relative_to_1970()
 }
 
+/// Returns the number of bytes of memory that are available, if the
+/// operating system reports it (currently only on Linux).
+pub fn available_memory() -> Option<u64> {
+    let meminfo = fs::read_to_string("/proc/meminfo").ok()?;
+    let line = meminfo.lines().find(|l| l.starts_with("MemAvailable:"))?;
+    let kb: u64 = line["MemAvailable:".len()..].trim()
+                                               .trim_end_matches("kB")
+                                               .trim().parse().ok()?;
+    Some(kb * 1024)
+}
+
 pub fn condense<'a>(val: Cow<'a, [u8]>, n: Option<usize>) -> Cow<'a, [u8]> {
     match n {
         None => val,
@@ -113,28 +132,81 @@ pub fn condense<'a>(val: Cow<'a, [u8]>, n: Option<usize>) -> Cow<'a, [u8]> {
     }
 }
 
+/// Returns true if `s` is an integer without leading zeros (so that `007`
+/// isn't one) that fits into 64 bits.
+pub fn is_integer(s: &[u8]) -> bool {
+    let digits = if s.first() == Some(&b'-') { &s[1..] } else { s };
+    !digits.is_empty()
+        && digits.iter().all(|b| b.is_ascii_digit())
+        && (digits.len() == 1 || digits[0] != b'0')
+        && str::from_utf8(s).unwrap().parse::<i64>().is_ok()
+}
+
 pub fn idx_path(csv_path: &Path) -> PathBuf {
     let mut p = csv_path.to_path_buf().into_os_string().into_string().unwrap();
     p.push_str(".idx");
     PathBuf::from(&p)
 }
 
+/// Returns the files matching `pattern`, in sorted order. Only the last
+/// component of the pattern may contain the wildcards `*` and `?`.
+pub fn glob(pattern: &Path) -> io::Result<Vec<PathBuf>> {
+    let other = |msg: &str| io::Error::new(io::ErrorKind::Other, msg);
+    let name = match pattern.file_name().and_then(|n| n.to_str()) {
+        Some(name) => name,
+        None => return Err(other("invalid file name pattern")),
+    };
+    let dir = match pattern.parent() {
+        Some(dir) if dir != Path::new("") => dir,
+        _ => Path::new("."),
+    };
+    if dir.to_string_lossy().contains(|c| c == '*' || c == '?') {
+        return Err(other("wildcards are only supported in file names"));
+    }
+    let mut paths = vec![];
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let matches = entry.file_name().to_str().map_or(false, |n| {
+            wildcard_match(name.as_bytes(), n.as_bytes())
+        });
+        if matches && entry.file_type()?.is_file() {
+            paths.push(pattern.with_file_name(entry.file_name()));
+        }
+    }
+    paths.sort();
+    Ok(paths)
+}
+
+fn wildcard_match(pattern: &[u8], s: &[u8]) -> bool {
+    match pattern.split_first() {
+        None => s.is_empty(),
+        Some((&b'*', rest)) => {
+            (0..s.len() + 1).any(|i| wildcard_match(rest, &s[i..]))
+        }
+        Some((&b'?', rest)) => !s.is_empty() && wildcard_match(rest, &s[1..]),
+        Some((&c, rest)) => {
+            s.first() == Some(&c) && wildcard_match(rest, &s[1..])
+        }
+    }
+}
+
 pub type Idx = Option<usize>;
 
 pub fn range(start: Idx, end: Idx, len: Idx, index: Idx)
-            -> Result<(usize, usize), String> {
+            -> CliResult<(usize, usize)> {
     match (start, end, len, index) {
         (None, None, None, Some(i)) => Ok((i, i+1)),
         (_, _, _, Some(_)) =>
-            Err("--index cannot be used with --start, --end or --len".to_owned()),
+            fail_usage!("--index cannot be used with --start, --end or --len"),
         (_, Some(_), Some(_), None) =>
-            Err("--end and --len cannot be used at the same time.".to_owned()),
+            fail_usage!("--end and --len cannot be used at the same time."),
         (_, None, None, None) => Ok((start.unwrap_or(0), ::std::usize::MAX)),
         (_, Some(e), None, None) => {
             let s = start.unwrap_or(0);
             if s > e {
-                Err(format!("The end of the range ({}) must be greater than or\n\
-                             equal to the start of the range ({}).", e, s))
+                fail_usage!(format!(
+                    "The end of the range ({}) must be greater than or\n\
+                     equal to the start of the range ({}).", e, s))
             } else {
                 Ok((s, e))
             }
@@ -220,3 +292,174 @@ impl<'de> Deserialize<'de> for FilenameTemplate {
         }
     }
 }
+
+/// Returns a reporter of the progress of reading the CSV data of `conf`,
+/// which does nothing unless the global `--progress` option is given (or
+/// `XSV_PROGRESS=1` is set and stderr is a terminal).
+///
+/// A command reports each record it reads with `Progress::record`, or
+/// passes its records through `Progress::records`.
+pub fn progress(conf: &Config) -> Progress {
+    Progress::new(config::show_progress(), conf.input_size())
+}
+
+/// How many records are read between checks of the clock.
+const PROGRESS_CHECK: u64 = 1024;
+
+/// Reports the progress of reading CSV data on stderr. It shows how much of
+/// the input was read, with the throughput and the time that remains, or
+/// only the number of records and bytes if the size isn't known.
+pub struct Progress {
+    enabled: bool,
+    total: Option<u64>,
+    records: u64,
+    bytes: u64,
+    /// The number of records after which the clock is checked.
+    next_check: u64,
+    start: time::Instant,
+    last_draw: Option<time::Instant>,
+    tty: bool,
+}
+
+impl Progress {
+    fn new(enabled: bool, total: Option<u64>) -> Progress {
+        Progress {
+            enabled: enabled,
+            total: total,
+            records: 0,
+            bytes: 0,
+            next_check: 1,
+            start: time::Instant::now(),
+            last_draw: None,
+            tty: io::stderr().is_terminal(),
+        }
+    }
+
+    /// Counts a record that was read.
+    #[inline]
+    pub fn record(&mut self, record: &csv::ByteRecord) {
+        if self.enabled {
+            self.records += 1;
+            if let Some(pos) = record.position() {
+                // About the end of the record, with the delimiters after
+                // its fields but without quotes.
+                self.bytes = pos.byte()
+                             + (record.as_slice().len() + record.len()) as u64;
+            }
+            if self.records >= self.next_check {
+                self.check();
+            }
+        }
+    }
+
+    /// Counts the records of an iterator as they are read.
+    pub fn records<'a, I>(
+        &'a mut self,
+        it: I,
+    ) -> impl Iterator<Item = csv::Result<csv::ByteRecord>> + 'a
+            where I: Iterator<Item = csv::Result<csv::ByteRecord>> + 'a {
+        it.inspect(move |r| {
+            if let Ok(ref r) = *r {
+                self.record(r);
+            }
+        })
+    }
+
+    /// Prints the final state, once all records were read. This is also
+    /// done when the reporter is dropped.
+    pub fn finish(&mut self) {
+        if let Some(total) = self.total {
+            self.bytes = total;
+        }
+        self.end();
+    }
+
+    fn end(&mut self) {
+        if self.enabled {
+            self.draw();
+            if self.tty {
+                eprintln!();
+            }
+            self.enabled = false;
+        }
+    }
+
+    fn check(&mut self) {
+        self.next_check = self.records + PROGRESS_CHECK;
+        let interval = if self.tty {
+            time::Duration::from_millis(200)
+        } else {
+            time::Duration::from_secs(5)
+        };
+        let now = time::Instant::now();
+        let due = match self.last_draw {
+            None => now - self.start >= interval,
+            Some(last) => now - last >= interval,
+        };
+        if due {
+            self.last_draw = Some(now);
+            self.draw();
+        }
+    }
+
+    fn draw(&self) {
+        let secs = self.start.elapsed().as_secs_f64();
+        let rate = if secs > 0.0 { self.bytes as f64 / secs } else { 0.0 };
+        let line = match self.total {
+            Some(total) if total > 0 => {
+                let done = self.bytes.min(total) as f64 / total as f64;
+                let width = 30;
+                let filled = (done * width as f64) as usize;
+                let eta = if rate > 0.0 {
+                    format_duration((total - self.bytes.min(total)) as f64
+                                    / rate)
+                } else {
+                    "?".to_owned()
+                };
+                format!("[{}{}] {:3.0}%  {} / {}  {}/s  ETA {}",
+                        "=".repeat(filled), " ".repeat(width - filled),
+                        done * 100.0, format_bytes(self.bytes as f64),
+                        format_bytes(total as f64), format_bytes(rate), eta)
+            }
+            _ => format!("{} records  {}  {}/s  {}",
+                         self.records, format_bytes(self.bytes as f64),
+                         format_bytes(rate), format_duration(secs)),
+        };
+        if self.tty {
+            // Clear the rest of the line, in case the last one was longer.
+            eprint!("\r{}\x1b[K", line);
+        } else {
+            eprintln!("{}", line);
+        }
+    }
+}
+
+impl Drop for Progress {
+    fn drop(&mut self) {
+        self.end();
+    }
+}
+
+fn format_bytes(n: f64) -> String {
+    let units = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut n = n;
+    let mut unit = 0;
+    while n >= 1024.0 && unit + 1 < units.len() {
+        n /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} {}", n as u64, units[unit])
+    } else {
+        format!("{:.1} {}", n, units[unit])
+    }
+}
+
+fn format_duration(secs: f64) -> String {
+    let secs = secs.round() as u64;
+    if secs >= 3600 {
+        format!("{}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+    } else {
+        format!("{}:{:02}", secs / 60, secs % 60)
+    }
+}
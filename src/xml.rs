@@ -0,0 +1,174 @@
+//! A minimal pull parser for XML, as far as needed for spreadsheet files.
+//!
+//! Namespace prefixes are dropped from the names of elements and
+//! attributes, so `<table:table-cell>` is reported as `table-cell`. Document
+//! type declarations are skipped without being interpreted, so only the
+//! predefined and numeric entities are replaced.
+
+use std::io;
+
+pub enum Event<'a> {
+    /// A start tag (or an empty element tag, if `empty` is true).
+    Start {
+        name: &'a str,
+        attrs: Vec<(&'a str, String)>,
+        empty: bool,
+    },
+    End(&'a str),
+    Text(String),
+}
+
+pub struct Reader<'a> {
+    s: &'a str,
+    pos: usize,
+}
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData, format!("invalid XML: {}", msg))
+}
+
+fn local(name: &str) -> &str {
+    match name.rfind(':') {
+        None => name,
+        Some(i) => &name[i + 1..],
+    }
+}
+
+/// Returns the value of the attribute called `name`.
+pub fn attr<'a>(attrs: &'a [(&str, String)], name: &str) -> Option<&'a str> {
+    attrs.iter().find(|a| a.0 == name).map(|a| &*a.1)
+}
+
+/// Replaces entity and character references in `s`.
+fn unescape(s: &str) -> io::Result<String> {
+    let mut out = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(i) = rest.find('&') {
+        out.push_str(&rest[..i]);
+        let end = match rest[i..].find(';') {
+            None => return Err(invalid("unterminated reference")),
+            Some(end) => i + end,
+        };
+        let c = match &rest[i + 1..end] {
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "amp" => Some('&'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            r if r.starts_with("#x") => {
+                u32::from_str_radix(&r[2..], 16).ok()
+                    .and_then(::std::char::from_u32)
+            }
+            r if r.starts_with('#') => {
+                r[1..].parse().ok().and_then(::std::char::from_u32)
+            }
+            _ => None,
+        };
+        match c {
+            None => {
+                return Err(invalid(&format!(
+                    "unknown reference '{}'", &rest[i..end + 1])));
+            }
+            Some(c) => out.push(c),
+        }
+        rest = &rest[end + 1..];
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+impl<'a> Reader<'a> {
+    pub fn new(s: &'a str) -> Reader<'a> {
+        Reader { s: s, pos: 0 }
+    }
+
+    /// Skips to just after the next occurrence of `pat`.
+    fn skip_past(&mut self, pat: &str) -> io::Result<&'a str> {
+        match self.s[self.pos..].find(pat) {
+            None => Err(invalid(&format!("expected '{}'", pat))),
+            Some(i) => {
+                let skipped = &self.s[self.pos..self.pos + i];
+                self.pos += i + pat.len();
+                Ok(skipped)
+            }
+        }
+    }
+
+    /// Returns the next event, or `None` at the end of the document.
+    pub fn next(&mut self) -> io::Result<Option<Event<'a>>> {
+        loop {
+            let rest = &self.s[self.pos..];
+            if rest.is_empty() {
+                return Ok(None);
+            }
+            if !rest.starts_with('<') {
+                let text = match rest.find('<') {
+                    None => rest,
+                    Some(i) => &rest[..i],
+                };
+                self.pos += text.len();
+                return Ok(Some(Event::Text(unescape(text)?)));
+            }
+            if rest.starts_with("<?") {
+                self.skip_past("?>")?;
+            } else if rest.starts_with("<!--") {
+                self.skip_past("-->")?;
+            } else if rest.starts_with("<![CDATA[") {
+                self.pos += "<![CDATA[".len();
+                let text = self.skip_past("]]>")?;
+                return Ok(Some(Event::Text(text.to_owned())));
+            } else if rest.starts_with("<!") {
+                // A document type declaration, possibly with an internal
+                // subset in brackets.
+                let end = match rest.find('[') {
+                    Some(i) if i < rest.find('>').unwrap_or(0) => "]>",
+                    _ => ">",
+                };
+                self.skip_past(end)?;
+            } else if rest.starts_with("</") {
+                self.pos += 2;
+                let name = self.skip_past(">")?.trim();
+                return Ok(Some(Event::End(local(name))));
+            } else {
+                return self.start_tag().map(Some);
+            }
+        }
+    }
+
+    fn start_tag(&mut self) -> io::Result<Event<'a>> {
+        self.pos += 1;
+        let s = self.s;
+        let is_name_end = |c: char| c.is_whitespace() || c == '/' || c == '>';
+        let name_len = s[self.pos..].find(is_name_end)
+            .ok_or_else(|| invalid("unterminated tag"))?;
+        let name = local(&s[self.pos..self.pos + name_len]);
+        self.pos += name_len;
+        let mut attrs = vec![];
+        loop {
+            let rest = &s[self.pos..];
+            let trimmed = rest.trim_start();
+            self.pos += rest.len() - trimmed.len();
+            if trimmed.starts_with("/>") {
+                self.pos += 2;
+                return Ok(Event::Start { name: name, attrs: attrs, empty: true });
+            } else if trimmed.starts_with('>') {
+                self.pos += 1;
+                return Ok(Event::Start { name: name, attrs: attrs, empty: false });
+            }
+            let attr_name = self.skip_past("=")?.trim();
+            let rest = s[self.pos..].trim_start();
+            self.pos = s.len() - rest.len();
+            let quote = match rest.chars().next() {
+                Some(q) if q == '"' || q == '\'' => q,
+                _ => return Err(invalid("unquoted attribute value")),
+            };
+            self.pos += 1;
+            let value = match quote {
+                '"' => self.skip_past("\"")?,
+                _ => self.skip_past("'")?,
+            };
+            attrs.push((local(attr_name), unescape(value)?));
+        }
+    }
+}
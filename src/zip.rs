@@ -0,0 +1,132 @@
+//! Reading of zip archives, as far as needed for spreadsheet files.
+//!
+//! Only stored and DEFLATE compressed entries are supported, and neither
+//! zip64 archives nor encryption are.
+
+use std::fs;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::Path;
+
+use byteorder::{ByteOrder, LittleEndian};
+
+use gzip::GzDecoder;
+
+const END_SIGNATURE: u32 = 0x0605_4b50;
+const END_LEN: usize = 22;
+const ENTRY_SIGNATURE: u32 = 0x0201_4b50;
+const LOCAL_SIGNATURE: u32 = 0x0403_4b50;
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData, format!("invalid zip archive: {}", msg))
+}
+
+struct Entry {
+    name: String,
+    method: u16,
+    compressed_len: u64,
+    len: u64,
+    /// The position of the entry's local header.
+    offset: u64,
+}
+
+pub struct ZipArchive {
+    file: fs::File,
+    entries: Vec<Entry>,
+}
+
+impl ZipArchive {
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<ZipArchive> {
+        let mut file = fs::File::open(path)?;
+        let file_len = file.seek(SeekFrom::End(0))?;
+        // The end of central directory record is followed by a comment of
+        // at most 65535 bytes.
+        let tail_len = ::std::cmp::min(file_len, (END_LEN + 0xFFFF) as u64);
+        file.seek(SeekFrom::Start(file_len - tail_len))?;
+        let mut tail = vec![0; tail_len as usize];
+        file.read_exact(&mut tail)?;
+        let end = match (0..tail.len().saturating_sub(END_LEN - 1))
+            .rev()
+            .find(|&i| LittleEndian::read_u32(&tail[i..]) == END_SIGNATURE)
+        {
+            None => return Err(invalid("no end of central directory")),
+            Some(i) => &tail[i..],
+        };
+        let count = LittleEndian::read_u16(&end[10..]);
+        let dir_len = LittleEndian::read_u32(&end[12..]);
+        let dir_offset = LittleEndian::read_u32(&end[16..]);
+        if count == 0xFFFF || dir_offset == 0xFFFF_FFFF {
+            return Err(invalid("zip64 archives are not supported"));
+        }
+
+        file.seek(SeekFrom::Start(dir_offset as u64))?;
+        let mut dir = vec![0; dir_len as usize];
+        file.read_exact(&mut dir)?;
+        let mut entries = vec![];
+        let mut pos = 0;
+        for _ in 0..count {
+            if dir.len() < pos + 46
+                || LittleEndian::read_u32(&dir[pos..]) != ENTRY_SIGNATURE {
+                return Err(invalid("bad central directory entry"));
+            }
+            let h = &dir[pos..];
+            let name_len = LittleEndian::read_u16(&h[28..]) as usize;
+            let extra_len = LittleEndian::read_u16(&h[30..]) as usize;
+            let comment_len = LittleEndian::read_u16(&h[32..]) as usize;
+            if dir.len() < pos + 46 + name_len {
+                return Err(invalid("bad central directory entry"));
+            }
+            entries.push(Entry {
+                name: String::from_utf8_lossy(&h[46..46 + name_len])
+                    .into_owned(),
+                method: LittleEndian::read_u16(&h[10..]),
+                compressed_len: LittleEndian::read_u32(&h[20..]) as u64,
+                len: LittleEndian::read_u32(&h[24..]) as u64,
+                offset: LittleEndian::read_u32(&h[42..]) as u64,
+            });
+            pos += 46 + name_len + extra_len + comment_len;
+        }
+        Ok(ZipArchive { file: file, entries: entries })
+    }
+
+    /// Returns true if the archive has an entry called `name`.
+    pub fn contains(&self, name: &str) -> bool {
+        self.entries.iter().any(|e| e.name == name)
+    }
+
+    /// Reads the decompressed data of the entry called `name`.
+    pub fn read(&mut self, name: &str) -> io::Result<Vec<u8>> {
+        let entry = match self.entries.iter().find(|e| e.name == name) {
+            None => {
+                return Err(io::Error::new(
+                    io::ErrorKind::NotFound,
+                    format!("zip archive has no entry '{}'", name)));
+            }
+            Some(entry) => entry,
+        };
+        let mut header = [0; 30];
+        self.file.seek(SeekFrom::Start(entry.offset))?;
+        self.file.read_exact(&mut header)?;
+        if LittleEndian::read_u32(&header) != LOCAL_SIGNATURE {
+            return Err(invalid("bad local header"));
+        }
+        let skip = LittleEndian::read_u16(&header[26..]) as i64
+                   + LittleEndian::read_u16(&header[28..]) as i64;
+        self.file.seek(SeekFrom::Current(skip))?;
+
+        let data = (&self.file).take(entry.compressed_len);
+        let mut buf = Vec::with_capacity(entry.len as usize);
+        match entry.method {
+            0 => io::BufReader::new(data).read_to_end(&mut buf)?,
+            8 => GzDecoder::raw(data).read_to_end(&mut buf)?,
+            m => {
+                return Err(invalid(&format!(
+                    "unsupported compression method {}", m)));
+            }
+        };
+        if buf.len() as u64 != entry.len {
+            return Err(invalid(&format!("entry '{}' is truncated", name)));
+        }
+        Ok(buf)
+    }
+}
@@ -0,0 +1,1419 @@
+//! Decoding and encoding of zstd compressed data.
+//!
+//! This is a plain implementation of the Zstandard format (RFC 8878). It
+//! decodes a stream of frames from start to end, which is all that reading
+//! compressed CSV data needs. Frames that need a dictionary are rejected.
+//!
+//! The encoder uses only a subset of the format: literals are Huffman coded
+//! if their byte values fit the simple form of the Huffman tree description,
+//! and the sequences are coded with the predefined distributions.
+
+use std::cmp;
+use std::io::{self, Read, Write};
+
+use byteorder::{ByteOrder, LittleEndian};
+
+use compress::{self, BitWriter, Matcher, Sequence};
+
+/// The first bytes of a zstd frame.
+const MAGIC: [u8; 4] = [0x28, 0xB5, 0x2F, 0xFD];
+
+/// The largest block, in bytes of decompressed data.
+const BLOCK_MAX: usize = 1 << 17;
+
+/// The largest window that is accepted, to bound memory usage.
+const WINDOW_MAX: u64 = 1 << 31;
+
+const LL_BASE: [u32; 36] = [
+    0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 18, 20, 22,
+    24, 28, 32, 40, 48, 64, 128, 256, 512, 1024, 2048, 4096, 8192, 16384,
+    32768, 65536,
+];
+const LL_EXTRA: [u8; 36] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 3, 3,
+    4, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16,
+];
+const ML_BASE: [u32; 53] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22,
+    23, 24, 25, 26, 27, 28, 29, 30, 31, 32, 33, 34, 35, 37, 39, 41, 43, 47,
+    51, 59, 67, 83, 99, 131, 259, 515, 1027, 2051, 4099, 8195, 16387, 32771,
+    65539,
+];
+const ML_EXTRA: [u8; 53] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 3, 3, 4, 4, 5, 7, 8, 9, 10, 11,
+    12, 13, 14, 15, 16,
+];
+
+/// The predefined distributions of the literal length, match length and
+/// offset codes, with their accuracy logs.
+const LL_DEFAULT: (u32, [i16; 36]) = (6, [
+    4, 3, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 1, 1, 1, 2, 2, 2, 2, 2, 2, 2, 2,
+    2, 3, 2, 1, 1, 1, 1, 1, -1, -1, -1, -1,
+]);
+const ML_DEFAULT: (u32, [i16; 53]) = (6, [
+    1, 4, 3, 2, 2, 2, 2, 2, 2, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1,
+    1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, -1, -1,
+    -1, -1, -1, -1, -1,
+]);
+const OF_DEFAULT: (u32, [i16; 29]) = (5, [
+    1, 1, 1, 1, 1, 1, 2, 2, 2, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1,
+    -1, -1, -1, -1, -1,
+]);
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData, format!("invalid zstd data: {}", msg))
+}
+
+/// Returns the position of the highest set bit of `n`, which must not be 0.
+fn highbit(n: u32) -> u32 {
+    31 - n.leading_zeros()
+}
+
+/// Reads bits from a slice, least significant bit first.
+struct ForwardBits<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> ForwardBits<'a> {
+    fn peek(&self, n: u32) -> u32 {
+        let mut v = 0u64;
+        let start = self.pos / 8;
+        for (i, &b) in self.data.iter().skip(start).take(5).enumerate() {
+            v |= (b as u64) << (8 * i);
+        }
+        ((v >> (self.pos % 8)) & ((1 << n) - 1)) as u32
+    }
+
+    fn consume(&mut self, n: u32) -> io::Result<()> {
+        self.pos += n as usize;
+        if self.pos > self.data.len() * 8 {
+            return Err(invalid("truncated table description"));
+        }
+        Ok(())
+    }
+
+    /// Returns the number of bytes that were (partially) read.
+    fn bytes(&self) -> usize {
+        (self.pos + 7) / 8
+    }
+}
+
+/// Reads bits from the end of a slice to its start, as used by the entropy
+/// coded streams. The stream's last byte ends with a 1 bit that marks where
+/// the data starts.
+struct BackwardBits<'a> {
+    data: &'a [u8],
+    /// The number of bits that are left to be read, which becomes negative
+    /// when more bits were read than there are (and the missing bits are
+    /// read as zeros).
+    left: isize,
+}
+
+impl<'a> BackwardBits<'a> {
+    fn new(data: &'a [u8]) -> io::Result<BackwardBits<'a>> {
+        match data.last() {
+            Some(&last) if last != 0 => Ok(BackwardBits {
+                data: data,
+                left: (data.len() * 8) as isize - 8 + highbit(last as u32)
+                      as isize,
+            }),
+            _ => Err(invalid("bad bitstream end mark")),
+        }
+    }
+
+    #[inline]
+    fn extract(&self, pos: usize, n: u32) -> u64 {
+        let start = pos / 8;
+        let v = if start + 8 <= self.data.len() {
+            LittleEndian::read_u64(&self.data[start..])
+        } else {
+            let mut v = 0u64;
+            for (i, &b) in self.data[start..].iter().enumerate() {
+                v |= (b as u64) << (8 * i);
+            }
+            v
+        };
+        (v >> (pos % 8)) & ((1 << n) - 1)
+    }
+
+    /// Returns the next `n` bits (at most 32) without consuming them.
+    #[inline]
+    fn peek(&self, n: u32) -> u32 {
+        if n == 0 {
+            return 0;
+        }
+        let pos = self.left - n as isize;
+        if pos >= 0 {
+            self.extract(pos as usize, n) as u32
+        } else if self.left > 0 {
+            (self.extract(0, self.left as u32) << -pos) as u32
+        } else {
+            0
+        }
+    }
+
+    #[inline]
+    fn bits(&mut self, n: u32) -> u32 {
+        let v = self.peek(n);
+        self.left -= n as isize;
+        v
+    }
+
+    /// Returns true if more bits were read than the stream has.
+    fn overflowed(&self) -> bool {
+        self.left < 0
+    }
+
+    fn finished(&self) -> bool {
+        self.left == 0
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+struct FseEntry {
+    symbol: u8,
+    nbits: u8,
+    base: u16,
+}
+
+/// A decoding table of finite state entropy (tANS) codes.
+#[derive(Clone, Debug)]
+struct Fse {
+    log: u32,
+    table: Vec<FseEntry>,
+}
+
+impl Fse {
+    /// Reads a table description, as stored in the data. Returns the table
+    /// and the number of bytes that its description took.
+    fn read(data: &[u8], max_log: u32, max_symbol: usize)
+        -> io::Result<(Fse, usize)>
+    {
+        let mut br = ForwardBits { data: data, pos: 0 };
+        let log = br.peek(4) + 5;
+        br.consume(4)?;
+        if log > max_log {
+            return Err(invalid("accuracy log too large"));
+        }
+        let size = 1i32 << log;
+        let mut remaining = size + 1;
+        let mut threshold = size;
+        let mut nbits = log + 1;
+        let mut probs = vec![];
+        while remaining > 1 {
+            if probs.len() > max_symbol {
+                return Err(invalid("too many symbols in table"));
+            }
+            let max = (2 * threshold - 1) - remaining;
+            let low = br.peek(nbits - 1) as i32;
+            let value = if low < max {
+                br.consume(nbits - 1)?;
+                low
+            } else {
+                let v = br.peek(nbits) as i32;
+                br.consume(nbits)?;
+                if v >= threshold { v - max } else { v }
+            };
+            let prob = value - 1;
+            remaining -= prob.abs();
+            probs.push(prob as i16);
+            if prob == 0 {
+                loop {
+                    let repeat = br.peek(2);
+                    br.consume(2)?;
+                    for _ in 0..repeat {
+                        probs.push(0);
+                    }
+                    if repeat != 3 {
+                        break;
+                    }
+                }
+            }
+            while remaining < threshold {
+                nbits -= 1;
+                threshold >>= 1;
+            }
+        }
+        if remaining != 1 || probs.len() > max_symbol + 1 {
+            return Err(invalid("bad table description"));
+        }
+        Ok((Fse::build(log, &probs)?, br.bytes()))
+    }
+
+    /// Builds the decoding table for a distribution of symbols, where -1
+    /// stands for a probability of less than 1.
+    fn build(log: u32, probs: &[i16]) -> io::Result<Fse> {
+        let size = 1usize << log;
+        let mut table = vec![FseEntry::default(); size];
+        let mut next = vec![0u32; probs.len()];
+        let mut high = size - 1;
+        for (s, &p) in probs.iter().enumerate() {
+            if p == -1 {
+                table[high].symbol = s as u8;
+                if high == 0 {
+                    return Err(invalid("bad table distribution"));
+                }
+                high -= 1;
+                next[s] = 1;
+            } else {
+                next[s] = p.max(0) as u32;
+            }
+        }
+        let step = (size >> 1) + (size >> 3) + 3;
+        let mut pos = 0;
+        for (s, &p) in probs.iter().enumerate() {
+            for _ in 0..p.max(0) {
+                table[pos].symbol = s as u8;
+                pos = (pos + step) & (size - 1);
+                while pos > high {
+                    pos = (pos + step) & (size - 1);
+                }
+            }
+        }
+        if pos != 0 {
+            return Err(invalid("bad table distribution"));
+        }
+        for entry in &mut table {
+            let s = entry.symbol as usize;
+            let state = next[s];
+            next[s] += 1;
+            let nbits = log - highbit(state);
+            entry.nbits = nbits as u8;
+            entry.base = ((state << nbits) as usize - size) as u16;
+        }
+        Ok(Fse { log: log, table: table })
+    }
+
+    /// A table that always decodes `symbol`, without reading any bits.
+    fn rle(symbol: u8) -> Fse {
+        Fse {
+            log: 0,
+            table: vec![FseEntry { symbol: symbol, nbits: 0, base: 0 }],
+        }
+    }
+}
+
+/// The state of decoding with a table of finite state entropy codes.
+struct FseState<'t> {
+    fse: &'t Fse,
+    state: usize,
+}
+
+impl<'t> FseState<'t> {
+    fn new(fse: &'t Fse, br: &mut BackwardBits) -> FseState<'t> {
+        FseState { fse: fse, state: br.bits(fse.log) as usize }
+    }
+
+    #[inline]
+    fn symbol(&self) -> u8 {
+        self.fse.table[self.state].symbol
+    }
+
+    #[inline]
+    fn update(&mut self, br: &mut BackwardBits) {
+        let entry = self.fse.table[self.state];
+        let bits = br.bits(entry.nbits as u32) as usize;
+        self.state = entry.base as usize + bits;
+    }
+}
+
+/// A decoding table of the Huffman codes of literals, which is indexed by
+/// the next `log` bits of a stream.
+#[derive(Clone, Debug)]
+struct Huffman {
+    log: u32,
+    /// The symbol and the length of its code.
+    table: Vec<(u8, u8)>,
+}
+
+impl Huffman {
+    /// Reads a Huffman tree description. Returns the table and the number
+    /// of bytes that its description took.
+    fn read(data: &[u8]) -> io::Result<(Huffman, usize)> {
+        let header = match data.first() {
+            Some(&b) => b as usize,
+            None => return Err(invalid("missing Huffman tree")),
+        };
+        let mut weights = vec![];
+        let len;
+        if header >= 128 {
+            let n = header - 127;
+            len = 1 + (n + 1) / 2;
+            if data.len() < len {
+                return Err(invalid("truncated Huffman tree"));
+            }
+            for i in 0..n {
+                let b = data[1 + i / 2];
+                weights.push(if i % 2 == 0 { b >> 4 } else { b & 15 });
+            }
+        } else {
+            len = 1 + header;
+            if data.len() < len {
+                return Err(invalid("truncated Huffman tree"));
+            }
+            let data = &data[1..len];
+            let (fse, used) = Fse::read(data, 6, 255)?;
+            let mut br = BackwardBits::new(&data[used..])?;
+            let mut s1 = FseState::new(&fse, &mut br);
+            let mut s2 = FseState::new(&fse, &mut br);
+            loop {
+                if weights.len() >= 255 {
+                    return Err(invalid("too many Huffman weights"));
+                }
+                weights.push(s1.symbol());
+                s1.update(&mut br);
+                if br.overflowed() {
+                    weights.push(s2.symbol());
+                    break;
+                }
+                weights.push(s2.symbol());
+                s2.update(&mut br);
+                if br.overflowed() {
+                    weights.push(s1.symbol());
+                    break;
+                }
+            }
+        }
+        Ok((Huffman::build(&mut weights)?, len))
+    }
+
+    /// Builds the table from the weights of all symbols but the last, whose
+    /// weight is implied.
+    fn build(weights: &mut Vec<u8>) -> io::Result<Huffman> {
+        if weights.len() > 255 {
+            return Err(invalid("too many Huffman weights"));
+        }
+        let mut total = 0u32;
+        for &w in weights.iter() {
+            if w > 11 {
+                return Err(invalid("bad Huffman weight"));
+            }
+            if w > 0 {
+                total += 1 << (w - 1);
+            }
+        }
+        if total == 0 {
+            return Err(invalid("bad Huffman weights"));
+        }
+        let log = highbit(total) + 1;
+        let rest = (1 << log) - total;
+        if log > 11 || !rest.is_power_of_two() {
+            return Err(invalid("bad Huffman weights"));
+        }
+        weights.push(highbit(rest) as u8 + 1);
+
+        let mut table = Vec::with_capacity(1 << log);
+        for w in 1..(log + 1) as u8 {
+            let nbits = log as u8 + 1 - w;
+            for (s, _) in weights.iter().enumerate().filter(|x| *x.1 == w) {
+                for _ in 0..(1 << (w - 1)) {
+                    table.push((s as u8, nbits));
+                }
+            }
+        }
+        Ok(Huffman { log: log, table: table })
+    }
+
+    /// Decodes one stream of literals until `out` has `len` more bytes.
+    fn decode(&self, data: &[u8], len: usize, out: &mut Vec<u8>)
+        -> io::Result<()>
+    {
+        let mut br = BackwardBits::new(data)?;
+        for _ in 0..len {
+            let (sym, nbits) = self.table[br.peek(self.log) as usize];
+            br.bits(nbits as u32);
+            out.push(sym);
+        }
+        if !br.finished() {
+            return Err(invalid("bad Huffman stream size"));
+        }
+        Ok(())
+    }
+}
+
+/// A small XXH64 hasher for the checksums of frames.
+struct Xxh64 {
+    v: [u64; 4],
+    buf: [u8; 32],
+    nbuf: usize,
+    len: u64,
+}
+
+const P1: u64 = 0x9E37_79B1_85EB_CA87;
+const P2: u64 = 0xC2B2_AE3D_27D4_EB4F;
+const P3: u64 = 0x1656_67B1_9E37_79F9;
+const P4: u64 = 0x85EB_CA77_C2B2_AE63;
+const P5: u64 = 0x27D4_EB2F_1656_67C5;
+
+fn xxh_round(acc: u64, input: u64) -> u64 {
+    acc.wrapping_add(input.wrapping_mul(P2)).rotate_left(31).wrapping_mul(P1)
+}
+
+impl Xxh64 {
+    fn new() -> Xxh64 {
+        Xxh64 {
+            v: [P1.wrapping_add(P2), P2, 0, 0u64.wrapping_sub(P1)],
+            buf: [0; 32],
+            nbuf: 0,
+            len: 0,
+        }
+    }
+
+    fn stripe(v: &mut [u64; 4], data: &[u8]) {
+        for (i, v) in v.iter_mut().enumerate() {
+            *v = xxh_round(*v, LittleEndian::read_u64(&data[8 * i..]));
+        }
+    }
+
+    fn update(&mut self, mut data: &[u8]) {
+        self.len += data.len() as u64;
+        if self.nbuf > 0 {
+            let k = cmp::min(32 - self.nbuf, data.len());
+            self.buf[self.nbuf..self.nbuf + k].copy_from_slice(&data[..k]);
+            self.nbuf += k;
+            data = &data[k..];
+            if self.nbuf < 32 {
+                return;
+            }
+            let buf = self.buf;
+            Xxh64::stripe(&mut self.v, &buf);
+            self.nbuf = 0;
+        }
+        while data.len() >= 32 {
+            Xxh64::stripe(&mut self.v, data);
+            data = &data[32..];
+        }
+        self.buf[..data.len()].copy_from_slice(data);
+        self.nbuf = data.len();
+    }
+
+    fn finish(&self) -> u64 {
+        let mut h = if self.len >= 32 {
+            let v = self.v;
+            let mut h = v[0].rotate_left(1)
+                .wrapping_add(v[1].rotate_left(7))
+                .wrapping_add(v[2].rotate_left(12))
+                .wrapping_add(v[3].rotate_left(18));
+            for &v in &v {
+                h = (h ^ xxh_round(0, v)).wrapping_mul(P1).wrapping_add(P4);
+            }
+            h
+        } else {
+            P5
+        };
+        h = h.wrapping_add(self.len);
+        let mut rest = &self.buf[..self.nbuf];
+        while rest.len() >= 8 {
+            h ^= xxh_round(0, LittleEndian::read_u64(rest));
+            h = h.rotate_left(27).wrapping_mul(P1).wrapping_add(P4);
+            rest = &rest[8..];
+        }
+        if rest.len() >= 4 {
+            h ^= (LittleEndian::read_u32(rest) as u64).wrapping_mul(P1);
+            h = h.rotate_left(23).wrapping_mul(P2).wrapping_add(P3);
+            rest = &rest[4..];
+        }
+        for &b in rest {
+            h ^= (b as u64).wrapping_mul(P5);
+            h = h.rotate_left(11).wrapping_mul(P1);
+        }
+        h ^= h >> 33;
+        h = h.wrapping_mul(P2);
+        h ^= h >> 29;
+        h = h.wrapping_mul(P3);
+        h ^ (h >> 32)
+    }
+}
+
+/// The state of decoding the current frame.
+struct Frame {
+    window: usize,
+    checksum: Option<Xxh64>,
+    /// The number of bytes decompressed from this frame so far.
+    out: u64,
+    reps: [usize; 3],
+    huffman: Option<Huffman>,
+    ll: Option<Fse>,
+    of: Option<Fse>,
+    ml: Option<Fse>,
+}
+
+/// A reader of the decompressed data in a zstd stream.
+///
+/// Streams made of several frames (e.g., created by concatenating zstd
+/// files) are decompressed as one. Skippable frames are skipped.
+pub struct ZstdDecoder<R> {
+    rdr: R,
+    frame: Option<Frame>,
+    /// The decompressed data, which starts with the data that later blocks
+    /// may still refer to.
+    buf: Vec<u8>,
+    /// The position of the first byte in `buf` that wasn't read yet.
+    pos: usize,
+    block: Vec<u8>,
+    literals: Vec<u8>,
+}
+
+impl<R: Read> ZstdDecoder<R> {
+    pub fn new(rdr: R) -> ZstdDecoder<R> {
+        ZstdDecoder {
+            rdr: rdr,
+            frame: None,
+            buf: vec![],
+            pos: 0,
+            block: vec![],
+            literals: vec![],
+        }
+    }
+
+    /// Fills `buf` completely. Returns false if the input ended before the
+    /// first byte, and an error if it ended later.
+    fn fill(&mut self, buf: &mut [u8]) -> io::Result<bool> {
+        let mut n = 0;
+        while n < buf.len() {
+            match self.rdr.read(&mut buf[n..]) {
+                Ok(0) if n == 0 => return Ok(false),
+                Ok(0) => return Err(invalid("unexpected end of data")),
+                Ok(k) => n += k,
+                Err(ref err) if err.kind() == io::ErrorKind::Interrupted => {}
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(true)
+    }
+
+    fn read_exact(&mut self, buf: &mut [u8]) -> io::Result<()> {
+        if buf.is_empty() || self.fill(buf)? {
+            Ok(())
+        } else {
+            Err(invalid("unexpected end of data"))
+        }
+    }
+
+    /// Reads the header of the next frame, skipping skippable frames.
+    /// Returns false if there is no further frame.
+    fn read_frame_header(&mut self) -> io::Result<bool> {
+        let mut magic = [0; 4];
+        loop {
+            if !self.fill(&mut magic)? {
+                return Ok(false);
+            }
+            if magic[1..] == [0x2A, 0x4D, 0x18] && magic[0] & 0xF0 == 0x50 {
+                let mut size = [0; 4];
+                self.read_exact(&mut size)?;
+                let size = LittleEndian::read_u32(&size) as u64;
+                let mut skip = (&mut self.rdr).take(size);
+                let skipped = io::copy(&mut skip, &mut io::sink())?;
+                if skipped < size {
+                    return Err(invalid("unexpected end of data"));
+                }
+                continue;
+            }
+            if magic != MAGIC {
+                return Err(invalid("bad magic number"));
+            }
+            break;
+        }
+
+        let mut desc = [0; 1];
+        self.read_exact(&mut desc)?;
+        let desc = desc[0];
+        if desc & 0x08 != 0 {
+            return Err(invalid("reserved bit set in frame header"));
+        }
+        let single_segment = desc & 0x20 != 0;
+        let mut window = 0u64;
+        if !single_segment {
+            let mut b = [0; 1];
+            self.read_exact(&mut b)?;
+            let base = 1u64 << (10 + (b[0] >> 3));
+            window = base + (base / 8) * (b[0] & 7) as u64;
+        }
+        let mut dict = [0; 4];
+        let dict_len = [0, 1, 2, 4][(desc & 3) as usize];
+        self.read_exact(&mut dict[..dict_len])?;
+        if LittleEndian::read_u32(&dict) != 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::Other,
+                "zstd data that needs a dictionary is not supported"));
+        }
+        let size_len = match desc >> 6 {
+            0 if single_segment => 1,
+            0 => 0,
+            1 => 2,
+            2 => 4,
+            _ => 8,
+        };
+        let mut size = [0; 8];
+        self.read_exact(&mut size[..size_len])?;
+        let mut size = LittleEndian::read_u64(&size);
+        if size_len == 2 {
+            size += 256;
+        }
+        if single_segment {
+            window = size;
+        }
+        if window > WINDOW_MAX {
+            return Err(invalid("window too large"));
+        }
+        self.frame = Some(Frame {
+            window: window as usize,
+            checksum: if desc & 0x04 != 0 { Some(Xxh64::new()) } else { None },
+            out: 0,
+            reps: [1, 4, 8],
+            huffman: None,
+            ll: None,
+            of: None,
+            ml: None,
+        });
+        Ok(true)
+    }
+
+    /// Decodes the next block into `buf`. Returns false at the end of the
+    /// data.
+    fn read_block(&mut self) -> io::Result<bool> {
+        if self.frame.is_none() && !self.read_frame_header()? {
+            return Ok(false);
+        }
+        let window = self.frame.as_ref().unwrap().window;
+        // Keep as much decompressed data as later blocks may refer to.
+        if self.buf.len() > 2 * window + BLOCK_MAX {
+            let drop = self.buf.len() - window;
+            self.buf.drain(..drop);
+            self.pos -= drop;
+        }
+        let start = self.buf.len();
+
+        let mut header = [0; 3];
+        self.read_exact(&mut header)?;
+        let header = LittleEndian::read_u24(&header) as usize;
+        let last = header & 1 == 1;
+        let size = header >> 3;
+        match (header >> 1) & 3 {
+            0 => {
+                self.block.resize(size, 0);
+                let mut block = ::std::mem::replace(&mut self.block, vec![]);
+                self.read_exact(&mut block)?;
+                self.buf.extend_from_slice(&block);
+                self.block = block;
+            }
+            1 => {
+                let mut b = [0; 1];
+                self.read_exact(&mut b)?;
+                let len = self.buf.len() + size;
+                self.buf.resize(len, b[0]);
+            }
+            2 => {
+                if size > cmp::min(window, BLOCK_MAX).max(1 << 10) {
+                    return Err(invalid("block too large"));
+                }
+                self.block.resize(size, 0);
+                let mut block = ::std::mem::replace(&mut self.block, vec![]);
+                self.read_exact(&mut block)?;
+                let res = self.decode_block(&block);
+                self.block = block;
+                res?;
+            }
+            _ => return Err(invalid("reserved block type")),
+        }
+        if self.buf.len() - start > BLOCK_MAX {
+            return Err(invalid("block too large"));
+        }
+
+        let frame = self.frame.as_mut().unwrap();
+        frame.out += (self.buf.len() - start) as u64;
+        if let Some(ref mut sum) = frame.checksum {
+            sum.update(&self.buf[start..]);
+        }
+        if last {
+            let expected = frame.checksum.as_ref().map(|sum| sum.finish());
+            self.frame = None;
+            if let Some(expected) = expected {
+                let mut sum = [0; 4];
+                self.read_exact(&mut sum)?;
+                if LittleEndian::read_u32(&sum) != expected as u32 {
+                    return Err(invalid("checksum mismatch"));
+                }
+            }
+        }
+        Ok(true)
+    }
+
+    /// Decodes a compressed block and appends its data to `buf`.
+    fn decode_block(&mut self, data: &[u8]) -> io::Result<()> {
+        let frame = self.frame.as_mut().unwrap();
+        let used = decode_literals(data, frame, &mut self.literals)?;
+        let data = &data[used..];
+
+        let (count, mut used) = match data {
+            [] => return Err(invalid("missing sequences section")),
+            [0, ..] => (0, 1),
+            [b, ..] if *b < 128 => (*b as usize, 1),
+            [b, c, ..] if *b < 255 => {
+                ((((*b as usize) - 128) << 8) + *c as usize, 2)
+            }
+            [255, c, d, ..] => {
+                (*c as usize + ((*d as usize) << 8) + 0x7F00, 3)
+            }
+            _ => return Err(invalid("truncated sequences section")),
+        };
+        if count == 0 {
+            if used != data.len() {
+                return Err(invalid("bad block size"));
+            }
+            self.buf.extend_from_slice(&self.literals);
+            return Ok(());
+        }
+        let modes = match data.get(used) {
+            Some(&b) => b,
+            None => return Err(invalid("truncated sequences section")),
+        };
+        used += 1;
+        if modes & 3 != 0 {
+            return Err(invalid("reserved bits set in sequences section"));
+        }
+        used += read_table(&data[used..], modes >> 6, &mut frame.ll, 9,
+                           LL_DEFAULT.0, &LL_DEFAULT.1)?;
+        used += read_table(&data[used..], (modes >> 4) & 3, &mut frame.of, 8,
+                           OF_DEFAULT.0, &OF_DEFAULT.1)?;
+        used += read_table(&data[used..], (modes >> 2) & 3, &mut frame.ml, 9,
+                           ML_DEFAULT.0, &ML_DEFAULT.1)?;
+        let mut br = BackwardBits::new(&data[used..])?;
+        let mut ll = FseState::new(frame.ll.as_ref().unwrap(), &mut br);
+        let mut of = FseState::new(frame.of.as_ref().unwrap(), &mut br);
+        let mut ml = FseState::new(frame.ml.as_ref().unwrap(), &mut br);
+
+        let buf = &mut self.buf;
+        let literals = &self.literals;
+        let mut lit = 0;
+        // Matches may only refer to data of the current frame.
+        let floor = buf.len() - cmp::min(frame.out, buf.len() as u64) as usize;
+        for i in 0..count {
+            let of_code = of.symbol() as u32;
+            let ml_code = ml.symbol() as usize;
+            let ll_code = ll.symbol() as usize;
+            if of_code > 31 || ml_code >= ML_BASE.len()
+                || ll_code >= LL_BASE.len() {
+                return Err(invalid("bad sequence code"));
+            }
+            let offset_value = (1u64 << of_code) + br.bits(of_code) as u64;
+            let match_len = ML_BASE[ml_code] as usize
+                + br.bits(ML_EXTRA[ml_code] as u32) as usize;
+            let lit_len = LL_BASE[ll_code] as usize
+                + br.bits(LL_EXTRA[ll_code] as u32) as usize;
+            if i + 1 < count {
+                ll.update(&mut br);
+                ml.update(&mut br);
+                of.update(&mut br);
+            }
+
+            let reps = &mut frame.reps;
+            let offset = if offset_value > 3 {
+                let offset = (offset_value - 3) as usize;
+                *reps = [offset, reps[0], reps[1]];
+                offset
+            } else {
+                let index = offset_value as usize
+                    + if lit_len == 0 { 1 } else { 0 };
+                match index {
+                    1 => reps[0],
+                    2 => {
+                        *reps = [reps[1], reps[0], reps[2]];
+                        reps[0]
+                    }
+                    _ => {
+                        let offset = if index == 3 {
+                            reps[2]
+                        } else {
+                            reps[0].wrapping_sub(1)
+                        };
+                        *reps = [offset, reps[0], reps[1]];
+                        offset
+                    }
+                }
+            };
+
+            if lit + lit_len > literals.len() {
+                return Err(invalid("literal length too large"));
+            }
+            buf.extend_from_slice(&literals[lit..lit + lit_len]);
+            lit += lit_len;
+            if offset == 0 || offset > buf.len() - floor {
+                return Err(invalid("offset too far back"));
+            }
+            let from = buf.len() - offset;
+            if offset >= match_len {
+                buf.extend_from_within(from..from + match_len);
+            } else {
+                for j in 0..match_len {
+                    let b = buf[from + j];
+                    buf.push(b);
+                }
+            }
+        }
+        if !br.finished() {
+            return Err(invalid("bad sequences bitstream size"));
+        }
+        buf.extend_from_slice(&literals[lit..]);
+        Ok(())
+    }
+}
+
+/// Decodes the literals section of a compressed block into `out`. Returns
+/// the size of the section.
+fn decode_literals(
+    data: &[u8],
+    frame: &mut Frame,
+    out: &mut Vec<u8>,
+) -> io::Result<usize> {
+    out.clear();
+    let b0 = match data.first() {
+        Some(&b) => b as usize,
+        None => return Err(invalid("missing literals section")),
+    };
+    let kind = b0 & 3;
+    let format = (b0 >> 2) & 3;
+    let byte = |i: usize| -> io::Result<usize> {
+        data.get(i).map(|&b| b as usize)
+            .ok_or_else(|| invalid("truncated literals section"))
+    };
+    if kind < 2 {
+        let (size, header) = match format {
+            0 | 2 => (b0 >> 3, 1),
+            1 => ((b0 >> 4) + (byte(1)? << 4), 2),
+            _ => ((b0 >> 4) + (byte(1)? << 4) + (byte(2)? << 12), 3),
+        };
+        if size > BLOCK_MAX {
+            return Err(invalid("too many literals"));
+        }
+        if kind == 0 {
+            match data.get(header..header + size) {
+                Some(lits) => out.extend_from_slice(lits),
+                None => return Err(invalid("truncated literals section")),
+            }
+            return Ok(header + size);
+        }
+        out.resize(size, byte(header)? as u8);
+        return Ok(header + 1);
+    }
+
+    let (streams, regen, comp, header) = match format {
+        0 | 1 => {
+            let v = b0 | byte(1)? << 8 | byte(2)? << 16;
+            (if format == 0 { 1 } else { 4 }, (v >> 4) & 0x3FF,
+             (v >> 14) & 0x3FF, 3)
+        }
+        2 => {
+            let v = b0 | byte(1)? << 8 | byte(2)? << 16 | byte(3)? << 24;
+            (4, (v >> 4) & 0x3FFF, (v >> 18) & 0x3FFF, 4)
+        }
+        _ => {
+            let v = b0 as u64 | (byte(1)? as u64) << 8
+                | (byte(2)? as u64) << 16 | (byte(3)? as u64) << 24
+                | (byte(4)? as u64) << 32;
+            (4, ((v >> 4) & 0x3FFFF) as usize,
+             ((v >> 22) & 0x3FFFF) as usize, 5)
+        }
+    };
+    if regen > BLOCK_MAX {
+        return Err(invalid("too many literals"));
+    }
+    let mut data = match data.get(header..header + comp) {
+        Some(data) => data,
+        None => return Err(invalid("truncated literals section")),
+    };
+    if kind == 2 {
+        let (huffman, used) = Huffman::read(data)?;
+        frame.huffman = Some(huffman);
+        data = &data[used..];
+    }
+    let huffman = match frame.huffman {
+        Some(ref huffman) => huffman,
+        None => return Err(invalid("missing Huffman tree")),
+    };
+    if streams == 1 {
+        huffman.decode(data, regen, out)?;
+    } else {
+        if data.len() < 6 {
+            return Err(invalid("truncated literals jump table"));
+        }
+        let lens = [
+            LittleEndian::read_u16(&data[0..]) as usize,
+            LittleEndian::read_u16(&data[2..]) as usize,
+            LittleEndian::read_u16(&data[4..]) as usize,
+        ];
+        let mut data = &data[6..];
+        let each = (regen + 3) / 4;
+        if 3 * each > regen {
+            return Err(invalid("bad literals size"));
+        }
+        for &len in &lens {
+            if len > data.len() {
+                return Err(invalid("bad literals jump table"));
+            }
+            huffman.decode(&data[..len], each, out)?;
+            data = &data[len..];
+        }
+        huffman.decode(data, regen - 3 * each, out)?;
+    }
+    Ok(header + comp)
+}
+
+/// Reads the table of a sequences section in the given mode, keeping it in
+/// `table` for later blocks. Returns the size of its description.
+fn read_table(
+    data: &[u8],
+    mode: u8,
+    table: &mut Option<Fse>,
+    max_log: u32,
+    default_log: u32,
+    default: &[i16],
+) -> io::Result<usize> {
+    match mode {
+        0 => {
+            *table = Some(Fse::build(default_log, default)?);
+            Ok(0)
+        }
+        1 => match data.first() {
+            Some(&symbol) => {
+                *table = Some(Fse::rle(symbol));
+                Ok(1)
+            }
+            None => Err(invalid("truncated sequences section")),
+        },
+        2 => {
+            let (fse, used) = Fse::read(data, max_log, default.len() - 1)?;
+            *table = Some(fse);
+            Ok(used)
+        }
+        _ => {
+            if table.is_none() {
+                return Err(invalid("missing table to repeat"));
+            }
+            Ok(0)
+        }
+    }
+}
+
+impl<R: Read> Read for ZstdDecoder<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.pos == self.buf.len() {
+            if !self.read_block()? {
+                return Ok(0);
+            }
+        }
+        let n = cmp::min(buf.len(), self.buf.len() - self.pos);
+        buf[..n].copy_from_slice(&self.buf[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+/// The window of the encoder, which is written as the window descriptor
+/// `ENCODE_WINDOW_LOG - 10 << 3`.
+const ENCODE_WINDOW_LOG: u32 = 20;
+
+/// The longest match of the encoder.
+const ENCODE_MAX_MATCH: usize = 1 << 16;
+
+/// Returns the index of the largest of `bases` that is at most `n`.
+fn code_for(bases: &[u32], n: usize) -> usize {
+    bases.iter().rposition(|&base| base as usize <= n).unwrap()
+}
+
+/// An encoding table of finite state entropy codes, as built by the
+/// reference implementation.
+struct FseEncoder {
+    log: u32,
+    /// The encoder states sorted by symbol.
+    states: Vec<u32>,
+    /// For every symbol, the values to find the next state and the number
+    /// of bits that are written.
+    delta_find_state: Vec<i32>,
+    delta_nbits: Vec<u32>,
+}
+
+impl FseEncoder {
+    fn new(log: u32, probs: &[i16]) -> FseEncoder {
+        let size = 1usize << log;
+        let decoder = Fse::build(log, probs).unwrap();
+        let mut cumul = vec![0usize; probs.len() + 1];
+        for (s, &p) in probs.iter().enumerate() {
+            cumul[s + 1] = cumul[s] + if p == -1 { 1 } else { p as usize };
+        }
+        let mut states = vec![0; size];
+        for (u, entry) in decoder.table.iter().enumerate() {
+            let s = entry.symbol as usize;
+            states[cumul[s]] = (size + u) as u32;
+            cumul[s] += 1;
+        }
+        let mut delta_find_state = vec![0; probs.len()];
+        let mut delta_nbits = vec![0; probs.len()];
+        let mut total = 0i32;
+        for (s, &p) in probs.iter().enumerate() {
+            match p {
+                0 => {
+                    delta_nbits[s] = ((log + 1) << 16).wrapping_sub(1 << log);
+                }
+                -1 | 1 => {
+                    delta_nbits[s] = (log << 16).wrapping_sub(1 << log);
+                    delta_find_state[s] = total - 1;
+                    total += 1;
+                }
+                p => {
+                    let p = p as u32;
+                    let max_bits = log - highbit(p - 1);
+                    delta_nbits[s] =
+                        (max_bits << 16).wrapping_sub(p << max_bits);
+                    delta_find_state[s] = total - p as i32;
+                    total += p as i32;
+                }
+            }
+        }
+        FseEncoder {
+            log: log,
+            states: states,
+            delta_find_state: delta_find_state,
+            delta_nbits: delta_nbits,
+        }
+    }
+
+    fn next_state(&self, value: u32, nbits: u32, symbol: usize) -> u32 {
+        let i = (value >> nbits) as i32 + self.delta_find_state[symbol];
+        self.states[i as usize]
+    }
+
+    /// Returns the initial state for the last symbol of the data.
+    fn init(&self, symbol: usize) -> u32 {
+        let delta = self.delta_nbits[symbol];
+        let nbits = delta.wrapping_add(1 << 15) >> 16;
+        let value = (nbits << 16).wrapping_sub(delta);
+        self.next_state(value, nbits, symbol)
+    }
+
+    fn encode(&self, state: &mut u32, symbol: usize, bw: &mut BitWriter) {
+        let nbits = state.wrapping_add(self.delta_nbits[symbol]) >> 16;
+        bw.put(*state as u64, nbits);
+        *state = self.next_state(*state, nbits, symbol);
+    }
+
+    fn flush(&self, state: u32, bw: &mut BitWriter) {
+        bw.put(state as u64, self.log);
+    }
+}
+
+/// Writes the header of a literals section of the given type (raw or RLE)
+/// for `n` literals.
+fn literals_header(kind: u8, n: usize, out: &mut Vec<u8>) {
+    if n < 32 {
+        out.push(kind | (n << 3) as u8);
+    } else if n < 1 << 12 {
+        out.push(kind | 1 << 2 | ((n & 15) << 4) as u8);
+        out.push((n >> 4) as u8);
+    } else {
+        out.push(kind | 3 << 2 | ((n & 15) << 4) as u8);
+        out.push((n >> 4) as u8);
+        out.push((n >> 12) as u8);
+    }
+}
+
+/// Returns the Huffman coded literals section for `lits`, if they can be
+/// coded that way and it's smaller than writing them as they are.
+fn huffman_literals(lits: &[u8]) -> Option<Vec<u8>> {
+    let mut freqs = [0u32; 256];
+    for &b in lits {
+        freqs[b as usize] += 1;
+    }
+    let last = freqs.iter().rposition(|&f| f > 0)?;
+    // The simple form of the tree description has at most 128 weights.
+    if lits.len() < 64 || last > 128
+        || freqs.iter().filter(|&&f| f > 0).count() < 2 {
+        return None;
+    }
+    let lens = compress::code_lengths(&freqs[..last + 1], 11);
+    let max = *lens.iter().max().unwrap() as u32;
+    let mut codes = vec![0u32; lens.len()];
+    let mut next = 0u32;
+    for len in (1..max + 1).rev() {
+        for (s, _) in lens.iter().enumerate().filter(|x| *x.1 as u32 == len) {
+            codes[s] = next >> (max - len);
+            next += 1 << (max - len);
+        }
+    }
+
+    let mut tree = vec![127 + last as u8];
+    for pair in lens[..last].chunks(2) {
+        let weight = |len: u8| if len == 0 { 0 } else { max as u8 + 1 - len };
+        let low = pair.get(1).map_or(0, |&len| weight(len));
+        tree.push(weight(pair[0]) << 4 | low);
+    }
+    let stream = |lits: &[u8]| -> Vec<u8> {
+        let mut bw = BitWriter::new();
+        for &b in lits.iter().rev() {
+            let b = b as usize;
+            bw.put(codes[b] as u64, lens[b] as u32);
+        }
+        bw.put(1, 1);
+        bw.align();
+        bw.out
+    };
+
+    let mut body = tree;
+    let format = if lits.len() < 1 << 10 {
+        body.extend(stream(lits));
+        0
+    } else {
+        let each = (lits.len() + 3) / 4;
+        let streams: Vec<Vec<u8>> = lits.chunks(each).map(stream).collect();
+        for stream in &streams[..3] {
+            body.extend_from_slice(&(stream.len() as u16).to_le_bytes());
+        }
+        for stream in &streams {
+            body.extend_from_slice(stream);
+        }
+        let size = cmp::max(lits.len(), body.len());
+        if size < 1 << 10 { 1 } else if size < 1 << 14 { 2 } else { 3 }
+    };
+    let (regen, comp) = (lits.len() as u64, body.len() as u64);
+    let (header, len): (u64, usize) = match format {
+        0 | 1 => (2 | format << 2 | regen << 4 | comp << 14, 3),
+        2 => (2 | 2 << 2 | regen << 4 | comp << 18, 4),
+        _ => (2 | 3 << 2 | regen << 4 | comp << 22, 5),
+    };
+    if len as u64 + comp >= regen {
+        return None;
+    }
+    let mut out = header.to_le_bytes()[..len].to_vec();
+    out.extend(body);
+    Some(out)
+}
+
+/// A writer that compresses the data written to it in the zstd format.
+///
+/// Flushing ends the current frame, so that everything written so far is
+/// complete zstd data. Data written after that starts a new frame, which
+/// decoders read as a continuation of the same stream. Dropping the writer
+/// flushes it, too (but ignores errors).
+pub struct ZstdEncoder<W: Write> {
+    wtr: W,
+    level: u32,
+    matcher: Matcher,
+    ll: FseEncoder,
+    ml: FseEncoder,
+    of: FseEncoder,
+    /// The last data that matches may refer to, followed by the data that
+    /// wasn't compressed yet.
+    data: Vec<u8>,
+    /// The start of the data that wasn't compressed yet.
+    pending: usize,
+    /// The position of `data[0]` in the current frame.
+    base: u64,
+    seqs: Vec<Sequence>,
+    out: Vec<u8>,
+    in_frame: bool,
+    /// Whether a complete frame was written.
+    finished: bool,
+    checksum: Xxh64,
+}
+
+impl<W: Write> ZstdEncoder<W> {
+    /// Creates an encoder with a compression level from 1 (fastest) to 19
+    /// (best compression).
+    pub fn new(wtr: W, level: u32) -> ZstdEncoder<W> {
+        ZstdEncoder {
+            wtr: wtr,
+            level: level,
+            matcher: ZstdEncoder::<W>::matcher(level),
+            ll: FseEncoder::new(LL_DEFAULT.0, &LL_DEFAULT.1),
+            ml: FseEncoder::new(ML_DEFAULT.0, &ML_DEFAULT.1),
+            of: FseEncoder::new(OF_DEFAULT.0, &OF_DEFAULT.1),
+            data: vec![],
+            pending: 0,
+            base: 0,
+            seqs: vec![],
+            out: vec![],
+            in_frame: false,
+            finished: false,
+            checksum: Xxh64::new(),
+        }
+    }
+
+    fn matcher(level: u32) -> Matcher {
+        let chain = 1 << cmp::min(level / 2 + 2, 9);
+        Matcher::new(1 << ENCODE_WINDOW_LOG, ENCODE_MAX_MATCH, chain,
+                     level >= 2)
+    }
+
+    fn start_frame(&mut self) {
+        if !self.in_frame {
+            self.out.extend_from_slice(&MAGIC);
+            // A checksum, no content size and a window descriptor.
+            self.out.push(0x04);
+            self.out.push(((ENCODE_WINDOW_LOG - 10) << 3) as u8);
+            self.in_frame = true;
+            self.checksum = Xxh64::new();
+        }
+    }
+
+    /// Compresses the pending data up to `end` as one block.
+    fn compress_block(&mut self, end: usize, last: bool) -> io::Result<()> {
+        self.seqs.clear();
+        self.matcher.parse(
+            &self.data[..end], self.base, self.pending, &mut self.seqs);
+        let src = &self.data[self.pending..end];
+
+        let mut lits = Vec::with_capacity(src.len());
+        let mut pos = 0;
+        for seq in &self.seqs {
+            lits.extend_from_slice(&src[pos..pos + seq.literals]);
+            pos += seq.literals + seq.len;
+        }
+        let mut body = match huffman_literals(&lits) {
+            Some(section) => section,
+            None if !lits.is_empty() && lits.iter().all(|&b| b == lits[0]) => {
+                let mut section = vec![];
+                literals_header(1, lits.len(), &mut section);
+                section.push(lits[0]);
+                section
+            }
+            None => {
+                let mut section = vec![];
+                literals_header(0, lits.len(), &mut section);
+                section.extend_from_slice(&lits);
+                section
+            }
+        };
+        self.encode_sequences(&mut body);
+
+        let kind = if src.is_empty() || body.len() >= src.len() {
+            body.clear();
+            body.extend_from_slice(src);
+            0
+        } else {
+            2
+        };
+        let header = last as u32 | kind << 1 | (body.len() as u32) << 3;
+        self.out.extend_from_slice(&header.to_le_bytes()[..3]);
+        self.out.extend_from_slice(&body);
+        self.checksum.update(src);
+
+        self.pending = end;
+        // Keep as much data as later matches may refer to.
+        let window = 1 << ENCODE_WINDOW_LOG;
+        if self.pending > 2 * window {
+            let drop = self.pending - window;
+            self.data.drain(..drop);
+            self.base += drop as u64;
+            self.pending -= drop;
+        }
+        self.wtr.write_all(&self.out)?;
+        self.out.clear();
+        Ok(())
+    }
+
+    /// Writes the sequences section for the sequences of the current block.
+    fn encode_sequences(&self, out: &mut Vec<u8>) {
+        // Literals after the last match don't need a sequence.
+        let seqs: Vec<&Sequence> =
+            self.seqs.iter().filter(|seq| seq.len > 0).collect();
+        let n = seqs.len();
+        if n < 128 {
+            out.push(n as u8);
+        } else if n < 0x7F00 {
+            out.push((n >> 8) as u8 + 128);
+            out.push(n as u8);
+        } else {
+            out.push(255);
+            out.extend_from_slice(&((n - 0x7F00) as u16).to_le_bytes());
+        }
+        if n == 0 {
+            return;
+        }
+        // All codes use the predefined distributions.
+        out.push(0);
+
+        let codes: Vec<(usize, usize, usize, u32)> = seqs.iter().map(|seq| {
+            let offset = (seq.dist + 3) as u32;
+            (code_for(&LL_BASE, seq.literals), code_for(&ML_BASE, seq.len),
+             highbit(offset) as usize, offset)
+        }).collect();
+        let mut bw = BitWriter::new();
+        let extra = |bw: &mut BitWriter, i: usize| {
+            let (ll, ml, of, offset) = codes[i];
+            bw.put((seqs[i].literals as u32 - LL_BASE[ll]) as u64,
+                   LL_EXTRA[ll] as u32);
+            bw.put((seqs[i].len as u32 - ML_BASE[ml]) as u64,
+                   ML_EXTRA[ml] as u32);
+            bw.put((offset - (1 << of)) as u64, of as u32);
+        };
+        let (ll, ml, of, _) = codes[n - 1];
+        let mut ll_state = self.ll.init(ll);
+        let mut ml_state = self.ml.init(ml);
+        let mut of_state = self.of.init(of);
+        extra(&mut bw, n - 1);
+        for i in (0..n - 1).rev() {
+            let (ll, ml, of, _) = codes[i];
+            self.of.encode(&mut of_state, of, &mut bw);
+            self.ml.encode(&mut ml_state, ml, &mut bw);
+            self.ll.encode(&mut ll_state, ll, &mut bw);
+            extra(&mut bw, i);
+        }
+        self.ml.flush(ml_state, &mut bw);
+        self.of.flush(of_state, &mut bw);
+        self.ll.flush(ll_state, &mut bw);
+        bw.put(1, 1);
+        bw.align();
+        out.extend(bw.out);
+    }
+
+    /// Compresses the pending data and writes the end of the frame.
+    fn finish_frame(&mut self) -> io::Result<()> {
+        self.start_frame();
+        let end = self.data.len();
+        self.compress_block(end, true)?;
+        let sum = self.checksum.finish() as u32;
+        self.out.extend_from_slice(&sum.to_le_bytes());
+        self.wtr.write_all(&self.out)?;
+        self.out.clear();
+        self.in_frame = false;
+        self.finished = true;
+        self.data.clear();
+        self.pending = 0;
+        self.base = 0;
+        self.matcher = ZstdEncoder::<W>::matcher(self.level);
+        Ok(())
+    }
+}
+
+impl<W: Write> Write for ZstdEncoder<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.start_frame();
+        self.data.extend_from_slice(buf);
+        // Keep the last block for `finish_frame`, which marks it as last.
+        while self.data.len() - self.pending > BLOCK_MAX {
+            let end = self.pending + BLOCK_MAX;
+            self.compress_block(end, false)?;
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if self.in_frame {
+            self.finish_frame()?;
+        }
+        self.wtr.flush()
+    }
+}
+
+impl<W: Write> Drop for ZstdEncoder<W> {
+    fn drop(&mut self) {
+        // Even without any data, the output should be valid zstd data.
+        if self.in_frame || !self.finished {
+            let _ = self.finish_frame();
+            let _ = self.wtr.flush();
+        }
+    }
+}
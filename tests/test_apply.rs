@@ -0,0 +1,107 @@
+use workdir::Workdir;
+
+fn data() -> Vec<Vec<String>> {
+    vec![
+        svec!["name", "price", "date"],
+        svec!["  big   box ", "$-3.456", "03/02/2021"],
+        svec!["mug", "$12", "2021-02-04"],
+        svec!["", "", ""],
+    ]
+}
+
+#[test]
+fn apply_in_place() {
+    let wrk = Workdir::new("apply_in_place");
+    wrk.create("in.csv", data());
+
+    let mut cmd = wrk.command("apply");
+    cmd.arg("-s").arg("name").arg("upper").arg("in.csv");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got[1], svec!["  BIG   BOX ", "$-3.456", "03/02/2021"]);
+    assert_eq!(got[2], svec!["MUG", "$12", "2021-02-04"]);
+
+    let mut cmd = wrk.command("apply");
+    cmd.arg("-s").arg("name").arg("squeeze").arg("in.csv");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got[1][0], " big box ");
+}
+
+#[test]
+fn apply_replace_new_column() {
+    let wrk = Workdir::new("apply_replace_new_column");
+    wrk.create("in.csv", data());
+
+    let mut cmd = wrk.command("apply");
+    cmd.arg("-s").arg("price").arg("--new-column").arg("amount")
+       .arg("replace").arg("$").arg("").arg("in.csv");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["name", "price", "date", "amount"],
+        svec!["  big   box ", "$-3.456", "03/02/2021", "-3.456"],
+        svec!["mug", "$12", "2021-02-04", "12"],
+        svec!["", "", "", ""],
+    ];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn apply_len_suffix() {
+    let wrk = Workdir::new("apply_len_suffix");
+    wrk.create("in.csv", data());
+
+    let mut cmd = wrk.command("apply");
+    cmd.arg("-s").arg("name,price").arg("--suffix").arg("_len")
+       .arg("len").arg("in.csv");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got[0], svec!["name", "price", "date", "name_len", "price_len"]);
+    assert_eq!(got[2], svec!["mug", "$12", "2021-02-04", "3", "3"]);
+}
+
+#[test]
+fn apply_round_abs() {
+    let wrk = Workdir::new("apply_round_abs");
+    wrk.create("in.csv", vec![
+        svec!["n"], svec!["-3.456"], svec!["2"], svec!["x"],
+    ]);
+
+    let mut cmd = wrk.command("apply");
+    cmd.arg("-s").arg("n").arg("round").arg("1").arg("in.csv");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got, vec![svec!["n"], svec!["-3.5"], svec!["2.0"], svec!["x"]]);
+
+    let mut cmd = wrk.command("apply");
+    cmd.arg("-s").arg("n").arg("--strict").arg("abs").arg("in.csv");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got, vec![svec!["n"], svec!["3.456"], svec!["2"], svec![""]]);
+}
+
+#[test]
+fn apply_datefmt_failures() {
+    let wrk = Workdir::new("apply_datefmt_failures");
+    wrk.create("in.csv", data());
+
+    let mut cmd = wrk.command("apply");
+    cmd.arg("-s").arg("date")
+       .arg("datefmt").arg("%d/%m/%Y").arg("%Y-%m-%d")
+       .arg("in.csv");
+    let output = cmd.output().unwrap();
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got[1][2], "2021-02-03");
+    assert_eq!(got[2][2], "2021-02-04");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("1 value(s) were invalid"), "{}", stderr);
+}
+
+#[test]
+fn apply_bad_arguments() {
+    let wrk = Workdir::new("apply_bad_arguments");
+    wrk.create("in.csv", data());
+
+    let mut cmd = wrk.command("apply");
+    cmd.arg("-s").arg("name").arg("shout").arg("in.csv");
+    wrk.assert_err(&mut cmd);
+
+    let mut cmd = wrk.command("apply");
+    cmd.arg("-s").arg("name").arg("replace").arg("a");
+    wrk.assert_err(&mut cmd);
+}
@@ -0,0 +1,49 @@
+use workdir::Workdir;
+
+fn data() -> Vec<Vec<String>> {
+    vec![svec!["h1", "h2"], svec!["a", "b, c"], svec!["d", "e"]]
+}
+
+#[test]
+fn behead() {
+    let wrk = Workdir::new("behead");
+    wrk.create("in.csv", data());
+
+    let mut cmd = wrk.command("behead");
+    cmd.arg("in.csv");
+    let got: String = wrk.stdout(&mut cmd);
+    assert_eq!(got, "a,\"b, c\"\nd,e");
+}
+
+#[test]
+fn behead_flip() {
+    let wrk = Workdir::new("behead_flip");
+    wrk.create("in.csv", data());
+
+    let mut cmd = wrk.command("behead");
+    cmd.arg("--flip").arg("in.csv");
+    let got: String = wrk.stdout(&mut cmd);
+    assert_eq!(got, "h1,h2");
+}
+
+#[test]
+fn behead_header_only() {
+    let wrk = Workdir::new("behead_header_only");
+    wrk.create("in.csv", vec![svec!["h1", "h2"]]);
+
+    let mut cmd = wrk.command("behead");
+    cmd.arg("in.csv");
+    let got: String = wrk.stdout(&mut cmd);
+    assert_eq!(got, "");
+}
+
+#[test]
+fn behead_flip_empty() {
+    let wrk = Workdir::new("behead_flip_empty");
+    wrk.create("in.csv", Vec::<Vec<String>>::new());
+
+    let mut cmd = wrk.command("behead");
+    cmd.arg("--flip").arg("in.csv");
+    let got: String = wrk.stdout(&mut cmd);
+    assert_eq!(got, "");
+}
@@ -0,0 +1,130 @@
+use std::fs;
+
+use workdir::Workdir;
+
+fn data() -> &'static str {
+    "# exported 2024-01-01\n\
+     city,pop\n\
+     Boston,667137\n\
+     # interleaved\n\
+     \"# Lagos\",15388000\n\
+     Lima,#9751717\n"
+}
+
+fn setup(name: &str) -> Workdir {
+    let wrk = Workdir::new(name);
+    fs::write(wrk.path("in.csv"), data()).unwrap();
+    wrk
+}
+
+#[test]
+fn comment_skips_lines() {
+    let wrk = setup("comment_skips_lines");
+    let mut cmd = wrk.command("--comment");
+    cmd.args(["#", "cat", "rows", "in.csv"]);
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["city", "pop"],
+        svec!["Boston", "667137"],
+        svec!["# Lagos", "15388000"],
+        svec!["Lima", "#9751717"],
+    ];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn comment_quotes_output() {
+    let wrk = setup("comment_quotes_output");
+    let mut cmd = wrk.command("--comment");
+    cmd.args(["#", "select", "city", "in.csv"]);
+    let got: String = wrk.stdout(&mut cmd);
+    assert_eq!(got, "city\nBoston\n\"# Lagos\"\nLima");
+}
+
+#[test]
+fn comment_without_option() {
+    let wrk = setup("comment_without_option");
+    let mut cmd = wrk.command("count");
+    cmd.arg("in.csv");
+    wrk.assert_err(&mut cmd);
+}
+
+#[test]
+fn comment_count() {
+    let wrk = setup("comment_count");
+    for fast in &[false, true] {
+        let mut cmd = wrk.command("--comment");
+        cmd.args(["#", "count", "in.csv"]);
+        if *fast {
+            cmd.arg("--fast");
+        }
+        let got: String = wrk.stdout(&mut cmd);
+        assert_eq!(got, "3");
+    }
+}
+
+#[test]
+fn comment_index() {
+    let wrk = setup("comment_index");
+    let mut cmd = wrk.command("--comment");
+    cmd.args(["#", "index", "in.csv"]);
+    wrk.run(&mut cmd);
+
+    let mut cmd = wrk.command("--comment");
+    cmd.args(["#", "slice", "-i", "2", "in.csv"]);
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got, vec![svec!["city", "pop"], svec!["Lima", "#9751717"]]);
+
+    let mut cmd = wrk.command("--comment");
+    cmd.args(["#", "count", "in.csv"]);
+    let got: String = wrk.stdout(&mut cmd);
+    assert_eq!(got, "3");
+
+    let mut cmd = wrk.command("--comment");
+    cmd.args(["#", "index", "--check", "in.csv"]);
+    wrk.run(&mut cmd);
+}
+
+#[test]
+fn comment_index_mismatch() {
+    let wrk = Workdir::new("comment_index_mismatch");
+    fs::write(wrk.path("in.csv"), "a\n1\n#2\n").unwrap();
+    let mut cmd = wrk.command("index");
+    cmd.arg("in.csv");
+    wrk.run(&mut cmd);
+
+    let mut cmd = wrk.command("--comment");
+    cmd.args(["#", "slice", "-i", "0", "in.csv"]);
+    let o = cmd.output().unwrap();
+    assert!(!o.status.success());
+    let stderr = String::from_utf8_lossy(&o.stderr);
+    assert!(stderr.contains("it was created without --comment"), "{}", stderr);
+
+    let mut cmd = wrk.command("--comment");
+    cmd.args(["#", "count", "--auto-index", "in.csv"]);
+    let got: String = wrk.stdout(&mut cmd);
+    assert_eq!(got, "1");
+
+    let mut cmd = wrk.command("count");
+    cmd.arg("in.csv");
+    wrk.assert_err(&mut cmd);
+}
+
+#[test]
+fn comment_multi_index() {
+    let wrk = Workdir::new("comment_multi_index");
+    fs::write(wrk.path("ev-1.csv"), "# one\na\n1\n").unwrap();
+    fs::write(wrk.path("ev-2.csv"), "a\n# two\n2\n3\n").unwrap();
+    let mut cmd = wrk.command("--comment");
+    cmd.args(["#", "index", "--multi", "ev-*.csv", "-o", "ev.midx"]);
+    wrk.run(&mut cmd);
+
+    let mut cmd = wrk.command("--comment");
+    cmd.args(["#", "count", "ev.midx"]);
+    let got: String = wrk.stdout(&mut cmd);
+    assert_eq!(got, "3");
+
+    let mut cmd = wrk.command("count");
+    cmd.arg("ev.midx");
+    wrk.assert_err(&mut cmd);
+}
@@ -0,0 +1,57 @@
+use workdir::Workdir;
+
+/// The names of the commands listed by `xsv --list`.
+fn commands(wrk: &Workdir) -> Vec<String> {
+    let mut cmd = wrk.command("--list");
+    let out: String = wrk.stdout(&mut cmd);
+    out.lines()
+       .skip(1)
+       .filter_map(|line| line.split_whitespace().next())
+       .map(|name| name.to_owned())
+       .collect()
+}
+
+#[test]
+fn completions_bash_has_every_command() {
+    let wrk = Workdir::new("completions_bash_has_every_command");
+    let commands = commands(&wrk);
+    assert!(commands.contains(&"completions".to_owned()));
+
+    let mut cmd = wrk.command("completions");
+    cmd.arg("bash");
+    let script: String = wrk.stdout(&mut cmd);
+    for name in &commands {
+        let case = format!("\n        {}) opts=\"", name);
+        let start = script.find(&case)
+            .unwrap_or_else(|| panic!("'{}' is not completed", name));
+        let opts = &script[start + case.len()..];
+        let opts = &opts[..opts.find('"').unwrap()];
+        assert!(opts.split(' ').any(|o| o == "--help"),
+                "the options of '{}' are missing: {}", name, opts);
+    }
+    assert!(script.contains("--no-headers"));
+    assert!(script.contains("complete -o filenames -F _xsv xsv"));
+}
+
+#[test]
+fn completions_other_shells() {
+    let wrk = Workdir::new("completions_other_shells");
+    for &(shell, expected) in &[
+        ("zsh", "'--select[Select the columns"),
+        ("fish", "-n '__fish_seen_subcommand_from slice' -s s -l start -r"),
+        ("powershell", "'reverse' = @('--memcheck'"),
+    ] {
+        let mut cmd = wrk.command("completions");
+        cmd.arg(shell);
+        let script: String = wrk.stdout(&mut cmd);
+        assert!(script.contains(expected), "{}: {}", shell, script);
+    }
+}
+
+#[test]
+fn completions_unknown_shell() {
+    let wrk = Workdir::new("completions_unknown_shell");
+    let mut cmd = wrk.command("completions");
+    cmd.arg("tcsh");
+    wrk.assert_err(&mut cmd);
+}
@@ -0,0 +1,255 @@
+use std::fs;
+use std::process;
+
+use workdir::Workdir;
+
+fn data() -> String {
+    [
+    "city,country,population,latitude,longitude\n",
+    "Boston,US,667137,42.357,-71.059\n",
+    "Boise,US,228959,43.613,-116.238\n",
+    "Berlin,DE,3644826,52.52,13.405\n",
+    "Bremen,DE,569352,53.079,8.801\n",
+    "Paris,FR,2148271,48.857,2.352\n",
+    "Lyon,FR,513275,45.764,4.836\n",
+    "Lagos,NG,15388000,6.524,3.379\n",
+    "Lima,PE,9751717,-12.046,-77.043\n",
+    "Tokyo,JP,13960000,35.676,139.65\n",
+    "Osaka,JP,2753862,34.694,135.502\n",
+    ].concat()
+}
+
+/// `data()` compressed with `zstd -19`.
+#[cfg(feature = "zstd")]
+const DATA_ZST: &[u8] = &[
+    0x28, 0xB5, 0x2F, 0xFD, 0x64, 0x60, 0x00, 0x25, 0x08, 0x00, 0xA6, 0x13,
+    0x3A, 0x1E, 0x60, 0x6D, 0x73, 0x80, 0x1A, 0x2B, 0xD2, 0x47, 0x23, 0x7B,
+    0xDA, 0xE1, 0x00, 0xD7, 0xB0, 0x09, 0xAE, 0x4B, 0xDA, 0x44, 0xE4, 0x16,
+    0xB9, 0x59, 0x76, 0x41, 0x90, 0x65, 0x98, 0x03, 0x35, 0x00, 0x30, 0x00,
+    0x30, 0x00, 0xED, 0x90, 0xE1, 0x53, 0x75, 0xA4, 0x27, 0x32, 0x0A, 0xF2,
+    0x4A, 0xE6, 0x96, 0x3B, 0xDE, 0xD4, 0x76, 0xB7, 0xCC, 0x75, 0x5F, 0xC1,
+    0x38, 0x14, 0x27, 0x29, 0x32, 0xEA, 0x01, 0xC5, 0xC8, 0x1D, 0x07, 0x65,
+    0x3A, 0x14, 0x9F, 0xA9, 0x89, 0x50, 0x26, 0x71, 0xF2, 0x4A, 0x34, 0x41,
+    0x94, 0xD8, 0x78, 0x65, 0x7A, 0x1A, 0x01, 0x09, 0xC1, 0xA5, 0x27, 0x6A,
+    0x10, 0x8A, 0xAD, 0x97, 0xEF, 0xD0, 0xAD, 0x5C, 0xBE, 0xF2, 0xC2, 0x66,
+    0x47, 0xE2, 0x44, 0x8C, 0xE2, 0xD5, 0x72, 0xD9, 0x95, 0x53, 0xF9, 0xAD,
+    0xAF, 0xB5, 0xDE, 0x07, 0x00, 0x87, 0x9E, 0x48, 0xE4, 0x30, 0xEA, 0xAD,
+    0x66, 0x33, 0xB6, 0x7C, 0x59, 0xE7, 0x06, 0x01, 0xC4, 0x1B, 0xC9, 0x53,
+    0x50, 0xD0, 0x6D, 0xEE, 0x9B, 0x72, 0xF6, 0xD7, 0x5A, 0xEF, 0x2D, 0xB3,
+    0x0F, 0x0C, 0x97, 0xE2, 0xA3, 0x29, 0xA8, 0xDE, 0x97, 0xED, 0xAD, 0x5D,
+    0x72, 0x3B, 0x3B, 0x5D, 0xC6, 0x50, 0xAC, 0x14, 0x94, 0xDE, 0xF1, 0xCB,
+    0xD7, 0xCE, 0x9C, 0xBE, 0xC6, 0xF4, 0x01, 0x4A, 0x17, 0x27, 0x2F, 0x7B,
+    0x83, 0x9D, 0x6D, 0xAF, 0xFD, 0x96, 0x9D, 0x36, 0x90, 0x46, 0x3A, 0x28,
+    0xEE, 0x6C, 0xD7, 0xC9, 0xBB, 0x1A, 0x2E, 0x3B, 0x82, 0xF0, 0x58, 0x81,
+    0xC4, 0x85, 0xDA, 0xB6, 0xD2, 0xB9, 0xE5, 0xDD, 0x56, 0x2A, 0x5B, 0xEE,
+    0x98, 0x63, 0xEC, 0xE0, 0x03, 0x09, 0x08, 0x10, 0xC8, 0x0F, 0x43, 0x9D,
+    0x99, 0x02, 0x03, 0xDC, 0x00, 0x85, 0x34, 0x98, 0xF4, 0x02, 0xDB, 0x42,
+    0x45, 0x5A, 0x24, 0x82, 0x05, 0x0F, 0x03, 0x11, 0x8E, 0xC2,
+];
+
+/// Writes `data()` to `in.csv`, and compressed to `in.csv.gz` (with the
+/// `gzip` program) and `in.csv.zst`.
+fn create_inputs(wrk: &Workdir) {
+    fs::write(wrk.path("in.csv"), data()).unwrap();
+    let status = process::Command::new("gzip")
+        .arg("-k").arg("-n").arg("-f").arg(wrk.path("in.csv"))
+        .status()
+        .unwrap();
+    assert!(status.success());
+    #[cfg(feature = "zstd")]
+    fs::write(wrk.path("in.csv.zst"), DATA_ZST).unwrap();
+}
+
+fn compressed_inputs() -> Vec<&'static str> {
+    let mut inputs = vec!["in.csv.gz"];
+    if cfg!(feature = "zstd") {
+        inputs.push("in.csv.zst");
+    }
+    inputs
+}
+
+#[test]
+fn compressed_same_output() {
+    let wrk = Workdir::new("compressed_same_output");
+    create_inputs(&wrk);
+
+    let run = |input: &str| -> String {
+        let mut cmd = wrk.command("sort");
+        cmd.arg("-s").arg("population").arg("-N").arg(input);
+        wrk.stdout(&mut cmd)
+    };
+    let expected = run("in.csv");
+    assert!(expected.starts_with("city,country"));
+    for input in compressed_inputs() {
+        assert_eq!(run(input), expected, "input {}", input);
+    }
+}
+
+#[test]
+fn compressed_stdin() {
+    let wrk = Workdir::new("compressed_stdin");
+    create_inputs(&wrk);
+
+    for input in compressed_inputs() {
+        let mut cmd = wrk.command("search");
+        cmd.arg("-s").arg("country").arg("^JP$")
+           .stdin(fs::File::open(wrk.path(input)).unwrap());
+        let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+        assert_eq!(got.len(), 3, "input {}", input);
+        assert_eq!(got[1][0], "Tokyo");
+        assert_eq!(got[2][0], "Osaka");
+    }
+}
+
+#[cfg(feature = "zstd")]
+#[test]
+fn compressed_zstd_no_index() {
+    let wrk = Workdir::new("compressed_zstd_no_index");
+    create_inputs(&wrk);
+
+    let mut cmd = wrk.command("index");
+    cmd.arg("in.csv.zst");
+    let output = cmd.output().unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr)
+                .contains("Cannot use an index with zstd compressed data"));
+}
+
+#[cfg(feature = "zstd")]
+#[test]
+fn compressed_zstd_corrupt() {
+    let wrk = Workdir::new("compressed_zstd_corrupt");
+    let mut data = DATA_ZST.to_vec();
+    let last = data.len() - 1;
+    data[last] ^= 1;
+    fs::write(wrk.path("in.csv.zst"), data).unwrap();
+
+    let mut cmd = wrk.command("count");
+    cmd.arg("in.csv.zst");
+    let output = cmd.output().unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr)
+                .contains("invalid zstd data: checksum mismatch"));
+}
+
+#[test]
+fn compressed_truncated() {
+    let wrk = Workdir::new("compressed_truncated");
+    create_inputs(&wrk);
+
+    for input in compressed_inputs() {
+        let data = fs::read(wrk.path(input)).unwrap();
+        let format = if input.ends_with(".gz") { "gzip" } else { "zstd" };
+        // Cut off in the compressed data, and in the trailer with the
+        // checksum.
+        for &len in &[data.len() / 2, data.len() - 2] {
+            let name = format!("cut{}", input.trim_start_matches("in"));
+            fs::write(wrk.path(&name), &data[..len]).unwrap();
+
+            let mut cmd = wrk.command("count");
+            cmd.arg(&name);
+            let output = cmd.output().unwrap();
+            assert_eq!(output.status.code(), Some(4), "{} {}", input, len);
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            assert!(stderr.contains(&format!("invalid {} data", format)),
+                    "{} {}: {}", input, len, stderr);
+        }
+    }
+}
+
+/// Returns the data in the gzip compressed file `name`, decompressed with
+/// the `gzip` program.
+fn gunzip(wrk: &Workdir, name: &str) -> String {
+    let output = process::Command::new("gzip")
+        .arg("-dc").arg(wrk.path(name))
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn compressed_output_gzip() {
+    let wrk = Workdir::new("compressed_output_gzip");
+    create_inputs(&wrk);
+
+    let mut cmd = wrk.command("sort");
+    cmd.arg("-s").arg("population").arg("-N").arg("in.csv");
+    let expected: String = wrk.stdout(&mut cmd);
+
+    let mut cmd = wrk.command("sort");
+    cmd.arg("-s").arg("population").arg("-N").arg("in.csv.gz")
+       .arg("-o").arg("out.csv.gz");
+    wrk.run(&mut cmd);
+    assert_eq!(gunzip(&wrk, "out.csv.gz").trim(), expected);
+
+    // Without any records, the output is still complete.
+    let mut cmd = wrk.command("search");
+    cmd.arg("nothing matches this").arg("in.csv")
+       .arg("-o").arg("empty.csv.gz");
+    wrk.run(&mut cmd);
+    assert_eq!(gunzip(&wrk, "empty.csv.gz"),
+               "city,country,population,latitude,longitude\n");
+}
+
+#[cfg(feature = "zstd")]
+#[test]
+fn compressed_output_zstd() {
+    let wrk = Workdir::new("compressed_output_zstd");
+    create_inputs(&wrk);
+
+    let mut cmd = wrk.command("select");
+    cmd.arg("city,population").arg("in.csv.zst").arg("-o").arg("out.csv.zst");
+    wrk.run(&mut cmd);
+    assert_eq!(&fs::read(wrk.path("out.csv.zst")).unwrap()[..4],
+               &[0x28, 0xB5, 0x2F, 0xFD]);
+
+    let mut cmd = wrk.command("cat");
+    cmd.arg("rows").arg("out.csv.zst");
+    let got: String = wrk.stdout(&mut cmd);
+    let mut cmd = wrk.command("select");
+    cmd.arg("city,population").arg("in.csv");
+    let expected: String = wrk.stdout(&mut cmd);
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn compressed_output_level() {
+    let wrk = Workdir::new("compressed_output_level");
+    create_inputs(&wrk);
+
+    for level in &["0", "9"] {
+        let mut cmd = wrk.command("cat");
+        cmd.env("XSV_COMPRESS_LEVEL", level)
+           .arg("rows").arg("in.csv").arg("in.csv")
+           .arg("-o").arg(format!("out{}.csv.gz", level));
+        wrk.run(&mut cmd);
+    }
+    let stored = fs::metadata(wrk.path("out0.csv.gz")).unwrap().len();
+    let best = fs::metadata(wrk.path("out9.csv.gz")).unwrap().len();
+    assert!(best < stored, "{} >= {}", best, stored);
+    assert_eq!(gunzip(&wrk, "out0.csv.gz"), gunzip(&wrk, "out9.csv.gz"));
+
+    let mut cmd = wrk.command("cat");
+    cmd.env("XSV_COMPRESS_LEVEL", "10")
+       .arg("rows").arg("in.csv").arg("-o").arg("out.csv.gz");
+    let output = cmd.output().unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr)
+                .contains("Invalid XSV_COMPRESS_LEVEL '10'"));
+}
+
+#[test]
+fn compressed_output_split() {
+    let wrk = Workdir::new("compressed_output_split");
+    create_inputs(&wrk);
+
+    let mut cmd = wrk.command("split");
+    cmd.arg("--size").arg("4").arg("--filename").arg("{}.csv.gz")
+       .arg(".").arg("in.csv");
+    wrk.run(&mut cmd);
+    let header = "city,country,population,latitude,longitude\n";
+    assert_eq!(gunzip(&wrk, "8.csv.gz"),
+               format!("{}{}", header, "Tokyo,JP,13960000,35.676,139.65\n\
+                                         Osaka,JP,2753862,34.694,135.502\n"));
+}
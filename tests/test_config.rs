@@ -0,0 +1,88 @@
+use std::fs;
+
+use workdir::Workdir;
+
+fn setup(name: &str, config: &str) -> Workdir {
+    let wrk = Workdir::new(name);
+    fs::write(wrk.path("in.csv"), "a;b\n1;2\n").unwrap();
+    fs::create_dir_all(wrk.path("xsv")).unwrap();
+    fs::write(wrk.path("xsv/config.toml"), config).unwrap();
+    wrk
+}
+
+#[test]
+fn config_file_delimiter() {
+    let wrk = setup("config_file_delimiter", "delimiter = \";\"\n");
+    let mut cmd = wrk.command("select");
+    cmd.args(["b", "in.csv"]);
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got, vec![svec!["b"], svec!["2"]]);
+}
+
+#[test]
+fn config_env_wins_over_file() {
+    let wrk = setup("config_env_wins_over_file",
+                    "delimiter = \"|\"\nno-headers = true\n");
+    let mut cmd = wrk.command("select");
+    cmd.args(["b", "in.csv"])
+       .env("XSV_DEFAULT_DELIMITER", ";")
+       .env("XSV_DEFAULT_NO_HEADERS", "0");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got, vec![svec!["b"], svec!["2"]]);
+}
+
+#[test]
+fn config_flag_wins_over_env() {
+    let wrk = setup("config_flag_wins_over_env", "delimiter = \"|\"\n");
+    let mut cmd = wrk.command("select");
+    cmd.args(["1", "--delimiter", ",", "in.csv"])
+       .env("XSV_DEFAULT_DELIMITER", ";");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got, vec![svec!["a;b"], svec!["1;2"]]);
+
+    let mut cmd = wrk.command("select");
+    cmd.args(["-d,", "1", "in.csv"]);
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got, vec![svec!["a;b"], svec!["1;2"]]);
+}
+
+#[test]
+fn config_global_defaults() {
+    let wrk = setup("config_global_defaults", "trim = \"all\"\n");
+    fs::write(wrk.path("in.csv"), "a, b\n1, 2\n").unwrap();
+    let mut cmd = wrk.command("select");
+    cmd.args(["b", "in.csv"]);
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got, vec![svec!["b"], svec!["2"]]);
+
+    // Headers are trimmed with --trim headers, but the fields aren't.
+    let mut cmd = wrk.command("--trim");
+    cmd.args(["headers", "select", "b", "in.csv"]);
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got, vec![svec!["b"], svec![" 2"]]);
+}
+
+#[test]
+fn config_show() {
+    let wrk = setup("config_show", "delimiter = \";\"\nquote = \"'\"\n");
+    let mut cmd = wrk.command("config");
+    cmd.arg("--show").env("XSV_DEFAULT_QUOTE", "\t");
+    let got: String = wrk.stdout(&mut cmd);
+    let lines: Vec<&str> = got.lines().skip(1).take(3).collect();
+    assert_eq!(lines, vec![
+        "delimiter = \";\"  # from config file",
+        "quote = \"\\t\"  # from XSV_DEFAULT_QUOTE",
+        "# no-headers is not set (XSV_DEFAULT_NO_HEADERS)",
+    ]);
+}
+
+#[test]
+fn config_invalid() {
+    let wrk = setup("config_invalid", "delimeter = \";\"\n");
+    let mut cmd = wrk.command("count");
+    cmd.arg("in.csv");
+    wrk.assert_err(&mut cmd);
+    let stderr = String::from_utf8(cmd.output().unwrap().stderr).unwrap();
+    assert!(stderr.contains("config.toml:1: unknown setting 'delimeter'."),
+            "{}", stderr);
+}
@@ -1,3 +1,5 @@
+use std::fs;
+
 use {CsvData, qcheck};
 use workdir::Workdir;
 
@@ -60,3 +62,137 @@ fn prop_count_indexed_headers() {
     }
     qcheck(p as fn(CsvData) -> bool);
 }
+
+#[test]
+fn prop_count_fast() {
+    fn p(rows: CsvData) -> bool {
+        let expected_count =
+            if rows.is_empty() { 0 } else { rows.len() - 1 };
+
+        let wrk = Workdir::new("prop_count_fast");
+        wrk.create("in.csv", rows);
+
+        let mut cmd = wrk.command("count");
+        cmd.arg("--fast").arg("in.csv");
+
+        let got_count: usize = wrk.stdout(&mut cmd);
+        rassert_eq!(got_count, expected_count)
+    }
+    qcheck(p as fn(CsvData) -> bool);
+}
+
+#[test]
+fn count_fast() {
+    let wrk = Workdir::new("count_fast");
+    wrk.create("in.csv", vec![
+        svec!["h1", "h2"],
+        svec!["a", "b"],
+        svec!["c", "d"],
+    ]);
+
+    let mut cmd = wrk.command("count");
+    cmd.arg("--fast").arg("in.csv");
+
+    let got: usize = wrk.stdout(&mut cmd);
+    assert_eq!(got, 2);
+}
+
+#[test]
+fn count_fast_empty_lines() {
+    let wrk = Workdir::new("count_fast_empty_lines");
+    fs::write(wrk.path("in.csv"), "\nh1,h2\n\na,b\n\n\nc,d").unwrap();
+
+    let mut cmd = wrk.command("count");
+    cmd.arg("--fast").arg("in.csv");
+
+    let got: usize = wrk.stdout(&mut cmd);
+    assert_eq!(got, 2);
+}
+
+#[test]
+fn count_fast_quoted_newlines() {
+    let wrk = Workdir::new("count_fast_quoted_newlines");
+    wrk.create("in.csv", vec![
+        svec!["h1", "h2"],
+        svec!["a", "b"],
+        svec!["multi\nline", "d"],
+        svec!["e", "another\n\nmulti\nline"],
+        svec!["g", "h"],
+    ]);
+
+    let mut cmd = wrk.command("count");
+    cmd.arg("--fast").arg("in.csv");
+
+    let got: usize = wrk.stdout(&mut cmd);
+    assert_eq!(got, 4);
+}
+
+#[test]
+fn count_fast_crlf() {
+    let wrk = Workdir::new("count_fast_crlf");
+    fs::write(wrk.path("in.csv"), "h1,h2\r\na,b\r\n\r\nc,d\r\n").unwrap();
+
+    let mut cmd = wrk.command("count");
+    cmd.arg("--fast").arg("--no-headers").arg("in.csv");
+
+    let got: usize = wrk.stdout(&mut cmd);
+    assert_eq!(got, 3);
+}
+
+#[test]
+fn count_fast_quote_after_many_blocks() {
+    let wrk = Workdir::new("count_fast_quote_after_many_blocks");
+    let mut rows = vec![svec!["h1", "h2"]];
+    for i in 0..50000 {
+        rows.push(vec![i.to_string(), "abcdefghijklmnopqrstuvwxyz".to_string()]);
+    }
+    rows.push(svec!["multi\nline", "x"]);
+    rows.push(svec!["y", "z"]);
+    wrk.create("in.csv", rows);
+
+    let mut cmd = wrk.command("count");
+    cmd.arg("--fast").arg("in.csv");
+
+    let got: usize = wrk.stdout(&mut cmd);
+    assert_eq!(got, 50002);
+}
+
+#[test]
+fn count_human_readable() {
+    let wrk = Workdir::new("count_human_readable");
+    let rows: Vec<Vec<String>> =
+        (0..1235).map(|i| vec![i.to_string()]).collect();
+    wrk.create("in.csv", rows);
+
+    let mut cmd = wrk.command("count");
+    cmd.arg("--human-readable").arg("--no-headers").arg("in.csv");
+    let got: String = wrk.stdout(&mut cmd);
+    assert_eq!(got, "1,235");
+
+    let mut cmd = wrk.command("count");
+    cmd.arg("--si").arg("--no-headers").arg("in.csv");
+    let got: String = wrk.stdout(&mut cmd);
+    assert_eq!(got, "1.2k");
+}
+
+#[test]
+fn count_width() {
+    let wrk = Workdir::new("count_width").flexible(true);
+    wrk.create("in.csv", vec![
+        svec!["h1", "h2"],
+        svec!["a", "bcd"],
+        svec!["e"],
+        svec!["xyz", "w", "123456"],
+    ]);
+
+    let mut cmd = wrk.command("count");
+    cmd.arg("--width").arg("in.csv");
+
+    let got: String = wrk.stdout(&mut cmd);
+    let expected = "\
+count       3
+max_length  12
+avg_length  6.00
+max_fields  3";
+    assert_eq!(got, expected);
+}
@@ -0,0 +1,94 @@
+use workdir::Workdir;
+
+#[test]
+fn datefmt_fallback_formats() {
+    let wrk = Workdir::new("datefmt_fallback_formats");
+    wrk.create("in.csv", vec![
+        svec!["id", "when"],
+        svec!["1", "03/12/24"],
+        svec!["2", "2024-03-12T00:00:00Z"],
+        svec!["3", "1710201600"],
+        svec!["4", "1710201600000"],
+        svec!["5", "12 March 2024"],
+        svec!["6", ""],
+        svec!["7", "soon"],
+    ]);
+
+    let mut cmd = wrk.command("datefmt");
+    cmd.arg("-s").arg("when").arg("in.csv");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["id", "when"],
+        svec!["1", "2024-03-12"],
+        svec!["2", "2024-03-12"],
+        svec!["3", "2024-03-12"],
+        svec!["4", "2024-03-12"],
+        svec!["5", "2024-03-12"],
+        svec!["6", ""],
+        svec!["7", "soon"],
+    ];
+    assert_eq!(got, expected);
+
+    let output = cmd.output().unwrap();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("when: 5 parsed, 1 failed"), "{}", stderr);
+}
+
+#[test]
+fn datefmt_input_format_suffix() {
+    let wrk = Workdir::new("datefmt_input_format_suffix");
+    wrk.create("in.csv", vec![
+        svec!["when"],
+        svec!["12.03.2024"],
+        svec!["03/12/24"],
+    ]);
+
+    let mut cmd = wrk.command("datefmt");
+    cmd.arg("-s").arg("when")
+       .arg("--input-format").arg("%d.%m.%Y")
+       .arg("--input-format").arg("%d/%m/%y")
+       .arg("--output-format").arg("%d %b %Y")
+       .arg("--suffix").arg("_fmt")
+       .arg("in.csv");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["when", "when_fmt"],
+        svec!["12.03.2024", "12 Mar 2024"],
+        svec!["03/12/24", "03 Dec 2024"],
+    ];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn datefmt_utc() {
+    let wrk = Workdir::new("datefmt_utc");
+    wrk.create("in.csv", vec![
+        svec!["when"],
+        svec!["2024-03-12T23:30:00-02:00"],
+    ]);
+
+    let mut cmd = wrk.command("datefmt");
+    cmd.arg("-s").arg("when").arg("--output-format").arg("%FT%T%z")
+       .arg("in.csv");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got[1], svec!["2024-03-12T23:30:00-0200"]);
+
+    cmd.arg("--utc");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got[1], svec!["2024-03-13T01:30:00+0000"]);
+}
+
+#[test]
+fn datefmt_strict() {
+    let wrk = Workdir::new("datefmt_strict");
+    wrk.create("in.csv", vec![svec!["when"], svec!["soon"]]);
+
+    let mut cmd = wrk.command("datefmt");
+    cmd.arg("-s").arg("when").arg("--strict-empty").arg("in.csv");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got, vec![svec!["when"], svec![""]]);
+
+    let mut cmd = wrk.command("datefmt");
+    cmd.arg("-s").arg("when").arg("--strict").arg("in.csv");
+    wrk.assert_err(&mut cmd);
+}
@@ -0,0 +1,122 @@
+use workdir::Workdir;
+
+fn data() -> Vec<Vec<String>> {
+    vec![
+        svec!["id", "name"],
+        svec!["1", "a"],
+        svec!["2", "b"],
+        svec!["1", "c"],
+        svec!["2", "b"],
+        svec!["3", "a"],
+    ]
+}
+
+#[test]
+fn dedup_whole_record() {
+    let wrk = Workdir::new("dedup_whole_record");
+    wrk.create("in.csv", data());
+
+    let mut cmd = wrk.command("dedup");
+    cmd.arg("in.csv");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["id", "name"],
+        svec!["1", "a"],
+        svec!["2", "b"],
+        svec!["1", "c"],
+        svec!["3", "a"],
+    ];
+    assert_eq!(got, expected);
+
+    let output = wrk.output(&mut cmd);
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert_eq!(stderr.trim(), "removed 1 duplicate record(s)");
+}
+
+#[test]
+fn dedup_select() {
+    let wrk = Workdir::new("dedup_select");
+    wrk.create("in.csv", data());
+
+    let mut cmd = wrk.command("dedup");
+    cmd.arg("-s").arg("id").arg("in.csv");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["id", "name"],
+        svec!["1", "a"],
+        svec!["2", "b"],
+        svec!["3", "a"],
+    ];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn dedup_keep_last() {
+    let wrk = Workdir::new("dedup_keep_last");
+    wrk.create("in.csv", data());
+
+    let mut cmd = wrk.command("dedup");
+    cmd.arg("-s").arg("name").arg("--keep").arg("last").arg("in.csv");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["id", "name"],
+        svec!["1", "c"],
+        svec!["2", "b"],
+        svec!["3", "a"],
+    ];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn dedup_dupes_output() {
+    let wrk = Workdir::new("dedup_dupes_output");
+    wrk.create("in.csv", data());
+
+    let mut cmd = wrk.command("dedup");
+    cmd.arg("-s").arg("id").arg("--dupes-output").arg("dupes.csv")
+       .arg("in.csv");
+    wrk.run(&mut cmd);
+
+    let got: String = wrk.from_str(&wrk.path("dupes.csv"));
+    assert_eq!(got, "id,name\n1,c\n2,b\n");
+}
+
+#[test]
+fn dedup_sorted() {
+    let wrk = Workdir::new("dedup_sorted");
+    wrk.create("in.csv", vec![
+        svec!["id", "name"],
+        svec!["1", "a"],
+        svec!["1", "b"],
+        svec!["2", "c"],
+        svec!["1", "d"],
+    ]);
+
+    let mut cmd = wrk.command("dedup");
+    cmd.arg("--sorted").arg("-s").arg("id").arg("in.csv");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got, vec![
+        svec!["id", "name"], svec!["1", "a"], svec!["2", "c"], svec!["1", "d"],
+    ]);
+
+    let mut cmd = wrk.command("dedup");
+    cmd.arg("--sorted").arg("--keep").arg("last").arg("-s").arg("id")
+       .arg("in.csv");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got, vec![
+        svec!["id", "name"], svec!["1", "b"], svec!["2", "c"], svec!["1", "d"],
+    ]);
+}
+
+#[test]
+fn dedup_quoting() {
+    let wrk = Workdir::new("dedup_quoting");
+    ::std::fs::write(
+        wrk.path("in.csv"), "h\n\"a,b\"\n\"a\"\"b\"\nx\n\"x\"\n\"a,b\"\n",
+    ).unwrap();
+
+    let mut cmd = wrk.command("dedup");
+    cmd.arg("in.csv");
+    let got: String = wrk.stdout(&mut cmd);
+    assert_eq!(got, "h\n\"a,b\"\n\"a\"\"b\"\nx");
+}
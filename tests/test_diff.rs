@@ -0,0 +1,149 @@
+use workdir::Workdir;
+
+fn left() -> Vec<Vec<String>> {
+    vec![
+        svec!["id", "name", "qty"],
+        svec!["1", "a", "5"],
+        svec!["2", "b", "6"],
+        svec!["3", "c", "7"],
+    ]
+}
+
+fn right() -> Vec<Vec<String>> {
+    vec![
+        svec!["qty", "id", "name"],
+        svec!["5", "1", "a"],
+        svec!["60", "2", "b"],
+        svec!["8", "4", "d"],
+    ]
+}
+
+#[test]
+fn diff_key() {
+    let wrk = Workdir::new("diff_key");
+    wrk.create("left.csv", left());
+    wrk.create("right.csv", right());
+
+    let mut cmd = wrk.command("diff");
+    cmd.arg("-s").arg("id").arg("left.csv").arg("right.csv");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["change", "id", "name", "qty"],
+        svec!["modified-old", "2", "b", "6"],
+        svec!["modified-new", "2", "b", "60"],
+        svec!["added", "4", "d", "8"],
+        svec!["removed", "3", "c", "7"],
+    ];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn diff_modified_arrow() {
+    let wrk = Workdir::new("diff_modified_arrow");
+    wrk.create("left.csv", vec![svec!["id", "v"], svec!["1", "x -> y"]]);
+    wrk.create("right.csv", vec![svec!["id", "v"], svec!["1", "z"]]);
+
+    // The old and new values are kept apart, whatever they contain.
+    let mut cmd = wrk.command("diff");
+    cmd.arg("-s").arg("id").arg("left.csv").arg("right.csv");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["change", "id", "v"],
+        svec!["modified-old", "1", "x -> y"],
+        svec!["modified-new", "1", "z"],
+    ];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn diff_sorted() {
+    let wrk = Workdir::new("diff_sorted");
+    wrk.create("left.csv", left());
+    wrk.create("right.csv", right());
+
+    let mut cmd = wrk.command("diff");
+    cmd.arg("--sorted").arg("-s").arg("id").arg("left.csv").arg("right.csv");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["change", "id", "name", "qty"],
+        svec!["modified-old", "2", "b", "6"],
+        svec!["modified-new", "2", "b", "60"],
+        svec!["removed", "3", "c", "7"],
+        svec!["added", "4", "d", "8"],
+    ];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn diff_sorted_unsorted_input() {
+    let wrk = Workdir::new("diff_sorted_unsorted_input");
+    wrk.create("left.csv", vec![svec!["id"], svec!["2"], svec!["1"]]);
+    wrk.create("right.csv", vec![svec!["id"], svec!["1"]]);
+
+    let mut cmd = wrk.command("diff");
+    cmd.arg("--sorted").arg("left.csv").arg("right.csv");
+    wrk.assert_err(&mut cmd);
+}
+
+#[test]
+fn diff_whole_record() {
+    let wrk = Workdir::new("diff_whole_record");
+    wrk.create("left.csv", left());
+    wrk.create("right.csv", right());
+
+    let mut cmd = wrk.command("diff");
+    cmd.arg("left.csv").arg("right.csv");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["change", "id", "name", "qty"],
+        svec!["added", "2", "b", "60"],
+        svec!["added", "4", "d", "8"],
+        svec!["removed", "2", "b", "6"],
+        svec!["removed", "3", "c", "7"],
+    ];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn diff_json() {
+    let wrk = Workdir::new("diff_json");
+    wrk.create("left.csv", left());
+    wrk.create("right.csv", right());
+
+    let mut cmd = wrk.command("diff");
+    cmd.arg("--json").arg("-s").arg("id").arg("left.csv").arg("right.csv");
+    let got: String = wrk.stdout(&mut cmd);
+    let expected = "\
+{\"change\":\"modified\",\"key\":{\"id\":\"2\"},\
+\"changes\":{\"qty\":{\"old\":\"6\",\"new\":\"60\"}}}
+{\"change\":\"added\",\"record\":{\"id\":\"4\",\"name\":\"d\",\"qty\":\"8\"}}
+{\"change\":\"removed\",\"record\":{\"id\":\"3\",\"name\":\"c\",\"qty\":\"7\"}}";
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn diff_different_headers() {
+    let wrk = Workdir::new("diff_different_headers");
+    wrk.create("left.csv", left());
+    wrk.create("right.csv", vec![
+        svec!["id", "name", "extra"],
+        svec!["1", "a", "x"],
+        svec!["2", "z", "y"],
+    ]);
+
+    let mut cmd = wrk.command("diff");
+    cmd.arg("-s").arg("id").arg("left.csv").arg("right.csv");
+    wrk.assert_err(&mut cmd);
+
+    let mut cmd = wrk.command("diff");
+    cmd.arg("-s").arg("id").arg("--ignore-extra-columns")
+       .arg("left.csv").arg("right.csv");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["change", "id", "name"],
+        svec!["modified-old", "2", "b"],
+        svec!["modified-new", "2", "z"],
+        svec!["removed", "3", "c"],
+    ];
+    assert_eq!(got, expected);
+}
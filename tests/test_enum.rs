@@ -0,0 +1,85 @@
+use workdir::Workdir;
+
+fn data() -> Vec<Vec<String>> {
+    vec![svec!["name"], svec!["a"], svec!["b"], svec!["c"]]
+}
+
+#[test]
+fn enum_default() {
+    let wrk = Workdir::new("enum_default");
+    wrk.create("in.csv", data());
+
+    let mut cmd = wrk.command("enum");
+    cmd.arg("in.csv");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["index", "name"],
+        svec!["0", "a"],
+        svec!["1", "b"],
+        svec!["2", "c"],
+    ];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn enum_append_start_increment() {
+    let wrk = Workdir::new("enum_append_start_increment");
+    wrk.create("in.csv", data());
+
+    let mut cmd = wrk.command("enum");
+    cmd.arg("--append").arg("--start").arg("10").arg("--increment").arg("-5")
+       .arg("-c").arg("n").arg("in.csv");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["name", "n"],
+        svec!["a", "10"],
+        svec!["b", "5"],
+        svec!["c", "0"],
+    ];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn enum_no_headers_constant() {
+    let wrk = Workdir::new("enum_no_headers_constant");
+    wrk.create("in.csv", data());
+
+    let mut cmd = wrk.command("enum");
+    cmd.arg("--no-headers").arg("--constant").arg("x").arg("in.csv");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["x", "name"],
+        svec!["x", "a"],
+        svec!["x", "b"],
+        svec!["x", "c"],
+    ];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn enum_uuid() {
+    let wrk = Workdir::new("enum_uuid");
+    wrk.create("in.csv", data());
+
+    let mut cmd = wrk.command("enum");
+    cmd.arg("--uuid").arg("in.csv");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got.len(), 4);
+    for row in &got[1..] {
+        let id = &row[0];
+        assert_eq!(id.len(), 36);
+        assert_eq!(&id[14..15], "4");
+        assert_eq!(id.matches('-').count(), 4);
+    }
+    assert!(got[1][0] != got[2][0]);
+}
+
+#[test]
+fn enum_name_collision() {
+    let wrk = Workdir::new("enum_name_collision");
+    wrk.create("in.csv", data());
+
+    let mut cmd = wrk.command("enum");
+    cmd.arg("-c").arg("name").arg("in.csv");
+    wrk.assert_err(&mut cmd);
+}
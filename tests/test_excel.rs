@@ -0,0 +1,170 @@
+use std::fs;
+use std::process;
+
+use workdir::Workdir;
+
+/// Creates a zip archive called `name` with the given entries.
+fn create_zip(wrk: &Workdir, name: &str, entries: &[(&str, &str)]) {
+    let dir = wrk.path(&format!("{}.d", name));
+    for &(path, data) in entries {
+        let path = dir.join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, data).unwrap();
+    }
+    let status = process::Command::new("zip")
+        .current_dir(&dir)
+        .arg("-q").arg("-r").arg(wrk.path(name)).arg(".")
+        .status()
+        .unwrap();
+    assert!(status.success());
+}
+
+fn create_xlsx(wrk: &Workdir, name: &str) {
+    create_zip(wrk, name, &[
+        ("xl/workbook.xml", "\
+<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>
+<workbook xmlns:r=\"rels\"><workbookPr/><sheets>\
+<sheet name=\"Data\" sheetId=\"1\" r:id=\"rId1\"/>\
+<sheet name=\"Other &amp; more\" sheetId=\"2\" r:id=\"rId2\"/>\
+</sheets></workbook>"),
+        ("xl/_rels/workbook.xml.rels", "\
+<Relationships>\
+<Relationship Id=\"rId1\" Target=\"worksheets/sheet1.xml\"/>\
+<Relationship Id=\"rId2\" Target=\"/xl/worksheets/sheet2.xml\"/>\
+</Relationships>"),
+        ("xl/sharedStrings.xml", "\
+<sst><si><t>name</t></si><si><t>when</t></si>\
+<si><r><t>Hello, </t></r><r><t xml:space=\"preserve\">\"world\"</t></r></si>\
+</sst>"),
+        ("xl/styles.xml", "\
+<styleSheet><numFmts count=\"1\">\
+<numFmt numFmtId=\"164\" formatCode=\"yyyy\\-mm\\-dd hh:mm\"/></numFmts>\
+<cellXfs count=\"3\"><xf numFmtId=\"0\"/><xf numFmtId=\"14\"/>\
+<xf numFmtId=\"164\"/></cellXfs></styleSheet>"),
+        ("xl/worksheets/sheet1.xml", "\
+<worksheet><sheetData>\
+<row r=\"1\"><c r=\"A1\" t=\"s\"><v>0</v></c><c r=\"B1\" t=\"s\"><v>1</v></c>\
+<c r=\"C1\" t=\"inlineStr\"><is><t>n</t></is></c></row>\
+<row r=\"2\"><c r=\"A2\" t=\"s\"><v>2</v></c><c r=\"B2\" s=\"1\"><v>45306</v></c>\
+<c r=\"C2\"><v>1.0000000000000001E-5</v></c></row>\
+<row r=\"4\"><c r=\"A4\" t=\"b\"><v>1</v></c>\
+<c r=\"B4\" s=\"2\"><v>45306.5</v></c><c r=\"C4\"><v>0.30000000000000004</v></c>\
+<c r=\"E4\" s=\"1\"/></row>\
+<row r=\"6\"><c r=\"A6\" s=\"1\"/></row>\
+</sheetData></worksheet>"),
+        ("xl/worksheets/sheet2.xml", "\
+<worksheet><sheetData><row><c><v>1</v></c><c t=\"str\"><f>A1</f><v>x</v></c>\
+</row></sheetData></worksheet>"),
+    ]);
+}
+
+#[test]
+fn excel_xlsx() {
+    let wrk = Workdir::new("excel_xlsx");
+    create_xlsx(&wrk, "in.xlsx");
+
+    let mut cmd = wrk.command("excel");
+    cmd.arg("in.xlsx");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["name", "when", "n"],
+        svec!["Hello, \"world\"", "2024-01-15", "0.00001"],
+        svec!["", "", ""],
+        svec!["true", "2024-01-15T12:00:00", "0.30000000000000004"],
+    ];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn excel_float_precision() {
+    let wrk = Workdir::new("excel_float_precision");
+    create_xlsx(&wrk, "in.xlsx");
+
+    let mut cmd = wrk.command("excel");
+    cmd.arg("--float-precision").arg("2").arg("in.xlsx");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got[1][2], "0");
+    assert_eq!(got[3][2], "0.3");
+}
+
+#[test]
+fn excel_sheets() {
+    let wrk = Workdir::new("excel_sheets");
+    create_xlsx(&wrk, "in.xlsx");
+
+    let mut cmd = wrk.command("excel");
+    cmd.arg("--list-sheets").arg("in.xlsx");
+    let got: String = wrk.stdout(&mut cmd);
+    assert_eq!(got, "Data\nOther & more");
+
+    let mut cmd = wrk.command("excel");
+    cmd.arg("--sheet").arg("Other & more").arg("in.xlsx");
+    let got: String = wrk.stdout(&mut cmd);
+    assert_eq!(got, "1,x");
+
+    let mut cmd = wrk.command("excel");
+    cmd.arg("--sheet").arg("2").arg("in.xlsx");
+    let got: String = wrk.stdout(&mut cmd);
+    assert_eq!(got, "1,x");
+
+    let mut cmd = wrk.command("excel");
+    cmd.arg("--sheet").arg("3").arg("in.xlsx");
+    wrk.assert_err(&mut cmd);
+}
+
+#[test]
+fn excel_ods() {
+    let wrk = Workdir::new("excel_ods");
+    create_zip(&wrk, "in.ods", &[
+        ("mimetype", "application/vnd.oasis.opendocument.spreadsheet"),
+        ("content.xml", "\
+<?xml version=\"1.0\" encoding=\"UTF-8\"?>
+<office:document-content xmlns:office=\"o\" xmlns:table=\"t\" xmlns:text=\"x\">\
+<office:body><office:spreadsheet><table:table table:name=\"S1\">\
+<table:table-row>\
+<table:table-cell office:value-type=\"string\">\
+<text:p>a<text:s text:c=\"2\"/>b</text:p><text:p>c</text:p></table:table-cell>\
+<table:table-cell table:number-columns-repeated=\"2\"/>\
+<table:table-cell office:value-type=\"float\" office:value=\"1e-7\">\
+<text:p>1E-07</text:p></table:table-cell>\
+</table:table-row>\
+<table:table-row table:number-rows-repeated=\"2\">\
+<table:table-cell office:value-type=\"date\" office:date-value=\"2024-01-15\"/>\
+<table:table-cell office:value-type=\"time\" office:time-value=\"PT08H30M00S\"/>\
+<table:table-cell office:value-type=\"boolean\" office:boolean-value=\"true\">\
+<office:annotation><text:p>note</text:p></office:annotation>\
+<text:p>TRUE</text:p></table:table-cell>\
+<table:table-cell table:number-columns-repeated=\"16384\"/>\
+</table:table-row>\
+<table:table-row table:number-rows-repeated=\"1048573\">\
+<table:table-cell table:number-columns-repeated=\"16384\"/>\
+</table:table-row>\
+</table:table><table:table table:name=\"S2\"/>\
+</office:spreadsheet></office:body></office:document-content>"),
+    ]);
+
+    let mut cmd = wrk.command("excel");
+    cmd.arg("in.ods");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["a  b\nc", "", "", "0.0000001"],
+        svec!["2024-01-15", "08:30:00", "true", ""],
+        svec!["2024-01-15", "08:30:00", "true", ""],
+    ];
+    assert_eq!(got, expected);
+
+    let mut cmd = wrk.command("excel");
+    cmd.arg("--list-sheets").arg("in.ods");
+    let got: String = wrk.stdout(&mut cmd);
+    assert_eq!(got, "S1\nS2");
+}
+
+#[test]
+fn excel_not_a_spreadsheet() {
+    let wrk = Workdir::new("excel_not_a_spreadsheet");
+    wrk.create("in.csv", vec![svec!["a"]]);
+
+    let mut cmd = wrk.command("excel");
+    cmd.arg("in.csv");
+    wrk.assert_err(&mut cmd);
+}
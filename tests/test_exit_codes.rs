@@ -0,0 +1,129 @@
+use std::fs;
+use std::process::{self, Stdio};
+
+use workdir::Workdir;
+
+fn status(cmd: &mut process::Command) -> (i32, String) {
+    let o = cmd.output().unwrap();
+    (o.status.code().unwrap(), String::from_utf8(o.stderr).unwrap())
+}
+
+#[test]
+fn exit_code_usage() {
+    let wrk = Workdir::new("exit_code_usage");
+    let mut cmd = wrk.command("select");
+    cmd.args(["--no-such-option", "a"]);
+    assert_eq!(status(&mut cmd).0, 2);
+
+    let mut cmd = wrk.command("Select");
+    assert_eq!(status(&mut cmd).0, 2);
+
+    let mut cmd = wrk.command("select");
+    cmd.arg("--help");
+    assert_eq!(status(&mut cmd).0, 0);
+}
+
+#[test]
+fn exit_code_command_usage() {
+    // Invalid usage that the commands find themselves, after docopt.
+    let wrk = Workdir::new("exit_code_command_usage");
+    fs::write(wrk.path("in.csv"), "a,b\n1,2\n").unwrap();
+    let cases: &[&[&str]] = &[
+        &["select", "nope", "in.csv"],
+        &["count", "--fast", "--width", "in.csv"],
+        &["apply", "upper", "a", "--new-column", "x", "--suffix", "_y",
+          "in.csv"],
+        &["sniff", "--json", "--shell", "in.csv"],
+        &["template", "--output-dir", "out", "{a}", "in.csv"],
+        &["slice", "--end", "1", "--len", "1", "in.csv"],
+        &["map", "c = a +", "in.csv"],
+        &["cat", "rows", "-", "-"],
+    ];
+    for args in cases {
+        let mut cmd = wrk.command(args[0]);
+        cmd.args(&args[1..]);
+        let (code, stderr) = status(&mut cmd);
+        assert_eq!(code, 2, "{:?}: {}", args, stderr);
+    }
+}
+
+#[test]
+fn exit_code_io() {
+    let wrk = Workdir::new("exit_code_io");
+    let mut cmd = wrk.command("count");
+    cmd.arg("missing.csv");
+    let (code, stderr) = status(&mut cmd);
+    assert_eq!(code, 3);
+    assert!(stderr.contains("failed to open missing.csv"), "{}", stderr);
+}
+
+#[test]
+fn exit_code_csv() {
+    let wrk = Workdir::new("exit_code_csv");
+    fs::write(wrk.path("in.csv"), "a,b\n1,2\n3\n").unwrap();
+    let mut cmd = wrk.command("stats");
+    cmd.arg("in.csv");
+    let (code, stderr) = status(&mut cmd);
+    assert_eq!(code, 4);
+    assert!(stderr.contains("record 2 (line: 3, byte: 8)"), "{}", stderr);
+}
+
+#[test]
+fn exit_code_data() {
+    let wrk = Workdir::new("exit_code_data");
+    fs::write(wrk.path("in.csv"), "a,a\n1,2\n").unwrap();
+    let mut cmd = wrk.command("headers");
+    cmd.args(["--check", "in.csv"]);
+    assert_eq!(status(&mut cmd).0, 4);
+}
+
+#[test]
+fn exit_code_command_data() {
+    let wrk = Workdir::new("exit_code_command_data");
+    fs::write(wrk.path("unsorted.csv"), "n\n2\n1\n").unwrap();
+    fs::write(wrk.path("sorted.csv"), "n\n3\n").unwrap();
+    fs::write(wrk.path("in.csv"), "a,b\nx,1\n").unwrap();
+    fs::write(wrk.path("other.csv"), "a,c\n").unwrap();
+    fs::write(wrk.path("layout.csv"), "name,start,width\nid,1,3\n")
+        .unwrap();
+    fs::write(wrk.path("in.txt"), "12\n").unwrap();
+    let cases: &[&[&str]] = &[
+        &["merge", "-s", "n", "unsorted.csv", "sorted.csv"],
+        &["map", "c = a + 1", "in.csv"],
+        &["filter", "--strict", "a > 1", "in.csv"],
+        &["fixed", "--layout", "layout.csv", "in.txt"],
+        &["headers", "--diff", "in.csv", "other.csv"],
+    ];
+    for args in cases {
+        let mut cmd = wrk.command(args[0]);
+        cmd.args(&args[1..]);
+        let (code, stderr) = status(&mut cmd);
+        assert_eq!(code, 4, "{:?}: {}", args, stderr);
+    }
+}
+
+#[test]
+fn exit_code_broken_pipe() {
+    let wrk = Workdir::new("exit_code_broken_pipe");
+    let mut data = String::from("n\n");
+    for i in 0..100000 {
+        data.push_str(&format!("{}\n", i));
+    }
+    fs::write(wrk.path("in.csv"), data).unwrap();
+    let names: Vec<String> = (0..100000).map(|i| i.to_string()).collect();
+    fs::write(wrk.path("wide.csv"), names.join(",")).unwrap();
+
+    // 'headers' writes with println!, and 'cat' with a CSV writer.
+    for args in &[["cat", "rows", "in.csv"], ["headers", "-j", "wide.csv"]] {
+        let mut cmd = wrk.command(args[0]);
+        cmd.args(&args[1..])
+           .stdout(Stdio::piped())
+           .stderr(Stdio::piped());
+        let mut child = cmd.spawn().unwrap();
+        // Close the pipe before reading anything.
+        drop(child.stdout.take());
+        let o = child.wait_with_output().unwrap();
+        assert_eq!(o.status.code(), Some(141));
+        assert_eq!(String::from_utf8(o.stderr).unwrap(), "");
+    }
+}
@@ -0,0 +1,89 @@
+use workdir::Workdir;
+
+fn data() -> Vec<Vec<String>> {
+    vec![
+        svec!["id", "tags", "scores"],
+        svec!["1", "red;blue", "1;2"],
+        svec!["2", "", ""],
+        svec!["3", "green", "3"],
+    ]
+}
+
+#[test]
+fn explode() {
+    let wrk = Workdir::new("explode");
+    wrk.create("in.csv", data());
+
+    let mut cmd = wrk.command("explode");
+    cmd.arg("tags").arg(";").arg("in.csv");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["id", "tags", "scores"],
+        svec!["1", "red", "1;2"],
+        svec!["1", "blue", "1;2"],
+        svec!["2", "", ""],
+        svec!["3", "green", "3"],
+    ];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn explode_drop_empty_rename() {
+    let wrk = Workdir::new("explode_drop_empty_rename");
+    wrk.create("in.csv", data());
+
+    let mut cmd = wrk.command("explode");
+    cmd.arg("--drop-empty").arg("--rename").arg("tag")
+       .arg("tags").arg(";").arg("in.csv");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["id", "tag", "scores"],
+        svec!["1", "red", "1;2"],
+        svec!["1", "blue", "1;2"],
+        svec!["3", "green", "3"],
+    ];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn explode_zip() {
+    let wrk = Workdir::new("explode_zip");
+    wrk.create("in.csv", data());
+
+    let mut cmd = wrk.command("explode");
+    cmd.arg("--zip").arg("tags,scores").arg(";").arg("in.csv");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["id", "tags", "scores"],
+        svec!["1", "red", "1"],
+        svec!["1", "blue", "2"],
+        svec!["2", "", ""],
+        svec!["3", "green", "3"],
+    ];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn explode_zip_mismatch() {
+    let wrk = Workdir::new("explode_zip_mismatch");
+    wrk.create("in.csv", vec![svec!["a", "b"], svec!["1;2", "3"]]);
+
+    let mut cmd = wrk.command("explode");
+    cmd.arg("--zip").arg("a,b").arg(";").arg("in.csv");
+    wrk.assert_err(&mut cmd);
+
+    let mut cmd = wrk.command("explode");
+    cmd.arg("a,b").arg(";").arg("in.csv");
+    wrk.assert_err(&mut cmd);
+}
+
+#[test]
+fn explode_quoting() {
+    let wrk = Workdir::new("explode_quoting");
+    wrk.create("in.csv", vec![svec!["v"], svec!["a,b|\"c\"|d"]]);
+
+    let mut cmd = wrk.command("explode");
+    cmd.arg("v").arg("|").arg("in.csv");
+    let got: String = wrk.stdout(&mut cmd);
+    assert_eq!(got, "v\n\"a,b\"\n\"\"\"c\"\"\"\nd");
+}
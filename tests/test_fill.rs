@@ -0,0 +1,114 @@
+use workdir::Workdir;
+
+fn data() -> Vec<Vec<String>> {
+    vec![
+        svec!["id", "name", "city"],
+        svec!["1", "", "Paris"],
+        svec!["1", "a", ""],
+        svec!["2", "", ""],
+        svec!["2", "b", "Rome"],
+        svec!["2", "", ""],
+    ]
+}
+
+#[test]
+fn fill_forward() {
+    let wrk = Workdir::new("fill_forward");
+    wrk.create("in.csv", data());
+
+    let mut cmd = wrk.command("fill");
+    cmd.arg("in.csv");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["id", "name", "city"],
+        svec!["1", "", "Paris"],
+        svec!["1", "a", "Paris"],
+        svec!["2", "a", "Paris"],
+        svec!["2", "b", "Rome"],
+        svec!["2", "b", "Rome"],
+    ];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn fill_select_value() {
+    let wrk = Workdir::new("fill_select_value");
+    wrk.create("in.csv", data());
+
+    let mut cmd = wrk.command("fill");
+    cmd.arg("-s").arg("city").arg("--value").arg("?").arg("in.csv");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["id", "name", "city"],
+        svec!["1", "", "Paris"],
+        svec!["1", "a", "?"],
+        svec!["2", "", "?"],
+        svec!["2", "b", "Rome"],
+        svec!["2", "", "?"],
+    ];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn fill_forward_default_value() {
+    let wrk = Workdir::new("fill_forward_default_value");
+    wrk.create("in.csv", data());
+
+    let mut cmd = wrk.command("fill");
+    cmd.arg("--forward").arg("--value").arg("?").arg("-s").arg("name")
+       .arg("in.csv");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let names: Vec<String> = got.into_iter().map(|r| r[1].clone()).collect();
+    assert_eq!(names, svec!["name", "?", "a", "a", "b", "b"]);
+}
+
+#[test]
+fn fill_backward() {
+    let wrk = Workdir::new("fill_backward");
+    wrk.create("in.csv", data());
+
+    let mut cmd = wrk.command("fill");
+    cmd.arg("--backward").arg("in.csv");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["id", "name", "city"],
+        svec!["1", "a", "Paris"],
+        svec!["1", "a", "Rome"],
+        svec!["2", "b", "Rome"],
+        svec!["2", "b", "Rome"],
+        svec!["2", "", ""],
+    ];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn fill_group_by() {
+    let wrk = Workdir::new("fill_group_by");
+    wrk.create("in.csv", data());
+
+    let mut cmd = wrk.command("fill");
+    cmd.arg("-s").arg("name,city").arg("--group-by").arg("id").arg("in.csv");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["id", "name", "city"],
+        svec!["1", "", "Paris"],
+        svec!["1", "a", "Paris"],
+        svec!["2", "", ""],
+        svec!["2", "b", "Rome"],
+        svec!["2", "b", "Rome"],
+    ];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn fill_group_by_backward() {
+    let wrk = Workdir::new("fill_group_by_backward");
+    wrk.create("in.csv", data());
+
+    let mut cmd = wrk.command("fill");
+    cmd.arg("--backward").arg("-s").arg("name").arg("-g").arg("id")
+       .arg("in.csv");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let names: Vec<String> = got.into_iter().map(|r| r[1].clone()).collect();
+    assert_eq!(names, svec!["name", "a", "a", "b", "b", ""]);
+}
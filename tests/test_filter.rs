@@ -0,0 +1,87 @@
+use workdir::Workdir;
+
+fn data() -> Vec<Vec<String>> {
+    vec![
+        svec!["id", "amount", "status", "email"],
+        svec!["1", "1500", "paid", "a@example.com"],
+        svec!["2", "n/a", "paid", ""],
+        svec!["3", "2000", "refunded", "c@example.com"],
+        svec!["4", "50", "paid", "d@example.com"],
+    ]
+}
+
+fn ids(rows: Vec<Vec<String>>) -> Vec<String> {
+    rows.into_iter().skip(1).map(|row| row[0].clone()).collect()
+}
+
+#[test]
+fn filter_comparisons() {
+    let wrk = Workdir::new("filter_comparisons");
+    wrk.create("in.csv", data());
+
+    let mut cmd = wrk.command("filter");
+    cmd.arg("amount > 1000 AND status != \"refunded\"").arg("in.csv");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got[0], data()[0]);
+    assert_eq!(ids(got), svec!["1"]);
+}
+
+#[test]
+fn filter_invert() {
+    let wrk = Workdir::new("filter_invert");
+    wrk.create("in.csv", data());
+
+    let mut cmd = wrk.command("filter");
+    cmd.arg("--invert").arg("amount > 1000").arg("in.csv");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(ids(got), svec!["2", "4"]);
+}
+
+#[test]
+fn filter_is_empty_and_in() {
+    let wrk = Workdir::new("filter_is_empty_and_in");
+    wrk.create("in.csv", data());
+
+    let mut cmd = wrk.command("filter");
+    cmd.arg("email is empty or id in (3, 4)").arg("in.csv");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(ids(got), svec!["2", "3", "4"]);
+
+    let mut cmd = wrk.command("filter");
+    cmd.arg("email is not empty and status not in ('refunded')")
+       .arg("in.csv");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(ids(got), svec!["1", "4"]);
+}
+
+#[test]
+fn filter_strict() {
+    let wrk = Workdir::new("filter_strict");
+    wrk.create("in.csv", data());
+
+    let mut cmd = wrk.command("filter");
+    cmd.arg("amount * 2 < 200").arg("in.csv");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(ids(got), svec!["4"]);
+
+    let mut cmd = wrk.command("filter");
+    cmd.arg("--strict").arg("amount < 200").arg("in.csv");
+    let output = cmd.output().unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr)
+                .contains("Error on line 3: 'n/a' is not a number"));
+}
+
+#[test]
+fn filter_syntax_error() {
+    let wrk = Workdir::new("filter_syntax_error");
+    wrk.create("in.csv", data());
+
+    let mut cmd = wrk.command("filter");
+    cmd.arg("id in (1 2)").arg("in.csv");
+    let output = cmd.output().unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("    id in (1 2)\n             ^ expected ',' \
+                             or ')', found 2"));
+}
@@ -0,0 +1,99 @@
+use std::fs;
+
+use workdir::Workdir;
+
+fn layout(wrk: &Workdir) {
+    fs::write(wrk.path("layout.csv"), "\
+name,start,width,trim,justify
+id,1,3,,right
+name,4,6
+code,12,4,no
+").unwrap();
+}
+
+#[test]
+fn fixed_to_csv() {
+    let wrk = Workdir::new("fixed_to_csv");
+    layout(&wrk);
+    fs::write(wrk.path("in.txt"),
+              "  1Alice   AB  \n 22Bob     C   \n\n333Carol   DEFG\n")
+        .unwrap();
+
+    let mut cmd = wrk.command("fixed");
+    cmd.arg("--layout").arg("layout.csv").arg("in.txt");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["id", "name", "code"],
+        svec!["  1", "Alice", "AB  "],
+        svec![" 22", "Bob", "C   "],
+        svec!["333", "Carol", "DEFG"],
+    ];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn fixed_chars() {
+    let wrk = Workdir::new("fixed_chars");
+    layout(&wrk);
+    fs::write(wrk.path("in.txt"), "  1Zoë     XY  \n").unwrap();
+
+    let mut cmd = wrk.command("fixed");
+    cmd.arg("--layout").arg("layout.csv").arg("--chars").arg("in.txt");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got[1], svec!["  1", "Zoë", "XY  "]);
+
+    // Counted in bytes, the line is one byte longer.
+    let mut cmd = wrk.command("fixed");
+    cmd.arg("--layout").arg("layout.csv").arg("in.txt");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got[1], svec!["  1", "Zoë", " XY "]);
+}
+
+#[test]
+fn fixed_short_line() {
+    let wrk = Workdir::new("fixed_short_line");
+    layout(&wrk);
+    fs::write(wrk.path("in.txt"), "  1Alice   AB  \n 22Bob\n").unwrap();
+
+    let mut cmd = wrk.command("fixed");
+    cmd.arg("--layout").arg("layout.csv").arg("in.txt");
+    let output = cmd.output().unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr)
+                .contains("Line 2 is 6 bytes long"));
+
+    let mut cmd = wrk.command("fixed");
+    cmd.arg("--layout").arg("layout.csv").arg("--pad-short").arg("in.txt");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got[2], svec![" 22", "Bob", ""]);
+}
+
+#[test]
+fn fixed_reverse() {
+    let wrk = Workdir::new("fixed_reverse");
+    layout(&wrk);
+    wrk.create("in.csv", vec![
+        svec!["code", "name", "id"],
+        svec!["AB", "Alice", "1"],
+        svec!["DEFG", "Carol", "333"],
+    ]);
+
+    let mut cmd = wrk.command("fixed");
+    cmd.arg("--layout").arg("layout.csv").arg("--reverse").arg("in.csv");
+    let got: String = wrk.stdout(&mut cmd);
+    assert_eq!(got, "  1Alice   AB  \n333Carol   DEFG");
+}
+
+#[test]
+fn fixed_reverse_too_wide() {
+    let wrk = Workdir::new("fixed_reverse_too_wide");
+    layout(&wrk);
+    wrk.create("in.csv", vec![
+        svec!["id", "name", "code"],
+        svec!["1", "Alexandra", "AB"],
+    ]);
+
+    let mut cmd = wrk.command("fixed");
+    cmd.arg("--layout").arg("layout.csv").arg("--reverse").arg("in.csv");
+    wrk.assert_err(&mut cmd);
+}
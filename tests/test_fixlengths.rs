@@ -91,3 +91,136 @@ fn prop_fixlengths_explicit_len() {
     }
     qcheck(p as fn(Vec<CsvRecord>, usize) -> TestResult);
 }
+
+fn ragged() -> Vec<Vec<String>> {
+    vec![
+        svec!["a", "b", "c"],
+        svec!["1"],
+        svec!["1", "2", "3", "4"],
+    ]
+}
+
+#[test]
+fn fixlengths_fill() {
+    let wrk = Workdir::new("fixlengths_fill").flexible(true);
+    wrk.create("in.csv", ragged());
+
+    let mut cmd = wrk.command("fixlengths");
+    cmd.arg("in.csv").arg("--fill").arg("NA");
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["a", "b", "c", "NA"],
+        svec!["1", "NA", "NA", "NA"],
+        svec!["1", "2", "3", "4"],
+    ];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn fixlengths_pad_only() {
+    let wrk = Workdir::new("fixlengths_pad_only").flexible(true);
+    wrk.create("in.csv", ragged());
+
+    let mut cmd = wrk.command("fixlengths");
+    cmd.arg("in.csv").arg("--pad-only").arg("-l").arg("3");
+
+    let got: String = wrk.stdout(&mut cmd);
+    assert_eq!(got, "a,b,c\n1,,\n1,2,3,4".to_string());
+
+    cmd.arg("--strict");
+    wrk.assert_err(&mut cmd);
+}
+
+#[test]
+fn fixlengths_truncate_only() {
+    let wrk = Workdir::new("fixlengths_truncate_only").flexible(true);
+    wrk.create("in.csv", ragged());
+
+    let mut cmd = wrk.command("fixlengths");
+    cmd.arg("in.csv").arg("--truncate-only").arg("-l").arg("3");
+
+    let got: String = wrk.stdout(&mut cmd);
+    assert_eq!(got, "a,b,c\n1\n1,2,3".to_string());
+}
+
+#[test]
+fn fixlengths_report() {
+    let wrk = Workdir::new("fixlengths_report").flexible(true);
+    wrk.create("in.csv", ragged());
+
+    let mut cmd = wrk.command("fixlengths");
+    cmd.arg("in.csv").arg("--report");
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["line", "length", "target"],
+        svec!["1", "3", "4"],
+        svec!["2", "1", "4"],
+    ];
+    assert_eq!(got, expected);
+}
+
+fn one_corrupt_row() -> Vec<Vec<String>> {
+    vec![
+        svec!["a", "b", "c"],
+        svec!["1"],
+        svec!["1", "2", "3", "4", "5", "6"],
+        svec!["1", "2", "3"],
+        svec!["4", "5", "6"],
+    ]
+}
+
+#[test]
+fn fixlengths_length_from_header() {
+    let wrk = Workdir::new("fixlengths_length_from_header").flexible(true);
+    wrk.create("in.csv", one_corrupt_row());
+
+    let mut cmd = wrk.command("fixlengths");
+    cmd.arg("in.csv").arg("--length-from").arg("header");
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["a", "b", "c"],
+        svec!["1", "", ""],
+        svec!["1", "2", "3"],
+        svec!["1", "2", "3"],
+        svec!["4", "5", "6"],
+    ];
+    assert_eq!(got, expected);
+
+    let stderr = String::from_utf8(wrk.output(&mut cmd).stderr).unwrap();
+    assert_eq!(stderr, "padded 1 record(s), truncated 1 record(s)\n");
+}
+
+#[test]
+fn fixlengths_length_from_mode() {
+    let wrk = Workdir::new("fixlengths_length_from_mode").flexible(true);
+    let mut rows = one_corrupt_row();
+    rows[0] = svec!["a", "b"];
+    wrk.create("in.csv", rows);
+
+    let mut cmd = wrk.command("fixlengths");
+    cmd.arg("in.csv").arg("--length-from").arg("mode");
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["a", "b", ""],
+        svec!["1", "", ""],
+        svec!["1", "2", "3"],
+        svec!["1", "2", "3"],
+        svec!["4", "5", "6"],
+    ];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn fixlengths_length_and_length_from() {
+    let wrk = Workdir::new("fixlengths_length_and_length_from")
+        .flexible(true);
+    wrk.create("in.csv", one_corrupt_row());
+
+    let mut cmd = wrk.command("fixlengths");
+    cmd.arg("in.csv").arg("--length-from").arg("max").arg("-l").arg("2");
+    wrk.assert_err(&mut cmd);
+}
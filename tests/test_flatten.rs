@@ -82,3 +82,58 @@ h2  st...\
 ";
     assert_eq!(got, expected.to_string());
 }
+
+#[test]
+fn flatten_json() {
+    let (wrk, mut cmd) = setup("flatten_json");
+    cmd.arg("--json");
+
+    let got: String = wrk.stdout(&mut cmd);
+    let expected = "\
+[
+  {\"h1\": \"abcdef\", \"h2\": \"ghijkl\"},
+  {\"h1\": \"mnopqr\", \"h2\": \"stuvwx\"}
+]";
+    assert_eq!(got, expected.to_string());
+}
+
+#[test]
+fn flatten_jsonl_no_headers_condense() {
+    let (wrk, mut cmd) = setup("flatten_jsonl_no_headers_condense");
+    cmd.arg("--jsonl").arg("--no-headers").arg("--condense").arg("3");
+
+    let got: String = wrk.stdout(&mut cmd);
+    let expected = "\
+{\"field_1\": \"h1\", \"field_2\": \"h2\"}
+{\"field_1\": \"abc...\", \"field_2\": \"ghi...\"}
+{\"field_1\": \"mno...\", \"field_2\": \"stu...\"}";
+    assert_eq!(got, expected.to_string());
+}
+
+#[test]
+fn flatten_jsonl_escaping() {
+    let wrk = Workdir::new("flatten_jsonl_escaping");
+    wrk.create("in.csv", vec![
+        svec!["say \"hi\"", "notes"],
+        svec!["a\\b", "two\nlines"],
+    ]);
+
+    let mut cmd = wrk.command("flatten");
+    cmd.arg("--jsonl").arg("in.csv");
+
+    let got: String = wrk.stdout(&mut cmd);
+    let expected = r#"{"say \"hi\"": "a\\b", "notes": "two\nlines"}"#;
+    assert_eq!(got, expected.to_string());
+}
+
+#[test]
+fn flatten_json_empty() {
+    let wrk = Workdir::new("flatten_json_empty");
+    wrk.create("in.csv", vec![svec!["h1", "h2"]]);
+
+    let mut cmd = wrk.command("flatten");
+    cmd.arg("--json").arg("in.csv");
+
+    let got: String = wrk.stdout(&mut cmd);
+    assert_eq!(got, "[]".to_string());
+}
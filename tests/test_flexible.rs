@@ -0,0 +1,140 @@
+use std::fs;
+use std::process;
+
+use workdir::Workdir;
+
+/// A file with one record that is too short and one that is too long.
+fn setup(name: &str) -> Workdir {
+    let wrk = Workdir::new(name);
+    fs::write(wrk.path("in.csv"), "id,n\n1,10\n2\n3,30,x\n4,40\n").unwrap();
+    fs::write(wrk.path("names.csv"), "id,name\n1,a\n2,b\n3,c\n4,d\n")
+        .unwrap();
+    wrk
+}
+
+fn flexible(wrk: &Workdir, policy: &str, command: &str) -> process::Command {
+    let mut cmd = wrk.command("--flexible");
+    cmd.arg(policy).arg(command);
+    cmd
+}
+
+fn stats(wrk: &Workdir, policy: &str) -> Vec<Vec<String>> {
+    let mut cmd = flexible(wrk, policy, "stats");
+    cmd.args(["-s", "n", "in.csv"]);
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    got.into_iter().map(|row| row[..5].to_vec()).collect()
+}
+
+fn join(wrk: &Workdir, policy: &str) -> Vec<Vec<String>> {
+    let mut cmd = flexible(wrk, policy, "join");
+    cmd.args(["id", "names.csv", "id", "in.csv"]);
+    wrk.read_stdout(&mut cmd)
+}
+
+#[test]
+fn flexible_error() {
+    let wrk = setup("flexible_error");
+    for command in &["stats", "join"] {
+        let mut cmd = flexible(&wrk, "error", command);
+        if *command == "join" {
+            cmd.args(["id", "names.csv"]);
+        }
+        cmd.arg("in.csv");
+        wrk.assert_err(&mut cmd);
+    }
+}
+
+#[test]
+fn flexible_pad() {
+    let wrk = setup("flexible_pad");
+    // The record that is too long is still an error.
+    let mut cmd = flexible(&wrk, "pad", "stats");
+    cmd.arg("in.csv");
+    let o = cmd.output().unwrap();
+    assert!(!o.status.success());
+    let stderr = String::from_utf8_lossy(&o.stderr);
+    assert!(stderr.contains("found record with 3 fields"), "{}", stderr);
+
+    fs::write(wrk.path("in.csv"), "id,n\n1,10\n2\n4,40\n").unwrap();
+    let expected = vec![
+        svec!["field", "type", "sum", "min", "max"],
+        svec!["n", "Integer", "50", "10", "40"],
+    ];
+    assert_eq!(stats(&wrk, "pad"), expected);
+    let expected = vec![
+        svec!["id", "name", "id", "n"],
+        svec!["1", "a", "1", "10"],
+        svec!["2", "b", "2", ""],
+        svec!["4", "d", "4", "40"],
+    ];
+    assert_eq!(join(&wrk, "pad"), expected);
+}
+
+#[test]
+fn flexible_truncate() {
+    let wrk = setup("flexible_truncate");
+    // The record that is too short is still an error.
+    let mut cmd = flexible(&wrk, "truncate", "stats");
+    cmd.arg("in.csv");
+    let o = cmd.output().unwrap();
+    assert!(!o.status.success());
+    let stderr = String::from_utf8_lossy(&o.stderr);
+    assert!(stderr.contains("found record with 1 fields, but the first \
+                             record (the header) has 2 fields"), "{}", stderr);
+
+    fs::write(wrk.path("in.csv"), "id,n\n1,10\n3,30,x\n4,40\n").unwrap();
+    let expected = vec![
+        svec!["field", "type", "sum", "min", "max"],
+        svec!["n", "Integer", "80", "10", "40"],
+    ];
+    assert_eq!(stats(&wrk, "truncate"), expected);
+    let expected = vec![
+        svec!["id", "name", "id", "n"],
+        svec!["1", "a", "1", "10"],
+        svec!["3", "c", "3", "30"],
+        svec!["4", "d", "4", "40"],
+    ];
+    assert_eq!(join(&wrk, "truncate"), expected);
+}
+
+#[test]
+fn flexible_skip() {
+    let wrk = setup("flexible_skip");
+    let expected = vec![
+        svec!["field", "type", "sum", "min", "max"],
+        svec!["n", "Integer", "50", "10", "40"],
+    ];
+    assert_eq!(stats(&wrk, "skip"), expected);
+    let expected = vec![
+        svec!["id", "name", "id", "n"],
+        svec!["1", "a", "1", "10"],
+        svec!["4", "d", "4", "40"],
+    ];
+    assert_eq!(join(&wrk, "skip"), expected);
+
+    let mut cmd = flexible(&wrk, "skip", "count");
+    cmd.arg("in.csv");
+    let o = cmd.output().unwrap();
+    assert_eq!(String::from_utf8_lossy(&o.stdout), "2\n");
+    assert_eq!(String::from_utf8_lossy(&o.stderr),
+               "skipped 2 record(s) with the wrong number of fields\n");
+}
+
+#[test]
+fn flexible_ignores_index() {
+    let wrk = setup("flexible_ignores_index");
+    fs::write(wrk.path("ok.csv"), "id,n\n1,10\n2,20\n").unwrap();
+    let mut cmd = wrk.command("index");
+    cmd.arg("ok.csv");
+    wrk.run(&mut cmd);
+    fs::write(wrk.path("ok.csv.idx"), "garbage").unwrap();
+
+    let mut cmd = flexible(&wrk, "skip", "count");
+    cmd.arg("ok.csv");
+    let got: String = wrk.stdout(&mut cmd);
+    assert_eq!(got, "2");
+
+    let mut cmd = flexible(&wrk, "pad", "index");
+    cmd.arg("in.csv");
+    wrk.assert_err(&mut cmd);
+}
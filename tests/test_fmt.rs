@@ -44,6 +44,45 @@ mnopqrhstuvwx";
     assert_eq!(got, expected.to_string());
 }
 
+#[test]
+fn fmt_delimiter_names() {
+    let names = [("tab", '\t'), ("PIPE", '|'), ("semicolon", ';'),
+                 ("space", ' '), ("\\t", '\t'), ("\\x1f", '\x1f')];
+    for &(name, delim) in &names {
+        let (wrk, mut cmd) = setup("fmt_delimiter_names");
+        cmd.args(["--out-delimiter", name]);
+
+        let got: String = wrk.stdout(&mut cmd);
+        let expected = "h1,h2\nabcdef,ghijkl\nmnopqr,stuvwx"
+            .replace(',', &delim.to_string());
+        assert_eq!(got, expected, "{}", name);
+    }
+}
+
+#[test]
+fn fmt_read_delimiter_escape() {
+    let wrk = Workdir::new("fmt_read_delimiter_escape");
+    ::std::fs::write(wrk.path("in.txt"), "a\x1fb\n1\x1f2\n").unwrap();
+    let mut cmd = wrk.command("fmt");
+    cmd.args(["-d", "\\x1F", "in.txt"]);
+
+    let got: String = wrk.stdout(&mut cmd);
+    assert_eq!(got, "a,b\n1,2");
+}
+
+#[test]
+fn fmt_delimiter_invalid() {
+    for arg in &["tabs", "\\x80", "\\xg1", "ab"] {
+        let (_wrk, mut cmd) = setup("fmt_delimiter_invalid");
+        cmd.args(["--out-delimiter", arg]);
+        let o = cmd.output().unwrap();
+        assert!(!o.status.success());
+        let stderr = String::from_utf8_lossy(&o.stderr);
+        assert!(stderr.contains(&format!("Could not convert '{}'", arg)),
+                "{}", stderr);
+    }
+}
+
 #[test]
 fn fmt_crlf() {
     let (wrk, mut cmd) = setup("fmt_crlf");
@@ -57,6 +96,43 @@ mnopqr,stuvwx";
     assert_eq!(got, expected.to_string());
 }
 
+#[test]
+fn fmt_terminator_crlf() {
+    let (wrk, mut cmd) = setup("fmt_terminator_crlf");
+    cmd.arg("--terminator").arg("crlf");
+
+    let got = wrk.output(&mut cmd).stdout;
+    let expected = "h1,h2\r\nabcdef,ghijkl\r\nmnopqr,stuvwx\r\n";
+    assert_eq!(String::from_utf8(got).unwrap(), expected);
+}
+
+#[test]
+fn fmt_terminator_char() {
+    let (wrk, mut cmd) = setup("fmt_terminator_char");
+    cmd.arg("--terminator").arg("\x1e");
+
+    let got = wrk.output(&mut cmd).stdout;
+    let expected = "h1,h2\x1eabcdef,ghijkl\x1emnopqr,stuvwx\x1e";
+    assert_eq!(String::from_utf8(got).unwrap(), expected);
+}
+
+#[test]
+fn fmt_terminator_overrides_ascii() {
+    let (wrk, mut cmd) = setup("fmt_terminator_overrides_ascii");
+    cmd.arg("--ascii").arg("--terminator").arg(";");
+
+    let got = wrk.output(&mut cmd).stdout;
+    let expected = "h1\x1fh2;abcdef\x1fghijkl;mnopqr\x1fstuvwx;";
+    assert_eq!(String::from_utf8(got).unwrap(), expected);
+}
+
+#[test]
+fn fmt_terminator_and_crlf() {
+    let (wrk, mut cmd) = setup("fmt_terminator_and_crlf");
+    cmd.arg("--crlf").arg("--terminator").arg(";");
+    wrk.assert_err(&mut cmd);
+}
+
 #[test]
 fn fmt_quote_always() {
     let (wrk, mut cmd) = setup("fmt_quote_always");
@@ -69,3 +145,171 @@ fn fmt_quote_always() {
 \"mnopqr\",\"stuvwx\"";
     assert_eq!(got, expected.to_string());
 }
+
+fn tricky() -> Vec<Vec<String>> {
+    vec![
+        svec!["h1", "h2", "h3"],
+        svec!["a,b", "q\"x", "12"],
+        svec!["multi\nline", "1.5", "plain"],
+    ]
+}
+
+fn quote_style_round_trip(name: &str, style: &str) -> String {
+    let wrk = Workdir::new(name);
+    wrk.create("in.csv", tricky());
+
+    let mut cmd = wrk.command("fmt");
+    cmd.arg("in.csv").arg("--quote-style").arg(style);
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got, tricky());
+    wrk.stdout(&mut cmd)
+}
+
+#[test]
+fn fmt_quote_style_minimal() {
+    let got = quote_style_round_trip("fmt_quote_style_minimal", "minimal");
+    let expected = "\
+h1,h2,h3
+\"a,b\",\"q\"\"x\",12
+\"multi
+line\",1.5,plain";
+    assert_eq!(got, expected.to_string());
+}
+
+#[test]
+fn fmt_quote_style_non_numeric() {
+    let got = quote_style_round_trip(
+        "fmt_quote_style_non_numeric", "non-numeric");
+    let expected = "\
+\"h1\",\"h2\",\"h3\"
+\"a,b\",\"q\"\"x\",12
+\"multi
+line\",1.5,\"plain\"";
+    assert_eq!(got, expected.to_string());
+}
+
+#[test]
+fn fmt_quote_style_always() {
+    let got = quote_style_round_trip("fmt_quote_style_always", "always");
+    let expected = "\
+\"h1\",\"h2\",\"h3\"
+\"a,b\",\"q\"\"x\",\"12\"
+\"multi
+line\",\"1.5\",\"plain\"";
+    assert_eq!(got, expected.to_string());
+}
+
+#[test]
+fn fmt_quote_style_never_escape() {
+    let wrk = Workdir::new("fmt_quote_style_never_escape");
+    wrk.create("in.csv", vec![
+        svec!["h1", "h2"],
+        svec!["a,b", "q\"x"],
+        svec!["multi\nline", "back\\slash"],
+    ]);
+
+    let mut cmd = wrk.command("fmt");
+    cmd.arg("in.csv").arg("--quote-style").arg("never");
+    wrk.assert_err(&mut cmd);
+
+    cmd.arg("--escape").arg("\\");
+    let got: String = wrk.stdout(&mut cmd);
+    let expected = "\
+h1,h2
+a\\,b,q\"x
+multi\\
+line,back\\\\slash";
+    assert_eq!(got, expected.to_string());
+}
+
+#[test]
+fn fmt_quote_style_never_plain() {
+    let (wrk, mut cmd) = setup("fmt_quote_style_never_plain");
+    cmd.arg("--quote-style").arg("never").arg("--out-delimiter").arg("h");
+    wrk.assert_err(&mut cmd);
+}
+
+fn padded() -> Vec<Vec<String>> {
+    vec![
+        svec![" id ", "name"],
+        svec![" 42 ", "John  "],
+        svec!["7", "Mary \t Ann"],
+        svec!["   ", "  Bob   Smith "],
+    ]
+}
+
+#[test]
+fn fmt_trim() {
+    let wrk = Workdir::new("fmt_trim");
+    wrk.create("in.csv", padded());
+
+    let mut cmd = wrk.command("fmt");
+    cmd.arg("--trim").arg("in.csv");
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["id", "name"],
+        svec!["42", "John"],
+        svec!["7", "Mary \t Ann"],
+        svec!["", "Bob   Smith"],
+    ];
+    assert_eq!(got, expected);
+
+    let stderr = String::from_utf8(wrk.output(&mut cmd).stderr).unwrap();
+    assert_eq!(stderr.trim(), "modified 5 cell(s)");
+}
+
+#[test]
+fn fmt_squeeze() {
+    let wrk = Workdir::new("fmt_squeeze");
+    wrk.create("in.csv", padded());
+
+    let mut cmd = wrk.command("fmt");
+    cmd.arg("--trim").arg("--squeeze").arg("in.csv");
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["id", "name"],
+        svec!["42", "John"],
+        svec!["7", "Mary Ann"],
+        svec!["", "Bob Smith"],
+    ];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn fmt_squeeze_only() {
+    let wrk = Workdir::new("fmt_squeeze_only");
+    wrk.create("in.csv", padded());
+
+    let mut cmd = wrk.command("fmt");
+    cmd.arg("--squeeze").arg("in.csv");
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec![" id ", "name"],
+        svec![" 42 ", "John "],
+        svec!["7", "Mary Ann"],
+        svec![" ", " Bob Smith "],
+    ];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn fmt_no_trim_headers() {
+    let wrk = Workdir::new("fmt_no_trim_headers");
+    wrk.create("in.csv", padded());
+
+    let mut cmd = wrk.command("fmt");
+    cmd.arg("--trim").arg("--no-trim-headers").arg("in.csv");
+
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec![" id ", "name"],
+        svec!["42", "John"],
+        svec!["7", "Mary \t Ann"],
+        svec!["", "Bob   Smith"],
+    ];
+    assert_eq!(got, expected);
+}
@@ -0,0 +1,128 @@
+use workdir::Workdir;
+
+fn data() -> Vec<Vec<String>> {
+    vec![
+        svec!["id", "name"],
+        svec!["1", "it's"],
+        svec!["2", "a; b $x"],
+    ]
+}
+
+#[test]
+fn foreach_unify() {
+    let wrk = Workdir::new("foreach_unify");
+    wrk.create("in.csv", data());
+
+    let mut cmd = wrk.command("foreach");
+    cmd.arg("--unify").arg("echo {name}").arg("in.csv");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["id", "name", "output"],
+        svec!["1", "it's", "it's"],
+        svec!["2", "a; b $x", "a; b $x"],
+    ];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn foreach_jobs_keeps_order() {
+    let wrk = Workdir::new("foreach_jobs_keeps_order");
+    wrk.create("in.csv", vec![
+        svec!["n"], svec!["0.3"], svec!["0"], svec!["0.1"],
+    ]);
+
+    let mut cmd = wrk.command("foreach");
+    cmd.arg("--unify").arg("--jobs").arg("3")
+       .arg("--new-column").arg("slept")
+       .arg("sleep {n} && echo {n}").arg("in.csv");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["n", "slept"],
+        svec!["0.3", "0.3"],
+        svec!["0", "0"],
+        svec!["0.1", "0.1"],
+    ];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn foreach_dry_run() {
+    let wrk = Workdir::new("foreach_dry_run");
+    wrk.create("in.csv", data());
+
+    let mut cmd = wrk.command("foreach");
+    cmd.arg("--dry-run").arg("touch {id}-{name}.txt").arg("in.csv");
+    let got: String = wrk.stdout(&mut cmd);
+    assert_eq!(got, "touch '1'-'it'\\''s'.txt\ntouch '2'-'a; b $x'.txt");
+}
+
+#[test]
+fn foreach_no_shell() {
+    let wrk = Workdir::new("foreach_no_shell");
+    wrk.create("in.csv", data());
+
+    let mut cmd = wrk.command("foreach");
+    cmd.arg("--no-shell").arg("--unify").arg("printf %s| {name}")
+       .arg("in.csv");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got[2], svec!["2", "a; b $x", "a; b $x|"]);
+
+    let mut cmd = wrk.command("foreach");
+    cmd.arg("--no-shell").arg("--dry-run").arg("printf %s| {name}")
+       .arg("in.csv");
+    let got: String = wrk.stdout(&mut cmd);
+    assert_eq!(got, "printf '%s|' 'it'\\''s'\nprintf '%s|' 'a; b $x'");
+}
+
+#[test]
+fn foreach_quoted_placeholder() {
+    let wrk = Workdir::new("foreach_quoted_placeholder");
+    wrk.create("in.csv", vec![
+        svec!["name"], svec!["\" ; echo INJECTED ; \""],
+    ]);
+
+    for command in &["echo \"{name}\"", "echo 'x {name}'",
+                     "echo \"a\\\" {name}\""] {
+        let mut cmd = wrk.command("foreach");
+        cmd.arg(command).arg("in.csv");
+        let output = cmd.output().unwrap();
+        assert_eq!(output.status.code(), Some(2), "{}", command);
+        assert!(output.stdout.is_empty(), "{}", command);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(stderr.contains("'{name}' is inside quotes"), "{}", stderr);
+    }
+
+    // Quotes around literal text and escaped quotes are fine.
+    let mut cmd = wrk.command("foreach");
+    cmd.arg("--unify").arg("echo \"a b\" \\\" {name} '{{x}}'").arg("in.csv");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got[1][1], "a b \" \" ; echo INJECTED ; \" {x}");
+}
+
+#[test]
+fn foreach_failures() {
+    let wrk = Workdir::new("foreach_failures");
+    wrk.create("in.csv", data());
+
+    let mut cmd = wrk.command("foreach");
+    cmd.arg("test {id} = 2").arg("in.csv");
+    let output = cmd.output().unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("the command for line 2 failed"), "{}", stderr);
+    assert!(stderr.contains("1 of 2 commands failed"), "{}", stderr);
+}
+
+#[test]
+fn foreach_halt_on_error() {
+    let wrk = Workdir::new("foreach_halt_on_error");
+    wrk.create("in.csv", data());
+
+    let mut cmd = wrk.command("foreach");
+    cmd.arg("--halt-on-error").arg("--unify").arg("echo {id}; false")
+       .arg("in.csv");
+    let output = cmd.output().unwrap();
+    assert!(!output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(stdout, "id,name,output\n1,it's,1\n");
+}
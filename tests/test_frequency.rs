@@ -124,6 +124,30 @@ fn frequency_select() {
     assert_eq!(got, expected);
 }
 
+#[test]
+fn frequency_jobs_shared_values() {
+    let wrk = Workdir::new("frequency_jobs_shared_values");
+    let mut rows = vec![svec!["h1", "h2"]];
+    for i in 0..100 {
+        let v = if i % 3 == 0 { "a" } else { "b" };
+        rows.push(svec![v, if i % 2 == 0 { "a" } else { " b " }]);
+    }
+    wrk.create_indexed("in.csv", rows);
+
+    let mut cmd = wrk.command("frequency");
+    cmd.args(&["--jobs", "4"]).arg("in.csv");
+    let mut got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    got.sort();
+    let expected = vec![
+        svec!["field", "value", "count"],
+        svec!["h1", "a", "34"],
+        svec!["h1", "b", "66"],
+        svec!["h2", "a", "50"],
+        svec!["h2", "b", "50"],
+    ];
+    assert_eq!(got, expected);
+}
+
 // This tests that a frequency table computed by `xsv` is always the same
 // as the frequency table computed in memory.
 #[test]
@@ -0,0 +1,112 @@
+use std::fs;
+
+use workdir::Workdir;
+
+const LINES: &str = "\
+{\"id\": 1, \"address\": {\"city\": \"Paris\", \"zip\": \"75001\"}, \"tags\": [\"a\", \"b\"]}
+{\"id\": 2.5, \"name\": \"caf\\u00e9 \\\"x\\\"\", \"address\": {\"city\": null}}
+
+{\"id\": true, \"tags\": []}
+";
+
+#[test]
+fn fromjson() {
+    let wrk = Workdir::new("fromjson");
+    fs::write(wrk.path("in.jsonl"), LINES).unwrap();
+
+    let mut cmd = wrk.command("fromjson");
+    cmd.arg("in.jsonl");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["id", "address.city", "address.zip", "tags", "name"],
+        svec!["1", "Paris", "75001", "[\"a\",\"b\"]", ""],
+        svec!["2.5", "", "", "", "café \"x\""],
+        svec!["true", "", "", "[]", ""],
+    ];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn fromjson_stdin_explode_arrays() {
+    let wrk = Workdir::new("fromjson_stdin_explode_arrays");
+    fs::write(wrk.path("in.jsonl"), LINES).unwrap();
+
+    let mut cmd = wrk.command("fromjson");
+    cmd.arg("--explode-arrays")
+       .stdin(fs::File::open(wrk.path("in.jsonl")).unwrap());
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got[0], svec![
+        "id", "address.city", "address.zip", "tags.0", "tags.1", "name",
+    ]);
+    assert_eq!(got[1], svec!["1", "Paris", "75001", "a", "b", ""]);
+}
+
+#[test]
+fn fromjson_columns() {
+    let wrk = Workdir::new("fromjson_columns");
+    fs::write(wrk.path("in.jsonl"), LINES).unwrap();
+
+    let mut cmd = wrk.command("fromjson");
+    cmd.arg("--columns").arg("name,id").arg("in.jsonl");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["name", "id"],
+        svec!["", "1"],
+        svec!["café \"x\"", "2.5"],
+        svec!["", "true"],
+    ];
+    assert_eq!(got, expected);
+
+    let out = wrk.output(&mut cmd);
+    assert!(String::from_utf8_lossy(&out.stderr)
+        .contains("dropped 5 value(s) of 3 key(s)"));
+}
+
+#[test]
+fn fromjson_array() {
+    let wrk = Workdir::new("fromjson_array");
+    fs::write(wrk.path("in.json"), "[{\"a\": 1},\n {\"b\": [1, {}]}]\n")
+        .unwrap();
+
+    let mut cmd = wrk.command("fromjson");
+    cmd.arg("--array").arg("in.json");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["a", "b"],
+        svec!["1", ""],
+        svec!["", "[1,{}]"],
+    ];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn fromjson_invalid() {
+    let wrk = Workdir::new("fromjson_invalid");
+    fs::write(wrk.path("in.json"), "{\"a\": 1}\n{\"a\": }\n").unwrap();
+
+    let mut cmd = wrk.command("fromjson");
+    cmd.arg("in.json");
+    let out = cmd.output().unwrap();
+    assert!(!out.status.success());
+    assert!(String::from_utf8_lossy(&out.stderr).contains("line 2"));
+}
+
+#[test]
+fn fromjson_json_round_trip() {
+    let wrk = Workdir::new("fromjson_json_round_trip");
+    let rows = vec![
+        svec!["id", "name"],
+        svec!["1", "a, \"b\"\nc"],
+        svec!["2", ""],
+    ];
+    wrk.create("in.csv", rows.clone());
+
+    let mut cmd = wrk.command("json");
+    cmd.arg("in.csv").arg("-o").arg("out.json");
+    wrk.run(&mut cmd);
+
+    let mut cmd = wrk.command("fromjson");
+    cmd.arg("--array").arg("out.json");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got, rows);
+}
@@ -0,0 +1,78 @@
+use workdir::Workdir;
+
+fn data() -> Vec<Vec<String>> {
+    vec![
+        svec!["region", "amount", "latency", "ts"],
+        svec!["west", "10", "1.5", "2018-01-02"],
+        svec!["east", "5", "n/a", "2018-01-01"],
+        svec!["west", "2.5", "0.5", "2018-01-03"],
+        svec!["east", "", "3", "2017-12-31"],
+        svec!["north", "x", "", ""],
+    ]
+}
+
+#[test]
+fn groupby() {
+    let wrk = Workdir::new("groupby");
+    wrk.create("in.csv", data());
+
+    let mut cmd = wrk.command("groupby");
+    cmd.arg("region")
+       .arg("--agg").arg("sum(amount),mean(latency),max(ts),count()")
+       .arg("in.csv");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["region", "amount_sum", "latency_mean", "ts_max", "count"],
+        svec!["east", "5", "3", "2018-01-01", "2"],
+        svec!["north", "", "", "", "1"],
+        svec!["west", "12.5", "1", "2018-01-03", "2"],
+    ];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn groupby_min_first_last_count_column() {
+    let wrk = Workdir::new("groupby_min_first_last_count_column");
+    wrk.create("in.csv", data());
+
+    let mut cmd = wrk.command("groupby");
+    cmd.arg("region")
+       .arg("--agg")
+       .arg("min(amount), first(ts), last(ts), count(amount)")
+       .arg("in.csv");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["region", "amount_min", "ts_first", "ts_last", "amount_count"],
+        svec!["east", "5", "2018-01-01", "2017-12-31", "1"],
+        svec!["north", "x", "", "", "1"],
+        svec!["west", "2.5", "2018-01-02", "2018-01-03", "2"],
+    ];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn groupby_report_skipped() {
+    let wrk = Workdir::new("groupby_report_skipped");
+    wrk.create("in.csv", data());
+
+    let mut cmd = wrk.command("groupby");
+    cmd.arg("region")
+       .arg("--agg").arg("sum(amount),mean(latency)")
+       .arg("--report-skipped")
+       .arg("in.csv");
+    let output = cmd.output().unwrap();
+    assert!(output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("amount_sum: 1 value(s) skipped"), "{}", stderr);
+    assert!(stderr.contains("latency_mean: 1 value(s) skipped"), "{}", stderr);
+}
+
+#[test]
+fn groupby_unknown_aggregate() {
+    let wrk = Workdir::new("groupby_unknown_aggregate");
+    wrk.create("in.csv", data());
+
+    let mut cmd = wrk.command("groupby");
+    cmd.arg("region").arg("--agg").arg("median(amount)").arg("in.csv");
+    wrk.assert_err(&mut cmd);
+}
@@ -0,0 +1,104 @@
+use std::fs;
+
+use workdir::Workdir;
+
+#[test]
+fn hash_sha256_select() {
+    let wrk = Workdir::new("hash_sha256_select");
+    wrk.create("in.csv", vec![
+        svec!["name", "age"],
+        svec!["alice", "30"],
+        svec!["bob", "41"],
+    ]);
+
+    let mut cmd = wrk.command("hash");
+    cmd.arg("--algorithm").arg("sha256").arg("-s").arg("name")
+       .arg("--new-column").arg("key").arg("in.csv");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["name", "age", "key"],
+        svec!["alice", "30",
+              "ff59f2717e898ca82ca655a2362fa7330b888649b59c9afb7dc794de50d1a73f"],
+        svec!["bob", "41",
+              "dd05f3e42387b3e813269fdd75e529863cc727e0125142ceb7fad91c74b67959"],
+    ];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn hash_fields_are_separated() {
+    let wrk = Workdir::new("hash_fields_are_separated");
+    fs::write(wrk.path("in.csv"), "\"a\",bc\nab,c\n").unwrap();
+
+    let mut cmd = wrk.command("hash");
+    cmd.arg("-n").arg("--algorithm").arg("md5").arg("in.csv");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["a", "bc", "780668a4e7443af2f238a4831ec96c0f"],
+        svec!["ab", "c", "135068c3bafda5c1acf6d6dca228ed82"],
+    ];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn hash_xxh3_default() {
+    let wrk = Workdir::new("hash_xxh3_default");
+    fs::write(wrk.path("in.csv"), "a,b\n1,2\n\"1\",2\n1,3\n").unwrap();
+
+    let mut cmd = wrk.command("hash");
+    cmd.arg("in.csv");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got[0], svec!["a", "b", "hash"]);
+    assert_eq!(got[1][2].len(), 16);
+    assert_eq!(got[1][2], got[2][2]);
+    assert_ne!(got[1][2], got[3][2]);
+}
+
+#[test]
+fn hash_xxh3_known_answers() {
+    // The hashed messages are '0:,' (3 bytes), and 24, 135 and 255 bytes
+    // long, which take the short, 17-128, 129-240 and long code paths of
+    // XXH3. The hash itself is checked against the reference vectors in
+    // src/digest.rs.
+    let values = vec![
+        String::new(), "a".repeat(20), "b".repeat(130), "c".repeat(250),
+    ];
+    let wrk = Workdir::new("hash_xxh3_known_answers");
+    let mut data = "v\n".to_owned();
+    for v in &values {
+        data.push_str(&format!("\"{}\"\n", v));
+    }
+    fs::write(wrk.path("in.csv"), data).unwrap();
+
+    let mut cmd = wrk.command("hash");
+    cmd.arg("in.csv");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let hashes: Vec<&str> = got[1..].iter().map(|r| &*r[1]).collect();
+    assert_eq!(hashes, vec![
+        "fbb90a26ee3d1b98",
+        "a9d1b86b2eb816ae",
+        "d77b84738cc359cc",
+        "bf09b6eef6037023",
+    ]);
+}
+
+#[test]
+fn hash_uniq() {
+    let wrk = Workdir::new("hash_uniq");
+    wrk.create("in.csv", vec![
+        svec!["id", "value"],
+        svec!["1", "a"],
+        svec!["2", "b"],
+        svec!["3", "a"],
+    ]);
+
+    let mut cmd = wrk.command("hash");
+    cmd.arg("--uniq").arg("-s").arg("value").arg("--algorithm").arg("sha1")
+       .arg("in.csv");
+    let output = cmd.output().unwrap();
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout),
+               "id,value\n1,a\n2,b\n");
+    assert!(String::from_utf8_lossy(&output.stderr)
+                .contains("removed 1 duplicate record(s)"));
+}
@@ -65,3 +65,140 @@ h2
 h3";
     assert_eq!(got, expected.to_string());
 }
+
+#[test]
+fn headers_check_ok() {
+    let (wrk, mut cmd) = setup("headers_check_ok");
+    cmd.arg("--check");
+
+    let got: String = wrk.stdout(&mut cmd);
+    assert_eq!(got, "".to_string());
+}
+
+#[test]
+fn headers_check_problems() {
+    let wrk = Workdir::new("headers_check_problems");
+    wrk.create("in.csv", vec![
+        svec!["id", "", " name", "id", "a,b", "id"],
+        svec!["1", "2", "3", "4", "5", "6"],
+    ]);
+
+    let mut cmd = wrk.command("headers");
+    cmd.arg("--check").arg("in.csv");
+    wrk.assert_err(&mut cmd);
+
+    let got = String::from_utf8(cmd.output().unwrap().stdout).unwrap();
+    let expected = "\
+1,4,6: duplicate header name 'id'
+2: empty header name
+3: header name ' name' has leading or trailing whitespace
+5: header name 'a,b' contains the delimiter
+";
+    assert_eq!(got, expected.to_string());
+}
+
+#[test]
+fn headers_check_multiple() {
+    let wrk = Workdir::new("headers_check_multiple");
+    wrk.create("in1.csv", vec![svec!["h1", "h2"]]);
+    wrk.create("in2.csv", vec![svec!["h1", "h1"]]);
+
+    let mut cmd = wrk.command("headers");
+    cmd.arg("--check").arg("in1.csv").arg("in2.csv");
+    wrk.assert_err(&mut cmd);
+
+    let got = String::from_utf8(cmd.output().unwrap().stdout).unwrap();
+    assert_eq!(got, "in2.csv: 1,2: duplicate header name 'h1'\n".to_string());
+}
+
+#[test]
+fn headers_diff() {
+    let wrk = Workdir::new("headers_diff");
+    wrk.create("in1.csv", vec![svec!["a", "B", "c"]]);
+    wrk.create("in2.csv", vec![svec!["c", "b ", "a", "z"]]);
+
+    let mut cmd = wrk.command("headers");
+    cmd.arg("--diff").arg("in1.csv").arg("in2.csv");
+    wrk.assert_err(&mut cmd);
+
+    let got = String::from_utf8(cmd.output().unwrap().stdout).unwrap();
+    let expected = "\
+name  in1.csv  in2.csv
+a     \u{2713}        \u{2713}
+B     \u{2713}        \u{2013}
+c     \u{2713}        \u{2713}
+b     \u{2013}        \u{2713}
+z     \u{2013}        \u{2713}
+'B' and 'b ' differ only by case or whitespace
+common columns in in2.csv are not in the same order as in in1.csv
+";
+    assert_eq!(got, expected.to_string());
+}
+
+#[test]
+fn headers_diff_ignore_case() {
+    let wrk = Workdir::new("headers_diff_ignore_case");
+    wrk.create("in1.csv", vec![svec!["a", "B"]]);
+    wrk.create("in2.csv", vec![svec!["A", "b"]]);
+
+    let mut cmd = wrk.command("headers");
+    cmd.arg("--diff").arg("in1.csv").arg("in2.csv");
+    wrk.assert_err(&mut cmd);
+
+    cmd.arg("--ignore-case");
+    let got: String = wrk.stdout(&mut cmd);
+    let expected = "\
+name  in1.csv  in2.csv
+a     \u{2713}        \u{2713}
+B     \u{2713}        \u{2713}";
+    assert_eq!(got, expected.to_string());
+}
+
+#[test]
+fn headers_json() {
+    let wrk = Workdir::new("headers_json");
+    wrk.create("in.csv", vec![svec!["a\"q", "line\nbreak"]]);
+
+    let mut cmd = wrk.command("headers");
+    cmd.arg("--json").arg("in.csv");
+
+    let got: String = wrk.stdout(&mut cmd);
+    let expected = r#"[
+  {"index": 1, "name": "a\"q"},
+  {"index": 2, "name": "line\nbreak"}
+]"#;
+    assert_eq!(got, expected.to_string());
+}
+
+#[test]
+fn headers_json_multiple() {
+    let (wrk, mut cmd) = setup("headers_json_multiple");
+    cmd.arg("in2.csv").arg("--json").arg("--just-names");
+
+    let got: String = wrk.stdout(&mut cmd);
+    let expected = r#"{
+  "in1.csv": [
+    {"name": "h1"},
+    {"name": "h2"}
+  ],
+  "in2.csv": [
+    {"name": "h2"},
+    {"name": "h3"}
+  ]
+}"#;
+    assert_eq!(got, expected.to_string());
+}
+
+#[test]
+fn headers_json_intersect() {
+    let (wrk, mut cmd) = setup("headers_json_intersect");
+    cmd.arg("in2.csv").arg("--json").arg("--intersect");
+
+    let got: String = wrk.stdout(&mut cmd);
+    let expected = r#"[
+  {"index": 1, "name": "h1"},
+  {"index": 2, "name": "h2"},
+  {"index": 3, "name": "h3"}
+]"#;
+    assert_eq!(got, expected.to_string());
+}
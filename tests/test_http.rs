@@ -0,0 +1,190 @@
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpListener;
+use std::thread;
+
+use workdir::Workdir;
+
+const DATA: &str = "city,pop\nBoston,667137\nLima,9751717\n";
+
+/// Serves HTTP requests on a local port, and returns the URL of `path` on
+/// it. `respond` gets the request (without its body) and returns the whole
+/// response.
+fn serve<F>(path: &str, respond: F) -> String
+        where F: Fn(&str) -> Vec<u8> + Send + 'static {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = stream.unwrap();
+            let mut request = String::new();
+            {
+                let mut rdr = BufReader::new(&mut stream);
+                loop {
+                    let mut line = String::new();
+                    if rdr.read_line(&mut line).unwrap() == 0
+                        || line == "\r\n" {
+                        break;
+                    }
+                    request.push_str(&line);
+                }
+            }
+            let _ = stream.write_all(&respond(&request));
+        }
+    });
+    format!("http://{}/{}", addr, path)
+}
+
+fn ok(body: &[u8]) -> Vec<u8> {
+    let mut response = format!(
+        "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        body.len()).into_bytes();
+    response.extend_from_slice(body);
+    response
+}
+
+fn status(line: &str) -> Vec<u8> {
+    format!("HTTP/1.1 {}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+            line).into_bytes()
+}
+
+#[test]
+fn http_read() {
+    let wrk = Workdir::new("http_read");
+    let url = serve("data.csv", |_| ok(DATA.as_bytes()));
+    let mut cmd = wrk.command("select");
+    cmd.arg("city").arg(&url);
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got, vec![svec!["city"], svec!["Boston"], svec!["Lima"]]);
+}
+
+#[test]
+fn http_read_gzip() {
+    let wrk = Workdir::new("http_read_gzip");
+    wrk.create("in.csv", vec![svec!["a"], svec!["1"], svec!["2"]]);
+    let mut cmd = wrk.command("fmt");
+    cmd.args(["in.csv", "-o", "in.csv.gz"]);
+    wrk.run(&mut cmd);
+    let gz = ::std::fs::read(wrk.path("in.csv.gz")).unwrap();
+
+    // Compressed data is detected by its first bytes, since the path of a
+    // URL doesn't have to end in '.gz'.
+    let url = serve("export?format=csv", move |_| ok(&gz));
+    let mut cmd = wrk.command("count");
+    cmd.arg(&url);
+    let got: String = wrk.stdout(&mut cmd);
+    assert_eq!(got, "2");
+}
+
+#[test]
+fn http_header() {
+    let wrk = Workdir::new("http_header");
+    let url = serve("data.csv", |request| {
+        if request.contains("\r\nAuthorization: Bearer secret\r\n") {
+            ok(DATA.as_bytes())
+        } else {
+            status("403 Forbidden")
+        }
+    });
+    let mut cmd = wrk.command("count");
+    cmd.arg(&url);
+    wrk.assert_err(&mut cmd);
+
+    let mut cmd = wrk.command("--http-header");
+    cmd.args(["Authorization: Bearer secret", "--http-header", "X-A: 1"]);
+    cmd.arg("count").arg(&url);
+    let got: String = wrk.stdout(&mut cmd);
+    assert_eq!(got, "2");
+}
+
+#[cfg(unix)]
+#[test]
+fn http_header_not_in_arguments() {
+    use std::env;
+    use std::fs;
+    use std::os::unix::fs::PermissionsExt;
+
+    // A stand-in for curl that records its arguments and standard input.
+    let wrk = Workdir::new("http_header_not_in_arguments");
+    let bin = wrk.path("bin");
+    fs::create_dir_all(&bin).unwrap();
+    let curl = bin.join("curl");
+    fs::write(&curl, format!(
+        "#!/bin/sh\necho \"$@\" > args.txt\ncat > stdin.txt\nprintf '{}'\n",
+        DATA.replace('\n', "\\n"))).unwrap();
+    fs::set_permissions(&curl, fs::Permissions::from_mode(0o755)).unwrap();
+    let path = format!("{}:{}", bin.display(),
+                       env::var("PATH").unwrap_or_default());
+
+    let mut cmd = wrk.command("--http-header");
+    cmd.args(["Authorization: Bearer secret", "count",
+              "http://example.com/data.csv"]);
+    cmd.env("PATH", path);
+    let got: String = wrk.stdout(&mut cmd);
+    assert_eq!(got, "2");
+    let args = fs::read_to_string(wrk.path("args.txt")).unwrap();
+    assert!(!args.contains("secret"), "{}", args);
+    let stdin = fs::read_to_string(wrk.path("stdin.txt")).unwrap();
+    assert_eq!(stdin, "Authorization: Bearer secret\n");
+}
+
+#[test]
+fn http_not_found() {
+    let wrk = Workdir::new("http_not_found");
+    let url = serve("missing.csv", |_| status("404 Not Found"));
+    let mut cmd = wrk.command("count");
+    cmd.arg(&url);
+    let o = cmd.output().unwrap();
+    assert!(!o.status.success());
+    let stderr = String::from_utf8_lossy(&o.stderr);
+    assert!(stderr.contains(&format!("failed to read {}", url)), "{}", stderr);
+    assert!(stderr.contains("404"), "{}", stderr);
+}
+
+#[test]
+fn http_cut_off() {
+    let wrk = Workdir::new("http_cut_off");
+    // The connection closes before the whole body was sent.
+    let url = serve("data.csv", |_| {
+        format!("HTTP/1.1 200 OK\r\nContent-Length: 1000\r\n\r\n{}", DATA)
+            .into_bytes()
+    });
+    let mut cmd = wrk.command("cat");
+    cmd.arg("rows").arg(&url);
+    let o = cmd.output().unwrap();
+    assert!(!o.status.success());
+    let stderr = String::from_utf8_lossy(&o.stderr);
+    assert!(stderr.contains(&format!("failed to read {}", url)), "{}", stderr);
+}
+
+#[test]
+fn http_download() {
+    let wrk = Workdir::new("http_download");
+    wrk.create("pops.csv", vec![svec!["city", "country"],
+                                svec!["Lima", "PE"]]);
+    let url = serve("data.csv", |_| ok(DATA.as_bytes()));
+
+    let mut cmd = wrk.command("join");
+    cmd.arg("city").arg(&url).args(["city", "pops.csv"]);
+    let o = cmd.output().unwrap();
+    assert!(!o.status.success());
+    let stderr = String::from_utf8_lossy(&o.stderr);
+    assert!(stderr.contains("--download"), "{}", stderr);
+
+    let mut cmd = wrk.command("--download");
+    cmd.arg("join").arg("city").arg(&url).args(["city", "pops.csv"]);
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["city", "pop", "city", "country"],
+        svec!["Lima", "9751717", "Lima", "PE"],
+    ];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn http_index() {
+    let wrk = Workdir::new("http_index");
+    let url = serve("data.csv", |_| ok(DATA.as_bytes()));
+    let mut cmd = wrk.command("index");
+    cmd.arg(&url);
+    wrk.assert_err(&mut cmd);
+}
@@ -0,0 +1,95 @@
+use workdir::Workdir;
+
+fn data() -> Vec<Vec<String>> {
+    vec![
+        svec!["user", "tag"],
+        svec!["1", "red"],
+        svec!["2", "green"],
+        svec!["1", "blue"],
+        svec!["1", "red"],
+    ]
+}
+
+#[test]
+fn implode() {
+    let wrk = Workdir::new("implode");
+    wrk.create("in.csv", data());
+
+    let mut cmd = wrk.command("implode");
+    cmd.arg("tag").arg(";").arg("in.csv");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["user", "tag"],
+        svec!["1", "red;blue;red"],
+        svec!["2", "green"],
+    ];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn implode_unique() {
+    let wrk = Workdir::new("implode_unique");
+    wrk.create("in.csv", data());
+
+    let mut cmd = wrk.command("implode");
+    cmd.arg("--unique").arg("tag").arg(";").arg("in.csv");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["user", "tag"],
+        svec!["1", "red;blue"],
+        svec!["2", "green"],
+    ];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn implode_sorted() {
+    let wrk = Workdir::new("implode_sorted");
+    wrk.create("in.csv", data());
+
+    let mut cmd = wrk.command("implode");
+    cmd.arg("--sorted").arg("tag").arg(";").arg("in.csv");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["user", "tag"],
+        svec!["1", "red"],
+        svec!["2", "green"],
+        svec!["1", "blue;red"],
+    ];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn implode_select() {
+    let wrk = Workdir::new("implode_select");
+    wrk.create("in.csv", vec![
+        svec!["user", "name", "tag"],
+        svec!["1", "x", "red"],
+        svec!["1", "y", "blue"],
+    ]);
+
+    let mut cmd = wrk.command("implode");
+    cmd.arg("-s").arg("user").arg("tag").arg(";").arg("in.csv");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got, vec![svec!["user", "name", "tag"], svec!["1", "x", "red;blue"]]);
+}
+
+#[test]
+fn implode_explode_round_trip() {
+    let wrk = Workdir::new("implode_explode_round_trip");
+    let rows = vec![
+        svec!["user", "tags"],
+        svec!["1", "red;\"blue, dark\""],
+        svec!["2", "green"],
+    ];
+    wrk.create("in.csv", rows.clone());
+
+    let mut cmd = wrk.command("explode");
+    cmd.arg("tags").arg(";").arg("in.csv").arg("-o").arg("exploded.csv");
+    wrk.run(&mut cmd);
+
+    let mut cmd = wrk.command("implode");
+    cmd.arg("tags").arg(";").arg("exploded.csv");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got, rows);
+}
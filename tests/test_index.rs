@@ -1,4 +1,5 @@
 use std::fs;
+use std::process;
 
 use filetime::{FileTime, set_file_times};
 
@@ -25,3 +26,254 @@ fn future_time(ft: FileTime) -> FileTime {
     let secs = ft.seconds_relative_to_1970();
     FileTime::from_seconds_since_1970(secs + 10_000, 0)
 }
+
+fn rows() -> Vec<Vec<String>> {
+    vec![svec!["h"], svec!["a"], svec!["b"]]
+}
+
+#[test]
+fn index_check_fresh() {
+    let wrk = Workdir::new("index_check_fresh");
+    wrk.create_indexed("in.csv", rows());
+
+    let mut cmd = wrk.command("index");
+    cmd.arg("--check").arg("in.csv");
+
+    let got: String = wrk.stdout(&mut cmd);
+    assert_eq!(got, "in.csv.idx: up to date".to_string());
+}
+
+#[test]
+fn index_check_missing() {
+    let wrk = Workdir::new("index_check_missing");
+    wrk.create("in.csv", rows());
+
+    let mut cmd = wrk.command("index");
+    cmd.arg("--check").arg("in.csv");
+    wrk.assert_err(&mut cmd);
+}
+
+#[test]
+fn index_stale_size() {
+    let wrk = Workdir::new("index_stale_size");
+    wrk.create_indexed("in.csv", rows());
+    wrk.create("in.csv", vec![svec!["h"], svec!["a"], svec!["b"], svec!["c"]]);
+
+    let mut cmd = wrk.command("count");
+    cmd.arg("in.csv");
+    let output = cmd.output().unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("is stale"), "unexpected stderr: {}", stderr);
+
+    let mut cmd = wrk.command("index");
+    cmd.arg("--check").arg("in.csv");
+    wrk.assert_err(&mut cmd);
+}
+
+#[test]
+fn index_auto_index() {
+    let wrk = Workdir::new("index_auto_index");
+    wrk.create_indexed("in.csv", rows());
+    wrk.create("in.csv", vec![svec!["h"], svec!["a"], svec!["b"], svec!["c"]]);
+
+    let mut cmd = wrk.command("count");
+    cmd.arg("--auto-index").arg("in.csv");
+    let got: String = wrk.stdout(&mut cmd);
+    assert_eq!(got, "3".to_string());
+
+    let mut cmd = wrk.command("index");
+    cmd.arg("--check").arg("in.csv");
+    wrk.run(&mut cmd);
+}
+
+#[test]
+fn index_old_format() {
+    let wrk = Workdir::new("index_old_format");
+    wrk.create("in.csv", rows());
+    // An index in the format written by older versions of xsv: the offset
+    // of every record followed by the number of records.
+    let mut idx = vec![];
+    for &n in &[0u64, 2, 4, 3] {
+        for i in (0..8).rev() {
+            idx.push((n >> (i * 8)) as u8);
+        }
+    }
+    fs::write(wrk.path("in.csv.idx"), idx).unwrap();
+
+    let mut cmd = wrk.command("slice");
+    cmd.arg("-i").arg("1").arg("in.csv");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got, vec![svec!["h"], svec!["b"]]);
+}
+
+/// Writes `data` gzip compressed to `name`, which must end in `.gz`, with
+/// the `gzip` program.
+fn create_gzip(wrk: &Workdir, name: &str, data: &str) {
+    let plain = wrk.path(&name[..name.len() - 3]);
+    fs::write(&plain, data).unwrap();
+    let status = process::Command::new("gzip")
+        .arg("-f").arg("-n").arg(&plain)
+        .status()
+        .unwrap();
+    assert!(status.success());
+}
+
+fn numbered_rows(n: usize) -> String {
+    let mut data = "n,square\n".to_owned();
+    for i in 0..n {
+        data.push_str(&format!("{},{}\n", i, i * i));
+    }
+    data
+}
+
+#[test]
+fn index_gzip_slice() {
+    let wrk = Workdir::new("index_gzip_slice");
+    create_gzip(&wrk, "in.csv.gz", &numbered_rows(50_000));
+
+    let mut cmd = wrk.command("index");
+    cmd.arg("--checkpoint-span").arg("4096").arg("in.csv.gz");
+    wrk.run(&mut cmd);
+
+    let mut cmd = wrk.command("count");
+    cmd.arg("in.csv.gz");
+    let got: String = wrk.stdout(&mut cmd);
+    assert_eq!(got, "50000".to_string());
+
+    // Seek forwards and then backwards again.
+    for &i in &["40000", "123", "49999"] {
+        let mut cmd = wrk.command("slice");
+        cmd.arg("-i").arg(i).arg("in.csv.gz");
+        let got: String = wrk.stdout(&mut cmd);
+        let i: u64 = i.parse().unwrap();
+        assert_eq!(got, format!("n,square\n{},{}", i, i * i));
+    }
+}
+
+#[test]
+fn index_gzip_multiple_members() {
+    let wrk = Workdir::new("index_gzip_multiple_members");
+    create_gzip(&wrk, "a.gz", "h\na\n");
+    create_gzip(&wrk, "b.gz", "b\nc\n");
+    let mut data = fs::read(wrk.path("a.gz")).unwrap();
+    data.extend(fs::read(wrk.path("b.gz")).unwrap());
+    fs::write(wrk.path("in.csv.gz"), data).unwrap();
+
+    let mut cmd = wrk.command("cat");
+    cmd.arg("rows").arg("in.csv.gz");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got, vec![svec!["h"], svec!["a"], svec!["b"], svec!["c"]]);
+
+    let mut cmd = wrk.command("index");
+    cmd.arg("in.csv.gz");
+    wrk.run(&mut cmd);
+
+    let mut cmd = wrk.command("slice");
+    cmd.arg("-i").arg("2").arg("in.csv.gz");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got, vec![svec!["h"], svec!["c"]]);
+}
+
+#[test]
+fn index_gzip_stale() {
+    let wrk = Workdir::new("index_gzip_stale");
+    create_gzip(&wrk, "in.csv.gz", "h\na\n");
+    let mut cmd = wrk.command("index");
+    cmd.arg("in.csv.gz");
+    wrk.run(&mut cmd);
+    create_gzip(&wrk, "in.csv.gz", "h\na\nb\n");
+
+    let mut cmd = wrk.command("index");
+    cmd.arg("--check").arg("in.csv.gz");
+    wrk.assert_err(&mut cmd);
+}
+
+fn create_shards(wrk: &Workdir) {
+    wrk.create("ev-0.csv", vec![svec!["a", "b"], svec!["0", "x"]]);
+    wrk.create("ev-1.csv", vec![svec!["a", "b"]]);
+    wrk.create("ev-2.csv",
+               vec![svec!["a", "b"], svec!["2", "x"], svec!["2", "y"]]);
+
+    let mut cmd = wrk.command("index");
+    cmd.arg("--multi").arg("ev-*.csv").arg("-o").arg("ev.multi-idx");
+    wrk.run(&mut cmd);
+}
+
+#[test]
+fn index_multi_count_slice() {
+    let wrk = Workdir::new("index_multi_count_slice");
+    create_shards(&wrk);
+
+    let mut cmd = wrk.command("count");
+    cmd.arg("ev.multi-idx");
+    let got: String = wrk.stdout(&mut cmd);
+    assert_eq!(got, "3".to_string());
+
+    let mut cmd = wrk.command("slice");
+    cmd.arg("-s").arg("0").arg("-e").arg("3").arg("ev.multi-idx");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got, vec![
+        svec!["a", "b"], svec!["0", "x"], svec!["2", "x"], svec!["2", "y"],
+    ]);
+
+    let mut cmd = wrk.command("slice");
+    cmd.arg("-i").arg("2").arg("ev.multi-idx");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got, vec![svec!["a", "b"], svec!["2", "y"]]);
+}
+
+#[test]
+fn index_multi_sample() {
+    let wrk = Workdir::new("index_multi_sample");
+    create_shards(&wrk);
+
+    let mut cmd = wrk.command("sample");
+    cmd.arg("10").arg("ev.multi-idx");
+    let mut got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    got[1..].sort();
+    assert_eq!(got, vec![
+        svec!["a", "b"], svec!["0", "x"], svec!["2", "x"], svec!["2", "y"],
+    ]);
+}
+
+#[test]
+fn index_multi_different_headers() {
+    let wrk = Workdir::new("index_multi_different_headers");
+    wrk.create("ev-0.csv", vec![svec!["a", "b"], svec!["0", "x"]]);
+    wrk.create("ev-1.csv", vec![svec!["a", "c"], svec!["1", "x"]]);
+
+    let mut cmd = wrk.command("index");
+    cmd.arg("--multi").arg("ev-*.csv").arg("-o").arg("ev.multi-idx");
+    wrk.assert_err(&mut cmd);
+
+    let mut cmd = wrk.command("index");
+    cmd.arg("--multi").arg("--no-headers")
+       .arg("ev-*.csv").arg("-o").arg("ev.multi-idx");
+    wrk.run(&mut cmd);
+
+    let mut cmd = wrk.command("count");
+    cmd.arg("--no-headers").arg("ev.multi-idx");
+    let got: String = wrk.stdout(&mut cmd);
+    assert_eq!(got, "4".to_string());
+}
+
+#[test]
+fn index_multi_stale() {
+    let wrk = Workdir::new("index_multi_stale");
+    create_shards(&wrk);
+    wrk.create("ev-3.csv", vec![svec!["a", "b"], svec!["3", "x"]]);
+
+    let mut cmd = wrk.command("count");
+    cmd.arg("ev.multi-idx");
+    let output = cmd.output().unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("was added"), "unexpected stderr: {}", stderr);
+
+    fs::remove_file(wrk.path("ev-3.csv")).unwrap();
+    wrk.create("ev-1.csv", vec![svec!["a", "b"], svec!["1", "x"]]);
+    let mut cmd = wrk.command("slice");
+    cmd.arg("-i").arg("0").arg("ev.multi-idx");
+    wrk.assert_err(&mut cmd);
+}
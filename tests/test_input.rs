@@ -0,0 +1,57 @@
+use std::fs;
+
+use workdir::Workdir;
+
+#[test]
+fn input_escape() {
+    let wrk = Workdir::new("input_escape");
+    fs::write(wrk.path("in.csv"), "a,\"b \\\"c\\\" d\"\n").unwrap();
+
+    let mut cmd = wrk.command("input");
+    cmd.arg("--escape").arg("\\").arg("in.csv");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got, vec![svec!["a", "b \"c\" d"]]);
+}
+
+#[test]
+fn input_skip_lines() {
+    let wrk = Workdir::new("input_skip_lines");
+    fs::write(wrk.path("in.csv"),
+              "Report of 2018\n# generated\n#\nh1,h2\n#x,y\n").unwrap();
+
+    let mut cmd = wrk.command("input");
+    cmd.arg("--skip-lines").arg("1")
+       .arg("--skip-lines-matching").arg("^#")
+       .arg("in.csv");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got, vec![svec!["h1", "h2"], svec!["#x", "y"]]);
+}
+
+#[test]
+fn input_trim_latin1_report() {
+    let wrk = Workdir::new("input_trim_latin1_report");
+    fs::write(wrk.path("in.csv"), b"h1,h2\n caf\xe9 ,b\nc,d,e\n").unwrap();
+
+    let mut cmd = wrk.command("input");
+    cmd.arg("--trim").arg("--encoding").arg("latin1").arg("--report")
+       .arg("in.csv");
+    let output = cmd.output().unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert_eq!(stdout, "h1,h2\ncafé,b\nc,d,e\n");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("trimmed 1 field(s)"), "{}", stderr);
+    assert!(stderr.contains("converted 1 field(s) to UTF-8"), "{}", stderr);
+    assert!(stderr.contains("found 1 record(s)"), "{}", stderr);
+}
+
+#[test]
+fn input_quote_style() {
+    let wrk = Workdir::new("input_quote_style");
+    wrk.create("in.csv", vec![svec!["a", "1"]]);
+
+    let mut cmd = wrk.command("input");
+    cmd.arg("--quote-style").arg("always").arg("in.csv");
+    let got: String = wrk.stdout(&mut cmd);
+    assert_eq!(got, "\"a\",\"1\"");
+}
@@ -0,0 +1,96 @@
+use std::fs;
+
+use workdir::Workdir;
+
+fn data() -> Vec<Vec<String>> {
+    vec![
+        svec!["id", "name", "address.city", "address.zip", "active"],
+        svec!["1", "a \"b\"", "Paris", "007", "TRUE"],
+        svec!["2.5", "", "Rome", "00100", "null"],
+    ]
+}
+
+#[test]
+fn json_array() {
+    let wrk = Workdir::new("json_array");
+    wrk.create("in.csv", data());
+
+    let mut cmd = wrk.command("json");
+    cmd.arg("-s").arg("id,name").arg("in.csv");
+    let got: String = wrk.stdout(&mut cmd);
+    let expected = "[\n  {\"id\": \"1\", \"name\": \"a \\\"b\\\"\"},\n  \
+                    {\"id\": \"2.5\", \"name\": \"\"}\n]";
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn json_empty() {
+    let wrk = Workdir::new("json_empty");
+    wrk.create("in.csv", vec![svec!["id"]]);
+
+    let mut cmd = wrk.command("json");
+    cmd.arg("in.csv");
+    let got: String = wrk.stdout(&mut cmd);
+    assert_eq!(got, "[]");
+}
+
+#[test]
+fn json_lines_infer_types() {
+    let wrk = Workdir::new("json_lines_infer_types");
+    wrk.create("in.csv", data());
+
+    let mut cmd = wrk.command("json");
+    cmd.arg("--jsonl").arg("--infer-types").arg("in.csv");
+    let got: String = wrk.stdout(&mut cmd);
+    let expected = "\
+{\"id\": 1, \"name\": \"a \\\"b\\\"\", \"address.city\": \"Paris\", \
+\"address.zip\": \"007\", \"active\": true}
+{\"id\": 2.5, \"name\": null, \"address.city\": \"Rome\", \
+\"address.zip\": \"00100\", \"active\": null}";
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn json_nest() {
+    let wrk = Workdir::new("json_nest");
+    wrk.create("in.csv", data());
+
+    let mut cmd = wrk.command("json");
+    cmd.arg("--jsonl").arg("--nest").arg(".").arg("-s").arg("1,3-4")
+       .arg("in.csv");
+    let got: String = wrk.stdout(&mut cmd);
+    let expected = "\
+{\"id\": \"1\", \"address\": {\"city\": \"Paris\", \"zip\": \"007\"}}
+{\"id\": \"2.5\", \"address\": {\"city\": \"Rome\", \"zip\": \"00100\"}}";
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn json_nest_conflict() {
+    let wrk = Workdir::new("json_nest_conflict");
+    // The same conflict is reported in either order of the columns.
+    for headers in &[svec!["a", "a.b.c"], svec!["a.b.c", "a"]] {
+        wrk.create("in.csv", vec![headers.clone(), svec!["1", "2"]]);
+
+        let mut cmd = wrk.command("json");
+        cmd.arg("--nest").arg(".").arg("in.csv");
+        let output = cmd.output().unwrap();
+        assert!(!output.status.success());
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(stderr.contains("Cannot nest 'a.b.c' inside the value of 'a'"),
+                "{:?}: {}", headers, stderr);
+    }
+}
+
+#[test]
+fn json_no_headers_invalid_utf8() {
+    let wrk = Workdir::new("json_no_headers_invalid_utf8");
+    fs::write(wrk.path("in.csv"), b"a,b\xff\n").unwrap();
+
+    let mut cmd = wrk.command("json");
+    cmd.arg("--jsonl").arg("--no-headers").arg("in.csv");
+    let out = wrk.output(&mut cmd);
+    assert_eq!(String::from_utf8_lossy(&out.stdout),
+               "{\"field_1\": \"a\", \"field_2\": \"b\u{fffd}\"}\n");
+    assert!(String::from_utf8_lossy(&out.stderr).contains("1 field(s)"));
+}
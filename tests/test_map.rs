@@ -0,0 +1,104 @@
+use workdir::Workdir;
+
+fn data() -> Vec<Vec<String>> {
+    vec![
+        svec!["first", "last", "price", "qty"],
+        svec!["Ada", "Lovelace", "2.5", "4"],
+        svec!["Alan", "Turing", "3", "10"],
+    ]
+}
+
+#[test]
+fn map_arithmetic() {
+    let wrk = Workdir::new("map_arithmetic");
+    wrk.create("in.csv", data());
+
+    let mut cmd = wrk.command("map");
+    cmd.arg("total = price * qty - 1").arg("in.csv");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["first", "last", "price", "qty", "total"],
+        svec!["Ada", "Lovelace", "2.5", "4", "9"],
+        svec!["Alan", "Turing", "3", "10", "29"],
+    ];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn map_multiple_expressions() {
+    let wrk = Workdir::new("map_multiple_expressions");
+    wrk.create("in.csv", data());
+
+    let mut cmd = wrk.command("map");
+    cmd.arg("-e").arg("name = concat(first, \" \", upper(last))")
+       .arg("-e").arg("size = if(len(name) > 11, \"long\", \"short\")")
+       .arg("-e").arg("price = round(price / 3, 2)")
+       .arg("in.csv");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["first", "last", "price", "qty", "name", "size"],
+        svec!["Ada", "Lovelace", "0.83", "4", "Ada LOVELACE", "long"],
+        svec!["Alan", "Turing", "1", "10", "Alan TURING", "short"],
+    ];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn map_one_expr_flag() {
+    let wrk = Workdir::new("map_one_expr_flag");
+    wrk.create("in.csv", data());
+
+    let mut cmd = wrk.command("map");
+    cmd.arg("-e").arg("p = round(price, 400)").arg("in.csv");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["first", "last", "price", "qty", "p"],
+        svec!["Ada", "Lovelace", "2.5", "4", "2.5"],
+        svec!["Alan", "Turing", "3", "10", "3"],
+    ];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn map_filter() {
+    let wrk = Workdir::new("map_filter");
+    wrk.create("in.csv", data());
+
+    let mut cmd = wrk.command("map");
+    cmd.arg("--filter").arg("qty >= 5 or substr(`first`, 0, 2) == 'Zz'")
+       .arg("in.csv");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["first", "last", "price", "qty"],
+        svec!["Alan", "Turing", "3", "10"],
+    ];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn map_type_error() {
+    let wrk = Workdir::new("map_type_error");
+    wrk.create("in.csv", data());
+
+    let mut cmd = wrk.command("map");
+    cmd.arg("x = last * 2").arg("in.csv");
+    let output = cmd.output().unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("line 2"), "{}", stderr);
+    assert!(stderr.contains("'Lovelace' is not a number"), "{}", stderr);
+}
+
+#[test]
+fn map_syntax_error() {
+    let wrk = Workdir::new("map_syntax_error");
+    wrk.create("in.csv", data());
+
+    let mut cmd = wrk.command("map");
+    cmd.arg("x = (price + ").arg("in.csv");
+    wrk.assert_err(&mut cmd);
+
+    let mut cmd = wrk.command("map");
+    cmd.arg("x = nope + 1").arg("in.csv");
+    wrk.assert_err(&mut cmd);
+}
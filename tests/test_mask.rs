@@ -0,0 +1,78 @@
+use std::fs;
+
+use workdir::Workdir;
+
+fn data() -> Vec<Vec<String>> {
+    vec![
+        svec!["email", "card", "notes"],
+        svec!["alice@example.com", "4111111111111111", "called twice"],
+        svec!["bob@example.com", "5500000000000004", ""],
+        svec!["alice@example.com", "12", "vip"],
+    ]
+}
+
+#[test]
+fn mask_hash_select() {
+    let wrk = Workdir::new("mask_hash_select");
+    wrk.create("in.csv", data());
+
+    let mut cmd = wrk.command("mask");
+    cmd.arg("-s").arg("email").arg("--key").arg("test-key").arg("in.csv");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let alice =
+        "f4ec100211f13d19d596a3b4a8d60f6a5ccf3d3a3c3c9fece41d1ff21e5475dd";
+    let bob =
+        "30f050000475abe14008c9063d27e977ea23f2049b708f77176837aa94aa46a5";
+    let expected = vec![
+        svec!["email", "card", "notes"],
+        svec![alice, "4111111111111111", "called twice"],
+        svec![bob, "5500000000000004", ""],
+        svec![alice, "12", "vip"],
+    ];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn mask_rules() {
+    let wrk = Workdir::new("mask_rules");
+    wrk.create("in.csv", data());
+    fs::write(wrk.path("key.txt"), "secret\n").unwrap();
+
+    let mut cmd = wrk.command("mask");
+    cmd.arg("--key-file").arg("key.txt")
+       .arg("-r").arg("email:hash:base64")
+       .arg("-r").arg("card:partial:0,4")
+       .arg("-r").arg("notes:redact:[removed]")
+       .arg("in.csv");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got[1], svec![
+        "o5jUnOGYCzZCvE29EQEh48lT4erbSX1Q3qI+lhH4Puc=",
+        "************1111",
+        "[removed]",
+    ]);
+    assert_eq!(got[2][1..].to_vec(), svec!["************0004", ""]);
+    assert_eq!(got[3][1..].to_vec(), svec!["**", "[removed]"]);
+}
+
+#[test]
+fn mask_rules_override_select() {
+    let wrk = Workdir::new("mask_rules_override_select");
+    wrk.create("in.csv", data());
+
+    let mut cmd = wrk.command("mask");
+    cmd.arg("-s").arg("card,notes").arg("--strategy").arg("redact")
+       .arg("-r").arg("notes:null").arg("in.csv");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got[1], svec!["alice@example.com", "REDACTED", ""]);
+    assert_eq!(got[3], svec!["alice@example.com", "REDACTED", ""]);
+}
+
+#[test]
+fn mask_hash_requires_key() {
+    let wrk = Workdir::new("mask_hash_requires_key");
+    wrk.create("in.csv", data());
+
+    let mut cmd = wrk.command("mask");
+    cmd.arg("-r").arg("email:hash").arg("in.csv");
+    wrk.assert_err(&mut cmd);
+}
@@ -0,0 +1,61 @@
+use workdir::Workdir;
+
+fn data() -> Vec<Vec<String>> {
+    vec![
+        svec!["id", "name", "jan", "feb"],
+        svec!["1", "a", "10", ""],
+        svec!["2", "b", "30", "40"],
+    ]
+}
+
+#[test]
+fn melt() {
+    let wrk = Workdir::new("melt");
+    wrk.create("in.csv", data());
+
+    let mut cmd = wrk.command("melt");
+    cmd.arg("--id").arg("id,name").arg("in.csv");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["id", "name", "variable", "value"],
+        svec!["1", "a", "jan", "10"],
+        svec!["1", "a", "feb", ""],
+        svec!["2", "b", "jan", "30"],
+        svec!["2", "b", "feb", "40"],
+    ];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn melt_value_columns_renamed() {
+    let wrk = Workdir::new("melt_value_columns_renamed");
+    wrk.create("in.csv", data());
+
+    let mut cmd = wrk.command("melt");
+    cmd.arg("--id").arg("id")
+       .arg("--value-columns").arg("jan-feb")
+       .arg("--variable-name").arg("month")
+       .arg("--value-name").arg("amount")
+       .arg("--drop-empty")
+       .arg("in.csv");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["id", "month", "amount"],
+        svec!["1", "jan", "10"],
+        svec!["2", "jan", "30"],
+        svec!["2", "feb", "40"],
+    ];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn melt_negated_id() {
+    let wrk = Workdir::new("melt_negated_id");
+    wrk.create("in.csv", data());
+
+    let mut cmd = wrk.command("melt");
+    cmd.arg("--id").arg("!jan-feb").arg("in.csv");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got[0], svec!["id", "name", "variable", "value"]);
+    assert_eq!(got.len(), 5);
+}
@@ -0,0 +1,67 @@
+use workdir::Workdir;
+
+#[test]
+fn merge_numeric() {
+    let wrk = Workdir::new("merge_numeric");
+    wrk.create("a.csv", vec![
+        svec!["n", "src"], svec!["1", "a"], svec!["5", "a"], svec!["10", "a"],
+    ]);
+    wrk.create("b.csv", vec![
+        svec!["n", "src"], svec!["2", "b"], svec!["5", "b"], svec!["7", "b"],
+    ]);
+
+    let mut cmd = wrk.command("merge");
+    cmd.arg("-N").arg("-s").arg("n").arg("a.csv").arg("b.csv");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["n", "src"],
+        svec!["1", "a"],
+        svec!["2", "b"],
+        svec!["5", "a"],
+        svec!["5", "b"],
+        svec!["7", "b"],
+        svec!["10", "a"],
+    ];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn merge_reverse() {
+    let wrk = Workdir::new("merge_reverse");
+    wrk.create("a.csv", vec![svec!["x"], svec!["c"], svec!["a"]]);
+    wrk.create("b.csv", vec![svec!["x"], svec!["d"], svec!["b"]]);
+    wrk.create("c.csv", vec![svec!["x"]]);
+
+    let mut cmd = wrk.command("merge");
+    cmd.arg("-R").arg("a.csv").arg("b.csv").arg("c.csv");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["x"], svec!["d"], svec!["c"], svec!["b"], svec!["a"],
+    ];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn merge_out_of_order() {
+    let wrk = Workdir::new("merge_out_of_order");
+    wrk.create("a.csv", vec![svec!["x"], svec!["a"], svec!["c"]]);
+    wrk.create("b.csv", vec![svec!["x"], svec!["d"], svec!["b"]]);
+
+    let mut cmd = wrk.command("merge");
+    cmd.arg("a.csv").arg("b.csv");
+    let output = cmd.output().unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("line 3 of b.csv"), "{}", stderr);
+}
+
+#[test]
+fn merge_different_headers() {
+    let wrk = Workdir::new("merge_different_headers");
+    wrk.create("a.csv", vec![svec!["x"], svec!["a"]]);
+    wrk.create("b.csv", vec![svec!["y"], svec!["b"]]);
+
+    let mut cmd = wrk.command("merge");
+    cmd.arg("a.csv").arg("b.csv");
+    wrk.assert_err(&mut cmd);
+}
@@ -0,0 +1,402 @@
+use std::fs;
+
+use workdir::Workdir;
+
+fn data() -> Vec<Vec<String>> {
+    vec![
+        svec!["id", "name", "score"],
+        svec!["1", "alpha", "1.5"],
+        svec!["2", "beta", ""],
+        svec!["3", "", "x"],
+    ]
+}
+
+fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+    haystack.windows(needle.len()).any(|w| w == needle)
+}
+
+#[test]
+fn parquet_file_layout() {
+    let wrk = Workdir::new("parquet_file_layout");
+    wrk.create("in.csv", data());
+
+    let mut cmd = wrk.command("parquet");
+    cmd.arg("--compression").arg("none").arg("in.csv")
+       .arg("-o").arg("out.parquet");
+    wrk.run(&mut cmd);
+
+    let got = fs::read(wrk.path("out.parquet")).unwrap();
+    assert_eq!(&got[..4], b"PAR1");
+    assert_eq!(&got[got.len() - 4..], b"PAR1");
+    let mut len = [0; 4];
+    len.copy_from_slice(&got[got.len() - 8..got.len() - 4]);
+    let meta = &got[got.len() - 8 - u32::from_le_bytes(len) as usize..];
+    assert!(contains(meta, b"name"));
+    assert!(contains(meta, b"score"));
+    // Without compression, the PLAIN encoded strings are stored verbatim.
+    assert!(contains(&got, b"\x05\x00\x00\x00alpha\x04\x00\x00\x00beta"));
+}
+
+#[test]
+fn parquet_type_mismatch() {
+    let wrk = Workdir::new("parquet_type_mismatch");
+    wrk.create("in.csv", data());
+
+    let mut cmd = wrk.command("parquet");
+    cmd.arg("--sample-rows").arg("2").arg("in.csv")
+       .arg("-o").arg("out.parquet");
+    let out = cmd.output().unwrap();
+    assert!(!out.status.success());
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    assert!(stderr.contains("'x' of column 'score' on line 4"));
+}
+
+#[test]
+fn parquet_schema() {
+    let wrk = Workdir::new("parquet_schema");
+    wrk.create("in.csv", data());
+    wrk.create("schema.csv", vec![
+        svec!["column", "type"],
+        svec!["score", "string"],
+    ]);
+
+    let mut cmd = wrk.command("parquet");
+    cmd.arg("--sample-rows").arg("2").arg("--schema").arg("schema.csv")
+       .arg("in.csv").arg("-o").arg("out.parquet");
+    wrk.run(&mut cmd);
+
+    wrk.create("bad.csv", vec![
+        svec!["column", "type"],
+        svec!["nope", "string"],
+    ]);
+    let mut cmd = wrk.command("parquet");
+    cmd.arg("--schema").arg("bad.csv").arg("in.csv")
+       .arg("-o").arg("out.parquet");
+    wrk.assert_err(&mut cmd);
+}
+
+/// A value of the Thrift compact protocol, with the fields of structs by
+/// their ID.
+#[derive(Debug)]
+enum Thrift {
+    Int(i64),
+    Binary(Vec<u8>),
+    List(Vec<Thrift>),
+    Struct(Vec<(i16, Thrift)>),
+}
+
+impl Thrift {
+    fn field(&self, id: i16) -> &Thrift {
+        match *self {
+            Thrift::Struct(ref fields) => {
+                &fields.iter().find(|f| f.0 == id).unwrap().1
+            }
+            ref v => panic!("not a struct: {:?}", v),
+        }
+    }
+
+    fn int(&self, id: i16) -> i64 {
+        match *self.field(id) {
+            Thrift::Int(n) => n,
+            ref v => panic!("not an integer: {:?}", v),
+        }
+    }
+
+    fn string(&self, id: i16) -> String {
+        match *self.field(id) {
+            Thrift::Binary(ref b) => String::from_utf8(b.clone()).unwrap(),
+            ref v => panic!("not a string: {:?}", v),
+        }
+    }
+
+    fn list(&self, id: i16) -> &[Thrift] {
+        match *self.field(id) {
+            Thrift::List(ref l) => l,
+            ref v => panic!("not a list: {:?}", v),
+        }
+    }
+}
+
+fn read_uvarint(data: &[u8], pos: &mut usize) -> u64 {
+    let (mut n, mut shift) = (0u64, 0);
+    loop {
+        let b = data[*pos];
+        *pos += 1;
+        n |= u64::from(b & 0x7F) << shift;
+        if b < 0x80 {
+            return n;
+        }
+        shift += 7;
+    }
+}
+
+fn read_varint(data: &[u8], pos: &mut usize) -> i64 {
+    let n = read_uvarint(data, pos);
+    (n >> 1) as i64 ^ -((n & 1) as i64)
+}
+
+fn read_thrift(data: &[u8], pos: &mut usize, ty: u8) -> Thrift {
+    match ty {
+        1 => Thrift::Int(1),
+        2 => Thrift::Int(0),
+        4 | 5 | 6 => Thrift::Int(read_varint(data, pos)),
+        8 => {
+            let len = read_uvarint(data, pos) as usize;
+            *pos += len;
+            Thrift::Binary(data[*pos - len..*pos].to_vec())
+        }
+        9 => {
+            let b = data[*pos];
+            *pos += 1;
+            let mut len = (b >> 4) as usize;
+            if len == 15 {
+                len = read_uvarint(data, pos) as usize;
+            }
+            let elem = match b & 0x0F {
+                // Booleans in lists are a byte each.
+                1 | 2 => 3,
+                t => t,
+            };
+            Thrift::List((0..len).map(|_| {
+                if elem == 3 {
+                    *pos += 1;
+                    Thrift::Int(i64::from(data[*pos - 1]))
+                } else {
+                    read_thrift(data, pos, elem)
+                }
+            }).collect())
+        }
+        12 => {
+            let mut fields = vec![];
+            let mut last = 0i16;
+            loop {
+                let b = data[*pos];
+                *pos += 1;
+                if b == 0 {
+                    return Thrift::Struct(fields);
+                }
+                let id = match b >> 4 {
+                    0 => read_varint(data, pos) as i16,
+                    delta => last + i16::from(delta),
+                };
+                last = id;
+                fields.push((id, read_thrift(data, pos, b & 0x0F)));
+            }
+        }
+        ty => panic!("unsupported Thrift type {}", ty),
+    }
+}
+
+/// Decompresses raw Snappy data.
+fn unsnappy(data: &[u8]) -> Vec<u8> {
+    let mut pos = 0;
+    let len = read_uvarint(data, &mut pos) as usize;
+    let mut out = Vec::with_capacity(len);
+    while pos < data.len() {
+        let tag = data[pos];
+        pos += 1;
+        let (offset, n) = match tag & 3 {
+            0 => {
+                let mut n = (tag >> 2) as usize;
+                if n >= 60 {
+                    let k = n - 59;
+                    n = (0..k).map(|i| (data[pos + i] as usize) << (8 * i))
+                              .sum();
+                    pos += k;
+                }
+                out.extend_from_slice(&data[pos..pos + n + 1]);
+                pos += n + 1;
+                continue;
+            }
+            1 => {
+                pos += 1;
+                let offset = ((tag as usize >> 5) << 8) | data[pos - 1] as usize;
+                (offset, ((tag >> 2) & 7) as usize + 4)
+            }
+            2 => {
+                pos += 2;
+                let offset = data[pos - 2] as usize
+                             | (data[pos - 1] as usize) << 8;
+                (offset, (tag >> 2) as usize + 1)
+            }
+            _ => panic!("unexpected 4 byte offset"),
+        };
+        for _ in 0..n {
+            let b = out[out.len() - offset];
+            out.push(b);
+        }
+    }
+    assert_eq!(out.len(), len);
+    out
+}
+
+/// The column values of a Parquet file, as written by `xsv parquet`, with
+/// `None` for nulls.
+struct Parquet {
+    meta: Thrift,
+    columns: Vec<(String, Vec<Option<String>>)>,
+}
+
+fn read_parquet(data: &[u8]) -> Parquet {
+    assert_eq!(&data[..4], b"PAR1");
+    assert_eq!(&data[data.len() - 4..], b"PAR1");
+    let mut len = [0; 4];
+    len.copy_from_slice(&data[data.len() - 8..data.len() - 4]);
+    let mut pos = data.len() - 8 - u32::from_le_bytes(len) as usize;
+    let meta = read_thrift(data, &mut pos, 12);
+    assert_eq!(pos, data.len() - 8);
+
+    let schema = meta.list(2);
+    let mut columns: Vec<(String, Vec<Option<String>>)> = schema[1..].iter()
+        .map(|el| (el.string(4), vec![]))
+        .collect();
+    for group in meta.list(4) {
+        for (i, chunk) in group.list(1).iter().enumerate() {
+            let cmeta = chunk.field(3);
+            let mut pos = cmeta.int(9) as usize;
+            let header = read_thrift(data, &mut pos, 12);
+            let body = &data[pos..pos + header.int(3) as usize];
+            let page = match cmeta.int(4) {
+                0 => body.to_vec(),
+                1 => unsnappy(body),
+                codec => panic!("can't decompress codec {}", codec),
+            };
+            assert_eq!(page.len() as i64, header.int(2));
+            let ty = schema[i + 1].int(1);
+            let num_values = header.field(5).int(1) as usize;
+            let values = read_page(&page, ty, num_values);
+            columns[i].1.extend(values);
+        }
+    }
+    Parquet { meta: meta, columns: columns }
+}
+
+fn read_page(page: &[u8], ty: i64, num_values: usize) -> Vec<Option<String>> {
+    let mut len = [0; 4];
+    len.copy_from_slice(&page[..4]);
+    let end = 4 + u32::from_le_bytes(len) as usize;
+    let mut pos = 4;
+    let mut defined = vec![];
+    while pos < end {
+        let run = read_uvarint(page, &mut pos);
+        // Only RLE runs (with a bit width of 1) are written.
+        assert_eq!(run & 1, 0);
+        defined.extend((0..run >> 1).map(|_| page[pos] == 1));
+        pos += 1;
+    }
+    assert_eq!(defined.len(), num_values);
+    let mut pos = end;
+    let mut nbool = 0;
+    defined.into_iter().map(|d| {
+        if !d {
+            return None;
+        }
+        Some(match ty {
+            0 => {
+                let b = page[pos + nbool / 8] >> (nbool % 8) & 1;
+                nbool += 1;
+                (b == 1).to_string()
+            }
+            2 | 5 => {
+                let mut b = [0; 8];
+                b.copy_from_slice(&page[pos..pos + 8]);
+                pos += 8;
+                if ty == 2 {
+                    i64::from_le_bytes(b).to_string()
+                } else {
+                    f64::from_le_bytes(b).to_string()
+                }
+            }
+            6 => {
+                let mut b = [0; 4];
+                b.copy_from_slice(&page[pos..pos + 4]);
+                let len = u32::from_le_bytes(b) as usize;
+                pos += 4 + len;
+                String::from_utf8(page[pos - len..pos].to_vec()).unwrap()
+            }
+            ty => panic!("unknown type {}", ty),
+        })
+    }).collect()
+}
+
+fn typed_data() -> Vec<Vec<String>> {
+    vec![
+        svec!["id", "name", "score", "ok"],
+        svec!["1", "alpha", "1.5", "true"],
+        svec!["2", "beta", "", "false"],
+        svec!["3", "", "-2", "TRUE"],
+    ]
+}
+
+fn write_parquet(name: &str, compression: &str) -> Vec<u8> {
+    let wrk = Workdir::new(name);
+    wrk.create("in.csv", typed_data());
+
+    let mut cmd = wrk.command("parquet");
+    cmd.arg("--compression").arg(compression)
+       .arg("--row-group-size").arg("2")
+       .arg("in.csv").arg("-o").arg("out.parquet");
+    wrk.run(&mut cmd);
+    fs::read(wrk.path("out.parquet")).unwrap()
+}
+
+fn assert_round_trip(file: &Parquet) {
+    let meta = &file.meta;
+    assert_eq!(meta.int(3), 3);
+    assert_eq!(meta.list(4).len(), 2);
+    let schema = meta.list(2);
+    assert_eq!(schema[0].int(5), 4);
+    let types: Vec<i64> = schema[1..].iter().map(|el| el.int(1)).collect();
+    assert_eq!(types, vec![2, 6, 5, 0]);
+    assert!(meta.string(6).starts_with("xsv"));
+
+    let some = |s: &str| Some(s.to_owned());
+    assert_eq!(file.columns, vec![
+        ("id".to_owned(), vec![some("1"), some("2"), some("3")]),
+        ("name".to_owned(), vec![some("alpha"), some("beta"), None]),
+        ("score".to_owned(), vec![some("1.5"), None, some("-2")]),
+        ("ok".to_owned(), vec![some("true"), some("false"), some("true")]),
+    ]);
+}
+
+#[test]
+fn parquet_round_trip() {
+    let data = write_parquet("parquet_round_trip", "none");
+    assert_round_trip(&read_parquet(&data));
+}
+
+#[test]
+fn parquet_round_trip_snappy() {
+    let data = write_parquet("parquet_round_trip_snappy", "snappy");
+    let file = read_parquet(&data);
+    assert_eq!(file.meta.list(4)[0].list(1)[0].field(3).int(4), 1);
+    assert_round_trip(&file);
+}
+
+#[test]
+fn parquet_zstd() {
+    let data = write_parquet("parquet_zstd", "zstd");
+    let plain = write_parquet("parquet_zstd_plain", "none");
+    let (meta, plain_meta) = {
+        let footer = |data: &[u8]| {
+            let mut len = [0; 4];
+            len.copy_from_slice(&data[data.len() - 8..data.len() - 4]);
+            let mut pos = data.len() - 8 - u32::from_le_bytes(len) as usize;
+            read_thrift(data, &mut pos, 12)
+        };
+        (footer(&data), footer(&plain))
+    };
+    let chunks = meta.list(4).iter().flat_map(|g| g.list(1));
+    let plain_chunks = plain_meta.list(4).iter().flat_map(|g| g.list(1));
+    for (chunk, plain_chunk) in chunks.zip(plain_chunks) {
+        let cmeta = chunk.field(3);
+        assert_eq!(cmeta.int(4), 6);
+        let mut pos = cmeta.int(9) as usize;
+        let header = read_thrift(&data, &mut pos, 12);
+        // Each page is a zstd frame of the same data as without compression.
+        assert_eq!(&data[pos..pos + 4], &[0x28, 0xB5, 0x2F, 0xFD]);
+        let mut plain_pos = plain_chunk.field(3).int(9) as usize;
+        let plain_header = read_thrift(&plain, &mut plain_pos, 12);
+        assert_eq!(header.int(2), plain_header.int(2));
+    }
+}
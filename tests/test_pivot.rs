@@ -0,0 +1,88 @@
+use workdir::Workdir;
+
+fn data() -> Vec<Vec<String>> {
+    vec![
+        svec!["date", "site", "metric", "value"],
+        svec!["mon", "a", "temp", "20"],
+        svec!["mon", "a", "rain", "5"],
+        svec!["tue", "a", "temp", "22"],
+        svec!["mon", "b", "temp", "18"],
+    ]
+}
+
+fn pivot_cmd(wrk: &Workdir, index: &str) -> ::std::process::Command {
+    let mut cmd = wrk.command("pivot");
+    cmd.arg("--index").arg(index)
+       .arg("--columns").arg("metric")
+       .arg("--values").arg("value")
+       .arg("in.csv");
+    cmd
+}
+
+#[test]
+fn pivot() {
+    let wrk = Workdir::new("pivot");
+    wrk.create("in.csv", data());
+
+    let mut cmd = pivot_cmd(&wrk, "date,site");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["date", "site", "rain", "temp"],
+        svec!["mon", "a", "5", "20"],
+        svec!["tue", "a", "", "22"],
+        svec!["mon", "b", "", "18"],
+    ];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn pivot_no_sort() {
+    let wrk = Workdir::new("pivot_no_sort");
+    wrk.create("in.csv", data());
+
+    let mut cmd = pivot_cmd(&wrk, "date,site");
+    cmd.arg("--no-sort");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got[0], svec!["date", "site", "temp", "rain"]);
+}
+
+#[test]
+fn pivot_collision_error() {
+    let wrk = Workdir::new("pivot_collision_error");
+    wrk.create("in.csv", data());
+
+    let mut cmd = pivot_cmd(&wrk, "date");
+    wrk.assert_err(&mut cmd);
+}
+
+#[test]
+fn pivot_agg_sum() {
+    let wrk = Workdir::new("pivot_agg_sum");
+    wrk.create("in.csv", data());
+
+    let mut cmd = pivot_cmd(&wrk, "date");
+    cmd.arg("--agg").arg("sum");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["date", "rain", "temp"],
+        svec!["mon", "5", "38"],
+        svec!["tue", "", "22"],
+    ];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn pivot_agg_count_last() {
+    let wrk = Workdir::new("pivot_agg_count_last");
+    wrk.create("in.csv", data());
+
+    let mut cmd = pivot_cmd(&wrk, "date");
+    cmd.arg("--agg").arg("count");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got[1], svec!["mon", "1", "2"]);
+
+    let mut cmd = pivot_cmd(&wrk, "date");
+    cmd.arg("--agg").arg("last");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got[1], svec!["mon", "5", "18"]);
+}
@@ -0,0 +1,56 @@
+use std::process;
+
+use workdir::Workdir;
+
+fn setup(name: &str) -> Workdir {
+    let wrk = Workdir::new(name);
+    wrk.create("in.csv", vec![
+        svec!["n", "name"],
+        svec!["3", "c"],
+        svec!["1", "a"],
+        svec!["2", "b"],
+    ]);
+    wrk
+}
+
+fn stderr(cmd: &mut process::Command) -> String {
+    let o = cmd.output().unwrap();
+    assert!(o.status.success());
+    String::from_utf8(o.stderr).unwrap()
+}
+
+#[test]
+fn progress_sort() {
+    let wrk = setup("progress_sort");
+    let mut cmd = wrk.command("--progress");
+    cmd.args(["sort", "in.csv"]);
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got, vec![
+        svec!["n", "name"],
+        svec!["1", "a"],
+        svec!["2", "b"],
+        svec!["3", "c"],
+    ]);
+    let err = stderr(&mut cmd);
+    assert!(err.contains("100%"), "{}", err);
+    assert!(err.contains("19 B / 19 B"), "{}", err);
+}
+
+#[test]
+fn progress_stdin_counts_records() {
+    let wrk = setup("progress_stdin_counts_records");
+    let mut cmd = wrk.command("--progress");
+    cmd.args(["split", "--size", "2", "out"])
+       .stdin(std::fs::File::open(wrk.path("in.csv")).unwrap());
+    let err = stderr(&mut cmd);
+    assert!(err.starts_with("3 records  19 B"), "{}", err);
+}
+
+#[test]
+fn progress_off() {
+    let wrk = setup("progress_off");
+    // XSV_PROGRESS only turns it on when stderr is a terminal.
+    let mut cmd = wrk.command("stats");
+    cmd.arg("in.csv").env("XSV_PROGRESS", "1");
+    assert_eq!(stderr(&mut cmd), "");
+}
@@ -37,3 +37,62 @@ fn prop_reverse_no_headers() {
     }
     qcheck(p as fn(CsvData) -> bool);
 }
+
+fn rows() -> Vec<Vec<String>> {
+    vec![svec!["h"], svec!["a"], svec!["b"], svec!["c"]]
+}
+
+#[test]
+fn reverse_indexed() {
+    let wrk = Workdir::new("reverse_indexed");
+    wrk.create_indexed("in.csv", rows());
+
+    let mut cmd = wrk.command("reverse");
+    cmd.arg("in.csv");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got, vec![svec!["h"], svec!["c"], svec!["b"], svec!["a"]]);
+}
+
+#[test]
+fn reverse_indexed_no_headers() {
+    let wrk = Workdir::new("reverse_indexed_no_headers");
+    wrk.create_indexed("in.csv", rows());
+
+    let mut cmd = wrk.command("reverse");
+    cmd.arg("--no-headers").arg("in.csv");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got, vec![svec!["c"], svec!["b"], svec!["a"], svec!["h"]]);
+}
+
+#[test]
+fn reverse_in_memory() {
+    let wrk = Workdir::new("reverse_in_memory");
+    wrk.create("in.csv", rows());
+
+    let mut cmd = wrk.command("reverse");
+    cmd.arg("--memcheck").arg("in.csv");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got, vec![svec!["h"], svec!["c"], svec!["b"], svec!["a"]]);
+}
+
+#[test]
+fn reverse_stale_index() {
+    let wrk = Workdir::new("reverse_stale_index");
+    wrk.create_indexed("in.csv", rows());
+    wrk.create("in.csv", vec![svec!["h"], svec!["a"], svec!["b"], svec!["c"],
+                              svec!["d"]]);
+
+    let mut cmd = wrk.command("reverse");
+    cmd.arg("in.csv");
+    let output = cmd.output().unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("is stale"), "unexpected stderr: {}", stderr);
+
+    let mut cmd = wrk.command("reverse");
+    cmd.arg("--auto-index").arg("in.csv");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got, vec![
+        svec!["h"], svec!["d"], svec!["c"], svec!["b"], svec!["a"],
+    ]);
+}
@@ -0,0 +1,71 @@
+use std::fs;
+
+use workdir::Workdir;
+
+fn data() -> Vec<Vec<String>> {
+    vec![
+        svec!["Total Sales ($)", "Región", "Notes", "notes", "2nd", "!!"],
+        svec!["1", "a", "b", "c", "d", "e"],
+    ]
+}
+
+#[test]
+fn safenames() {
+    let wrk = Workdir::new("safenames");
+    wrk.create("in.csv", data());
+
+    let mut cmd = wrk.command("safenames");
+    cmd.arg("in.csv");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["total_sales", "región", "notes", "notes_2", "_2nd", "column"],
+        svec!["1", "a", "b", "c", "d", "e"],
+    ];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn safenames_ascii_prefix() {
+    let wrk = Workdir::new("safenames_ascii_prefix");
+    wrk.create("in.csv", data());
+
+    let mut cmd = wrk.command("safenames");
+    cmd.arg("--ascii").arg("--prefix").arg("c").arg("in.csv");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got[0], svec![
+        "total_sales", "region", "notes", "notes_2", "c2nd", "column",
+    ]);
+}
+
+#[test]
+fn safenames_report_and_mapping() {
+    let wrk = Workdir::new("safenames_report_and_mapping");
+    wrk.create("in.csv", vec![svec!["A b", "a_b"], svec!["1", "2"]]);
+
+    let mut cmd = wrk.command("safenames");
+    cmd.arg("--report").arg("in.csv");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["old", "new"],
+        svec!["A b", "a_b"],
+        svec!["a_b", "a_b_2"],
+    ];
+    assert_eq!(got, expected);
+
+    let mut cmd = wrk.command("safenames");
+    cmd.arg("--mapping-output").arg("map.csv").arg("in.csv");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got, vec![svec!["a_b", "a_b_2"], svec!["1", "2"]]);
+    let mapping = fs::read_to_string(wrk.path("map.csv")).unwrap();
+    assert_eq!(mapping, "old,new\nA b,a_b\na_b,a_b_2\n");
+}
+
+#[test]
+fn safenames_no_headers() {
+    let wrk = Workdir::new("safenames_no_headers");
+    wrk.create("in.csv", data());
+
+    let mut cmd = wrk.command("safenames");
+    cmd.arg("--no-headers").arg("in.csv");
+    wrk.assert_err(&mut cmd);
+}
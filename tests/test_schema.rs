@@ -0,0 +1,141 @@
+use std::fs;
+
+use workdir::Workdir;
+
+fn data() -> Vec<Vec<String>> {
+    vec![
+        svec!["id", "price", "name", "active", "day", "note"],
+        svec!["1", "1.5", "apple", "true", "2018-01-31", ""],
+        svec!["2", "10", "kiwi", "FALSE", "2018-02-01", "x"],
+        svec!["3", "", "banana", "true", "2018-02-02", "5"],
+    ]
+}
+
+#[test]
+fn schema() {
+    let wrk = Workdir::new("schema");
+    wrk.create("in.csv", data());
+
+    let mut cmd = wrk.command("schema");
+    cmd.arg("in.csv");
+    let got: String = wrk.stdout(&mut cmd);
+    let expected = r#"{
+  "$schema": "https://json-schema.org/draft/2020-12/schema",
+  "type": "object",
+  "properties": {
+    "id": {
+      "type": "integer",
+      "minimum": 1,
+      "maximum": 3
+    },
+    "price": {
+      "type": [
+        "number",
+        "null"
+      ],
+      "minimum": 1.5,
+      "maximum": 10
+    },
+    "name": {
+      "type": "string",
+      "minLength": 4,
+      "maxLength": 6
+    },
+    "active": {
+      "type": "boolean"
+    },
+    "day": {
+      "type": "string",
+      "format": "date"
+    },
+    "note": {
+      "type": [
+        "string",
+        "null"
+      ],
+      "minLength": 1,
+      "maxLength": 1
+    }
+  },
+  "required": [
+    "id",
+    "price",
+    "name",
+    "active",
+    "day",
+    "note"
+  ],
+  "additionalProperties": false
+}"#;
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn schema_enum_and_sample_rows() {
+    let wrk = Workdir::new("schema_enum_and_sample_rows");
+    wrk.create("in.csv", data());
+
+    let mut cmd = wrk.command("schema");
+    cmd.arg("--enum-threshold").arg("3").arg("--sample-rows").arg("2")
+       .arg("in.csv");
+    let got: String = wrk.stdout(&mut cmd);
+    assert!(got.contains(r#""enum": [
+        "apple",
+        "kiwi"
+      ]"#), "{}", got);
+    assert!(got.contains(r#""maxLength": 5"#), "{}", got);
+}
+
+#[test]
+fn schema_strict_types() {
+    let wrk = Workdir::new("schema_strict_types");
+    wrk.create("in.csv", data());
+
+    let mut cmd = wrk.command("schema");
+    cmd.arg("--strict-types").arg("in.csv");
+    wrk.assert_err(&mut cmd);
+}
+
+#[test]
+fn schema_validate_round_trip() {
+    let wrk = Workdir::new("schema_validate_round_trip");
+    wrk.create("in.csv", data());
+
+    let mut cmd = wrk.command("schema");
+    cmd.arg("--enum-threshold").arg("10").arg("-o").arg("schema.json")
+       .arg("in.csv");
+    wrk.run(&mut cmd);
+
+    let mut cmd = wrk.command("val");
+    cmd.arg("--schema").arg("schema.json").arg("in.csv");
+    let got: String = wrk.stdout(&mut cmd);
+    assert_eq!(got, "File is valid");
+}
+
+#[test]
+fn schema_validate_errors() {
+    let wrk = Workdir::new("schema_validate_errors");
+    wrk.create("in.csv", data());
+    let mut cmd = wrk.command("schema");
+    cmd.arg("-o").arg("schema.json").arg("in.csv");
+    wrk.run(&mut cmd);
+    assert!(fs::metadata(wrk.path("schema.json")).is_ok());
+
+    wrk.create("new.csv", vec![
+        svec!["id", "price", "name", "active", "day", "note"],
+        svec!["4", "abc", "fig", "true", "2018-02-30", ""],
+    ]);
+    let mut cmd = wrk.command("val");
+    cmd.arg("--schema").arg("schema.json").arg("new.csv");
+    let output = cmd.output().unwrap();
+    assert!(!output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let expected = "\
+Line_Number,Column,Value,Error
+2,id,4,the value is greater than the maximum 3
+2,price,abc,the value is not of type number
+2,name,fig,the value is shorter than 4 characters
+2,day,2018-02-30,the value is not of type date
+";
+    assert_eq!(stdout, expected);
+}
@@ -1,3 +1,5 @@
+use std::fs;
+
 use workdir::Workdir;
 
 macro_rules! select_test {
@@ -114,3 +116,43 @@ select_test_err!(select_err_idx_not_int_2, "h1[a]");
 select_test_err!(select_err_unclosed_quote, r#""h1"#);
 select_test_err!(select_err_unclosed_bracket, r#""h1"[1"#);
 select_test_err!(select_err_expected_end_of_field, "a-b-");
+
+fn index_fields(wrk: &Workdir) {
+    let mut cmd = wrk.command("index");
+    cmd.arg("--fields").arg("data.csv");
+    wrk.run(&mut cmd);
+}
+
+#[test]
+fn select_index_fields() {
+    let wrk = Workdir::new("select_index_fields");
+    wrk.create("data.csv", vec![
+        svec!["a", "b", "c"],
+        svec!["1", "2", "3"],
+        svec!["x,y", "say \"hi\"", "z"],
+        svec!["4", "", "6"],
+    ]);
+    index_fields(&wrk);
+
+    let mut cmd = wrk.command("select");
+    cmd.arg("c,a").arg("data.csv");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got, vec![
+        svec!["c", "a"],
+        svec!["3", "1"],
+        svec!["z", "x,y"],
+        svec!["6", "4"],
+    ]);
+}
+
+#[test]
+fn select_index_fields_no_headers_crlf() {
+    let wrk = Workdir::new("select_index_fields_no_headers_crlf");
+    fs::write(wrk.path("data.csv"), "a,b\r\n1,2\r\n\r\n3,4").unwrap();
+    index_fields(&wrk);
+
+    let mut cmd = wrk.command("select");
+    cmd.arg("--no-headers").arg("2").arg("data.csv");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got, vec![svec!["b"], svec!["2"], svec!["4"]]);
+}
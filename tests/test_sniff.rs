@@ -0,0 +1,81 @@
+use std::fs;
+use std::process;
+
+use workdir::Workdir;
+
+#[test]
+fn sniff_semicolon() {
+    let wrk = Workdir::new("sniff_semicolon");
+    fs::write(wrk.path("in.csv"),
+              "name;age;city\r\n\"Smith; J\";42;Berlin\r\nDoe;7;Rome\r\n")
+        .unwrap();
+
+    let mut cmd = wrk.command("sniff");
+    cmd.arg("in.csv");
+    let got: String = wrk.stdout(&mut cmd);
+    assert!(got.contains("Delimiter:        ';'\n"), "{}", got);
+    assert!(got.contains("Header row:       yes\n"), "{}", got);
+    assert!(got.contains("Columns:          3\n"), "{}", got);
+    assert!(got.contains("Line terminator:  CRLF\n"), "{}", got);
+}
+
+#[test]
+fn sniff_json() {
+    let wrk = Workdir::new("sniff_json");
+    fs::write(wrk.path("in.csv"), "\u{feff}a|b\n1|2\n3|4\n").unwrap();
+
+    let mut cmd = wrk.command("sniff");
+    cmd.arg("--json").arg("in.csv");
+    let got: String = wrk.stdout(&mut cmd);
+    let expected = "{\"delimiter\":\"|\",\"delimiter_confidence\":\"high\",\
+                    \"quote\":\"\\\"\",\"has_header\":true,\
+                    \"header_confidence\":\"high\",\"columns\":2,\
+                    \"line_terminator\":\"LF\",\"encoding\":\"UTF-8\",\
+                    \"bom\":true,\"records_sampled\":3}";
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn sniff_shell() {
+    let wrk = Workdir::new("sniff_shell");
+    fs::write(wrk.path("in.csv"), "1\t2\t3\n4\t5\t6\n").unwrap();
+
+    let mut cmd = wrk.command("sniff");
+    cmd.arg("--shell").arg("in.csv");
+    let got: String = wrk.stdout(&mut cmd);
+    assert_eq!(got, "-d tab -n");
+}
+
+#[cfg(unix)]
+#[test]
+fn sniff_shell_substitution() {
+    let wrk = Workdir::new("sniff_shell_substitution");
+    for &(name, delimiter) in &[("semicolon", ";"), ("pipe", "|"),
+                                ("tab", "\t")] {
+        let data = "name,age\nAnn,31\nBob,40\n".replace(",", delimiter);
+        fs::write(wrk.path(&format!("{}.csv", name)), data).unwrap();
+
+        // The example of 'xsv sniff --help', where the options are split
+        // into words but not unquoted.
+        let mut cmd = process::Command::new("sh");
+        cmd.current_dir(wrk.path(""))
+           .env("XSV", wrk.xsv_bin())
+           .arg("-c")
+           .arg(format!("\"$XSV\" stats $(\"$XSV\" sniff --shell {0}.csv) \
+                         {0}.csv | \"$XSV\" select field", name));
+        let got: String = wrk.stdout(&mut cmd);
+        assert_eq!(got, "field\nname\nage", "{}", name);
+    }
+}
+
+#[test]
+fn sniff_low_confidence() {
+    let wrk = Workdir::new("sniff_low_confidence");
+    fs::write(wrk.path("in.csv"), "just one line of text\n").unwrap();
+
+    let mut cmd = wrk.command("sniff");
+    cmd.arg("in.csv");
+    let got: String = wrk.stdout(&mut cmd);
+    assert!(got.contains("Delimiter:        ',' (low confidence)"), "{}", got);
+    assert!(got.contains("Header row:       yes (low confidence)"), "{}", got);
+}
@@ -23,3 +23,328 @@ abcdefg  a    a
 a        abc  z\
 ")
 }
+
+#[test]
+fn table_max_width() {
+    let wrk = Workdir::new("table_max_width");
+    wrk.create("in.csv", vec![
+        svec!["id", "description"],
+        svec!["1", "a rather long description"],
+        svec!["2", "short"],
+    ]);
+
+    let mut cmd = wrk.command("table");
+    cmd.arg("--max-width").arg("10").arg("in.csv");
+
+    let got: String = wrk.stdout(&mut cmd);
+    assert_eq!(&*got, "\
+id  descripti…
+1   a rather …
+2   short\
+")
+}
+
+#[test]
+fn table_max_width_unicode() {
+    let wrk = Workdir::new("table_max_width_unicode");
+    wrk.create("in.csv", vec![
+        svec!["name", "x"],
+        svec!["東京都庁舎", "a"],
+        svec!["abc", "b"],
+    ]);
+
+    let mut cmd = wrk.command("table");
+    cmd.arg("--max-width").arg("6").arg("in.csv");
+
+    let got: String = wrk.stdout(&mut cmd);
+    assert_eq!(&*got, "\
+name   x
+東京…  a
+abc    b\
+")
+}
+
+#[test]
+fn table_wrap() {
+    let wrk = Workdir::new("table_wrap");
+    wrk.create("in.csv", vec![
+        svec!["id", "description", "n"],
+        svec!["1", "a rather long description", "x"],
+        svec!["2", "short", "y"],
+    ]);
+
+    let mut cmd = wrk.command("table");
+    cmd.arg("--max-width").arg("11").arg("--wrap").arg("in.csv");
+
+    let got: String = wrk.stdout(&mut cmd);
+    assert_eq!(&*got, "\
+id  description  n
+1   a rather     x
+    long
+    description
+2   short        y\
+")
+}
+
+#[test]
+fn table_width_and_pad() {
+    let wrk = Workdir::new("table_width_and_pad");
+    wrk.create("in.csv", data());
+
+    let mut cmd = wrk.command("table");
+    cmd.arg("--width").arg("4").arg("--pad").arg("1")
+       .arg("--max-width").arg("5").arg("in.csv");
+
+    let got: String = wrk.stdout(&mut cmd);
+    assert_eq!(&*got, "\
+h1    h2   h3
+abcd… a    a
+a     abc  z\
+")
+}
+
+#[test]
+fn table_align_right() {
+    let wrk = Workdir::new("table_align_right");
+    wrk.create("in.csv", data());
+
+    let mut cmd = wrk.command("table");
+    cmd.arg("--align").arg("right").arg("in.csv");
+
+    let got = String::from_utf8(wrk.output(&mut cmd).stdout).unwrap();
+    assert_eq!(&*got, "     h1   h2  h3
+abcdefg    a   a
+      a  abc   z
+")
+}
+
+#[test]
+fn table_markdown() {
+    let wrk = Workdir::new("table_markdown");
+    wrk.create("in.csv", vec![
+        svec!["name", "notes"],
+        svec!["a|b", "back\\slash"],
+        svec!["c", "two\nlines"],
+    ]);
+
+    let mut cmd = wrk.command("table");
+    cmd.arg("--format").arg("markdown").arg("in.csv");
+
+    let got: String = wrk.stdout(&mut cmd);
+    assert_eq!(&*got, "\
+| name | notes        |
+| ---- | ------------ |
+| a\\|b | back\\\\slash  |
+| c    | two<br>lines |\
+")
+}
+
+#[test]
+fn table_markdown_align() {
+    let wrk = Workdir::new("table_markdown_align");
+    wrk.create("in.csv", data());
+
+    let mut cmd = wrk.command("table");
+    cmd.arg("--format").arg("markdown").arg("--align").arg("center")
+       .arg("in.csv");
+
+    let got: String = wrk.stdout(&mut cmd);
+    assert_eq!(&*got, "\
+|   h1    | h2  | h3  |
+| :-----: | :-: | :-: |
+| abcdefg |  a  |  a  |
+|    a    | abc |  z  |\
+")
+}
+
+#[test]
+fn table_markdown_no_headers() {
+    let wrk = Workdir::new("table_markdown_no_headers");
+    wrk.create("in.csv", data());
+
+    let mut cmd = wrk.command("table");
+    cmd.arg("--format").arg("markdown").arg("--no-headers").arg("in.csv");
+
+    let got: String = wrk.stdout(&mut cmd);
+    assert_eq!(&*got, "\
+| col1    | col2 | col3 |
+| ------- | ---- | ---- |
+| h1      | h2   | h3   |
+| abcdefg | a    | a    |
+| a       | abc  | z    |\
+")
+}
+
+fn prices() -> Vec<Vec<String>> {
+    vec![
+        svec!["name", "price", "code"],
+        svec!["apple", "1.5", "007"],
+        svec!["watermelon", "12", "N/A"],
+        svec!["fig", "-0.25", "x1"],
+    ]
+}
+
+#[test]
+fn table_align_auto() {
+    let wrk = Workdir::new("table_align_auto");
+    wrk.create("in.csv", prices());
+
+    let mut cmd = wrk.command("table");
+    cmd.arg("--align").arg("auto").arg("in.csv");
+
+    let got: String = wrk.stdout(&mut cmd);
+    assert_eq!(&*got, "\
+name        price  code
+apple         1.5  007
+watermelon     12  N/A
+fig         -0.25  x1\
+")
+}
+
+#[test]
+fn table_align_per_column() {
+    let wrk = Workdir::new("table_align_per_column");
+    wrk.create("in.csv", prices());
+
+    let mut cmd = wrk.command("table");
+    cmd.arg("--align").arg("auto,name:right,code:center").arg("in.csv");
+
+    let got = String::from_utf8(wrk.output(&mut cmd).stdout).unwrap();
+    assert_eq!(&*got, "      name  price  code
+     apple    1.5  007
+watermelon     12  N/A
+       fig  -0.25   x1
+")
+}
+
+#[test]
+fn table_align_auto_markdown() {
+    let wrk = Workdir::new("table_align_auto_markdown");
+    wrk.create("in.csv", prices());
+
+    let mut cmd = wrk.command("table");
+    cmd.arg("--align").arg("auto").arg("--format").arg("markdown")
+       .arg("in.csv");
+
+    let got: String = wrk.stdout(&mut cmd);
+    assert_eq!(&*got, "\
+| name       | price | code |
+| :--------- | ----: | :--- |
+| apple      |   1.5 | 007  |
+| watermelon |    12 | N/A  |
+| fig        | -0.25 | x1   |\
+")
+}
+
+#[test]
+fn table_align_unknown() {
+    let wrk = Workdir::new("table_align_unknown");
+    wrk.create("in.csv", prices());
+
+    let mut cmd = wrk.command("table");
+    cmd.arg("--align").arg("price:middle").arg("in.csv");
+    wrk.assert_err(&mut cmd);
+}
+
+fn growing() -> Vec<Vec<String>> {
+    vec![
+        svec!["id", "value"],
+        svec!["1", "abc"],
+        svec!["2", "abcd"],
+        svec!["3", "abcdefghij"],
+        svec!["4", "ab"],
+    ]
+}
+
+#[test]
+fn table_sample() {
+    let wrk = Workdir::new("table_sample");
+    wrk.create("in.csv", growing());
+
+    let mut cmd = wrk.command("table");
+    cmd.arg("--sample").arg("2").arg("in.csv");
+
+    let got: String = wrk.stdout(&mut cmd);
+    assert_eq!(&*got, "\
+id  value
+1   abc
+2   abcd
+3   abcd…
+4   ab\
+")
+}
+
+#[test]
+fn table_sample_wrap() {
+    let wrk = Workdir::new("table_sample_wrap");
+    wrk.create("in.csv", growing());
+
+    let mut cmd = wrk.command("table");
+    cmd.arg("--sample").arg("2").arg("--max-width").arg("20").arg("--wrap")
+       .arg("in.csv");
+
+    let got: String = wrk.stdout(&mut cmd);
+    assert_eq!(&*got, "\
+id  value
+1   abc
+2   abcd
+3   abcde
+    fghij
+4   ab\
+")
+}
+
+#[test]
+fn table_sample_markdown() {
+    let wrk = Workdir::new("table_sample_markdown");
+    wrk.create("in.csv", growing());
+
+    // Values wider than their column aren't cut, since the cells of a
+    // Markdown table needn't line up.
+    let mut cmd = wrk.command("table");
+    cmd.args(["--sample", "2", "--format", "markdown", "in.csv"]);
+
+    let got: String = wrk.stdout(&mut cmd);
+    assert_eq!(&*got, "\
+| id  | value |
+| --- | ----- |
+| 1   | abc   |
+| 2   | abcd  |
+| 3   | abcdefghij |
+| 4   | ab    |\
+")
+}
+
+#[test]
+fn table_exact() {
+    let wrk = Workdir::new("table_exact");
+    wrk.create("in.csv", growing());
+
+    let mut cmd = wrk.command("table");
+    cmd.arg("--sample").arg("2").arg("--exact").arg("in.csv");
+
+    let got: String = wrk.stdout(&mut cmd);
+    assert_eq!(&*got, "\
+id  value
+1   abc
+2   abcd
+3   abcdefghij
+4   ab\
+")
+}
+
+#[test]
+fn table_max_rows() {
+    let wrk = Workdir::new("table_max_rows");
+    wrk.create("in.csv", growing());
+
+    let mut cmd = wrk.command("table");
+    cmd.arg("--max-rows").arg("2").arg("in.csv");
+
+    let got: String = wrk.stdout(&mut cmd);
+    assert_eq!(&*got, "\
+id  value
+1   abc
+2   abcd\
+")
+}
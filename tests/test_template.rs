@@ -0,0 +1,77 @@
+use std::fs;
+
+use workdir::Workdir;
+
+fn data() -> Vec<Vec<String>> {
+    vec![
+        svec!["id", "name"],
+        svec!["1", "O'Brien"],
+        svec!["2", " ann "],
+    ]
+}
+
+#[test]
+fn template() {
+    let wrk = Workdir::new("template");
+    wrk.create("in.csv", data());
+
+    let mut cmd = wrk.command("template");
+    cmd.arg("--template")
+       .arg("INSERT INTO t VALUES ({id}, {name|escape_sql});")
+       .arg("in.csv");
+    let got: String = wrk.stdout(&mut cmd);
+    let expected = "\
+INSERT INTO t VALUES (1, 'O''Brien');
+INSERT INTO t VALUES (2, ' ann ');";
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn template_filters_and_braces() {
+    let wrk = Workdir::new("template_filters_and_braces");
+    wrk.create("in.csv", data());
+
+    let mut cmd = wrk.command("template");
+    cmd.arg("--template").arg("{{{name|trim|upper}}}").arg("in.csv");
+    let got: String = wrk.stdout(&mut cmd);
+    assert_eq!(got, "{O'BRIEN}\n{ANN}");
+}
+
+#[test]
+fn template_file_and_no_headers() {
+    let wrk = Workdir::new("template_file_and_no_headers");
+    wrk.create("in.csv", data());
+    fs::write(wrk.path("t.tpl"), "{2} has id {1}\n").unwrap();
+
+    let mut cmd = wrk.command("template");
+    cmd.arg("--template-file").arg("t.tpl").arg("--no-headers")
+       .arg("in.csv");
+    let got: String = wrk.stdout(&mut cmd);
+    assert_eq!(got, "name has id id\nO'Brien has id 1\n ann  has id 2");
+}
+
+#[test]
+fn template_output_dir() {
+    let wrk = Workdir::new("template_output_dir");
+    wrk.create("in.csv", data());
+
+    let mut cmd = wrk.command("template");
+    cmd.arg("--template").arg("Hello {name|trim}")
+       .arg("--output-dir").arg("out")
+       .arg("--filename").arg("{id}.txt")
+       .arg("in.csv");
+    wrk.run(&mut cmd);
+    let got = fs::read_to_string(wrk.path("out/2.txt")).unwrap();
+    assert_eq!(got, "Hello ann\n");
+    assert!(wrk.path("out/1.txt").exists());
+}
+
+#[test]
+fn template_unknown_column() {
+    let wrk = Workdir::new("template_unknown_column");
+    wrk.create("in.csv", data());
+
+    let mut cmd = wrk.command("template");
+    cmd.arg("--template").arg("{nope}").arg("in.csv");
+    wrk.assert_err(&mut cmd);
+}
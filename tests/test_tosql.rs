@@ -0,0 +1,115 @@
+use workdir::Workdir;
+
+fn data() -> Vec<Vec<String>> {
+    vec![
+        svec!["id", "name", "score", "zip"],
+        svec!["1", "O'Brien", "1.5", "007"],
+        svec!["-20", "a,b", "2", "010"],
+        svec!["3", "", "", ""],
+    ]
+}
+
+#[test]
+fn tosql_dump() {
+    let wrk = Workdir::new("tosql_dump");
+    wrk.create("people.csv", data());
+
+    let mut cmd = wrk.command("tosql");
+    cmd.arg("--batch-size").arg("2").arg("people.csv");
+    let got: String = wrk.stdout(&mut cmd);
+    let expected = "\
+BEGIN;
+CREATE TABLE \"people\" (
+  \"id\" INTEGER,
+  \"name\" TEXT,
+  \"score\" REAL,
+  \"zip\" TEXT
+);
+INSERT INTO \"people\" (\"id\", \"name\", \"score\", \"zip\") VALUES
+  (1, 'O''Brien', 1.5, '007'),
+  (-20, 'a,b', 2, '010');
+INSERT INTO \"people\" (\"id\", \"name\", \"score\", \"zip\") VALUES
+  (3, '', NULL, '');
+COMMIT;";
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn tosql_all_text_replace() {
+    let wrk = Workdir::new("tosql_all_text_replace");
+    wrk.create("in.csv", data());
+
+    let mut cmd = wrk.command("tosql");
+    cmd.arg("--all-text").arg("--if-exists").arg("replace")
+       .arg("-t").arg("my \"table\"").arg("in.csv");
+    let got: String = wrk.stdout(&mut cmd);
+    assert!(got.starts_with("\
+BEGIN;
+DROP TABLE IF EXISTS \"my \"\"table\"\"\";
+CREATE TABLE \"my \"\"table\"\"\" (
+  \"id\" TEXT,"));
+    assert!(got.contains("('1', 'O''Brien', '1.5', '007')"));
+}
+
+#[test]
+fn tosql_sample_size() {
+    let wrk = Workdir::new("tosql_sample_size");
+    wrk.create("in.csv", vec![
+        svec!["n"], svec!["1"], svec!["x"],
+    ]);
+
+    let mut cmd = wrk.command("tosql");
+    cmd.arg("--sample-size").arg("1").arg("--if-exists").arg("append")
+       .arg("in.csv");
+    let got: String = wrk.stdout(&mut cmd);
+    assert!(got.contains("CREATE TABLE IF NOT EXISTS \"in\" (\n  \"n\" INTEGER"));
+    assert!(got.contains("  (1),\n  ('x');"));
+}
+
+#[test]
+fn tosql_no_headers() {
+    let wrk = Workdir::new("tosql_no_headers");
+    wrk.create("in.csv", vec![svec!["a", "1"]]);
+
+    let mut cmd = wrk.command("tosql");
+    cmd.arg("--no-headers").arg("in.csv");
+    let got: String = wrk.stdout(&mut cmd);
+    assert!(got.contains("\"field_1\" TEXT,\n  \"field_2\" INTEGER"));
+}
+
+#[test]
+fn tosql_duplicate_columns() {
+    let wrk = Workdir::new("tosql_duplicate_columns");
+    wrk.create("in.csv", vec![svec!["a", "A"], svec!["1", "2"]]);
+
+    let mut cmd = wrk.command("tosql");
+    cmd.arg("in.csv");
+    wrk.assert_err(&mut cmd);
+}
+
+#[test]
+fn tosql_blob_values() {
+    let wrk = Workdir::new("tosql_blob_values");
+    let mut data = b"s\n".to_vec();
+    data.extend_from_slice(b"a\0b\n\xff'\n");
+    ::std::fs::write(wrk.path("in.csv"), data).unwrap();
+
+    let mut cmd = wrk.command("tosql");
+    cmd.arg("in.csv");
+    let got: String = wrk.stdout(&mut cmd);
+    assert!(got.contains("  (X'610062'),\n  (X'FF27');"), "{}", got);
+}
+
+#[cfg(feature = "sqlite")]
+#[test]
+fn tosql_db_without_sqlite3() {
+    let wrk = Workdir::new("tosql_db_without_sqlite3");
+    wrk.create("in.csv", data());
+
+    let mut cmd = wrk.command("tosql");
+    cmd.arg("--db").arg("out.db").arg("in.csv").env("PATH", "");
+    wrk.assert_err(&mut cmd);
+    let stderr = String::from_utf8(cmd.output().unwrap().stderr).unwrap();
+    assert!(stderr.contains("sqlite3 command-line shell, which was not \
+                             found"), "{}", stderr);
+}
@@ -0,0 +1,54 @@
+use workdir::Workdir;
+
+fn data() -> Vec<Vec<String>> {
+    vec![
+        svec!["h1", "h2", "h3"],
+        svec!["a", "b", "c"],
+        svec!["d", "e"],
+    ]
+}
+
+fn transposed() -> Vec<Vec<String>> {
+    vec![
+        svec!["h1", "a", "d"],
+        svec!["h2", "b", "e"],
+        svec!["h3", "c", ""],
+    ]
+}
+
+#[test]
+fn transpose() {
+    let wrk = Workdir::new("transpose").flexible(true);
+    wrk.create("in.csv", data());
+
+    let mut cmd = wrk.command("transpose");
+    cmd.arg("in.csv");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got, transposed());
+}
+
+#[test]
+fn transpose_multipass() {
+    let wrk = Workdir::new("transpose_multipass").flexible(true);
+    wrk.create("in.csv", data());
+
+    let mut cmd = wrk.command("transpose");
+    cmd.arg("--multipass").arg("in.csv");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got, transposed());
+}
+
+#[test]
+fn transpose_twice() {
+    let wrk = Workdir::new("transpose_twice");
+    wrk.create("in.csv", vec![svec!["name", "value"], svec!["x", "1"]]);
+
+    let mut cmd = wrk.command("transpose");
+    cmd.arg("in.csv").arg("-o").arg("t.csv");
+    wrk.run(&mut cmd);
+
+    let mut cmd = wrk.command("transpose");
+    cmd.arg("t.csv");
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got, vec![svec!["name", "value"], svec!["x", "1"]]);
+}
@@ -0,0 +1,99 @@
+use std::fs;
+
+use workdir::Workdir;
+
+/// Writes CSV data as is, so that the whitespace and quotes are kept.
+fn create(wrk: &Workdir, name: &str, data: &str) {
+    fs::write(wrk.path(name), data).unwrap();
+}
+
+#[test]
+fn trim_join() {
+    let wrk = Workdir::new("trim_join");
+    create(&wrk, "cities.csv", "city ,state\n Boston ,MA\nBuffalo, NY\n");
+    create(&wrk, "places.csv", "city,place\nBoston,\"  Logan  \"\n");
+
+    let mut cmd = wrk.command("join");
+    cmd.args(["city", "cities.csv", "city", "places.csv"]);
+    wrk.assert_err(&mut cmd);
+
+    let mut cmd = wrk.command("--trim");
+    cmd.args(["all", "join", "city", "cities.csv", "city", "places.csv"]);
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["city", "state", "city", "place"],
+        svec!["Boston", "MA", "Boston", "Logan"],
+    ];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn trim_frequency() {
+    let wrk = Workdir::new("trim_frequency");
+    create(&wrk, "in.csv", "city, state\nBoston, MA\nBuffalo,NY\nSalem,MA\n");
+
+    let mut cmd = wrk.command("frequency");
+    cmd.args(["-s", "state", "in.csv"]);
+    wrk.assert_err(&mut cmd);
+
+    let mut cmd = wrk.command("--trim");
+    cmd.args(["headers", "frequency", "-s", "state", "in.csv"]);
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    let expected = vec![
+        svec!["field", "value", "count"],
+        svec!["state", "MA", "2"],
+        svec!["state", "NY", "1"],
+    ];
+    assert_eq!(got, expected);
+}
+
+#[test]
+fn trim_stats() {
+    let wrk = Workdir::new("trim_stats");
+    create(&wrk, "in.csv", "n\n 42\n\"7 \"\n");
+
+    let mut cmd = wrk.command("stats");
+    cmd.args(["-s", "n", "in.csv"]);
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got[1][1], "Unicode");
+    assert_eq!(got[1][2], "");
+
+    let mut cmd = wrk.command("--trim");
+    cmd.args(["fields", "stats", "-s", "n", "in.csv"]);
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got[1][..5], svec!["n", "Integer", "49", "7", "42"][..]);
+}
+
+#[test]
+fn trim_headers_only() {
+    let wrk = Workdir::new("trim_headers_only");
+    create(&wrk, "in.csv", " a , b \n 1 , 2 \n");
+
+    let mut cmd = wrk.command("--trim");
+    cmd.args(["headers", "select", "b", "in.csv"]);
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got, vec![svec!["b"], svec![" 2 "]]);
+}
+
+#[test]
+fn trim_with_field_index() {
+    let wrk = Workdir::new("trim_with_field_index");
+    create(&wrk, "in.csv", "a,b\n1, x \n");
+    let mut cmd = wrk.command("index");
+    cmd.args(["--fields", "in.csv"]);
+    wrk.run(&mut cmd);
+
+    let mut cmd = wrk.command("--trim");
+    cmd.args(["all", "select", "b", "in.csv"]);
+    let got: Vec<Vec<String>> = wrk.read_stdout(&mut cmd);
+    assert_eq!(got, vec![svec!["b"], svec!["x"]]);
+}
+
+#[test]
+fn trim_invalid_mode() {
+    let wrk = Workdir::new("trim_invalid_mode");
+    create(&wrk, "in.csv", "a\n1\n");
+    let mut cmd = wrk.command("--trim");
+    cmd.args(["some", "select", "a", "in.csv"]);
+    wrk.assert_err(&mut cmd);
+}
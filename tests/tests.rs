@@ -33,24 +33,64 @@ macro_rules! rassert_eq {
 
 mod workdir;
 
+mod test_apply;
+mod test_behead;
 mod test_cat;
+mod test_comment;
+mod test_completions;
+mod test_compressed;
+mod test_config;
 mod test_count;
+mod test_datefmt;
+mod test_dedup;
+mod test_diff;
+mod test_enum;
+mod test_excel;
+mod test_exit_codes;
+mod test_explode;
 mod test_fixlengths;
+mod test_fill;
+mod test_filter;
+mod test_fixed;
 mod test_flatten;
+mod test_flexible;
 mod test_fmt;
+mod test_foreach;
 mod test_frequency;
+mod test_fromjson;
+mod test_groupby;
+mod test_hash;
 mod test_headers;
+#[cfg(feature = "http")]
+mod test_http;
+mod test_implode;
 mod test_index;
+mod test_input;
 mod test_join;
+mod test_json;
+mod test_map;
+mod test_mask;
+mod test_melt;
+mod test_merge;
+mod test_parquet;
 mod test_partition;
+mod test_progress;
+mod test_pivot;
 mod test_reverse;
+mod test_safenames;
+mod test_schema;
 mod test_search;
 mod test_select;
 mod test_slice;
+mod test_sniff;
 mod test_sort;
 mod test_split;
 mod test_stats;
 mod test_table;
+mod test_template;
+mod test_tosql;
+mod test_transpose;
+mod test_trim;
 
 fn qcheck<T: Testable>(p: T) {
     QuickCheck::new().gen(StdGen::new(thread_rng(), 5)).quickcheck(p);
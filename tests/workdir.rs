@@ -85,6 +85,13 @@ impl Workdir {
     pub fn command(&self, sub_command: &str) -> process::Command {
         let mut cmd = process::Command::new(&self.xsv_bin());
         cmd.current_dir(&self.dir).arg(sub_command);
+        // Keep the defaults of the user running the tests out of them.
+        cmd.env("XDG_CONFIG_HOME", &self.dir);
+        for (var, _) in env::vars_os() {
+            if var.to_string_lossy().starts_with("XSV_DEFAULT_") {
+                cmd.env_remove(var);
+            }
+        }
         cmd
     }
 